@@ -0,0 +1,472 @@
+//! Alerts from the config's `alerts` section: a node that starts failing a check is reported
+//! once to every webhook and by mail, and again when it is OK (or after `repeat_hours` while
+//! it is not). The resolve goes to the channels that got the failure, and is tried again like
+//! the failure until each of them has it.
+//!
+//! Which nodes are failing and which channels they were reported to is kept in the state dir,
+//! under a lock, so `beeg check` runs from cron and `beeg daemon` share it and a failure is not
+//! re-sent each run.
+
+use crate::checks::{CheckReport, Severity};
+use crate::config::{self, AlertLevel, WebhookFormat};
+use crate::state;
+use anyhow::{bail, Context, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::process::{Command, Stdio};
+
+const STATE_FILE: &str = "alerts.json";
+
+/// A failing node as last seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Tracked {
+    pub status: String,
+    /// When the node started failing
+    pub since: i64,
+    /// When it was last reported; `None` until every channel has it
+    #[serde(default)]
+    pub notified: Option<i64>,
+    /// Channels (see `channels`) that already have the report still due on the others
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub delivered: BTreeSet<String>,
+    /// Channels that got this failure at least once, and so are owed its resolve
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub reported: BTreeSet<String>,
+    /// OK again; kept until every channel in `reported` has the resolve
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub resolved: bool,
+}
+
+/// Failing nodes by check and node name.
+pub(crate) type Tracking = BTreeMap<String, BTreeMap<String, Tracked>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// One node to report.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct Alert {
+    pub state: AlertState,
+    pub check: String,
+    pub node: String,
+    pub status: String,
+    pub detail: String,
+    /// When the node started failing
+    pub since: i64,
+}
+
+fn failing(level: AlertLevel, status: Severity) -> bool {
+    match level {
+        AlertLevel::Warn => status != Severity::Ok,
+        AlertLevel::Fail => status == Severity::Failure,
+    }
+}
+
+/// What to report for `report` given what was tracked, updating `tracking` for nodes that start
+/// or stop failing. Alerts are only marked as sent by `mark_sent`, so a failed delivery, firing
+/// or resolved, is tried again by the next run on the channels that missed it.
+pub(crate) fn evaluate(tracking: &mut Tracking, report: &CheckReport, alerts: &config::Alerts, now: i64) -> Vec<Alert> {
+    let repeat = alerts.repeat_hours.map(|h| h as i64 * 3600);
+    let configured = channels(alerts);
+    let mut out = Vec::new();
+    for r in report.leaf_results() {
+        let nodes = tracking.entry(r.check.to_string()).or_default();
+        let status = r.status.as_str().to_string();
+        if failing(alerts.min_status, r.status) {
+            let t = nodes.entry(r.node.clone()).or_insert(Tracked {
+                status: status.clone(), since: now, notified: None, delivered: BTreeSet::new(), reported: BTreeSet::new(), resolved: false,
+            });
+            // failing again before every resolve went out: the channels that have one hear of it anew
+            if std::mem::take(&mut t.resolved) { t.notified = None; }
+            t.status = status.clone();
+            let due = match t.notified { None => true, Some(at) => repeat.is_some_and(|every| now - at >= every) };
+            if due {
+                out.push(Alert { state: AlertState::Firing, check: r.check.to_string(), node: r.node.clone(), status, detail: r.detail.clone(), since: t.since });
+            }
+        } else if let Some(t) = nodes.get_mut(&r.node) {
+            // state written before channels were tracked: the failure went to all of them
+            if t.reported.is_empty() && t.notified.is_some() { t.reported = configured.iter().cloned().collect(); }
+            // a channel since removed from the config is owed nothing
+            t.reported.retain(|c| configured.contains(c));
+            if !alerts.send_resolved || t.reported.is_empty() {
+                nodes.remove(&r.node);
+                continue;
+            }
+            (t.resolved, t.status) = (true, status.clone());
+            t.delivered.clear();
+            out.push(Alert { state: AlertState::Resolved, check: r.check.to_string(), node: r.node.clone(), status, detail: r.detail.clone(), since: t.since });
+        }
+    }
+    tracking.retain(|_, nodes| !nodes.is_empty());
+    out
+}
+
+/// Delivery channels of `alerts`: `webhook 1`, `webhook 2`, ... in config order, and `email`.
+/// The URLs are secrets and are not used as names.
+pub(crate) fn channels(alerts: &config::Alerts) -> Vec<String> {
+    let hooks = (1..=alerts.webhooks.len()).map(|i| format!("webhook {}", i));
+    hooks.chain(alerts.email.as_ref().map(|_| "email".to_string())).collect()
+}
+
+/// The alerts of `due` that `channel` still needs: firing ones it did not get yet, and resolves
+/// of failures it got.
+pub(crate) fn pending(tracking: &Tracking, due: &[Alert], channel: &str) -> Vec<Alert> {
+    due.iter().filter(|a| {
+        let t = tracking.get(&a.check).and_then(|n| n.get(&a.node));
+        match a.state {
+            AlertState::Firing => !t.is_some_and(|t| t.delivered.contains(channel)),
+            AlertState::Resolved => t.is_some_and(|t| t.reported.contains(channel)),
+        }
+    }).cloned().collect()
+}
+
+/// Record that `channel` got `sent`; a firing alert counts as reported once every one of
+/// `channels` has it, and a resolved node is forgotten once every channel it was reported to has
+/// the resolve.
+pub(crate) fn mark_sent(tracking: &mut Tracking, sent: &[Alert], channel: &str, channels: &[String], now: i64) {
+    for a in sent {
+        let Some(nodes) = tracking.get_mut(&a.check) else { continue };
+        let Some(t) = nodes.get_mut(&a.node) else { continue };
+        match a.state {
+            AlertState::Firing => {
+                t.delivered.insert(channel.to_string());
+                t.reported.insert(channel.to_string());
+                if channels.iter().all(|c| t.delivered.contains(c)) {
+                    t.notified = Some(now);
+                    t.delivered.clear();
+                }
+            }
+            AlertState::Resolved => {
+                t.reported.remove(channel);
+                if t.reported.is_empty() { nodes.remove(&a.node); }
+            }
+        }
+        if nodes.is_empty() { tracking.remove(&a.check); }
+    }
+}
+
+fn time(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0).map(crate::timefmt::rfc3339).unwrap_or_default()
+}
+
+fn title(cluster: Option<&str>, alerts: &[Alert]) -> String {
+    let firing = alerts.iter().filter(|a| a.state == AlertState::Firing).count();
+    format!("beeg{}: {} failing, {} resolved", cluster.map(|c| format!(" [{}]", c)).unwrap_or_default(), firing, alerts.len() - firing)
+}
+
+fn line(a: &Alert) -> String {
+    let what = match a.state {
+        AlertState::Firing => format!("{} {} on {}", a.status, a.check, a.node),
+        AlertState::Resolved => format!("resolved: {} on {} is {} again", a.check, a.node, a.status),
+    };
+    if a.detail.is_empty() { what } else { format!("{}: {}", what, a.detail) }
+}
+
+/// Request body for `format`.
+pub(crate) fn payload(format: WebhookFormat, cluster: Option<&str>, alerts: &[Alert], now: i64) -> serde_json::Value {
+    let firing = alerts.iter().any(|a| a.state == AlertState::Firing);
+    let title = title(cluster, alerts);
+    let lines: Vec<String> = alerts.iter().map(line).collect();
+    match format {
+        WebhookFormat::Generic => {
+            let list: Vec<serde_json::Value> = alerts.iter().map(|a| {
+                let mut v = serde_json::to_value(a).unwrap_or_default();
+                v["since"] = time(a.since).into();
+                v
+            }).collect();
+            serde_json::json!({ "source": "beeg", "cluster": cluster, "time": time(now), "alerts": list })
+        }
+        WebhookFormat::Slack => serde_json::json!({ "text": format!("*{}*\n{}", title, lines.join("\n")) }),
+        WebhookFormat::Teams => serde_json::json!({
+            "@type": "MessageCard",
+            "@context": "http://schema.org/extensions",
+            "summary": title,
+            "title": title,
+            "themeColor": if firing { "D70000" } else { "2EB886" },
+            "text": lines.join("<br>"),
+        }),
+    }
+}
+
+/// Write `option = "value"` lines to a curl config file readable only by the user, for `-K`.
+/// Remove it once curl is done.
+fn curlrc(kind: &str, options: &[(&str, &str)]) -> Result<std::path::PathBuf> {
+    let dir = state::state_dir();
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(".{}.{}.curlrc", kind, std::process::id()));
+    let mut f = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&path)?;
+    for (option, value) in options { writeln!(f, "{} = {}", option, serde_json::to_string(value)?)?; }
+    Ok(path)
+}
+
+/// Run `cmd` with `input` on stdin; an error carries curl's stderr.
+fn run_curl(cmd: &mut Command, input: &[u8]) -> Result<()> {
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+    child.stdin.take().context("curl stdin")?.write_all(input)?;
+    let out = child.wait_with_output()?;
+    if !out.status.success() { bail!("{}", String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(())
+}
+
+/// POST `body` with curl. Slack and Teams URLs carry their token, so the URL goes through a
+/// curl config file rather than the command line, where other users could read it.
+pub fn post(url: &str, body: &serde_json::Value) -> Result<()> {
+    let rc = curlrc("webhook", &[("url", url)])?;
+    let mut cmd = Command::new("curl");
+    cmd.args(["-fsS", "-m", "10", "-H", "Content-Type: application/json", "--data-binary", "@-", "-K"]).arg(&rc);
+    let result = run_curl(&mut cmd, body.to_string().as_bytes());
+    let _ = std::fs::remove_file(rc);
+    result
+}
+
+/// `scheme://host` of a webhook URL, for messages; the path holds the token.
+pub fn url_host(url: &str) -> &str {
+    let start = url.find("://").map_or(0, |i| i + 3);
+    url[start..].find('/').map_or(url, |i| &url[..start + i])
+}
+
+/// Subject and plain-text body of a digest: the alerts, then the table of each check with a
+/// node that started failing.
+pub(crate) fn digest(cluster: Option<&str>, reports: &[&CheckReport], alerts: &[Alert], now: i64) -> (String, String) {
+    let subject = title(cluster, alerts);
+    let mut body = format!("Check results from {} at {}\n\n", hostname(), time(now));
+    for a in alerts { body.push_str(&line(a)); body.push('\n'); }
+    for r in reports.iter().filter(|r| alerts.iter().any(|a| a.state == AlertState::Firing && r.leaf_results().iter().any(|x| x.check == a.check))) {
+        let (header, rows) = r.plain_rows();
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(header);
+        for row in rows { table.add_row(row); }
+        body.push_str(&format!("\n{}: {}\n{}\n", r.check, r.severity().as_str(), table));
+    }
+    (subject, body)
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname").map(|h| h.trim().to_string()).unwrap_or_else(|_| "beeg".into())
+}
+
+/// RFC 5322 message with CRLF line ends.
+fn message(email: &config::Email, subject: &str, body: &str, now: i64) -> String {
+    let date = chrono::DateTime::from_timestamp(now, 0).unwrap_or_default().to_rfc2822();
+    let headers = [
+        format!("From: {}", email.from),
+        format!("To: {}", email.to.join(", ")),
+        format!("Subject: {}", subject),
+        format!("Date: {}", date),
+        "MIME-Version: 1.0".to_string(),
+        "Content-Type: text/plain; charset=utf-8".to_string(),
+        "Content-Transfer-Encoding: 8bit".to_string(),
+    ];
+    let mut msg = headers.join("\r\n");
+    msg.push_str("\r\n\r\n");
+    // curl escapes lines starting with a dot itself
+    for l in body.lines() { msg.push_str(l); msg.push_str("\r\n"); }
+    msg
+}
+
+/// Hand the message to the SMTP server with curl. Credentials go through a curl config file
+/// rather than the command line, where other users could read them.
+fn send_mail(email: &config::Email, msg: &str) -> Result<()> {
+    let mut cmd = Command::new("curl");
+    cmd.args(["-sS", "-m", "30", "--url", &email.smtp_url, "--mail-from", &email.from, "--upload-file", "-"]);
+    for to in &email.to { cmd.args(["--mail-rcpt", to]); }
+    if email.require_tls { cmd.arg("--ssl-reqd"); }
+    let mut secrets = None;
+    if let Some(user) = &email.username {
+        let password = match &email.password_env {
+            Some(var) => std::env::var(var).with_context(|| format!("reading the SMTP password from ${}", var))?,
+            None => String::new(),
+        };
+        let path = curlrc("smtp", &[("user", &format!("{}:{}", user, password))])?;
+        cmd.arg("-K").arg(&path);
+        secrets = Some(path);
+    }
+    let result = run_curl(&mut cmd, msg.as_bytes());
+    if let Some(path) = secrets { let _ = std::fs::remove_file(path); }
+    result
+}
+
+/// Report the nodes of finished check runs that started or stopped failing: one webhook
+/// request and one mail for all of `reports`. Does nothing without configured webhooks or
+/// email; a failed delivery is warned about and tried again by the next run on that channel only.
+pub fn process_all(cfg: &config::Config, reports: &[&CheckReport]) {
+    let alerts = &cfg.alerts;
+    if alerts.webhooks.is_empty() && alerts.email.is_none() { return; }
+    // held until the state is saved, so a cron run and the daemon do not both send
+    let _lock = state::lock(STATE_FILE).map_err(|e| log::warn!("alert state: {:#}", e)).ok();
+    let now = chrono::Utc::now().timestamp();
+    let mut tracking: Tracking = state::read_json(STATE_FILE).unwrap_or_default();
+    let due: Vec<Alert> = reports.iter().flat_map(|r| evaluate(&mut tracking, r, alerts, now)).collect();
+    if !due.is_empty() {
+        let cluster = cfg.cluster.as_ref().and_then(|c| c.name.as_deref());
+        let channels = channels(alerts);
+        for (hook, channel) in alerts.webhooks.iter().zip(&channels) {
+            let mine = pending(&tracking, &due, channel);
+            if mine.is_empty() { continue; }
+            match post(&hook.url, &payload(hook.format, cluster, &mine, now)) {
+                Ok(()) => mark_sent(&mut tracking, &mine, channel, &channels, now),
+                Err(e) => log::warn!("alert {} ({}): {:#}", channel, url_host(&hook.url), e),
+            }
+        }
+        if let Some(email) = &alerts.email {
+            let mine = pending(&tracking, &due, "email");
+            if !mine.is_empty() {
+                let (subject, body) = digest(cluster, reports, &mine, now);
+                match send_mail(email, &message(email, &subject, &body, now)) {
+                    Ok(()) => mark_sent(&mut tracking, &mine, "email", &channels, now),
+                    Err(e) => log::warn!("alert mail to {}: {:#}", email.to.join(", "), e),
+                }
+            }
+        }
+    }
+    if let Err(e) = state::write_json(STATE_FILE, &tracking) { log::warn!("saving alert state: {:#}", e); }
+}
+
+/// `process_all` for a single check run.
+pub fn process(cfg: &config::Config, report: &CheckReport) {
+    process_all(cfg, &[report]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::CheckResult;
+
+    fn report(statuses: &[(&str, Severity)]) -> CheckReport {
+        let mut r = CheckReport::new("ofed", &[]);
+        r.results = statuses.iter().map(|(node, status)| CheckResult {
+            check: "ofed", node: node.to_string(), status: *status, detail: "d".into(),
+            duration: std::time::Duration::ZERO, cells: Vec::new(), data: serde_json::json!({}),
+        }).collect();
+        r
+    }
+
+    #[test]
+    fn deduplicated() {
+        let hook = config::Webhook { url: "https://hooks.example/beeg".into(), format: WebhookFormat::Generic };
+        let cfg = config::Alerts { repeat_hours: Some(1), webhooks: vec![hook], ..Default::default() };
+        let mut t = Tracking::new();
+        let a = evaluate(&mut t, &report(&[("n1", Severity::Ok), ("n2", Severity::Failure), ("n3", Severity::Warning)]), &cfg, 0);
+        assert_eq!(a.len(), 1);
+        assert_eq!((a[0].node.as_str(), a[0].state), ("n2", AlertState::Firing));
+
+        // not delivered: tried again
+        assert_eq!(evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 60).len(), 1);
+        mark_sent(&mut t, &a, "webhook 1", &["webhook 1".into()], 60);
+        assert!(evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 120).is_empty());
+        // repeated after repeat_hours, still counting from when it started
+        let again = evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 60 + 3600);
+        assert_eq!((again.len(), again[0].since), (1, 0));
+
+        let ok = evaluate(&mut t, &report(&[("n2", Severity::Ok)]), &cfg, 4000);
+        assert_eq!((ok.len(), ok[0].state), (1, AlertState::Resolved));
+        mark_sent(&mut t, &ok, "webhook 1", &["webhook 1".into()], 4000);
+        assert!(t.is_empty());
+
+        let warn = config::Alerts { min_status: AlertLevel::Warn, ..Default::default() };
+        assert_eq!(evaluate(&mut Tracking::new(), &report(&[("n3", Severity::Warning)]), &warn, 0).len(), 1);
+    }
+
+    #[test]
+    fn per_channel() {
+        let cfg = config::Alerts {
+            webhooks: vec![config::Webhook { url: "https://hooks.slack.com/services/T0/B0/secret".into(), format: WebhookFormat::Slack }; 2],
+            ..Default::default()
+        };
+        let channels = channels(&cfg);
+        assert_eq!(channels, ["webhook 1", "webhook 2"]);
+        let mut t = Tracking::new();
+        let due = evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 0);
+        // the first webhook got it, the second failed
+        let first = pending(&t, &due, "webhook 1");
+        mark_sent(&mut t, &first, "webhook 1", &channels, 0);
+        let due = evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 60);
+        assert!(pending(&t, &due, "webhook 1").is_empty());
+        let retry = pending(&t, &due, "webhook 2");
+        assert_eq!(retry.len(), 1);
+        mark_sent(&mut t, &retry, "webhook 2", &channels, 60);
+        assert!(evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 120).is_empty());
+        assert_eq!(t["ofed"]["n2"].notified, Some(60));
+        assert_eq!(url_host("https://hooks.slack.com/services/T0/B0/secret"), "https://hooks.slack.com");
+    }
+
+    #[test]
+    fn resolve_retried() {
+        let cfg = config::Alerts {
+            webhooks: vec![config::Webhook { url: "https://hooks.example/beeg".into(), format: WebhookFormat::Generic }; 2],
+            ..Default::default()
+        };
+        let channels = channels(&cfg);
+        let mut t = Tracking::new();
+        let due = evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 0);
+        for c in &channels {
+            let mine = pending(&t, &due, c);
+            mark_sent(&mut t, &mine, c, &channels, 0);
+        }
+        // the resolve reaches the first webhook; the second fails and gets it next run
+        let ok = evaluate(&mut t, &report(&[("n2", Severity::Ok)]), &cfg, 60);
+        let first = pending(&t, &ok, "webhook 1");
+        assert_eq!((first.len(), first[0].state), (1, AlertState::Resolved));
+        mark_sent(&mut t, &first, "webhook 1", &channels, 60);
+        let again = evaluate(&mut t, &report(&[("n2", Severity::Ok)]), &cfg, 120);
+        assert!(pending(&t, &again, "webhook 1").is_empty());
+        let second = pending(&t, &again, "webhook 2");
+        assert_eq!((second.len(), second[0].state), (1, AlertState::Resolved));
+        mark_sent(&mut t, &second, "webhook 2", &channels, 120);
+        assert!(t.is_empty());
+        assert!(evaluate(&mut t, &report(&[("n2", Severity::Ok)]), &cfg, 180).is_empty());
+    }
+
+    #[test]
+    fn resolve_to_reported_channels() {
+        let cfg = config::Alerts {
+            webhooks: vec![config::Webhook { url: "https://hooks.example/beeg".into(), format: WebhookFormat::Generic }; 2],
+            ..Default::default()
+        };
+        let channels = channels(&cfg);
+        let mut t = Tracking::new();
+        // the failure reached only the first webhook before the node recovered
+        let due = evaluate(&mut t, &report(&[("n2", Severity::Failure)]), &cfg, 0);
+        let first = pending(&t, &due, "webhook 1");
+        mark_sent(&mut t, &first, "webhook 1", &channels, 0);
+        assert_eq!(t["ofed"]["n2"].notified, None);
+        let ok = evaluate(&mut t, &report(&[("n2", Severity::Ok)]), &cfg, 60);
+        assert_eq!(pending(&t, &ok, "webhook 1").len(), 1);
+        assert!(pending(&t, &ok, "webhook 2").is_empty());
+        let resolve = pending(&t, &ok, "webhook 1");
+        mark_sent(&mut t, &resolve, "webhook 1", &channels, 60);
+        assert!(t.is_empty());
+
+        // a failure no channel got is dropped without a resolve
+        evaluate(&mut t, &report(&[("n3", Severity::Failure)]), &cfg, 120);
+        assert!(evaluate(&mut t, &report(&[("n3", Severity::Ok)]), &cfg, 180).is_empty());
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn payloads() {
+        let a = vec![Alert { state: AlertState::Firing, check: "ofed".into(), node: "n2".into(), status: "FAIL".into(), detail: "d".into(), since: 0 }];
+        let slack = payload(WebhookFormat::Slack, Some("prod"), &a, 0);
+        assert_eq!(slack["text"], "*beeg [prod]: 1 failing, 0 resolved*\nFAIL ofed on n2: d");
+        let generic = payload(WebhookFormat::Generic, None, &a, 0);
+        assert_eq!((generic["alerts"][0]["state"].as_str(), generic["alerts"][0]["since"].as_str()), (Some("firing"), Some("1970-01-01T00:00:00Z")));
+        assert_eq!(payload(WebhookFormat::Teams, None, &a, 0)["@type"], "MessageCard");
+
+        let r = report(&[("n1", Severity::Ok), ("n2", Severity::Failure)]);
+        let (subject, body) = digest(Some("prod"), &[&r], &a, 0);
+        assert_eq!(subject, "beeg [prod]: 1 failing, 0 resolved");
+        assert!(body.contains("FAIL ofed on n2: d\n\nofed: FAIL\n") && body.contains("│ n2   ┆ FAIL   ┆ d      │"));
+        let email = config::Email { smtp_url: "smtp://mail:587".into(), from: "beeg@x".into(), to: vec!["a@x".into(), "b@x".into()], username: None, password_env: None, require_tls: true };
+        let msg = message(&email, &subject, "hi\n.\nbye", 0);
+        assert!(msg.starts_with("From: beeg@x\r\nTo: a@x, b@x\r\nSubject: beeg [prod]"));
+        assert!(msg.ends_with("\r\n\r\nhi\r\n.\r\nbye\r\n"));
+    }
+}
@@ -0,0 +1,464 @@
+use crate::checks::{permission_denied, wrap_timeout, Severity, NOPERM_HINT};
+use crate::sshconfig::diff_lines;
+use crate::{config, identity, prompt, transport};
+use anyhow::{bail, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+/// Where the BeeGFS services keep their configs.
+pub(crate) const CONF_DIR: &str = "/etc/beegfs";
+
+/// Exit code of the write command when the file changed after it was read.
+const CHANGED_UNDERNEATH: i32 = 4;
+
+/// `client`, `beegfs-client.conf` or an absolute path, as the file on the node.
+pub(crate) fn conf_path(file: &str) -> String {
+    if file.starts_with('/') { return file.to_string(); }
+    let name = if file.ends_with(".conf") { file.to_string() } else { format!("{}.conf", file) };
+    let name = if name.starts_with("beegfs-") { name } else { format!("beegfs-{}", name) };
+    format!("{}/{}", CONF_DIR, name)
+}
+
+/// BeeGFS keys are plain identifiers such as `connMgmtdPortTCP`.
+fn check_key(key: &str) -> Result<()> {
+    let ok = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !ok { bail!("'{}' is not a BeeGFS config key", key); }
+    Ok(())
+}
+
+/// `(key, value)` of a `key = value` line; comments and blank lines have none.
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let t = line.trim();
+    if t.is_empty() || t.starts_with('#') { return None; }
+    let (k, v) = t.split_once('=')?;
+    Some((k.trim(), v.trim()))
+}
+
+/// The value of `key` in a BeeGFS config; the last assignment wins, as in the services.
+pub(crate) fn get_value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    text.lines().filter_map(parse_line).filter(|(k, _)| *k == key).map(|(_, v)| v).next_back()
+}
+
+/// `text` with every `key = ...` line given `value`, keeping comments, layout and the spacing
+/// around `=`. `None` when the key is not set in the file: BeeGFS rejects keys it does not
+/// know, so a missing one is not appended.
+pub(crate) fn set_value(text: &str, key: &str, value: &str) -> Option<String> {
+    let mut found = false;
+    let mut out = String::with_capacity(text.len() + value.len());
+    for line in text.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\n', '\r']);
+        match parse_line(body) {
+            Some((k, _)) if k == key => {
+                found = true;
+                let eq = body.find('=').unwrap_or(body.len());
+                let rest = &body[eq + 1..];
+                out.push_str(&body[..=eq]);
+                out.push_str(&rest[..rest.len() - rest.trim_start().len()]);
+                out.push_str(value);
+                out.push_str(&line[body.len()..]);
+            }
+            _ => out.push_str(line),
+        }
+    }
+    found.then_some(out)
+}
+
+fn esc(s: &str) -> String {
+    shell_escape::escape(s.to_string().into()).into_owned()
+}
+
+/// What the write command prints when the file already holds the new contents, e.g. after
+/// an earlier run that lost its connection once the write was done.
+const ALREADY_WRITTEN: &str = "already written";
+
+/// Copies `path` to `backup`, writes `new` to `<path>.beeg-tmp` next to it (a copy of the file,
+/// so owner and mode stay) and renames that over `path`, so a reader never sees half a file.
+/// Refuses when the file no longer holds `old`, i.e. someone edited it since it was read; a
+/// file that already holds `new` is left alone.
+pub(crate) fn write_command(path: &str, backup: &str, old: &str, new: &str) -> String {
+    format!(
+        "printf %s {new} | cmp -s - {f} && {{ echo {done}; exit 0; }}; \
+         printf %s {old} | cmp -s - {f} || {{ echo {msg} >&2; exit {code}; }}; \
+         cp -p {f} {b} && cp -p {f} {t} && printf %s {new} > {t} && mv -f {t} {f} || {{ rm -f {t}; exit 1; }}",
+        old = esc(old),
+        new = esc(new),
+        f = esc(path),
+        b = esc(backup),
+        t = esc(&format!("{}.beeg-tmp", path)),
+        done = esc(ALREADY_WRITTEN),
+        msg = esc(&format!("{} changed since it was read", path)),
+        code = CHANGED_UNDERNEATH,
+    )
+}
+
+/// `path` read on every node, in node order.
+fn read_all(cfg: &config::Config, nodes: &[&config::Node], path: &str, timeout: u64) -> Vec<Result<String, String>> {
+    let cmd = wrap_timeout(&format!("cat -- {}", esc(path)), timeout);
+    crate::checks::per_node(cfg, nodes, |tr, n| match tr.exec_idempotent(&n.host, &cmd) {
+        Ok(out) if out.status == Some(0) => Ok(out.stdout),
+        Ok(out) => Err(out.stderr.trim().lines().last().unwrap_or("cannot read the file").to_string()),
+        Err(e) => Err(e.to_string()),
+    }).into_iter().map(|(_, res, _)| res).collect()
+}
+
+/// OK without errors, NOPERM when the user was refused every time, else a failure.
+fn errors_severity<'a>(errors: impl IntoIterator<Item = &'a String>) -> Severity {
+    errors.into_iter().map(|e| if permission_denied(e) { Severity::NoPerm } else { Severity::Failure }).max().unwrap_or_default()
+}
+
+/// Table cell for a read or write error.
+fn error_cell(e: &str) -> String {
+    format!("{}: {}", if permission_denied(e) { Severity::NoPerm } else { Severity::Failure }.as_str(), e)
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Value {
+    node: String,
+    host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub fn get(cli: &crate::GlobalArgs, cfg: &config::Config, selector: &str, file: &str, key: &str, timeout: u64) -> Result<Severity> {
+    check_key(key)?;
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    let path = conf_path(file);
+    let mut rows: Vec<Value> = nodes.iter().zip(read_all(cfg, &nodes, &path, timeout)).map(|(n, r)| {
+        let (value, error) = match r {
+            Ok(text) => (get_value(&text, key).map(String::from), None),
+            Err(e) => (None, Some(e)),
+        };
+        Value { node: n.name.clone(), host: n.host.clone(), value, error }
+    }).collect();
+    config::sort_rows(cfg, cli.sort, &mut rows, |r| &r.node);
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "File", key]);
+            for r in &rows {
+                let cell = match (&r.value, &r.error) {
+                    (_, Some(e)) => error_cell(e),
+                    (Some(v), None) => v.clone(),
+                    (None, None) => "(not set)".into(),
+                };
+                table.add_row(vec![r.node.clone(), path.clone(), cell]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({"key": key, "file": path, "nodes": rows});
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    let failed: Vec<&str> = rows.iter().filter(|r| r.error.is_some()).map(|r| r.node.as_str()).collect();
+    if !failed.is_empty() { log::warn!("could not read {} on {}", path, failed.join(", ")); }
+    let errors = errors_severity(rows.iter().filter_map(|r| r.error.as_ref()));
+    if errors == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+    let unset: Vec<&str> = rows.iter().filter(|r| r.error.is_none() && r.value.is_none()).map(|r| r.node.as_str()).collect();
+    if !unset.is_empty() { log::warn!("{} is not set in {} on {}", key, path, unset.join(", ")); }
+    let mut values: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for r in &rows { if let Some(v) = &r.value { values.entry(v).or_default().push(&r.node); } }
+    if values.len() > 1 {
+        log::warn!("{} differs between nodes:{}", key, values.iter().map(|(v, nodes)| format!("\n  {}: {}", v, nodes.join(", "))).collect::<String>());
+    }
+    Ok(if errors != Severity::Ok { errors } else if !unset.is_empty() || values.len() > 1 { Severity::Warning } else { Severity::Ok })
+}
+
+/// Every key of a BeeGFS config with its value, whitespace collapsed; the last assignment wins.
+pub(crate) fn normalize(text: &str) -> BTreeMap<String, String> {
+    text.lines().filter_map(parse_line)
+        .map(|(k, v)| (k.to_string(), v.split_whitespace().collect::<Vec<_>>().join(" ")))
+        .collect()
+}
+
+/// A key whose value is not the same everywhere.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Drift {
+    pub key: String,
+    /// The reference node's value, or the most common one; `None` when it is not set there
+    pub expected: Option<String>,
+    /// Nodes that differ from `expected`, with their value (`None`: not set)
+    pub differs: BTreeMap<String, Option<String>>,
+}
+
+/// Keys that differ across `configs` (node name, keys), against `reference` or else the
+/// value most nodes have.
+pub fn drift(configs: &[(&str, BTreeMap<String, String>)], reference: Option<&str>, ignore: &[String]) -> Vec<Drift> {
+    let keys: std::collections::BTreeSet<&String> = configs.iter().flat_map(|(_, c)| c.keys()).collect();
+    let mut out = Vec::new();
+    for key in keys.into_iter().filter(|k| !ignore.contains(k)) {
+        let value = |c: &BTreeMap<String, String>| c.get(key).cloned();
+        let expected = match reference.and_then(|r| configs.iter().find(|(n, _)| *n == r)) {
+            Some((_, c)) => value(c),
+            None => {
+                let mut counts: BTreeMap<Option<String>, usize> = BTreeMap::new();
+                for (_, c) in configs { *counts.entry(value(c)).or_default() += 1; }
+                // ties go to the first value in order, so the result does not depend on node order
+                counts.into_iter().fold((None, 0), |best, (v, n)| if n > best.1 { (v, n) } else { best }).0
+            }
+        };
+        let differs: BTreeMap<String, Option<String>> = configs.iter()
+            .filter(|(_, c)| value(c) != expected)
+            .map(|(n, c)| (n.to_string(), value(c)))
+            .collect();
+        if !differs.is_empty() { out.push(Drift { key: key.clone(), expected, differs }); }
+    }
+    out
+}
+
+pub fn diff(cli: &crate::GlobalArgs, cfg: &config::Config, selector: &str, file: &str, reference: Option<&str>, ignore: &[String], timeout: u64) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    if let Some(r) = reference {
+        if !nodes.iter().any(|n| n.name == r) { bail!("reference node '{}' is not among the selected nodes", r); }
+    }
+    let path = conf_path(file);
+    let mut configs = Vec::new();
+    let mut unreadable = Vec::new();
+    for (n, r) in nodes.iter().zip(read_all(cfg, &nodes, &path, timeout)) {
+        match r {
+            Ok(text) => configs.push((n.name.as_str(), normalize(&text))),
+            Err(e) => unreadable.push((n.name.as_str(), e)),
+        }
+    }
+    if let Some(r) = reference.filter(|r| unreadable.iter().any(|(n, _)| n == r)) {
+        bail!("cannot read {} on the reference node {}", path, r);
+    }
+    let drifted = drift(&configs, reference, ignore);
+    let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "(not set)".into());
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Key", reference.unwrap_or("Most nodes"), "Differs on"]);
+            for d in &drifted {
+                let mut by: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+                for (n, v) in &d.differs { by.entry(show(v)).or_default().push(n); }
+                let lines: Vec<String> = by.iter().map(|(v, ns)| format!("{}: {}", v, ns.join(", "))).collect();
+                table.add_row(vec![d.key.clone(), show(&d.expected), lines.join("\n")]);
+            }
+            if drifted.is_empty() && crate::tables::separator(cli.output).is_none() {
+                println!("{}: no drift across {} node(s)", path, configs.len());
+            } else {
+                crate::tables::print(cli.output, &table);
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "file": path,
+                "reference": reference,
+                "nodes": configs.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+                "drift": drifted,
+                "unreadable": unreadable.iter().map(|(n, e)| serde_json::json!({"node": n, "error": e})).collect::<Vec<_>>(),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    for (n, e) in &unreadable { log::warn!("could not read {} on {}: {}", path, n, e); }
+    let errors = errors_severity(unreadable.iter().map(|(_, e)| e));
+    if errors == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+    if !drifted.is_empty() { log::warn!("{} key(s) of {} differ across nodes", drifted.len(), path); }
+    Ok(if errors != Severity::Ok { errors } else if !drifted.is_empty() { Severity::Warning } else { Severity::Ok })
+}
+
+/// One node's planned change.
+#[derive(Debug, Clone, Serialize)]
+struct Edit {
+    node: String,
+    host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_value: Option<String>,
+    /// `-`/`+` lines; empty when the value is already set
+    diff: Vec<String>,
+    #[serde(skip)]
+    contents: Option<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backup: Option<String>,
+    written: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub struct SetOptions<'a> {
+    pub file: &'a str,
+    pub dry_run: bool,
+    /// Ask before writing
+    pub confirm: bool,
+    /// Answer the question with yes, for automation
+    pub yes: bool,
+    pub timeout: u64,
+}
+
+fn print_diffs(edits: &[Edit], path: &str) {
+    for e in edits {
+        match (&e.error, e.diff.is_empty()) {
+            (Some(err), _) => println!("{} ({}): {}", e.node, e.host, err),
+            (None, true) => println!("{} ({}): {} already set, unchanged", e.node, e.host, path),
+            (None, false) => {
+                println!("--- {} ({}): {}", e.node, e.host, path);
+                for line in &e.diff { println!("{}", line); }
+            }
+        }
+    }
+}
+
+pub fn set(cli: &crate::GlobalArgs, cfg: &config::Config, selector: &str, key: &str, value: &str, opts: &SetOptions) -> Result<Severity> {
+    check_key(key)?;
+    if value.contains('\n') { bail!("the value for {} must be a single line", key); }
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    let path = conf_path(opts.file);
+    let backup = format!("{}.beeg-{}", path, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+
+    let mut edits: Vec<Edit> = nodes.iter().zip(read_all(cfg, &nodes, &path, opts.timeout)).map(|(n, r)| {
+        let mut e = Edit { node: n.name.clone(), host: n.host.clone(), old_value: None, diff: Vec::new(), contents: None, backup: None, written: false, error: None };
+        match r {
+            Err(err) => e.error = Some(err),
+            Ok(old) => match set_value(&old, key, value) {
+                None => e.error = Some(format!("{} is not set in {}", key, path)),
+                Some(new) => {
+                    e.old_value = get_value(&old, key).map(String::from);
+                    e.diff = diff_lines(&old, &new);
+                    if !e.diff.is_empty() { e.contents = Some((old, new)); }
+                }
+            },
+        }
+        e
+    }).collect();
+    config::sort_rows(cfg, cli.sort, &mut edits, |e| &e.node);
+    let pending: Vec<&str> = edits.iter().filter(|e| e.contents.is_some()).map(|e| e.node.as_str()).collect();
+
+    if opts.dry_run {
+        match cli.output {
+            crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => print_diffs(&edits, &path),
+            crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+                let v = serde_json::json!({"dry_run": true, "key": key, "value": value, "file": path, "nodes": edits});
+                crate::formats::print(cli.output, &v)?;
+            }
+        }
+        return Ok(errors_severity(edits.iter().filter_map(|e| e.error.as_ref())));
+    }
+
+    if !pending.is_empty() && opts.confirm && !opts.yes {
+        if !std::io::stdin().is_terminal() { bail!("refusing to edit {} without confirmation; pass --yes to run non-interactively", path); }
+        if matches!(cli.output, crate::Output::Human) { print_diffs(&edits, &path); }
+        let question = format!("Set `{} = {}` in {} on {} node(s) ({})?", key, value, path, pending.len(), pending.join(", "));
+        if !prompt::confirm(&question)? {
+            eprintln!("Aborted; no files were changed.");
+            return Ok(Severity::Warning);
+        }
+    }
+
+    if !pending.is_empty() {
+        let targets: Vec<&config::Node> = nodes.iter().copied().filter(|n| pending.contains(&n.name.as_str())).collect();
+        identity::verify(cfg, &targets, opts.timeout)?;
+    }
+
+    let tr = transport::from_config(cfg);
+    for e in edits.iter_mut() {
+        let Some((old, new)) = &e.contents else { continue };
+        let cmd = wrap_timeout(&write_command(&path, &backup, old, new), opts.timeout);
+        match tr.exec(&e.host, &cmd) {
+            Ok(out) if out.status == Some(0) && out.stdout.trim() == ALREADY_WRITTEN => e.written = true,
+            Ok(out) if out.status == Some(0) => { e.written = true; e.backup = Some(backup.clone()); }
+            Ok(out) => e.error = Some(out.stderr.trim().lines().last().unwrap_or("write failed").to_string()),
+            Err(err) => e.error = Some(err.to_string()),
+        }
+    }
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "File", "Before", "Result"]);
+            for e in &edits {
+                let result = match (&e.error, e.written) {
+                    (Some(err), _) => error_cell(err),
+                    (None, true) => match &e.backup {
+                        Some(b) => format!("{} = {} (backup {})", key, value, b),
+                        None => format!("{} = {} (already written)", key, value),
+                    },
+                    (None, false) => "unchanged".into(),
+                };
+                table.add_row(vec![e.node.clone(), path.clone(), e.old_value.clone().unwrap_or_else(|| "-".into()), result]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({"key": key, "value": value, "file": path, "nodes": edits});
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    let failed: Vec<&str> = edits.iter().filter(|e| e.error.is_some()).map(|e| e.node.as_str()).collect();
+    if !failed.is_empty() { log::warn!("{} was not changed on {}", path, failed.join(", ")); }
+    let errors = errors_severity(edits.iter().filter_map(|e| e.error.as_ref()));
+    if errors == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+    let written = edits.iter().filter(|e| e.written).count();
+    if written > 0 { eprintln!("NOTE: restart the BeeGFS service on the {} changed node(s) for the new value to apply", written); }
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &str = "# beegfs-client.conf\nsysMgmtdHost             = mgmt01\n\n# connMgmtdPortTCP = 9008\nconnMgmtdPortTCP         = 8008\r\nlogLevel=3\n";
+
+    #[test]
+    fn edits() {
+        assert_eq!(conf_path("client"), "/etc/beegfs/beegfs-client.conf");
+        assert_eq!(conf_path("beegfs-storage.conf"), "/etc/beegfs/beegfs-storage.conf");
+        assert_eq!(conf_path("/opt/beegfs/meta.conf"), "/opt/beegfs/meta.conf");
+        assert!(check_key("connMgmtdPortTCP").is_ok());
+        assert!(check_key("conn Port").is_err());
+
+        assert_eq!(get_value(CONF, "connMgmtdPortTCP"), Some("8008"));
+        assert_eq!(get_value(CONF, "sysMountSanityCheckMS"), None);
+        let new = set_value(CONF, "connMgmtdPortTCP", "8108").unwrap();
+        assert_eq!(new, CONF.replace("= 8008", "= 8108"));
+        assert_eq!(set_value(CONF, "logLevel", "5").unwrap(), CONF.replace("logLevel=3", "logLevel=5"));
+        assert!(set_value(CONF, "sysMountSanityCheckMS", "0").is_none());
+        assert_eq!(diff_lines(CONF, &new), vec!["-connMgmtdPortTCP         = 8008", "+connMgmtdPortTCP         = 8108"]);
+
+        let cmd = write_command("/etc/beegfs/beegfs-client.conf", "/etc/beegfs/beegfs-client.conf.beeg-1", "a = 1\n", "a = 2\n");
+        assert!(cmd.starts_with("printf %s 'a = 2\n' | cmp -s - /etc/beegfs/beegfs-client.conf && { echo 'already written'; exit 0; };"));
+        assert!(cmd.contains("printf %s 'a = 1\n' | cmp -s - /etc/beegfs/beegfs-client.conf || {"));
+        assert!(cmd.ends_with("printf %s 'a = 2\n' > /etc/beegfs/beegfs-client.conf.beeg-tmp && mv -f /etc/beegfs/beegfs-client.conf.beeg-tmp /etc/beegfs/beegfs-client.conf || { rm -f /etc/beegfs/beegfs-client.conf.beeg-tmp; exit 1; }"));
+    }
+
+    #[test]
+    fn drift_across_nodes() {
+        let a = normalize(CONF);
+        assert_eq!(a.get("sysMgmtdHost").map(String::as_str), Some("mgmt01"));
+        let mut b = a.clone();
+        b.insert("connMgmtdPortTCP".into(), "9008".into());
+        let mut c = a.clone();
+        c.remove("logLevel");
+        let configs = vec![("n1", a), ("n2", b), ("n3", c)];
+
+        let d = drift(&configs, None, &[]);
+        assert_eq!(d.len(), 2);
+        assert_eq!((d[0].key.as_str(), d[0].expected.as_deref()), ("connMgmtdPortTCP", Some("8008")));
+        assert_eq!(d[0].differs, BTreeMap::from([("n2".to_string(), Some("9008".to_string()))]));
+        assert_eq!(d[1].differs, BTreeMap::from([("n3".to_string(), None)]));
+
+        let d = drift(&configs, Some("n2"), &["logLevel".to_string()]);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].differs.keys().collect::<Vec<_>>(), vec!["n1", "n3"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! `--getentryinfo`.
+
+use crate::transfer;
+use regex::Regex;
+use serde::Serialize;
+
+/// Where a file or directory lives.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EntryInfo {
+    pub entry_type: Option<String>,
+    pub entry_id: Option<String>,
+    /// The owning meta node; the current primary when metadata is buddy mirrored
+    pub metadata_node: Option<String>,
+    pub metadata_buddy_group: Option<String>,
+    pub pattern: Option<String>,
+    pub chunk_size: Option<u64>,
+    pub desired_targets: Option<u32>,
+    /// Only reported for files; a directory's pattern applies to new files in it
+    pub actual_targets: Option<u32>,
+    pub storage_pool: Option<String>,
+    /// Storage targets, or buddy groups for a buddy-mirrored pattern
+    pub targets: Vec<Placement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Placement {
+    pub id: String,
+    pub node: String,
+}
+
+pub fn parse_entry_info(text: &str) -> EntryInfo {
+    let kv = Regex::new(r"^\+?\s*([A-Za-z][A-Za-z ]*?):\s*(.*?)\s*$").unwrap();
+    let target = Regex::new(r"^\s+\+\s*(\d+)\s*@\s*(.+?)\s*$").unwrap();
+    let desired = Regex::new(r"desired:\s*(\d+)").unwrap();
+    let actual = Regex::new(r"actual:\s*(\d+)").unwrap();
+    let mut e = EntryInfo::default();
+    for line in text.lines() {
+        if let Some(c) = target.captures(line) {
+            e.targets.push(Placement { id: c[1].to_string(), node: c[2].to_string() });
+            continue;
+        }
+        let Some(c) = kv.captures(line) else { continue };
+        let value = c[2].to_string();
+        match c[1].to_ascii_lowercase().as_str() {
+            "entry type" => e.entry_type = Some(value),
+            "entryid" => e.entry_id = Some(value),
+            "metadata node" | "current primary metadata node" => e.metadata_node = Some(value),
+            "metadata buddy group" => e.metadata_buddy_group = Some(value),
+            "type" => e.pattern = Some(value),
+            "chunksize" => e.chunk_size = transfer::parse_size(&value).ok(),
+            "number of storage targets" => {
+                e.desired_targets = desired.captures(&value).and_then(|c| c[1].parse().ok());
+                e.actual_targets = actual.captures(&value).and_then(|c| c[1].parse().ok());
+            }
+            "storage pool" => e.storage_pool = Some(value),
+            _ => {}
+        }
+    }
+    e
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V72_FILE: &str = "Entry type: file
+EntryID: 0-5F3A1B2C-1
+Metadata node: meta01 [ID: 1]
+Stripe pattern details:
++ Type: RAID0
++ Chunksize: 512K
++ Number of storage targets: desired: 4; actual: 3
++ Storage Pool: 1 (Default)
++ Storage targets:
+  + 101 @ stor01 [ID: 1]
+  + 102 @ stor01 [ID: 1]
+  + 201 @ stor02 [ID: 2]
+";
+
+    const V73_DIR: &str = "Entry type: directory
+EntryID: root
+Metadata node: meta01 [ID: 1]
+Stripe pattern details:
++ Type: Buddy Mirror
++ Chunksize: 1M
++ Number of storage targets: desired: 4
++ Storage Pool: 1 (Default)
+";
+
+    const V74_MIRRORED: &str = "Entry type: file
+EntryID: 0-5E8F-1
+Metadata buddy group: 1
+Current primary metadata node: meta01 [ID: 1]
+Stripe pattern details:
++ Type: Buddy Mirror
++ Chunksize: 1M
++ Number of storage targets: desired: 2; actual: 2
++ Storage Pool: 1 (Default)
++ Storage mirror buddy groups:
+  + 1
+  + 2
+";
+
+    #[test]
+    fn entry_info() {
+        let e = parse_entry_info(V72_FILE);
+        assert_eq!(e.entry_type.as_deref(), Some("file"));
+        assert_eq!(e.entry_id.as_deref(), Some("0-5F3A1B2C-1"));
+        assert_eq!(e.pattern.as_deref(), Some("RAID0"));
+        assert_eq!(e.chunk_size, Some(512 << 10));
+        assert_eq!((e.desired_targets, e.actual_targets), (Some(4), Some(3)));
+        assert_eq!(e.storage_pool.as_deref(), Some("1 (Default)"));
+        assert_eq!(e.targets.len(), 3);
+        assert_eq!(e.targets[2], Placement { id: "201".into(), node: "stor02 [ID: 2]".into() });
+        assert_eq!(e.metadata_buddy_group, None);
+
+        let dir = parse_entry_info(V73_DIR);
+        assert_eq!(dir.pattern.as_deref(), Some("Buddy Mirror"));
+        assert_eq!(dir.actual_targets, None);
+        assert!(dir.targets.is_empty());
+
+        let m = parse_entry_info(V74_MIRRORED);
+        assert_eq!(m.metadata_buddy_group.as_deref(), Some("1"));
+        assert_eq!(m.metadata_node.as_deref(), Some("meta01 [ID: 1]"));
+        assert_eq!(m.desired_targets, Some(2));
+    }
+}
@@ -0,0 +1,394 @@
+//! Typed wrappers around `beegfs-ctl`: each runs one listing on a node over the transport and
+//! parses it into the structs the checks consume, so no check scrapes ctl text itself.
+//!
+//! Listings are read by their header line rather than by column position. Columns come and go
+//! between releases (older ones print `--spaceinfo` without a `Pool` column), and the last
+//! column, `NodeID`, has spaces in it with `--longnodes`.
+//!
+//! BeeGFS 8 replaces beegfs-ctl with the `beegfs` tool (see `v8`). Which one a node has is
+//! probed once per run, and the wrappers translate each listing to its syntax.
+
+mod entry;
+mod nodes;
+mod resync;
+mod targets;
+mod v8;
+
+pub use entry::{parse_entry_info, EntryInfo};
+pub use nodes::{parse_listnodes, ListedNode};
+pub use resync::{parse_resync_stats, ResyncStats};
+pub use targets::{parse_mirror_groups, parse_spaceinfo, parse_storage_pools, parse_target_states, MirrorGroup, StoragePool, TargetSpace, TargetState};
+
+use crate::checks::{check_permission, wrap_timeout};
+use crate::{config, transport};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Which servers a listing is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeType {
+    Meta,
+    Storage,
+}
+
+impl NodeType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NodeType::Meta => "meta",
+            NodeType::Storage => "storage",
+        }
+    }
+
+    /// `meta` or `storage`, as the checks take it on the command line.
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "meta" => Ok(NodeType::Meta),
+            "storage" => Ok(NodeType::Storage),
+            _ => anyhow::bail!("unknown node type '{}' (expected meta or storage)", s),
+        }
+    }
+}
+
+/// Release of the BeeGFS tools on a node, e.g. 7.3.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The `Version:` line of `beegfs-ctl --version`, else the first dotted number (`beegfs 8.0.1`);
+/// the patch level may be missing.
+pub fn parse_version(text: &str) -> Option<Version> {
+    let v = match text.lines().find_map(|l| l.trim().strip_prefix("Version:")) {
+        Some(v) => v.trim(),
+        None => text.split_whitespace().find(|w| w.split('.').take(2).filter(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())).count() == 2)?,
+    };
+    let mut parts = v.split(|c: char| !c.is_ascii_digit()).filter(|p| !p.is_empty()).map(|p| p.parse::<u32>());
+    Some(Version {
+        major: parts.next()?.ok()?,
+        minor: parts.next()?.ok()?,
+        patch: parts.next().and_then(|p| p.ok()).unwrap_or(0),
+    })
+}
+
+/// One data row of a listing, cells by header name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    cells: Vec<(String, String)>,
+}
+
+impl Row {
+    /// The first cell under `column` (case-insensitive); `None` when the row is too short.
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.cells.iter().find(|(c, _)| c.eq_ignore_ascii_case(column)).map(|(_, v)| v.as_str())
+    }
+}
+
+/// The rows under the first `====` ruler of a `beegfs-ctl` listing, up to the next blank line.
+/// Header names are separated by two or more spaces; data rows start with a numeric ID and
+/// their last column takes the rest of the line.
+pub fn rows(text: &str) -> Vec<Row> {
+    let lines: Vec<&str> = text.lines().collect();
+    let Some(ruler) = lines.iter().position(|l| l.trim_start().starts_with("==")) else { return Vec::new() };
+    let Some(header) = ruler.checked_sub(1).map(|i| lines[i]) else { return Vec::new() };
+    let columns: Vec<&str> = header.trim().split("  ").map(str::trim).filter(|c| !c.is_empty()).collect();
+    lines[ruler + 1..].iter().take_while(|l| !l.trim().is_empty()).filter_map(|l| {
+        let tokens: Vec<&str> = l.split_whitespace().collect();
+        if !tokens.first()?.chars().all(|c| c.is_ascii_digit()) { return None; }
+        let cells = columns.iter().enumerate().filter_map(|(i, c)| {
+            let value = if i + 1 == columns.len() { tokens.get(i..).filter(|t| !t.is_empty())?.join(" ") } else { tokens.get(i)?.to_string() };
+            Some((c.to_string(), value))
+        }).collect();
+        Some(Row { cells })
+    }).collect()
+}
+
+/// Which admin tool a node has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// `beegfs-ctl`, up to BeeGFS 7
+    Ctl,
+    /// `beegfs`, BeeGFS 8 and later
+    Beegfs,
+}
+
+impl Tool {
+    pub fn command(self) -> &'static str {
+        match self {
+            Tool::Ctl => "beegfs-ctl",
+            Tool::Beegfs => "beegfs",
+        }
+    }
+}
+
+/// Prints `ctl` or `beegfs`, whichever is installed; beegfs-ctl when both are.
+const TOOL_PROBE: &str = "if command -v beegfs-ctl >/dev/null 2>&1; then echo ctl; elif command -v beegfs >/dev/null 2>&1; then echo beegfs; fi";
+
+/// What `TOOL_PROBE` printed. Without either, beegfs-ctl is assumed so errors name the familiar tool.
+pub fn parse_tool(text: &str) -> Tool {
+    if text.trim() == "beegfs" { Tool::Beegfs } else { Tool::Ctl }
+}
+
+/// Global flags that point the local `beegfs` at the mgmtd's gRPC API.
+pub fn grpc_flags(grpc: &config::Grpc, addr: &str) -> String {
+    let quote = |s: &str| shell_escape::escape(s.to_string().into()).to_string();
+    let mut flags = vec![format!("--mgmtd-addr={}", quote(addr))];
+    if grpc.tls_disable { flags.push("--tls-disable".into()); }
+    if let Some(c) = &grpc.tls_cert_file { flags.push(format!("--tls-cert-file={}", quote(c))); }
+    if let Some(a) = &grpc.auth_file { flags.push(format!("--auth-file={}", quote(a))); }
+    else if grpc.auth_disable { flags.push("--auth-disable".into()); }
+    flags.join(" ")
+}
+
+/// Whether an executable `beegfs` is on `path` (a `PATH` value).
+fn tool_on_path(path: &std::ffi::OsStr) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::env::split_paths(path).any(|dir| {
+        std::fs::metadata(dir.join(Tool::Beegfs.command())).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    })
+}
+
+/// Whether this machine can run `backend: beegfs-tool`; looked up once per run.
+fn local_tool() -> bool {
+    static FOUND: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *FOUND.get_or_init(|| {
+        let found = std::env::var_os("PATH").is_some_and(|p| tool_on_path(&p));
+        if !found { log::warn!("--backend beegfs-tool: no `beegfs` tool on this machine; reading the listings over ssh instead"); }
+        found
+    })
+}
+
+/// Tool per host, probed once per run.
+static TOOLS: Mutex<BTreeMap<String, Tool>> = Mutex::new(BTreeMap::new());
+
+/// The BeeGFS admin tool on one node: `beegfs-ctl`, or `beegfs` on BeeGFS 8. Each wrapper
+/// runs the listing in the syntax of whichever one the node has.
+///
+/// With `backend: beegfs-tool` the listings come from the `beegfs` tool on this machine, which
+/// asks the mgmtd over gRPC, so nothing runs on the node. A machine without the tool falls back
+/// to the ssh backend, with a warning, rather than losing the listings.
+pub struct Ctl<'a> {
+    tr: Box<dyn transport::Transport + Send + Sync>,
+    node: &'a config::Node,
+    timeout: u64,
+    /// `--mgmtd-addr` and friends for the local `beegfs`, with the address it reaches
+    grpc: Option<(String, String)>,
+}
+
+impl<'a> Ctl<'a> {
+    pub fn new(cfg: &config::Config, node: &'a config::Node, timeout: u64) -> Self {
+        if cfg.backend == config::Backend::BeegfsTool && local_tool() {
+            let local = config::Config { transport: "local".into(), use_sudo: false, sudo_user: None, sudo_askpass: None, ..cfg.clone() };
+            let addr = cfg.grpc.address(cfg, node);
+            return Ctl { tr: transport::from_config(&local), node, timeout, grpc: Some((grpc_flags(&cfg.grpc, &addr), addr)) };
+        }
+        Ctl { tr: transport::from_config(cfg), node, timeout, grpc: None }
+    }
+
+    pub fn tool(&self) -> Result<Tool> {
+        if self.grpc.is_some() { return Ok(Tool::Beegfs); }
+        if let Some(t) = TOOLS.lock().unwrap().get(&self.node.host) { return Ok(*t); }
+        let out = self.tr.exec_idempotent(&self.node.host, &wrap_timeout(TOOL_PROBE, self.timeout))?;
+        let tool = parse_tool(&out.stdout);
+        TOOLS.lock().unwrap().insert(self.node.host.clone(), tool);
+        Ok(tool)
+    }
+
+    /// Run a read-only `args`; it may be tried again when the node cannot be reached.
+    fn exec(&self, tool: Tool, args: &str) -> Result<transport::ExecOutput> {
+        self.run(tool, args, true)
+    }
+
+    fn run(&self, tool: Tool, args: &str, read_only: bool) -> Result<transport::ExecOutput> {
+        let exec = |host: &str, cmd: &str| if read_only { self.tr.exec_idempotent(host, cmd) } else { self.tr.exec(host, cmd) };
+        let Some((flags, addr)) = &self.grpc else {
+            return exec(&self.node.host, &wrap_timeout(&format!("{} {}", tool.command(), args), self.timeout));
+        };
+        let out = exec(addr, &wrap_timeout(&format!("beegfs {} {}", flags, args), self.timeout))?;
+        if out.status == Some(127) {
+            anyhow::bail!("--backend beegfs-tool needs the BeeGFS 8 `beegfs` tool on this machine: {}", out.stderr.trim());
+        }
+        Ok(out)
+    }
+
+    /// What the tool says its version is; `None` when it cannot be run or does not say.
+    pub fn version(&self) -> Option<Version> {
+        let tool = self.tool().ok()?;
+        self.exec(tool, "--version 2>&1").ok().and_then(|o| parse_version(&o.stdout))
+    }
+
+    /// Run a listing and parse it. An empty result is checked for a refused command, so that
+    /// it reads as NOPERM instead of as a cluster with nothing in it.
+    fn listing<T>(&self, tool: Tool, args: &str, parse: impl Fn(&str) -> T, empty: impl Fn(&T) -> bool) -> Result<T> {
+        let out = self.exec(tool, args)?;
+        let parsed = parse(&out.stdout);
+        if empty(&parsed) {
+            // `--listtargets` for beegfs-ctl, `target list` for beegfs
+            let words = if tool == Tool::Ctl { 1 } else { 2 };
+            let what: Vec<&str> = args.split_whitespace().take(words).collect();
+            check_permission(&out, &self.node.name, &format!("{} {}", tool.command(), what.join(" ")))?;
+        }
+        Ok(parsed)
+    }
+
+    /// Error for a listing that came back empty, naming the tool and its release since an
+    /// output format beeg does not understand looks the same.
+    pub fn nothing(&self, what: &str) -> anyhow::Error {
+        let tool = self.tool().unwrap_or(Tool::Ctl).command();
+        match self.version() {
+            Some(v) => anyhow::anyhow!("no {} reported by {} {} on {}", what, tool, v, self.node.name),
+            None => anyhow::anyhow!("no {} reported by {} on {}", what, tool, self.node.name),
+        }
+    }
+
+    fn not_translated(&self, what: &str) -> anyhow::Error {
+        match &self.grpc {
+            Some((_, addr)) => anyhow::anyhow!("--backend beegfs-tool ({}) cannot read {}; it needs beegfs-ctl over ssh", addr, what),
+            None => anyhow::anyhow!("{} only has the BeeGFS 8 `beegfs` tool, and beeg reads {} through beegfs-ctl only", self.node.name, what),
+        }
+    }
+
+    /// Reachability and consistency per target id: `--listtargets --state --longnodes`.
+    pub fn target_states(&self, nt: NodeType) -> Result<BTreeMap<String, TargetState>> {
+        match self.tool()? {
+            Tool::Ctl => self.listing(Tool::Ctl, &format!("--listtargets --nodetype={} --state --longnodes", nt.as_str()), parse_target_states, BTreeMap::is_empty),
+            Tool::Beegfs => self.listing(Tool::Beegfs, &format!("{} --node-type={}", v8::TARGET_STATES, nt.as_str()), |t| v8::parse_target_states(t, nt.as_str()), BTreeMap::is_empty),
+        }
+    }
+
+    /// Space and inodes of every storage target: `--listtargets --spaceinfo --longnodes`.
+    pub fn target_space(&self) -> Result<Vec<TargetSpace>> {
+        match self.tool()? {
+            Tool::Ctl => self.listing(Tool::Ctl, "--listtargets --nodetype=storage --spaceinfo --longnodes", parse_spaceinfo, Vec::is_empty),
+            Tool::Beegfs => self.listing(Tool::Beegfs, v8::TARGET_SPACE, v8::parse_spaceinfo, Vec::is_empty),
+        }
+    }
+
+    pub fn mirror_groups(&self, nt: NodeType) -> Result<Vec<MirrorGroup>> {
+        match self.tool()? {
+            Tool::Ctl => self.listing(Tool::Ctl, &format!("--listmirrorgroups --nodetype={}", nt.as_str()), parse_mirror_groups, Vec::is_empty),
+            Tool::Beegfs => self.listing(Tool::Beegfs, &format!("{} --node-type={}", v8::MIRROR_GROUPS, nt.as_str()), |t| v8::parse_mirror_groups(t, nt.as_str()), Vec::is_empty),
+        }
+    }
+
+    pub fn storage_pools(&self) -> Result<Vec<StoragePool>> {
+        match self.tool()? {
+            Tool::Ctl => self.listing(Tool::Ctl, "--liststoragepools", parse_storage_pools, Vec::is_empty),
+            Tool::Beegfs => self.listing(Tool::Beegfs, v8::STORAGE_POOLS, v8::parse_storage_pools, Vec::is_empty),
+        }
+    }
+
+    /// Servers of one type with reachability and, for meta, which one holds the root.
+    pub fn nodes(&self, nt: NodeType) -> Result<Vec<ListedNode>> {
+        match self.tool()? {
+            Tool::Ctl => self.listing(Tool::Ctl, &format!("--listnodes --nodetype={} --details --reachable", nt.as_str()), parse_listnodes, Vec::is_empty),
+            Tool::Beegfs => self.listing(Tool::Beegfs, &format!("{} --node-type={}", v8::NODES, nt.as_str()), |t| v8::parse_nodes(t, nt.as_str()), Vec::is_empty),
+        }
+    }
+
+    /// The resync job of one buddy group: `--resyncstats`.
+    pub fn resync_stats(&self, nt: NodeType, group: &str) -> Result<ResyncStats> {
+        if self.tool()? == Tool::Beegfs { return Err(self.not_translated("resync stats")); }
+        let out = self.exec(Tool::Ctl, &format!("--resyncstats --nodetype={} --mirrorgroupid={}", nt.as_str(), shell_escape::escape(group.into())))?;
+        match parse_resync_stats(&out.stdout) {
+            Some(s) => Ok(s),
+            None => {
+                check_permission(&out, &self.node.name, "beegfs-ctl --resyncstats")?;
+                let err = out.stderr.trim().lines().last().or_else(|| out.stdout.trim().lines().last()).unwrap_or("").to_string();
+                anyhow::bail!("beegfs-ctl --resyncstats for group {} on {} failed{}", group, self.node.name, if err.is_empty() { String::new() } else { format!(": {}", err) });
+            }
+        }
+    }
+
+    /// Set a target's state in the mgmtd: `--setstate --force`, e.g. `offline` for maintenance
+    /// and `good` to take it back.
+    pub fn set_target_state(&self, nt: NodeType, id: &str, state: &str) -> Result<()> {
+        if self.tool()? == Tool::Beegfs { anyhow::bail!("setting target states needs beegfs-ctl, which {} does not have", self.node.name); }
+        let args = format!("--setstate --nodetype={} --targetid={} --state={} --force", nt.as_str(), shell_escape::escape(id.into()), shell_escape::escape(state.into()));
+        let out = self.run(Tool::Ctl, &args, false)?;
+        if out.status == Some(0) { return Ok(()); }
+        check_permission(&out, &self.node.name, "beegfs-ctl --setstate")?;
+        let err = out.stderr.trim().lines().last().or_else(|| out.stdout.trim().lines().last()).unwrap_or("").to_string();
+        anyhow::bail!("beegfs-ctl --setstate for target {} on {} failed{}", id, self.node.name, if err.is_empty() { String::new() } else { format!(": {}", err) });
+    }
+
+    /// Where `path` lives; fails when beegfs-ctl does not know it.
+    pub fn entry_info(&self, path: &str) -> Result<EntryInfo> {
+        if self.tool()? == Tool::Beegfs { return Err(self.not_translated("entry info")); }
+        let out = self.exec(Tool::Ctl, &format!("--getentryinfo {}", shell_escape::escape(path.into())))?;
+        let e = parse_entry_info(&out.stdout);
+        if e.entry_type.is_none() {
+            check_permission(&out, &self.node.name, "beegfs-ctl --getentryinfo")?;
+            let err = out.stderr.trim();
+            anyhow::bail!("beegfs-ctl --getentryinfo {} on {} failed{}", path, self.node.name, if err.is_empty() { String::new() } else { format!(": {}", err) });
+        }
+        Ok(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version() {
+        let v = parse_version("BeeGFS Command-Line Control Tool (http://www.beegfs.com)\nVersion: 7.3.2\n").unwrap();
+        assert_eq!(v, Version { major: 7, minor: 3, patch: 2 });
+        assert_eq!(v.to_string(), "7.3.2");
+        assert_eq!(parse_version("Version: 7.4\n"), Some(Version { major: 7, minor: 4, patch: 0 }));
+        assert!(parse_version("Version: 7.2.14-el8\n").unwrap() < v);
+        assert_eq!(parse_version("beegfs-ctl: command not found\n"), None);
+        assert_eq!(parse_version("beegfs 8.0.1 (commit 3f2a9c1)\n"), Some(Version { major: 8, minor: 0, patch: 1 }));
+        assert_eq!(parse_tool("beegfs\n"), Tool::Beegfs);
+        assert_eq!(parse_tool("ctl\n"), Tool::Ctl);
+        assert_eq!(parse_tool(""), Tool::Ctl);
+
+        let grpc = config::Grpc { tls_cert_file: Some("/etc/beegfs/cert.pem".into()), ..Default::default() };
+        assert_eq!(grpc_flags(&grpc, "mgmt01:8010"), "--mgmtd-addr='mgmt01:8010' --tls-cert-file=/etc/beegfs/cert.pem");
+        let grpc = config::Grpc { auth_disable: true, ..grpc };
+        assert_eq!(grpc_flags(&grpc, "mgmt01:8010"), "--mgmtd-addr='mgmt01:8010' --tls-cert-file=/etc/beegfs/cert.pem --auth-disable");
+        let grpc = config::Grpc { auth_file: Some("/etc/beegfs/conn.auth".into()), ..grpc };
+        assert!(grpc_flags(&grpc, "mgmt01:8010").ends_with(" --auth-file=/etc/beegfs/conn.auth"));
+    }
+
+    #[test]
+    fn local_beegfs_tool() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("beeg-tool-{}", std::process::id()));
+        let (with, without) = (dir.join("with"), dir.join("without"));
+        for d in [&with, &without] { std::fs::create_dir_all(d).unwrap(); }
+        std::fs::write(with.join("beegfs"), "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(with.join("beegfs"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        // present but not executable does not count
+        std::fs::write(without.join("beegfs"), "").unwrap();
+        let path = |dirs: &[&std::path::PathBuf]| std::env::join_paths(dirs).unwrap();
+        assert!(tool_on_path(&path(&[&without, &with])));
+        assert!(!tool_on_path(&path(&[&without])));
+        assert!(!tool_on_path(std::ffi::OsStr::new("")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn header_rows() {
+        let r = rows("Pool ID   Pool Description                      Targets                 Buddy Groups
+======= ================== ============================ ============================
+      1            Default 101,102
+      2           capacity 201,202,203                  1,2
+
+Number of pools: 2
+");
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].get("pool description"), Some("Default"));
+        assert_eq!(r[0].get("Buddy Groups"), None);
+        assert_eq!(r[1].get("Buddy Groups"), Some("1,2"));
+        assert!(rows("no listing here\n").is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+//! `--listnodes --details --reachable`.
+
+use regex::Regex;
+
+/// One server as the management node lists it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListedNode {
+    pub id: String,
+    pub name: String,
+    pub reachable: Option<bool>,
+    pub heartbeat: Option<String>,
+    /// Holds the root directory (meta only)
+    pub root: bool,
+}
+
+/// Blocks of `name [ID: n]` followed by indented `Key: value` details.
+pub fn parse_listnodes(text: &str) -> Vec<ListedNode> {
+    let header = Regex::new(r"^(\S+)\s+\[ID:\s*(\d+)\]").unwrap();
+    let reach = Regex::new(r"(?i)^\s+Reachable:\s*<?(yes|no)>?").unwrap();
+    let hb = Regex::new(r"(?i)^\s+Last (?:heartbeat|contact):\s*(.+?)\s*$").unwrap();
+    let root = Regex::new(r"(?i)^\s+Root:\s*<?(yes|no)>?").unwrap();
+    let mut nodes: Vec<ListedNode> = Vec::new();
+    for line in text.lines() {
+        if let Some(c) = header.captures(line) {
+            nodes.push(ListedNode { name: c[1].to_string(), id: c[2].to_string(), ..Default::default() });
+            continue;
+        }
+        let Some(cur) = nodes.last_mut() else { continue };
+        if let Some(c) = reach.captures(line) {
+            cur.reachable = Some(c[1].eq_ignore_ascii_case("yes"));
+        } else if let Some(c) = hb.captures(line) {
+            cur.heartbeat = Some(c[1].to_string());
+        } else if let Some(c) = root.captures(line) {
+            cur.root = c[1].eq_ignore_ascii_case("yes");
+        }
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V72_LISTNODES: &str = "meta01 [ID: 1]
+   Ports: UDP: 8005; TCP: 8005
+   Interfaces: ib0(RDMA) ib0(TCP)
+   Reachable: <yes>
+   Root: yes
+meta02 [ID: 2]
+   Ports: UDP: 8005; TCP: 8005
+   Reachable: <no>
+
+Number of nodes: 2
+Root: 1
+";
+
+    const V74_LISTNODES: &str = "meta01.cluster.local [ID: 1]
+   Ports: UDP: 8005; TCP: 8005
+   Interfaces: ib0:net1(RDMA) ib0:net1(TCP) eth0(TCP)
+   Last heartbeat: 2 seconds ago
+   Reachable: <yes>
+   Root: <yes>
+
+Number of nodes: 1
+Root: 1
+";
+
+    #[test]
+    fn listnodes() {
+        let nodes = parse_listnodes(V72_LISTNODES);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].name, "meta01");
+        assert!(nodes[0].root);
+        assert_eq!(nodes[0].reachable, Some(true));
+        assert_eq!(nodes[1].reachable, Some(false));
+        assert!(!nodes[1].root);
+        let nodes = parse_listnodes(V74_LISTNODES);
+        assert_eq!((nodes[0].name.as_str(), nodes[0].id.as_str()), ("meta01.cluster.local", "1"));
+        assert_eq!(nodes[0].heartbeat.as_deref(), Some("2 seconds ago"));
+        assert!(nodes[0].root);
+    }
+}
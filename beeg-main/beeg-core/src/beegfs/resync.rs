@@ -0,0 +1,113 @@
+//! `--resyncstats`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One buddy group's resync job as the primary reports it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResyncStats {
+    /// `Not started`, `Running`, `Success`, `Interrupted`, `Failure` or `Errors`
+    pub state: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// The `# of ...` counters by their name, e.g. `synced files`
+    pub counters: BTreeMap<String, u64>,
+}
+
+impl ResyncStats {
+    fn count(&self, name: &str) -> Option<u64> {
+        self.counters.get(name).copied()
+    }
+
+    /// Synced and to-sync entries of the kinds the job reports a total for: the sync
+    /// candidates of a storage resync, the discovered directories of a meta resync.
+    pub fn progress(&self) -> (u64, u64) {
+        ["dirs", "files"].iter().fold((0, 0), |(done, total), kind| {
+            match self.count(&format!("{} sync candidates", &kind[..kind.len() - 1])).or_else(|| self.count(&format!("discovered {}", kind))) {
+                Some(t) => (done + self.count(&format!("synced {}", kind)).unwrap_or(0), total + t),
+                None => (done, total),
+            }
+        })
+    }
+
+    /// Every entry synced so far, including the kinds without a total; it only grows while
+    /// the job makes headway.
+    pub fn synced(&self) -> u64 {
+        self.counters.iter().filter(|(k, _)| k.starts_with("synced ") || k.ends_with(" synced")).map(|(_, v)| v).sum()
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.counters.iter().filter(|(k, _)| k.contains("error")).map(|(_, v)| v).sum()
+    }
+
+    pub fn running(&self) -> bool {
+        self.state.eq_ignore_ascii_case("running")
+    }
+}
+
+pub fn parse_resync_stats(text: &str) -> Option<ResyncStats> {
+    let mut s = ResyncStats::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let (key, value) = (key.trim(), value.trim());
+        if let Some(name) = key.strip_prefix("# of") {
+            if let Ok(n) = value.parse() { s.counters.insert(name.trim().to_lowercase(), n); }
+            continue;
+        }
+        match key.to_ascii_lowercase().as_str() {
+            "job state" => s.state = value.to_string(),
+            "job start time" if !value.is_empty() => s.start_time = Some(value.to_string()),
+            "job end time" if !value.is_empty() => s.end_time = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    (!s.state.is_empty()).then_some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORAGE: &str = "Job state: Running
+Job start time: Thu Oct 15 06:10:02 2026
+# of discovered dirs: 1200
+# of discovered files: 84000
+# of dir sync candidates: 1200
+# of file sync candidates: 42000
+# of synced dirs: 1200
+# of synced files: 9800
+# of dir sync errors: 0
+# of file sync errors: 3
+";
+
+    const META: &str = "Job state: Success
+Job start time: Thu Oct 15 06:10:02 2026
+Job end time: Thu Oct 15 06:12:40 2026
+# of discovered dirs: 500
+# of discovery errors: 0
+# of synced dirs: 500
+# of synced files: 7000
+# of dir sync errors: 0
+# of file sync errors: 0
+# of client sessions to sync: 4
+# of synced client sessions: 4
+session sync error: No
+# of modification objects synced: 12
+# of modification sync errors: 0
+";
+
+    #[test]
+    fn resync_stats() {
+        let s = parse_resync_stats(STORAGE).unwrap();
+        assert!(s.running());
+        assert_eq!(s.start_time.as_deref(), Some("Thu Oct 15 06:10:02 2026"));
+        assert_eq!(s.progress(), (11000, 43200));
+        assert_eq!((s.synced(), s.errors()), (11000, 3));
+
+        let m = parse_resync_stats(META).unwrap();
+        assert_eq!(m.progress(), (500, 500));
+        assert_eq!(m.synced(), 500 + 7000 + 4 + 12);
+        assert_eq!(m.errors(), 0);
+        assert!(parse_resync_stats("Communication with the server failed\n").is_none());
+    }
+}
@@ -0,0 +1,207 @@
+//! `--listtargets`, `--listmirrorgroups` and `--liststoragepools`.
+
+use super::rows;
+use std::collections::BTreeMap;
+
+/// One target as reported by `--listtargets --state --longnodes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetState {
+    pub reachability: String,
+    pub consistency: String,
+    /// `beegfs-storage stor01 [ID: 1]`, or the bare node ID without `--longnodes`
+    pub node: String,
+}
+
+/// Space and inode counts of one storage target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetSpace {
+    pub id: String,
+    pub node: String,
+    /// Capacity pool the mgmtd put the target in: normal, low or emergency
+    pub pool: Option<String>,
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+}
+
+impl TargetSpace {
+    pub fn free_pct(&self) -> f64 { pct(self.free_bytes, self.total_bytes) }
+    pub fn inodes_free_pct(&self) -> f64 { pct(self.free_inodes, self.total_inodes) }
+}
+
+fn pct(free: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { free as f64 * 100.0 / total as f64 }
+}
+
+/// One buddy mirror group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorGroup {
+    pub id: String,
+    pub primary: String,
+    pub secondary: String,
+}
+
+/// One line of `--liststoragepools`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoragePool {
+    pub id: String,
+    pub name: String,
+    pub targets: Vec<String>,
+}
+
+/// `7300.0GiB` -> bytes, `730.0M` -> count; byte units are binary, bare counts decimal.
+pub fn parse_amount(s: &str) -> Option<u64> {
+    let t = s.trim();
+    let (t, base) = match t.strip_suffix("iB") {
+        Some(t) => (t, 1024f64),
+        None => (t.strip_suffix('B').unwrap_or(t), 1000f64),
+    };
+    let (num, exp) = match t.chars().last()? {
+        'k' | 'K' => (&t[..t.len() - 1], 1),
+        'M' => (&t[..t.len() - 1], 2),
+        'G' => (&t[..t.len() - 1], 3),
+        'T' => (&t[..t.len() - 1], 4),
+        'P' => (&t[..t.len() - 1], 5),
+        _ => (t, 0),
+    };
+    let n: f64 = num.parse().ok()?;
+    Some((n * base.powi(exp)) as u64)
+}
+
+/// Target id -> state, from `--listtargets --state` with or without `--longnodes`.
+pub fn parse_target_states(text: &str) -> BTreeMap<String, TargetState> {
+    rows(text).into_iter().filter_map(|r| Some((r.get("TargetID")?.to_string(), TargetState {
+        reachability: r.get("Reachability")?.to_string(),
+        consistency: r.get("Consistency")?.to_string(),
+        node: r.get("NodeID")?.to_string(),
+    }))).collect()
+}
+
+/// `--listtargets --spaceinfo --longnodes`; the pool is `None` where the release leaves the column out.
+pub fn parse_spaceinfo(text: &str) -> Vec<TargetSpace> {
+    rows(text).into_iter().filter_map(|r| Some(TargetSpace {
+        id: r.get("TargetID")?.to_string(),
+        pool: r.get("Pool").map(String::from),
+        total_bytes: parse_amount(r.get("Total")?)?,
+        free_bytes: parse_amount(r.get("Free")?)?,
+        total_inodes: parse_amount(r.get("ITotal")?)?,
+        free_inodes: parse_amount(r.get("IFree")?)?,
+        node: r.get("NodeID")?.to_string(),
+    })).collect()
+}
+
+pub fn parse_mirror_groups(text: &str) -> Vec<MirrorGroup> {
+    rows(text).into_iter().filter_map(|r| Some(MirrorGroup {
+        id: r.get("BuddyGroupID")?.to_string(),
+        primary: r.get("PrimaryTargetID")?.to_string(),
+        secondary: r.get("SecondaryTargetID")?.to_string(),
+    })).collect()
+}
+
+pub fn parse_storage_pools(text: &str) -> Vec<StoragePool> {
+    rows(text).into_iter().filter_map(|r| Some(StoragePool {
+        id: r.get("Pool ID")?.to_string(),
+        name: r.get("Pool Description")?.to_string(),
+        targets: r.get("Targets").map(|t| t.split(',').map(String::from).collect()).unwrap_or_default(),
+    })).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 7.2 prints no pool column with `--spaceinfo`.
+    const V72_SPACEINFO: &str = "TargetID        Total         Free    %      ITotal       IFree    %   NodeID
+========        =====         ====    =      ======       =====    =   ======
+     101    7300.0GiB    6000.0GiB  82%      730.0M       20.0M   3%   beegfs-storage stor01 [ID: 1]
+     102    7300.0GiB     300.0GiB   4%      730.0M      700.0M  96%   beegfs-storage stor01 [ID: 1]
+";
+
+    const V73_SPACEINFO: &str = "TargetID     Pool        Total         Free    %      ITotal       IFree    %   NodeID
+========     ====        =====         ====    =      ======       =====    =   ======
+     201   normal    7300.0GiB    6000.0GiB  82%      730.0M      700.0M  96%   beegfs-storage stor02 [ID: 2]
+     202 emergency   7300.0GiB      30.0GiB   0%      730.0M      700.0M  96%   beegfs-storage stor02 [ID: 2]
+";
+
+    const V74_SPACEINFO: &str = "TargetID     Pool        Total         Free    %      ITotal       IFree    %   NodeID
+========     ====        =====         ====    =      ======       =====    =   ======
+     301      low      14.6TiB       1.2TiB   8%        1.5G        1.4G  93%   beegfs-storage stor03.cluster.local [ID: 3]
+";
+
+    const V72_STATES: &str = "TargetID     Reachability  Consistency   NodeID
+========     ============  ===========   ======
+     101           Online         Good   beegfs-storage stor01 [ID: 1]
+     102           Online         Good   beegfs-storage stor01 [ID: 1]
+     201           Online         Good   beegfs-storage stor02 [ID: 2]
+     202          Offline Needs-resync   beegfs-storage stor02 [ID: 2]
+";
+
+    /// Meta targets without `--longnodes`: the node column is the bare ID.
+    const V73_META_STATES: &str = "TargetID     Reachability  Consistency        NodeID
+========     ============  ===========        ======
+       1           Online         Good             1
+       2          Offline         Good             2
+";
+
+    const V74_STATES: &str = "TargetID     Reachability  Consistency   NodeID
+========     ============  ===========   ======
+     301   Probably-offline         Good   beegfs-storage stor03.cluster.local [ID: 3]
+";
+
+    const GROUPS: &str = "     BuddyGroupID     PrimaryTargetID   SecondaryTargetID
+     ============     ===============   =================
+                1                 101                 201
+                2                 102                 202
+";
+
+    #[test]
+    fn spaceinfo() {
+        let t = parse_spaceinfo(V72_SPACEINFO);
+        assert_eq!(t.len(), 2);
+        assert_eq!(t[0].free_bytes, 6000 * (1 << 30));
+        assert_eq!(t[0].free_inodes, 20_000_000);
+        assert_eq!(t[1].node, "beegfs-storage stor01 [ID: 1]");
+        assert_eq!(t[1].pool, None);
+        let t = parse_spaceinfo(V73_SPACEINFO);
+        assert_eq!(t[1].pool.as_deref(), Some("emergency"));
+        assert_eq!(t[1].free_bytes, 30 * (1 << 30));
+        let t = parse_spaceinfo(V74_SPACEINFO);
+        assert_eq!((t[0].id.as_str(), t[0].pool.as_deref()), ("301", Some("low")));
+        assert_eq!(t[0].total_inodes, 1_500_000_000);
+        assert_eq!(t[0].node, "beegfs-storage stor03.cluster.local [ID: 3]");
+        assert_eq!(parse_amount("512B"), Some(512));
+        assert_eq!(parse_amount("1.5KiB"), Some(1536));
+        assert_eq!(parse_amount("n/a"), None);
+    }
+
+    #[test]
+    fn states() {
+        let st = parse_target_states(V72_STATES);
+        assert_eq!(st.len(), 4);
+        assert_eq!(st["202"].consistency, "Needs-resync");
+        assert_eq!(st["101"].node, "beegfs-storage stor01 [ID: 1]");
+        let st = parse_target_states(V73_META_STATES);
+        assert_eq!((st["2"].reachability.as_str(), st["2"].node.as_str()), ("Offline", "2"));
+        let st = parse_target_states(V74_STATES);
+        assert_eq!(st["301"].reachability, "Probably-offline");
+        // a spaceinfo listing has no state columns
+        assert!(parse_target_states(V73_SPACEINFO).is_empty());
+    }
+
+    #[test]
+    fn groups_and_pools() {
+        let g = parse_mirror_groups(GROUPS);
+        assert_eq!(g.len(), 2);
+        assert_eq!(g[1], MirrorGroup { id: "2".into(), primary: "102".into(), secondary: "202".into() });
+        let p = parse_storage_pools("Pool ID   Pool Description                      Targets                 Buddy Groups
+======= ================== ============================ ============================
+      1            Default 101,102
+      2           capacity 201,202,203                  1,2
+      3              empty
+");
+        assert_eq!(p.len(), 3);
+        assert_eq!(p[1], StoragePool { id: "2".into(), name: "capacity".into(), targets: vec!["201".into(), "202".into(), "203".into()] });
+        assert!(p[2].targets.is_empty());
+    }
+}
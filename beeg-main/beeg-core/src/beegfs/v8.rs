@@ -0,0 +1,176 @@
+//! The `beegfs` tool of BeeGFS 8, which replaces `beegfs-ctl` and talks to the mgmtd over gRPC.
+//!
+//! Its listings are read as `--output=json --raw`: one object per row, keyed by the lower-case
+//! column name, with sizes as plain numbers. Entity IDs may come with a type prefix (`s:101`).
+
+use super::targets::parse_amount;
+use super::{ListedNode, MirrorGroup, StoragePool, TargetSpace, TargetState};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+pub const TARGET_STATES: &str = "target list --output=json --raw";
+pub const TARGET_SPACE: &str = "target list --node-type=storage --capacity --output=json --raw";
+pub const MIRROR_GROUPS: &str = "mirror list --output=json --raw";
+pub const STORAGE_POOLS: &str = "pool list --output=json --raw";
+pub const NODES: &str = "node list --output=json --raw";
+
+/// The row objects of a listing: a JSON array, an object holding one, or one object per line.
+fn records(text: &str) -> Vec<Map<String, Value>> {
+    let objects = |v: Vec<Value>| v.into_iter().filter_map(|v| match v { Value::Object(m) => Some(m), _ => None }).collect();
+    match serde_json::from_str::<Value>(text.trim()) {
+        Ok(Value::Array(rows)) => objects(rows),
+        Ok(Value::Object(m)) => match m.values().find_map(|v| v.as_array()) {
+            Some(rows) => objects(rows.clone()),
+            None => vec![m],
+        },
+        _ => text.lines().filter_map(|l| serde_json::from_str::<Map<String, Value>>(l.trim()).ok()).collect(),
+    }
+}
+
+/// The first of `keys` the row has (case-insensitive), as text.
+fn field(row: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|k| row.iter().find(|(name, _)| name.eq_ignore_ascii_case(k)).map(|(_, v)| v)).and_then(|v| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }).filter(|s| !s.is_empty())
+}
+
+/// `s:101` -> `101`; plain IDs stay as they are.
+fn id(raw: &str) -> String {
+    raw.rsplit(':').next().unwrap_or(raw).trim().to_string()
+}
+
+fn amount(row: &Map<String, Value>, keys: &[&str]) -> Option<u64> {
+    let v = field(row, keys)?;
+    v.parse().ok().or_else(|| parse_amount(&v))
+}
+
+/// Target id -> state; `node_type` narrows a listing that covers both meta and storage.
+pub fn parse_target_states(text: &str, node_type: &str) -> BTreeMap<String, TargetState> {
+    records(text).into_iter()
+        .filter(|r| field(r, &["node_type", "type"]).is_none_or(|t| t.eq_ignore_ascii_case(node_type)))
+        .filter_map(|r| Some((id(&field(&r, &["id", "target_id"])?), TargetState {
+            reachability: field(&r, &["reachability", "reachable"])?,
+            consistency: field(&r, &["consistency"])?,
+            node: field(&r, &["node", "node_alias", "node_id"])?,
+        })))
+        .collect()
+}
+
+pub fn parse_spaceinfo(text: &str) -> Vec<TargetSpace> {
+    records(text).into_iter().filter_map(|r| Some(TargetSpace {
+        id: id(&field(&r, &["id", "target_id"])?),
+        node: field(&r, &["node", "node_alias", "node_id"])?,
+        pool: field(&r, &["cap_pool", "capacity_pool"]).map(|p| p.to_lowercase()),
+        total_bytes: amount(&r, &["space", "total_space"])?,
+        free_bytes: amount(&r, &["free_space"])?,
+        total_inodes: amount(&r, &["inodes", "total_inodes"])?,
+        free_inodes: amount(&r, &["free_inodes"])?,
+    })).collect()
+}
+
+pub fn parse_mirror_groups(text: &str, node_type: &str) -> Vec<MirrorGroup> {
+    records(text).into_iter()
+        .filter(|r| field(r, &["node_type", "type"]).is_none_or(|t| t.eq_ignore_ascii_case(node_type)))
+        .filter_map(|r| Some(MirrorGroup {
+            id: id(&field(&r, &["id", "group_id"])?),
+            primary: id(&field(&r, &["primary_target", "primary"])?),
+            secondary: id(&field(&r, &["secondary_target", "secondary"])?),
+        }))
+        .collect()
+}
+
+pub fn parse_storage_pools(text: &str) -> Vec<StoragePool> {
+    records(text).into_iter().filter_map(|r| {
+        let targets = r.iter().find(|(k, _)| k.eq_ignore_ascii_case("targets")).map(|(_, v)| match v {
+            Value::Array(a) => a.iter().filter_map(|t| match t {
+                Value::String(s) => Some(id(s)),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            }).collect(),
+            Value::String(s) => s.split(',').filter(|t| !t.trim().is_empty()).map(id).collect(),
+            _ => Vec::new(),
+        }).unwrap_or_default();
+        Some(StoragePool { id: id(&field(&r, &["id", "pool_id"])?), name: field(&r, &["alias", "name", "description"])?, targets })
+    }).collect()
+}
+
+/// `yes`/`true`/`online` and their opposites; `None` for anything else.
+fn flag(v: &str) -> Option<bool> {
+    match v.to_ascii_lowercase().as_str() {
+        "yes" | "true" | "online" => Some(true),
+        "no" | "false" | "offline" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn parse_nodes(text: &str, node_type: &str) -> Vec<ListedNode> {
+    records(text).into_iter()
+        .filter(|r| field(r, &["node_type", "type"]).is_none_or(|t| t.eq_ignore_ascii_case(node_type)))
+        .filter_map(|r| Some(ListedNode {
+            id: id(&field(&r, &["id", "node_id"])?),
+            name: field(&r, &["alias", "name", "node"])?,
+            reachable: field(&r, &["reachable", "reachability"]).and_then(|v| flag(&v)),
+            heartbeat: field(&r, &["last_contact", "last_heartbeat"]),
+            root: field(&r, &["root", "meta_root"]).and_then(|v| flag(&v)).unwrap_or(false),
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGETS: &str = r#"[
+  {"uid": 1001, "alias": "target_101", "id": "s:101", "node_type": "storage", "node": "stor01", "reachability": "Online", "consistency": "Good"},
+  {"uid": 1002, "alias": "target_202", "id": "s:202", "node_type": "storage", "node": "stor02", "reachability": "Offline", "consistency": "Needs_resync"},
+  {"uid": 2001, "alias": "meta_1", "id": "m:1", "node_type": "meta", "node": "meta01", "reachability": "Online", "consistency": "Good"}
+]"#;
+
+    const CAPACITY: &str = r#"{"uid": 1001, "id": "s:101", "node": "stor01", "cap_pool": "Normal", "space": 8026531840000, "free_space": 6597069766656, "inodes": 730000000, "free_inodes": 700000000}
+{"uid": 1002, "id": "s:202", "node": "stor02", "cap_pool": "Emergency", "space": "7300.0GiB", "free_space": "30.0GiB", "inodes": 730000000, "free_inodes": 20000000}
+"#;
+
+    #[test]
+    fn targets() {
+        let st = parse_target_states(TARGETS, "storage");
+        assert_eq!(st.keys().collect::<Vec<_>>(), vec!["101", "202"]);
+        assert_eq!((st["202"].reachability.as_str(), st["202"].consistency.as_str(), st["202"].node.as_str()), ("Offline", "Needs_resync", "stor02"));
+        assert_eq!(parse_target_states(TARGETS, "meta").len(), 1);
+
+        let t = parse_spaceinfo(CAPACITY);
+        assert_eq!(t.len(), 2);
+        assert_eq!((t[0].id.as_str(), t[0].pool.as_deref(), t[0].free_bytes), ("101", Some("normal"), 6597069766656));
+        assert_eq!(t[1].free_bytes, 30 * (1 << 30));
+        assert_eq!(t[1].free_inodes, 20_000_000);
+    }
+
+    #[test]
+    fn groups_and_pools() {
+        let g = parse_mirror_groups(r#"{"rows": [
+  {"id": 1, "alias": "bg_1", "node_type": "storage", "primary_target": "s:101", "secondary_target": "s:201"},
+  {"id": 2, "alias": "bg_m1", "node_type": "meta", "primary_target": "m:1", "secondary_target": "m:2"}
+]}"#, "storage");
+        assert_eq!(g, vec![MirrorGroup { id: "1".into(), primary: "101".into(), secondary: "201".into() }]);
+        let p = parse_storage_pools(r#"[{"id": "1", "alias": "Default", "targets": ["s:101", "s:102"]}, {"id": 2, "alias": "capacity", "targets": "201,202"}, {"id": 3, "alias": "empty", "targets": []}]"#);
+        assert_eq!(p.len(), 3);
+        assert_eq!(p[0], StoragePool { id: "1".into(), name: "Default".into(), targets: vec!["101".into(), "102".into()] });
+        assert_eq!(p[1].targets, vec!["201", "202"]);
+        assert!(p[2].targets.is_empty());
+        assert!(parse_storage_pools("beegfs: error: connecting to the management service").is_empty());
+    }
+
+    #[test]
+    fn nodes() {
+        let n = parse_nodes(r#"[
+  {"uid": 11, "id": "m:1", "alias": "meta01", "node_type": "meta", "reachable": true, "last_contact": "3s", "root": true},
+  {"uid": 12, "id": "m:2", "alias": "meta02", "node_type": "meta", "reachable": false},
+  {"uid": 21, "id": "s:1", "alias": "stor01", "node_type": "storage", "reachable": true}
+]"#, "meta");
+        assert_eq!(n.len(), 2);
+        assert_eq!(n[0], ListedNode { id: "1".into(), name: "meta01".into(), reachable: Some(true), heartbeat: Some("3s".into()), root: true });
+        assert_eq!((n[1].reachable, n[1].root), (Some(false), false));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::config;
+use clap::Args;
+use std::time::Instant;
+
+use super::{client, single_node, Check, CheckReport, CheckResult, Severity, StorageTarget, CUDA, NVIDIA_DRIVER, NVIDIA_FS, OFED};
+
+#[derive(Debug, Args)]
+pub struct AllArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Mountpoint for the client-mount probes (skipped when omitted)
+    #[arg(long)]
+    pub mount: Option<String>,
+    /// Node to run the storage-target check from (skipped when omitted)
+    #[arg(long)]
+    pub storage_node: Option<String>,
+    /// Target IDs for storage-target: comma-separated or 'all'
+    #[arg(long, default_value = "all")]
+    pub targets: String,
+    /// Platform requirements for the version checks, e.g. `cuda>=12.2 driver>=535 ofed>=23.10`
+    #[arg(long, num_args = 1.., value_parser = super::compat::parse_requirement)]
+    pub require: Vec<super::Requirement>,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+pub struct All;
+
+impl Check for All {
+    const NAME: &'static str = "all";
+    const ABOUT: &'static str = "Run every node check in one pass and summarize pass/fail";
+    type Args = AllArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &AllArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+/// One summary row per check, with the check's own report as a section; skipped checks count for nothing.
+pub fn run(cfg: &config::Config, args: &AllArgs) -> CheckReport {
+    let mut report = CheckReport::new(All::NAME, &["Check"]);
+    let mut skipped = serde_json::Map::new();
+
+    for check in [&NVIDIA_DRIVER, &CUDA, &NVIDIA_FS, &OFED] {
+        let started = Instant::now();
+        let sub = super::version_report(cfg, &args.selector, check, &args.require);
+        add_section(&mut report, &args.selector, sub, started);
+    }
+    match &args.mount {
+        Some(mount) => {
+            let started = Instant::now();
+            let sub = client::mount_report(&client::collect_mount(cfg, &args.selector, mount, args.timeout));
+            add_section(&mut report, &args.selector, sub, started);
+        }
+        None => { skipped.insert("client-mount".into(), "no --mount given".into()); }
+    }
+    match &args.storage_node {
+        Some(sel) => {
+            let started = Instant::now();
+            match single_node(cfg, sel).and_then(|node| super::storage_target_report(cfg, node, &args.targets, args.timeout)) {
+                Ok(sub) => add_section(&mut report, sel, sub, started),
+                Err(e) => report.push(CheckResult {
+                    check: StorageTarget::NAME,
+                    node: sel.clone(),
+                    status: super::error_severity(&e),
+                    detail: e.to_string(),
+                    duration: started.elapsed(),
+                    cells: vec![StorageTarget::NAME.to_string()],
+                    data: serde_json::json!({}),
+                }),
+            }
+        }
+        None => { skipped.insert("storage-target".into(), "no --storage-node given".into()); }
+    }
+
+    if !skipped.is_empty() {
+        let why: Vec<String> = skipped.iter().map(|(c, w)| format!("{} ({})", c, w.as_str().unwrap_or_default())).collect();
+        report.preamble.push(format!("Skipped: {}", why.join(", ")));
+        report.data.insert("skipped".into(), skipped.into());
+    }
+    let failed: Vec<&str> = report.results.iter().filter(|r| r.status == Severity::Failure).map(|r| r.check).collect();
+    if !failed.is_empty() {
+        report.warn(format!("{} check(s) failed: {}", failed.len(), failed.join(", ")));
+    }
+    let noperm: Vec<&str> = report.results.iter().filter(|r| r.status == Severity::NoPerm).map(|r| r.check).collect();
+    if !noperm.is_empty() {
+        report.warn(format!("{} check(s) lacked permission: {}", noperm.len(), noperm.join(", ")));
+    }
+    report
+}
+
+fn add_section(report: &mut CheckReport, scope: &str, mut sub: CheckReport, started: Instant) {
+    sub.duration = started.elapsed();
+    report.results.push(CheckResult {
+        check: sub.check,
+        node: scope.to_string(),
+        status: sub.severity(),
+        detail: sub.summary(),
+        duration: sub.duration,
+        cells: vec![sub.check.to_string()],
+        data: serde_json::json!({}),
+    });
+    report.sections.push(sub);
+}
@@ -0,0 +1,163 @@
+use crate::beegfs::{self, MirrorGroup, NodeType, TargetState};
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use super::{single_node, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct BuddyGroupsArgs {
+    /// Management node to query (name/host/label); must resolve to one node
+    #[arg(long, visible_alias = "node")]
+    pub selector: String,
+    /// Which buddy groups to check
+    #[arg(long, default_value = "storage", value_parser = ["storage", "meta"])]
+    pub nodetype: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+/// One buddy group with both members resolved against the target states.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Group {
+    pub id: String,
+    pub primary: String,
+    pub secondary: String,
+    pub issues: Vec<(Severity, String)>,
+}
+
+impl Group {
+    fn severity(&self) -> Severity {
+        self.issues.iter().map(|(s, _)| *s).max().unwrap_or_default()
+    }
+}
+
+pub(crate) fn evaluate(groups: &[MirrorGroup], states: &BTreeMap<String, TargetState>) -> Vec<Group> {
+    groups.iter().map(|MirrorGroup { id, primary: p, secondary: s }| {
+        let mut issues = Vec::new();
+        let (ps, ss) = (states.get(p), states.get(s));
+        if ps.is_none() { issues.push((Severity::Failure, format!("primary target {} not listed", p))); }
+        if ss.is_none() { issues.push((Severity::Failure, format!("secondary target {} not listed", s))); }
+        if let (Some(ps), Some(ss)) = (ps, ss) {
+            if ps.node == ss.node {
+                issues.push((Severity::Failure, format!("primary and secondary on the same node ({})", ps.node)));
+            }
+        }
+        for (role, st) in [("primary", ps), ("secondary", ss)] {
+            let Some(st) = st else { continue };
+            if st.reachability.eq_ignore_ascii_case("offline") {
+                issues.push((Severity::Failure, format!("{} offline", role)));
+            } else if !st.reachability.eq_ignore_ascii_case("online") {
+                issues.push((Severity::Warning, format!("{} {}", role, st.reachability.to_lowercase())));
+            }
+            if st.consistency.eq_ignore_ascii_case("needs-resync") {
+                issues.push((Severity::Warning, format!("{} needs resync", role)));
+            } else if !st.consistency.eq_ignore_ascii_case("good") {
+                issues.push((Severity::Failure, format!("{} consistency {}", role, st.consistency.to_lowercase())));
+            }
+        }
+        Group { id: id.clone(), primary: p.clone(), secondary: s.clone(), issues }
+    }).collect()
+}
+
+pub struct BuddyGroups;
+
+impl Check for BuddyGroups {
+    const NAME: &'static str = "buddy-groups";
+    const ABOUT: &'static str = "Buddy mirror group placement, reachability and resync state (via a mgmt node)";
+    type Args = BuddyGroupsArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &BuddyGroupsArgs) -> anyhow::Result<CheckReport> {
+        run(cfg, args)
+    }
+}
+
+/// One result per group, filed under the node of its primary target.
+pub fn run(cfg: &config::Config, args: &BuddyGroupsArgs) -> anyhow::Result<CheckReport> {
+    let mgmt = single_node(cfg, &args.selector)?;
+    let nt = NodeType::parse(&args.nodetype)?;
+    let started = Instant::now();
+    let mut report = CheckReport::new(BuddyGroups::NAME, &["Group", "Primary", "Primary state", "Secondary", "Secondary node", "Secondary state"]);
+
+    let ctl = beegfs::Ctl::new(cfg, mgmt, args.timeout);
+    let groups = ctl.mirror_groups(nt)?;
+    if groups.is_empty() {
+        report.warn(format!("no {} buddy mirror groups reported by beegfs-ctl on {}", nt.as_str(), mgmt.name));
+        report.floor = Severity::Warning;
+        return Ok(report);
+    }
+    let states = ctl.target_states(nt)?;
+    let rows = evaluate(&groups, &states);
+    let duration = started.elapsed();
+
+    let node_of = |t: &str| states.get(t).map(|s| s.node.clone()).unwrap_or_else(|| "-".into());
+    let state_of = |t: &str| states.get(t).map(|s| format!("{}/{}", s.reachability, s.consistency)).unwrap_or_else(|| "-".into());
+    let target = |t: &str| serde_json::json!({
+        "target": t,
+        "node": states.get(t).map(|s| &s.node),
+        "reachability": states.get(t).map(|s| &s.reachability),
+        "consistency": states.get(t).map(|s| &s.consistency),
+    });
+    for g in &rows {
+        let issues: Vec<&str> = g.issues.iter().map(|(_, m)| m.as_str()).collect();
+        report.push(CheckResult {
+            check: BuddyGroups::NAME,
+            node: node_of(&g.primary),
+            status: g.severity(),
+            detail: issues.join("; "),
+            duration,
+            cells: vec![
+                g.id.clone(), g.primary.clone(), state_of(&g.primary),
+                g.secondary.clone(), node_of(&g.secondary), state_of(&g.secondary),
+            ],
+            data: serde_json::json!({
+                "group": g.id,
+                "primary": target(&g.primary),
+                "secondary": target(&g.secondary),
+                "issues": issues,
+            }),
+        });
+    }
+
+    let bad: Vec<&Group> = rows.iter().filter(|g| g.severity() != Severity::Ok).collect();
+    if !bad.is_empty() {
+        let ids: Vec<&str> = bad.iter().map(|g| g.id.as_str()).collect();
+        let targets: Vec<&str> = bad.iter().flat_map(|g| [g.primary.as_str(), g.secondary.as_str()]).collect();
+        report.warn_about(format!("{} buddy groups with issues: {}", nt.as_str(), ids.join(", ")), NoteKind::Target, &targets);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUPS: &str = "     BuddyGroupID     PrimaryTargetID   SecondaryTargetID
+     ============     ===============   =================
+                1                 101                 201
+                2                 102                 202
+                3                 103                 104
+";
+
+    const STATES: &str = "TargetID     Reachability  Consistency   NodeID
+========     ============  ===========   ======
+     101           Online         Good   beegfs-storage stor01 [ID: 1]
+     102           Online         Good   beegfs-storage stor01 [ID: 1]
+     103           Online         Good   beegfs-storage stor01 [ID: 1]
+     104           Online         Good   beegfs-storage stor01 [ID: 1]
+     201           Online         Good   beegfs-storage stor02 [ID: 2]
+     202          Offline Needs-resync   beegfs-storage stor02 [ID: 2]
+";
+
+    #[test]
+    fn flags() {
+        let rows = evaluate(&beegfs::parse_mirror_groups(GROUPS), &beegfs::parse_target_states(STATES));
+        assert_eq!(rows[0].severity(), Severity::Ok);
+        assert_eq!(rows[1].severity(), Severity::Failure);
+        assert!(rows[1].issues.iter().any(|(_, m)| m == "secondary needs resync"));
+        assert!(rows[2].issues.iter().any(|(_, m)| m.starts_with("primary and secondary on the same node")));
+    }
+}
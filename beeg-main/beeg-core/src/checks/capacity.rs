@@ -0,0 +1,270 @@
+use crate::notes::NoteKind;
+use crate::beegfs::{self, TargetSpace};
+use crate::{config, state, transport};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use super::{single_node, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct CapacityArgs {
+    /// Management node to query (name/host/label); must resolve to one node
+    #[arg(long, visible_alias = "node")]
+    pub selector: String,
+    /// Warn when a target has less free space than this
+    #[arg(long, default_value_t = 10)]
+    pub min_free_percent: u8,
+    /// Warn when a target has fewer free inodes than this
+    #[arg(long, default_value_t = 10)]
+    pub min_free_inodes_percent: u8,
+    /// Warn when space or inodes are estimated to run out within this many days
+    #[arg(long, default_value_t = 30)]
+    pub warn_days: u64,
+    /// Days of recorded history to estimate the fill rate from
+    #[arg(long, default_value_t = 7)]
+    pub history_days: u64,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+const HISTORY_FILE: &str = "capacity.json";
+/// Samples older than this are dropped from the history file.
+const HISTORY_KEEP_SECS: i64 = 90 * 86_400;
+/// Samples closer together than this replace each other.
+const HISTORY_MIN_GAP_SECS: i64 = 3_600;
+
+/// One recorded reading of a target.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Sample {
+    pub ts: i64,
+    pub free_bytes: u64,
+    pub free_inodes: u64,
+}
+
+/// mgmt node -> target id -> samples, oldest first.
+type History = BTreeMap<String, BTreeMap<String, Vec<Sample>>>;
+
+/// Append `s`, replacing a sample taken less than an hour earlier and dropping expired ones.
+pub(crate) fn record(samples: &mut Vec<Sample>, s: Sample) {
+    samples.retain(|o| s.ts - o.ts < HISTORY_KEEP_SECS);
+    if samples.last().is_some_and(|l| s.ts - l.ts < HISTORY_MIN_GAP_SECS) { samples.pop(); }
+    samples.push(s);
+}
+
+/// Days until `free` reaches zero at the rate seen since the oldest sample in the window.
+/// `None` when there is no usable history or the target is not filling up.
+pub(crate) fn days_to_full(samples: &[Sample], now: Sample, window_secs: i64, field: fn(&Sample) -> u64) -> Option<f64> {
+    let base = samples.iter().find(|s| now.ts - s.ts <= window_secs && now.ts - s.ts >= HISTORY_MIN_GAP_SECS)?;
+    let used = field(base) as f64 - field(&now) as f64;
+    if used <= 0.0 { return None; }
+    let per_sec = used / (now.ts - base.ts) as f64;
+    Some(field(&now) as f64 / per_sec / 86_400.0)
+}
+
+/// What a target runs out of first, and roughly when.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Exhaustion {
+    Space,
+    Inodes,
+}
+
+impl Exhaustion {
+    fn as_str(self) -> &'static str {
+        match self { Exhaustion::Space => "space", Exhaustion::Inodes => "inodes" }
+    }
+}
+
+/// The resource that will run out first: by estimate when there is one, else by the lower free percentage.
+pub(crate) fn first_out(t: &TargetSpace, space_days: Option<f64>, inode_days: Option<f64>) -> (Exhaustion, Option<f64>) {
+    match (space_days, inode_days) {
+        (Some(s), Some(i)) if i < s => (Exhaustion::Inodes, Some(i)),
+        (Some(s), _) => (Exhaustion::Space, Some(s)),
+        (None, Some(i)) => (Exhaustion::Inodes, Some(i)),
+        (None, None) if t.inodes_free_pct() < t.free_pct() => (Exhaustion::Inodes, None),
+        (None, None) => (Exhaustion::Space, None),
+    }
+}
+
+struct Row {
+    target: TargetSpace,
+    space_days: Option<f64>,
+    inode_days: Option<f64>,
+    first: (Exhaustion, Option<f64>),
+    issues: Vec<(Severity, String)>,
+}
+
+impl Row {
+    fn severity(&self) -> Severity {
+        self.issues.iter().map(|(s, _)| *s).max().unwrap_or_default()
+    }
+}
+
+fn evaluate(t: TargetSpace, history: &[Sample], now: Sample, args: &CapacityArgs) -> Row {
+    let window = args.history_days as i64 * 86_400;
+    let space_days = days_to_full(history, now, window, |s| s.free_bytes);
+    let inode_days = days_to_full(history, now, window, |s| s.free_inodes);
+    let mut issues = Vec::new();
+    if t.free_bytes == 0 {
+        issues.push((Severity::Failure, "no free space".to_string()));
+    } else if t.free_pct() < args.min_free_percent as f64 {
+        issues.push((Severity::Warning, format!("free space below {}%", args.min_free_percent)));
+    }
+    if t.free_inodes == 0 {
+        issues.push((Severity::Failure, "no free inodes".to_string()));
+    } else if t.inodes_free_pct() < args.min_free_inodes_percent as f64 {
+        issues.push((Severity::Warning, format!("free inodes below {}%", args.min_free_inodes_percent)));
+    }
+    if let Some(p @ ("low" | "emergency")) = t.pool.as_deref() {
+        issues.push((Severity::Warning, format!("in the {} capacity pool", p)));
+    }
+    let first = first_out(&t, space_days, inode_days);
+    if let (what, Some(d)) = first {
+        if d < args.warn_days as f64 {
+            issues.push((Severity::Warning, format!("{} full in about {:.0} days", what.as_str(), d)));
+        }
+    }
+    Row { target: t, space_days, inode_days, first, issues }
+}
+
+pub struct Capacity;
+
+impl Check for Capacity {
+    const NAME: &'static str = "capacity";
+    const ABOUT: &'static str = "Storage target free space and inodes, with days-to-full from history (via a mgmt node)";
+    type Args = CapacityArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &CapacityArgs) -> anyhow::Result<CheckReport> {
+        run(cfg, args)
+    }
+}
+
+/// Storage targets with space, inodes and capacity pool, as the mgmt node sees them.
+pub fn fetch_targets(cfg: &config::Config, mgmt: &config::Node, timeout: u64) -> anyhow::Result<Vec<TargetSpace>> {
+    let ctl = beegfs::Ctl::new(cfg, mgmt, timeout);
+    let mut targets = ctl.target_space()?;
+    if targets.is_empty() { return Err(ctl.nothing("storage targets")); }
+    // older beegfs-ctl leaves the pool out of --spaceinfo; beegfs-df always has it
+    if targets.iter().all(|t| t.pool.is_none()) {
+        let df = transport::from_config(cfg).exec_idempotent(&mgmt.host, &wrap_timeout("beegfs-df 2>/dev/null", timeout))
+            .map(|o| super::meta::parse_df(&o.stdout, "STORAGE TARGETS")).unwrap_or_default();
+        for t in &mut targets {
+            t.pool = df.get(&t.id).map(|(pool, _, _)| pool.clone());
+        }
+    }
+    Ok(targets)
+}
+
+pub fn run(cfg: &config::Config, args: &CapacityArgs) -> anyhow::Result<CheckReport> {
+    let mgmt = single_node(cfg, &args.selector)?;
+    let started = Instant::now();
+    let targets = fetch_targets(cfg, mgmt, args.timeout)?;
+    let duration = started.elapsed();
+    let mut report = CheckReport::new(Capacity::NAME, &["Target", "Pool", "Free", "Total", "Free %", "Inodes free", "Inodes total", "Inodes free %", "Runs out first", "Days left"]);
+
+    let ts = chrono::Utc::now().timestamp();
+    let mut history: History = state::read_json(HISTORY_FILE).unwrap_or_default();
+    let per_target = history.entry(mgmt.name.clone()).or_default();
+    let rows: Vec<Row> = targets.into_iter().map(|t| {
+        let now = Sample { ts, free_bytes: t.free_bytes, free_inodes: t.free_inodes };
+        let samples = per_target.entry(t.id.clone()).or_default();
+        let row = evaluate(t, samples, now, args);
+        record(samples, now);
+        row
+    }).collect();
+    if let Err(e) = state::write_json(HISTORY_FILE, &history) {
+        report.warn(format!("could not record capacity history: {:#}", e));
+    }
+
+    let days = |d: Option<f64>| d.map(|d| format!("{:.0}", d)).unwrap_or_else(|| "-".into());
+    for r in &rows {
+        let t = &r.target;
+        let issues: Vec<&str> = r.issues.iter().map(|(_, m)| m.as_str()).collect();
+        report.push(CheckResult {
+            check: Capacity::NAME,
+            node: t.node.clone(),
+            status: r.severity(),
+            detail: issues.join("; "),
+            duration,
+            cells: vec![
+                t.id.clone(), t.pool.clone().unwrap_or_else(|| "-".into()),
+                crate::transfer::format_size(t.free_bytes), crate::transfer::format_size(t.total_bytes), format!("{:.0}%", t.free_pct()),
+                t.free_inodes.to_string(), t.total_inodes.to_string(), format!("{:.0}%", t.inodes_free_pct()),
+                r.first.0.as_str().to_string(), days(r.first.1),
+            ],
+            data: serde_json::json!({
+                "target": t.id,
+                "pool": t.pool,
+                "free_bytes": t.free_bytes,
+                "total_bytes": t.total_bytes,
+                "free_pct": t.free_pct(),
+                "free_inodes": t.free_inodes,
+                "total_inodes": t.total_inodes,
+                "inodes_free_pct": t.inodes_free_pct(),
+                "space_days_left": r.space_days,
+                "inodes_days_left": r.inode_days,
+                "runs_out_first": r.first.0.as_str(),
+                "issues": issues,
+            }),
+        });
+    }
+
+    for pool in ["emergency", "low"] {
+        let bad: Vec<&str> = rows.iter().filter(|r| r.target.pool.as_deref() == Some(pool)).map(|r| r.target.id.as_str()).collect();
+        if !bad.is_empty() { report.warn_about(format!("storage targets in the {} capacity pool: {}", pool, bad.join(", ")), NoteKind::Target, &bad); }
+    }
+    if rows.iter().any(|r| r.target.pool.is_none()) {
+        report.warn("capacity pool unknown for some targets (neither beegfs-ctl nor beegfs-df reported it)");
+    }
+    for what in [Exhaustion::Inodes, Exhaustion::Space] {
+        let bad: Vec<&str> = rows.iter()
+            .filter(|r| r.first.0 == what && r.issues.iter().any(|(_, m)| !m.starts_with("in the ")))
+            .map(|r| r.target.id.as_str()).collect();
+        if !bad.is_empty() { report.warn_about(format!("storage targets running out of {} first: {}", what.as_str(), bad.join(", ")), NoteKind::Target, &bad); }
+    }
+    if rows.iter().all(|r| r.space_days.is_none() && r.inode_days.is_none()) {
+        report.warn("not enough capacity history for days-to-full yet; estimates appear after runs at least an hour apart");
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(free_bytes: u64, free_inodes: u64) -> TargetSpace {
+        TargetSpace { id: "101".into(), node: "s01".into(), pool: None, total_bytes: 7300, free_bytes, total_inodes: 730, free_inodes }
+    }
+
+    #[test]
+    fn days_and_first_out() {
+        let day = 86_400;
+        let s = |ts, free_bytes, free_inodes| Sample { ts, free_bytes, free_inodes };
+        let hist = vec![s(0, 1000, 1000), s(day, 900, 500)];
+        let now = s(2 * day, 800, 100);
+        // oldest sample inside the window: 200 bytes / 900 inodes used in 2 days
+        assert_eq!(days_to_full(&hist, now, 7 * day, |s| s.free_bytes), Some(8.0));
+        assert!(days_to_full(&hist, now, 7 * day, |s| s.free_inodes).unwrap() < 1.0);
+        // only the last day is in the window
+        assert_eq!(days_to_full(&hist, now, day, |s| s.free_bytes), Some(8.0));
+        assert_eq!(days_to_full(&[], now, day, |s| s.free_bytes), None);
+        assert_eq!(days_to_full(&hist, s(2 * day, 2000, 2000), 7 * day, |s| s.free_bytes), None);
+
+        let t = [target(6000, 20), target(300, 700)];
+        assert_eq!(first_out(&t[0], None, None), (Exhaustion::Inodes, None));
+        assert_eq!(first_out(&t[1], None, None), (Exhaustion::Space, None));
+        assert_eq!(first_out(&t[1], Some(40.0), Some(12.0)), (Exhaustion::Inodes, Some(12.0)));
+    }
+
+    #[test]
+    fn history_rolls() {
+        let mut h = Vec::new();
+        record(&mut h, Sample { ts: 0, free_bytes: 1, free_inodes: 1 });
+        record(&mut h, Sample { ts: 60, free_bytes: 2, free_inodes: 2 });
+        assert_eq!(h.len(), 1);
+        record(&mut h, Sample { ts: HISTORY_KEEP_SECS + 60, free_bytes: 3, free_inodes: 3 });
+        assert_eq!(h.iter().map(|s| s.free_bytes).collect::<Vec<_>>(), vec![3]);
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::{wrap_timeout, CheckReport, Severity};
+use crate::config;
+
+/// How many changes to show per node; the newest come first.
+const SHOWN: usize = 10;
+
+/// Something that changed on a node recently and may explain a failure.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// Unix time on the node's clock
+    pub at: i64,
+    /// `package`, `service`, `config` or `boot`
+    pub kind: &'static str,
+    pub what: String,
+    /// How long before the probe, e.g. `2h`
+    pub age: String,
+}
+
+/// Package installs (rpm install time, dpkg log), BeeGFS unit starts (systemd) and stops
+/// (audit log, which systemd writes SERVICE_STOP records to), /etc/beegfs edits and the
+/// last boot, all on the node's own clock.
+fn probe_script(window_secs: u64) -> String {
+    format!(r#"now=$(date +%s); since=$((now - {}))
+echo "NOW $now"
+echo "TZ $(date +%z)"
+echo "BOOT $((now - $(cut -d. -f1 /proc/uptime)))"
+if command -v rpm >/dev/null 2>&1; then rpm -qa --qf '%{{INSTALLTIME}} %{{NAME}}-%{{VERSION}}-%{{RELEASE}}\n' 2>/dev/null | awk -v s="$since" '$1 >= s {{print "RPM", $0}}'; fi
+[ -r /var/log/dpkg.log ] && grep -E '^[0-9-]+ [0-9:]+ (install|upgrade|remove) ' /var/log/dpkg.log | tail -n 200 | sed 's/^/DPKG /'
+for u in $(systemctl list-units --all --no-legend --plain 'beegfs-*' 2>/dev/null | awk '{{print $1}}'); do
+  t=$(systemctl show -p ActiveEnterTimestamp --value "$u" 2>/dev/null)
+  [ -n "$t" ] && echo "START $(date -d "$t" +%s 2>/dev/null || echo 0) $u"
+done
+[ -r /var/log/audit/audit.log ] && grep 'type=SERVICE_STOP .*unit=beegfs-' /var/log/audit/audit.log | sed -n 's/.*msg=audit(\([0-9]*\)\.[^)]*).*unit=\([^ ]*\).*/STOP \1 \2/p' | tail -n 50
+find /etc/beegfs -maxdepth 1 -type f ! -name '*.beeg-*' -newermt "@$since" -printf 'CONF %T@ %p\n' 2>/dev/null
+true"#, window_secs)
+}
+
+/// Changes in `text` (the probe's output) within `window_secs` before the probe, newest first.
+pub(crate) fn parse(text: &str, window_secs: u64) -> Vec<Change> {
+    let mut now = 0i64;
+    let mut tz = FixedOffset::east_opt(0).unwrap();
+    let mut raw: Vec<(i64, &'static str, String)> = Vec::new();
+    for line in text.lines() {
+        let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let mut fields = rest.splitn(2, ' ');
+        let (first, second) = (fields.next().unwrap_or(""), fields.next().unwrap_or("").trim());
+        match key {
+            "NOW" => now = rest.parse().unwrap_or(0),
+            "TZ" => {
+                // +0200
+                let secs = rest.get(1..3).and_then(|h| h.parse::<i32>().ok()).unwrap_or(0) * 3600
+                    + rest.get(3..5).and_then(|m| m.parse::<i32>().ok()).unwrap_or(0) * 60;
+                let offset = if rest.starts_with('-') { FixedOffset::west_opt(secs) } else { FixedOffset::east_opt(secs) };
+                if let Some(o) = offset { tz = o; }
+            }
+            "BOOT" => if let Ok(t) = rest.parse() { raw.push((t, "boot", "node booted".into())) },
+            "RPM" => if let Ok(t) = first.parse() { raw.push((t, "package", format!("{} installed", second))) },
+            "DPKG" => {
+                // 2024-05-01 10:00:00 upgrade beegfs-client:amd64 7.4.2 7.4.3
+                let f: Vec<&str> = rest.split_whitespace().collect();
+                let Some(at) = f.get(..2)
+                    .and_then(|dt| NaiveDateTime::parse_from_str(&dt.join(" "), "%Y-%m-%d %H:%M:%S").ok())
+                    .and_then(|dt| tz.from_local_datetime(&dt).single())
+                else { continue };
+                let (Some(action), Some(pkg)) = (f.get(2), f.get(3)) else { continue };
+                let pkg = pkg.split(':').next().unwrap_or(pkg);
+                let (old, new) = (f.get(4).copied().unwrap_or("<none>"), f.get(5).copied().unwrap_or("<none>"));
+                let what = match *action {
+                    "upgrade" => format!("{} upgraded {} -> {}", pkg, old, new),
+                    "install" => format!("{} {} installed", pkg, new),
+                    _ => format!("{} {} removed", pkg, old),
+                };
+                raw.push((at.timestamp(), "package", what));
+            }
+            "START" => if let Ok(t) = first.parse() { raw.push((t, "service", format!("{} started", second))) },
+            "STOP" => if let Ok(t) = first.parse() { raw.push((t, "service", format!("{} stopped", second))) },
+            "CONF" => if let Ok(t) = first.parse::<f64>() { raw.push((t as i64, "config", format!("{} modified", second))) },
+            _ => {}
+        }
+    }
+    let since = now - window_secs as i64;
+    raw.retain(|(at, _, _)| *at >= since && *at > 0);
+    raw.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+    raw.dedup();
+    raw.into_iter().map(|(at, kind, what)| Change { at, kind, what, age: crate::notes::age(at, now) }).collect()
+}
+
+/// Inventory nodes with a failed result in `report` or its sections.
+fn failed_nodes(report: &CheckReport, out: &mut Vec<String>) {
+    for s in &report.sections { failed_nodes(s, out); }
+    for r in &report.results {
+        if r.status == Severity::Failure && !out.contains(&r.node) { out.push(r.node.clone()); }
+    }
+}
+
+/// Recent changes on each node that failed in `report`, or the error that kept us from them.
+pub fn gather(cfg: &config::Config, report: &CheckReport, window_hours: u64) -> BTreeMap<String, Result<Vec<Change>, String>> {
+    let mut names = Vec::new();
+    failed_nodes(report, &mut names);
+    let nodes: Vec<&config::Node> = cfg.nodes.iter().filter(|n| names.contains(&n.name)).collect();
+    let window = window_hours * 3600;
+    let cmd = wrap_timeout(&probe_script(window), 30);
+    super::per_node(cfg, &nodes, |tr, n| tr.exec_idempotent(&n.host, &cmd).map(|o| parse(&o.stdout, window)).map_err(|e| e.to_string()))
+        .into_iter().map(|(n, res, _)| (n.name.clone(), res))
+        .collect()
+}
+
+pub fn to_json(changes: &BTreeMap<String, Result<Vec<Change>, String>>) -> serde_json::Value {
+    changes.iter().map(|(node, res)| {
+        let v = match res {
+            Ok(c) => serde_json::to_value(c).unwrap_or_default(),
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        (node.clone(), v)
+    }).collect::<serde_json::Map<_, _>>().into()
+}
+
+/// The "Recent changes" section under a failed report, in the order the report lists nodes.
+pub fn print(cfg: &config::Config, order: config::NodeOrder, changes: &BTreeMap<String, Result<Vec<Change>, String>>, window_hours: u64) {
+    let mut rows: Vec<(&String, &Result<Vec<Change>, String>)> = changes.iter().collect();
+    rows.sort_by_key(|(n, _)| cfg.nodes.iter().position(|c| &c.name == *n));
+    config::sort_rows(cfg, order, &mut rows, |(n, _)| n);
+    println!("Recent changes on failed nodes (last {}h):", window_hours);
+    for (node, res) in rows {
+        match res {
+            Ok(c) if c.is_empty() => println!("  {}: nothing recorded", node),
+            Ok(c) => {
+                for ch in c.iter().take(SHOWN) { println!("  {}: {} {} ago", node, ch.what, ch.age); }
+                if c.len() > SHOWN { println!("  {}: ... and {} earlier", node, c.len() - SHOWN); }
+            }
+            Err(e) => println!("  {}: could not read: {}", node, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_changes() {
+        let now = 1_714_557_600; // 2024-05-01 10:00:00 UTC
+        let out = format!("NOW {now}\nTZ +0200\nBOOT {}\n\
+            RPM {} beegfs-storage-7.4.3-1.el9\n\
+            DPKG 2024-05-01 10:00:00 upgrade beegfs-client:amd64 7.4.2 7.4.3\n\
+            DPKG 2024-04-01 10:00:00 install beegfs-utils:amd64 <none> 7.4.2\n\
+            START {} beegfs-storage.service\nSTART 0 beegfs-meta.service\n\
+            STOP {} beegfs-storage.service\n\
+            CONF {}.5 /etc/beegfs/beegfs-storage.conf\n",
+            now - 10 * 86_400, now - 2 * 3600, now - 1800, now - 1900, now - 86_400);
+        let c = parse(&out, 3 * 86_400);
+        let what: Vec<&str> = c.iter().map(|c| c.what.as_str()).collect();
+        assert_eq!(what, vec![
+            "beegfs-storage.service started",
+            "beegfs-storage.service stopped",
+            "beegfs-client upgraded 7.4.2 -> 7.4.3",
+            "beegfs-storage-7.4.3-1.el9 installed",
+            "/etc/beegfs/beegfs-storage.conf modified",
+        ]);
+        assert_eq!((c[0].kind, c[0].age.as_str()), ("service", "30m"));
+        // the dpkg log is in local time: 10:00 at +0200 is two hours before the probe
+        assert_eq!((c[2].at, c[2].age.as_str()), (now - 7200, "2h"));
+        assert_eq!(c[4].age, "1d");
+    }
+}
@@ -0,0 +1,570 @@
+use crate::notes::NoteKind;
+use crate::{config, transport};
+#[cfg(feature = "tui")]
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+#[cfg(feature = "tui")]
+use ratatui::{prelude::*, widgets::*};
+#[cfg(feature = "tui")]
+use std::io::IsTerminal;
+#[cfg(feature = "tui")]
+use std::sync::mpsc;
+#[cfg(feature = "tui")]
+use std::{thread, time::Instant};
+use anyhow::Context;
+use std::{fs, path::Path, time::Duration};
+use super::{wrap_timeout, rand_suffix, Check, CheckReport, CheckResult, Severity};
+
+// Uses super::ClientMountArgs from checks::mod
+
+#[cfg(feature = "tui")]
+#[derive(Clone, Debug)]
+enum Update {
+    Set { idx: usize, col: usize, val: String, output: String },
+    Done { idx: usize, duration: Duration },
+}
+
+/// What the live view shows on top of the probe results.
+#[cfg(feature = "tui")]
+#[derive(Debug, Default)]
+struct MountView {
+    /// Highlighted row among the ones the filter lets through
+    row: usize,
+    /// Case-insensitive part of a node name or host
+    filter: String,
+    /// Typing into the filter after `/`
+    editing: bool,
+    /// Probe output of the highlighted node under the table
+    detail: bool,
+}
+
+/// A key press the view cannot handle on its own.
+#[cfg(feature = "tui")]
+#[derive(Debug, PartialEq)]
+enum Action {
+    Quit,
+    /// Re-run the failed probes of the highlighted node
+    Retry,
+    /// Re-run every failed probe
+    RetryAll,
+}
+
+#[cfg(feature = "tui")]
+impl MountView {
+    /// Indexes of the rows the filter lets through, in row order.
+    fn visible(&self, rows: &[MountRow]) -> Vec<usize> {
+        let f = self.filter.to_lowercase();
+        rows.iter().enumerate().filter(|(_, r)| r.node.to_lowercase().contains(&f) || r.host.to_lowercase().contains(&f)).map(|(i, _)| i).collect()
+    }
+
+    /// Apply a key press, or hand it back when the check has to act on it.
+    fn key(&mut self, code: KeyCode, shown: usize) -> Option<Action> {
+        let last = shown.saturating_sub(1);
+        if self.editing {
+            match code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => { self.filter.pop(); }
+                KeyCode::Enter => self.editing = false,
+                KeyCode::Esc => { self.filter.clear(); self.editing = false; }
+                _ => return None,
+            }
+            self.row = 0;
+            return None;
+        }
+        match code {
+            KeyCode::Char('q') => return Some(Action::Quit),
+            KeyCode::Char('r') => return Some(Action::Retry),
+            KeyCode::Char('R') => return Some(Action::RetryAll),
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Esc if self.detail => self.detail = false,
+            KeyCode::Esc => { self.filter.clear(); self.row = 0; }
+            KeyCode::Enter => self.detail = !self.detail,
+            KeyCode::Down | KeyCode::Char('j') => self.row = (self.row + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.row = self.row.saturating_sub(1),
+            KeyCode::PageDown => self.row = (self.row + PAGE).min(last),
+            KeyCode::PageUp => self.row = self.row.saturating_sub(PAGE),
+            KeyCode::Home | KeyCode::Char('g') => self.row = 0,
+            KeyCode::End | KeyCode::Char('G') => self.row = last,
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Rows moved by PageUp/PageDown.
+#[cfg(feature = "tui")]
+const PAGE: usize = 10;
+
+pub struct ClientMount;
+
+impl Check for ClientMount {
+    const NAME: &'static str = "client-mount";
+    const ABOUT: &'static str = "Client mount checks with live TUI (--no-tui, or no terminal, prints the table)";
+    type Args = super::ClientMountArgs;
+    const RESUMABLE: bool = false;
+
+    #[cfg(feature = "tui")]
+    fn run(cli: &crate::GlobalArgs, cfg: &config::Config, args: &super::ClientMountArgs) -> anyhow::Result<CheckReport> {
+        // cron, CI and pipes get the plain report
+        let report = if args.no_tui || !std::io::stdout().is_terminal() {
+            if cli.record_session.is_some() { anyhow::bail!("--record-session records the live TUI; it needs a terminal and no --no-tui"); }
+            mount_report(&collect_mount(cfg, &args.selector, &args.mount, args.timeout))
+        } else {
+            run_mount_tui(cli, cfg, args)?
+        };
+        Ok(exported(report, args.export.as_deref()))
+    }
+
+    #[cfg(not(feature = "tui"))]
+    fn run(cli: &crate::GlobalArgs, cfg: &config::Config, args: &super::ClientMountArgs) -> anyhow::Result<CheckReport> {
+        if cli.record_session.is_some() { anyhow::bail!("--record-session needs a beeg built with the `tui` feature"); }
+        let report = mount_report(&collect_mount(cfg, &args.selector, &args.mount, args.timeout));
+        Ok(exported(report, args.export.as_deref()))
+    }
+}
+
+/// Write `report` to `--export`. A file that cannot be written only warns, so the results
+/// still reach the screen.
+fn exported(mut report: CheckReport, path: Option<&Path>) -> CheckReport {
+    let Some(path) = path else { return report };
+    if let Err(e) = fs::write(path, export_text(&report, path)).with_context(|| format!("writing --export file: {}", path.display())) {
+        report.warn(format!("{:#}", e));
+        report.floor = report.floor.max(Severity::Warning);
+    }
+    report
+}
+
+/// The table as CSV or TSV by the file's extension, else the report as `--output json` prints it.
+fn export_text(report: &CheckReport, path: &Path) -> String {
+    let sep = match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("csv") => ',',
+        Some("tsv") => '\t',
+        _ => return serde_json::to_string_pretty(&report.to_json()).unwrap_or_default() + "\n",
+    };
+    let (header, rows) = report.plain_rows();
+    std::iter::once(header).chain(rows).map(|r| crate::tables::line(&r, sep) + "\n").collect()
+}
+
+#[cfg(feature = "tui")]
+pub fn run_mount_tui(cli: &crate::GlobalArgs, cfg: &config::Config, args: &super::ClientMountArgs) -> anyhow::Result<CheckReport> {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let timeout = args.timeout;
+    let mount = args.mount.clone();
+    // Cancelled on quit, so probes still in flight stop rather than outlive the view
+    let cancel = transport::cancel::of(cfg).child();
+    let cfg = &config::Config { cancel: Some(cancel.clone()), ..cfg.clone() };
+
+    // Channel for updates from worker threads
+    let (tx, rx) = mpsc::channel::<Update>();
+
+    // One worker per node, and again per node for a retry; all are joined on quit
+    let mut workers = Vec::new();
+    let spawn = |idx: usize, cols: Vec<usize>| {
+        let tx = tx.clone();
+        let host = nodes[idx].host.clone();
+        let tr = transport::from_config(cfg);
+        let mount = mount.clone();
+        thread::spawn(move || {
+            let started = Instant::now();
+            probe_node(tr.as_ref(), &host, &mount, timeout, cols, |col, val, output| {
+                let _ = tx.send(Update::Set { idx, col, val, output });
+            });
+            let _ = tx.send(Update::Done { idx, duration: started.elapsed() });
+        })
+    };
+    for idx in 0..nodes.len() {
+        workers.push(spawn(idx, (0..PROBE_NAMES.len()).collect()));
+    }
+
+    // TUI setup
+    let mut terminal = crate::tui::enter(cli, "beeg check client-mount")?;
+
+    // Model
+    let mut rows: Vec<MountRow> = nodes.iter().map(|n| MountRow { node: n.name.clone(), host: n.host.clone(), ..Default::default() }).collect();
+    let mut outputs: Vec<[String; 5]> = vec![Default::default(); rows.len()];
+    // Nodes with probes in flight
+    let mut busy = vec![true; rows.len()];
+    let mut view = MountView::default();
+    let mut state = TableState::default();
+
+    // Event loop; stays up after the last probe so the results can be browsed
+    let tick_rate = Duration::from_millis(100);
+    let ended = loop {
+        // Apply updates
+        while let Ok(upd) = rx.try_recv() {
+            match upd {
+                Update::Set { idx, col, val, output } => {
+                    if let Some(probe) = rows.get_mut(idx).and_then(|r| r.probes.get_mut(col)) {
+                        *probe = Some(val);
+                        outputs[idx][col] = output;
+                    }
+                }
+                Update::Done { idx, duration } => {
+                    if let Some(r) = rows.get_mut(idx) { r.duration = duration; }
+                    busy[idx] = false;
+                }
+            }
+        }
+        let shown = view.visible(&rows);
+        view.row = view.row.min(shown.len().saturating_sub(1));
+        let selected = shown.get(view.row).copied();
+        let (total_done, done_count) = (rows.len(), busy.iter().filter(|b| !**b).count());
+
+        // Draw UI
+        let drawn = terminal.draw(|f| {
+            let mut constraints = vec![Constraint::Length(3), Constraint::Min(3)];
+            if view.detail { constraints.push(Constraint::Percentage(40)); }
+            constraints.extend([Constraint::Length(1), Constraint::Length(1)]);
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(constraints)
+                .split(f.size());
+
+            let filter = match (view.editing, view.filter.is_empty()) {
+                (true, _) => format!("filter: /{}_", view.filter),
+                (false, false) => format!("filter: /{}", view.filter),
+                (false, true) => "press / to filter by node".to_string(),
+            };
+            let title = Paragraph::new(filter)
+                .block(Block::default().borders(Borders::ALL).title("Client Mount"));
+            f.render_widget(title, chunks[0]);
+
+            let header = Row::new([&["Node", "Host"][..], &PROBE_NAMES[..]].concat())
+                .style(Style::default().add_modifier(Modifier::BOLD));
+            let body_rows = shown.iter().map(|&i| {
+                let r = &rows[i];
+                let mut cells = vec![r.node.clone(), r.host.clone()];
+                cells.extend(r.probes.iter().map(cell));
+                Row::new(cells)
+            });
+            let counted = if shown.len() == rows.len() { String::new() } else { format!(" ({} of {} nodes)", shown.len(), rows.len()) };
+            let table = Table::new(body_rows, [
+                    Constraint::Length(14),
+                    Constraint::Length(18),
+                    Constraint::Length(10),
+                    Constraint::Length(8),
+                    Constraint::Length(8),
+                    Constraint::Length(8),
+                    Constraint::Length(8),
+                ])
+                .header(header)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .block(Block::default().borders(Borders::ALL).title(format!("Mount {}{}", args.mount, counted)))
+                ;
+            state.select(selected.map(|_| view.row));
+            f.render_stateful_widget(table, chunks[1], &mut state);
+
+            if view.detail {
+                let text: Vec<Line> = match selected {
+                    Some(i) => PROBE_NAMES.iter().enumerate().flat_map(|(col, name)| {
+                        let head = Line::styled(format!("{}: {}", name, cell(&rows[i].probes[col])), Style::default().add_modifier(Modifier::BOLD));
+                        std::iter::once(head).chain(outputs[i][col].lines().map(|l| Line::from(l.to_string())).collect::<Vec<_>>())
+                    }).collect(),
+                    None => vec![Line::from("no node matches the filter")],
+                };
+                let title = selected.map(|i| format!("Probe output on {}", rows[i].node)).unwrap_or_default();
+                f.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }).block(Block::default().borders(Borders::ALL).title(title)), chunks[2]);
+            }
+
+            let progress = if done_count >= total_done { format!("Completed: {}/{} — all probes finished", done_count, total_done) } else { format!("Completed: {}/{}", done_count, total_done) };
+            f.render_widget(Paragraph::new(progress), chunks[chunks.len() - 2]);
+            let keys = if view.editing { "type to filter · enter keep · esc clear" } else { "↑↓/pgup/pgdn select · / filter · enter probe output · r retry failed (R: all nodes) · esc back · q quit" };
+            f.render_widget(Paragraph::new(keys).style(Style::default().add_modifier(Modifier::DIM)), chunks[chunks.len() - 1]);
+        });
+        if let Err(e) = drawn { break Err(e); }
+
+        // errors end the loop rather than return, so the terminal is always restored
+        match event::poll(tick_rate) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => break Err(e),
+        }
+        let key = match event::read() {
+            Ok(Event::Key(key)) => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        if key.kind != KeyEventKind::Press { continue; }
+        let retry: Vec<usize> = match view.key(key.code, shown.len()) {
+            Some(Action::Quit) => break Ok(()),
+            Some(Action::Retry) => selected.into_iter().collect(),
+            Some(Action::RetryAll) => (0..rows.len()).collect(),
+            None => continue,
+        };
+        // a node still probing gets its retry once it is done
+        for idx in retry {
+            let cols = rows[idx].failed_probes();
+            if busy[idx] || cols.is_empty() { continue; }
+            for &col in &cols { rows[idx].probes[col] = None; }
+            busy[idx] = true;
+            workers.push(spawn(idx, cols));
+        }
+    };
+    let (total_done, done_count) = (rows.len(), busy.iter().filter(|b| !**b).count());
+
+    cancel.cancel();
+    let left = crate::tui::leave(&mut terminal);
+    // cancelled probes end promptly; none is left running once the report is returned
+    for w in workers { let _ = w.join(); }
+    left?;
+    ended?;
+
+    let mut report = mount_report(&rows);
+    if done_count < total_done {
+        report.warn(format!("quit before all probes finished ({}/{})", done_count, total_done));
+    }
+    Ok(report)
+}
+
+/// Column headers for the five per-node probes, in probe order.
+pub const PROBE_NAMES: [&str; 5] = ["Defined", "Client", "df -h", "ls", "rw"];
+
+/// Run the mount probes `cols` (indexed like `PROBE_NAMES`) against one host, reporting each
+/// result as it lands along with what the command printed.
+pub fn probe_node(tr: &dyn transport::Transport, host: &str, mount: &str, timeout: u64, cols: impl IntoIterator<Item = usize>, mut emit: impl FnMut(usize, String, String)) {
+    for col in cols {
+        let cmd = probe_cmd(col, mount);
+        let cmd = wrap_timeout(&cmd, timeout);
+        // the rw probe writes a file, so it is not tried twice
+        let out = if col < 4 { tr.exec_idempotent(host, &cmd) } else { tr.exec(host, &cmd) };
+        let shown = transcript(&cmd, &out);
+        let val = match col {
+            // df prints nothing for a path that is not mounted
+            2 => match out {
+                Ok(o) => {
+                    if o.stdout.trim().is_empty() { "ERR".to_string() } else { "OK".to_string() }
+                }
+                Err(e) => format!("ERR:{}", e),
+            },
+            _ => pick_ok(out),
+        };
+        emit(col, val, shown);
+    }
+}
+
+fn probe_cmd(col: usize, mount: &str) -> String {
+    match col {
+        // mount defined in config
+        0 => format!(
+            "f=/etc/beegfs/beegfs-mounts.conf; if [ -e $f ] && [ ! -r $f ]; then echo NOPERM; \
+             elif grep -E '^[^#].*\\s+{}(\\s|$)' $f >/dev/null 2>&1; then echo OK; else echo MISSING; fi",
+            shell_escape::escape(mount.into())
+        ),
+        // client active
+        1 => "systemctl is-active beegfs-client >/dev/null 2>&1 && systemctl is-active beegfs-helperd >/dev/null 2>&1 && echo OK || echo MISSING".to_string(),
+        // df -h mount
+        2 => format!("df -h {} 2>&1 | tail -n +2 || true", shell_escape::escape(mount.into())),
+        // ls mount
+        3 => or_denied(&format!("ls -la {}", shell_escape::escape(mount.into()))),
+        // write+delete random file
+        _ => {
+            let file_path = format!("{}/.beeg_check_{}", mount, rand_suffix());
+            or_denied(&format!(
+                "dd if=/dev/urandom of={} bs=4K count=1 status=none && rm -f {}",
+                shell_escape::escape(file_path.clone().into()),
+                shell_escape::escape(file_path.into())
+            ))
+        }
+    }
+}
+
+/// The command, its exit code and what it printed, for the TUI's detail pane.
+fn transcript(cmd: &str, res: &anyhow::Result<transport::ExecOutput>) -> String {
+    let mut text = format!("$ {}\n", cmd);
+    match res {
+        Ok(o) => {
+            text += &match o.status {
+                Some(code) => format!("exit {}\n", code),
+                None => "killed by a signal\n".to_string(),
+            };
+            for (name, stream) in [("stdout", &o.stdout), ("stderr", &o.stderr)] {
+                if !stream.trim().is_empty() { text += &format!("{}:\n{}\n", name, stream.trim_end()); }
+            }
+        }
+        Err(e) => text += &format!("error: {:#}\n", e),
+    }
+    text
+}
+
+/// Probe results for one node, indexed like `PROBE_NAMES`; `None` until a probe reports.
+#[derive(Clone, Debug, Default)]
+pub struct MountRow {
+    pub node: String,
+    pub host: String,
+    pub probes: [Option<String>; 5],
+    pub duration: Duration,
+}
+
+impl MountRow {
+    /// Indexes of the probes that came back other than OK or NOPERM.
+    pub fn failed_probes(&self) -> Vec<usize> {
+        self.probes.iter().enumerate().filter(|(_, v)| matches!(v, Some(v) if v != "OK" && v != "NOPERM")).map(|(i, _)| i).collect()
+    }
+
+    /// Names of the probes that came back other than OK or NOPERM.
+    pub fn failed(&self) -> Vec<&'static str> {
+        self.failed_probes().into_iter().map(|i| PROBE_NAMES[i]).collect()
+    }
+
+    /// Names of the probes the SSH user was not allowed to run.
+    pub fn denied(&self) -> Vec<&'static str> {
+        PROBE_NAMES.iter().zip(self.probes.iter()).filter(|(_, v)| matches!(v, Some(v) if v == "NOPERM")).map(|(n, _)| *n).collect()
+    }
+
+    /// Names of the probes that never reported.
+    pub fn pending(&self) -> Vec<&'static str> {
+        PROBE_NAMES.iter().zip(self.probes.iter()).filter(|(_, v)| v.is_none()).map(|(n, _)| *n).collect()
+    }
+}
+
+/// Run the mount probes on all selected nodes in parallel without a TUI.
+pub fn collect_mount(cfg: &config::Config, selector: &str, mount: &str, timeout: u64) -> Vec<MountRow> {
+    let nodes = config::select_nodes(cfg, selector);
+    super::per_node(cfg, &nodes, |tr, n| {
+        let mut probes: [Option<String>; 5] = Default::default();
+        probe_node(tr, &n.host, mount, timeout, 0..PROBE_NAMES.len(), |col, val, _| probes[col] = Some(val));
+        Ok(probes)
+    }).into_iter().map(|(n, probes, duration)| {
+        let probes = probes.unwrap_or_else(|_: String| std::array::from_fn(|_| Some("ERR".to_string())));
+        MountRow { node: n.name.clone(), host: n.host.clone(), probes, duration }
+    }).collect()
+}
+
+/// Any failed probe fails a node; probes cut short by quitting only warn.
+pub fn mount_report(rows: &[MountRow]) -> CheckReport {
+    let mut columns = vec!["Host"];
+    columns.extend(PROBE_NAMES);
+    let mut report = CheckReport::new(ClientMount::NAME, &columns);
+    for r in rows {
+        let (failed, denied, pending) = (r.failed(), r.denied(), r.pending());
+        let (status, detail) = if !failed.is_empty() {
+            (Severity::Failure, format!("failed: {}", failed.join(", ")))
+        } else if !denied.is_empty() {
+            (Severity::NoPerm, format!("permission denied: {}", denied.join(", ")))
+        } else if !pending.is_empty() {
+            (Severity::Warning, format!("not finished: {}", pending.join(", ")))
+        } else {
+            (Severity::Ok, String::new())
+        };
+        let mut cells = vec![r.host.clone()];
+        cells.extend(r.probes.iter().map(cell));
+        let probe = |i: usize| r.probes[i].clone();
+        report.push(CheckResult {
+            check: ClientMount::NAME,
+            node: r.node.clone(),
+            status,
+            detail,
+            duration: r.duration,
+            cells,
+            data: serde_json::json!({
+                "host": r.host,
+                "defined": probe(0),
+                "client": probe(1),
+                "df": probe(2),
+                "ls": probe(3),
+                "rw": probe(4),
+            }),
+        });
+    }
+    let failed: Vec<&str> = rows.iter().filter(|r| !r.failed().is_empty()).map(|r| r.node.as_str()).collect();
+    if !failed.is_empty() {
+        report.warn_about(format!("client mount probes failed on {} node(s): {}", failed.len(), failed.join(", ")), NoteKind::Node, &failed);
+    }
+    report
+}
+
+fn cell(v: &Option<String>) -> String {
+    match v {
+        Some(s) => s.clone(),
+        None => "...".to_string(),
+    }
+}
+
+/// `cmd` printing OK, NOPERM when it was refused for lack of permission, or ERR; on failure
+/// its error output goes to stderr.
+fn or_denied(cmd: &str) -> String {
+    format!(
+        "err=$({{ {}; }} 2>&1 >/dev/null) && echo OK || {{ printf '%s\\n' \"$err\" >&2; case \"$err\" in *'Permission denied'*|*'Operation not permitted'*) echo NOPERM;; *) echo ERR;; esac; }}",
+        cmd
+    )
+}
+
+fn pick_ok(res: anyhow::Result<transport::ExecOutput>) -> String {
+    match res {
+        Ok(o) => {
+            let s = o.stdout.trim();
+            if s.starts_with("OK") { "OK".into() } else if s.starts_with("NOPERM") { "NOPERM".into() } else { "ERR".into() }
+        }
+        Err(_) => "ERR".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_shows_streams() {
+        let out = Ok(transport::ExecOutput { stdout: "ERR\n".into(), stderr: "ls: cannot access '/mnt/beegfs': Transport endpoint is not connected\n".into(), status: Some(0) });
+        assert_eq!(transcript("ls", &out), "$ ls\nexit 0\nstdout:\nERR\nstderr:\nls: cannot access '/mnt/beegfs': Transport endpoint is not connected\n");
+        let quiet = Ok(transport::ExecOutput { stdout: String::new(), stderr: String::new(), status: None });
+        assert_eq!(transcript("df", &quiet), "$ df\nkilled by a signal\n");
+    }
+
+    #[test]
+    fn export_formats() {
+        let row = |node: &str, last: &str| MountRow { node: node.into(), host: format!("{}.cluster", node), probes: ["OK", "OK", "OK", "OK", last].map(|v| Some(v.to_string())), ..Default::default() };
+        let report = mount_report(&[row("cli01", "OK"), row("cli02", "ERR")]);
+        let csv = export_text(&report, Path::new("out/results.CSV"));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Node,Host,Defined,Client,df -h,ls,rw"), "{}", lines[0]);
+        assert!(lines[2].starts_with("cli02,cli02.cluster,OK,OK,OK,OK,ERR,FAIL,"), "{}", lines[2]);
+        assert!(export_text(&report, Path::new("results.tsv")).lines().nth(1).unwrap().starts_with("cli01\tcli01.cluster\tOK"));
+        let v: serde_json::Value = serde_json::from_str(&export_text(&report, Path::new("results.json"))).unwrap();
+        assert_eq!((v["status"].as_str(), v["results"][1]["rw"].as_str()), (Some("FAIL"), Some("ERR")));
+
+        let kept = exported(report, Some(Path::new("/nonexistent/dir/results.json")));
+        assert_eq!(kept.results.len(), 2);
+        assert!(kept.warnings.last().unwrap().message.starts_with("writing --export file: /nonexistent/dir/results.json"));
+        assert_eq!(kept.severity(), Severity::Failure);
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn view_filters_and_scrolls() {
+        let rows: Vec<MountRow> = (1..=30).map(|i| MountRow { node: format!("cli{:02}", i), host: format!("10.0.0.{}", i), ..Default::default() }).collect();
+        let mut v = MountView::default();
+        assert_eq!(v.visible(&rows).len(), 30);
+        v.key(KeyCode::PageDown, 30);
+        v.key(KeyCode::PageDown, 30);
+        v.key(KeyCode::Char('j'), 30);
+        assert_eq!(v.row, 21);
+        v.key(KeyCode::End, 30);
+        assert_eq!(v.row, 29);
+
+        v.key(KeyCode::Char('/'), 30);
+        for c in "CLI2".chars() { v.key(KeyCode::Char(c), 30); }
+        assert_eq!(v.key(KeyCode::Char('q'), 30), None, "q while filtering is typed, not quit");
+        v.key(KeyCode::Backspace, 30);
+        v.key(KeyCode::Enter, 30);
+        assert_eq!((v.filter.as_str(), v.editing, v.row), ("CLI2", false, 0));
+        assert_eq!(v.visible(&rows), (19..=28).collect::<Vec<_>>());
+
+        v.key(KeyCode::Enter, 10);
+        assert!(v.detail);
+        v.key(KeyCode::Esc, 10);
+        assert!(!v.detail && v.filter == "CLI2");
+        v.key(KeyCode::Esc, 10);
+        assert!(v.filter.is_empty());
+        assert_eq!(v.key(KeyCode::Char('r'), 30), Some(Action::Retry));
+        assert_eq!(v.key(KeyCode::Char('R'), 30), Some(Action::RetryAll));
+        assert_eq!(v.key(KeyCode::Char('q'), 30), Some(Action::Quit));
+    }
+
+    #[test]
+    fn failed_probes() {
+        let r = MountRow { probes: [Some("OK".into()), Some("MISSING".into()), Some("ERR:timed out".into()), Some("NOPERM".into()), None], ..Default::default() };
+        assert_eq!(r.failed_probes(), vec![1, 2]);
+        assert_eq!(r.failed(), vec!["Client", "df -h"]);
+    }
+}
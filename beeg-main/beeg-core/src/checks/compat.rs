@@ -0,0 +1,109 @@
+use regex::Regex;
+use std::cmp::Ordering;
+
+use super::{VersionCheck, CUDA, NVIDIA_DRIVER, NVIDIA_FS, OFED};
+
+/// Comparison in a platform requirement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self { Op::Eq => "=", Op::Ne => "!=", Op::Lt => "<", Op::Le => "<=", Op::Gt => ">", Op::Ge => ">=" }
+    }
+}
+
+/// One entry of the compatibility matrix, e.g. `cuda>=12.2` or `driver>=535`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    /// `json_key` of the version check it applies to: driver, cuda, nvidia_fs or ofed
+    pub component: &'static str,
+    pub op: Op,
+    pub version: Vec<u64>,
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let v: Vec<String> = self.version.iter().map(u64::to_string).collect();
+        write!(f, "{}{}{}", self.component, self.op.as_str(), v.join("."))
+    }
+}
+
+const COMPONENTS: [&VersionCheck; 4] = [&NVIDIA_DRIVER, &CUDA, &NVIDIA_FS, &OFED];
+
+/// clap value parser for `--require`; the component is the check name or its JSON key.
+pub fn parse_requirement(s: &str) -> Result<Requirement, String> {
+    let at = s.find(['<', '>', '=', '!']).ok_or_else(|| format!("'{}' has no comparison; use e.g. cuda>=12.2", s))?;
+    let (name, rest) = (s[..at].trim(), &s[at..]);
+    let component = COMPONENTS.iter().find(|c| c.name == name || c.json_key == name).map(|c| c.json_key)
+        .ok_or_else(|| format!("unknown component '{}'; expected one of driver, cuda, nvidia-fs, ofed", name))?;
+    let (op, v) = [(">=", Op::Ge), ("<=", Op::Le), ("==", Op::Eq), ("!=", Op::Ne), (">", Op::Gt), ("<", Op::Lt), ("=", Op::Eq)]
+        .into_iter()
+        .find_map(|(text, op)| rest.strip_prefix(text).map(|v| (op, v.trim())))
+        .ok_or_else(|| format!("bad comparison in '{}'", s))?;
+    let version = v.split('.').map(|p| p.parse::<u64>()).collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("bad version '{}' in '{}'", v, s))?;
+    Ok(Requirement { component, op, version })
+}
+
+/// The numeric part of a reported version: `535.129.03`, `12.2`, or `23.10` out of
+/// `MLNX_OFED_LINUX-23.10-0.5.5.0`.
+pub fn numeric(version: &str) -> Option<Vec<u64>> {
+    let re = Regex::new(r"\d+(?:\.\d+)*").unwrap();
+    let m = re.find(version)?;
+    m.as_str().split('.').map(|p| p.parse().ok()).collect()
+}
+
+impl Requirement {
+    /// Compares only as many components as the requirement gives, so `cuda=12.2` holds for
+    /// 12.2.1 and `driver>=535` for 535.129.03. `None` when the version has no number in it.
+    pub fn holds(&self, version: &str) -> Option<bool> {
+        let have = numeric(version)?;
+        let have: Vec<u64> = (0..self.version.len()).map(|i| have.get(i).copied().unwrap_or(0)).collect();
+        let ord = have.cmp(&self.version);
+        Some(match self.op {
+            Op::Eq => ord == Ordering::Equal,
+            Op::Ne => ord != Ordering::Equal,
+            Op::Lt => ord == Ordering::Less,
+            Op::Le => ord != Ordering::Greater,
+            Op::Gt => ord == Ordering::Greater,
+            Op::Ge => ord != Ordering::Less,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements() {
+        let r = parse_requirement("cuda>=12.2").unwrap();
+        assert_eq!((r.component, r.op, r.version.clone()), ("cuda", Op::Ge, vec![12, 2]));
+        assert_eq!(r.holds("12.4"), Some(true));
+        assert_eq!(r.holds("12.1"), Some(false));
+        assert_eq!(r.holds("unknown"), None);
+
+        let d = parse_requirement("nvidia-driver>=535").unwrap();
+        assert_eq!(d.to_string(), "driver>=535");
+        assert_eq!(d.holds("535.129.03"), Some(true));
+        assert_eq!(d.holds("530.30.02"), Some(false));
+
+        let o = parse_requirement("ofed>=23.10").unwrap();
+        assert_eq!(o.holds("MLNX_OFED_LINUX-23.10-0.5.5.0"), Some(true));
+        assert_eq!(o.holds("MLNX_OFED_LINUX-5.8-3.0.7.0"), Some(false));
+        assert_eq!(parse_requirement("cuda=12.2").unwrap().holds("12.2.1"), Some(true));
+        assert_eq!(parse_requirement("cuda<12").unwrap().holds("12.0"), Some(false));
+
+        assert!(parse_requirement("cuda 12.2").is_err());
+        assert!(parse_requirement("gpu>=1").is_err());
+        assert!(parse_requirement("cuda>=12.x").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct ConnConfigArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// Settings that decide which interfaces and networks BeeGFS connects over.
+const KEYS: &[&str] = &[
+    "connInterfacesFile", "connInterfacesList", "connRDMAInterfacesFile",
+    "connNetFilterFile", "connTcpOnlyFilterFile", "connUseRDMA",
+];
+
+/// Settings naming a file; its entries follow as `FILE <key> <entry>`, or `MISSING <key> <path>`
+/// / `NOPERM <path>` when it cannot be read.
+const FILE_KEYS: &[&str] = &["connInterfacesFile", "connRDMAInterfacesFile", "connNetFilterFile", "connTcpOnlyFilterFile"];
+
+/// Per config `CONF <file>` and `SET <key> <value>`, then each interface as
+/// `NIC <name> <operstate> <rdma|->` and each address as `ADDR <name> <ip>`.
+fn probe() -> String {
+    format!(r#"for f in /etc/beegfs/beegfs-*.conf; do
+  if [ -e "$f" ] && [ ! -r "$f" ]; then echo "NOPERM $f"; continue; fi
+  [ -r "$f" ] || continue
+  echo "CONF ${{f##*/}}"
+  for k in {keys}; do
+    v=$(sed -n "s/^[[:space:]]*$k[[:space:]]*=[[:space:]]*\([^#]*\).*/\1/p" "$f" | tail -n1 | sed 's/[[:space:]]*$//')
+    [ -n "$v" ] && echo "SET $k $v"
+  done
+  for k in {files}; do
+    p=$(sed -n "s/^[[:space:]]*$k[[:space:]]*=[[:space:]]*\([^[:space:]#]*\).*/\1/p" "$f" | tail -n1)
+    [ -n "$p" ] || continue
+    if [ -r "$p" ]; then sed 's/#.*//' "$p" | awk -v k="$k" 'NF {{ print "FILE", k, $1 }}'
+    elif [ -e "$p" ]; then echo "NOPERM $p"
+    else echo "MISSING $k $p"; fi
+  done
+done
+for d in /sys/class/net/*; do
+  r=-; [ -d "$d/device/infiniband" ] && r=rdma
+  echo "NIC ${{d##*/}} $(cat "$d/operstate" 2>/dev/null) $r"
+done
+ip -o addr show 2>/dev/null | awk '{{ split($4, a, "/"); print "ADDR", $2, a[1] }}'"#, keys = KEYS.join(" "), files = FILE_KEYS.join(" "))
+}
+
+/// The connection settings of one BeeGFS config and the entries of the files they name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ConnConf {
+    pub file: String,
+    pub settings: BTreeMap<String, String>,
+    /// `key -> entries` of the files named by `FILE_KEYS`
+    pub files: BTreeMap<String, Vec<String>>,
+    /// `(key, path)` of named files that do not exist
+    pub missing: Vec<(String, String)>,
+}
+
+impl ConnConf {
+    /// Interfaces BeeGFS is told to use: connInterfacesFile, else connInterfacesList.
+    fn interfaces(&self) -> Vec<String> {
+        match self.files.get("connInterfacesFile") {
+            Some(f) => f.clone(),
+            None => self.settings.get("connInterfacesList")
+                .map(|l| l.split(',').map(str::trim).filter(|i| !i.is_empty()).map(String::from).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A network interface of the node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Nic {
+    pub state: String,
+    pub rdma: bool,
+    pub addrs: Vec<IpAddr>,
+}
+
+/// What the probe found on one node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Probe {
+    pub confs: Vec<ConnConf>,
+    pub nics: BTreeMap<String, Nic>,
+    pub denied: Vec<String>,
+}
+
+pub(crate) fn parse_probe(text: &str) -> Probe {
+    let mut p = Probe::default();
+    for line in text.lines() {
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let (key, value) = rest.split_once(' ').map(|(k, v)| (k, v.trim())).unwrap_or((rest.trim(), ""));
+        match (tag, p.confs.last_mut()) {
+            ("CONF", _) => p.confs.push(ConnConf { file: rest.trim().to_string(), ..Default::default() }),
+            ("SET", Some(c)) => { c.settings.insert(key.to_string(), value.to_string()); }
+            ("FILE", Some(c)) => c.files.entry(key.to_string()).or_default().push(value.to_string()),
+            ("MISSING", Some(c)) => c.missing.push((key.to_string(), value.to_string())),
+            ("NOPERM", _) => p.denied.push(rest.trim().to_string()),
+            ("NIC", _) => {
+                let f: Vec<&str> = rest.split_whitespace().collect();
+                if let [name, state, rdma] = f[..] {
+                    let nic = p.nics.entry(name.to_string()).or_default();
+                    (nic.state, nic.rdma) = (state.to_string(), rdma == "rdma");
+                }
+            }
+            ("ADDR", _) => {
+                if let Ok(ip) = value.parse() { p.nics.entry(key.to_string()).or_default().addrs.push(ip); }
+            }
+            _ => {}
+        }
+    }
+    p
+}
+
+/// A connNetFilterFile/connTcpOnlyFilterFile entry: `10.0.0.0/16`, or a bare address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Net {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl Net {
+    pub fn parse(entry: &str) -> Option<Net> {
+        let (addr, prefix) = entry.split_once('/').unwrap_or((entry, ""));
+        let addr: IpAddr = addr.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = if prefix.is_empty() { max } else { prefix.parse().ok().filter(|p| *p <= max)? };
+        Some(Net { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let bits = |a: &IpAddr| match a {
+            IpAddr::V4(a) => (u32::from(*a) as u128) << 96,
+            IpAddr::V6(a) => u128::from(*a),
+        };
+        if self.addr.is_ipv4() != ip.is_ipv4() { return false; }
+        // IPv4 sits in the top bits, so the prefix counts from the top either way
+        let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+        bits(&self.addr) & mask == bits(ip) & mask
+    }
+}
+
+/// What is wrong with one config's connection settings on a node with these interfaces.
+pub(crate) fn evaluate(c: &ConnConf, nics: &BTreeMap<String, Nic>) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    let have = || nics.keys().filter(|n| *n != "lo").cloned().collect::<Vec<_>>().join(", ");
+    for (key, path) in &c.missing {
+        issues.push((Severity::Failure, format!("{} {} does not exist", key, path)));
+    }
+    let source = if c.files.contains_key("connInterfacesFile") { "connInterfacesFile" } else { "connInterfacesList" };
+    let interfaces = c.interfaces();
+    let mut seen = Vec::new();
+    for i in &interfaces {
+        if seen.contains(&i) { issues.push((Severity::Warning, format!("{} is listed twice in {}", i, source))); continue; }
+        seen.push(i);
+        match nics.get(i) {
+            None => issues.push((Severity::Failure, format!("{} in {} does not exist (have {})", i, source, have()))),
+            Some(n) if n.state == "down" => issues.push((Severity::Warning, format!("{} in {} is down", i, source))),
+            Some(n) if n.addrs.is_empty() => issues.push((Severity::Warning, format!("{} in {} has no address", i, source))),
+            _ => {}
+        }
+    }
+    for i in c.files.get("connRDMAInterfacesFile").into_iter().flatten() {
+        match nics.get(i) {
+            None => issues.push((Severity::Failure, format!("{} in connRDMAInterfacesFile does not exist (have {})", i, have()))),
+            Some(n) if !n.rdma => issues.push((Severity::Failure, format!("{} in connRDMAInterfacesFile is not an RDMA device", i))),
+            _ => {}
+        }
+    }
+    let rdma_on = c.settings.get("connUseRDMA").is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    if rdma_on && !nics.values().any(|n| n.rdma) {
+        issues.push((Severity::Warning, "connUseRDMA is true but the node has no RDMA device".into()));
+    } else if rdma_on && !interfaces.is_empty() && !interfaces.iter().any(|i| nics.get(i).is_some_and(|n| n.rdma)) {
+        issues.push((Severity::Warning, format!("connUseRDMA is true but none of {} is an RDMA device", interfaces.join(", "))));
+    }
+    for key in ["connNetFilterFile", "connTcpOnlyFilterFile"] {
+        let Some(entries) = c.files.get(key) else { continue };
+        let (nets, bad): (Vec<_>, Vec<_>) = entries.iter().map(|e| (e, Net::parse(e))).partition(|(_, n)| n.is_some());
+        for (e, _) in &bad { issues.push((Severity::Failure, format!("{} entry '{}' is not an address or network", key, e))); }
+        if key != "connNetFilterFile" || nets.is_empty() { continue; }
+        let nets: Vec<Net> = nets.into_iter().filter_map(|(_, n)| n).collect();
+        // peers that share the filter only connect to addresses inside it
+        let used: Vec<(&String, &Nic)> = nics.iter()
+            .filter(|(name, _)| *name != "lo" && (interfaces.is_empty() || interfaces.contains(name)))
+            .collect();
+        let inside = |n: &Nic| n.addrs.iter().any(|a| nets.iter().any(|net| net.contains(a)));
+        if !used.iter().any(|(_, n)| inside(n)) {
+            issues.push((Severity::Failure, "no address of the interfaces BeeGFS uses is inside connNetFilterFile".into()));
+        } else {
+            for (name, n) in used.iter().filter(|(name, n)| interfaces.contains(name) && !n.addrs.is_empty() && !inside(n)) {
+                let addrs: Vec<String> = n.addrs.iter().map(|a| a.to_string()).collect();
+                issues.push((Severity::Warning, format!("{} ({}) is outside connNetFilterFile", name, addrs.join(", "))));
+            }
+        }
+    }
+    issues
+}
+
+pub struct ConnConfig;
+
+impl Check for ConnConfig {
+    const NAME: &'static str = "conn-config";
+    const ABOUT: &'static str = "connInterfacesFile, connNetFilterFile and RDMA settings against the node's actual interfaces";
+    type Args = ConnConfigArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &ConnConfigArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &ConnConfigArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let script = probe();
+    let mut report = CheckReport::new(ConnConfig::NAME, &["Config", "Interfaces", "Net filter", "RDMA"]);
+    let mut broken = Vec::new();
+    super::per_node_each(cfg, &nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(&script, args.timeout))
+            .map(|out| parse_probe(&out.stdout))
+            .map_err(|e| super::exec_error(&e))
+    }, |_, n, res, took| {
+        let row = |status, detail: String, cells: Vec<String>, data| CheckResult {
+            check: ConnConfig::NAME, node: n.name.clone(), status, detail, duration: took, cells, data,
+        };
+        let p = match res {
+            Ok(p) => p,
+            Err(e) => {
+                report.push(row(Severity::Failure, e, vec!["-".to_string(); 4], serde_json::json!({})));
+                return;
+            }
+        };
+        if !p.denied.is_empty() {
+            report.push(row(Severity::NoPerm, format!("cannot read {}: permission denied", p.denied.join(", ")), vec!["-".to_string(); 4], serde_json::json!({ "denied": p.denied })));
+        }
+        let relevant: Vec<&ConnConf> = p.confs.iter().filter(|c| !c.settings.is_empty()).collect();
+        if relevant.is_empty() {
+            if p.denied.is_empty() {
+                report.push(row(Severity::Ok, "no connection settings: BeeGFS uses every interface".into(), vec!["-".to_string(); 4], serde_json::json!({ "configs": [] })));
+            }
+            return;
+        }
+        for c in relevant {
+            let issues = evaluate(c, &p.nics);
+            if issues.iter().any(|(s, _)| *s == Severity::Failure) { broken.push(n.name.clone()); }
+            let interfaces = c.interfaces();
+            let filter = c.files.get("connNetFilterFile").map(|f| f.join(", "))
+                .or_else(|| c.settings.get("connNetFilterFile").map(|_| "unreadable".into()));
+            let rdma = match (c.settings.get("connUseRDMA"), c.files.get("connRDMAInterfacesFile")) {
+                (_, Some(f)) => f.join(", "),
+                (Some(v), None) => v.clone(),
+                (None, None) => "-".to_string(),
+            };
+            let data = serde_json::json!({
+                "config": c.file,
+                "settings": c.settings,
+                "files": c.files,
+                "interfaces": interfaces,
+                "issues": issues.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+            });
+            report.push(row(
+                issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+                issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "),
+                vec![
+                    c.file.clone(),
+                    if interfaces.is_empty() { "any".to_string() } else { interfaces.join(", ") },
+                    filter.unwrap_or_else(|| "-".to_string()),
+                    rdma,
+                ],
+                data,
+            ));
+        }
+    });
+    report.in_node_order(&nodes);
+    broken.sort_by_key(|b| nodes.iter().position(|n| &n.name == b));
+    broken.dedup();
+    if !broken.is_empty() {
+        report.warn_about(format!("connection settings naming missing interfaces, files or networks on {}", broken.join(", ")), NoteKind::Node, &broken);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE_OUT: &str = "CONF beegfs-client.conf
+SET connInterfacesFile /etc/beegfs/interfaces
+SET connNetFilterFile /etc/beegfs/netfilter
+SET connRDMAInterfacesFile /etc/beegfs/rdma
+SET connUseRDMA true
+FILE connInterfacesFile ib0
+FILE connInterfacesFile ib1
+FILE connNetFilterFile 10.1.0.0/16
+FILE connNetFilterFile 10.300.0.0/16
+FILE connRDMAInterfacesFile ib0
+FILE connRDMAInterfacesFile eth0
+CONF beegfs-helperd.conf
+CONF beegfs-storage.conf
+SET connNetFilterFile /etc/beegfs/storage-netfilter
+MISSING connNetFilterFile /etc/beegfs/storage-netfilter
+NIC eth0 up -
+NIC ib0 up rdma
+NIC lo unknown -
+ADDR lo 127.0.0.1
+ADDR eth0 10.0.0.5
+ADDR ib0 10.2.0.5
+";
+
+    #[test]
+    fn interfaces_and_filters() {
+        let p = parse_probe(PROBE_OUT);
+        assert_eq!(p.confs.len(), 3);
+        assert_eq!(p.confs[0].interfaces(), vec!["ib0", "ib1"]);
+        assert!(p.nics["ib0"].rdma && !p.nics["eth0"].rdma);
+        assert_eq!(p.confs[2].missing, vec![("connNetFilterFile".to_string(), "/etc/beegfs/storage-netfilter".to_string())]);
+
+        let issues: Vec<String> = evaluate(&p.confs[0], &p.nics).into_iter().map(|(_, m)| m).collect();
+        assert_eq!(issues, vec![
+            "ib1 in connInterfacesFile does not exist (have eth0, ib0)",
+            "eth0 in connRDMAInterfacesFile is not an RDMA device",
+            "connNetFilterFile entry '10.300.0.0/16' is not an address or network",
+            "no address of the interfaces BeeGFS uses is inside connNetFilterFile",
+        ]);
+        let list = ConnConf { settings: [("connInterfacesList".to_string(), "ib0, eth0".to_string())].into(), ..Default::default() };
+        assert!(evaluate(&list, &p.nics).is_empty());
+
+        let net = Net::parse("10.1.0.0/16").unwrap();
+        assert!(net.contains(&"10.1.200.3".parse().unwrap()) && !net.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(Net::parse("10.0.0.5").unwrap().contains(&"10.0.0.5".parse().unwrap()));
+        assert!(Net::parse("fd00::/8").unwrap().contains(&"fd12::1".parse().unwrap()));
+        assert!(Net::parse("10.0.0.0/33").is_none());
+    }
+}
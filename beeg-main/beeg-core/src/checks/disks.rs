@@ -0,0 +1,329 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{permission_denied, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct DisksArgs {
+    /// Storage node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Also check the devices under storeMetaDirectory
+    #[arg(long)]
+    pub meta: bool,
+    /// Warn when an SSD has used this percentage of its rated endurance
+    #[arg(long, default_value_t = 80)]
+    pub wear_warn: u64,
+    /// Fail at this percentage of rated endurance used
+    #[arg(long, default_value_t = 90)]
+    pub wear_fail: u64,
+    /// Fail at this many reallocated sectors; any at all warn
+    #[arg(long, default_value_t = 50)]
+    pub max_reallocated: u64,
+    /// Warn above this many NVMe media errors or SCSI uncorrected errors
+    #[arg(long, default_value_t = 0)]
+    pub max_media_errors: u64,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 60)]
+    pub timeout: u64,
+}
+
+/// `MAP <dir> <disk>` for every physical disk under each target directory (through partitions,
+/// md and device mapper), then `SMART <disk> <tool>` and that tool's JSON per disk.
+fn probe(meta: bool) -> String {
+    let keys = if meta { r"store\(Storage\|Meta\)Directory" } else { "storeStorageDirectory" };
+    format!(r#"D=$(sed -n 's/^[[:space:]]*{keys}[[:space:]]*=[[:space:]]*\([^#]*\).*/\{n}/p' /etc/beegfs/beegfs-storage.conf /etc/beegfs/beegfs-meta.conf 2>/dev/null | tr ',' ' ')
+ALL=
+for d in $D; do
+  s=$(findmnt -no SOURCE -T "$d" 2>/dev/null)
+  for k in $(lsblk -lnso NAME,TYPE "$s" 2>/dev/null | awk '$2 == "disk" {{ print $1 }}' | sort -u); do echo "MAP $d $k"; ALL="$ALL $k"; done
+done
+for k in $(echo $ALL | tr ' ' '\n' | sort -u); do
+  if command -v smartctl >/dev/null 2>&1; then echo "SMART $k smartctl"; smartctl -a -j "/dev/$k" 2>&1
+  elif command -v nvme >/dev/null 2>&1 && [ "${{k#nvme}}" != "$k" ]; then echo "SMART $k nvme"; nvme smart-log -o json "/dev/$k" 2>&1
+  else echo "SMART $k none"; fi
+done"#, keys = keys, n = if meta { 2 } else { 1 })
+}
+
+/// Health of one disk, from `smartctl -j` or `nvme smart-log -o json`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Health {
+    pub model: Option<String>,
+    /// SMART overall-health self-assessment
+    pub passed: Option<bool>,
+    /// NVMe critical warning bits
+    pub critical_warning: Option<u64>,
+    /// Percentage of rated endurance used
+    pub wear: Option<u64>,
+    pub reallocated: Option<u64>,
+    /// Pending plus offline uncorrectable sectors
+    pub pending: Option<u64>,
+    /// NVMe media errors, or SCSI uncorrected read/write errors
+    pub media_errors: Option<u64>,
+    pub spare: Option<(u64, u64)>,
+    pub temperature: Option<u64>,
+}
+
+fn at<'a>(v: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(v, |v, k| v.get(k))
+}
+
+fn num(v: &Value, path: &[&str]) -> Option<u64> {
+    at(v, path).and_then(Value::as_u64)
+}
+
+/// The JSON object in a tool's output; smartctl and nvme may print warnings around it.
+fn json(text: &str) -> Option<Value> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str(text.get(start..=end)?).ok()
+}
+
+pub(crate) fn parse_smartctl(text: &str) -> Option<Health> {
+    let v = json(text)?;
+    // smartctl without access to the device still prints its JSON, only without data
+    if ["smart_status", "nvme_smart_health_information_log", "ata_smart_attributes"].iter().all(|k| v.get(k).is_none()) { return None; }
+    let attr = |id: u64| at(&v, &["ata_smart_attributes", "table"]).and_then(Value::as_array)
+        .and_then(|t| t.iter().find(|a| a.get("id").and_then(Value::as_u64) == Some(id)))
+        .cloned();
+    let raw = |id: u64| attr(id).and_then(|a| num(&a, &["raw", "value"]));
+    let nvme = |k: &str| num(&v, &["nvme_smart_health_information_log", k]);
+    // ATA SSDs report life left as a normalized value counting down from 100
+    let ata_wear = [177, 231, 233].iter().find_map(|id| attr(*id).and_then(|a| num(&a, &["value"]))).map(|left| 100u64.saturating_sub(left.min(100)));
+    let scsi_errors = ["read", "write"].iter().filter_map(|d| num(&v, &["scsi_error_counter_log", d, "total_uncorrected_errors"])).reduce(|a, b| a + b);
+    Some(Health {
+        model: at(&v, &["model_name"]).or_else(|| at(&v, &["scsi_model_name"])).and_then(Value::as_str).map(String::from),
+        passed: at(&v, &["smart_status", "passed"]).and_then(Value::as_bool),
+        critical_warning: nvme("critical_warning"),
+        wear: nvme("percentage_used").or(num(&v, &["scsi_percentage_used_endurance_indicator"])).or(ata_wear),
+        reallocated: raw(5).or(num(&v, &["scsi_grown_defect_list"])),
+        pending: match (raw(197), raw(198)) { (None, None) => None, (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)) },
+        media_errors: nvme("media_errors").or(scsi_errors),
+        spare: nvme("available_spare").zip(nvme("available_spare_threshold")),
+        temperature: num(&v, &["temperature", "current"]),
+    })
+}
+
+pub(crate) fn parse_nvme_smart_log(text: &str) -> Option<Health> {
+    let v = json(text)?;
+    let n = |k: &str| num(&v, &[k]);
+    Some(Health {
+        critical_warning: Some(n("critical_warning")?),
+        wear: n("percent_used").or(n("percentage_used")),
+        media_errors: n("media_errors"),
+        spare: n("avail_spare").zip(n("spare_thresh")),
+        // nvme-cli reports Kelvin
+        temperature: n("temperature").map(|k| k.saturating_sub(273)),
+        ..Default::default()
+    })
+}
+
+/// Everything wrong with a disk; the worst decides its status.
+pub(crate) fn evaluate(h: &Health, args: &DisksArgs) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    if h.passed == Some(false) { issues.push((Severity::Failure, "SMART overall health check failed".into())); }
+    if let Some(w) = h.critical_warning.filter(|w| *w != 0) { issues.push((Severity::Failure, format!("NVMe critical warning 0x{:02x}", w))); }
+    if let Some((spare, threshold)) = h.spare.filter(|(s, t)| s < t) { issues.push((Severity::Failure, format!("available spare {}% below its {}% threshold", spare, threshold))); }
+    match h.wear {
+        Some(w) if w >= args.wear_fail => issues.push((Severity::Failure, format!("{}% of rated endurance used", w))),
+        Some(w) if w >= args.wear_warn => issues.push((Severity::Warning, format!("{}% of rated endurance used", w))),
+        _ => {}
+    }
+    match h.reallocated {
+        Some(r) if r >= args.max_reallocated => issues.push((Severity::Failure, format!("{} reallocated sectors", r))),
+        Some(r) if r > 0 => issues.push((Severity::Warning, format!("{} reallocated sectors", r))),
+        _ => {}
+    }
+    if let Some(p) = h.pending.filter(|p| *p > 0) { issues.push((Severity::Warning, format!("{} pending or uncorrectable sectors", p))); }
+    if let Some(m) = h.media_errors.filter(|m| *m > args.max_media_errors) { issues.push((Severity::Warning, format!("{} media errors", m))); }
+    issues
+}
+
+/// (disk, target directories on it, tool, tool output)
+type Disk = (String, Vec<String>, String, String);
+
+pub(crate) fn parse_probe(text: &str) -> Vec<Disk> {
+    let mut dirs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut disks: Vec<Disk> = Vec::new();
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("MAP ") {
+            if let Some((dir, disk)) = rest.rsplit_once(' ') { dirs.entry(disk.to_string()).or_default().push(dir.to_string()); }
+        } else if let Some(rest) = line.strip_prefix("SMART ") {
+            let (disk, tool) = rest.split_once(' ').unwrap_or((rest, "none"));
+            disks.push((disk.to_string(), Vec::new(), tool.to_string(), String::new()));
+        } else if let Some(d) = disks.last_mut() {
+            d.3.push_str(line);
+            d.3.push('\n');
+        }
+    }
+    for d in &mut disks { d.1 = dirs.remove(&d.0).unwrap_or_default(); }
+    disks
+}
+
+struct Sample {
+    node: String,
+    disks: Result<Vec<Disk>, String>,
+    duration: Duration,
+}
+
+/// Probe every node, handing each `Sample` to `each` as soon as its node answers.
+fn sample(cfg: &config::Config, nodes: &[&config::Node], args: &DisksArgs, mut each: impl FnMut(Sample)) {
+    let script = probe(args.meta);
+    super::per_node_each(cfg, nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(&script, args.timeout))
+            .map(|out| parse_probe(&out.stdout))
+            .map_err(|e| super::exec_error(&e))
+    }, |_, n, disks, duration| each(Sample { node: n.name.clone(), disks, duration }));
+}
+
+pub struct Disks;
+
+impl Check for Disks {
+    const NAME: &'static str = "disks";
+    const ABOUT: &'static str = "SMART/NVMe health of the disks under the storage targets: media errors, wear and reallocated sectors";
+    type Args = DisksArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &DisksArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &DisksArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(Disks::NAME, &["Disk", "Targets", "Model", "Health", "Wear", "Realloc", "Media errors", "Temp"]);
+    let opt = |v: Option<u64>, unit: &str| v.map(|v| format!("{}{}", v, unit)).unwrap_or_else(|| "-".into());
+    sample(cfg, &nodes, args, |s| {
+        let disks = match s.disks {
+            Ok(d) => d,
+            Err(e) => {
+                report.push(CheckResult { check: Disks::NAME, node: s.node, status: Severity::Failure, detail: e, duration: s.duration, cells: vec!["-".into(); 8], data: serde_json::json!({}) });
+                return;
+            }
+        };
+        if disks.is_empty() {
+            let detail = "no storage target directories found in /etc/beegfs or no disks under them".to_string();
+            report.push(CheckResult { check: Disks::NAME, node: s.node, status: Severity::Warning, detail, duration: s.duration, cells: vec!["-".into(); 8], data: serde_json::json!({ "disks": [] }) });
+            return;
+        }
+        for (disk, dirs, tool, out) in disks {
+            let health = match tool.as_str() {
+                "smartctl" => parse_smartctl(&out),
+                "nvme" => parse_nvme_smart_log(&out),
+                _ => None,
+            };
+            let (status, detail) = match &health {
+                Some(h) => {
+                    let issues = evaluate(h, args);
+                    (issues.iter().map(|(s, _)| *s).max().unwrap_or_default(), issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "))
+                }
+                None if tool == "none" => (Severity::Failure, "neither smartctl nor nvme-cli is installed".to_string()),
+                None if permission_denied(&out) => (Severity::NoPerm, format!("{} needs root to read /dev/{}", tool, disk)),
+                None => (Severity::Failure, out.lines().map(str::trim).rfind(|l| !l.is_empty()).unwrap_or("no health data").to_string()),
+            };
+            let h = health.clone().unwrap_or_default();
+            let verdict = match (h.passed, h.critical_warning) {
+                (Some(false), _) => "FAILED".to_string(),
+                (_, Some(w)) if w != 0 => "CRITICAL".to_string(),
+                (Some(true), _) | (_, Some(_)) => "PASSED".to_string(),
+                _ => "-".to_string(),
+            };
+            let cells = vec![
+                disk.clone(),
+                if dirs.is_empty() { "-".into() } else { dirs.join(", ") },
+                h.model.clone().unwrap_or_else(|| "-".into()),
+                verdict,
+                opt(h.wear, "%"),
+                opt(h.reallocated, ""),
+                opt(h.media_errors, ""),
+                opt(h.temperature, "°C"),
+            ];
+            let data = serde_json::json!({
+                "disk": disk,
+                "targets": dirs,
+                "tool": tool,
+                "model": h.model,
+                "smart_passed": h.passed,
+                "critical_warning": h.critical_warning,
+                "wear_percent": h.wear,
+                "reallocated_sectors": h.reallocated,
+                "pending_sectors": h.pending,
+                "media_errors": h.media_errors,
+                "available_spare": h.spare.map(|(s, _)| s),
+                "temperature_c": h.temperature,
+            });
+            report.push(CheckResult { check: Disks::NAME, node: s.node.clone(), status, detail, duration: s.duration, cells, data });
+        }
+    });
+    report.in_node_order(&nodes);
+
+    let mut failing: Vec<String> = report.results.iter().filter(|r| r.status == Severity::Failure).map(|r| r.node.clone()).collect();
+    failing.dedup();
+    if !failing.is_empty() {
+        report.warn_about(format!("disks under storage targets are failing or unchecked on {}", failing.join(", ")), NoteKind::Node, &failing);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMARTCTL_NVME: &str = r#"{
+  "device": {"name": "/dev/nvme0n1", "type": "nvme", "protocol": "NVMe"},
+  "model_name": "SAMSUNG MZWLJ3T8HBLS-00007",
+  "smart_status": {"passed": true},
+  "nvme_smart_health_information_log": {"critical_warning": 0, "temperature": 38, "available_spare": 100, "available_spare_threshold": 10, "percentage_used": 83, "media_errors": 2},
+  "temperature": {"current": 38}
+}"#;
+
+    const SMARTCTL_ATA: &str = r#"{
+  "device": {"name": "/dev/sda", "type": "sat", "protocol": "ATA"},
+  "model_name": "ST16000NM001G",
+  "smart_status": {"passed": true},
+  "ata_smart_attributes": {"table": [
+    {"id": 5, "name": "Reallocated_Sector_Ct", "value": 100, "raw": {"value": 64}},
+    {"id": 197, "name": "Current_Pending_Sector", "value": 100, "raw": {"value": 1}},
+    {"id": 198, "name": "Offline_Uncorrectable", "value": 100, "raw": {"value": 0}}
+  ]},
+  "temperature": {"current": 31}
+}"#;
+
+    fn args() -> DisksArgs {
+        DisksArgs { selector: "all".into(), meta: false, wear_warn: 80, wear_fail: 90, max_reallocated: 50, max_media_errors: 0, timeout: 60 }
+    }
+
+    #[test]
+    fn smart_health() {
+        let nvme = parse_smartctl(SMARTCTL_NVME).unwrap();
+        assert_eq!((nvme.wear, nvme.media_errors, nvme.spare, nvme.temperature), (Some(83), Some(2), Some((100, 10)), Some(38)));
+        assert_eq!(evaluate(&nvme, &args()), vec![
+            (Severity::Warning, "83% of rated endurance used".to_string()),
+            (Severity::Warning, "2 media errors".to_string()),
+        ]);
+
+        let ata = parse_smartctl(SMARTCTL_ATA).unwrap();
+        assert_eq!((ata.reallocated, ata.pending, ata.wear), (Some(64), Some(1), None));
+        assert_eq!(evaluate(&ata, &args()).iter().map(|(s, _)| *s).collect::<Vec<_>>(), vec![Severity::Failure, Severity::Warning]);
+
+        let log = parse_nvme_smart_log(r#"{"critical_warning": 4, "temperature": 310, "avail_spare": 5, "spare_thresh": 10, "percent_used": 12, "media_errors": 0}"#).unwrap();
+        assert_eq!((log.temperature, log.wear), (Some(37), Some(12)));
+        assert_eq!(evaluate(&log, &args()).len(), 2);
+        let denied = r#"{"smartctl": {"messages": [{"string": "Smartctl open device: /dev/sda failed: Permission denied", "severity": "error"}]}, "device": {"name": "/dev/sda"}}"#;
+        assert_eq!(parse_smartctl(denied), None);
+        assert!(permission_denied(denied));
+    }
+
+    #[test]
+    fn target_disks() {
+        let out = format!("MAP /data/storage nvme0n1\nMAP /data/storage2 nvme0n1\nSMART nvme0n1 smartctl\n{}\nSMART sdb none\n", SMARTCTL_NVME);
+        let disks = parse_probe(&out);
+        assert_eq!(disks.len(), 2);
+        assert_eq!((disks[0].0.as_str(), disks[0].1.clone(), disks[0].2.as_str()), ("nvme0n1", vec!["/data/storage".to_string(), "/data/storage2".to_string()], "smartctl"));
+        assert!(parse_smartctl(&disks[0].3).is_some());
+        assert_eq!((disks[1].2.as_str(), disks[1].1.is_empty()), ("none", true));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct DnsArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Do not warn about addresses without a matching reverse record
+    #[arg(long)]
+    pub no_reverse: bool,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// `HOSTNAME`/`FQDN`, the BeeGFS interfaces from connInterfacesList/connInterfacesFile
+/// (`INTERFACES`) and `ADDR <interface> <ip>` per configured address.
+const IDENTITY: &str = r#"echo "HOSTNAME $(hostname 2>/dev/null)"
+echo "FQDN $(hostname -f 2>/dev/null)"
+L=$(sed -n 's/^[[:space:]]*connInterfacesList[[:space:]]*=[[:space:]]*\([^#]*\).*/\1/p' /etc/beegfs/beegfs-*.conf 2>/dev/null | tr ',' ' ')
+F=$(sed -n 's/^[[:space:]]*connInterfacesFile[[:space:]]*=[[:space:]]*\([^[:space:]#]*\).*/\1/p' /etc/beegfs/beegfs-*.conf 2>/dev/null | sort -u)
+I=$( { for i in $L; do echo "$i"; done; for f in $F; do sed 's/#.*//' "$f" 2>/dev/null; done; } | awk 'NF { print $1 }' | sort -u | tr '\n' ' ')
+echo "INTERFACES $I"
+ip -o addr show 2>/dev/null | awk '{ split($4, a, "/"); print "ADDR", $2, a[1] }'"#;
+
+/// `RESOLVE <name> <ip,ip,...>` per name through the node's resolver (/etc/hosts, DNS, ...),
+/// and `REVERSE <ip> <name>` for each address it returned.
+fn lookup_script(names: &[String]) -> String {
+    let names: Vec<String> = names.iter().map(|n| shell_escape::escape(n.as_str().into()).into_owned()).collect();
+    format!(r#"for n in {}; do
+  a=$(getent ahosts "$n" 2>/dev/null | awk '{{ print $1 }}' | sort -u | tr '\n' ',')
+  echo "RESOLVE $n ${{a%,}}"
+  for ip in $(echo "$a" | tr ',' ' '); do echo "REVERSE $ip $(getent hosts "$ip" 2>/dev/null | awk '{{ print $2; exit }}')"; done
+done"#, names.join(" "))
+}
+
+/// Who a node says it is and which addresses it has.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Identity {
+    pub hostname: String,
+    pub fqdn: String,
+    /// Interfaces BeeGFS is configured to use; empty when it may use any
+    pub interfaces: Vec<String>,
+    /// `(interface, address)`
+    pub addrs: Vec<(String, String)>,
+}
+
+impl Identity {
+    fn interface_of(&self, ip: &str) -> Option<&str> {
+        self.addrs.iter().find(|(_, a)| a == ip).map(|(i, _)| i.as_str())
+    }
+}
+
+pub(crate) fn parse_identity(text: &str) -> Identity {
+    let mut id = Identity::default();
+    for line in text.lines() {
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match tag {
+            "HOSTNAME" => id.hostname = rest.trim().to_string(),
+            "FQDN" => id.fqdn = rest.trim().to_string(),
+            "INTERFACES" => id.interfaces = rest.split_whitespace().map(String::from).collect(),
+            "ADDR" => {
+                if let Some((iface, ip)) = rest.split_once(' ') { id.addrs.push((iface.to_string(), ip.trim().to_string())); }
+            }
+            _ => {}
+        }
+    }
+    id
+}
+
+/// What one node's resolver says about one name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Lookup {
+    /// Sorted; empty when the name does not resolve
+    pub addrs: Vec<String>,
+    /// `ip -> name`; empty names have no reverse record
+    pub reverse: BTreeMap<String, String>,
+}
+
+/// `name -> lookup` on one node.
+type Lookups = BTreeMap<String, Lookup>;
+
+pub(crate) fn parse_lookups(text: &str) -> Lookups {
+    let mut lookups = Lookups::new();
+    let mut reverse = BTreeMap::new();
+    for line in text.lines() {
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let (key, value) = rest.split_once(' ').unwrap_or((rest, ""));
+        match tag {
+            "RESOLVE" => {
+                let mut addrs: Vec<String> = value.split(',').map(str::trim).filter(|a| !a.is_empty()).map(String::from).collect();
+                addrs.sort();
+                lookups.insert(key.to_string(), Lookup { addrs, ..Default::default() });
+            }
+            "REVERSE" => { reverse.insert(key.to_string(), value.trim().to_string()); }
+            _ => {}
+        }
+    }
+    for l in lookups.values_mut() {
+        l.reverse = l.addrs.iter().map(|a| (a.clone(), reverse.get(a).cloned().unwrap_or_default())).collect();
+    }
+    lookups
+}
+
+fn is_loopback(ip: &str) -> bool {
+    ip.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Every node's answer for `name`.
+fn answers<'a>(lookups: &'a [(Result<Lookups, String>, Duration)], name: &'a str) -> impl Iterator<Item = &'a Lookup> + 'a {
+    lookups.iter().filter_map(move |(r, _)| r.as_ref().ok()?.get(name))
+}
+
+/// `a` and `b` name the same host: equal, or one is the other's short form.
+fn same_host(a: &str, b: &str) -> bool {
+    let short = |s: &str| s.split('.').next().unwrap_or("").to_ascii_lowercase();
+    a.eq_ignore_ascii_case(b) || (short(a) == short(b) && (!a.contains('.') || !b.contains('.')))
+}
+
+/// The answer most nodes gave for a name; ties go to the first in sort order.
+fn majority<'a>(answers: impl Iterator<Item = &'a Lookup>) -> Option<&'a Lookup> {
+    let mut counts: BTreeMap<&Vec<String>, (usize, &Lookup)> = BTreeMap::new();
+    for l in answers.filter(|l| !l.addrs.is_empty()) { counts.entry(&l.addrs).or_insert((0, l)).0 += 1; }
+    let max = counts.values().map(|(c, _)| *c).max()?;
+    counts.into_values().find(|(c, _)| *c == max).map(|(_, l)| l)
+}
+
+/// What is wrong with the addresses `name` of node `id` resolves to: not its own, not on the
+/// interfaces BeeGFS uses, or without a reverse record back to the name.
+pub(crate) fn evaluate_target(name: &str, id: &Identity, answer: &Lookup, reverse: bool) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    let remote: Vec<&String> = answer.addrs.iter().filter(|a| !is_loopback(a)).collect();
+    if remote.is_empty() {
+        return vec![(Severity::Warning, format!("{} only resolves to loopback {}: other nodes cannot reach it by name", name, answer.addrs.join(", ")))];
+    }
+    for ip in &remote {
+        match id.interface_of(ip) {
+            None if !id.addrs.is_empty() => issues.push((Severity::Warning, format!("{} resolves to {}, not an address of this node", name, ip))),
+            Some(iface) if !id.interfaces.is_empty() && !id.interfaces.iter().any(|i| i == iface) => issues.push((Severity::Warning, format!(
+                "{} resolves to {} on {}, not on the BeeGFS interfaces {}", name, ip, iface, id.interfaces.join(", "),
+            ))),
+            _ => {}
+        }
+        if reverse {
+            match answer.reverse.get(*ip).map(String::as_str) {
+                Some("") | None => issues.push((Severity::Warning, format!("{} has no reverse record", ip))),
+                Some(back) if !same_host(back, name) && !same_host(back, &id.fqdn) => {
+                    issues.push((Severity::Warning, format!("{} reverses to {}, not {}", ip, back, name)));
+                }
+                _ => {}
+            }
+        }
+    }
+    issues
+}
+
+pub struct Dns;
+
+impl Check for Dns {
+    const NAME: &'static str = "dns";
+    const ABOUT: &'static str = "Hostname resolution between nodes: consistent, forward/reverse agreeing, on the BeeGFS interfaces";
+    type Args = DnsArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &DnsArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &DnsArgs) -> CheckReport {
+    let selected = config::select_nodes(cfg, &args.selector);
+    let all: Vec<&config::Node> = cfg.nodes.iter().collect();
+    let ids: BTreeMap<&str, (Result<Identity, String>, Duration)> = super::per_node(cfg, &all, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(IDENTITY, args.timeout))
+            .map(|out| parse_identity(&out.stdout))
+            .map_err(|e| super::exec_error(&e))
+    }).into_iter().map(|(n, res, took)| (n.name.as_str(), (res, took))).collect();
+
+    // every node is looked up by its hostname and by the inventory host, unless that is an address
+    let names: BTreeMap<&str, Vec<String>> = all.iter().map(|n| {
+        let mut names = Vec::new();
+        if let Some((Ok(id), _)) = ids.get(n.name.as_str()) {
+            if !id.hostname.is_empty() { names.push(id.hostname.clone()); }
+        }
+        if n.host.parse::<IpAddr>().is_err() && !names.iter().any(|h| same_host(h, &n.host)) { names.push(n.host.clone()); }
+        (n.name.as_str(), names)
+    }).collect();
+    let mut wanted: Vec<String> = names.values().flatten().cloned().collect();
+    wanted.sort();
+    wanted.dedup();
+    let lookups: Vec<(Result<Lookups, String>, Duration)> = super::per_node(cfg, &selected, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(&lookup_script(&wanted), args.timeout))
+            .map(|out| parse_lookups(&out.stdout))
+            .map_err(|e| super::exec_error(&e))
+    }).into_iter().map(|(_, res, took)| (res, took)).collect();
+
+    let mut report = CheckReport::new(Dns::NAME, &["Hostname", "Resolves to", "Interface", "Resolved"]);
+    let (mut unresolved, mut inconsistent) = (Vec::new(), Vec::new());
+    for (n, (res, took)) in selected.iter().zip(&lookups) {
+        let id = match ids.get(n.name.as_str()) {
+            Some((Ok(id), _)) => id,
+            Some((Err(e), d)) => {
+                report.push(CheckResult { check: Dns::NAME, node: n.name.clone(), status: Severity::Failure, detail: e.clone(), duration: *d, cells: vec!["-".to_string(); 4], data: serde_json::json!({}) });
+                continue;
+            }
+            None => continue,
+        };
+        let mut issues = Vec::new();
+        let mut resolved = (0, 0);
+        match res {
+            Err(e) => issues.push((Severity::Failure, format!("lookups failed: {}", e))),
+            Ok(mine) => {
+                for (other, name) in names.iter().flat_map(|(o, ns)| ns.iter().map(move |n| (*o, n))) {
+                    let Some(l) = mine.get(name) else { continue };
+                    resolved.1 += 1;
+                    if l.addrs.is_empty() {
+                        issues.push((Severity::Failure, format!("cannot resolve {} ({})", name, other)));
+                        unresolved.push(n.name.clone());
+                        continue;
+                    }
+                    resolved.0 += 1;
+                    if let Some(most) = majority(answers(&lookups, name)).filter(|m| m.addrs != l.addrs) {
+                        issues.push((Severity::Warning, format!("resolves {} to {}, most nodes to {}", name, l.addrs.join(", "), most.addrs.join(", "))));
+                        inconsistent.push(n.name.clone());
+                    }
+                }
+            }
+        }
+        // what the node's own names resolve to, as most nodes see it
+        let own = names.get(n.name.as_str()).cloned().unwrap_or_default();
+        let mut shown = None;
+        for name in &own {
+            let Some(answer) = majority(answers(&lookups, name)) else { continue };
+            shown.get_or_insert((name.clone(), answer.addrs.clone()));
+            issues.extend(evaluate_target(name, id, answer, !args.no_reverse));
+        }
+        let (address, iface) = match &shown {
+            Some((_, addrs)) => (addrs.join(", "), addrs.iter().filter_map(|a| id.interface_of(a)).collect::<Vec<_>>().join(", ")),
+            None => ("-".to_string(), String::new()),
+        };
+        let data = serde_json::json!({
+            "hostname": id.hostname,
+            "fqdn": id.fqdn,
+            "names": own,
+            "addresses": shown.as_ref().map(|(_, a)| a),
+            "beegfs_interfaces": id.interfaces,
+            "resolved": resolved.0,
+            "looked_up": resolved.1,
+            "issues": issues.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+        });
+        report.push(CheckResult {
+            check: Dns::NAME,
+            node: n.name.clone(),
+            status: issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+            detail: issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "),
+            duration: *took,
+            cells: vec![
+                if id.hostname.is_empty() { "-".to_string() } else { id.hostname.clone() },
+                address,
+                if iface.is_empty() { "-".to_string() } else { iface },
+                if res.is_ok() { format!("{}/{}", resolved.0, resolved.1) } else { "-".to_string() },
+            ],
+            data,
+        });
+    }
+    unresolved.dedup();
+    inconsistent.dedup();
+    if !unresolved.is_empty() {
+        report.warn_about(format!("nodes that cannot resolve every node: {}", unresolved.join(", ")), NoteKind::Node, &unresolved);
+    }
+    if !inconsistent.is_empty() {
+        report.warn_about(format!("nodes resolving names differently from the rest (check /etc/hosts): {}", inconsistent.join(", ")), NoteKind::Node, &inconsistent);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution() {
+        let id = parse_identity("HOSTNAME stor01\nFQDN stor01.cluster.local\nINTERFACES ib0 \nADDR lo 127.0.0.1\nADDR eth0 10.0.0.11\nADDR ib0 10.1.0.11\n");
+        assert_eq!(id.interfaces, vec!["ib0"]);
+        assert_eq!(id.interface_of("10.1.0.11"), Some("ib0"));
+
+        let l = parse_lookups("RESOLVE stor01 10.1.0.11\nREVERSE 10.1.0.11 stor01.cluster.local\nRESOLVE stor02 \nRESOLVE meta01 10.0.0.21,10.1.0.21\nREVERSE 10.0.0.21 meta01\nREVERSE 10.1.0.21 \n");
+        assert!(l["stor02"].addrs.is_empty());
+        assert_eq!(l["meta01"].reverse.get("10.1.0.21").map(String::as_str), Some(""));
+        assert!(evaluate_target("stor01", &id, &l["stor01"], true).is_empty());
+
+        let eth = Lookup { addrs: vec!["10.0.0.11".into()], reverse: [("10.0.0.11".into(), "stor01-eth".into())].into() };
+        assert_eq!(evaluate_target("stor01", &id, &eth, true).into_iter().map(|(_, m)| m).collect::<Vec<_>>(), vec![
+            "stor01 resolves to 10.0.0.11 on eth0, not on the BeeGFS interfaces ib0", "10.0.0.11 reverses to stor01-eth, not stor01",
+        ]);
+        let lo = Lookup { addrs: vec!["127.0.1.1".into()], ..Default::default() };
+        assert_eq!(evaluate_target("stor01", &id, &lo, true).len(), 1);
+
+        let (a, b) = (l["stor01"].clone(), eth.clone());
+        assert_eq!(majority([&a, &b, &b].into_iter()).map(|m| &m.addrs), Some(&b.addrs));
+        assert_eq!(majority([&b, &a].into_iter()).map(|m| &m.addrs), Some(&b.addrs));
+        assert!(same_host("stor01", "STOR01.cluster.local") && !same_host("stor01.a", "stor01.b"));
+    }
+}
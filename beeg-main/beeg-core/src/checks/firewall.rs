@@ -0,0 +1,461 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct FirewallArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Only inspect the firewall rules; do not test connections to the servers
+    #[arg(long)]
+    pub no_connect: bool,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 60)]
+    pub timeout: u64,
+}
+
+/// BeeGFS services, the config key prefix of their own port, its default and the protocols
+/// other nodes reach it over.
+const SERVICES: &[(&str, &str, u16, &[&str])] = &[
+    ("mgmtd", "connMgmtdPort", 8008, &["tcp", "udp"]),
+    ("meta", "connMetaPort", 8005, &["tcp", "udp"]),
+    ("storage", "connStoragePort", 8003, &["tcp", "udp"]),
+    ("client", "connClientPort", 8004, &["udp"]),
+];
+
+/// `CONF <service>` per readable service config and `PORT <service> <key> <value>` for its port
+/// settings; `LISTEN <proto> <port>` per listening socket after `SS`; the rules of whichever
+/// firewall is in use, one per line behind `FWD <zone>`, `NFT` or `IPT`; `NOPERM <what>` for
+/// what the user may not read.
+const PROBE: &str = r#"for s in mgmtd meta storage client; do
+  f=/etc/beegfs/beegfs-$s.conf
+  if [ -e "$f" ] && [ ! -r "$f" ]; then echo "NOPERM $f"; continue; fi
+  [ -r "$f" ] || continue
+  echo "CONF $s"
+  sed -n "s/^[[:space:]]*\(conn[A-Za-z]*Port[TU][CD]P\|connPortShift\)[[:space:]]*=[[:space:]]*\([0-9]*\).*/PORT $s \1 \2/p" "$f"
+done
+if command -v ss >/dev/null 2>&1; then
+  echo SS
+  ss -Hlntu 2>/dev/null | awk '{n=split($5,a,":"); print "LISTEN", $1, a[n]}'
+fi
+if command -v firewall-cmd >/dev/null 2>&1 && firewall-cmd --state >/dev/null 2>&1; then
+  r=$(firewall-cmd --list-all 2>&1) || { echo "NOPERM firewalld"; r=; }
+  [ -n "$r" ] && echo "$r" | sed 's/^/FWD /'
+elif command -v nft >/dev/null 2>&1; then
+  r=$(nft list ruleset 2>&1) || { echo "NOPERM nft"; r=; }
+  [ -n "$r" ] && echo "$r" | sed 's/^/NFT /'
+elif command -v iptables-save >/dev/null 2>&1; then
+  r=$(iptables-save 2>&1) || { echo "NOPERM iptables"; r=; }
+  [ -n "$r" ] && echo "$r" | sed 's/^/IPT /'
+fi"#;
+
+/// A port one of a node's BeeGFS services is reached on.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Endpoint {
+    pub service: String,
+    pub proto: &'static str,
+    pub port: u16,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}/{}", self.service, self.port, self.proto)
+    }
+}
+
+/// The firewall a node filters with, and its rules as the tool lists them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Firewall {
+    None,
+    Firewalld(Vec<String>),
+    Nft(Vec<String>),
+    Iptables(Vec<String>),
+}
+
+impl Firewall {
+    fn name(&self) -> &'static str {
+        match self {
+            Firewall::None => "none",
+            Firewall::Firewalld(_) => "firewalld",
+            Firewall::Nft(_) => "nft",
+            Firewall::Iptables(_) => "iptables",
+        }
+    }
+
+    /// The rule (or policy) that keeps `proto`/`port` from being reached, if any.
+    pub fn blocker(&self, proto: &str, port: u16) -> Option<String> {
+        match self {
+            Firewall::None => None,
+            Firewall::Firewalld(lines) => firewalld_blocker(lines, proto, port),
+            Firewall::Nft(lines) => nft_blocker(lines, proto, port),
+            Firewall::Iptables(lines) => iptables_blocker(lines, proto, port),
+        }
+    }
+}
+
+/// What the first probe found on a node.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Host {
+    pub endpoints: Vec<Endpoint>,
+    /// `(proto, port)` of the listening sockets; `None` without ss
+    pub listening: Option<BTreeSet<(String, u16)>>,
+    pub firewall: Firewall,
+    pub denied: Vec<String>,
+}
+
+impl Host {
+    fn listens(&self, e: &Endpoint) -> Option<bool> {
+        self.listening.as_ref().map(|l| l.contains(&(e.proto.to_string(), e.port)))
+    }
+}
+
+/// `role` decides the service when no BeeGFS config is readable (BeeGFS 8's mgmtd has none).
+pub(crate) fn parse_probe(text: &str, role: &str) -> Host {
+    let mut confs: BTreeMap<String, BTreeMap<String, u16>> = BTreeMap::new();
+    let (mut listening, mut denied) = (None::<BTreeSet<(String, u16)>>, Vec::new());
+    let (mut fwd, mut nft, mut ipt) = (Vec::new(), Vec::new(), Vec::new());
+    for line in text.lines() {
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match tag {
+            "CONF" => { confs.entry(rest.trim().to_string()).or_default(); }
+            "PORT" => {
+                if let [service, key, value] = rest.split_whitespace().collect::<Vec<_>>()[..] {
+                    if let Ok(v) = value.parse() { confs.entry(service.to_string()).or_default().insert(key.to_string(), v); }
+                }
+            }
+            "SS" => listening = Some(BTreeSet::new()),
+            "LISTEN" => {
+                if let (Some(l), Some((proto, port))) = (listening.as_mut(), rest.split_once(' ')) {
+                    if let Ok(port) = port.trim().parse() { l.insert((proto.to_string(), port)); }
+                }
+            }
+            "FWD" => fwd.push(rest.to_string()),
+            "NFT" => nft.push(rest.to_string()),
+            "IPT" => ipt.push(rest.to_string()),
+            "NOPERM" => denied.push(rest.trim().to_string()),
+            _ => {}
+        }
+    }
+    if confs.is_empty() && SERVICES.iter().any(|(s, ..)| *s == role || (role == "mgmt" && *s == "mgmtd")) {
+        confs.insert(if role == "mgmt" { "mgmtd".into() } else { role.to_string() }, BTreeMap::new());
+    }
+    let mut endpoints = Vec::new();
+    for (service, keys) in &confs {
+        let Some((_, key, default, protos)) = SERVICES.iter().find(|(s, ..)| s == service) else { continue };
+        let shift = keys.get("connPortShift").copied().unwrap_or(0);
+        for &proto in *protos {
+            let own = keys.get(&format!("{}{}", key, proto.to_uppercase())).copied().unwrap_or(*default);
+            endpoints.push(Endpoint { service: service.clone(), proto, port: own.saturating_add(shift) });
+        }
+    }
+    let firewall = if !fwd.is_empty() { Firewall::Firewalld(fwd) }
+        else if !nft.is_empty() { Firewall::Nft(nft) }
+        else if !ipt.is_empty() { Firewall::Iptables(ipt) }
+        else { Firewall::None };
+    Host { endpoints, listening, firewall, denied }
+}
+
+/// Whether a port list such as `8003`, `8000-8010`, `8000:8010` or `{ 8003, 8005 }` holds `port`.
+fn covers(spec: &str, port: u16) -> bool {
+    spec.split(|c: char| c == ',' || c == '{' || c == '}' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .any(|item| match item.split_once(['-', ':']) {
+            Some((a, b)) => a.parse::<u16>().is_ok_and(|a| a <= port) && b.parse::<u16>().is_ok_and(|b| port <= b),
+            None => item.parse::<u16>() == Ok(port),
+        })
+}
+
+/// firewalld rejects what the zone neither opens as a port, rich rule or `beegfs*` service.
+fn firewalld_blocker(lines: &[String], proto: &str, port: u16) -> Option<String> {
+    let zone = lines.first()?.split_whitespace().next().unwrap_or("?").to_string();
+    let field = |name: &str| lines.iter().find_map(|l| l.trim().strip_prefix(name).map(str::trim)).unwrap_or("");
+    if field("target:") == "ACCEPT" { return None; }
+    let open = field("ports:").split_whitespace().any(|p| p.split_once('/').is_some_and(|(spec, pr)| pr == proto && covers(spec, port)))
+        || field("services:").split_whitespace().any(|s| s.starts_with("beegfs"))
+        || lines.iter().any(|l| l.contains(&format!("port port=\"{}\" protocol=\"{}\" accept", port, proto)));
+    (!open).then(|| format!("firewalld zone {} does not open {}/{}", zone, port, proto))
+}
+
+/// Walk the input chains (and regular chains they may jump to) of an `nft list ruleset`: the
+/// first rule for the port decides, else a `policy drop` of an input chain.
+fn nft_blocker(lines: &[String], proto: &str, port: u16) -> Option<String> {
+    let dport = Regex::new(r"\b(tcp|udp) dport (\{[^}]*\}|\S+)").unwrap();
+    let counter = Regex::new(r"\bcounter( packets \d+ bytes \d+)?").unwrap();
+    let (mut chain, mut hook) = (String::new(), None::<String>);
+    let mut policy = None;
+    for raw in lines {
+        let l = raw.trim();
+        if let Some(name) = l.strip_prefix("chain ").and_then(|r| r.strip_suffix('{')) {
+            (chain, hook) = (name.trim().to_string(), None);
+            continue;
+        }
+        if l.starts_with("type ") {
+            hook = l.split_whitespace().skip_while(|w| *w != "hook").nth(1).map(String::from);
+            if hook.as_deref() == Some("input") && l.contains("policy drop") && policy.is_none() {
+                policy = Some(format!("chain {} policy drop", chain));
+            }
+            continue;
+        }
+        if hook.as_deref().is_some_and(|h| h != "input") { continue; }
+        let words: Vec<&str> = l.split_whitespace().collect();
+        let Some(at) = words.iter().position(|w| matches!(*w, "accept" | "drop" | "reject")) else { continue };
+        let condition = counter.replace_all(&words[..at].join(" "), "").trim().to_string();
+        let applies = match dport.captures(&condition) {
+            Some(c) => &c[1] == proto && covers(&c[2], port),
+            None => condition.is_empty() || condition == format!("meta l4proto {}", proto) || condition == format!("ip protocol {}", proto),
+        };
+        if applies {
+            return (words[at] != "accept").then(|| format!("chain {}: {}", chain, l));
+        }
+    }
+    policy
+}
+
+/// Walk INPUT and custom chains of the filter table in an `iptables-save`: the first rule for
+/// the port decides, else the INPUT policy.
+fn iptables_blocker(lines: &[String], proto: &str, port: u16) -> Option<String> {
+    let comment = Regex::new(r#"-m comment --comment ("[^"]*"|\S+)"#).unwrap();
+    let (mut filter, mut policy) = (false, None);
+    for raw in lines {
+        let l = raw.trim();
+        if let Some(table) = l.strip_prefix('*') { filter = table == "filter"; continue; }
+        if !filter { continue; }
+        if l.starts_with(":INPUT DROP") || l.starts_with(":INPUT REJECT") { policy = Some("INPUT policy DROP".to_string()); continue; }
+        let Some(rule) = l.strip_prefix("-A ") else { continue };
+        let rule = comment.replace_all(rule, "");
+        let words: Vec<&str> = rule.split_whitespace().collect();
+        if matches!(words.first(), Some(&("OUTPUT" | "FORWARD")) | None) { continue; }
+        let value = |flag: &str| words.windows(2).find(|w| w[0] == flag).map(|w| w[1]);
+        let Some(verdict @ ("ACCEPT" | "DROP" | "REJECT")) = value("-j") else { continue };
+        if value("-p").is_some_and(|p| p != proto) { continue; }
+        let applies = match value("--dport").or(value("--dports")) {
+            Some(spec) => covers(spec, port),
+            // an unconditional rule: nothing but the chain, protocol and verdict
+            None => words[1..].chunks(2).all(|w| matches!(w[0], "-p" | "-m" | "-j" | "--reject-with")),
+        };
+        if applies {
+            return (verdict != "ACCEPT").then(|| l.to_string());
+        }
+    }
+    policy
+}
+
+/// Script testing each endpoint of the given hosts: `REACH <node> <proto> <port> <result>`,
+/// TCP `open`, `refused` or `timeout` (dropped), UDP best effort `open` or `closed` (an ICMP
+/// port-unreachable came back), `n/a` without nc.
+fn connect_script(targets: &[(&str, &str, &Endpoint)]) -> String {
+    let mut s = String::from(r#"t() { if timeout 3 bash -c "</dev/tcp/$2/$3" >/dev/null 2>&1; then r=open; elif [ $? -eq 124 ]; then r=timeout; else r=refused; fi; echo "REACH $1 tcp $3 $r"; }
+u() { if ! command -v nc >/dev/null 2>&1; then r=n/a; elif nc -z -u -w 2 "$2" "$3" >/dev/null 2>&1; then r=open; else r=closed; fi; echo "REACH $1 udp $3 $r"; }
+"#);
+    for (node, host, e) in targets {
+        let f = if e.proto == "tcp" { "t" } else { "u" };
+        s.push_str(&format!("{} {} {} {} &\n", f, shell_escape::escape((*node).into()), shell_escape::escape((*host).into()), e.port));
+    }
+    s.push_str("wait");
+    s
+}
+
+/// `(node, proto, port) -> result` of the connection tests from one node.
+type Reach = BTreeMap<(String, String, u16), String>;
+
+pub(crate) fn parse_reach(text: &str) -> Reach {
+    text.lines().filter_map(|l| {
+        let [tag, node, proto, port, result] = l.split_whitespace().collect::<Vec<_>>()[..] else { return None };
+        (tag == "REACH").then_some(())?;
+        Some(((node.to_string(), proto.to_string(), port.parse().ok()?), result.to_string()))
+    }).collect()
+}
+
+pub struct Firewalls;
+
+impl Check for Firewalls {
+    const NAME: &'static str = "firewall";
+    const ABOUT: &'static str = "Firewall rules and connectivity of the BeeGFS ports between clients and servers";
+    type Args = FirewallArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &FirewallArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &FirewallArgs) -> CheckReport {
+    let selected = config::select_nodes(cfg, &args.selector);
+    // the servers are inspected too, selected or not, to find what blocks the connections
+    let mut inspected = selected.clone();
+    for n in cfg.nodes.iter().filter(|n| matches!(n.role(), "mgmtd" | "mgmt" | "meta" | "storage")) {
+        inspected.insert(cfg, n);
+    }
+    let hosts: BTreeMap<&str, (Result<Host, String>, Duration)> = inspected.iter().map(|n| n.name.as_str())
+        .zip(super::per_node(cfg, &inspected, |tr, n| {
+            tr.exec_idempotent(&n.host, &wrap_timeout(PROBE, args.timeout))
+                .map(|out| parse_probe(&out.stdout, n.role()))
+                .map_err(|e| super::exec_error(&e))
+        }).into_iter().map(|(_, res, took)| (res, took)))
+        .collect();
+
+    // every server endpoint, as each selected node should reach it
+    let servers: Vec<(&config::Node, Endpoint)> = inspected.iter()
+        .filter_map(|n| Some((*n, hosts.get(n.name.as_str())?.0.as_ref().ok()?)))
+        .flat_map(|(n, h)| h.endpoints.iter().filter(|e| e.service != "client").map(move |e| (n, e.clone())))
+        .collect();
+    let reach: Vec<(Result<Reach, String>, Duration)> = if args.no_connect {
+        Vec::new()
+    } else {
+        super::per_node(cfg, &selected, |tr, src| {
+            let targets: Vec<(&str, &str, &Endpoint)> = servers.iter()
+                .filter(|(n, _)| n.name != src.name)
+                .map(|(n, e)| (n.name.as_str(), n.host.as_str(), e))
+                .collect();
+            if targets.is_empty() { return Ok(Reach::new()); }
+            tr.exec_idempotent(&src.host, &wrap_timeout(&connect_script(&targets), args.timeout))
+                .map(|out| parse_reach(&out.stdout))
+                .map_err(|e| super::exec_error(&e))
+        }).into_iter().map(|(_, res, took)| (res, took)).collect()
+    };
+
+    let mut issues: BTreeMap<&str, Vec<(Severity, String)>> = BTreeMap::new();
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    let mut down: BTreeSet<(&str, &Endpoint)> = BTreeSet::new();
+    for (src, (res, _)) in selected.iter().zip(&reach) {
+        let Ok(results) = res else { continue };
+        for (dst, e) in servers.iter().filter(|(n, _)| n.name != src.name) {
+            let Some(result) = results.get(&(dst.name.clone(), e.proto.to_string(), e.port)) else { continue };
+            if result == "n/a" { continue; }
+            let count = counts.entry(src.name.as_str()).or_default();
+            count.1 += 1;
+            if result == "open" { count.0 += 1; continue; }
+            let host = hosts.get(dst.name.as_str()).and_then(|(h, _)| h.as_ref().ok());
+            // nothing to reach is the service's problem, reported once on the server if it has a row
+            if host.and_then(|h| h.listens(e)) == Some(false) {
+                if selected.iter().any(|n| n.name == dst.name) {
+                    down.insert((dst.name.as_str(), e));
+                } else {
+                    issues.entry(src.name.as_str()).or_default().push((Severity::Failure, format!("cannot reach {} {}: not listening", dst.name, e)));
+                }
+                continue;
+            }
+            let why = match host {
+                Some(h) => match h.firewall.blocker(e.proto, e.port) {
+                    Some(rule) => format!("blocked on {} by {}", dst.name, rule),
+                    None if !h.denied.is_empty() => format!("{}'s firewall rules are not readable", dst.name),
+                    None => format!("no rule on {} blocks it", dst.name),
+                },
+                None => format!("{} could not be inspected", dst.name),
+            };
+            let severity = if e.proto == "tcp" { Severity::Failure } else { Severity::Warning };
+            issues.entry(src.name.as_str()).or_default().push((severity, format!("cannot reach {} {} ({}): {}", dst.name, e, result, why)));
+        }
+    }
+    for (node, e) in &down {
+        issues.entry(node).or_default().push((Severity::Failure, format!("{} is not listening", e)));
+    }
+
+    let mut report = CheckReport::new(Firewalls::NAME, &["Role", "Ports", "Firewall", "Reachable"]);
+    let mut cut_off = Vec::new();
+    for n in &selected {
+        let role = if n.role().is_empty() { "-".to_string() } else { n.role().to_string() };
+        let (host, duration) = match hosts.get(n.name.as_str()) {
+            Some((Ok(h), d)) => (h, *d),
+            Some((Err(e), d)) => {
+                report.push(CheckResult { check: Firewalls::NAME, node: n.name.clone(), status: Severity::Failure, detail: e.clone(), duration: *d, cells: vec![role, "-".into(), "-".into(), "-".into()], data: serde_json::json!({}) });
+                continue;
+            }
+            None => continue,
+        };
+        let mut found = issues.remove(n.name.as_str()).unwrap_or_default();
+        for e in &host.endpoints {
+            if let Some(rule) = host.firewall.blocker(e.proto, e.port) {
+                found.push((Severity::Warning, format!("{} blocked by {}", e, rule)));
+            }
+        }
+        if !host.denied.is_empty() {
+            let severity = if found.is_empty() { Severity::NoPerm } else { Severity::Ok };
+            found.push((severity, format!("cannot read {}: permission denied", host.denied.join(", "))));
+        }
+        if found.iter().any(|(s, m)| *s == Severity::Failure && m.starts_with("cannot reach")) { cut_off.push(n.name.clone()); }
+        let reachable = match counts.get(n.name.as_str()) {
+            Some((ok, all)) => format!("{}/{}", ok, all),
+            None => "-".to_string(),
+        };
+        let ports = if host.endpoints.is_empty() { "-".to_string() } else {
+            host.endpoints.iter().map(|e| format!("{}/{}", e.port, e.proto)).collect::<Vec<_>>().join(", ")
+        };
+        let data = serde_json::json!({
+            "role": n.role(),
+            "endpoints": host.endpoints.iter().map(|e| serde_json::json!({ "service": e.service, "proto": e.proto, "port": e.port })).collect::<Vec<_>>(),
+            "firewall": host.firewall.name(),
+            "reachable": counts.get(n.name.as_str()).map(|c| c.0),
+            "tested": counts.get(n.name.as_str()).map(|c| c.1),
+            "issues": found.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+        });
+        report.push(CheckResult {
+            check: Firewalls::NAME,
+            node: n.name.clone(),
+            status: found.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+            detail: found.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "),
+            duration,
+            cells: vec![role, ports, host.firewall.name().to_string(), reachable],
+            data,
+        });
+    }
+    if !cut_off.is_empty() {
+        report.warn_about(format!("nodes that cannot reach every BeeGFS server port: {}", cut_off.join(", ")), NoteKind::Node, &cut_off);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn probe_and_rules() {
+        let h = parse_probe("CONF storage\nPORT storage connStoragePortTCP 9003\nPORT storage connPortShift 100\nCONF client\nSS\nLISTEN tcp 9103\nLISTEN udp 8003\nIPT *filter\nIPT :INPUT ACCEPT [0:0]\n", "storage");
+        assert_eq!(h.endpoints.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec!["client 8004/udp", "storage 9103/tcp", "storage 8103/udp"]);
+        assert_eq!(h.listens(&h.endpoints[1]), Some(true));
+        assert_eq!(h.listens(&h.endpoints[2]), Some(false));
+        assert!(matches!(h.firewall, Firewall::Iptables(_)));
+        assert_eq!(parse_probe("", "mgmt").endpoints[0].to_string(), "mgmtd 8008/tcp");
+
+        let nft = Firewall::Nft(lines("table inet filter {
+	chain input {
+		type filter hook input priority filter; policy drop;
+		ct state established,related accept
+		tcp dport { 22, 8005 } accept
+		tcp dport 8000-8010 counter packets 3 bytes 180 drop
+	}
+	chain output {
+		type filter hook output priority filter; policy accept;
+		tcp dport 8003 drop
+	}
+}"));
+        assert_eq!(nft.blocker("tcp", 8005), None);
+        assert_eq!(nft.blocker("tcp", 8003).as_deref(), Some("chain input: tcp dport 8000-8010 counter packets 3 bytes 180 drop"));
+        assert_eq!(nft.blocker("udp", 8003).as_deref(), Some("chain input policy drop"));
+
+        let ipt = Firewall::Iptables(lines("*nat\n-A PREROUTING -j DROP\nCOMMIT\n*filter\n:INPUT ACCEPT [0:0]\n-A INPUT -m state --state RELATED,ESTABLISHED -j ACCEPT\n-A INPUT -p tcp -m multiport --dports 8003,8005 -m comment --comment \"beegfs servers\" -j ACCEPT\n-A INPUT -j REJECT --reject-with icmp-host-prohibited\nCOMMIT"));
+        assert_eq!(ipt.blocker("tcp", 8003), None);
+        assert_eq!(ipt.blocker("udp", 8003).as_deref(), Some("-A INPUT -j REJECT --reject-with icmp-host-prohibited"));
+
+        let fwd = Firewall::Firewalld(lines("public (active)\n  target: default\n  services: ssh\n  ports: 8003/tcp 8005-8008/udp\n  rich rules:\n\trule family=\"ipv4\" port port=\"8008\" protocol=\"tcp\" accept"));
+        assert_eq!(fwd.blocker("tcp", 8003), None);
+        assert_eq!(fwd.blocker("udp", 8006), None);
+        assert_eq!(fwd.blocker("tcp", 8008), None);
+        assert_eq!(fwd.blocker("tcp", 8005).as_deref(), Some("firewalld zone public does not open 8005/tcp"));
+
+        let reach = parse_reach("REACH stor01 tcp 8003 timeout\nREACH meta01 udp 8005 open\nnoise\n");
+        assert_eq!(reach.get(&("stor01".into(), "tcp".into(), 8003)).map(String::as_str), Some("timeout"));
+        assert_eq!(reach.len(), 2);
+    }
+}
@@ -0,0 +1,326 @@
+use crate::notes::NoteKind;
+use crate::{config, transport};
+use clap::Args;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{permission_denied, rand_suffix, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct GdsArgs {
+    /// BeeGFS mountpoint GDS I/O should go to
+    #[arg(long)]
+    pub mount: String,
+    /// GPU node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Also write and read a file on the mount with gdsio in GPU direct mode
+    #[arg(long)]
+    pub gdsio: bool,
+    /// GPU gdsio uses
+    #[arg(long, default_value_t = 0)]
+    pub gpu: u32,
+    /// File size of the gdsio sample, as gdsio takes it
+    #[arg(long, default_value = "256M")]
+    pub size: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 60)]
+    pub timeout: u64,
+}
+
+/// Finds a GDS tool in PATH or under the CUDA install it ships with.
+fn tool(name: &str) -> String {
+    format!("$(command -v {n} 2>/dev/null || ls /usr/local/cuda*/gds/tools/{n} 2>/dev/null | tail -n 1)", n = name)
+}
+
+/// Whether `mount` is a BeeGFS mount, then `gdscheck -p` after a `GDSCHECK` line (`NOGDSCHECK`
+/// without it).
+fn check_probe(mount: &str) -> String {
+    format!(
+        "echo \"MOUNTED $(awk -v m={m} '$2 == m && $3 == \"beegfs\" {{ print \"yes\"; exit }}' /proc/mounts)\"\n\
+         g={g}\n\
+         if [ -z \"$g\" ]; then echo NOGDSCHECK; else echo GDSCHECK; \"$g\" -p 2>&1; fi",
+        m = shell_escape::escape(mount.into()),
+        g = tool("gdscheck"),
+    )
+}
+
+/// gdsio writing (`-I 1`) or reading (`-I 0`) `file` in GPU direct mode (`-x 0`).
+fn gdsio_cmd(file: &str, write: bool, args: &GdsArgs) -> String {
+    format!(
+        "d={}; [ -n \"$d\" ] || {{ echo 'gdsio not found'; exit 127; }}; \"$d\" -f {} -d {} -w 1 -s {} -i 1M -x 0 -I {} 2>&1",
+        tool("gdsio"), shell_escape::escape(file.into()), args.gpu, shell_escape::escape(args.size.clone().into()), u8::from(write),
+    )
+}
+
+/// A GPU as gdscheck lists it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Gpu {
+    pub index: u32,
+    pub name: String,
+    pub supports_gds: bool,
+}
+
+/// What `gdscheck -p` reported.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct GdsCheck {
+    pub release: Option<String>,
+    pub nvidia_fs: Option<String>,
+    /// The DRIVER CONFIGURATION matrix: `BeeGFS` -> `Supported`, `--Mellanox PeerDirect` -> `Enabled`, ...
+    pub drivers: BTreeMap<String, String>,
+    /// `properties.use_compat_mode`: cuFile quietly falls back to POSIX I/O where GDS cannot be used
+    pub compat_mode: Option<bool>,
+    pub gpus: Vec<Gpu>,
+    /// The platform verification line
+    pub verification: Option<String>,
+}
+
+impl GdsCheck {
+    pub fn beegfs(&self) -> Option<&str> {
+        self.drivers.iter().find(|(k, _)| k.eq_ignore_ascii_case("beegfs")).map(|(_, v)| v.as_str())
+    }
+}
+
+pub(crate) fn parse_gdscheck(text: &str) -> GdsCheck {
+    let release = Regex::new(r"GDS release version:\s*(\S+)").unwrap();
+    let nvfs = Regex::new(r"nvidia_fs version:\s*(\S+)").unwrap();
+    let gpu = Regex::new(r"GPU index (\d+)\s+(.+?)\s+bar:").unwrap();
+    let mut g = GdsCheck::default();
+    let mut section = String::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(c) = release.captures(line) { g.release = Some(c[1].to_string()); }
+        if let Some(c) = nvfs.captures(line) { g.nvidia_fs = Some(c[1].to_string()); }
+        if line.ends_with(':') && line.chars().all(|c| c.is_ascii_uppercase() || c == ' ' || c == ':') {
+            section = line.trim_end_matches(':').to_string();
+            continue;
+        }
+        if let Some(c) = gpu.captures(line) {
+            g.gpus.push(Gpu { index: c[1].parse().unwrap_or(0), name: c[2].to_string(), supports_gds: line.contains("supports GDS") });
+        } else if line.starts_with("Platform verification") {
+            g.verification = Some(line.to_string());
+        } else if let Some((k, v)) = line.split_once(':') {
+            let (k, v) = (k.trim(), v.trim());
+            match section.as_str() {
+                "DRIVER CONFIGURATION" if !k.is_empty() => { g.drivers.insert(k.to_string(), v.to_string()); }
+                "CUFILE CONFIGURATION" if k == "properties.use_compat_mode" => g.compat_mode = Some(v == "true"),
+                _ => {}
+            }
+        }
+    }
+    g
+}
+
+/// Throughput of a gdsio run, e.g. `2.345 GiB/sec`, and its transfer type (`GPUD`).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Gdsio {
+    pub throughput: String,
+    pub xfer: Option<String>,
+}
+
+pub(crate) fn parse_gdsio(text: &str) -> Option<Gdsio> {
+    let tp = Regex::new(r"Throughput:\s*([\d.]+\s*[^\s,]+)").unwrap();
+    let xfer = Regex::new(r"XferType:\s*(\S+)").unwrap();
+    text.lines().find_map(|l| Some(Gdsio { throughput: tp.captures(l)?[1].to_string(), xfer: xfer.captures(l).map(|c| c[1].to_string()) }))
+}
+
+/// Everything wrong with one node per gdscheck; the worst decides its status.
+pub(crate) fn evaluate(g: &GdsCheck) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    match g.beegfs() {
+        Some(v) if v.eq_ignore_ascii_case("supported") => {}
+        Some(v) => issues.push((Severity::Failure, format!("gdscheck reports BeeGFS {}", v))),
+        None => issues.push((Severity::Failure, "gdscheck lists no BeeGFS driver support".into())),
+    }
+    if g.gpus.is_empty() {
+        issues.push((Severity::Failure, "gdscheck lists no GPUs".into()));
+    } else if !g.gpus.iter().any(|gpu| gpu.supports_gds) {
+        issues.push((Severity::Failure, "no GPU supports GDS".into()));
+    }
+    if let Some(v) = g.verification.as_deref().filter(|v| !v.contains("succeeded")) {
+        issues.push((Severity::Failure, v.to_string()));
+    }
+    if g.compat_mode == Some(true) {
+        issues.push((Severity::Warning, "cuFile compat mode is on: I/O falls back to POSIX where GDS fails, so it can work without GDS".into()));
+    }
+    issues
+}
+
+struct Sample {
+    node: String,
+    mounted: bool,
+    gdscheck: Result<Option<GdsCheck>, String>,
+    /// Write and read results of the gdsio sample, when asked for
+    io: Vec<(&'static str, Result<Gdsio, String>)>,
+    duration: Duration,
+}
+
+fn gdsio_run(tr: &dyn transport::Transport, host: &str, cmd: &str, timeout: u64) -> Result<Gdsio, String> {
+    let out = tr.exec(host, &wrap_timeout(cmd, timeout)).map_err(|e| super::exec_error(&e))?;
+    match (out.status, parse_gdsio(&out.stdout)) {
+        (Some(0), Some(r)) => Ok(r),
+        _ => Err(out.stdout.lines().chain(out.stderr.lines()).map(str::trim).rfind(|l| !l.is_empty()).unwrap_or("gdsio failed").to_string()),
+    }
+}
+
+/// Probe every node, handing each `Sample` to `each` as soon as its node answers.
+fn sample(cfg: &config::Config, nodes: &[&config::Node], args: &GdsArgs, mut each: impl FnMut(Sample)) {
+    super::per_node_each(cfg, nodes, |tr, n| {
+        let out = tr.exec_idempotent(&n.host, &wrap_timeout(&check_probe(&args.mount), args.timeout)).map_err(|e| super::exec_error(&e))?;
+        let mounted = out.stdout.lines().any(|l| l.trim() == "MOUNTED yes");
+        let gdscheck = match out.stdout.split_once("GDSCHECK\n") {
+            Some((_, rest)) if !out.stdout.contains("NOGDSCHECK") => Some(parse_gdscheck(rest)),
+            _ => None,
+        };
+        let mut io = Vec::new();
+        if args.gdsio && mounted && gdscheck.is_some() {
+            let file = format!("{}/.beeg-gds-{}-{}", args.mount.trim_end_matches('/'), n.name, rand_suffix());
+            let write = gdsio_run(tr, &n.host, &gdsio_cmd(&file, true, args), args.timeout);
+            let wrote = write.is_ok();
+            io.push(("write", write));
+            if wrote { io.push(("read", gdsio_run(tr, &n.host, &gdsio_cmd(&file, false, args), args.timeout))); }
+            // removed even when Ctrl-C cut the I/O short
+            let cleanup = transport::from_config(&transport::cancel::detached(cfg));
+            let _ = cleanup.exec(&n.host, &wrap_timeout(&format!("rm -f {}", shell_escape::escape(file.into())), args.timeout));
+        }
+        Ok((mounted, gdscheck, io))
+    }, |_, n, probe, duration| each(match probe {
+        Ok((mounted, gdscheck, io)) => Sample { node: n.name.clone(), mounted, gdscheck: Ok(gdscheck), io, duration },
+        Err(e) => Sample { node: n.name.clone(), mounted: false, gdscheck: Err(e), io: Vec::new(), duration },
+    }));
+}
+
+pub struct Gds;
+
+impl Check for Gds {
+    const NAME: &'static str = "gds";
+    const ABOUT: &'static str = "GPUDirect Storage end to end: gdscheck's support matrix for BeeGFS and the GPUs, optionally gdsio I/O on the mount";
+    type Args = GdsArgs;
+    const RESUMABLE: bool = false;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &GdsArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &GdsArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(Gds::NAME, &["GDS", "nvidia_fs", "BeeGFS", "GDS GPUs", "Compat mode", "Write", "Read"]);
+    sample(cfg, &nodes, args, |s| {
+        let dash = || "-".to_string();
+        let (status, detail, cells, data) = match &s.gdscheck {
+            Err(e) => (Severity::Failure, e.clone(), vec![dash(); 7], serde_json::json!({})),
+            Ok(None) => (Severity::Failure, "gdscheck not found (CUDA GDS tools not installed)".to_string(), vec![dash(); 7], serde_json::json!({ "mounted": s.mounted })),
+            Ok(Some(g)) => {
+                let mut issues = evaluate(g);
+                if !s.mounted { issues.push((Severity::Failure, format!("{} is not a BeeGFS mount", args.mount))); }
+                for (op, r) in &s.io {
+                    match r {
+                        Err(e) if permission_denied(e) => issues.push((Severity::NoPerm, format!("gdsio {}: {}", op, e))),
+                        Err(e) => issues.push((Severity::Failure, format!("gdsio {} failed: {}", op, e))),
+                        Ok(r) if r.xfer.as_deref().is_some_and(|x| x != "GPUD") => issues.push((Severity::Warning, format!("gdsio {} ran as {}, not GPU direct", op, r.xfer.as_deref().unwrap_or_default()))),
+                        Ok(_) => {}
+                    }
+                }
+                let status = issues.iter().map(|(s, _)| *s).max().unwrap_or_default();
+                let io = |op: &str| s.io.iter().find(|(o, _)| *o == op).map(|(_, r)| match r {
+                    Ok(r) => r.throughput.clone(),
+                    Err(_) => "FAIL".into(),
+                }).unwrap_or_else(dash);
+                let gds_gpus = g.gpus.iter().filter(|gpu| gpu.supports_gds).count();
+                let cells = vec![
+                    g.release.clone().unwrap_or_else(dash),
+                    g.nvidia_fs.clone().unwrap_or_else(dash),
+                    g.beegfs().unwrap_or("-").to_string(),
+                    format!("{}/{}", gds_gpus, g.gpus.len()),
+                    g.compat_mode.map(|c| if c { "on" } else { "off" }.to_string()).unwrap_or_else(dash),
+                    io("write"),
+                    io("read"),
+                ];
+                let data = serde_json::json!({
+                    "mounted": s.mounted,
+                    "gds_release": g.release,
+                    "nvidia_fs": g.nvidia_fs,
+                    "drivers": g.drivers,
+                    "compat_mode": g.compat_mode,
+                    "gpus": g.gpus.iter().map(|gpu| serde_json::json!({ "index": gpu.index, "name": gpu.name, "supports_gds": gpu.supports_gds })).collect::<Vec<_>>(),
+                    "verification": g.verification,
+                    "gdsio": s.io.iter().map(|(op, r)| match r {
+                        Ok(r) => serde_json::json!({ "op": op, "throughput": r.throughput, "xfer": r.xfer }),
+                        Err(e) => serde_json::json!({ "op": op, "error": e }),
+                    }).collect::<Vec<_>>(),
+                    "issues": issues.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+                });
+                (status, issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "), cells, data)
+            }
+        };
+        report.push(CheckResult { check: Gds::NAME, node: s.node, status, detail, duration: s.duration, cells, data });
+    });
+    report.in_node_order(&nodes);
+
+    let broken: Vec<String> = report.results.iter().filter(|r| r.status == Severity::Failure).map(|r| r.node.clone()).collect();
+    if !broken.is_empty() {
+        report.warn_about(format!("GDS I/O to {} does not work on {}", args.mount, broken.join(", ")), NoteKind::Node, &broken);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GDSCHECK: &str = " GDS release version: 1.7.2.10
+ nvidia_fs version:  2.17 libcufile version: 2.12
+ Platform: x86_64
+ ============
+ ENVIRONMENT:
+ ============
+ =====================
+ DRIVER CONFIGURATION:
+ =====================
+ NVMe               : Supported
+ NFS                : Unsupported
+ BeeGFS             : Supported
+ WekaFS             : Unsupported
+ --Mellanox PeerDirect : Enabled
+ =====================
+ CUFILE CONFIGURATION:
+ =====================
+ properties.use_compat_mode : true
+ properties.max_direct_io_size_kb : 16384
+ =========
+ GPU INFO:
+ =========
+ GPU index 0 NVIDIA A100-SXM4-80GB bar:1 bar size (MiB):131072 supports GDS, IOMMU State: Disabled
+ GPU index 1 NVIDIA A100-SXM4-80GB bar:1 bar size (MiB):131072 supports GDS, IOMMU State: Disabled
+ ==============
+ PLATFORM INFO:
+ ==============
+ IOMMU: disabled
+ Platform verification succeeded
+";
+
+    #[test]
+    fn gdscheck_matrix() {
+        let g = parse_gdscheck(GDSCHECK);
+        assert_eq!((g.release.as_deref(), g.nvidia_fs.as_deref()), (Some("1.7.2.10"), Some("2.17")));
+        assert_eq!(g.beegfs(), Some("Supported"));
+        assert_eq!(g.drivers.get("NFS").map(String::as_str), Some("Unsupported"));
+        assert_eq!(g.drivers.get("--Mellanox PeerDirect").map(String::as_str), Some("Enabled"));
+        assert_eq!(g.compat_mode, Some(true));
+        assert_eq!(g.gpus.len(), 2);
+        assert_eq!(g.gpus[1], Gpu { index: 1, name: "NVIDIA A100-SXM4-80GB".into(), supports_gds: true });
+        assert_eq!(evaluate(&g).iter().map(|(s, _)| *s).collect::<Vec<_>>(), vec![Severity::Warning]);
+
+        let off = parse_gdscheck(&GDSCHECK.replace("BeeGFS             : Supported", "BeeGFS             : Unsupported").replace("use_compat_mode : true", "use_compat_mode : false").replace("supports GDS", "does not support GDS"));
+        let issues = evaluate(&off);
+        assert_eq!(issues, vec![(Severity::Failure, "gdscheck reports BeeGFS Unsupported".into()), (Severity::Failure, "no GPU supports GDS".into())]);
+    }
+
+    #[test]
+    fn gdsio_output() {
+        let r = parse_gdsio("IoType: WRITE XferType: GPUD Threads: 1 DataSetSize: 262144/262144(KiB) IOSize: 1024(KiB) Throughput: 2.345112 GiB/sec, Avg_Latency: 416.123 usecs ops: 256 total_time 0.106 secs\n").unwrap();
+        assert_eq!(r, Gdsio { throughput: "2.345112 GiB/sec".into(), xfer: Some("GPUD".into()) });
+        assert_eq!(parse_gdsio("Error: cuFileHandleRegister failed: internal error\n"), None);
+    }
+}
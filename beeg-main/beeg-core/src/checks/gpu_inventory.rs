@@ -0,0 +1,292 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct GpuInventoryArgs {
+    /// GPU node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Labels whose nodes should have the same GPUs; every label of the selected nodes by default
+    #[arg(long, value_delimiter = ',')]
+    pub group_by: Vec<String>,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// NVIDIA display/3D controllers on the PCI bus, then nvidia-smi's GPU listing after an `SMI`
+/// line (`NOSMI` without the tool).
+const PROBE: &str = r#"n=0
+for d in /sys/bus/pci/devices/*; do
+  [ "$(cat "$d/vendor" 2>/dev/null)" = 0x10de ] || continue
+  case "$(cat "$d/class" 2>/dev/null)" in 0x0300*|0x0302*) n=$((n+1)) ;; esac
+done
+echo "PCI $n"
+if command -v nvidia-smi >/dev/null 2>&1; then
+  echo SMI
+  nvidia-smi --query-gpu=index,name,vbios_version,ecc.mode.current,persistence_mode --format=csv,noheader 2>&1
+else echo NOSMI; fi"#;
+
+/// One GPU as nvidia-smi lists it. ECC and persistence mode are `Enabled`, `Disabled` or `[N/A]`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Gpu {
+    pub index: u32,
+    pub model: String,
+    pub vbios: String,
+    pub ecc: String,
+    pub persistence: String,
+}
+
+pub(crate) fn parse_gpus(text: &str) -> Vec<Gpu> {
+    text.lines().filter_map(|l| {
+        let f: Vec<&str> = l.split(',').map(str::trim).collect();
+        match f.as_slice() {
+            [index, model, vbios, ecc, persistence] => Some(Gpu {
+                index: index.parse().ok()?,
+                model: model.to_string(),
+                vbios: vbios.to_string(),
+                ecc: ecc.to_string(),
+                persistence: persistence.to_string(),
+            }),
+            _ => None,
+        }
+    }).collect()
+}
+
+/// A node's GPUs as compared within a label group: every field joined over its distinct values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Inventory {
+    pub count: usize,
+    pub model: String,
+    pub vbios: String,
+    pub ecc: String,
+    pub persistence: String,
+}
+
+impl Inventory {
+    pub fn of(gpus: &[Gpu]) -> Self {
+        let distinct = |f: fn(&Gpu) -> &str| {
+            let mut v: Vec<&str> = gpus.iter().map(f).collect();
+            v.sort();
+            v.dedup();
+            v.join(" + ")
+        };
+        Inventory {
+            count: gpus.len(),
+            model: distinct(|g| &g.model),
+            vbios: distinct(|g| &g.vbios),
+            ecc: distinct(|g| &g.ecc),
+            persistence: distinct(|g| &g.persistence),
+        }
+    }
+
+    /// (field, value) pairs in column order.
+    fn fields(&self) -> [(&'static str, String); 5] {
+        [
+            ("GPU count", self.count.to_string()),
+            ("model", self.model.clone()),
+            ("VBIOS", self.vbios.clone()),
+            ("ECC", self.ecc.clone()),
+            ("persistence mode", self.persistence.clone()),
+        ]
+    }
+}
+
+/// Warnings about one node's own GPUs: mixed models or VBIOS, ECC or persistence mode off.
+pub(crate) fn evaluate(gpus: &[Gpu]) -> Vec<String> {
+    let mut issues = Vec::new();
+    let inv = Inventory::of(gpus);
+    if inv.model.contains(" + ") { issues.push(format!("mixed GPU models: {}", inv.model)); }
+    if inv.vbios.contains(" + ") { issues.push(format!("mixed VBIOS versions: {}", inv.vbios)); }
+    let off = |f: fn(&Gpu) -> &str| gpus.iter().filter(|g| f(g).eq_ignore_ascii_case("disabled")).map(|g| g.index.to_string()).collect::<Vec<_>>();
+    let ecc_off = off(|g| &g.ecc);
+    if !ecc_off.is_empty() { issues.push(format!("ECC disabled on GPU {}", ecc_off.join(","))); }
+    let pm_off = off(|g| &g.persistence);
+    if !pm_off.is_empty() { issues.push(format!("persistence mode disabled on GPU {}", pm_off.join(","))); }
+    issues
+}
+
+/// A field that is not the same on every node of a label group.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Difference<'a> {
+    pub label: String,
+    pub field: &'static str,
+    /// The most common value in the group
+    pub common: String,
+    /// Nodes with another value, and that value
+    pub odd: Vec<(&'a str, String)>,
+}
+
+/// The differences within each label with two or more nodes.
+pub(crate) fn heterogeneous<'a>(groups: &BTreeMap<String, Vec<(&'a str, &'a Inventory)>>) -> Vec<Difference<'a>> {
+    let mut out = Vec::new();
+    for (label, members) in groups.iter().filter(|(_, m)| m.len() > 1) {
+        for i in 0..5 {
+            let field = members[0].1.fields()[i].0;
+            let values: Vec<(&str, String)> = members.iter().map(|(n, inv)| (*n, inv.fields()[i].1.clone())).collect();
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for (_, v) in &values { *counts.entry(v.as_str()).or_default() += 1; }
+            if counts.len() < 2 { continue; }
+            // ties go to the first value in sort order so the outcome does not depend on node order
+            let common = counts.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))).map(|(v, _)| v.to_string()).unwrap_or_default();
+            let odd = values.into_iter().filter(|(_, v)| *v != common).collect();
+            out.push(Difference { label: label.clone(), field, common, odd });
+        }
+    }
+    out
+}
+
+struct Sample {
+    node: String,
+    /// NVIDIA GPUs on the PCI bus
+    pci: usize,
+    /// nvidia-smi's listing; `None` when nvidia-smi is not installed
+    smi: Result<Option<Result<Vec<Gpu>, String>>, String>,
+    duration: Duration,
+}
+
+fn sample(cfg: &config::Config, nodes: &[&config::Node], timeout: u64) -> Vec<Sample> {
+    super::per_node(cfg, nodes, |tr, n| {
+        let out = tr.exec_idempotent(&n.host, &wrap_timeout(PROBE, timeout)).map_err(|e| super::exec_error(&e))?;
+        let pci = out.stdout.lines().find_map(|l| l.strip_prefix("PCI ")).and_then(|v| v.trim().parse().ok()).unwrap_or(0);
+        let smi = match out.stdout.split_once("SMI\n") {
+            Some((_, rest)) if !out.stdout.contains("NOSMI") => {
+                let gpus = parse_gpus(rest);
+                // nvidia-smi prints why it could not talk to the driver instead of rows
+                if gpus.is_empty() && !rest.trim().is_empty() && !rest.contains("No devices were found") {
+                    Some(Err(rest.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("nvidia-smi failed").to_string()))
+                } else {
+                    Some(Ok(gpus))
+                }
+            }
+            _ => None,
+        };
+        Ok((pci, smi))
+    }).into_iter().map(|(n, probe, duration)| {
+        let (pci, smi) = match probe {
+            Ok((pci, smi)) => (pci, Ok(smi)),
+            Err(e) => (0, Err(e)),
+        };
+        Sample { node: n.name.clone(), pci, smi, duration }
+    }).collect()
+}
+
+pub struct GpuInventory;
+
+impl Check for GpuInventory {
+    const NAME: &'static str = "gpu-inventory";
+    const ABOUT: &'static str = "GPU model, count, VBIOS, ECC and persistence mode per node, warning where nodes sharing a label differ";
+    type Args = GpuInventoryArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &GpuInventoryArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &GpuInventoryArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(GpuInventory::NAME, &["GPUs", "Model", "VBIOS", "ECC", "Persistence"]);
+    let mut inventories: BTreeMap<String, Inventory> = BTreeMap::new();
+    for s in sample(cfg, &nodes, args.timeout) {
+        let dash = || vec!["-".to_string(); 5];
+        let (status, detail, cells, data) = match &s.smi {
+            Err(e) => (Severity::Failure, e.clone(), dash(), serde_json::json!({})),
+            Ok(None) if s.pci == 0 => (Severity::Ok, "no NVIDIA GPUs".to_string(), dash(), serde_json::json!({ "pci_gpus": 0 })),
+            Ok(Some(Ok(gpus))) if gpus.is_empty() && s.pci == 0 => (Severity::Ok, "no NVIDIA GPUs".to_string(), dash(), serde_json::json!({ "pci_gpus": 0 })),
+            Ok(None) => (Severity::Failure, format!("{} NVIDIA GPUs on the PCI bus but nvidia-smi is not installed", s.pci), dash(), serde_json::json!({ "pci_gpus": s.pci })),
+            Ok(Some(Err(e))) => (Severity::Failure, format!("nvidia-smi: {}", e), dash(), serde_json::json!({ "pci_gpus": s.pci, "error": e })),
+            Ok(Some(Ok(gpus))) => {
+                let inv = Inventory::of(gpus);
+                let mut issues = evaluate(gpus);
+                let mut status = if issues.is_empty() { Severity::Ok } else { Severity::Warning };
+                if s.pci > gpus.len() {
+                    issues.insert(0, format!("nvidia-smi sees {} of {} NVIDIA GPUs on the PCI bus", gpus.len(), s.pci));
+                    status = Severity::Failure;
+                }
+                let cells = inv.fields().into_iter().map(|(_, v)| if v.is_empty() { "-".to_string() } else { v }).collect();
+                let data = serde_json::json!({
+                    "pci_gpus": s.pci,
+                    "gpus": gpus.iter().map(|g| serde_json::json!({
+                        "index": g.index, "model": g.model, "vbios": g.vbios, "ecc": g.ecc, "persistence_mode": g.persistence,
+                    })).collect::<Vec<_>>(),
+                    "issues": issues,
+                });
+                if !gpus.is_empty() { inventories.insert(s.node.clone(), inv); }
+                (status, issues.join("; "), cells, data)
+            }
+        };
+        report.push(CheckResult { check: GpuInventory::NAME, node: s.node, status, detail, duration: s.duration, cells, data });
+    }
+
+    let mut groups: BTreeMap<String, Vec<(&str, &Inventory)>> = BTreeMap::new();
+    for n in &nodes {
+        let Some(inv) = inventories.get(&n.name) else { continue };
+        for l in n.labels.iter().filter(|l| args.group_by.is_empty() || args.group_by.contains(l)) {
+            groups.entry(l.clone()).or_default().push((n.name.as_str(), inv));
+        }
+    }
+    let mut odd_nodes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut messages = Vec::new();
+    for Difference { label, field, common, odd } in heterogeneous(&groups) {
+        for (node, value) in &odd {
+            odd_nodes.entry(node.to_string()).or_default().push(format!("{} {} differs from '{}' on the rest of label {}", field, value, common, label));
+        }
+        let names: Vec<String> = odd.iter().map(|(n, _)| n.to_string()).collect();
+        messages.push((format!("label {}: {} differs ({} on most nodes, not on {})", label, field, common, names.join(", ")), names));
+    }
+    for r in report.results.iter_mut() {
+        let Some(diffs) = odd_nodes.get(&r.node) else { continue };
+        r.status = r.status.max(Severity::Warning);
+        r.detail = std::iter::once(r.detail.clone()).filter(|d| !d.is_empty()).chain(diffs.iter().cloned()).collect::<Vec<_>>().join("; ");
+        r.data["heterogeneous"] = serde_json::json!(diffs);
+    }
+    for (message, names) in messages {
+        report.warn_about(message, NoteKind::Node, &names);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMI: &str = "0, NVIDIA A100-SXM4-80GB, 92.00.36.00.02, Enabled, Enabled
+1, NVIDIA A100-SXM4-80GB, 92.00.36.00.02, Enabled, Disabled
+2, NVIDIA A100-SXM4-80GB, 92.00.45.00.03, Disabled, Enabled
+";
+
+    #[test]
+    fn nvidia_smi_rows() {
+        let gpus = parse_gpus(SMI);
+        assert_eq!(gpus.len(), 3);
+        assert_eq!(gpus[2], Gpu { index: 2, model: "NVIDIA A100-SXM4-80GB".into(), vbios: "92.00.45.00.03".into(), ecc: "Disabled".into(), persistence: "Enabled".into() });
+        assert!(parse_gpus("NVIDIA-SMI has failed because it couldn't communicate with the NVIDIA driver.\n").is_empty());
+        let inv = Inventory::of(&gpus);
+        assert_eq!((inv.count, inv.vbios.as_str(), inv.ecc.as_str()), (3, "92.00.36.00.02 + 92.00.45.00.03", "Disabled + Enabled"));
+        assert_eq!(evaluate(&gpus), vec![
+            "mixed VBIOS versions: 92.00.36.00.02 + 92.00.45.00.03",
+            "ECC disabled on GPU 2",
+            "persistence mode disabled on GPU 1",
+        ]);
+        assert!(evaluate(&parse_gpus("0, NVIDIA GeForce RTX 4090, 95.02.3C.00.8E, [N/A], Enabled")).is_empty());
+    }
+
+    #[test]
+    fn label_groups() {
+        let gpus = parse_gpus(SMI);
+        let full = Inventory::of(&[gpus[0].clone(), gpus[0].clone()]);
+        let half = Inventory::of(&[gpus[0].clone()]);
+        let groups = BTreeMap::from([
+            ("dgx".to_string(), vec![("gpu01", &full), ("gpu02", &full), ("gpu03", &half)]),
+            ("rack1".to_string(), vec![("gpu01", &full)]),
+        ]);
+        let h = heterogeneous(&groups);
+        assert_eq!(h.len(), 1);
+        assert_eq!(h, vec![Difference { label: "dgx".into(), field: "GPU count", common: "2".into(), odd: vec![("gpu03", "1".into())] }]);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::beegconf;
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct HelperdArgs {
+    /// Client selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Warn when the partition holding the client log has less free space than this
+    #[arg(long, default_value_t = 10)]
+    pub min_free_percent: u8,
+    /// Warn when the client log has not been written for this many hours
+    #[arg(long, default_value_t = 168)]
+    pub max_log_age_hours: u64,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+const CONF: &str = "/etc/beegfs/beegfs-helperd.conf";
+
+/// Service states, then the helperd config after a `CONF` line (`NOCONF` when missing,
+/// `NOPERM` when the user may not read it).
+fn service_probe() -> String {
+    format!(
+        "echo \"CLIENT $(systemctl is-active beegfs-client 2>/dev/null)\"\n\
+         echo \"HELPERD $(systemctl is-active beegfs-helperd 2>/dev/null)\"\n\
+         if [ -r {c} ]; then echo CONF; cat {c}; elif [ -e {c} ]; then echo NOPERM; else echo NOCONF; fi",
+        c = CONF,
+    )
+}
+
+/// Log file, its partition, helperd's port and rotation, for the settings from the config.
+fn log_probe(s: &Settings) -> String {
+    let f = shell_escape::escape(s.log.clone().into());
+    let port = s.port;
+    let mut cmd = String::from("echo \"NOW $(date +%s)\"\n");
+    cmd += "if command -v ss >/dev/null 2>&1; then\n";
+    cmd += &format!("  echo \"LISTEN $(ss -Hltn 'sport = :{}' | wc -l)\"\n", port);
+    cmd += &format!("  echo \"CONNS $(ss -Htn state established '( sport = :{} )' | wc -l)\"\n", port);
+    cmd += "fi\n";
+    if !s.log.is_empty() {
+        cmd += &format!("f={}\n", f);
+        cmd += "if [ -r \"$f\" ]; then echo \"LOG $(stat -c '%s %Y' \"$f\") $(wc -l < \"$f\")\"; elif [ -e \"$f\" ]; then echo LOGNOPERM; fi\n";
+        cmd += "echo \"DF $(df -P \"$(dirname \"$f\")\" 2>/dev/null | awk 'NR==2 {print $2, $4, $6}')\"\n";
+        cmd += "echo \"ROTATED $(ls -d \"$f\".* 2>/dev/null | wc -l)\"\n";
+        cmd += "echo \"LOGROTATE $(grep -ls -- \"$f\" /etc/logrotate.conf /etc/logrotate.d/* 2>/dev/null | head -n 1)\"\n";
+    }
+    cmd
+}
+
+/// Service states and the helperd config of one node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Services {
+    pub client: String,
+    pub helperd: String,
+    pub conf: Option<String>,
+    /// The config exists but the user may not read it
+    pub conf_denied: bool,
+}
+
+pub(crate) fn parse_services(text: &str) -> Services {
+    let mut s = Services::default();
+    let mut lines = text.lines();
+    for line in lines.by_ref() {
+        if let Some(v) = line.strip_prefix("CLIENT") { s.client = v.trim().to_string(); }
+        if let Some(v) = line.strip_prefix("HELPERD") { s.helperd = v.trim().to_string(); }
+        if line == "CONF" { break; }
+        if line == "NOCONF" { return s; }
+        if line == "NOPERM" { s.conf_denied = true; return s; }
+    }
+    s.conf = Some(lines.collect::<Vec<_>>().join("\n"));
+    s
+}
+
+/// Helperd settings that matter here, with BeeGFS's defaults for unset keys.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Settings {
+    /// `logStdFile`; empty when helperd logs to stdout (the journal)
+    pub log: String,
+    pub port: u16,
+    /// Lines after which helperd rotates the log itself; 0 turns that off
+    pub num_lines: u64,
+}
+
+impl Settings {
+    pub fn from_conf(text: &str) -> Settings {
+        let num = |k: &str, d: u64| beegconf::get_value(text, k).and_then(|v| v.parse().ok()).unwrap_or(d);
+        Settings {
+            log: beegconf::get_value(text, "logStdFile").unwrap_or("/var/log/beegfs-client.log").to_string(),
+            port: num("connHelperdPortTCP", 8006) as u16,
+            num_lines: num("logNumLines", 50_000),
+        }
+    }
+}
+
+/// What `log_probe` found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct LogState {
+    pub now: Option<i64>,
+    pub listening: Option<bool>,
+    pub connections: Option<u64>,
+    /// (size in bytes, mtime, lines) when the log file exists
+    pub file: Option<(u64, i64, u64)>,
+    /// (size, available) in KiB and the mountpoint of the log's partition
+    pub partition: Option<(u64, u64, String)>,
+    pub rotated: u64,
+    /// logrotate rule naming the log file
+    pub logrotate: Option<String>,
+    /// The log file exists but the user may not read it
+    pub log_denied: bool,
+}
+
+impl LogState {
+    pub fn free_pct(&self) -> Option<f64> {
+        let (total, avail, _) = self.partition.as_ref()?;
+        (*total > 0).then(|| *avail as f64 * 100.0 / *total as f64)
+    }
+}
+
+pub(crate) fn parse_log_probe(text: &str) -> LogState {
+    let mut s = LogState::default();
+    for line in text.lines() {
+        let (key, v) = line.split_once(' ').unwrap_or((line, ""));
+        let v = v.trim();
+        let nums: Vec<&str> = v.split_whitespace().collect();
+        match key {
+            "NOW" => s.now = v.parse().ok(),
+            "LISTEN" => s.listening = v.parse::<u64>().ok().map(|n| n > 0),
+            "CONNS" => s.connections = v.parse().ok(),
+            "LOG" => if let [size, mtime, lines] = nums[..] {
+                s.file = size.parse().ok().zip(mtime.parse().ok()).zip(lines.parse().ok()).map(|((a, b), c)| (a, b, c));
+            },
+            "DF" => if let [total, avail, mount] = nums[..] {
+                s.partition = total.parse().ok().zip(avail.parse().ok()).map(|(t, a)| (t, a, mount.to_string()));
+            },
+            "ROTATED" => s.rotated = v.parse().unwrap_or(0),
+            "LOGROTATE" if !v.is_empty() => s.logrotate = Some(v.to_string()),
+            "LOGNOPERM" => s.log_denied = true,
+            _ => {}
+        }
+    }
+    s
+}
+
+/// Everything wrong with one node; the worst decides its status.
+pub(crate) fn evaluate(svc: &Services, settings: &Settings, log: &LogState, args: &HelperdArgs) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    let client_up = svc.client == "active";
+    if svc.helperd != "active" {
+        issues.push((Severity::Failure, format!("beegfs-helperd {}", if svc.helperd.is_empty() { "not running" } else { &svc.helperd })));
+    } else if log.listening == Some(false) {
+        issues.push((Severity::Failure, format!("beegfs-helperd not listening on port {}", settings.port)));
+    } else if client_up && log.connections == Some(0) {
+        issues.push((Severity::Warning, format!("client module has no connection to beegfs-helperd (port {})", settings.port)));
+    }
+    if !client_up {
+        issues.push((Severity::Warning, format!("beegfs-client {}", if svc.client.is_empty() { "not running" } else { &svc.client })));
+    }
+    if settings.log.is_empty() { return issues; }
+
+    match (log.file, log.now) {
+        (None, _) if log.log_denied => issues.push((Severity::NoPerm, format!("cannot read log file {}: permission denied", settings.log))),
+        (None, _) => issues.push((Severity::Failure, format!("log file {} missing", settings.log))),
+        (Some((_, mtime, lines)), now) => {
+            let age_h = now.map(|n| (n - mtime).max(0) as u64 / 3600);
+            if client_up && age_h.is_some_and(|h| h > args.max_log_age_hours) {
+                issues.push((Severity::Warning, format!("log not written for {}h", age_h.unwrap_or_default())));
+            }
+            if settings.num_lines > 0 && lines > 2 * settings.num_lines {
+                issues.push((Severity::Warning, format!("log has {} lines but should rotate at {}", lines, settings.num_lines)));
+            } else if settings.num_lines == 0 && log.logrotate.is_none() {
+                issues.push((Severity::Warning, "log is never rotated (logNumLines = 0 and no logrotate rule)".to_string()));
+            }
+        }
+    }
+    if let (Some((_, avail, mount)), Some(pct)) = (&log.partition, log.free_pct()) {
+        if *avail == 0 || pct < 1.0 {
+            issues.push((Severity::Failure, format!("log partition {} is full", mount)));
+        } else if pct < args.min_free_percent as f64 {
+            issues.push((Severity::Warning, format!("log partition {} has {:.0}% free", mount, pct)));
+        }
+    }
+    issues
+}
+
+struct Sample {
+    node: String,
+    services: Result<Services, String>,
+    /// `None` when the node has no helperd config
+    log: Option<(Settings, Result<LogState, String>)>,
+    duration: Duration,
+}
+
+/// Probe every node, handing each `Sample` to `each` as soon as its node answers.
+fn sample(cfg: &config::Config, nodes: &[&config::Node], timeout: u64, mut each: impl FnMut(Sample)) {
+    super::per_node_each(cfg, nodes, |tr, n| {
+        let services = tr.exec_idempotent(&n.host, &wrap_timeout(&service_probe(), timeout)).map(|o| parse_services(&o.stdout)).map_err(|e| super::exec_error(&e))?;
+        let log = services.conf.as_deref().map(|conf| {
+            let settings = Settings::from_conf(conf);
+            let state = tr.exec_idempotent(&n.host, &wrap_timeout(&log_probe(&settings), timeout)).map(|o| parse_log_probe(&o.stdout)).map_err(|e| super::exec_error(&e));
+            (settings, state)
+        });
+        Ok((services, log))
+    }, |_, n, probe, duration| {
+        let (services, log) = match probe {
+            Ok((services, log)) => (Ok(services), log),
+            Err(e) => (Err(e), None),
+        };
+        each(Sample { node: n.name.clone(), services, log, duration })
+    });
+}
+
+pub struct Helperd;
+
+impl Check for Helperd {
+    const NAME: &'static str = "helperd";
+    const ABOUT: &'static str = "beegfs-helperd on clients: service, client module connection, log writes, rotation and log disk space";
+    type Args = HelperdArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &HelperdArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &HelperdArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(Helperd::NAME, &["Client", "Helperd", "Conns", "Log", "Last write", "Log free", "Rotation"]);
+    let mut not_clients = Vec::new();
+    sample(cfg, &nodes, args.timeout, |s| {
+        let dash = || "-".to_string();
+        let state = |s: &str| if s.is_empty() { dash() } else { s.to_string() };
+        let (status, detail, cells, data) = match (&s.services, &s.log) {
+            (Err(e), _) => (Severity::Failure, e.clone(), vec![dash(); 7], serde_json::json!({})),
+            (Ok(svc), None) if svc.client != "active" && svc.helperd != "active" => {
+                not_clients.push(s.node.clone());
+                (Severity::Ok, "not a BeeGFS client".to_string(), vec![state(&svc.client), state(&svc.helperd), dash(), dash(), dash(), dash(), dash()], serde_json::json!({ "client": svc.client, "helperd": svc.helperd }))
+            }
+            (Ok(svc), None) if svc.conf_denied => {
+                let cells = vec![state(&svc.client), state(&svc.helperd), dash(), dash(), dash(), dash(), dash()];
+                (Severity::NoPerm, format!("cannot read {}: permission denied", CONF), cells, serde_json::json!({ "client": svc.client, "helperd": svc.helperd }))
+            }
+            (Ok(svc), None) => {
+                let cells = vec![state(&svc.client), state(&svc.helperd), dash(), dash(), dash(), dash(), dash()];
+                (Severity::Warning, format!("{} not readable", CONF), cells, serde_json::json!({ "client": svc.client, "helperd": svc.helperd }))
+            }
+            (Ok(_), Some((_, Err(e)))) => (Severity::Failure, e.clone(), vec![dash(); 7], serde_json::json!({})),
+            (Ok(svc), Some((settings, Ok(log)))) => {
+                let issues = evaluate(svc, settings, log, args);
+                let status = issues.iter().map(|(s, _)| *s).max().unwrap_or_default();
+                let age = log.file.zip(log.now).map(|((_, mtime, _), now)| (mtime, now));
+                let rotation = if settings.num_lines > 0 {
+                    format!("every {} lines, {} kept", settings.num_lines, log.rotated)
+                } else {
+                    log.logrotate.clone().unwrap_or_else(|| "none".into())
+                };
+                let cells = vec![
+                    state(&svc.client),
+                    state(&svc.helperd),
+                    log.connections.map(|c| c.to_string()).unwrap_or_else(dash),
+                    if settings.log.is_empty() { "journal".into() } else { settings.log.clone() },
+                    age.map(|(mtime, now)| format!("{} ago", crate::notes::age(mtime, now))).unwrap_or_else(dash),
+                    log.free_pct().map(|p| format!("{:.0}%", p)).unwrap_or_else(dash),
+                    rotation,
+                ];
+                let data = serde_json::json!({
+                    "client": svc.client,
+                    "helperd": svc.helperd,
+                    "port": settings.port,
+                    "listening": log.listening,
+                    "connections": log.connections,
+                    "log_file": settings.log,
+                    "log_bytes": log.file.map(|f| f.0),
+                    "log_lines": log.file.map(|f| f.2),
+                    "log_age_secs": age.map(|(mtime, now)| (now - mtime).max(0)),
+                    "log_partition": log.partition.as_ref().map(|p| &p.2),
+                    "log_partition_free_pct": log.free_pct(),
+                    "log_num_lines": settings.num_lines,
+                    "rotated_files": log.rotated,
+                    "logrotate": log.logrotate,
+                    "issues": issues.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+                });
+                (status, issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "), cells, data)
+            }
+        };
+        report.push(CheckResult { check: Helperd::NAME, node: s.node, status, detail, duration: s.duration, cells, data });
+    });
+    report.in_node_order(&nodes);
+    not_clients.sort_by_key(|c| nodes.iter().position(|n| &n.name == c));
+
+    let down: Vec<String> = report.results.iter().filter(|r| r.detail.starts_with("beegfs-helperd")).map(|r| r.node.clone()).collect();
+    if !down.is_empty() {
+        report.warn_about(format!("beegfs-helperd is not serving on {}; client log messages there are lost", down.join(", ")), NoteKind::Node, &down);
+    }
+    if !not_clients.is_empty() {
+        report.preamble.push(format!("Not BeeGFS clients (skipped): {}", not_clients.join(", ")));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> HelperdArgs {
+        HelperdArgs { selector: "all".into(), min_free_percent: 10, max_log_age_hours: 168, timeout: 10 }
+    }
+
+    #[test]
+    fn probes_and_issues() {
+        let svc = parse_services("CLIENT active\nHELPERD active\nCONF\n# comment\nlogStdFile = /var/log/beegfs-client.log\nconnHelperdPortTCP = 8106\nlogNumLines = 0\n");
+        assert_eq!((svc.client.as_str(), svc.helperd.as_str()), ("active", "active"));
+        let settings = Settings::from_conf(svc.conf.as_deref().unwrap());
+        assert_eq!(settings, Settings { log: "/var/log/beegfs-client.log".into(), port: 8106, num_lines: 0 });
+        assert_eq!(parse_services("CLIENT inactive\nHELPERD inactive\nNOCONF\n").conf, None);
+        assert!(parse_services("CLIENT active\nHELPERD active\nNOPERM\n").conf_denied);
+        assert_eq!(Settings::from_conf("").port, 8006);
+
+        let log = parse_log_probe("NOW 1700000000\nLISTEN 1\nCONNS 2\nLOG 4096 1699990000 120\nDF 1000000 500000 /var\nROTATED 0\nLOGROTATE \n");
+        assert_eq!(log.file, Some((4096, 1_699_990_000, 120)));
+        assert_eq!(log.partition, Some((1_000_000, 500_000, "/var".into())));
+        assert_eq!((log.connections, log.free_pct()), (Some(2), Some(50.0)));
+        assert_eq!(evaluate(&svc, &settings, &log, &args()), vec![(Severity::Warning, "log is never rotated (logNumLines = 0 and no logrotate rule)".to_string())]);
+
+        // helperd dead, log gone and its partition full
+        let dead = Services { helperd: "failed".into(), ..svc.clone() };
+        let bad = parse_log_probe("NOW 1700000000\nLISTEN 0\nCONNS 0\nDF 1000000 0 /var\nROTATED 0\nLOGROTATE /etc/logrotate.d/beegfs\n");
+        let issues = evaluate(&dead, &settings, &bad, &args());
+        assert_eq!(issues.iter().map(|(s, _)| *s).collect::<Vec<_>>(), vec![Severity::Failure; 3]);
+        assert_eq!(issues[0].1, "beegfs-helperd failed");
+        assert_eq!(issues[2].1, "log partition /var is full");
+    }
+}
@@ -0,0 +1,335 @@
+use crate::notes::NoteKind;
+use crate::{config, state};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct IbFabricArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Only these HCAs (e.g. mlx5_0,mlx5_2); every HCA by default
+    #[arg(short = 'd', long, value_delimiter = ',')]
+    pub device: Vec<String>,
+    /// Rate in Gbit/s every active port should run at (e.g. 200 for HDR); the fastest port seen by default
+    #[arg(long)]
+    pub expected_rate: Option<f64>,
+    /// Warn when an error counter is above this, however old the errors are
+    #[arg(long, default_value_t = 1000)]
+    pub max_errors: u64,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// `ibstat` after an `IBSTAT` line (`NOIBSTAT` without it), then `perfquery` for each port after
+/// a `PERFQUERY <ca> <port>` line.
+const PROBE: &str = r#"command -v ibstat >/dev/null 2>&1 || { echo NOIBSTAT; exit 0; }
+echo IBSTAT
+ibstat 2>&1
+for ca in $(ibstat -l 2>/dev/null); do
+  n=$(ibstat "$ca" 2>/dev/null | awk '/Number of ports:/ { print $4; exit }')
+  for p in $(seq 1 "${n:-1}"); do
+    echo "PERFQUERY $ca $p"
+    perfquery -C "$ca" -P "$p" 2>&1
+  done
+done"#;
+
+/// Counters that only move when something is wrong with the link or the traffic on it.
+pub(crate) const ERROR_COUNTERS: &[&str] = &[
+    "SymbolErrorCounter",
+    "LinkErrorRecoveryCounter",
+    "LinkDownedCounter",
+    "PortRcvErrors",
+    "PortRcvRemotePhysicalErrors",
+    "PortXmitDiscards",
+    "LocalLinkIntegrityErrors",
+    "ExcessiveBufferOverrunErrors",
+];
+
+/// One HCA port.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Port {
+    pub ca: String,
+    pub port: u32,
+    pub state: String,
+    pub physical_state: String,
+    /// Gbit/s as ibstat reports it
+    pub rate: Option<f64>,
+    pub link_layer: String,
+    /// `ERROR_COUNTERS` that perfquery reported
+    pub counters: BTreeMap<String, u64>,
+}
+
+impl Port {
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.ca, self.port)
+    }
+
+    pub fn infiniband(&self) -> bool {
+        self.link_layer.is_empty() || self.link_layer.eq_ignore_ascii_case("infiniband")
+    }
+
+    /// Active and LinkUp; physically disabled ports are not expected to be up.
+    pub fn up(&self) -> bool {
+        self.state.eq_ignore_ascii_case("active") && self.physical_state.eq_ignore_ascii_case("linkup")
+    }
+
+    pub fn disabled(&self) -> bool {
+        self.physical_state.eq_ignore_ascii_case("disabled")
+    }
+}
+
+pub(crate) fn parse_ibstat(text: &str) -> Vec<Port> {
+    let mut ports: Vec<Port> = Vec::new();
+    let mut ca = String::new();
+    for line in text.lines() {
+        let t = line.trim();
+        if let Some(name) = t.strip_prefix("CA '").and_then(|s| s.strip_suffix('\'')) {
+            ca = name.to_string();
+        } else if let Some(n) = t.strip_prefix("Port ").and_then(|s| s.strip_suffix(':')).and_then(|n| n.parse().ok()) {
+            ports.push(Port { ca: ca.clone(), port: n, ..Default::default() });
+        } else if let (Some(p), Some((k, v))) = (ports.last_mut().filter(|p| p.ca == ca), t.split_once(':')) {
+            let v = v.trim().to_string();
+            match k.trim() {
+                "State" => p.state = v,
+                "Physical state" => p.physical_state = v,
+                "Rate" => p.rate = v.parse().ok(),
+                "Link layer" => p.link_layer = v,
+                _ => {}
+            }
+        }
+    }
+    ports
+}
+
+/// `SymbolErrorCounter:..............12` lines of `perfquery`.
+pub(crate) fn parse_perfquery(text: &str) -> BTreeMap<String, u64> {
+    text.lines().filter_map(|l| {
+        let (k, v) = l.split_once(':')?;
+        let k = k.trim();
+        if !ERROR_COUNTERS.contains(&k) { return None; }
+        Some((k.to_string(), v.trim_start_matches('.').trim().parse().ok()?))
+    }).collect()
+}
+
+/// Error counters of a port at the last run, keyed by `node/ca/port` in the state file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Seen {
+    pub at: i64,
+    pub counters: BTreeMap<String, u64>,
+}
+
+/// Kept in the state dir so the next run can tell which counters are still climbing.
+const COUNTERS_FILE: &str = "ib-counters.json";
+
+/// What is wrong with one port; the worst decides its status.
+pub(crate) fn evaluate(port: &Port, expected: Option<f64>, max_errors: u64, prev: Option<&Seen>) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    if !port.up() {
+        issues.push((Severity::Failure, format!("port is {}/{}", port.state, port.physical_state)));
+        return issues;
+    }
+    if let (Some(rate), Some(want)) = (port.rate, expected) {
+        if rate < want { issues.push((Severity::Warning, format!("running at {} Gb/s, expected {}", rate, want))); }
+    }
+    for (name, v) in &port.counters {
+        // a counter below its previous value was reset or the HCA restarted; start over from it
+        let grew = prev.and_then(|p| p.counters.get(name)).filter(|was| *v > **was).map(|was| v - was);
+        match grew {
+            Some(d) => issues.push((Severity::Warning, format!("{} +{} since the last run", name, d))),
+            None if *v > max_errors => issues.push((Severity::Warning, format!("{} is {}", name, v))),
+            None => {}
+        }
+    }
+    issues
+}
+
+struct Sample {
+    node: String,
+    ports: Result<Option<Vec<Port>>, String>,
+    duration: Duration,
+}
+
+fn sample(cfg: &config::Config, nodes: &[&config::Node], timeout: u64) -> Vec<Sample> {
+    super::per_node(cfg, nodes, |tr, n| {
+        let out = tr.exec_idempotent(&n.host, &wrap_timeout(PROBE, timeout)).map_err(|e| super::exec_error(&e))?;
+        if out.stdout.lines().any(|l| l.trim() == "NOIBSTAT") { return Ok(None); }
+        let mut sections = out.stdout.split("\nPERFQUERY ");
+        let mut ports = parse_ibstat(sections.next().unwrap_or_default());
+        for section in sections {
+            let (head, body) = section.split_once('\n').unwrap_or((section, ""));
+            let mut f = head.split_whitespace();
+            let (Some(ca), Some(p)) = (f.next(), f.next().and_then(|p| p.parse::<u32>().ok())) else { continue };
+            if let Some(port) = ports.iter_mut().find(|x| x.ca == ca && x.port == p) { port.counters = parse_perfquery(body); }
+        }
+        Ok(Some(ports))
+    }).into_iter().map(|(n, ports, duration)| Sample { node: n.name.clone(), ports, duration }).collect()
+}
+
+pub struct IbFabric;
+
+impl Check for IbFabric {
+    const NAME: &'static str = "ib-fabric";
+    const ABOUT: &'static str = "InfiniBand port state, negotiated rate and error counters (ibstat, perfquery) per node";
+    type Args = IbFabricArgs;
+    const RESUMABLE: bool = false;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &IbFabricArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &IbFabricArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(IbFabric::NAME, &["Port", "State", "Physical", "Rate", "Symbol errors", "Link downed", "Rcv errors"]);
+    let samples = sample(cfg, &nodes, args.timeout);
+
+    let wanted = |p: &Port| p.infiniband() && (args.device.is_empty() || args.device.contains(&p.ca));
+    let fastest = samples.iter()
+        .filter_map(|s| s.ports.as_ref().ok()?.as_ref())
+        .flatten()
+        .filter(|p| wanted(p) && p.up())
+        .filter_map(|p| p.rate)
+        .fold(None, |m: Option<f64>, r| Some(m.map_or(r, |m| m.max(r))));
+    let expected = args.expected_rate.or(fastest);
+    if args.expected_rate.is_none() {
+        if let Some(r) = fastest { report.preamble.push(format!("Expected rate: {} Gb/s, the fastest active port (--expected-rate to set it)", r)); }
+    }
+
+    let mut seen: BTreeMap<String, Seen> = state::read_json(COUNTERS_FILE).unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    let dash = || "-".to_string();
+    for s in samples {
+        let ports = match s.ports {
+            Err(e) => {
+                report.push(CheckResult { check: IbFabric::NAME, node: s.node, status: Severity::Failure, detail: e, duration: s.duration, cells: vec![dash(); 7], data: serde_json::json!({}) });
+                continue;
+            }
+            Ok(None) => {
+                let detail = "ibstat not found (infiniband-diags not installed)".to_string();
+                report.push(CheckResult { check: IbFabric::NAME, node: s.node, status: Severity::Failure, detail, duration: s.duration, cells: vec![dash(); 7], data: serde_json::json!({}) });
+                continue;
+            }
+            Ok(Some(ports)) => ports.into_iter().filter(|p| wanted(p) && !p.disabled()).collect::<Vec<_>>(),
+        };
+        if ports.is_empty() {
+            report.push(CheckResult { check: IbFabric::NAME, node: s.node, status: Severity::Ok, detail: "no InfiniBand ports".into(), duration: s.duration, cells: vec![dash(); 7], data: serde_json::json!({ "ports": [] }) });
+            continue;
+        }
+        for p in ports {
+            let key = format!("{}/{}", s.node, p.name());
+            let issues = evaluate(&p, expected, args.max_errors, seen.get(&key));
+            let counter = |name: &str| p.counters.get(name).map(|v| v.to_string()).unwrap_or_else(dash);
+            let cells = vec![
+                p.name(),
+                p.state.clone(),
+                p.physical_state.clone(),
+                p.rate.map(|r| format!("{} Gb/s", r)).unwrap_or_else(dash),
+                counter("SymbolErrorCounter"),
+                counter("LinkDownedCounter"),
+                counter("PortRcvErrors"),
+            ];
+            let data = serde_json::json!({
+                "port": p.name(),
+                "state": p.state,
+                "physical_state": p.physical_state,
+                "rate_gbps": p.rate,
+                "expected_rate_gbps": expected,
+                "counters": p.counters,
+                "issues": issues.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+            });
+            if !p.counters.is_empty() { seen.insert(key, Seen { at: now, counters: p.counters.clone() }); }
+            report.push(CheckResult {
+                check: IbFabric::NAME,
+                node: s.node.clone(),
+                status: issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+                detail: issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "),
+                duration: s.duration,
+                cells,
+                data,
+            });
+        }
+    }
+    if let Err(e) = state::write_json(COUNTERS_FILE, &seen) { report.warn(format!("saving IB counters: {:#}", e)); }
+
+    let mut down: Vec<String> = report.results.iter().filter(|r| r.status == Severity::Failure).map(|r| r.node.clone()).collect();
+    down.dedup();
+    if !down.is_empty() {
+        report.warn_about(format!("InfiniBand ports down or not checked on {}", down.join(", ")), NoteKind::Node, &down);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IBSTAT: &str = "CA 'mlx5_0'
+	CA type: MT4123
+	Number of ports: 1
+	Firmware version: 20.31.1014
+	Port 1:
+		State: Active
+		Physical state: LinkUp
+		Rate: 100
+		Base lid: 12
+		Link layer: InfiniBand
+CA 'mlx5_1'
+	CA type: MT4123
+	Number of ports: 1
+	Port 1:
+		State: Down
+		Physical state: Polling
+		Rate: 10
+		Link layer: InfiniBand
+CA 'mlx5_2'
+	Number of ports: 1
+	Port 1:
+		State: Active
+		Physical state: LinkUp
+		Rate: 100
+		Link layer: Ethernet
+";
+
+    const PERFQUERY: &str = "# Port counters: Lid 12 port 1 (CapMask: 0x5300)
+PortSelect:......................1
+CounterSelect:...................0x0000
+SymbolErrorCounter:..............37
+LinkErrorRecoveryCounter:........0
+LinkDownedCounter:...............2
+PortRcvErrors:...................0
+PortXmitData:....................123456789
+";
+
+    #[test]
+    fn ibstat_ports() {
+        let ports = parse_ibstat(IBSTAT);
+        assert_eq!(ports.len(), 3);
+        assert_eq!((ports[0].name(), ports[0].rate, ports[0].up()), ("mlx5_0/1".to_string(), Some(100.0), true));
+        assert_eq!((ports[1].state.as_str(), ports[1].physical_state.as_str(), ports[1].up()), ("Down", "Polling", false));
+        assert!(!ports[2].infiniband());
+        let c = parse_perfquery(PERFQUERY);
+        assert_eq!(c.get("SymbolErrorCounter"), Some(&37));
+        assert_eq!(c.get("LinkDownedCounter"), Some(&2));
+        assert!(!c.contains_key("PortXmitData"));
+    }
+
+    #[test]
+    fn port_issues() {
+        let mut p = parse_ibstat(IBSTAT).remove(0);
+        p.counters = parse_perfquery(PERFQUERY);
+        assert_eq!(evaluate(&p, Some(100.0), 1000, None), vec![]);
+        assert_eq!(evaluate(&p, Some(200.0), 10, None), vec![
+            (Severity::Warning, "running at 100 Gb/s, expected 200".to_string()),
+            (Severity::Warning, "SymbolErrorCounter is 37".to_string()),
+        ]);
+        let prev = Seen { at: 0, counters: BTreeMap::from([("SymbolErrorCounter".to_string(), 30), ("LinkDownedCounter".to_string(), 2)]) };
+        assert_eq!(evaluate(&p, None, 1000, Some(&prev)), vec![(Severity::Warning, "SymbolErrorCounter +7 since the last run".to_string())]);
+        assert_eq!(evaluate(&parse_ibstat(IBSTAT).remove(1), None, 1000, None), vec![(Severity::Failure, "port is Down/Polling".to_string())]);
+    }
+}
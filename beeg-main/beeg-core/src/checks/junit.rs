@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+
+use super::{CheckReport, CheckResult, Severity};
+
+fn esc(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // XML 1.0 has no escape for other control characters
+            c if c.is_control() && !matches!(c, '\n' | '\t' | '\r') => out.push('?'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// One `<testcase>` per node: FAIL is a failure, NOPERM an error (the test could not run),
+/// and WARN passes unless `strict`, with its detail kept in `system-out`.
+fn testcase(out: &mut String, r: &CheckResult, strict: bool) {
+    let _ = write!(out, "    <testcase classname=\"beeg.{}\" name=\"{}\" time=\"{:.3}\"", esc(r.check), esc(&r.node), r.duration.as_secs_f64());
+    let detail = if r.detail.is_empty() { r.status.as_str().to_string() } else { r.detail.clone() };
+    match r.status {
+        Severity::Ok => { out.push_str("/>\n"); return; }
+        Severity::Failure => { let _ = write!(out, ">\n      <failure type=\"FAIL\" message=\"{0}\">{0}</failure>\n", esc(&detail)); }
+        Severity::Warning if strict => { let _ = write!(out, ">\n      <failure type=\"WARN\" message=\"{0}\">{0}</failure>\n", esc(&detail)); }
+        Severity::Warning => { let _ = write!(out, ">\n      <system-out>WARN: {}</system-out>\n", esc(&detail)); }
+        Severity::NoPerm => { let _ = write!(out, ">\n      <error type=\"NOPERM\" message=\"{0}\">{0}</error>\n", esc(&detail)); }
+    }
+    out.push_str("    </testcase>\n");
+}
+
+fn testsuite(out: &mut String, name: &str, results: &[&CheckResult], warnings: &[String], strict: bool, started: DateTime<Utc>) {
+    let failures = results.iter().filter(|r| r.status == Severity::Failure || (strict && r.status == Severity::Warning)).count();
+    let errors = results.iter().filter(|r| r.status == Severity::NoPerm).count();
+    let time: f64 = results.iter().map(|r| r.duration.as_secs_f64()).sum();
+    let _ = writeln!(out, "  <testsuite name=\"beeg.{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"0\" time=\"{:.3}\" timestamp=\"{}\">",
+        esc(name), results.len(), failures, errors, time, started.format("%Y-%m-%dT%H:%M:%S"));
+    for r in results { testcase(out, r, strict); }
+    if !warnings.is_empty() {
+        let _ = writeln!(out, "    <system-err>{}</system-err>", esc(&warnings.iter().map(|w| format!("WARNING: {}", w)).collect::<Vec<_>>().join("\n")));
+    }
+    out.push_str("  </testsuite>\n");
+}
+
+/// JUnit XML for one run: a test suite per check (per section for `check all`) with a test
+/// case per node. A run that ended in an error is a single errored test case.
+pub(crate) fn render(check: &str, outcome: Result<&CheckReport, &anyhow::Error>, started: DateTime<Utc>, strict: bool) -> String {
+    let mut suites = String::new();
+    match outcome {
+        Ok(report) => {
+            for s in &report.sections {
+                let results: Vec<&CheckResult> = s.results.iter().collect();
+                let warnings: Vec<String> = s.warnings.iter().map(|w| w.message.clone()).collect();
+                testsuite(&mut suites, s.check, &results, &warnings, strict, started);
+            }
+            // `check all` also lists each section as a row; only the rest are test cases of their own
+            let own: Vec<&CheckResult> = report.results.iter().filter(|r| !report.sections.iter().any(|s| s.check == r.check)).collect();
+            if report.sections.is_empty() || !own.is_empty() {
+                let warnings: Vec<String> = report.warnings.iter().map(|w| w.message.clone()).collect();
+                testsuite(&mut suites, report.check, &own, &warnings, strict, started);
+            }
+        }
+        Err(e) => {
+            let msg = esc(&format!("{:#}", e));
+            let _ = writeln!(suites, "  <testsuite name=\"beeg.{0}\" tests=\"1\" failures=\"0\" errors=\"1\" skipped=\"0\" time=\"0\" timestamp=\"{1}\">\n    \
+                <testcase classname=\"beeg.{0}\" name=\"run\" time=\"0\">\n      <error type=\"ERROR\" message=\"{2}\">{2}</error>\n    </testcase>\n  </testsuite>",
+                esc(check), started.format("%Y-%m-%dT%H:%M:%S"), msg);
+        }
+    }
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"beeg check {}\">\n{}</testsuites>\n", esc(check), suites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn result(check: &'static str, node: &str, status: Severity, detail: &str) -> CheckResult {
+        CheckResult { check, node: node.into(), status, detail: detail.into(), duration: Duration::from_millis(250), cells: Vec::new(), data: serde_json::json!({}) }
+    }
+
+    #[test]
+    fn junit_xml() {
+        let mut r = CheckReport::new("storage-target", &[]);
+        r.results = vec![
+            result("storage-target", "s1", Severity::Ok, ""),
+            result("storage-target", "s2", Severity::Failure, "target 102 <offline>"),
+            result("storage-target", "s3", Severity::Warning, "needs-resync"),
+        ];
+        r.warn("1 target offline");
+        let xml = render("storage-target", Ok(&r), Utc::now(), false);
+        assert!(xml.contains("<testsuite name=\"beeg.storage-target\" tests=\"3\" failures=\"1\" errors=\"0\""));
+        assert!(xml.contains("<testcase classname=\"beeg.storage-target\" name=\"s1\" time=\"0.250\"/>"));
+        assert!(xml.contains("<failure type=\"FAIL\" message=\"target 102 &lt;offline&gt;\">"));
+        assert!(xml.contains("<system-out>WARN: needs-resync</system-out>"));
+        assert!(xml.contains("<system-err>WARNING: 1 target offline</system-err>"));
+        // --strict turns the warning into a failure
+        assert!(render("storage-target", Ok(&r), Utc::now(), true).contains("failures=\"2\""));
+
+        let xml = render("cuda", Err(&anyhow::anyhow!("no nodes")), Utc::now(), false);
+        assert!(xml.contains("errors=\"1\"") && xml.contains("<error type=\"ERROR\" message=\"no nodes\">"));
+    }
+}
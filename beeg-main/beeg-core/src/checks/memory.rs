@@ -0,0 +1,300 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct MemoryArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Seconds between the two swap counter reads
+    #[arg(long, default_value_t = 2)]
+    pub interval: u64,
+    /// Warn when estimated connection buffers take more than this share of RAM (percent)
+    #[arg(long, default_value_t = 25)]
+    pub conn_percent: u8,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// Client defaults of the settings that size a node's connection buffers.
+const DEFAULT_MAX_INTERNODE: u64 = 12;
+const DEFAULT_RDMA_BUF_SIZE: u64 = 8192;
+const DEFAULT_RDMA_BUF_NUM: u64 = 70;
+
+/// `MEM <key>: <kB>` from /proc/meminfo, `THP`/`DEFRAG` with the selected transparent hugepage
+/// modes, `SWAP1`/`SWAP2` with pswpin and pswpout `interval` seconds apart, and
+/// `CONN <service> <key> <value>` for the connection settings in the service configs.
+fn probe(interval: u64) -> String {
+    format!(r#"grep -E '^(MemTotal|MemAvailable|SwapTotal|SwapFree|HugePages_Total|Hugepagesize):' /proc/meminfo | sed 's/^/MEM /'
+t=/sys/kernel/mm/transparent_hugepage
+echo "THP $(sed -n 's/.*\[\(.*\)\].*/\1/p' $t/enabled 2>/dev/null)"
+echo "DEFRAG $(sed -n 's/.*\[\(.*\)\].*/\1/p' $t/defrag 2>/dev/null)"
+echo "SWAP1 $(awk '/^pswp(in|out) /{{s+=$2}} END{{print s+0}}' /proc/vmstat)"
+sleep {interval}
+echo "SWAP2 $(awk '/^pswp(in|out) /{{s+=$2}} END{{print s+0}}' /proc/vmstat)"
+for s in client storage meta; do
+  f=/etc/beegfs/beegfs-$s.conf; [ -r "$f" ] || continue
+  sed -n "s/^[[:space:]]*\(connMaxInternodeNum\|connRDMABufSize\|connRDMABufNum\)[[:space:]]*=[[:space:]]*\([0-9]*\).*/CONN $s \1 \2/p" "$f"
+done"#)
+}
+
+/// One node's memory picture; sizes in kB as /proc/meminfo reports them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct MemInfo {
+    pub total: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+    pub hugepages: u64,
+    pub hugepage_size: u64,
+    pub thp: String,
+    pub defrag: String,
+    /// Pages swapped in and out per second over the probe interval
+    pub swap_rate: f64,
+    /// `(service, setting) -> value` from the BeeGFS configs on the node
+    pub conn: BTreeMap<(String, String), u64>,
+}
+
+impl MemInfo {
+    fn hugepages_kb(&self) -> u64 { self.hugepages * self.hugepage_size }
+
+    /// `setting` of the client config on this node, else the client default.
+    fn client(&self, setting: &str, default: u64) -> u64 {
+        self.conn.get(&("client".to_string(), setting.to_string())).copied().unwrap_or(default)
+    }
+}
+
+pub(crate) fn parse_probe(text: &str, interval: u64) -> MemInfo {
+    let mut m = MemInfo::default();
+    let (mut before, mut after) = (None, None);
+    for line in text.lines() {
+        let Some((tag, rest)) = line.split_once(' ') else { continue };
+        match tag {
+            "MEM" => {
+                let Some((key, value)) = rest.split_once(':') else { continue };
+                let value = value.split_whitespace().next().and_then(|v| v.parse().ok()).unwrap_or(0);
+                match key {
+                    "MemTotal" => m.total = value,
+                    "MemAvailable" => m.available = value,
+                    "SwapTotal" => m.swap_total = value,
+                    "SwapFree" => m.swap_free = value,
+                    "HugePages_Total" => m.hugepages = value,
+                    "Hugepagesize" => m.hugepage_size = value,
+                    _ => {}
+                }
+            }
+            "THP" => m.thp = rest.trim().to_string(),
+            "DEFRAG" => m.defrag = rest.trim().to_string(),
+            "SWAP1" => before = rest.trim().parse::<u64>().ok(),
+            "SWAP2" => after = rest.trim().parse::<u64>().ok(),
+            "CONN" => {
+                let f: Vec<&str> = rest.split_whitespace().collect();
+                if let [service, key, value] = f[..] {
+                    if let Ok(v) = value.parse() { m.conn.insert((service.to_string(), key.to_string()), v); }
+                }
+            }
+            _ => {}
+        }
+    }
+    if let (Some(b), Some(a)) = (before, after) {
+        m.swap_rate = a.saturating_sub(b) as f64 / interval.max(1) as f64;
+    }
+    m
+}
+
+/// kB as GiB with one decimal.
+fn gib(kb: u64) -> String {
+    format!("{:.1}GiB", kb as f64 / (1024.0 * 1024.0))
+}
+
+/// Upper bound of the RDMA buffers a node's BeeGFS connections can pin, in kB: every
+/// connection holds `connRDMABufNum` send and receive buffers of `connRDMABufSize` bytes, and
+/// each peer may open up to the client's `connMaxInternodeNum` connections. Clients connect
+/// to every server; servers take connections from every client, with `client` the
+/// (connMaxInternodeNum, connRDMABufSize, connRDMABufNum) to assume for them.
+pub(crate) fn conn_memory(role: &str, m: &MemInfo, servers: u64, clients: u64, client: (u64, u64, u64)) -> Option<u64> {
+    let (peers, (internode, size, num)) = match role {
+        "client" => (servers, (
+            m.client("connMaxInternodeNum", DEFAULT_MAX_INTERNODE),
+            m.client("connRDMABufSize", DEFAULT_RDMA_BUF_SIZE),
+            m.client("connRDMABufNum", DEFAULT_RDMA_BUF_NUM),
+        )),
+        "storage" | "meta" => (clients, client),
+        _ => return None,
+    };
+    Some(peers * internode * size * num * 2 / 1024)
+}
+
+/// What is wrong with a node's memory setup; swapping and hugepages only matter on servers.
+pub(crate) fn evaluate(role: &str, m: &MemInfo, conn_kb: Option<u64>, args: &MemoryArgs) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    if m.total == 0 { return vec![(Severity::Failure, "could not read /proc/meminfo".into())]; }
+    let server = matches!(role, "storage" | "meta" | "mgmtd" | "mgmt");
+    if server && m.swap_rate > 0.0 {
+        issues.push((Severity::Warning, format!("swapping: {:.0} pages/s", m.swap_rate)));
+    }
+    if m.available * 20 < m.total {
+        issues.push((Severity::Warning, format!("only {} of {} available", gib(m.available), gib(m.total))));
+    }
+    if server && m.thp == "always" && m.defrag == "always" {
+        issues.push((Severity::Warning, "transparent hugepages always with defrag always: direct compaction stalls server threads".into()));
+    }
+    if server && m.hugepages_kb() * 2 > m.total {
+        issues.push((Severity::Warning, format!("{} of {} reserved as hugepages, unavailable to the page cache", gib(m.hugepages_kb()), gib(m.total))));
+    }
+    if let Some(kb) = conn_kb.filter(|kb| *kb * 100 > m.total * args.conn_percent as u64) {
+        issues.push((Severity::Warning, format!(
+            "connection buffers may take {} ({}% of RAM); lower connMaxInternodeNum or connRDMABufNum",
+            gib(kb), kb * 100 / m.total,
+        )));
+    }
+    issues
+}
+
+struct Sample {
+    node: String,
+    role: String,
+    probe: Result<MemInfo, String>,
+    duration: Duration,
+}
+
+fn sample(cfg: &config::Config, nodes: &[&config::Node], args: &MemoryArgs) -> Vec<Sample> {
+    let script = probe(args.interval);
+    super::per_node(cfg, nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(&script, args.timeout))
+            .map(|out| parse_probe(&out.stdout, args.interval))
+            .map_err(|e| super::exec_error(&e))
+    }).into_iter().map(|(n, probe, duration)| Sample { node: n.name.clone(), role: n.role().to_string(), probe, duration }).collect()
+}
+
+pub struct Memory;
+
+impl Check for Memory {
+    const NAME: &'static str = "memory";
+    const ABOUT: &'static str = "RAM, swap activity, hugepages and connection buffer memory per node";
+    type Args = MemoryArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &MemoryArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &MemoryArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(Memory::NAME, &["Role", "RAM", "Available", "Swap used", "Swap pages/s", "THP", "Hugepages", "Conn buffers"]);
+    let samples = sample(cfg, &nodes, args);
+
+    // peers are counted over the whole inventory, not just the selection
+    let servers = cfg.nodes.iter().filter(|n| matches!(n.role(), "storage" | "meta")).count() as u64;
+    let clients = cfg.nodes.iter().filter(|n| n.role() == "client").count() as u64;
+    // servers size their buffers after the largest client settings seen in this run
+    let seen: Vec<&MemInfo> = samples.iter().filter(|s| s.role == "client").filter_map(|s| s.probe.as_ref().ok()).collect();
+    let largest = |setting: &str, default: u64| seen.iter().map(|m| m.client(setting, default)).max().unwrap_or(default);
+    let client = (
+        largest("connMaxInternodeNum", DEFAULT_MAX_INTERNODE),
+        largest("connRDMABufSize", DEFAULT_RDMA_BUF_SIZE),
+        largest("connRDMABufNum", DEFAULT_RDMA_BUF_NUM),
+    );
+    report.preamble.push(format!(
+        "connection buffers assume {} servers and {} clients, connMaxInternodeNum {}, connRDMABufSize {}, connRDMABufNum {}",
+        servers, clients, client.0, client.1, client.2,
+    ));
+
+    let mut swapping = Vec::new();
+    for s in samples {
+        let m = match s.probe {
+            Ok(m) => m,
+            Err(e) => {
+                let mut cells = vec![if s.role.is_empty() { "-".to_string() } else { s.role.clone() }];
+                cells.extend(vec!["-".to_string(); 7]);
+                report.push(CheckResult { check: Memory::NAME, node: s.node, status: Severity::Failure, detail: e, duration: s.duration, cells, data: serde_json::json!({}) });
+                continue;
+            }
+        };
+        let conn_kb = conn_memory(&s.role, &m, servers, clients, client);
+        let issues = evaluate(&s.role, &m, conn_kb, args);
+        if issues.iter().any(|(_, i)| i.starts_with("swapping")) { swapping.push(s.node.clone()); }
+        let cells = vec![
+            if s.role.is_empty() { "-".to_string() } else { s.role.clone() },
+            gib(m.total),
+            gib(m.available),
+            if m.swap_total == 0 { "no swap".to_string() } else { gib(m.swap_total - m.swap_free) },
+            format!("{:.0}", m.swap_rate),
+            if m.thp.is_empty() { "-".to_string() } else { format!("{}/{}", m.thp, m.defrag) },
+            if m.hugepages == 0 { "0".to_string() } else { gib(m.hugepages_kb()) },
+            conn_kb.map(gib).unwrap_or_else(|| "-".to_string()),
+        ];
+        let data = serde_json::json!({
+            "role": s.role,
+            "total_kb": m.total,
+            "available_kb": m.available,
+            "swap_total_kb": m.swap_total,
+            "swap_free_kb": m.swap_free,
+            "swap_pages_per_sec": m.swap_rate,
+            "thp": m.thp,
+            "thp_defrag": m.defrag,
+            "hugepages_kb": m.hugepages_kb(),
+            "conn_buffers_kb": conn_kb,
+            "conn_settings": m.conn.iter().map(|((svc, k), v)| (format!("{}.{}", svc, k), *v)).collect::<BTreeMap<_, _>>(),
+        });
+        report.push(CheckResult {
+            check: Memory::NAME,
+            node: s.node,
+            status: issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+            detail: issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "),
+            duration: s.duration,
+            cells,
+            data,
+        });
+    }
+    if !swapping.is_empty() {
+        report.warn_about(format!("servers swapping: {}", swapping.join(", ")), NoteKind::Node, &swapping);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE_OUT: &str = "MEM MemTotal:       263921548 kB
+MEM MemAvailable:   30000000 kB
+MEM SwapTotal:       8388604 kB
+MEM SwapFree:        4194300 kB
+MEM HugePages_Total:   65536
+MEM Hugepagesize:       2048 kB
+THP always
+DEFRAG always
+SWAP1 1200
+SWAP2 1400
+CONN client connMaxInternodeNum 32
+CONN client connRDMABufNum 140
+";
+
+    #[test]
+    fn meminfo() {
+        let m = parse_probe(PROBE_OUT, 2);
+        assert_eq!((m.total, m.swap_free, m.hugepages_kb()), (263921548, 4194300, 134217728));
+        assert_eq!(m.swap_rate, 100.0);
+        assert_eq!(m.client("connMaxInternodeNum", 12), 32);
+        assert_eq!(m.client("connRDMABufSize", 8192), 8192);
+
+        // 200 servers x 32 connections x 140 buffers x 8 KiB x 2 = 13.7 GiB
+        let conn = conn_memory("client", &m, 200, 0, (0, 0, 0));
+        assert_eq!(conn, Some(14336000));
+        assert_eq!(conn_memory("mgmtd", &m, 200, 0, (0, 0, 0)), None);
+
+        let args = MemoryArgs { selector: "all".into(), interval: 2, conn_percent: 25, timeout: 30 };
+        assert!(evaluate("client", &m, conn, &args).is_empty());
+        let issues: Vec<String> = evaluate("storage", &m, Some(80_000_000), &args).into_iter().map(|(_, i)| i).collect();
+        assert_eq!(issues.len(), 4, "{:?}", issues);
+        assert!(issues[0].starts_with("swapping: 100 pages/s"));
+        assert!(issues[3].contains("(30% of RAM)"));
+    }
+}
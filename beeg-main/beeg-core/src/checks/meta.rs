@@ -0,0 +1,180 @@
+use crate::beegfs::{self, NodeType};
+use crate::notes::NoteKind;
+use crate::{config, transport};
+use clap::Args;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use super::{single_node, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct MetaArgs {
+    /// Management node to query (name/host/label); must resolve to one node
+    #[arg(long, visible_alias = "node")]
+    pub selector: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+/// One metadata server as seen from the management node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct MetaNode {
+    pub id: String,
+    pub name: String,
+    pub reachable: Option<bool>,
+    pub heartbeat: Option<String>,
+    pub root: bool,
+    pub reachability: Option<String>,
+    pub consistency: Option<String>,
+    pub pool: Option<String>,
+    pub free_pct: Option<u8>,
+    pub inodes_free_pct: Option<u8>,
+}
+
+/// Parse one block of `beegfs-df` ("METADATA SERVERS" or "STORAGE TARGETS") into target id -> (pool, free %, inodes free %).
+pub(crate) fn parse_df(text: &str, section: &str) -> BTreeMap<String, (String, u8, u8)> {
+    let row = Regex::new(r"^\s*(\d+)\s+(\S+)\s+\S+\s+\S+\s+(\d+)%\s+\S+\s+\S+\s+(\d+)%").unwrap();
+    let mut out = BTreeMap::new();
+    let mut inside = false;
+    for line in text.lines() {
+        if line.ends_with(':') && !line.starts_with(' ') { inside = line.starts_with(section); continue; }
+        if !inside { continue; }
+        if let Some(c) = row.captures(line) {
+            out.insert(c[1].to_string(), (c[2].to_string(), c[3].parse().unwrap_or(0), c[4].parse().unwrap_or(0)));
+        }
+    }
+    out
+}
+
+/// Unreachable or offline fails; any other state, consistency or pool than online/good/normal warns.
+fn node_status(n: &MetaNode) -> (Severity, String) {
+    let mut issues = Vec::new();
+    if n.reachable == Some(false) { issues.push((Severity::Failure, "not reachable".to_string())); }
+    match n.reachability.as_deref() {
+        Some(r) if r.eq_ignore_ascii_case("offline") => issues.push((Severity::Failure, "target offline".to_string())),
+        Some(r) if !r.eq_ignore_ascii_case("online") => issues.push((Severity::Warning, format!("target {}", r.to_lowercase()))),
+        _ => {}
+    }
+    if let Some(c) = n.consistency.as_deref().filter(|c| !c.eq_ignore_ascii_case("good")) {
+        issues.push((Severity::Warning, format!("consistency {}", c.to_lowercase())));
+    }
+    if let Some(p) = n.pool.as_deref().filter(|p| *p != "normal") {
+        issues.push((Severity::Warning, format!("in the {} capacity pool", p)));
+    }
+    let severity = issues.iter().map(|(s, _)| *s).max().unwrap_or_default();
+    (severity, issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "))
+}
+
+pub struct Meta;
+
+impl Check for Meta {
+    const NAME: &'static str = "meta";
+    const ABOUT: &'static str = "Metadata server reachability, state and capacity pools (via a mgmt node)";
+    type Args = MetaArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &MetaArgs) -> anyhow::Result<CheckReport> {
+        run(cfg, args)
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &MetaArgs) -> anyhow::Result<CheckReport> {
+    let mgmt = single_node(cfg, &args.selector)?;
+    let started = Instant::now();
+    let tr = transport::from_config(cfg);
+    let timeout = args.timeout;
+
+    let ctl = beegfs::Ctl::new(cfg, mgmt, timeout);
+    let mut nodes: Vec<MetaNode> = ctl.nodes(NodeType::Meta)?.into_iter().map(|n| MetaNode {
+        id: n.id,
+        name: n.name,
+        reachable: n.reachable,
+        heartbeat: n.heartbeat,
+        root: n.root,
+        ..Default::default()
+    }).collect();
+    if nodes.is_empty() { return Err(ctl.nothing("metadata nodes")); }
+    let states = ctl.target_states(NodeType::Meta).unwrap_or_default();
+    let df = tr.exec_idempotent(&mgmt.host, &wrap_timeout("beegfs-df 2>/dev/null", timeout))
+        .map(|o| parse_df(&o.stdout, "METADATA SERVERS")).unwrap_or_default();
+    // meta target IDs equal meta node IDs
+    for n in &mut nodes {
+        if let Some(st) = states.get(&n.id) {
+            n.reachability = Some(st.reachability.clone());
+            n.consistency = Some(st.consistency.clone());
+        }
+        if let Some((pool, free, ifree)) = df.get(&n.id) {
+            n.pool = Some(pool.clone());
+            n.free_pct = Some(*free);
+            n.inodes_free_pct = Some(*ifree);
+        }
+    }
+
+    let duration = started.elapsed();
+
+    let opt = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".into());
+    let pct = |v: Option<u8>| v.map(|p| format!("{}%", p)).unwrap_or_else(|| "-".into());
+    let mut report = CheckReport::new(Meta::NAME, &["NodeID", "Reachable", "State", "Heartbeat", "Pool", "Free", "Inodes free"]);
+    for n in &nodes {
+        let (status, detail) = node_status(n);
+        let reach = match n.reachable { Some(true) => "yes", Some(false) => "no", None => "-" };
+        let state = match (&n.reachability, &n.consistency) {
+            (Some(r), Some(c)) => format!("{}/{}", r, c),
+            _ => "-".into(),
+        };
+        let id = if n.root { format!("{} (root)", n.id) } else { n.id.clone() };
+        report.push(CheckResult {
+            check: Meta::NAME,
+            node: n.name.clone(),
+            status,
+            detail,
+            duration,
+            cells: vec![id, reach.to_string(), state, opt(&n.heartbeat), opt(&n.pool), pct(n.free_pct), pct(n.inodes_free_pct)],
+            data: serde_json::json!({
+                "id": n.id,
+                "root": n.root,
+                "reachable": n.reachable,
+                "reachability": n.reachability,
+                "consistency": n.consistency,
+                "last_heartbeat": n.heartbeat,
+                "pool": n.pool,
+                "free_pct": n.free_pct,
+                "inodes_free_pct": n.inodes_free_pct,
+            }),
+        });
+    }
+
+    let with = |sev: Severity| -> Vec<&str> { nodes.iter().filter(|n| node_status(n).0 == sev).map(|n| n.name.as_str()).collect() };
+    let unreachable = with(Severity::Failure);
+    if !unreachable.is_empty() { report.warn_about(format!("unreachable metadata servers: {}", unreachable.join(", ")), NoteKind::Node, &unreachable); }
+    let degraded = with(Severity::Warning);
+    if !degraded.is_empty() { report.warn_about(format!("degraded metadata servers: {}", degraded.join(", ")), NoteKind::Node, &degraded); }
+    if states.is_empty() { report.warn(format!("could not read meta target states on {}", mgmt.name)); }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DF: &str = "METADATA SERVERS:
+TargetID   Cap. Pool        Total         Free    %      ITotal       IFree    %
+========   =========        =====         ====    =      ======       =====    =
+       1      normal     122.3GiB     106.2GiB  87%        8.2M        7.7M  94%
+       2         low     122.3GiB      10.0GiB   8%        8.2M        1.1M  13%
+
+STORAGE TARGETS:
+TargetID   Cap. Pool        Total         Free    %      ITotal       IFree    %
+========   =========        =====         ====    =      ======       =====    =
+     101      normal    7300.0GiB    6000.0GiB  82%      730.0M      700.0M  96%
+";
+
+    #[test]
+    fn df() {
+        let df = parse_df(DF, "METADATA SERVERS");
+        assert_eq!(df.len(), 2);
+        assert_eq!(df.get("2"), Some(&("low".to_string(), 8, 13)));
+        assert_eq!(parse_df(DF, "STORAGE TARGETS").keys().collect::<Vec<_>>(), vec!["101"]);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{beegfs, config, identity, prompt, timefmt, transport};
+use chrono::{DateTime, Utc};
+use clap::Args;
+use std::time::{Duration, Instant};
+
+use super::{rand_suffix, single_node, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct MetaMirrorArgs {
+    /// BeegFS mountpoint used by both clients (e.g., /mnt/beegfs)
+    #[arg(long)]
+    pub mount: String,
+    /// Client that creates the test file; must resolve to one node
+    #[arg(long)]
+    pub writer: String,
+    /// Client that stats the file while the primary is down; must resolve to one node
+    #[arg(long)]
+    pub reader: String,
+    /// Node running the primary beegfs-meta (default: detected via --getentryinfo)
+    #[arg(long)]
+    pub primary: Option<String>,
+    /// Seconds to wait for the secondary meta to serve the file. The mgmtd switches over only
+    /// once the primary has been unreachable for its sysTargetOfflineTimeoutSecs (180 by
+    /// default), so keep this above that value plus a margin
+    #[arg(long, default_value_t = 240)]
+    pub failover_timeout: u64,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+    /// Do not ask for confirmation before stopping beegfs-meta
+    #[arg(long)]
+    pub yes: bool,
+}
+
+/// Pause between stat attempts while waiting for the secondary.
+const FAILOVER_POLL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+struct Step {
+    name: &'static str,
+    node: String,
+    ok: bool,
+    detail: String,
+    at: DateTime<Utc>,
+    elapsed: Duration,
+}
+
+impl Step {
+    fn finish(name: &'static str, node: &str, ok: bool, detail: String, started: Instant) -> Self {
+        let elapsed = started.elapsed();
+        let at = Utc::now() - chrono::Duration::from_std(elapsed).unwrap_or_else(|_| chrono::Duration::zero());
+        Step { name, node: node.to_string(), ok, detail, at, elapsed }
+    }
+}
+
+pub struct MetaMirror;
+
+impl Check for MetaMirror {
+    const NAME: &'static str = "meta-mirror";
+    const ABOUT: &'static str = "Metadata buddy mirror failover drill (stops the primary meta; asks first)";
+    type Args = MetaMirrorArgs;
+    const RESUMABLE: bool = false;
+    const WATCHABLE: bool = false;
+
+    fn run(cli: &crate::GlobalArgs, cfg: &config::Config, args: &MetaMirrorArgs) -> anyhow::Result<CheckReport> {
+        run(cli, cfg, args)
+    }
+}
+
+pub fn run(cli: &crate::GlobalArgs, cfg: &config::Config, args: &MetaMirrorArgs) -> anyhow::Result<CheckReport> {
+    let writer = single_node(cfg, &args.writer)?;
+    let reader = single_node(cfg, &args.reader)?;
+    let tr = transport::from_config(cfg);
+    // cleanup and the restart of the primary must run even after Ctrl-C cancelled the drill
+    let restore = transport::from_config(&transport::cancel::detached(cfg));
+    let cancel = transport::cancel::of(cfg);
+    let timeout = args.timeout;
+    identity::verify(cfg, &[writer, reader], timeout)?;
+    let file_path = format!("{}/.beeg_mirror_{}", args.mount.trim_end_matches('/'), rand_suffix());
+    let file_q = shell_escape::escape(file_path.clone().into()).to_string();
+    let mut steps: Vec<Step> = Vec::new();
+
+    // 1: create the probe file through the writer client
+    let started = Instant::now();
+    let cmd = format!("dd if=/dev/urandom of={} bs=4K count=1 status=none && sync && echo OK || echo ERR", file_q);
+    let ok = exec_ok(tr.as_ref(), &writer.host, &wrap_timeout(&cmd, timeout));
+    steps.push(Step::finish("create", &writer.name, ok, file_path.clone(), started));
+    if !ok {
+        return Ok(report(cli, &steps));
+    }
+
+    // 2: find out which meta node owns the entry
+    let started = Instant::now();
+    let owner = beegfs::Ctl::new(cfg, writer, timeout).entry_info(&file_path).unwrap_or_default();
+    // `meta01 [ID: 1]` -> `meta01`
+    let owner_primary = owner.metadata_node.as_deref().and_then(|m| m.split_whitespace().next());
+    let primary = match &args.primary {
+        Some(sel) => Some(single_node(cfg, sel)?),
+        None => owner_primary.and_then(|p| cfg.nodes.iter().find(|n| n.name == p || n.host == p)),
+    };
+    let mirrored = owner.metadata_buddy_group.is_some();
+    let detail = match (&owner.metadata_buddy_group, owner_primary) {
+        (Some(g), Some(p)) => format!("buddy group {}, primary {}", g, p),
+        (None, Some(p)) => format!("not mirrored (owner {})", p),
+        _ => "no entry info".to_string(),
+    };
+    steps.push(Step::finish("entry-info", &writer.name, mirrored && primary.is_some(), detail, started));
+    let primary = match primary {
+        Some(p) if mirrored => p,
+        _ => {
+            cleanup(restore.as_ref(), writer, &file_q, timeout, &mut steps);
+            let mut r = report(cli, &steps);
+            if mirrored {
+                r.warn(format!("primary meta node {:?} is not in the inventory; pass --primary", owner_primary));
+            }
+            return Ok(r);
+        }
+    };
+
+    // 3: the reader must see the file before we break anything
+    let started = Instant::now();
+    let stat_cmd = format!("stat -c %s {} >/dev/null 2>&1 && echo OK || echo ERR", file_q);
+    let ok = exec_ok(tr.as_ref(), &reader.host, &wrap_timeout(&stat_cmd, timeout));
+    steps.push(Step::finish("stat-before", &reader.name, ok, String::new(), started));
+    if !ok {
+        cleanup(restore.as_ref(), writer, &file_q, timeout, &mut steps);
+        return Ok(report(cli, &steps));
+    }
+
+    if let Err(e) = identity::verify(cfg, &[primary], timeout) {
+        cleanup(restore.as_ref(), writer, &file_q, timeout, &mut steps);
+        return Err(e);
+    }
+    if !args.yes {
+        let question = format!("Stop beegfs-meta on {} ({}) to exercise the metadata mirror?", primary.name, primary.host);
+        if !prompt::confirm(&question)? {
+            eprintln!("Aborted; no services were touched.");
+            cleanup(restore.as_ref(), writer, &file_q, timeout, &mut steps);
+            // nothing was verified, so never report a clean pass
+            let mut r = report(cli, &steps);
+            r.floor = Severity::Warning;
+            return Ok(r);
+        }
+    }
+
+    // 4: take the primary down
+    let started = Instant::now();
+    let ok = exec_ok(tr.as_ref(), &primary.host, &wrap_timeout("systemctl stop beegfs-meta && echo OK || echo ERR", timeout));
+    steps.push(Step::finish("stop-primary", &primary.name, ok, "systemctl stop beegfs-meta".into(), started));
+
+    // 5: poll the reader until the secondary answers or we give up
+    if ok {
+        let started = Instant::now();
+        let deadline = Duration::from_secs(args.failover_timeout);
+        let mut served = false;
+        while started.elapsed() < deadline && !cancel.is_cancelled() {
+            if exec_ok(tr.as_ref(), &reader.host, &wrap_timeout(&stat_cmd, timeout)) {
+                served = true;
+                break;
+            }
+            let pause = Instant::now();
+            while pause.elapsed() < FAILOVER_POLL && !cancel.is_cancelled() { std::thread::sleep(Duration::from_millis(100)); }
+        }
+        let detail = match (served, cancel.is_cancelled()) {
+            (true, _) => "served by secondary".to_string(),
+            (false, true) => format!("interrupted after {}s", started.elapsed().as_secs()),
+            (false, false) => format!("not statable after {}s", args.failover_timeout),
+        };
+        steps.push(Step::finish("stat-failover", &reader.name, served, detail, started));
+    }
+
+    // 6: always try to bring the primary back
+    let started = Instant::now();
+    let ok = exec_ok(restore.as_ref(), &primary.host, &wrap_timeout("systemctl start beegfs-meta && echo OK || echo ERR", timeout));
+    steps.push(Step::finish("restore-primary", &primary.name, ok, "systemctl start beegfs-meta".into(), started));
+    cleanup(restore.as_ref(), writer, &file_q, timeout, &mut steps);
+    let mut r = report(cli, &steps);
+    if !ok {
+        r.warn(format!("failed to restart beegfs-meta on {}; restore it manually", primary.name));
+    }
+    Ok(r)
+}
+
+fn cleanup(tr: &dyn transport::Transport, writer: &config::Node, file_q: &str, timeout: u64, steps: &mut Vec<Step>) {
+    let started = Instant::now();
+    let cmd = format!("rm -f {} && echo OK || echo ERR", file_q);
+    let ok = exec_ok(tr, &writer.host, &wrap_timeout(&cmd, timeout));
+    steps.push(Step::finish("cleanup", &writer.name, ok, String::new(), started));
+}
+
+fn exec_ok(tr: &dyn transport::Transport, host: &str, cmd: &str) -> bool {
+    matches!(tr.exec(host, cmd), Ok(o) if o.stdout.trim().starts_with("OK"))
+}
+
+/// One result per drill step, in the order they ran.
+fn report(cli: &crate::GlobalArgs, steps: &[Step]) -> CheckReport {
+    let mut report = CheckReport::new(MetaMirror::NAME, &["Step", "At", "Time"]);
+    for s in steps {
+        report.push(CheckResult {
+            check: MetaMirror::NAME,
+            node: s.node.clone(),
+            status: if s.ok { Severity::Ok } else { Severity::Failure },
+            detail: s.detail.clone(),
+            duration: s.elapsed,
+            cells: vec![s.name.to_string(), timefmt::human(s.at, cli.utc), format!("{:.1}s", s.elapsed.as_secs_f64())],
+            data: serde_json::json!({ "step": s.name, "at": timefmt::rfc3339(s.at) }),
+        });
+    }
+    let failed: Vec<&str> = steps.iter().filter(|s| !s.ok).map(|s| s.name).collect();
+    if !failed.is_empty() {
+        report.warn(format!("metadata mirror drill failed at: {}", failed.join(", ")));
+    }
+    report
+}
@@ -0,0 +1,182 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct MgmtdArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Expected sysMgmtdHost (default: the value most nodes agree on)
+    #[arg(long)]
+    pub expect: Option<String>,
+    /// Port to test when a config does not set connMgmtdPortTCP/UDP
+    #[arg(long, default_value_t = 8008)]
+    pub port: u16,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+/// One BeegFS service config on one node and what its mgmtd looks like from there.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MgmtdProbe {
+    pub file: String,
+    pub host: String,
+    pub port_tcp: u16,
+    pub tcp: String,
+    pub udp: String,
+}
+
+fn probe_script(port: u16) -> String {
+    // One line per config: file, sysMgmtdHost, TCP port, TCP result, UDP result; configs
+    // the user may not read get a NOPERM line instead.
+    // UDP is best effort: nc -u only notices an ICMP port-unreachable.
+    format!(r#"for f in /etc/beegfs/beegfs-*.conf; do
+  if [ -e "$f" ] && [ ! -r "$f" ]; then echo "NOPERM $f"; continue; fi
+  [ -r "$f" ] || continue
+  h=$(sed -n 's/^[[:space:]]*sysMgmtdHost[[:space:]]*=[[:space:]]*\([^[:space:]#]*\).*/\1/p' "$f" | tail -n1)
+  [ -n "$h" ] || continue
+  t=$(sed -n 's/^[[:space:]]*connMgmtdPortTCP[[:space:]]*=[[:space:]]*\([0-9]*\).*/\1/p' "$f" | tail -n1)
+  u=$(sed -n 's/^[[:space:]]*connMgmtdPortUDP[[:space:]]*=[[:space:]]*\([0-9]*\).*/\1/p' "$f" | tail -n1)
+  t=${{t:-{port}}}; u=${{u:-{port}}}
+  tcp=$(timeout 3 bash -c "</dev/tcp/$h/$t" >/dev/null 2>&1 && echo open || echo closed)
+  if command -v nc >/dev/null 2>&1; then udp=$(nc -z -u -w 2 "$h" "$u" >/dev/null 2>&1 && echo open || echo closed); else udp=n/a; fi
+  echo "MGMTD ${{f##*/}} $h $t $tcp $udp"
+done"#)
+}
+
+pub(crate) fn parse_probe_output(text: &str) -> Vec<MgmtdProbe> {
+    text.lines()
+        .filter_map(|l| {
+            let f: Vec<&str> = l.split_whitespace().collect();
+            if f.len() != 6 || f[0] != "MGMTD" { return None; }
+            Some(MgmtdProbe {
+                file: f[1].to_string(),
+                host: f[2].to_string(),
+                port_tcp: f[3].parse().ok()?,
+                tcp: f[4].to_string(),
+                udp: f[5].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Configs the probe found but could not read.
+pub(crate) fn parse_denied(text: &str) -> Vec<String> {
+    text.lines().filter_map(|l| l.strip_prefix("NOPERM ")).map(|f| f.trim().to_string()).collect()
+}
+
+/// The sysMgmtdHost named by most configs; `None` on a tie.
+pub(crate) fn majority_host<'a>(hosts: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for h in hosts { *counts.entry(h).or_default() += 1; }
+    let max = *counts.values().max()?;
+    let mut top = counts.iter().filter(|(_, c)| **c == max);
+    let first = top.next()?.0.to_string();
+    if top.next().is_some() { None } else { Some(first) }
+}
+
+/// Probes of the readable configs, and the unreadable ones.
+type ProbeResult = Result<(Vec<MgmtdProbe>, Vec<String>), String>;
+
+pub struct Mgmtd;
+
+impl Check for Mgmtd {
+    const NAME: &'static str = "mgmtd";
+    const ABOUT: &'static str = "Verify nodes point at the same, reachable management daemon";
+    type Args = MgmtdArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &MgmtdArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &MgmtdArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let script = wrap_timeout(&probe_script(args.port), args.timeout);
+    let results: Vec<(&config::Node, ProbeResult, Duration)> = super::per_node(cfg, &nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &script).map(|o| (parse_probe_output(&o.stdout), parse_denied(&o.stdout))).map_err(|e| super::exec_error(&e))
+    });
+
+    let expected = args.expect.clone().or_else(|| majority_host(
+        results.iter().filter_map(|(_, r, _)| r.as_ref().ok()).flat_map(|(p, _)| p).map(|p| p.host.as_str())
+    ));
+
+    let mut report = CheckReport::new(Mgmtd::NAME, &["Config", "sysMgmtdHost", "TCP", "UDP"]);
+    let row = |n: &config::Node, status, detail: String, duration, p: Option<&MgmtdProbe>| {
+        let (file, host, tcp, udp) = match p {
+            Some(p) => (p.file.clone(), p.host.clone(), p.tcp.clone(), p.udp.clone()),
+            None => ("-".into(), "-".into(), "-".into(), "-".into()),
+        };
+        CheckResult {
+            check: Mgmtd::NAME,
+            node: n.name.clone(),
+            status,
+            detail,
+            duration,
+            data: serde_json::json!({ "config": file, "mgmtd_host": host, "tcp": tcp, "udp": udp }),
+            cells: vec![file, host, tcp, udp],
+        }
+    };
+    let (mut wrong, mut unreachable) = (Vec::new(), Vec::new());
+    for (n, res, took) in &results {
+        match res {
+            Err(e) => report.push(row(n, Severity::Failure, e.clone(), *took, None)),
+            Ok((probes, denied)) if probes.is_empty() && !denied.is_empty() => {
+                report.push(row(n, Severity::NoPerm, format!("cannot read {}: permission denied", denied.join(", ")), *took, None));
+            }
+            Ok((probes, _)) if probes.is_empty() => report.push(row(n, Severity::Warning, "no readable beegfs config with sysMgmtdHost".into(), *took, None)),
+            Ok((probes, _)) => {
+                for p in probes {
+                    let (sev, note) = if expected.as_deref().is_some_and(|e| e != p.host) {
+                        wrong.push(n.name.as_str());
+                        (Severity::Failure, format!("expected {}", expected.as_deref().unwrap_or("?")))
+                    } else if p.tcp != "open" {
+                        (Severity::Failure, format!("TCP {} unreachable", p.port_tcp))
+                    } else if p.udp == "closed" {
+                        (Severity::Warning, "UDP port unreachable".to_string())
+                    } else {
+                        (Severity::Ok, String::new())
+                    };
+                    if p.tcp == "closed" { unreachable.push(n.name.as_str()); }
+                    report.push(row(n, sev, note, *took, Some(p)));
+                }
+            }
+        }
+    }
+
+    if !wrong.is_empty() { report.warn_about(format!("nodes pointing at the wrong mgmtd: {}", wrong.join(", ")), NoteKind::Node, &wrong); }
+    if !unreachable.is_empty() { report.warn_about(format!("mgmtd unreachable over TCP from: {}", unreachable.join(", ")), NoteKind::Node, &unreachable); }
+    if expected.is_none() && results.iter().any(|(_, r, _)| r.as_ref().is_ok_and(|(p, _)| !p.is_empty())) {
+        report.warn("nodes disagree on sysMgmtdHost and no --expect was given");
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_probe() {
+        let out = "MGMTD beegfs-client.conf mgmt01 8008 open closed\nnoise\nMGMTD beegfs-storage.conf mgmt01 9008 closed n/a\n";
+        let p = parse_probe_output(out);
+        assert_eq!(p.len(), 2);
+        assert_eq!(p[1].port_tcp, 9008);
+        assert_eq!(p[1].udp, "n/a");
+        assert_eq!(parse_denied("NOPERM /etc/beegfs/beegfs-meta.conf\n"), vec!["/etc/beegfs/beegfs-meta.conf"]);
+        assert!(parse_denied(out).is_empty());
+    }
+
+    #[test]
+    fn majority() {
+        assert_eq!(majority_host(["a", "b", "a"].into_iter()), Some("a".to_string()));
+        assert_eq!(majority_host(["a", "b"].into_iter()), None);
+        assert_eq!(majority_host(std::iter::empty()), None);
+    }
+}
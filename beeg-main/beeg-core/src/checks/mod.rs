@@ -0,0 +1,747 @@
+use crate::notes::NoteKind;
+use crate::{config, transport};
+use clap::Args;
+use comfy_table::{Table, presets::UTF8_FULL};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+pub mod all;
+pub mod buddy_groups;
+pub mod capacity;
+pub mod client;
+pub mod conn_config;
+pub mod disks;
+pub mod dns;
+pub mod firewall;
+pub mod gds;
+pub mod gpu_inventory;
+pub mod helperd;
+pub mod ib_fabric;
+pub mod memory;
+pub mod meta;
+pub mod mgmtd;
+pub mod netdev;
+pub mod pool_limits;
+pub mod meta_mirror;
+pub mod rdma;
+pub mod sysctl;
+pub mod target_fs;
+pub mod time;
+pub mod versions;
+mod changes;
+pub mod compat;
+mod registry;
+mod junit;
+mod report;
+mod summary;
+mod watch;
+
+pub use compat::Requirement;
+pub use registry::{register, Check, CheckCmd, Registration};
+pub use report::{CheckReport, CheckResult, Warning};
+
+/// Every built-in check, in `beeg check --help` order. New checks go here.
+pub static REGISTRY: &[Registration] = &[
+    register::<NvidiaDriver>(),
+    register::<Cuda>(),
+    register::<NvidiaFs>(),
+    register::<gds::Gds>(),
+    register::<gpu_inventory::GpuInventory>(),
+    register::<Ofed>(),
+    register::<ib_fabric::IbFabric>(),
+    register::<netdev::Netdev>(),
+    register::<firewall::Firewalls>(),
+    register::<dns::Dns>(),
+    register::<conn_config::ConnConfig>(),
+    register::<sysctl::Sysctl>(),
+    register::<memory::Memory>(),
+    register::<client::ClientMount>(),
+    register::<StorageTarget>(),
+    register::<disks::Disks>(),
+    register::<target_fs::TargetFs>(),
+    register::<meta::Meta>(),
+    register::<mgmtd::Mgmtd>(),
+    register::<all::All>(),
+    register::<meta_mirror::MetaMirror>(),
+    register::<buddy_groups::BuddyGroups>(),
+    register::<time::Time>(),
+    register::<rdma::Rdma>(),
+    register::<versions::Versions>(),
+    register::<capacity::Capacity>(),
+    register::<pool_limits::PoolLimits>(),
+    register::<helperd::Helperd>(),
+];
+
+#[derive(Debug, Args)]
+pub struct ClientMountArgs {
+    /// Target mountpoint (e.g., /mnt/beegfs)
+    #[arg(long)]
+    pub mount: String,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+    /// Print the result table (or JSON) without the live TUI; implied when stdout is not a terminal
+    #[arg(long)]
+    pub no_tui: bool,
+    /// Also write the results to FILE once the probes finish or the TUI quits: CSV for .csv, TSV for .tsv, else JSON
+    #[arg(long, value_name = "FILE")]
+    pub export: Option<std::path::PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct StorageTargetArgs {
+    /// Node to run the check on (name/host/label); must resolve to one node
+    #[arg(long, visible_alias = "node")]
+    pub selector: String,
+    /// Target IDs: comma-separated or 'all'
+    #[arg(long, default_value = "all")]
+    pub targets: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct NvidiaArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Platform requirements, e.g. `cuda>=12.2 driver>=535 ofed>=23.10`; only this check's component applies
+    #[arg(long, num_args = 1.., value_parser = compat::parse_requirement)]
+    pub require: Vec<Requirement>,
+}
+
+#[derive(Debug, Args)]
+pub struct CudaArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Platform requirements, e.g. `cuda>=12.2 driver>=535 ofed>=23.10`; only this check's component applies
+    #[arg(long, num_args = 1.., value_parser = compat::parse_requirement)]
+    pub require: Vec<Requirement>,
+}
+
+#[derive(Debug, Args)]
+pub struct NvidiaFsArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Platform requirements, e.g. `cuda>=12.2 driver>=535 ofed>=23.10`; only this check's component applies
+    #[arg(long, num_args = 1.., value_parser = compat::parse_requirement)]
+    pub require: Vec<Requirement>,
+}
+
+#[derive(Debug, Args)]
+pub struct OfedArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Platform requirements, e.g. `cuda>=12.2 driver>=535 ofed>=23.10`; only this check's component applies
+    #[arg(long, num_args = 1.., value_parser = compat::parse_requirement)]
+    pub require: Vec<Requirement>,
+}
+
+/// How bad a check result is; the worst one across a run decides the exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Severity {
+    #[default]
+    Ok,
+    Warning,
+    /// The SSH user was not allowed to look, so nothing is known about it either way
+    NoPerm,
+    Failure,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self { Severity::Ok => "OK", Severity::Warning => "WARN", Severity::NoPerm => "NOPERM", Severity::Failure => "FAIL" }
+    }
+
+    /// Process exit code: 0 ok, 1 warnings, 2 failures, 4 when permission was denied and
+    /// nothing failed (3 is what usage stats record for errors); `strict` turns warnings
+    /// into failures.
+    pub fn exit_code(self, strict: bool) -> u8 {
+        match self {
+            Severity::Ok => 0,
+            Severity::Warning if !strict => 1,
+            Severity::NoPerm => 4,
+            _ => 2,
+        }
+    }
+
+    /// Service state and exit code for `--output nagios`: permission denied is UNKNOWN, since
+    /// nothing is known either way.
+    pub fn nagios(self, strict: bool) -> (&'static str, u8) {
+        match self {
+            Severity::Ok => ("OK", 0),
+            Severity::Warning if !strict => ("WARNING", 1),
+            Severity::NoPerm => ("UNKNOWN", 3),
+            _ => ("CRITICAL", 2),
+        }
+    }
+}
+
+/// What to do about a NOPERM result.
+pub const NOPERM_HINT: &str = "set `\"become\": true` in the config to probe through sudo, or connect as a user that may read it";
+
+/// Whether a probe's stderr says the remote user lacks permission, as opposed to the thing
+/// being missing. SSH's own `Permission denied (publickey)` is a login failure, not this.
+pub fn permission_denied(stderr: &str) -> bool {
+    const DENIALS: &[&str] = &["Permission denied", "Operation not permitted", "must be run as root", "Access denied", "are you root"];
+    stderr.lines().any(|l| DENIALS.iter().any(|d| l.contains(d)) && !l.contains("(publickey"))
+}
+
+/// A probe the SSH user may not run or read; reported as NOPERM rather than as a failure.
+#[derive(Debug)]
+pub(crate) struct PermissionDenied {
+    pub node: String,
+    /// The command or file that was refused
+    pub what: String,
+}
+
+impl std::fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} on {}: permission denied; {}", self.what, self.node, NOPERM_HINT)
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+/// `Err(PermissionDenied)` when `out` was refused for lack of permission.
+pub fn check_permission(out: &transport::ExecOutput, node: &str, what: &str) -> anyhow::Result<()> {
+    if permission_denied(&out.stderr) {
+        return Err(PermissionDenied { node: node.to_string(), what: what.to_string() }.into());
+    }
+    Ok(())
+}
+
+/// Detail for a probe that did not complete: a timeout reads differently from a command
+/// that could not be run, as it may well succeed on a quieter node or with more time.
+pub fn exec_error(e: &anyhow::Error) -> String {
+    if transport::timed_out(e) { e.to_string() } else { format!("exec failed: {}", e) }
+}
+
+/// NOPERM for an error that comes from a refused probe, else a failure.
+pub fn error_severity(e: &anyhow::Error) -> Severity {
+    if e.chain().any(|c| c.is::<PermissionDenied>()) { Severity::NoPerm } else { Severity::Failure }
+}
+
+pub fn run_check_cmd(cli: &crate::GlobalArgs, cfg: &config::Config, cmd: &CheckCmd) -> anyhow::Result<Severity> {
+    if cmd.name == registry::LIST {
+        list_checks(cli)?;
+        return Ok(Severity::Ok);
+    }
+    if let Some(every) = cmd.watch() {
+        if matches!(cli.output, crate::Output::Nagios) { anyhow::bail!("--output nagios reports one run; it cannot be combined with --watch"); }
+        return watch::run(cli, cfg, cmd, every);
+    }
+    let started = chrono::Utc::now();
+    report::stream_results(matches!(cli.output, crate::Output::Jsonl));
+    let outcome = cmd.run(cli, cfg);
+    report::stream_results(false);
+    let mut report = match outcome {
+        Ok(r) => r,
+        Err(e) => {
+            summary::write_all(&cmd.report_files(), &cmd.name, Err(&e), started, cli.strict)?;
+            return Err(e);
+        }
+    };
+    crate::telemetry::record_sections(&report);
+    crate::history::record(&report, started);
+    crate::alerts::process(cfg, &report);
+    report.sort_nodes(cfg, cli.sort);
+    let recent = match cmd.changes_window() {
+        // a plugin run has nowhere to show them and a deadline to keep
+        Some(_) if matches!(cli.output, crate::Output::Nagios) => None,
+        Some(hours) if report.severity() == Severity::Failure => Some((hours, changes::gather(cfg, &report, hours))).filter(|(_, c)| !c.is_empty()),
+        _ => None,
+    };
+    if let Some((_, c)) = &recent { report.data.insert("recent_changes".into(), changes::to_json(c)); }
+    report.render(cli)?;
+    if let (crate::Output::Human, Some((hours, c))) = (cli.output, &recent) { changes::print(cfg, cli.sort, c, *hours); }
+    summary::write_all(&cmd.report_files(), &cmd.name, Ok(&report), started, cli.strict)?;
+    Ok(report.severity())
+}
+
+fn list_checks(cli: &crate::GlobalArgs) -> anyhow::Result<()> {
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Check", "Description"]);
+            for r in REGISTRY { table.add_row(vec![r.name, r.about]); }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let arr: Vec<_> = REGISTRY.iter().map(|r| serde_json::json!({"name": r.name, "about": r.about})).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a selector that must match exactly one node.
+pub fn single_node<'a>(cfg: &'a config::Config, selector: &str) -> anyhow::Result<&'a config::Node> {
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.len() != 1 {
+        anyhow::bail!("selector '{}' must resolve to exactly one node (got {})", selector, nodes.len());
+    }
+    Ok(nodes[0])
+}
+
+pub fn wrap_timeout(cmd: &str, seconds: u64) -> String {
+    // Use GNU coreutils timeout; if unavailable on remote, command may fail quickly
+    format!("timeout {}s sh -lc {}", seconds, shell_escape::escape(cmd.into()))
+}
+
+/// Run `probe` for each node, at most `config::parallel` at once, with a transport made from
+/// `cfg`; results in node order, each with how long it took. A probe that panics reads as an error.
+pub fn per_node<'a, T: Send>(cfg: &config::Config, nodes: &[&'a config::Node], probe: impl Fn(&dyn transport::Transport, &config::Node) -> Result<T, String> + Sync) -> Vec<(&'a config::Node, Result<T, String>, Duration)> {
+    let mut slots: Vec<Option<(Result<T, String>, Duration)>> = nodes.iter().map(|_| None).collect();
+    per_node_each(cfg, nodes, probe, |i, _, res, took| slots[i] = Some((res, took)));
+    nodes.iter().zip(slots).map(|(n, slot)| {
+        let (res, took) = slot.unwrap_or_else(|| (Err("probe thread panicked".into()), Duration::ZERO));
+        (*n, res, took)
+    }).collect()
+}
+
+/// `per_node`, handing each node's outcome (with its index in `nodes`) to `each` on this
+/// thread as soon as its probe is done rather than once they all are, so results can be
+/// pushed, and printed with `--output jsonl`, as they come in. A fixed pool of workers takes
+/// the nodes in order, so a large cluster does not open an ssh session to every node at once.
+pub fn per_node_each<'a, T: Send>(
+    cfg: &config::Config,
+    nodes: &[&'a config::Node],
+    probe: impl Fn(&dyn transport::Transport, &config::Node) -> Result<T, String> + Sync,
+    mut each: impl FnMut(usize, &'a config::Node, Result<T, String>, Duration),
+) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    // the check being run, for the records of each node's commands
+    let context = crate::logging::context();
+    std::thread::scope(|s| {
+        for _ in 0..config::parallel(cfg).min(nodes.len()) {
+            let (probe, next, tx, context) = (&probe, &next, tx.clone(), &context);
+            s.spawn(move || loop {
+                let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let Some(n) = nodes.get(i) else { return };
+                let mut fields = context.clone();
+                fields.extend([("node", n.name.clone()), ("host", n.host.clone())]);
+                let _scope = crate::logging::enter(fields);
+                let started = Instant::now();
+                let tr = transport::from_config(cfg);
+                let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| probe(tr.as_ref(), n)))
+                    .unwrap_or_else(|_| Err("probe thread panicked".into()));
+                let took = started.elapsed();
+                match &res {
+                    Ok(_) => log::info!(node = n.name.as_str(), host = n.host.as_str(), duration_ms = took.as_millis() as u64; "node probed"),
+                    Err(e) => log::info!(node = n.name.as_str(), host = n.host.as_str(), duration_ms = took.as_millis() as u64; "node probe failed: {}", e),
+                }
+                if tx.send((i, res, took)).is_err() { return; }
+            });
+        }
+        drop(tx);
+        for (i, res, took) in rx { each(i, nodes[i], res, took); }
+    });
+}
+
+pub fn rand_suffix() -> String {
+    use rand::RngCore;
+    let mut rng = rand::rngs::OsRng;
+    let mut buf = [0u8; 4];
+    rng.fill_bytes(&mut buf);
+    hex::encode(buf)
+}
+
+
+/// (target id, present, state, service active) for one storage target
+pub(crate) type TargetRow = (String, bool, String, bool);
+
+/// Query beegfs-storage service state and target states on `node`, limited to `targets` ("all" or CSV).
+pub(crate) fn collect_storage_targets(cfg: &config::Config, node: &config::Node, targets: &str, timeout: u64) -> anyhow::Result<(Vec<TargetRow>, bool)> {
+    let tr = transport::from_config(cfg);
+
+    // Check service
+    let svc_cmd = "systemctl is-active beegfs-storage >/dev/null 2>&1 && echo active || echo inactive";
+    let svc = tr.exec_idempotent(&node.host, &wrap_timeout(svc_cmd, timeout))?;
+    let service_active = svc.stdout.trim().starts_with("active");
+
+    // reachability/consistency per target, e.g. Online/Good
+    let ctl = crate::beegfs::Ctl::new(cfg, node, timeout);
+    let found: BTreeMap<String, String> = ctl.target_states(crate::beegfs::NodeType::Storage)?.into_iter()
+        .map(|(id, st)| (id, format!("{}/{}", st.reachability, st.consistency)))
+        .collect();
+
+    // Desired target set
+    let target_list: Vec<String> = if targets.eq_ignore_ascii_case("all") {
+        found.keys().cloned().collect()
+    } else {
+        targets.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+    };
+
+    // Build result rows
+    let mut rows = Vec::new();
+    for tid in target_list {
+        let present = found.contains_key(&tid);
+        let state = found.get(&tid).cloned().unwrap_or_else(|| "missing".to_string());
+        rows.push((tid, present, state, service_active));
+    }
+    Ok((rows, service_active))
+}
+
+/// Missing target IDs and present targets grouped by state.
+pub(crate) fn storage_target_issues(rows: &[TargetRow]) -> (Vec<&str>, BTreeMap<&str, Vec<&str>>) {
+    let missing: Vec<&str> = rows.iter().filter(|(_,p,_,_)| !*p).map(|(t,_,_,_)| t.as_str()).collect();
+    let mut states: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (tid, present, state, _) in rows { if *present { states.entry(state.as_str()).or_default().push(tid.as_str()); } }
+    (missing, states)
+}
+
+/// Inactive service, missing or offline targets fail; a target in another state than most only warns.
+pub fn storage_target_report(cfg: &config::Config, node: &config::Node, targets: &str, timeout: u64) -> anyhow::Result<CheckReport> {
+    let started = Instant::now();
+    let (rows, service_active) = collect_storage_targets(cfg, node, targets, timeout)?;
+    let duration = started.elapsed();
+    let (missing, states) = storage_target_issues(&rows);
+    let common = states.iter().max_by_key(|(_, ids)| ids.len()).map(|(st, _)| *st).unwrap_or("-");
+
+    let mut report = CheckReport::new(StorageTarget::NAME, &["Target", "Present", "State", "Service"]);
+    for (tid, present, state, svc) in &rows {
+        let (status, detail) = if !svc {
+            (Severity::Failure, "beegfs-storage inactive".to_string())
+        } else if !present {
+            (Severity::Failure, "target missing".to_string())
+        } else if state.to_ascii_lowercase().contains("offline") {
+            (Severity::Failure, format!("target {}", state))
+        } else if states.len() > 1 && state != common {
+            (Severity::Warning, format!("state differs from most targets ({})", common))
+        } else {
+            (Severity::Ok, String::new())
+        };
+        report.push(CheckResult {
+            check: StorageTarget::NAME,
+            node: node.name.clone(),
+            status,
+            detail,
+            duration,
+            cells: vec![tid.clone(), if *present { "YES" } else { "NO" }.into(), state.clone(), if *svc { "active" } else { "inactive" }.into()],
+            data: serde_json::json!({ "target": tid, "present": present, "state": state, "service_active": svc }),
+        });
+    }
+
+    if !missing.is_empty() { report.warn_about(format!("missing targets: {}", missing.join(", ")), NoteKind::Target, &missing); }
+    if states.len() > 1 {
+        report.warnings.push(Warning {
+            message: "target state mismatch:".into(),
+            lines: states.iter().map(|(st, ids)| format!("{}: {}", st, ids.join(", "))).collect(),
+            notes: Vec::new(),
+        });
+    }
+    if !service_active {
+        report.warn_about(format!("beegfs-storage service is inactive on {}", node.name), NoteKind::Node, &[&node.name]);
+        report.floor = Severity::Failure;
+    }
+    Ok(report)
+}
+
+pub struct StorageTarget;
+
+impl Check for StorageTarget {
+    const NAME: &'static str = "storage-target";
+    const ABOUT: &'static str = "Storage target health check from a single node";
+    type Args = StorageTargetArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &StorageTargetArgs) -> anyhow::Result<CheckReport> {
+        let node = single_node(cfg, &args.selector)?;
+        storage_target_report(cfg, node, &args.targets, args.timeout)
+    }
+}
+
+/// What one node reported for a version check.
+#[derive(Debug, Clone)]
+pub(crate) struct VersionRow {
+    pub node: String,
+    pub host: String,
+    pub version: String,
+    pub ok: bool,
+    pub stderr: String,
+    pub duration: Duration,
+}
+
+/// A version probe and how it is labelled in tables, JSON and warnings.
+pub struct VersionCheck {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub column: &'static str,
+    pub json_key: &'static str,
+    pub query: &'static str,
+    /// `(uname -m pattern, query)` used instead of `query` on matching nodes
+    pub per_arch: &'static [(&'static str, &'static str)],
+    pub ignore: &'static [&'static str],
+}
+
+impl VersionCheck {
+    /// Shell snippet run on each node; picks the per-arch query on the node itself.
+    pub fn script(&self) -> String {
+        if self.per_arch.is_empty() { return self.query.to_string(); }
+        let mut s = String::from("case \"$(uname -m)\" in\n");
+        for (arch, q) in self.per_arch { s += &format!("  {}) {} ;;\n", arch, q); }
+        s += &format!("  *) {} ;;\nesac", self.query);
+        s
+    }
+}
+
+pub const NVIDIA_DRIVER: VersionCheck = VersionCheck {
+    name: "nvidia-driver",
+    label: "NVIDIA driver",
+    column: "Driver",
+    json_key: "driver",
+    query: "nvidia-smi --query-gpu=driver_version --format=csv,noheader 2>/dev/null | head -n1 || modinfo -F version nvidia 2>/dev/null | head -n1 || echo unknown",
+    // Jetson/Tegra boards have no nvidia-smi; report the L4T release instead
+    per_arch: &[("aarch64|arm64", r#"v=$(nvidia-smi --query-gpu=driver_version --format=csv,noheader 2>/dev/null | head -n1); [ -n "$v" ] || v=$(modinfo -F version nvidia 2>/dev/null | head -n1); [ -n "$v" ] || v=$(sed -n 's/^# R\([0-9]*\) (release), REVISION: \([0-9.]*\).*/L4T-R\1.\2/p' /etc/nv_tegra_release 2>/dev/null); echo "${v:-unknown}""#)],
+    ignore: &["unknown"],
+};
+
+pub const CUDA: VersionCheck = VersionCheck {
+    name: "cuda",
+    label: "CUDA",
+    column: "CUDA",
+    json_key: "cuda",
+    query: "nvidia-smi --query-gpu=cuda_version --format=csv,noheader 2>/dev/null | head -n1 || nvcc --version 2>/dev/null | awk '/release/ {print $NF}' | sed 's/^V//' | head -n1 || awk '{print $3}' /usr/local/cuda/version.txt 2>/dev/null | head -n1 || echo unknown",
+    // aarch64 toolkits keep nvcc off PATH and ship version.json instead of version.txt
+    per_arch: &[("aarch64|arm64", r#"v=$(nvidia-smi --query-gpu=cuda_version --format=csv,noheader 2>/dev/null | head -n1); [ -n "$v" ] || v=$(/usr/local/cuda/bin/nvcc --version 2>/dev/null | awk '/release/ {print $NF}' | sed 's/^V//' | head -n1); [ -n "$v" ] || v=$(grep -A3 '"cuda"' /usr/local/cuda/version.json 2>/dev/null | sed -n 's/.*"version" *: *"\([^"]*\)".*/\1/p' | head -n1); echo "${v:-unknown}""#)],
+    ignore: &["unknown"],
+};
+
+pub const NVIDIA_FS: VersionCheck = VersionCheck {
+    name: "nvidia-fs",
+    label: "nvidia-fs",
+    column: "nvidia-fs",
+    json_key: "nvidia_fs",
+    query: "modinfo -F version nvidia_fs 2>/dev/null | head -n1 || modinfo -F version nvidia-fs 2>/dev/null | head -n1 || lsmod | awk '$1 ~ /^(nvidia_fs|nvidia-fs)$/ {print \"loaded\"}' | head -n1 || echo unknown",
+    per_arch: &[],
+    ignore: &["unknown", "loaded"],
+};
+
+pub(crate) const OFED: VersionCheck = VersionCheck {
+    name: "ofed",
+    label: "OFED/RDMA",
+    column: "OFED/RDMA",
+    json_key: "ofed",
+    query: "ofed_info -s 2>/dev/null | head -n1 || modinfo -F version mlx5_core 2>/dev/null | head -n1 || modinfo -F version mlx5_ib 2>/dev/null | head -n1 || ibv_devinfo --version 2>/dev/null | head -n1 || echo unknown",
+    per_arch: &[],
+    ignore: &["unknown"],
+};
+
+pub(crate) fn collect_versions(cfg: &config::Config, selector: &str, check: &VersionCheck) -> Vec<VersionRow> {
+    let tr = transport::from_config(cfg);
+    let nodes = config::select_nodes(cfg, selector);
+
+    let mut results = Vec::new();
+    for n in nodes {
+        let started = Instant::now();
+        let out = tr.exec_idempotent(&n.host, &check.script());
+        let (version, ok, stderr) = match out {
+            Ok(v) => {
+                let v_str = v.stdout.trim();
+                let ver = if v_str.is_empty() { "unknown" } else { v_str };
+                (ver.to_string(), ver != "unknown", v.stderr)
+            }
+            Err(e) => ("error".into(), false, e.to_string()),
+        };
+        results.push(VersionRow { node: n.name.clone(), host: n.host.clone(), version, ok, stderr, duration: started.elapsed() });
+    }
+    results
+}
+
+/// Missing components fail; nodes on another version than most only warn. With requirements
+/// for this component, versions are held against them instead and may differ between nodes.
+pub(crate) fn version_report(cfg: &config::Config, selector: &str, check: &VersionCheck, require: &[Requirement]) -> CheckReport {
+    let rows = collect_versions(cfg, selector, check);
+    let (missing, versions) = version_issues(&rows, check.ignore);
+    let common = versions.iter().max_by_key(|(_, nodes)| nodes.len()).map(|(v, _)| *v).unwrap_or("-");
+    let require: Vec<&Requirement> = require.iter().filter(|r| r.component == check.json_key).collect();
+    let (mut below, mut unversioned) = (Vec::new(), Vec::new());
+
+    let mut report = CheckReport::new(check.name, &["Host", check.column]);
+    for r in &rows {
+        let unmet: Vec<String> = if r.ok { require.iter().filter(|q| q.holds(&r.version) == Some(false)).map(|q| q.to_string()).collect() } else { Vec::new() };
+        let (status, detail) = if !r.ok {
+            (Severity::Failure, format!("{} not found", check.label))
+        } else if !unmet.is_empty() {
+            below.push(r.node.as_str());
+            (Severity::Failure, format!("needs {}", unmet.join(", ")))
+        } else if !require.is_empty() && compat::numeric(&r.version).is_none() {
+            unversioned.push(r.node.as_str());
+            (Severity::Warning, format!("cannot compare '{}' with the requirements", r.version))
+        } else if !require.is_empty() {
+            (Severity::Ok, String::new())
+        } else if versions.len() > 1 && versions.contains_key(r.version.as_str()) && r.version != common {
+            (Severity::Warning, format!("most nodes have {}", common))
+        } else {
+            (Severity::Ok, String::new())
+        };
+        report.push(CheckResult {
+            check: check.name,
+            node: r.node.clone(),
+            status,
+            detail,
+            duration: r.duration,
+            cells: vec![r.host.clone(), r.version.clone()],
+            data: serde_json::json!({ "host": r.host, check.json_key: r.version, "ok": r.ok, "stderr": r.stderr }),
+        });
+    }
+
+    if !missing.is_empty() {
+        report.warn_about(format!("{} missing on {} node(s): {}", check.label, missing.len(), missing.join(", ")), NoteKind::Node, &missing);
+    }
+    if !require.is_empty() {
+        let text: Vec<String> = require.iter().map(|q| q.to_string()).collect();
+        report.data.insert("requirements".into(), serde_json::json!(text));
+        if !below.is_empty() {
+            report.warn_about(format!("{} does not meet {} on {} node(s): {}", check.label, text.join(", "), below.len(), below.join(", ")), NoteKind::Node, &below);
+        }
+        if !unversioned.is_empty() {
+            report.warn(format!("{} reports no version number on {}; the requirements were not checked there", check.label, unversioned.join(", ")));
+        }
+    } else if versions.len() > 1 {
+        // the odd ones out are the nodes not on the most common version
+        let most = versions.values().map(Vec::len).max().unwrap_or(0);
+        report.warnings.push(Warning {
+            message: format!("{} version mismatch across nodes:", check.label),
+            lines: versions.iter().map(|(ver, nodes)| format!("{}: {}", ver, nodes.join(", "))).collect(),
+            notes: versions.values().filter(|n| n.len() < most)
+                .map(|n| (NoteKind::Node, n.iter().map(|s| s.to_string()).collect())).collect(),
+        });
+    }
+    report
+}
+
+/// Implements `Check` for a version probe: unit struct, subcommand name, args, `VersionCheck` const.
+macro_rules! version_check {
+    ($ty:ident, $args:ty, $spec:ident, $about:literal) => {
+        pub struct $ty;
+
+        impl Check for $ty {
+            const NAME: &'static str = $spec.name;
+            const ABOUT: &'static str = $about;
+            type Args = $args;
+
+            fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &$args) -> anyhow::Result<CheckReport> {
+                Ok(version_report(cfg, &args.selector, &$spec, &args.require))
+            }
+        }
+    };
+}
+
+version_check!(NvidiaDriver, NvidiaArgs, NVIDIA_DRIVER, "Check NVIDIA driver presence and version on nodes");
+version_check!(Cuda, CudaArgs, CUDA, "Check CUDA toolkit/version on nodes");
+version_check!(NvidiaFs, NvidiaFsArgs, NVIDIA_FS, "Check NVIDIA GPUDirect Storage (nvidia-fs) kernel module");
+version_check!(Ofed, OfedArgs, OFED, "Check OFED / RDMA stack version");
+
+/// Nodes where the component is missing, and OK nodes grouped by version (ignored versions skipped).
+pub(crate) fn version_issues<'a>(results: &'a [VersionRow], ignore_versions: &[&str]) -> (Vec<&'a str>, BTreeMap<&'a str, Vec<&'a str>>) {
+    // Missing/not found
+    let missing: Vec<&str> = results.iter().filter(|r| !r.ok).map(|r| r.node.as_str()).collect();
+
+    // Version groups among OK nodes (excluding ignored versions)
+    let mut versions: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for r in results.iter().filter(|r| r.ok) {
+        if ignore_versions.iter().any(|ig| ig.eq_ignore_ascii_case(&r.version)) || r.version.is_empty() {
+            continue;
+        }
+        versions.entry(r.version.as_str()).or_default().push(r.node.as_str());
+    }
+    (missing, versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_permission_denied() {
+        assert!(permission_denied("cat: /etc/beegfs/beegfs-client.conf: Permission denied\n"));
+        assert!(permission_denied("Error: must be run as root"));
+        assert!(!permission_denied("root@n1: Permission denied (publickey,password)."));
+        assert!(!permission_denied("cat: /etc/beegfs/beegfs-client.conf: No such file or directory"));
+        let out = transport::ExecOutput { stdout: String::new(), stderr: "Permission denied".into(), status: Some(1) };
+        let e = check_permission(&out, "n1", "beegfs-ctl --listtargets").unwrap_err();
+        assert_eq!(error_severity(&e.context("storage targets")), Severity::NoPerm);
+        assert_eq!(error_severity(&anyhow::anyhow!("no targets")), Severity::Failure);
+    }
+
+    #[test]
+    fn version_check_arch_dispatch() {
+        let s = CUDA.script();
+        assert!(s.starts_with("case \"$(uname -m)\" in"));
+        assert!(s.contains("aarch64|arm64) ") && s.contains("*) nvidia-smi"));
+        assert_eq!(OFED.script(), OFED.query);
+    }
+
+    #[test]
+    fn per_node_order() {
+        let cfg: config::Config = serde_json::from_value(serde_json::json!({
+            "transport": "local",
+            "nodes": [{"name": "a", "host": "localhost"}, {"name": "b", "host": "localhost"}, {"name": "c", "host": "localhost"}],
+        })).unwrap();
+        let nodes: Vec<&config::Node> = cfg.nodes.iter().collect();
+        let got = per_node(&cfg, &nodes, |tr, n| {
+            if n.name == "b" { panic!("boom"); }
+            // the first node finishes last
+            let pause = if n.name == "a" { "0.2" } else { "0" };
+            tr.exec(&n.host, &format!("sleep {}; echo {}", pause, n.name)).map(|o| o.stdout.trim().to_string()).map_err(|e| e.to_string())
+        });
+        let got: Vec<(&str, Result<String, String>)> = got.into_iter().map(|(n, r, _)| (n.name.as_str(), r)).collect();
+        assert_eq!(got, vec![("a", Ok("a".into())), ("b", Err("probe thread panicked".into())), ("c", Ok("c".into()))]);
+    }
+
+    #[test]
+    fn per_node_each_as_finished() {
+        let cfg: config::Config = serde_json::from_value(serde_json::json!({
+            "transport": "local",
+            "nodes": [{"name": "a", "host": "localhost"}, {"name": "b", "host": "localhost"}],
+        })).unwrap();
+        let nodes: Vec<&config::Node> = cfg.nodes.iter().collect();
+        let mut report = CheckReport::new("test", &[]);
+        per_node_each(&cfg, &nodes, |tr, n| {
+            let pause = if n.name == "a" { "0.3" } else { "0" };
+            tr.exec(&n.host, &format!("sleep {}", pause)).map_err(|e| e.to_string())
+        }, |_, n, _, duration| report.push(CheckResult { check: "test", node: n.name.clone(), status: Severity::Ok, detail: String::new(), duration, cells: vec![], data: serde_json::json!({}) }));
+        assert_eq!(report.results.iter().map(|r| r.node.as_str()).collect::<Vec<_>>(), ["b", "a"]);
+        report.in_node_order(&nodes);
+        assert_eq!(report.results.iter().map(|r| r.node.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+    }
+
+    #[test]
+    fn per_node_bounded() {
+        let cfg: config::Config = serde_json::from_value(serde_json::json!({
+            "transport": "local",
+            "exec_parallel": 3,
+            "nodes": (0..10).map(|i| serde_json::json!({"name": format!("n{}", i), "host": "localhost"})).collect::<Vec<_>>(),
+        })).unwrap();
+        let nodes: Vec<&config::Node> = cfg.nodes.iter().collect();
+        let (running, most) = (std::sync::atomic::AtomicUsize::new(0), std::sync::atomic::AtomicUsize::new(0));
+        let results = per_node(&cfg, &nodes, |_, n| {
+            use std::sync::atomic::Ordering::SeqCst;
+            most.fetch_max(running.fetch_add(1, SeqCst) + 1, SeqCst);
+            std::thread::sleep(Duration::from_millis(30));
+            running.fetch_sub(1, SeqCst);
+            Ok(n.name.clone())
+        });
+        assert_eq!(most.into_inner(), 3);
+        assert_eq!(results.iter().map(|(n, r, _)| (n.name.as_str(), r.clone().unwrap())).filter(|(a, b)| a == b).count(), 10);
+        assert_eq!(config::parallel(&config::Config { exec_parallel: Some(0), ..cfg.clone() }), 1);
+    }
+
+    #[test]
+    fn parse_check_args() {
+        let cmd = CheckCmd::parse(["memory", "-s", "storage", "--watch", "5"]).unwrap();
+        assert_eq!((cmd.name.as_str(), cmd.watch()), ("memory", Some(5)));
+        assert!(cmd.registration().is_some_and(|r| r.resumable));
+        assert!(CheckCmd::parse(["no-such-check"]).is_err());
+        assert!(CheckCmd::parse(["memory", "--interval", "soon"]).is_err());
+    }
+}
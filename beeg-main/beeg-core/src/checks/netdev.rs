@@ -0,0 +1,335 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct NetdevArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Interfaces to inspect; by default those in connInterfacesFile/connInterfacesList of /etc/beegfs/*.conf
+    #[arg(short, long, value_delimiter = ',')]
+    pub interface: Vec<String>,
+    /// MTU every interface should have (e.g. 9000)
+    #[arg(long)]
+    pub expect_mtu: Option<u32>,
+    /// Link speed every interface, or every slave of a bond, should run at (e.g. 100G, 25G, 1000M)
+    #[arg(long, value_parser = parse_rate)]
+    pub expect_rate: Option<u64>,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// `100G`, `25g`, `1000M` or a bare number of Gbit/s, in Mbit/s.
+pub(crate) fn parse_rate(s: &str) -> Result<u64, String> {
+    let t = s.trim().to_ascii_lowercase();
+    let t = t.trim_end_matches("b/s").trim_end_matches("bps").trim_end_matches('b');
+    let (n, mult) = match t.strip_suffix('g') {
+        Some(n) => (n, 1000.0),
+        None => match t.strip_suffix('m') { Some(n) => (n, 1.0), None => (t, 1000.0) },
+    };
+    match n.trim().parse::<f64>() {
+        Ok(v) if v > 0.0 => Ok((v * mult) as u64),
+        _ => Err(format!("invalid rate '{}': expected e.g. 100G, 25G or 1000M", s)),
+    }
+}
+
+/// Mbit/s as `100G`, `2.5G` or `100M`.
+pub(crate) fn rate(mbps: u64) -> String {
+    if mbps >= 1000 { format!("{}G", mbps as f64 / 1000.0) } else { format!("{}M", mbps) }
+}
+
+/// The interfaces (`INTERFACES` line), then an `IF|name|master|state|mtu|speed|driver|firmware|bond mode`
+/// line for each of them and each slave of a bond. `I` preset from `--interface` skips the configs.
+fn probe(interfaces: &[String]) -> String {
+    format!(r#"I={}
+if [ -z "$I" ]; then
+  L=$(sed -n 's/^[[:space:]]*connInterfacesList[[:space:]]*=[[:space:]]*\([^#]*\).*/\1/p' /etc/beegfs/beegfs-*.conf 2>/dev/null | tr ',' ' ')
+  F=$(sed -n 's/^[[:space:]]*connInterfacesFile[[:space:]]*=[[:space:]]*\([^[:space:]#]*\).*/\1/p' /etc/beegfs/beegfs-*.conf 2>/dev/null | sort -u)
+  I=$( {{ for i in $L; do echo "$i"; done; for f in $F; do sed 's/#.*//' "$f" 2>/dev/null; done; }} | awk 'NF {{ print $1 }}' | sort -u | tr '\n' ' ')
+fi
+echo "INTERFACES $I"
+show() {{
+  d=/sys/class/net/$1
+  if [ ! -e "$d" ]; then echo "IF|$1|$2|missing|||||"; return; fi
+  e=$(ethtool -i "$1" 2>/dev/null)
+  drv=$(echo "$e" | sed -n 's/^driver: //p'); fw=$(echo "$e" | sed -n 's/^firmware-version: //p')
+  echo "IF|$1|$2|$(cat "$d/operstate" 2>/dev/null)|$(cat "$d/mtu" 2>/dev/null)|$(cat "$d/speed" 2>/dev/null)|$drv|$fw|$(cut -d' ' -f1 "$d/bonding/mode" 2>/dev/null)"
+}}
+for i in $I; do
+  show "$i" ""
+  for s in $(cat "/sys/class/net/$i/bonding/slaves" 2>/dev/null); do show "$s" "$i"; done
+done"#, shell_escape::escape(interfaces.join(" ").into()))
+}
+
+/// One network interface as sysfs and `ethtool -i` describe it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Iface {
+    pub name: String,
+    /// The bond this is a slave of
+    pub master: Option<String>,
+    /// operstate; `missing` when the interface does not exist
+    pub state: String,
+    pub mtu: Option<u32>,
+    /// Mbit/s; unknown for interfaces without a link or that do not report it
+    pub speed: Option<u64>,
+    pub driver: String,
+    pub firmware: String,
+    /// e.g. `802.3ad`, for bonds only
+    pub bond_mode: Option<String>,
+}
+
+impl Iface {
+    pub fn missing(&self) -> bool {
+        self.state == "missing"
+    }
+
+    /// `unknown` is what virtual and some IPoIB devices report while they carry traffic.
+    pub fn up(&self) -> bool {
+        matches!(self.state.as_str(), "up" | "unknown")
+    }
+}
+
+/// The configured interfaces and every interface line, slaves included.
+pub(crate) fn parse_probe(text: &str) -> (Vec<String>, Vec<Iface>) {
+    let configured = text.lines().find_map(|l| l.strip_prefix("INTERFACES")).map(|l| l.split_whitespace().map(String::from).collect()).unwrap_or_default();
+    let nonempty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    let ifaces = text.lines().filter_map(|l| {
+        let f: Vec<&str> = l.strip_prefix("IF|")?.split('|').collect();
+        if f.len() != 8 { return None; }
+        Some(Iface {
+            name: f[0].to_string(),
+            master: nonempty(f[1]),
+            state: f[2].trim().to_string(),
+            mtu: f[3].trim().parse().ok(),
+            // sysfs says -1 without a link
+            speed: f[4].trim().parse::<i64>().ok().filter(|s| *s > 0).map(|s| s as u64),
+            driver: f[5].trim().to_string(),
+            firmware: f[6].trim().to_string(),
+            bond_mode: nonempty(f[7]),
+        })
+    }).collect();
+    (configured, ifaces)
+}
+
+/// What is wrong with one interface and its bond slaves, against the expected MTU and rate.
+pub(crate) fn evaluate(iface: &Iface, slaves: &[&Iface], mtu: Option<u32>, rate_mbps: Option<u64>) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    if iface.missing() { return vec![(Severity::Failure, format!("{} does not exist", iface.name))]; }
+    if !iface.up() { return vec![(Severity::Failure, format!("{} is {}", iface.name, iface.state))]; }
+    if let (Some(want), Some(have)) = (mtu, iface.mtu) {
+        if have != want { issues.push((Severity::Warning, format!("MTU {}, expected {}", have, want))); }
+    }
+    if iface.bond_mode.is_some() && slaves.is_empty() {
+        issues.push((Severity::Failure, format!("bond {} has no slaves", iface.name)));
+    }
+    for s in slaves.iter().filter(|s| !s.up()) {
+        issues.push((Severity::Warning, format!("bond slave {} is {}", s.name, s.state)));
+    }
+    if let Some(want) = rate_mbps {
+        // a bond reports the sum of its slaves; each link has to run at the rate
+        let links: Vec<&Iface> = if slaves.is_empty() { vec![iface] } else { slaves.iter().copied().filter(|s| s.up()).collect() };
+        for l in links {
+            match l.speed {
+                Some(have) if have < want => issues.push((Severity::Warning, format!("{} at {}, expected {}", l.name, rate(have), rate(want)))),
+                Some(_) => {}
+                None => issues.push((Severity::Warning, format!("{} reports no link speed", l.name))),
+            }
+        }
+    }
+    issues
+}
+
+/// A row's values that are compared across nodes, per interface name.
+fn compared(iface: &Iface, slaves: &[&Iface]) -> [(&'static str, String); 4] {
+    let links: Vec<&Iface> = if slaves.is_empty() { vec![iface] } else { slaves.to_vec() };
+    let joined = |f: fn(&Iface) -> String| {
+        let mut v: Vec<String> = links.iter().map(|l| f(l)).filter(|v| !v.is_empty()).collect();
+        v.sort();
+        v.dedup();
+        v.join(" + ")
+    };
+    [
+        ("MTU", iface.mtu.map(|m| m.to_string()).unwrap_or_default()),
+        ("link speed", joined(|l| l.speed.map(rate).unwrap_or_default())),
+        ("bond mode", iface.bond_mode.clone().unwrap_or_default()),
+        ("firmware", joined(|l| if l.firmware.is_empty() { String::new() } else { format!("{} {}", l.driver, l.firmware) })),
+    ]
+}
+
+/// The most common value and the (node, value) pairs that differ from it; `None` when all agree.
+pub(crate) fn odd_ones<'a>(values: &[(&'a str, String)]) -> Option<(String, Vec<(&'a str, String)>)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, v) in values { *counts.entry(v.as_str()).or_default() += 1; }
+    if counts.len() < 2 { return None; }
+    // ties go to the first value in sort order so the outcome does not depend on node order
+    let common = counts.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))).map(|(v, _)| v.to_string())?;
+    Some((common.clone(), values.iter().filter(|(_, v)| *v != common).cloned().collect()))
+}
+
+struct Sample {
+    node: String,
+    probe: Result<(Vec<String>, Vec<Iface>), String>,
+    duration: Duration,
+}
+
+fn sample(cfg: &config::Config, nodes: &[&config::Node], args: &NetdevArgs) -> Vec<Sample> {
+    let script = probe(&args.interface);
+    super::per_node(cfg, nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(&script, args.timeout))
+            .map(|out| parse_probe(&out.stdout))
+            .map_err(|e| super::exec_error(&e))
+    }).into_iter().map(|(n, probe, duration)| Sample { node: n.name.clone(), probe, duration }).collect()
+}
+
+pub struct Netdev;
+
+impl Check for Netdev {
+    const NAME: &'static str = "netdev";
+    const ABOUT: &'static str = "MTU, link speed, bonding mode and NIC firmware of the interfaces BeeGFS uses, compared across nodes";
+    type Args = NetdevArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &NetdevArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &NetdevArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(Netdev::NAME, &["Interface", "State", "MTU", "Speed", "Bond", "Driver", "Firmware"]);
+    let dash = || "-".to_string();
+    let or_dash = |s: String| if s.is_empty() { dash() } else { s };
+    // (interface name, field) -> [(node, value)], for the comparison across nodes
+    let mut seen: BTreeMap<(String, &'static str), Vec<(String, String)>> = BTreeMap::new();
+    for s in sample(cfg, &nodes, args) {
+        let (configured, ifaces) = match s.probe {
+            Ok(p) => p,
+            Err(e) => {
+                report.push(CheckResult { check: Netdev::NAME, node: s.node, status: Severity::Failure, detail: e, duration: s.duration, cells: vec![dash(); 7], data: serde_json::json!({}) });
+                continue;
+            }
+        };
+        if configured.is_empty() {
+            let detail = "no connInterfacesFile or connInterfacesList in /etc/beegfs; name the interfaces with --interface".to_string();
+            report.push(CheckResult { check: Netdev::NAME, node: s.node, status: Severity::Warning, detail, duration: s.duration, cells: vec![dash(); 7], data: serde_json::json!({ "interfaces": [] }) });
+            continue;
+        }
+        for iface in ifaces.iter().filter(|i| i.master.is_none()) {
+            let slaves: Vec<&Iface> = ifaces.iter().filter(|i| i.master.as_deref() == Some(iface.name.as_str())).collect();
+            let issues = evaluate(iface, &slaves, args.expect_mtu, args.expect_rate);
+            let fields = compared(iface, &slaves);
+            if iface.up() {
+                for (field, value) in &fields {
+                    seen.entry((iface.name.clone(), *field)).or_default().push((s.node.clone(), value.clone()));
+                }
+            }
+            let speed = if slaves.is_empty() { iface.speed.map(rate).unwrap_or_default() } else { fields[1].1.clone() };
+            let bond = iface.bond_mode.as_ref().map(|m| format!("{}: {}", m, slaves.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(","))).unwrap_or_default();
+            let links: Vec<&Iface> = if slaves.is_empty() { vec![iface] } else { slaves.clone() };
+            let distinct = |f: fn(&Iface) -> &str| {
+                let mut v: Vec<&str> = links.iter().map(|l| f(l)).filter(|v| !v.is_empty()).collect();
+                v.dedup();
+                v.join(", ")
+            };
+            let cells = vec![
+                iface.name.clone(),
+                iface.state.clone(),
+                iface.mtu.map(|m| m.to_string()).unwrap_or_else(dash),
+                or_dash(speed),
+                or_dash(bond),
+                or_dash(distinct(|l| &l.driver)),
+                or_dash(distinct(|l| &l.firmware)),
+            ];
+            let data = serde_json::json!({
+                "interface": iface.name,
+                "state": iface.state,
+                "mtu": iface.mtu,
+                "speed_mbps": iface.speed,
+                "driver": iface.driver,
+                "firmware": iface.firmware,
+                "bond_mode": iface.bond_mode,
+                "slaves": slaves.iter().map(|s| serde_json::json!({
+                    "interface": s.name, "state": s.state, "speed_mbps": s.speed, "driver": s.driver, "firmware": s.firmware,
+                })).collect::<Vec<_>>(),
+                "issues": issues.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+            });
+            report.push(CheckResult {
+                check: Netdev::NAME,
+                node: s.node.clone(),
+                status: issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+                detail: issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "),
+                duration: s.duration,
+                cells,
+                data,
+            });
+        }
+    }
+
+    let mut messages = Vec::new();
+    for ((iface, field), values) in &seen {
+        let values: Vec<(&str, String)> = values.iter().map(|(n, v)| (n.as_str(), v.clone())).collect();
+        let Some((common, odd)) = odd_ones(&values) else { continue };
+        for (node, value) in &odd {
+            let Some(r) = report.results.iter_mut().find(|r| r.node == *node && r.cells[0] == *iface) else { continue };
+            r.status = r.status.max(Severity::Warning);
+            let note = format!("{} {} differs from {} on most nodes", field, or_dash(value.clone()), or_dash(common.clone()));
+            r.detail = if r.detail.is_empty() { note } else { format!("{}; {}", r.detail, note) };
+        }
+        let names: Vec<String> = odd.iter().map(|(n, _)| n.to_string()).collect();
+        messages.push((format!("{} {} differs across nodes ({} on most, not on {})", iface, field, or_dash(common), names.join(", ")), names));
+    }
+    for (message, names) in messages {
+        report.warn_about(message, NoteKind::Node, &names);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE: &str = "INTERFACES bond0 ib0
+IF|bond0||up|9000|50000|bonding||802.3ad
+IF|ens1f0|bond0|up|9000|25000|mlx5_core|16.35.2000|
+IF|ens1f1|bond0|down|9000|-1|mlx5_core|16.35.2000|
+IF|ib0||up|2044|200000|mlx5_core|20.31.1014|
+";
+
+    #[test]
+    fn rates() {
+        assert_eq!(parse_rate("100G"), Ok(100_000));
+        assert_eq!(parse_rate("25gb"), Ok(25_000));
+        assert_eq!(parse_rate("1000M"), Ok(1000));
+        assert_eq!(parse_rate("200"), Ok(200_000));
+        assert!(parse_rate("fast").is_err());
+        assert_eq!((rate(100_000), rate(2500), rate(100)), ("100G".to_string(), "2.5G".to_string(), "100M".to_string()));
+    }
+
+    #[test]
+    fn interfaces() {
+        let (configured, ifaces) = parse_probe(PROBE);
+        assert_eq!(configured, vec!["bond0", "ib0"]);
+        assert_eq!(ifaces.len(), 4);
+        assert_eq!(ifaces[0].bond_mode.as_deref(), Some("802.3ad"));
+        assert_eq!((ifaces[2].master.as_deref(), ifaces[2].speed), (Some("bond0"), None));
+
+        let slaves = vec![&ifaces[1], &ifaces[2]];
+        assert_eq!(evaluate(&ifaces[0], &slaves, Some(9000), Some(25_000)), vec![(Severity::Warning, "bond slave ens1f1 is down".to_string())]);
+        assert_eq!(evaluate(&ifaces[3], &[], Some(9000), Some(100_000)), vec![(Severity::Warning, "MTU 2044, expected 9000".to_string())]);
+        assert_eq!(evaluate(&ifaces[1], &[], None, Some(100_000)), vec![(Severity::Warning, "ens1f0 at 25G, expected 100G".to_string())]);
+        let gone = Iface { name: "ib1".into(), state: "missing".into(), ..Default::default() };
+        assert_eq!(evaluate(&gone, &[], None, None), vec![(Severity::Failure, "ib1 does not exist".to_string())]);
+    }
+
+    #[test]
+    fn majority() {
+        let v = vec![("n1", "9000".to_string()), ("n2", "9000".to_string()), ("n3", "1500".to_string())];
+        assert_eq!(odd_ones(&v), Some(("9000".to_string(), vec![("n3", "1500".to_string())])));
+        assert_eq!(odd_ones(&v[..2]), None);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::beegfs::TargetSpace;
+use crate::notes::NoteKind;
+use crate::{config, transfer, transport};
+use clap::Args;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use super::capacity::fetch_targets;
+use super::{single_node, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct PoolLimitsArgs {
+    /// Management node to query (name/host/label); must resolve to one node
+    #[arg(long, visible_alias = "node")]
+    pub selector: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+/// beegfs-mgmtd.conf keys for the storage capacity pools, with the mgmtd's built-in defaults.
+const LIMIT_KEYS: &[(&str, &str)] = &[
+    ("tuneStorageSpaceLowLimit", "1T"),
+    ("tuneStorageSpaceEmergencyLimit", "20G"),
+    ("tuneStorageInodesLowLimit", "10M"),
+    ("tuneStorageInodesEmergencyLimit", "1M"),
+    ("tuneStorageDynamicPools", "true"),
+];
+
+// mgmtd limits from its config; the chooser is a meta setting, read if a meta server shares the node
+pub const PROBE: &str = r#"for k in tuneStorageSpaceLowLimit tuneStorageSpaceEmergencyLimit tuneStorageInodesLowLimit tuneStorageInodesEmergencyLimit tuneStorageDynamicPools; do
+  v=$(sed -n "s/^[[:space:]]*$k[[:space:]]*=[[:space:]]*\([^[:space:]#]*\).*/\1/p" /etc/beegfs/beegfs-mgmtd.conf 2>/dev/null | tail -n1)
+  echo "CONF $k $v"
+done
+c=$(sed -n 's/^[[:space:]]*tuneTargetChooser[[:space:]]*=[[:space:]]*\([^[:space:]#]*\).*/\1/p' /etc/beegfs/beegfs-meta.conf 2>/dev/null | tail -n1)
+echo "CHOOSER $c""#;
+
+/// Capacity pool thresholds as configured on the mgmtd.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    pub space_low: u64,
+    pub space_emergency: u64,
+    pub inodes_low: u64,
+    pub inodes_emergency: u64,
+    /// The mgmtd may raise the limits at runtime when targets are unevenly filled
+    pub dynamic: bool,
+    /// Keys that were not set in the config file and use the built-in default
+    pub defaulted: Vec<&'static str>,
+}
+
+/// Parse the probe into limits (falling back to mgmtd defaults) and the meta target chooser.
+pub fn parse_probe(text: &str) -> anyhow::Result<(Limits, Option<String>)> {
+    let mut set: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut chooser = None;
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("CONF ") {
+            if let Some((k, v)) = rest.split_once(' ') {
+                if !v.trim().is_empty() { set.insert(k, v.trim()); }
+            }
+        } else if let Some(c) = line.strip_prefix("CHOOSER ") {
+            if !c.trim().is_empty() { chooser = Some(c.trim().to_string()); }
+        }
+    }
+    let mut defaulted = Vec::new();
+    let mut get = |key: &'static str, default: &'static str| {
+        set.get(key).copied().unwrap_or_else(|| { defaulted.push(key); default })
+    };
+    let mut values = Vec::new();
+    for (k, d) in LIMIT_KEYS { values.push((*k, get(k, d))); }
+    let size = |i: usize| transfer::parse_size(values[i].1).map_err(|e| anyhow::anyhow!("{}: {}", values[i].0, e));
+    let limits = Limits {
+        space_low: size(0)?,
+        space_emergency: size(1)?,
+        inodes_low: size(2)?,
+        inodes_emergency: size(3)?,
+        dynamic: values[4].1.eq_ignore_ascii_case("true"),
+        defaulted,
+    };
+    Ok((limits, chooser))
+}
+
+type Issues = Vec<(Severity, String)>;
+
+/// The pool the static limits put a target in, and which limit did it.
+pub fn expected_pool(t: &TargetSpace, l: &Limits) -> (&'static str, Option<&'static str>) {
+    if t.free_bytes < l.space_emergency { ("emergency", Some("space")) }
+    else if t.free_inodes < l.inodes_emergency { ("emergency", Some("inodes")) }
+    else if t.free_bytes < l.space_low { ("low", Some("space")) }
+    else if t.free_inodes < l.inodes_low { ("low", Some("inodes")) }
+    else { ("normal", None) }
+}
+
+fn issues(t: &TargetSpace, l: &Limits) -> Issues {
+    let (expected, why) = expected_pool(t, l);
+    let mut out = Vec::new();
+    match t.pool.as_deref() {
+        Some("emergency") => out.push((Severity::Warning, "emergency pool: skipped for new files".to_string())),
+        Some("low") => out.push((Severity::Warning, "low pool: used only when normal targets run short".to_string())),
+        _ => {}
+    }
+    if let Some(p) = t.pool.as_deref() {
+        // dynamic pools move the limits up on purpose, so only a static setup should match exactly
+        if p != expected && !l.dynamic {
+            let by = why.map(|w| format!(" by free {}", w)).unwrap_or_default();
+            out.push((Severity::Warning, format!("mgmtd says {}, configured limits say {}{}", p, expected, by)));
+        }
+    }
+    out
+}
+
+pub struct PoolLimits;
+
+impl Check for PoolLimits {
+    const NAME: &'static str = "pool-limits";
+    const ABOUT: &'static str = "Configured capacity pool limits vs target free space; targets in the emergency pool (via a mgmt node)";
+    type Args = PoolLimitsArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &PoolLimitsArgs) -> anyhow::Result<CheckReport> {
+        run(cfg, args)
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &PoolLimitsArgs) -> anyhow::Result<CheckReport> {
+    let mgmt = single_node(cfg, &args.selector)?;
+    let tr = transport::from_config(cfg);
+    let started = Instant::now();
+    let out = tr.exec_idempotent(&mgmt.host, &wrap_timeout(PROBE, args.timeout))?;
+    let (limits, chooser) = parse_probe(&out.stdout)?;
+    let targets = fetch_targets(cfg, mgmt, args.timeout)?;
+    let duration = started.elapsed();
+
+    let fmt = transfer::format_size;
+    let mut report = CheckReport::new(PoolLimits::NAME, &["Target", "Free", "Inodes free", "Pool", "By limits"]);
+    report.preamble.push(format!(
+        "Limits on {}: space low < {}, emergency < {}; inodes low < {}, emergency < {}; dynamic pools {}; target chooser {}",
+        mgmt.name, fmt(limits.space_low), fmt(limits.space_emergency), limits.inodes_low, limits.inodes_emergency,
+        if limits.dynamic { "on" } else { "off" }, chooser.as_deref().unwrap_or("-"),
+    ));
+    report.data.insert("mgmt".into(), mgmt.name.clone().into());
+    report.data.insert("limits".into(), serde_json::json!({
+        "space_low": limits.space_low,
+        "space_emergency": limits.space_emergency,
+        "inodes_low": limits.inodes_low,
+        "inodes_emergency": limits.inodes_emergency,
+        "dynamic_pools": limits.dynamic,
+        "defaulted": limits.defaulted,
+    }));
+    report.data.insert("target_chooser".into(), serde_json::json!(chooser));
+    for t in &targets {
+        let expected = expected_pool(t, &limits).0;
+        let issues = issues(t, &limits);
+        let text: Vec<&str> = issues.iter().map(|(_, m)| m.as_str()).collect();
+        report.push(CheckResult {
+            check: PoolLimits::NAME,
+            node: t.node.clone(),
+            status: issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+            detail: text.join("; "),
+            duration,
+            cells: vec![t.id.clone(), fmt(t.free_bytes), t.free_inodes.to_string(), t.pool.clone().unwrap_or_else(|| "-".into()), expected.to_string()],
+            data: serde_json::json!({
+                "target": t.id,
+                "free_bytes": t.free_bytes,
+                "free_inodes": t.free_inodes,
+                "pool": t.pool,
+                "expected_pool": expected,
+                "issues": text,
+            }),
+        });
+    }
+
+    let emergency: Vec<&str> = targets.iter().filter(|t| t.pool.as_deref() == Some("emergency")).map(|t| t.id.as_str()).collect();
+    if !emergency.is_empty() {
+        report.warn_about(
+            format!("{} of {} storage targets are in the emergency pool and get no new files: {}", emergency.len(), targets.len(), emergency.join(", ")),
+            NoteKind::Target, &emergency,
+        );
+    }
+    if !limits.defaulted.is_empty() {
+        report.warn(format!("not set in beegfs-mgmtd.conf on {}, assumed mgmtd defaults: {}", mgmt.name, limits.defaulted.join(", ")));
+    }
+    if limits.dynamic {
+        report.warn("dynamic pools are on; the mgmtd may raise the limits above, so pools are not compared against them");
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(free_bytes: u64, free_inodes: u64, pool: &str) -> TargetSpace {
+        TargetSpace { id: "101".into(), node: "s01".into(), pool: Some(pool.into()), total_bytes: 0, free_bytes, total_inodes: 0, free_inodes }
+    }
+
+    #[test]
+    fn limits_and_pools() {
+        let (l, chooser) = parse_probe("CONF tuneStorageSpaceLowLimit 2T\nCONF tuneStorageSpaceEmergencyLimit \nCONF tuneStorageDynamicPools false\nCHOOSER randomized\n").unwrap();
+        assert_eq!(l.space_low, 2 << 40);
+        assert_eq!(l.space_emergency, 20 << 30);
+        assert!(!l.dynamic);
+        assert!(l.defaulted.contains(&"tuneStorageSpaceEmergencyLimit"));
+        assert_eq!(chooser.as_deref(), Some("randomized"));
+
+        assert_eq!(expected_pool(&target(10 << 30, 100 << 20, "normal"), &l), ("emergency", Some("space")));
+        assert_eq!(expected_pool(&target(3 << 40, 5 << 20, "normal"), &l), ("low", Some("inodes")));
+        assert_eq!(expected_pool(&target(3 << 40, 100 << 20, "normal"), &l).0, "normal");
+
+        let i = issues(&target(10 << 30, 100 << 20, "normal"), &l);
+        assert_eq!(i[0].1, "mgmtd says normal, configured limits say emergency by free space");
+        assert_eq!(issues(&target(10 << 30, 100 << 20, "emergency"), &l).len(), 1);
+        assert!(parse_probe("CONF tuneStorageInodesLowLimit lots\n").is_err());
+    }
+}
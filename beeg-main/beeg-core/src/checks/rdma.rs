@@ -0,0 +1,173 @@
+use crate::notes::NoteKind;
+use crate::{config, transport};
+use clap::Args;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{single_node, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct RdmaArgs {
+    /// Node that runs the perftest client (name/host/label); must resolve to one node
+    #[arg(long)]
+    pub client: String,
+    /// Node that runs the perftest server; must resolve to one node
+    #[arg(long)]
+    pub server: String,
+    /// Address the client connects to (default: the server's inventory host)
+    #[arg(long)]
+    pub server_addr: Option<String>,
+    /// perftest benchmarks to run, in order
+    #[arg(long, value_delimiter = ',', default_value = "send,read", value_parser = ["send", "read", "write"])]
+    pub tests: Vec<String>,
+    /// Warn when average bandwidth is below this many Gbit/s
+    #[arg(long)]
+    pub min_gbps: Option<f64>,
+    /// RDMA device on both nodes (e.g. mlx5_0); perftest picks one when unset
+    #[arg(short = 'd', long)]
+    pub device: Option<String>,
+    /// TCP port for the perftest rendezvous
+    #[arg(long, default_value_t = 18515)]
+    pub port: u16,
+    /// Message size in bytes
+    #[arg(long, default_value_t = 65536)]
+    pub size: u64,
+    /// Seconds each benchmark runs
+    #[arg(long, default_value_t = 5)]
+    pub duration: u64,
+    /// Timeout seconds per operation (on top of --duration)
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// Average and peak bandwidth in Gbit/s from a perftest `--report_gbits` run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Bandwidth {
+    pub peak: f64,
+    pub average: f64,
+}
+
+/// Parse the result table of `ib_{send,read,write}_bw --report_gbits`.
+pub(crate) fn parse_perftest(text: &str) -> Option<Bandwidth> {
+    let mut cols: Option<(usize, usize)> = None;
+    let mut last = None;
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if line.contains("#bytes") {
+            // "BW peak[Gb/sec]" spans two fields; index the data columns instead
+            let names: Vec<&str> = line.split("  ").map(str::trim).filter(|s| !s.is_empty()).collect();
+            let peak = names.iter().position(|n| n.starts_with("BW peak"))?;
+            let avg = names.iter().position(|n| n.starts_with("BW average"))?;
+            cols = Some((peak, avg));
+            continue;
+        }
+        let Some((p, a)) = cols else { continue };
+        if fields.first().is_some_and(|f| f.parse::<u64>().is_ok()) && fields.len() > p.max(a) {
+            if let (Ok(peak), Ok(average)) = (fields[p].parse(), fields[a].parse()) {
+                last = Some(Bandwidth { peak, average });
+            }
+        }
+    }
+    last
+}
+
+fn perftest_cmd(test: &str, args: &RdmaArgs, server: Option<&str>) -> String {
+    let mut cmd = format!("ib_{}_bw --report_gbits -F -s {} -D {} -p {}", test, args.size, args.duration, args.port);
+    if let Some(d) = &args.device { cmd += &format!(" -d {}", shell_escape::escape(d.into())); }
+    if let Some(s) = server { cmd += &format!(" {}", shell_escape::escape(s.into())); }
+    cmd + " 2>&1"
+}
+
+pub struct Rdma;
+
+impl Check for Rdma {
+    const NAME: &'static str = "rdma-bandwidth";
+    const ABOUT: &'static str = "RDMA verbs bandwidth between two nodes (ib_send_bw/ib_read_bw)";
+    type Args = RdmaArgs;
+    const RESUMABLE: bool = false;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &RdmaArgs) -> anyhow::Result<CheckReport> {
+        run(cfg, args)
+    }
+}
+
+/// One result per benchmark, filed under the client node.
+pub fn run(cfg: &config::Config, args: &RdmaArgs) -> anyhow::Result<CheckReport> {
+    let client = single_node(cfg, &args.client)?;
+    let server = single_node(cfg, &args.server)?;
+    let addr = args.server_addr.clone().unwrap_or_else(|| server.host.clone());
+    let limit = args.timeout + args.duration;
+
+    let gbps = |v: Option<f64>| v.map(|g| format!("{:.2}", g)).unwrap_or_else(|| "-".into());
+    let mut report = CheckReport::new(Rdma::NAME, &["Test", "Server", "Avg Gb/s", "Peak Gb/s"]);
+    for test in &args.tests {
+        let started = Instant::now();
+        let server_cmd = wrap_timeout(&perftest_cmd(test, args, None), limit);
+        let server_host = server.host.clone();
+        let tr = transport::from_config(cfg);
+        let srv = thread::spawn(move || tr.exec(&server_host, &server_cmd));
+        // give the server time to listen before the client connects
+        thread::sleep(Duration::from_secs(2));
+        let client_out = transport::from_config(cfg).exec(&client.host, &wrap_timeout(&perftest_cmd(test, args, Some(&addr)), limit));
+        let server_out = srv.join().map_err(|_| anyhow::anyhow!("server thread panicked"))?;
+
+        let bw = client_out.as_ref().ok().and_then(|o| parse_perftest(&o.stdout));
+        let (sev, note) = match (bw, &client_out) {
+            (_, Err(e)) => (Severity::Failure, format!("client {}", super::exec_error(e))),
+            (None, Ok(o)) => {
+                let why = o.stdout.lines().chain(server_out.as_ref().map(|s| s.stdout.as_str()).unwrap_or("").lines())
+                    .find(|l| l.contains("not found") || l.contains("Couldn't") || l.contains("Unable") || l.contains("ERROR"))
+                    .unwrap_or("no bandwidth figures in output").trim().to_string();
+                (Severity::Failure, why)
+            }
+            (Some(b), _) if args.min_gbps.is_some_and(|m| b.average < m) => {
+                (Severity::Warning, format!("below {} Gb/s", args.min_gbps.unwrap_or_default()))
+            }
+            _ => (Severity::Ok, String::new()),
+        };
+        let name = format!("ib_{}_bw", test);
+        report.push(CheckResult {
+            check: Rdma::NAME,
+            node: client.name.clone(),
+            status: sev,
+            detail: note,
+            duration: started.elapsed(),
+            cells: vec![name.clone(), server.name.clone(), gbps(bw.map(|b| b.average)), gbps(bw.map(|b| b.peak))],
+            data: serde_json::json!({
+                "test": name,
+                "server": server.name,
+                "avg_gbps": bw.map(|b| b.average),
+                "peak_gbps": bw.map(|b| b.peak),
+                "min_gbps": args.min_gbps,
+            }),
+        });
+    }
+
+    let slow: Vec<String> = report.results.iter().filter(|r| r.status == Severity::Warning).map(|r| r.cells[0].clone()).collect();
+    if !slow.is_empty() {
+        let message = format!("RDMA bandwidth {} -> {} below {} Gb/s: {}", client.name, server.name, args.min_gbps.unwrap_or_default(), slow.join(", "));
+        report.warn_about(message, NoteKind::Node, &[client.name.as_str(), server.name.as_str()]);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEND_BW: &str = "---------------------------------------------------------------------------------------
+                    Send BW Test
+ Dual-port       : OFF          Device         : mlx5_0
+ Link type       : IB
+---------------------------------------------------------------------------------------
+ #bytes     #iterations    BW peak[Gb/sec]    BW average[Gb/sec]   MsgRate[Mpps]
+ 65536      1000             97.23              97.21              0.185414
+---------------------------------------------------------------------------------------
+";
+
+    #[test]
+    fn perftest_table() {
+        assert_eq!(parse_perftest(SEND_BW), Some(Bandwidth { peak: 97.23, average: 97.21 }));
+        assert_eq!(parse_perftest("bash: ib_send_bw: command not found"), None);
+    }
+}
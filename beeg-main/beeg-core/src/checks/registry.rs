@@ -0,0 +1,177 @@
+use crate::config;
+use clap::{Arg, ArgMatches, Command, FromArgMatches};
+use std::time::Instant;
+
+use super::summary::ReportFile;
+use super::CheckReport;
+
+/// A built-in check. Implement this on a unit struct and add
+/// `register::<YourCheck>()` to `checks::REGISTRY`; the `beeg check`
+/// subcommand, its `--help` and `beeg check list` follow from that.
+/// `run` only gathers results: output, warnings and the exit code come from the returned report.
+pub trait Check {
+    /// Subcommand name (`beeg check <NAME>`)
+    const NAME: &'static str;
+    /// One-line description for `--help` and `beeg check list`
+    const ABOUT: &'static str;
+    /// Command-line arguments of the check
+    type Args: clap::Args;
+    /// Whether `beeg resume last` may replay recorded node output into a new run; off for
+    /// checks that change state or measure time
+    const RESUMABLE: bool = true;
+    /// Whether `--watch` may rerun the check; off for drills that change cluster state
+    const WATCHABLE: bool = true;
+
+    fn run(cli: &crate::GlobalArgs, cfg: &config::Config, args: &Self::Args) -> anyhow::Result<CheckReport>;
+}
+
+/// Type-erased entry in the check registry.
+pub struct Registration {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub resumable: bool,
+    pub watchable: bool,
+    augment: fn(Command) -> Command,
+    run: fn(&crate::GlobalArgs, &config::Config, &ArgMatches) -> anyhow::Result<CheckReport>,
+}
+
+pub const fn register<C: Check>() -> Registration {
+    Registration {
+        name: C::NAME,
+        about: C::ABOUT,
+        resumable: C::RESUMABLE,
+        watchable: C::WATCHABLE,
+        augment: <C::Args as clap::Args>::augment_args,
+        run: run_erased::<C>,
+    }
+}
+
+fn run_erased<C: Check>(cli: &crate::GlobalArgs, cfg: &config::Config, m: &ArgMatches) -> anyhow::Result<CheckReport> {
+    let args = <C::Args as FromArgMatches>::from_arg_matches(m)?;
+    let started = Instant::now();
+    let mut report = C::run(cli, cfg, &args)?;
+    report.duration = started.elapsed();
+    Ok(report)
+}
+
+/// `list` is reserved for listing the registry itself.
+pub const LIST: &str = "list";
+
+/// Parsed `beeg check <name> ...`: the registered name plus its raw matches.
+#[derive(Debug, Clone)]
+pub struct CheckCmd {
+    pub name: String,
+    matches: ArgMatches,
+}
+
+impl CheckCmd {
+    /// Parse `args` the way `beeg check` does, check name first, e.g. `["ofed", "-s", "storage"]`.
+    pub fn parse<I, T>(args: I) -> Result<CheckCmd, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let cmd = <CheckCmd as clap::Subcommand>::augment_subcommands(Command::new("check").no_binary_name(true));
+        CheckCmd::from_arg_matches(&cmd.try_get_matches_from(args)?)
+    }
+
+    pub fn registration(&self) -> Option<&'static Registration> {
+        super::REGISTRY.iter().find(|r| r.name == self.name)
+    }
+
+    pub fn run(&self, cli: &crate::GlobalArgs, cfg: &config::Config) -> anyhow::Result<CheckReport> {
+        let Some(r) = self.registration() else { anyhow::bail!("unknown check '{}'", self.name) };
+        let _scope = crate::logging::scope(&[("check", &self.name)]);
+        let started = std::time::Instant::now();
+        let report = (r.run)(cli, cfg, &self.matches);
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match &report {
+            Ok(rep) => log::info!(check = self.name.as_str(), severity = rep.severity().as_str(), duration_ms; "check finished"),
+            Err(e) => log::info!(check = self.name.as_str(), duration_ms; "check failed: {:#}", e),
+        }
+        report
+    }
+
+    /// Seconds between reruns, when `--watch` was given.
+    pub fn watch(&self) -> Option<u64> {
+        self.matches.try_get_one::<u64>(WATCH).ok().flatten().copied()
+    }
+
+    /// Files to write the run summary to (`--report-file`, repeatable).
+    pub fn report_files(&self) -> Vec<ReportFile> {
+        self.matches.try_get_many::<ReportFile>(REPORT_FILE).ok().flatten().map(|v| v.cloned().collect()).unwrap_or_default()
+    }
+
+    /// Hours of recent changes to look up on failed nodes; `None` when turned off.
+    pub fn changes_window(&self) -> Option<u64> {
+        self.matches.try_get_one::<u64>(CHANGES).ok().flatten().copied().filter(|h| *h > 0)
+    }
+}
+
+const WATCH: &str = "watch";
+
+/// `--watch` is shared by every check that allows it, so it is added here rather than to each `Args`.
+fn watch_arg() -> Arg {
+    Arg::new(WATCH)
+        .long(WATCH)
+        .value_name("SECONDS")
+        .value_parser(clap::value_parser!(u64).range(1..))
+        .help("Rerun every SECONDS, refreshing the table and highlighting changes (JSON lines with --output json)")
+}
+
+const CHANGES: &str = "changes";
+
+/// `--changes` applies to every check: a failure looks up what changed on its nodes.
+fn changes_arg() -> Arg {
+    Arg::new(CHANGES)
+        .long(CHANGES)
+        .value_name("HOURS")
+        .value_parser(clap::value_parser!(u64))
+        .default_value("72")
+        .help("On failure, list package, service, config and boot changes of the failed nodes from the last HOURS (0: off)")
+}
+
+const REPORT_FILE: &str = "report-file";
+
+/// `--report-file` applies to every check, whatever `--output` says.
+fn report_file_arg() -> Arg {
+    Arg::new(REPORT_FILE)
+        .long(REPORT_FILE)
+        .value_name("[FORMAT=]PATH")
+        .value_parser(super::summary::parse_report_file)
+        .action(clap::ArgAction::Append)
+        .help("Also write a run summary to PATH, e.g. for CI: JSON (verdict, per-node results, warnings), or JUnit XML with junit=PATH; repeatable, rewritten after every --watch run")
+}
+
+impl FromArgMatches for CheckCmd {
+    fn from_arg_matches(m: &ArgMatches) -> Result<Self, clap::Error> {
+        match m.subcommand() {
+            Some((name, sub)) => Ok(CheckCmd { name: name.to_string(), matches: sub.clone() }),
+            None => Err(clap::Error::new(clap::error::ErrorKind::MissingSubcommand)),
+        }
+    }
+
+    fn update_from_arg_matches(&mut self, m: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(m)?;
+        Ok(())
+    }
+}
+
+impl clap::Subcommand for CheckCmd {
+    fn augment_subcommands(mut cmd: Command) -> Command {
+        cmd = cmd.subcommand(Command::new(LIST).about("List available checks"));
+        for r in super::REGISTRY {
+            let sub = (r.augment)(Command::new(r.name)).about(r.about).arg(changes_arg()).arg(report_file_arg());
+            cmd = cmd.subcommand(if r.watchable { sub.arg(watch_arg()) } else { sub });
+        }
+        cmd.subcommand_required(true)
+    }
+
+    fn augment_subcommands_for_update(cmd: Command) -> Command {
+        Self::augment_subcommands(cmd)
+    }
+
+    fn has_subcommand(name: &str) -> bool {
+        name == LIST || super::REGISTRY.iter().any(|r| r.name == name)
+    }
+}
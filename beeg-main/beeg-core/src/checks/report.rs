@@ -0,0 +1,386 @@
+use crate::notes::{self, NoteKind};
+use comfy_table::{Cell, Table, presets::UTF8_FULL};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::Severity;
+
+/// Set while results are printed as JSON lines the moment a check adds them (`--output jsonl`).
+static STREAM_RESULTS: AtomicBool = AtomicBool::new(false);
+
+pub fn stream_results(on: bool) {
+    STREAM_RESULTS.store(on, Ordering::Relaxed);
+}
+
+/// How one node (or target, buddy group, drill step) fared in a check.
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub check: &'static str,
+    pub node: String,
+    pub status: Severity,
+    /// What is wrong; may carry context for OK results too
+    pub detail: String,
+    pub duration: Duration,
+    /// Table cells between Node and Status, one per `CheckReport::columns`
+    pub cells: Vec<String>,
+    /// Check-specific fields merged into the JSON object
+    pub data: serde_json::Value,
+}
+
+impl CheckResult {
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut v = serde_json::json!({
+            "check": self.check,
+            "node": self.node,
+            "status": self.status.as_str(),
+            "detail": self.detail,
+            "duration_ms": self.duration.as_millis() as u64,
+        });
+        if let (Some(obj), Some(extra)) = (v.as_object_mut(), self.data.as_object()) {
+            for (k, x) in extra { obj.entry(k.clone()).or_insert_with(|| x.clone()); }
+        }
+        v
+    }
+
+    /// Table row: node, the check's own cells, status and detail.
+    pub fn row(&self) -> Vec<String> {
+        let mut row = vec![self.node.clone()];
+        row.extend(self.cells.iter().cloned());
+        row.extend([self.status.as_str().to_string(), self.detail.clone()]);
+        row
+    }
+
+    /// `row` as table cells, the status in its severity's color.
+    pub fn cells_colored(&self) -> Vec<Cell> {
+        let status = self.cells.len() + 1;
+        self.row().into_iter().enumerate()
+            .map(|(i, c)| if i == status { crate::tables::status_cell(c, self.status) } else { Cell::new(c) })
+            .collect()
+    }
+}
+
+/// A `WARNING:` line on stderr, with operator notes for the nodes or targets it names.
+#[derive(Debug, Clone, Default)]
+pub struct Warning {
+    pub message: String,
+    /// Indented lines under the message
+    pub lines: Vec<String>,
+    pub notes: Vec<(NoteKind, Vec<String>)>,
+}
+
+/// Everything one check run found. `check` commands render it the same way for every check
+/// and take the exit code from `severity()`.
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    pub check: &'static str,
+    /// Headers of the check-specific table columns
+    pub columns: Vec<String>,
+    pub results: Vec<CheckResult>,
+    pub warnings: Vec<Warning>,
+    /// Lines above the table in human output
+    pub preamble: Vec<String>,
+    /// Report-level JSON fields, e.g. the limits a check compared against
+    pub data: serde_json::Map<String, serde_json::Value>,
+    /// Reports of sub-checks (`check all`), rendered ahead of this one
+    pub sections: Vec<CheckReport>,
+    /// Lowest severity of the run regardless of results, e.g. for an aborted drill
+    pub floor: Severity,
+    pub duration: Duration,
+}
+
+impl CheckReport {
+    pub fn new(check: &'static str, columns: &[&str]) -> Self {
+        CheckReport { check, columns: columns.iter().map(|c| c.to_string()).collect(), ..Default::default() }
+    }
+
+    /// Add a node's result; with `--output jsonl` it is printed right away.
+    pub fn push(&mut self, result: CheckResult) {
+        if STREAM_RESULTS.load(Ordering::Relaxed) {
+            let mut line = result.to_json();
+            line["type"] = "result".into();
+            println!("{}", line);
+        }
+        self.results.push(result);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(Warning { message: message.into(), ..Default::default() });
+    }
+
+    /// Warn and show the operator notes on `subjects` underneath.
+    pub fn warn_about<S: AsRef<str>>(&mut self, message: impl Into<String>, kind: NoteKind, subjects: &[S]) {
+        self.warnings.push(Warning {
+            message: message.into(),
+            lines: Vec::new(),
+            notes: vec![(kind, subjects.iter().map(|s| s.as_ref().to_string()).collect())],
+        });
+    }
+
+    /// Put results pushed as nodes finished back in the order of `nodes`; results for anything
+    /// else keep their place after them.
+    pub fn in_node_order(&mut self, nodes: &[&crate::config::Node]) {
+        self.results.sort_by_key(|r| nodes.iter().position(|n| n.name == r.node).unwrap_or(nodes.len()));
+    }
+
+    /// Reorder the node results, and those of sub-checks, for `--sort`.
+    pub fn sort_nodes(&mut self, cfg: &crate::config::Config, order: crate::config::NodeOrder) {
+        crate::config::sort_rows(cfg, order, &mut self.results, |r| &r.node);
+        for s in &mut self.sections { s.sort_nodes(cfg, order); }
+    }
+
+    /// Worst result, or the floor if that is worse.
+    pub fn severity(&self) -> Severity {
+        self.results.iter().map(|r| r.status).max().unwrap_or_default().max(self.floor)
+    }
+
+    /// How many results have `status`.
+    pub fn count(&self, status: Severity) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+
+    /// One line for summaries: what is not OK, or how many results were.
+    pub fn summary(&self) -> String {
+        let bad: Vec<String> = self.results.iter().filter(|r| r.status != Severity::Ok)
+            .map(|r| if r.detail.is_empty() { r.node.clone() } else { format!("{}: {}", r.node, r.detail) })
+            .collect();
+        if !bad.is_empty() { return bad.join("; "); }
+        if self.severity() != Severity::Ok {
+            return self.warnings.iter().map(|w| w.message.as_str()).collect::<Vec<_>>().join("; ");
+        }
+        format!("{} result(s) OK", self.results.len())
+    }
+
+    pub fn header(&self) -> Vec<String> {
+        let mut header = vec!["Node".to_string()];
+        header.extend(self.columns.iter().cloned());
+        header.extend(["Status".to_string(), "Detail".to_string()]);
+        header
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(self.header());
+        for r in &self.results { table.add_row(r.cells_colored()); }
+        table
+    }
+
+    /// Results of this report and its sections, skipping the per-section rows of `check all`
+    /// that only repeat a section's outcome.
+    pub fn leaf_results(&self) -> Vec<&CheckResult> {
+        let mut out: Vec<&CheckResult> = self.sections.iter().flat_map(CheckReport::leaf_results).collect();
+        out.extend(self.results.iter().filter(|r| !self.sections.iter().any(|s| s.check == r.check)));
+        out
+    }
+
+    /// Header and rows for `--output csv`/`tsv`: the table itself, or for a report with
+    /// sections one row per node and check, since each check has its own columns.
+    pub fn plain_rows(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        if self.sections.is_empty() { return (self.header(), self.results.iter().map(CheckResult::row).collect()); }
+        let header = ["Check", "Node", "Status", "Detail"].map(String::from).to_vec();
+        let rows = self.leaf_results().iter().map(|r| vec![r.check.to_string(), r.node.clone(), r.status.as_str().to_string(), r.detail.clone()]).collect();
+        (header, rows)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut v = serde_json::json!({
+            "check": self.check,
+            "status": self.severity().as_str(),
+            "duration_ms": self.duration.as_millis() as u64,
+            "counts": {
+                "ok": self.count(Severity::Ok),
+                "warn": self.count(Severity::Warning),
+                "noperm": self.count(Severity::NoPerm),
+                "fail": self.count(Severity::Failure),
+            },
+            "results": self.results.iter().map(CheckResult::to_json).collect::<Vec<_>>(),
+            "warnings": self.warnings.iter().map(|w| &w.message).collect::<Vec<_>>(),
+        });
+        if let Some(obj) = v.as_object_mut() {
+            if !self.sections.is_empty() {
+                obj.insert("sections".into(), self.sections.iter().map(CheckReport::to_json).collect());
+            }
+            for (k, x) in &self.data { obj.entry(k.clone()).or_insert_with(|| x.clone()); }
+        }
+        v
+    }
+
+    /// The last line of `--output jsonl`: status, counts over every result and all warnings.
+    fn summary_line(&self) -> serde_json::Value {
+        let results = self.leaf_results();
+        let count = |s: Severity| results.iter().filter(|r| r.status == s).count();
+        let mut warnings = Vec::new();
+        super::summary::warnings(self, &mut warnings);
+        serde_json::json!({
+            "type": "summary",
+            "check": self.check,
+            "status": self.severity().as_str(),
+            "duration_ms": self.duration.as_millis() as u64,
+            "counts": {
+                "ok": count(Severity::Ok),
+                "warn": count(Severity::Warning),
+                "noperm": count(Severity::NoPerm),
+                "fail": count(Severity::Failure),
+            },
+            "warnings": warnings,
+        })
+    }
+
+    fn print_human(&self) {
+        for s in &self.sections {
+            println!("== {} ==", s.check);
+            s.print_human();
+        }
+        if !self.sections.is_empty() { println!("== summary =="); }
+        for line in &self.preamble { println!("{}", line); }
+        if !self.results.is_empty() { println!("{}", self.table()); }
+    }
+
+    /// Warnings go to stderr so they never break JSON consumers.
+    fn print_warnings(&self) {
+        for s in &self.sections { s.print_warnings(); }
+        for w in &self.warnings {
+            log::warn!("{}", w.lines.iter().fold(w.message.clone(), |m, line| m + "\n  " + line));
+            for (kind, subjects) in &w.notes {
+                let subjects: Vec<&str> = subjects.iter().map(String::as_str).collect();
+                notes::show(*kind, &subjects);
+            }
+        }
+    }
+
+    /// Nagios plugin output: a status line with perfdata, then a line per result that is not OK.
+    pub fn nagios(&self, strict: bool) -> String {
+        let results = self.leaf_results();
+        let count = |s: Severity| results.iter().filter(|r| r.status == s).count();
+        let severity = self.severity();
+        let text = match severity {
+            Severity::Ok => format!("{} of {} OK", count(Severity::Ok), results.len()),
+            // `check all`: which checks are not OK, the nodes follow below
+            _ if !self.sections.is_empty() => {
+                let bad: Vec<&str> = self.sections.iter().filter(|s| s.severity() != Severity::Ok).map(|s| s.check).collect();
+                format!("{} of {} checks not OK: {}", bad.len(), self.sections.len(), bad.join(", "))
+            }
+            _ => self.summary(),
+        };
+        // `|` starts the perfdata
+        let clean = |t: &str| t.replace('|', "/").replace('\n', " ");
+        let mut out = format!(
+            "BEEG {} {} - {} | ok={} warn={} fail={} noperm={} duration={:.3}s\n",
+            self.check.to_uppercase(), severity.nagios(strict).0, clean(&text),
+            count(Severity::Ok), count(Severity::Warning), count(Severity::Failure), count(Severity::NoPerm), self.duration.as_secs_f64(),
+        );
+        for r in results.iter().filter(|r| r.status != Severity::Ok) {
+            let what = if self.sections.is_empty() { r.node.clone() } else { format!("{} on {}", r.check, r.node) };
+            out.push_str(&clean(&format!("{} {}: {}", r.status.as_str(), what, r.detail)));
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn render(&self, cli: &crate::GlobalArgs) -> anyhow::Result<()> {
+        match cli.output {
+            crate::Output::Human => self.print_human(),
+            crate::Output::Nagios => print!("{}", self.nagios(cli.strict)),
+            crate::Output::Json | crate::Output::Yaml => crate::formats::print(cli.output, &self.to_json())?,
+            // the results went out as they came; what is left is the verdict
+            crate::Output::Jsonl => println!("{}", self.summary_line()),
+            crate::Output::Csv | crate::Output::Tsv => {
+                for line in &self.preamble { crate::tables::note(cli.output, line); }
+                let (header, rows) = self.plain_rows();
+                let mut table = Table::new();
+                table.set_header(header);
+                for row in rows { table.add_row(row); }
+                crate::tables::print(cli.output, &table);
+            }
+        }
+        self.print_warnings();
+        let denied = self.count(Severity::NoPerm);
+        if denied > 0 {
+            log::warn!("{} result(s) could not be checked for lack of permission; {}", denied, super::NOPERM_HINT);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(node: &str, status: Severity, detail: &str) -> CheckResult {
+        CheckResult {
+            check: "demo",
+            node: node.into(),
+            status,
+            detail: detail.into(),
+            duration: Duration::from_millis(1500),
+            cells: vec!["x".into()],
+            data: serde_json::json!({"value": 7, "node": "ignored"}),
+        }
+    }
+
+    #[test]
+    fn severity_summary_json() {
+        let mut r = CheckReport::new("demo", &["Value"]);
+        assert_eq!(r.severity(), Severity::Ok);
+        r.results.push(result("n1", Severity::Ok, ""));
+        assert_eq!(r.summary(), "1 result(s) OK");
+        r.floor = Severity::Warning;
+        r.warn("mixed");
+        assert_eq!((r.severity(), r.summary().as_str()), (Severity::Warning, "mixed"));
+        r.results.push(result("n2", Severity::Failure, "probe failed"));
+        assert_eq!(r.severity(), Severity::Failure);
+        assert_eq!(r.summary(), "n2: probe failed");
+
+        let v = r.to_json();
+        assert_eq!(v["status"], "FAIL");
+        assert_eq!(v["results"][1]["duration_ms"], 1500);
+        assert_eq!(v["results"][1]["value"], 7);
+        // check-specific data never overrides the shared fields
+        assert_eq!(v["results"][1]["node"], "n2");
+        assert_eq!(v["warnings"][0], "mixed");
+        assert_eq!(r.table().row_iter().count(), 2);
+    }
+
+    #[test]
+    fn noperm_counts_and_exit_code() {
+        let mut r = CheckReport::new("demo", &["Value"]);
+        r.results.push(result("n1", Severity::Warning, "slow"));
+        r.results.push(result("n2", Severity::NoPerm, "cannot read beegfs-client.conf: permission denied"));
+        assert_eq!(r.severity(), Severity::NoPerm);
+        assert_eq!((r.severity().exit_code(false), r.severity().exit_code(true)), (4, 4));
+        let v = r.to_json();
+        assert_eq!(v["status"], "NOPERM");
+        assert_eq!((v["counts"]["warn"].as_u64(), v["counts"]["noperm"].as_u64(), v["counts"]["fail"].as_u64()), (Some(1), Some(1), Some(0)));
+        // a failure elsewhere still wins
+        r.results.push(result("n3", Severity::Failure, "down"));
+        assert_eq!(r.severity().exit_code(false), 2);
+    }
+
+    #[test]
+    fn nagios_output() {
+        let mut r = CheckReport::new("demo", &["Value"]);
+        r.results.push(result("n1", Severity::Ok, ""));
+        assert_eq!(r.nagios(false), "BEEG DEMO OK - 1 of 1 OK | ok=1 warn=0 fail=0 noperm=0 duration=0.000s\n");
+        r.results.push(result("n2", Severity::Warning, "slow | very"));
+        r.duration = Duration::from_millis(1500);
+        assert_eq!(r.nagios(false), "BEEG DEMO WARNING - n2: slow / very | ok=1 warn=1 fail=0 noperm=0 duration=1.500s\nWARN n2: slow / very\n");
+        assert!(r.nagios(true).starts_with("BEEG DEMO CRITICAL - "));
+        assert_eq!((Severity::Warning.nagios(false).1, Severity::Failure.nagios(false).1, Severity::NoPerm.nagios(false)), (1, 2, ("UNKNOWN", 3)));
+    }
+
+    #[test]
+    fn jsonl_summary_line() {
+        let mut sub = CheckReport::new("demo", &["Value"]);
+        sub.push(result("n1", Severity::Ok, ""));
+        sub.push(result("n2", Severity::Warning, "slow"));
+        sub.warn("n2 is slow");
+        let mut all = CheckReport::new("all", &["Check"]);
+        all.results.push(CheckResult { check: "demo", node: "all".into(), ..result("all", Severity::Warning, "") });
+        all.sections.push(sub);
+        let v = all.summary_line();
+        assert_eq!((v["type"].as_str(), v["status"].as_str()), (Some("summary"), Some("WARN")));
+        // the section row is not counted twice
+        assert_eq!((v["counts"]["ok"].as_u64(), v["counts"]["warn"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(v["warnings"][0]["message"], "n2 is slow");
+    }
+}
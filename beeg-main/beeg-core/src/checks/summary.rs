@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{CheckReport, Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Json,
+    Junit,
+}
+
+/// One `--report-file`: `PATH` or `json=PATH` for the JSON summary, `junit=PATH` for JUnit XML.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportFile {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+pub(crate) fn parse_report_file(s: &str) -> Result<ReportFile, String> {
+    let (format, path) = match s.split_once('=') {
+        Some(("json", p)) => (ReportFormat::Json, p),
+        Some(("junit", p)) => (ReportFormat::Junit, p),
+        _ => (ReportFormat::Json, s),
+    };
+    if path.is_empty() { return Err("expected PATH, json=PATH or junit=PATH".into()); }
+    Ok(ReportFile { format, path: PathBuf::from(path) })
+}
+
+pub(super) fn warnings(report: &CheckReport, out: &mut Vec<serde_json::Value>) {
+    for s in &report.sections { warnings(s, out); }
+    out.extend(report.warnings.iter().map(|w| serde_json::json!({ "check": report.check, "message": w.message })));
+}
+
+/// The `--report-file` document for one run of `check`: the verdict and exit code, every
+/// node's result per check, warnings, and the full report as `--output json` prints it.
+/// A run that ended in an error still gets one, with verdict `ERROR`.
+pub(crate) fn build(check: &str, outcome: Result<&CheckReport, &anyhow::Error>, started: DateTime<Utc>, strict: bool) -> serde_json::Value {
+    let mut v = serde_json::json!({
+        "beeg_version": crate::selfupdate::CURRENT,
+        "check": check,
+        "started": crate::timefmt::rfc3339(started),
+        "finished": crate::timefmt::rfc3339(Utc::now()),
+    });
+    match outcome {
+        Ok(report) => {
+            let results = report.leaf_results();
+            let mut nodes: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+            for r in &results { nodes.entry(r.node.as_str()).or_default().insert(r.check, r.status.as_str()); }
+            let count = |s: Severity| results.iter().filter(|r| r.status == s).count();
+            let mut warns = Vec::new();
+            warnings(report, &mut warns);
+            v["verdict"] = report.severity().as_str().into();
+            v["exit_code"] = report.severity().exit_code(strict).into();
+            v["duration_ms"] = (report.duration.as_millis() as u64).into();
+            v["counts"] = serde_json::json!({
+                "ok": count(Severity::Ok),
+                "warn": count(Severity::Warning),
+                "noperm": count(Severity::NoPerm),
+                "fail": count(Severity::Failure),
+            });
+            v["nodes"] = serde_json::to_value(nodes).unwrap_or_default();
+            v["results"] = results.iter().map(|r| serde_json::json!({
+                "check": r.check,
+                "node": r.node,
+                "status": r.status.as_str(),
+                "detail": r.detail,
+                "duration_ms": r.duration.as_millis() as u64,
+            })).collect();
+            v["warnings"] = warns.into();
+            v["report"] = report.to_json();
+        }
+        Err(e) => {
+            v["verdict"] = "ERROR".into();
+            v["exit_code"] = super::error_severity(e).exit_code(strict).into();
+            v["error"] = format!("{:#}", e).into();
+        }
+    }
+    v
+}
+
+/// Write `data` to `path`, replacing it in one step so a CI job never reads half a file.
+fn write(path: &Path, data: &[u8]) -> Result<()> {
+    let name = path.file_name().context("--report-file needs a file name")?.to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&tmp, data).with_context(|| format!("writing report file: {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replacing report file: {}", path.display()))?;
+    Ok(())
+}
+
+/// Write every `--report-file` of one run of `check`.
+pub(crate) fn write_all(files: &[ReportFile], check: &str, outcome: Result<&CheckReport, &anyhow::Error>, started: DateTime<Utc>, strict: bool) -> Result<()> {
+    for f in files {
+        let data = match f.format {
+            ReportFormat::Json => serde_json::to_vec_pretty(&build(check, outcome, started, strict))?,
+            ReportFormat::Junit => super::junit::render(check, outcome, started, strict).into_bytes(),
+        };
+        write(&f.path, &data)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::CheckResult;
+    use std::time::Duration;
+
+    fn result(check: &'static str, node: &str, status: Severity) -> CheckResult {
+        CheckResult { check, node: node.into(), status, detail: String::new(), duration: Duration::from_millis(20), cells: Vec::new(), data: serde_json::json!({}) }
+    }
+
+    #[test]
+    fn summary_of_check_all() {
+        let mut cuda = CheckReport::new("cuda", &[]);
+        cuda.results = vec![result("cuda", "n1", Severity::Ok), result("cuda", "n2", Severity::Failure)];
+        cuda.warn("cuda differs");
+        let mut all = CheckReport::new("all", &[]);
+        all.results = vec![result("cuda", "all", Severity::Failure), result("storage-target", "s1", Severity::NoPerm)];
+        all.sections.push(cuda);
+
+        let v = build("all", Ok(&all), Utc::now(), false);
+        assert_eq!((v["verdict"].as_str(), v["exit_code"].as_u64()), (Some("FAIL"), Some(2)));
+        // the section row "cuda on all" is not repeated
+        assert_eq!(v["results"].as_array().map(Vec::len), Some(3));
+        assert_eq!(v["nodes"]["n2"]["cuda"], "FAIL");
+        assert_eq!(v["nodes"]["s1"]["storage-target"], "NOPERM");
+        assert_eq!((v["counts"]["fail"].as_u64(), v["counts"]["noperm"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(v["warnings"][0]["check"], "cuda");
+
+        let v = build("all", Err(&anyhow::anyhow!("no nodes")), Utc::now(), false);
+        assert_eq!((v["verdict"].as_str(), v["error"].as_str()), (Some("ERROR"), Some("no nodes")));
+    }
+
+    #[test]
+    fn report_file_formats() {
+        let f = |s| parse_report_file(s).map(|r| (r.format, r.path));
+        assert_eq!(f("out/beeg.json"), Ok((ReportFormat::Json, PathBuf::from("out/beeg.json"))));
+        assert_eq!(f("junit=out/beeg.xml"), Ok((ReportFormat::Junit, PathBuf::from("out/beeg.xml"))));
+        assert_eq!(f("a=b.json"), Ok((ReportFormat::Json, PathBuf::from("a=b.json"))));
+        assert!(f("junit=").is_err());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::formats::Format;
+use crate::notes::NoteKind;
+use crate::config;
+use anyhow::Context;
+use clap::Args;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::compat::Op;
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct SysctlArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Tuning profile (YAML, TOML or JSON) to compare against instead of the built-in one
+    #[arg(long)]
+    pub profile: Option<PathBuf>,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// The built-in profile: the BeeGFS tuning recommendations for servers and clients on TCP and RDMA.
+pub(crate) const DEFAULT_PROFILE: &str = r#"# socket buffers large enough for 100G+ links, and writeback that starts early and in
+# small steps instead of stalling the node on a huge dirty page cache
+sysctl:
+  net.core.rmem_max: ">= 67108864"
+  net.core.wmem_max: ">= 67108864"
+  net.core.netdev_max_backlog: ">= 30000"
+  net.core.somaxconn: ">= 1024"
+  net.ipv4.tcp_sack: 1
+  vm.dirty_background_ratio: "<= 5"
+  vm.dirty_ratio: "<= 20"
+  vm.vfs_cache_pressure: "<= 50"
+  vm.min_free_kbytes: ">= 262144"
+# devices under storeStorageDirectory and storeMetaDirectory
+read_ahead_kb: ">= 4096"
+cpu_governor: performance
+"#;
+
+/// What a profile holds a value to: a comparison for numbers, otherwise the exact text.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expect {
+    Cmp(Op, f64),
+    Exact(String),
+}
+
+impl Expect {
+    /// `>= 4096`, `< 20`, `!= 0`; anything without an operator must match as it is, with runs
+    /// of whitespace counting as one space (`4096 87380 134217728`).
+    pub fn parse(text: &str) -> Expect {
+        let t = text.trim();
+        let ops = [(">=", Op::Ge), ("<=", Op::Le), ("==", Op::Eq), ("!=", Op::Ne), (">", Op::Gt), ("<", Op::Lt), ("=", Op::Eq)];
+        ops.into_iter()
+            .find_map(|(s, op)| Some(Expect::Cmp(op, t.strip_prefix(s)?.trim().parse().ok()?)))
+            .unwrap_or_else(|| Expect::Exact(normalize(t)))
+    }
+
+    pub fn holds(&self, value: &str) -> bool {
+        match self {
+            Expect::Exact(want) => normalize(value) == *want,
+            Expect::Cmp(op, want) => value.trim().parse::<f64>().is_ok_and(|have| match op {
+                Op::Eq => have == *want,
+                Op::Ne => have != *want,
+                Op::Lt => have < *want,
+                Op::Le => have <= *want,
+                Op::Gt => have > *want,
+                Op::Ge => have >= *want,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Expect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expect::Exact(v) => write!(f, "{}", v),
+            Expect::Cmp(op, v) => write!(f, "{} {}", op.as_str(), v),
+        }
+    }
+}
+
+fn normalize(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    #[serde(default)]
+    sysctl: BTreeMap<String, Value>,
+    read_ahead_kb: Option<Value>,
+    cpu_governor: Option<String>,
+}
+
+/// A tuning profile, as loaded from `DEFAULT_PROFILE` or `--profile`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Profile {
+    pub sysctl: BTreeMap<String, Expect>,
+    pub read_ahead_kb: Option<Expect>,
+    pub cpu_governor: Option<String>,
+}
+
+pub(crate) fn parse_profile(value: Value) -> anyhow::Result<Profile> {
+    let raw: RawProfile = serde_json::from_value(value)?;
+    let expect = |v: &Value| Expect::parse(&match v { Value::String(s) => s.clone(), v => v.to_string() });
+    for key in raw.sysctl.keys() {
+        // the keys end up in the probe script
+        if !key.chars().all(|c| c.is_ascii_alphanumeric() || "._-/".contains(c)) { anyhow::bail!("'{}' is not a sysctl name", key); }
+    }
+    Ok(Profile {
+        sysctl: raw.sysctl.iter().map(|(k, v)| (k.clone(), expect(v))).collect(),
+        read_ahead_kb: raw.read_ahead_kb.as_ref().map(expect),
+        cpu_governor: raw.cpu_governor,
+    })
+}
+
+fn load_profile(path: Option<&PathBuf>) -> anyhow::Result<Profile> {
+    match path {
+        None => parse_profile(crate::formats::parse_yaml(DEFAULT_PROFILE)?),
+        Some(p) => {
+            let text = std::fs::read_to_string(p).with_context(|| format!("reading profile: {}", p.display()))?;
+            let value = Format::detect(p, &text).parse(&text).with_context(|| format!("parsing profile: {}", p.display()))?;
+            parse_profile(value).with_context(|| format!("parsing profile: {}", p.display()))
+        }
+    }
+}
+
+/// `SYSCTL <key> <value>` per profile key, `GOVERNOR <governor>=<cpus> ...`, and
+/// `RA <dir> <device> <kb>` for each storage and metadata directory in the BeeGFS configs.
+fn probe(profile: &Profile) -> String {
+    let keys: Vec<String> = profile.sysctl.keys().map(|k| shell_escape::escape(k.into()).to_string()).collect();
+    format!(r#"for k in {}; do printf 'SYSCTL %s %s\n' "$k" "$(sysctl -n "$k" 2>/dev/null | tr '\t' ' ')"; done
+echo "GOVERNOR $(cat /sys/devices/system/cpu/cpu*/cpufreq/scaling_governor 2>/dev/null | sort | uniq -c | awk '{{ printf "%s=%s ", $2, $1 }}')"
+D=$(sed -n 's/^[[:space:]]*store\(Storage\|Meta\)Directory[[:space:]]*=[[:space:]]*\([^#]*\).*/\2/p' /etc/beegfs/beegfs-storage.conf /etc/beegfs/beegfs-meta.conf 2>/dev/null | tr ',' ' ')
+for d in $D; do
+  n=$(basename "$(readlink -f "$(findmnt -no SOURCE -T "$d" 2>/dev/null)")")
+  q=/sys/class/block/$n/queue; [ -d "$q" ] || q=/sys/class/block/$n/../queue
+  echo "RA $d $n $(cat "$q/read_ahead_kb" 2>/dev/null)"
+done"#, keys.join(" "))
+}
+
+/// What one node reported.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Tuning {
+    /// Empty for keys the kernel does not have
+    pub sysctl: BTreeMap<String, String>,
+    /// Governor -> number of CPUs running it; empty without cpufreq, e.g. in most VMs
+    pub governors: BTreeMap<String, usize>,
+    /// (directory, device, read_ahead_kb)
+    pub read_ahead: Vec<(String, String, Option<u64>)>,
+}
+
+pub(crate) fn parse_probe(text: &str) -> Tuning {
+    let mut t = Tuning::default();
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("SYSCTL ") {
+            let (k, v) = rest.split_once(' ').unwrap_or((rest, ""));
+            t.sysctl.insert(k.to_string(), v.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("GOVERNOR") {
+            t.governors = rest.split_whitespace().filter_map(|g| {
+                let (name, n) = g.split_once('=')?;
+                Some((name.to_string(), n.parse().ok()?))
+            }).collect();
+        } else if let Some(rest) = line.strip_prefix("RA ") {
+            let f: Vec<&str> = rest.split_whitespace().collect();
+            if let [dir, dev, rest @ ..] = f.as_slice() {
+                t.read_ahead.push((dir.to_string(), dev.to_string(), rest.first().and_then(|kb| kb.parse().ok())));
+            }
+        }
+    }
+    t
+}
+
+/// A setting that is off the profile.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Deviation {
+    /// The sysctl key, `read_ahead_kb <dir>` or `cpu_governor`
+    pub setting: String,
+    pub value: String,
+    pub want: String,
+}
+
+/// Deviations from `profile`, and the settings the node does not have at all.
+pub(crate) fn compare(profile: &Profile, t: &Tuning) -> (Vec<Deviation>, Vec<String>) {
+    let mut off = Vec::new();
+    let mut absent = Vec::new();
+    for (key, want) in &profile.sysctl {
+        match t.sysctl.get(key).filter(|v| !v.is_empty()) {
+            None => absent.push(key.clone()),
+            Some(v) if !want.holds(v) => off.push(Deviation { setting: key.clone(), value: v.clone(), want: want.to_string() }),
+            Some(_) => {}
+        }
+    }
+    if let Some(want) = &profile.read_ahead_kb {
+        for (dir, dev, kb) in &t.read_ahead {
+            match kb {
+                Some(kb) if !want.holds(&kb.to_string()) => off.push(Deviation { setting: format!("read_ahead_kb {} ({})", dir, dev), value: kb.to_string(), want: want.to_string() }),
+                Some(_) => {}
+                None => absent.push(format!("read_ahead_kb {}", dir)),
+            }
+        }
+    }
+    if let Some(want) = &profile.cpu_governor {
+        let other: Vec<String> = t.governors.iter().filter(|(g, _)| *g != want).map(|(g, n)| format!("{} on {} CPUs", g, n)).collect();
+        if t.governors.is_empty() {
+            absent.push("cpu_governor".into());
+        } else if !other.is_empty() {
+            off.push(Deviation { setting: "cpu_governor".into(), value: other.join(", "), want: want.clone() });
+        }
+    }
+    (off, absent)
+}
+
+struct Sample {
+    node: String,
+    tuning: Result<Tuning, String>,
+    duration: Duration,
+}
+
+/// Probe every node, handing each `Sample` to `each` as soon as its node answers.
+fn sample(cfg: &config::Config, nodes: &[&config::Node], script: &str, timeout: u64, mut each: impl FnMut(Sample)) {
+    super::per_node_each(cfg, nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(script, timeout))
+            .map(|out| parse_probe(&out.stdout))
+            .map_err(|e| super::exec_error(&e))
+    }, |_, n, tuning, duration| each(Sample { node: n.name.clone(), tuning, duration }));
+}
+
+pub struct Sysctl;
+
+impl Check for Sysctl {
+    const NAME: &'static str = "sysctl";
+    const ABOUT: &'static str = "Kernel tunings for BeeGFS and RDMA (net.core.*, vm.dirty_*, target read-ahead, CPU governor) against a profile";
+    type Args = SysctlArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &SysctlArgs) -> anyhow::Result<CheckReport> {
+        run(cfg, args)
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &SysctlArgs) -> anyhow::Result<CheckReport> {
+    let profile = load_profile(args.profile.as_ref())?;
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(Sysctl::NAME, &["Checked", "Off profile", "Governor", "Read-ahead KiB"]);
+    report.preamble.push(format!("Profile: {}", args.profile.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "built-in".into())));
+    report.data.insert("profile".into(), args.profile.as_ref().map(|p| p.display().to_string()).into());
+
+    // setting -> nodes off it, for the summary warnings
+    let mut by_setting: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    sample(cfg, &nodes, &probe(&profile), args.timeout, |s| {
+        let t = match s.tuning {
+            Ok(t) => t,
+            Err(e) => {
+                report.push(CheckResult { check: Sysctl::NAME, node: s.node, status: Severity::Failure, detail: e, duration: s.duration, cells: vec!["-".into(); 4], data: serde_json::json!({}) });
+                return;
+            }
+        };
+        let (off, absent) = compare(&profile, &t);
+        for d in &off {
+            // every read-ahead directory counts as the one setting
+            let setting = if d.setting.starts_with("read_ahead_kb") { "read_ahead_kb".to_string() } else { d.setting.clone() };
+            let nodes = by_setting.entry(setting).or_default();
+            if !nodes.contains(&s.node) { nodes.push(s.node.clone()); }
+        }
+        let settings = profile.sysctl.len() + if profile.read_ahead_kb.is_some() { t.read_ahead.len() } else { 0 } + usize::from(profile.cpu_governor.is_some());
+        let checked = settings - absent.len();
+        let governor = t.governors.keys().cloned().collect::<Vec<_>>().join(", ");
+        let mut ra: Vec<String> = t.read_ahead.iter().filter_map(|(_, _, kb)| kb.map(|k| k.to_string())).collect();
+        ra.dedup();
+        let cells = vec![
+            checked.to_string(),
+            off.len().to_string(),
+            if governor.is_empty() { "-".into() } else { governor },
+            if ra.is_empty() { "-".into() } else { ra.join(", ") },
+        ];
+        let data = serde_json::json!({
+            "sysctl": t.sysctl,
+            "governors": t.governors,
+            "read_ahead": t.read_ahead.iter().map(|(dir, dev, kb)| serde_json::json!({ "dir": dir, "device": dev, "read_ahead_kb": kb })).collect::<Vec<_>>(),
+            "deviations": off.iter().map(|d| serde_json::json!({ "setting": d.setting, "value": d.value, "want": d.want })).collect::<Vec<_>>(),
+            "absent": absent,
+        });
+        report.push(CheckResult {
+            check: Sysctl::NAME,
+            node: s.node,
+            status: if off.is_empty() { Severity::Ok } else { Severity::Warning },
+            detail: off.iter().map(|d| format!("{} is {}, want {}", d.setting, d.value, d.want)).collect::<Vec<_>>().join("; "),
+            duration: s.duration,
+            cells,
+            data,
+        });
+    });
+    report.in_node_order(&nodes);
+    for (setting, nodes) in by_setting {
+        let want = if setting == "read_ahead_kb" { profile.read_ahead_kb.as_ref().map(|e| e.to_string()) }
+            else if setting == "cpu_governor" { profile.cpu_governor.clone() }
+            else { profile.sysctl.get(&setting).map(|e| e.to_string()) };
+        report.warn_about(format!("{} off profile ({}) on {}", setting, want.unwrap_or_default(), nodes.join(", ")), NoteKind::Node, &nodes);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE: &str = "SYSCTL net.core.rmem_max 212992
+SYSCTL net.core.wmem_max 134217728
+SYSCTL net.core.netdev_max_backlog 250000
+SYSCTL net.core.somaxconn 4096
+SYSCTL net.ipv4.tcp_sack 1
+SYSCTL vm.dirty_background_ratio 10
+SYSCTL vm.dirty_ratio 20
+SYSCTL vm.vfs_cache_pressure 50
+SYSCTL vm.min_free_kbytes
+GOVERNOR performance=62 powersave=2
+RA /data/beegfs/storage nvme0n1 128
+RA /data/beegfs/meta md0 8192
+";
+
+    #[test]
+    fn expectations() {
+        assert_eq!(Expect::parse(">= 4096"), Expect::Cmp(Op::Ge, 4096.0));
+        assert!(Expect::parse(">= 4096").holds("8192"));
+        assert!(!Expect::parse("<= 5").holds("10"));
+        assert!(!Expect::parse("<= 5").holds("unknown"));
+        assert!(Expect::parse("4096   87380 134217728").holds("4096\t87380 134217728"));
+        assert!(Expect::parse("performance").holds("performance"));
+        assert_eq!(Expect::parse("!=0").to_string(), "!= 0");
+    }
+
+    #[test]
+    fn builtin_profile() {
+        let p = load_profile(None).unwrap();
+        assert_eq!(p.sysctl.get("vm.dirty_ratio"), Some(&Expect::Cmp(Op::Le, 20.0)));
+        assert_eq!(p.sysctl.get("net.ipv4.tcp_sack"), Some(&Expect::Exact("1".into())));
+        assert_eq!(p.cpu_governor.as_deref(), Some("performance"));
+        assert!(parse_profile(serde_json::json!({ "sysctls": {} })).is_err());
+        assert!(parse_profile(serde_json::json!({ "sysctl": { "vm.x; reboot": 1 } })).is_err());
+
+        let t = parse_probe(PROBE);
+        assert_eq!(t.governors.get("powersave"), Some(&2));
+        assert_eq!(t.read_ahead[0], ("/data/beegfs/storage".to_string(), "nvme0n1".to_string(), Some(128)));
+        let (off, absent) = compare(&p, &t);
+        assert_eq!(off.iter().map(|d| d.setting.as_str()).collect::<Vec<_>>(), vec![
+            "net.core.rmem_max", "vm.dirty_background_ratio", "read_ahead_kb /data/beegfs/storage (nvme0n1)", "cpu_governor",
+        ]);
+        assert_eq!(off[3], Deviation { setting: "cpu_governor".into(), value: "powersave on 2 CPUs".into(), want: "performance".into() });
+        assert_eq!(absent, vec!["vm.min_free_kbytes"]);
+    }
+}
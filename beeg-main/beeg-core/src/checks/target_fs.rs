@@ -0,0 +1,264 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct TargetFsArgs {
+    /// Storage/meta node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Filesystem storage targets should be on
+    #[arg(long, default_value = "xfs")]
+    pub storage_fs: String,
+    /// Filesystem metadata targets should be on
+    #[arg(long, default_value = "ext4")]
+    pub meta_fs: String,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// `TARGET <kind> <dir> <findmnt pairs>` per storage and metadata directory in the service
+/// configs, `INODE <dir> <bytes>` for ext4 where tune2fs may read the device, and `NOPERM <conf>`
+/// for a config the user may not read.
+const PROBE: &str = r#"for kind in storage meta; do
+  f=/etc/beegfs/beegfs-$kind.conf
+  if [ -e "$f" ] && [ ! -r "$f" ]; then echo "NOPERM $f"; continue; fi
+  [ -r "$f" ] || continue
+  k=storeStorageDirectory; [ $kind = meta ] && k=storeMetaDirectory
+  for d in $(sed -n "s/^[[:space:]]*$k[[:space:]]*=[[:space:]]*\([^#]*\).*/\1/p" "$f" | tr ',' ' '); do
+    m=$(findmnt -nP -o SOURCE,FSTYPE,OPTIONS,TARGET -T "$d" 2>/dev/null | head -n1)
+    echo "TARGET $kind $d $m"
+    case "$m" in *'FSTYPE="ext4"'*)
+      s=$(echo "$m" | sed 's/^SOURCE="\([^"]*\)".*/\1/')
+      i=$(tune2fs -l "$s" 2>/dev/null | sed -n 's/^Inode size:[[:space:]]*//p')
+      [ -n "$i" ] && echo "INODE $d $i" ;;
+    esac
+  done
+done"#;
+
+/// Filesystems that lose their content on reboot or are not local block storage.
+const NOT_LOCAL: &[&str] = &["tmpfs", "ramfs", "overlay", "nfs", "nfs4", "cifs", "fuse", "beegfs"];
+
+/// The mount a target directory is on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Target {
+    /// `storage` or `meta`
+    pub kind: String,
+    pub dir: String,
+    pub source: String,
+    pub fstype: String,
+    pub options: Vec<String>,
+    pub mountpoint: String,
+    /// ext4 inode size, when tune2fs could read the device
+    pub inode_size: Option<u64>,
+}
+
+impl Target {
+    fn has(&self, option: &str) -> bool {
+        self.options.iter().any(|o| o == option || o.split_once('=').is_some_and(|(k, _)| k == option))
+    }
+
+    fn option(&self, key: &str) -> Option<&str> {
+        self.options.iter().find_map(|o| o.strip_prefix(key)?.strip_prefix('='))
+    }
+}
+
+/// Targets, and configs the probe could not read.
+pub(crate) fn parse_probe(text: &str) -> (Vec<Target>, Vec<String>) {
+    let pair = Regex::new(r#"(\w+)="([^"]*)""#).unwrap();
+    let mut targets: Vec<Target> = Vec::new();
+    let mut denied = Vec::new();
+    for line in text.lines() {
+        if let Some(conf) = line.strip_prefix("NOPERM ") {
+            denied.push(conf.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("TARGET ") {
+            let mut f = rest.splitn(3, ' ');
+            let (Some(kind), Some(dir)) = (f.next(), f.next()) else { continue };
+            let mut t = Target { kind: kind.to_string(), dir: dir.to_string(), ..Default::default() };
+            for c in pair.captures_iter(f.next().unwrap_or_default()) {
+                match &c[1] {
+                    "SOURCE" => t.source = c[2].to_string(),
+                    "FSTYPE" => t.fstype = c[2].to_string(),
+                    "OPTIONS" => t.options = c[2].split(',').map(String::from).collect(),
+                    "TARGET" => t.mountpoint = c[2].to_string(),
+                    _ => {}
+                }
+            }
+            targets.push(t);
+        } else if let Some(rest) = line.strip_prefix("INODE ") {
+            if let Some((dir, size)) = rest.rsplit_once(' ') {
+                if let Some(t) = targets.iter_mut().find(|t| t.dir == dir) { t.inode_size = size.trim().parse().ok(); }
+            }
+        }
+    }
+    (targets, denied)
+}
+
+/// What is wrong with one target's filesystem; the worst decides its status.
+pub(crate) fn evaluate(t: &Target, args: &TargetFsArgs) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    if t.fstype.is_empty() { return vec![(Severity::Failure, format!("{} does not exist or is on no mount", t.dir))]; }
+    if NOT_LOCAL.iter().any(|fs| t.fstype == *fs || t.fstype.starts_with("fuse.")) {
+        return vec![(Severity::Failure, format!("on {}, not a local disk filesystem", t.fstype))];
+    }
+    if t.has("ro") { issues.push((Severity::Failure, "mounted read-only".into())); }
+    let want = if t.kind == "meta" { &args.meta_fs } else { &args.storage_fs };
+    if t.fstype != *want { issues.push((Severity::Warning, format!("{} on {}, {} is recommended", t.kind, t.fstype, want))); }
+    if t.mountpoint == "/" { issues.push((Severity::Warning, "on the root filesystem".into())); }
+    // every access turns into an inode write without noatime; relatime still writes once a day
+    if !t.has("noatime") { issues.push((Severity::Warning, "not mounted noatime".into())); }
+    if t.has("sync") || t.has("dirsync") || t.has("wsync") { issues.push((Severity::Warning, "mounted with synchronous writes".into())); }
+    match t.fstype.as_str() {
+        "ext4" => {
+            if t.option("data") == Some("journal") { issues.push((Severity::Warning, "data=journal writes all data twice".into())); }
+            if t.kind == "meta" && t.has("nouser_xattr") { issues.push((Severity::Failure, "nouser_xattr: metadata is kept in extended attributes".into())); }
+            if let Some(i) = t.inode_size.filter(|i| *i < 512 && t.kind == "meta") {
+                issues.push((Severity::Warning, format!("inode size {}: metadata only fits inside 512-byte inodes (mkfs.ext4 -I 512)", i)));
+            }
+        }
+        "xfs" => {
+            if t.has("noalign") { issues.push((Severity::Warning, "noalign ignores the RAID stripe geometry".into())); }
+            if t.has("inode32") { issues.push((Severity::Warning, "inode32 crowds all inodes into the first terabyte".into())); }
+        }
+        _ => {}
+    }
+    issues
+}
+
+struct Sample {
+    node: String,
+    probe: Result<(Vec<Target>, Vec<String>), String>,
+    duration: Duration,
+}
+
+/// Probe every node, handing each `Sample` to `each` as soon as its node answers.
+fn sample(cfg: &config::Config, nodes: &[&config::Node], timeout: u64, mut each: impl FnMut(Sample)) {
+    super::per_node_each(cfg, nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &wrap_timeout(PROBE, timeout))
+            .map(|out| parse_probe(&out.stdout))
+            .map_err(|e| super::exec_error(&e))
+    }, |_, n, probe, duration| each(Sample { node: n.name.clone(), probe, duration }));
+}
+
+pub struct TargetFs;
+
+impl Check for TargetFs {
+    const NAME: &'static str = "target-fs";
+    const ABOUT: &'static str = "Filesystem and mount options under each storage and metadata target (XFS/ext4, noatime, ...)";
+    type Args = TargetFsArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &TargetFsArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &TargetFsArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let mut report = CheckReport::new(TargetFs::NAME, &["Kind", "Directory", "Device", "FS", "Options"]);
+    let dash = || vec!["-".to_string(); 5];
+    sample(cfg, &nodes, args.timeout, |s| {
+        let (targets, denied) = match s.probe {
+            Ok(p) => p,
+            Err(e) => {
+                report.push(CheckResult { check: TargetFs::NAME, node: s.node, status: Severity::Failure, detail: e, duration: s.duration, cells: dash(), data: serde_json::json!({}) });
+                return;
+            }
+        };
+        if !denied.is_empty() {
+            let detail = format!("cannot read {}: permission denied", denied.join(", "));
+            report.push(CheckResult { check: TargetFs::NAME, node: s.node.clone(), status: Severity::NoPerm, detail, duration: s.duration, cells: dash(), data: serde_json::json!({ "denied": denied }) });
+        }
+        if targets.is_empty() {
+            if denied.is_empty() {
+                let detail = "no storeStorageDirectory or storeMetaDirectory in /etc/beegfs".to_string();
+                report.push(CheckResult { check: TargetFs::NAME, node: s.node, status: Severity::Ok, detail, duration: s.duration, cells: dash(), data: serde_json::json!({ "targets": [] }) });
+            }
+            return;
+        }
+        // targets sharing a filesystem each report its full size, so free space is counted twice
+        let mut by_fs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for t in targets.iter().filter(|t| !t.mountpoint.is_empty()) { by_fs.entry(&t.mountpoint).or_default().push(&t.dir); }
+        for t in &targets {
+            let mut issues = evaluate(t, args);
+            if let Some(others) = by_fs.get(t.mountpoint.as_str()).filter(|d| d.len() > 1) {
+                let others: Vec<&str> = others.iter().copied().filter(|d| *d != t.dir).collect();
+                issues.push((Severity::Warning, format!("shares {} with {}", t.mountpoint, others.join(", "))));
+            }
+            let cells = vec![
+                t.kind.clone(),
+                t.dir.clone(),
+                if t.source.is_empty() { "-".into() } else { t.source.clone() },
+                if t.fstype.is_empty() { "-".into() } else { t.fstype.clone() },
+                if t.options.is_empty() { "-".into() } else { t.options.join(",") },
+            ];
+            let data = serde_json::json!({
+                "kind": t.kind,
+                "dir": t.dir,
+                "device": t.source,
+                "fstype": t.fstype,
+                "options": t.options,
+                "mountpoint": t.mountpoint,
+                "inode_size": t.inode_size,
+                "issues": issues.iter().map(|(_, m)| m).collect::<Vec<_>>(),
+            });
+            report.push(CheckResult {
+                check: TargetFs::NAME,
+                node: s.node.clone(),
+                status: issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+                detail: issues.into_iter().map(|(_, m)| m).collect::<Vec<_>>().join("; "),
+                duration: s.duration,
+                cells,
+                data,
+            });
+        }
+    });
+    report.in_node_order(&nodes);
+
+    let mut failing: Vec<String> = report.results.iter().filter(|r| r.status == Severity::Failure).map(|r| r.node.clone()).collect();
+    failing.dedup();
+    if !failing.is_empty() {
+        report.warn_about(format!("targets on an unsuitable filesystem on {}", failing.join(", ")), NoteKind::Node, &failing);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE_OUT: &str = r#"TARGET storage /data/storage1 SOURCE="/dev/md0" FSTYPE="xfs" OPTIONS="rw,noatime,attr2,inode64,logbsize=256k,sunit=1024,swidth=8192,noquota" TARGET="/data/storage1"
+TARGET storage /data/storage2 SOURCE="/dev/md1" FSTYPE="ext4" OPTIONS="rw,relatime,data=journal" TARGET="/data"
+TARGET storage /data/storage3 SOURCE="/dev/md1" FSTYPE="ext4" OPTIONS="rw,relatime,data=journal" TARGET="/data"
+TARGET meta /data/meta SOURCE="/dev/nvme0n1p1" FSTYPE="ext4" OPTIONS="rw,noatime,nodiratime" TARGET="/data/meta"
+INODE /data/meta 256
+TARGET meta /scratch/meta SOURCE="tmpfs" FSTYPE="tmpfs" OPTIONS="rw" TARGET="/scratch"
+"#;
+
+    fn args() -> TargetFsArgs {
+        TargetFsArgs { selector: "all".into(), storage_fs: "xfs".into(), meta_fs: "ext4".into(), timeout: 30 }
+    }
+
+    #[test]
+    fn mounts() {
+        let (targets, denied) = parse_probe(PROBE_OUT);
+        assert!(denied.is_empty());
+        assert_eq!(targets.len(), 5);
+        assert_eq!((targets[0].fstype.as_str(), targets[0].mountpoint.as_str()), ("xfs", "/data/storage1"));
+        assert_eq!(targets[3].inode_size, Some(256));
+        assert_eq!(targets[1].option("data"), Some("journal"));
+
+        assert!(evaluate(&targets[0], &args()).is_empty());
+        assert_eq!(evaluate(&targets[1], &args()).into_iter().map(|(_, m)| m).collect::<Vec<_>>(), vec![
+            "storage on ext4, xfs is recommended", "not mounted noatime", "data=journal writes all data twice",
+        ]);
+        assert_eq!(evaluate(&targets[3], &args()).len(), 1);
+        assert_eq!(evaluate(&targets[4], &args()), vec![(Severity::Failure, "on tmpfs, not a local disk filesystem".to_string())]);
+        assert_eq!(parse_probe("NOPERM /etc/beegfs/beegfs-meta.conf\n").1, vec!["/etc/beegfs/beegfs-meta.conf"]);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::time::Duration;
+
+use super::{single_node, wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct TimeArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Largest tolerated offset in milliseconds
+    #[arg(long, default_value_t = 500)]
+    pub max_skew_ms: u64,
+    /// Measure skew against this node instead of the local host
+    #[arg(long)]
+    pub reference: Option<String>,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+// Clock first so the sync tooling does not add to the measured delay.
+const PROBE: &str = r#"echo "NOW $(date +%s%N)"
+if command -v chronyc >/dev/null 2>&1; then
+  echo "SYNC chrony $(chronyc -n tracking 2>/dev/null | sed -n 's/^Leap status *: *//p')"
+elif command -v ntpstat >/dev/null 2>&1; then
+  ntpstat >/dev/null 2>&1 && echo "SYNC ntpstat synchronised" || echo "SYNC ntpstat unsynchronised"
+elif command -v timedatectl >/dev/null 2>&1; then
+  echo "SYNC timedatectl $(timedatectl show -p NTPSynchronized --value 2>/dev/null)"
+fi"#;
+
+/// What a node said about its clock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ClockReport {
+    /// Remote wall clock, nanoseconds since the epoch
+    pub now_ns: Option<i128>,
+    /// (tool, status) from chrony/ntpstat/timedatectl
+    pub sync: Option<(String, String)>,
+}
+
+impl ClockReport {
+    /// `Some(false)` only when the sync tool positively says the clock is not synchronised.
+    pub fn synced(&self) -> Option<bool> {
+        let (_, status) = self.sync.as_ref()?;
+        let s = status.to_ascii_lowercase();
+        if s.is_empty() { return None; }
+        Some(matches!(s.as_str(), "normal" | "synchronised" | "yes"))
+    }
+}
+
+pub(crate) fn parse_probe(text: &str) -> ClockReport {
+    let mut r = ClockReport::default();
+    for line in text.lines() {
+        if let Some(v) = line.strip_prefix("NOW ") {
+            r.now_ns = v.trim().parse().ok();
+        } else if let Some(v) = line.strip_prefix("SYNC ") {
+            let (tool, status) = v.split_once(' ').unwrap_or((v, ""));
+            r.sync = Some((tool.to_string(), status.trim().to_string()));
+        }
+    }
+    r
+}
+
+/// Offset of a remote clock from ours, taking the remote reading as made halfway through the round trip.
+pub(crate) fn offset_ns(sent_ns: i128, received_ns: i128, remote_ns: i128) -> i128 {
+    remote_ns - (sent_ns + (received_ns - sent_ns) / 2)
+}
+
+struct Sample {
+    node: String,
+    report: Result<ClockReport, String>,
+    offset_ns: Option<i128>,
+    rtt_ns: i128,
+}
+
+fn sample(cfg: &config::Config, nodes: &[&config::Node], timeout: u64) -> Vec<Sample> {
+    let cmd = wrap_timeout(PROBE, timeout);
+    super::per_node(cfg, nodes, |tr, n| {
+        let now = || chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as i128;
+        let sent = now();
+        let res = tr.exec_idempotent(&n.host, &cmd).map(|o| parse_probe(&o.stdout)).map_err(|e| super::exec_error(&e));
+        Ok((sent, now(), res))
+    }).into_iter().map(|(n, probe, _)| {
+        let (sent, received, report) = probe.unwrap_or_else(|e| (0, 0, Err(e)));
+        let offset_ns = report.as_ref().ok().and_then(|r| r.now_ns).map(|t| offset_ns(sent, received, t));
+        Sample { node: n.name.clone(), report, offset_ns, rtt_ns: received - sent }
+    }).collect()
+}
+
+pub struct Time;
+
+impl Check for Time {
+    const NAME: &'static str = "time";
+    const ABOUT: &'static str = "Clock skew and NTP sync state across nodes";
+    type Args = TimeArgs;
+    const RESUMABLE: bool = false;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &TimeArgs) -> anyhow::Result<CheckReport> {
+        run(cfg, args)
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &TimeArgs) -> anyhow::Result<CheckReport> {
+    let mut nodes = config::select_nodes(cfg, &args.selector);
+    let reference = args.reference.as_deref().map(|r| single_node(cfg, r)).transpose()?;
+    if let Some(r) = reference { nodes.insert(cfg, r); }
+    let samples = sample(cfg, &nodes, args.timeout);
+    // skews are relative to the local clock unless a reference node is given
+    let base = match reference {
+        Some(r) => match samples.iter().find(|s| s.node == r.name).and_then(|s| s.offset_ns) {
+            Some(o) => o,
+            None => anyhow::bail!("could not read the clock of reference node {}", r.name),
+        },
+        None => 0,
+    };
+    let max_ns = args.max_skew_ms as i128 * 1_000_000;
+
+    let against = reference.map(|r| r.name.clone()).unwrap_or_else(|| "local".into());
+    let skew_col = format!("Skew vs {} (ms)", against);
+    let mut report = CheckReport::new(Time::NAME, &[skew_col.as_str(), "RTT (ms)", "Sync"]);
+    let ms = |ns: i128| ns as f64 / 1e6;
+    for s in &samples {
+        let skew = s.offset_ns.map(|o| o - base);
+        let sync = match s.report.as_ref().ok().and_then(|r| r.sync.as_ref()) {
+            Some((tool, status)) if !status.is_empty() => format!("{} {}", tool, status),
+            _ => "-".into(),
+        };
+        let (status, detail) = match (&s.report, skew) {
+            (Err(e), _) => (Severity::Failure, e.clone()),
+            (Ok(_), None) => (Severity::Failure, "no clock reading".to_string()),
+            (Ok(_), Some(k)) if k.abs() > max_ns => (Severity::Warning, format!("off by more than {}ms", args.max_skew_ms)),
+            (Ok(r), Some(_)) if r.synced() == Some(false) => (Severity::Warning, "clock not synchronised".to_string()),
+            _ => (Severity::Ok, String::new()),
+        };
+        let skew_ms = skew.map(ms);
+        report.push(CheckResult {
+            check: Time::NAME,
+            node: s.node.clone(),
+            status,
+            detail,
+            duration: Duration::from_nanos(s.rtt_ns.max(0) as u64),
+            cells: vec![skew_ms.map(|k| format!("{:+.1}", k)).unwrap_or_else(|| "-".into()), format!("{:.1}", ms(s.rtt_ns)), sync.clone()],
+            data: serde_json::json!({ "reference": against, "skew_ms": skew_ms, "rtt_ms": ms(s.rtt_ns), "sync": sync }),
+        });
+    }
+
+    let skewed: Vec<String> = report.results.iter().filter(|r| r.detail.starts_with("off by")).map(|r| r.node.clone()).collect();
+    if !skewed.is_empty() { report.warn_about(format!("clock skew above {}ms on: {}", args.max_skew_ms, skewed.join(", ")), NoteKind::Node, &skewed); }
+    // the measurement itself is only good to about half the round trip
+    if samples.iter().any(|s| ms(s.rtt_ns) / 2.0 > args.max_skew_ms as f64) {
+        report.warn("round trips exceed twice --max-skew-ms; skew figures are not precise enough to trust");
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_output() {
+        let r = parse_probe("NOW 1700000000123456789\nSYNC chrony Normal\n");
+        assert_eq!(r.now_ns, Some(1_700_000_000_123_456_789));
+        assert_eq!(r.synced(), Some(true));
+        assert_eq!(parse_probe("NOW 1\nSYNC ntpstat unsynchronised\n").synced(), Some(false));
+        assert_eq!(parse_probe("NOW 1\nSYNC timedatectl \n").synced(), None);
+        assert_eq!(parse_probe("garbage").now_ns, None);
+    }
+
+    #[test]
+    fn offset_midpoint() {
+        assert_eq!(offset_ns(1_000, 3_000, 2_000), 0);
+        assert_eq!(offset_ns(1_000, 3_000, 2_500), 500);
+        assert_eq!(offset_ns(1_000, 1_000, 400), -600);
+    }
+}
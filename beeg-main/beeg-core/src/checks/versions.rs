@@ -0,0 +1,197 @@
+use crate::notes::NoteKind;
+use crate::config;
+use clap::Args;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use super::{wrap_timeout, Check, CheckReport, CheckResult, Severity};
+
+#[derive(Debug, Args)]
+pub struct VersionsArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    pub selector: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+}
+
+pub(crate) const PACKAGES: &[&str] = &["beegfs-client", "beegfs-meta", "beegfs-storage", "beegfs-mgmtd"];
+
+fn probe_script() -> String {
+    // rpm first, then dpkg (installed packages only); modinfo looks under the running kernel
+    format!(r#"echo "KERNEL $(uname -r)"
+for p in {}; do
+  v=$(rpm -q --qf '%{{VERSION}}-%{{RELEASE}}' "$p" 2>/dev/null) || v=$(dpkg-query -W -f='${{db:Status-Abbrev}}${{Version}}' "$p" 2>/dev/null | sed -n 's/^ii *//p')
+  echo "PKG $p ${{v:-none}}"
+done
+m=$(modinfo -F version beegfs 2>/dev/null | head -n1); echo "MODULE ${{m:-none}}"
+lsmod | grep -q '^beegfs ' && echo "LOADED yes" || echo "LOADED no""#, PACKAGES.join(" "))
+}
+
+/// Installed BeegFS pieces on one node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct NodeVersions {
+    pub kernel: String,
+    pub packages: BTreeMap<String, String>,
+    /// `beegfs` module version for the running kernel, if one is built
+    pub module: Option<String>,
+    pub loaded: bool,
+}
+
+pub(crate) fn parse_probe(text: &str) -> NodeVersions {
+    let mut v = NodeVersions::default();
+    for line in text.lines() {
+        let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match key {
+            "KERNEL" => v.kernel = rest.to_string(),
+            "PKG" => {
+                if let Some((name, ver)) = rest.split_once(' ') {
+                    if ver != "none" { v.packages.insert(name.to_string(), ver.trim().to_string()); }
+                }
+            }
+            "MODULE" if rest != "none" && !rest.is_empty() => v.module = Some(rest.to_string()),
+            "LOADED" => v.loaded = rest == "yes",
+            _ => {}
+        }
+    }
+    v
+}
+
+/// Upstream version without the distro release: `7.4.2-1.el9` -> `7.4.2`.
+pub(crate) fn upstream(version: &str) -> &str {
+    version.split(['-', '~', '+']).next().unwrap_or(version)
+}
+
+/// `major.minor` of a version string.
+fn major_minor(version: &str) -> String {
+    upstream(version).split('.').take(2).collect::<Vec<_>>().join(".")
+}
+
+type Issues = Vec<(Severity, String)>;
+
+/// Per-node problems: a client without a module for the running kernel, or a module from another release.
+pub(crate) fn node_issues(v: &NodeVersions) -> Issues {
+    let mut issues = Vec::new();
+    if let Some(client) = v.packages.get("beegfs-client") {
+        match &v.module {
+            None => issues.push((Severity::Failure, format!("beegfs-client installed but no beegfs module built for kernel {}", v.kernel))),
+            Some(m) if major_minor(m) != major_minor(client) => {
+                issues.push((Severity::Warning, format!("beegfs module {} does not match beegfs-client {}", m, client)));
+            }
+            Some(_) if !v.loaded => issues.push((Severity::Warning, "beegfs module built but not loaded".to_string())),
+            _ => {}
+        }
+    }
+    let local: BTreeSet<&str> = v.packages.values().map(|p| upstream(p)).collect();
+    if local.len() > 1 {
+        issues.push((Severity::Warning, format!("mixed BeegFS packages on the node: {}", local.into_iter().collect::<Vec<_>>().join(", "))));
+    }
+    issues
+}
+
+pub struct Versions;
+
+impl Check for Versions {
+    const NAME: &'static str = "versions";
+    const ABOUT: &'static str = "Kernel and BeegFS package version matrix across nodes";
+    type Args = VersionsArgs;
+
+    fn run(_cli: &crate::GlobalArgs, cfg: &config::Config, args: &VersionsArgs) -> anyhow::Result<CheckReport> {
+        Ok(run(cfg, args))
+    }
+}
+
+pub fn run(cfg: &config::Config, args: &VersionsArgs) -> CheckReport {
+    let nodes = config::select_nodes(cfg, &args.selector);
+    let script = wrap_timeout(&probe_script(), args.timeout);
+    let results: Vec<(&config::Node, Result<NodeVersions, String>, Duration)> = super::per_node(cfg, &nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &script).map(|o| parse_probe(&o.stdout)).map_err(|e| super::exec_error(&e))
+    });
+
+    // (node, versions, issues, probe time)
+    let rows: Vec<(&config::Node, Option<&NodeVersions>, Issues, Duration)> = results.iter().map(|(n, r, took)| match r {
+        Ok(v) if v.kernel.is_empty() => (*n, None, vec![(Severity::Failure, "no output from probe".to_string())], *took),
+        Ok(v) => (*n, Some(v), node_issues(v), *took),
+        Err(e) => (*n, None, vec![(Severity::Failure, e.clone())], *took),
+    }).collect();
+
+    // cluster-wide: every BeegFS package should come from the same release
+    let mut by_version: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (n, v, _, _) in &rows {
+        for p in v.iter().flat_map(|v| v.packages.values()) {
+            by_version.entry(upstream(p)).or_default().insert(n.name.as_str());
+        }
+    }
+
+    let mut columns = vec!["Kernel"];
+    columns.extend(PACKAGES.iter().map(|p| p.trim_start_matches("beegfs-")));
+    columns.push("Module");
+    let mut report = CheckReport::new(Versions::NAME, &columns);
+    let cell = |v: Option<&NodeVersions>, p: &str| v.and_then(|v| v.packages.get(p)).cloned().unwrap_or_else(|| "-".into());
+    for (n, v, issues, took) in &rows {
+        let mut cells = vec![v.map(|v| v.kernel.clone()).unwrap_or_else(|| "-".into())];
+        cells.extend(PACKAGES.iter().map(|p| cell(*v, p)));
+        cells.push(match v {
+            Some(NodeVersions { module: Some(m), loaded, .. }) => format!("{}{}", m, if *loaded { "" } else { " (not loaded)" }),
+            _ => "-".into(),
+        });
+        let notes: Vec<&str> = issues.iter().map(|(_, m)| m.as_str()).collect();
+        report.push(CheckResult {
+            check: Versions::NAME,
+            node: n.name.clone(),
+            status: issues.iter().map(|(s, _)| *s).max().unwrap_or_default(),
+            detail: notes.join("; "),
+            duration: *took,
+            cells,
+            data: serde_json::json!({
+                "kernel": v.map(|v| &v.kernel),
+                "packages": v.map(|v| &v.packages),
+                "module": v.and_then(|v| v.module.as_ref()),
+                "module_loaded": v.map(|v| v.loaded),
+                "issues": notes,
+            }),
+        });
+    }
+
+    if by_version.len() > 1 {
+        let groups: Vec<String> = by_version.iter()
+            .map(|(ver, nodes)| format!("{} on {}", ver, nodes.iter().copied().collect::<Vec<_>>().join(", ")))
+            .collect();
+        report.warn(format!("mixed BeegFS versions: {}", groups.join("; ")));
+        report.floor = Severity::Warning;
+    }
+    let broken: Vec<&str> = rows.iter()
+        .filter(|(_, _, i, _)| i.iter().any(|(_, m)| m.starts_with("beegfs-client installed but")))
+        .map(|(n, _, _, _)| n.name.as_str()).collect();
+    if !broken.is_empty() { report.warn_about(format!("beegfs client module missing for the running kernel on: {}", broken.join(", ")), NoteKind::Node, &broken); }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_and_issues() {
+        let v = parse_probe("KERNEL 5.14.0-362.el9.x86_64\nPKG beegfs-client 7.4.2-1.el9\nPKG beegfs-meta none\nPKG beegfs-storage none\nPKG beegfs-mgmtd none\nMODULE none\nLOADED no\n");
+        assert_eq!(v.packages.len(), 1);
+        assert_eq!(v.module, None);
+        let issues = node_issues(&v);
+        assert_eq!(issues[0].0, Severity::Failure);
+
+        let ok = parse_probe("KERNEL 6.1\nPKG beegfs-client 7.4.2-1\nPKG beegfs-utils 7.4.2-1\nMODULE 7.4.2\nLOADED yes\n");
+        assert!(node_issues(&ok).is_empty());
+
+        let skewed = parse_probe("KERNEL 6.1\nPKG beegfs-client 7.4.2-1\nPKG beegfs-storage 7.3.4-1\nMODULE 7.3.4\nLOADED yes\n");
+        assert_eq!(node_issues(&skewed).len(), 2);
+    }
+
+    #[test]
+    fn upstream_versions() {
+        assert_eq!(upstream("7.4.2-1.el9"), "7.4.2");
+        assert_eq!(upstream("7.4.2"), "7.4.2");
+        assert_eq!(major_minor("7.4.2-1.el9"), "7.4");
+    }
+}
@@ -0,0 +1,191 @@
+use comfy_table::{presets::UTF8_FULL, Attribute, Cell, Color, Table};
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use super::{CheckCmd, CheckReport, CheckResult, Severity};
+use crate::config;
+
+/// Table rows of one run by check, node and first check-specific cell; that cell tells
+/// several rows of one node apart (a target ID, a host).
+type Snapshot = BTreeMap<(String, String, String), Vec<String>>;
+
+fn key(check: &str, r: &CheckResult) -> (String, String, String) {
+    (check.to_string(), r.node.clone(), r.cells.first().cloned().unwrap_or_default())
+}
+
+fn snapshot(report: &CheckReport) -> Snapshot {
+    let mut snap = Snapshot::new();
+    for s in &report.sections { snap.extend(snapshot(s)); }
+    for r in &report.results { snap.insert(key(report.check, r), r.row()); }
+    snap
+}
+
+/// Per cell of `row`, whether it differs from the previous run; every cell of a new row does.
+fn changed(prev: Option<&Snapshot>, check: &str, r: &CheckResult, row: &[String]) -> Vec<bool> {
+    match prev.map(|p| p.get(&key(check, r))) {
+        None => vec![false; row.len()],
+        Some(None) => vec![true; row.len()],
+        Some(Some(old)) => row.iter().enumerate().map(|(i, c)| old.get(i) != Some(c)).collect(),
+    }
+}
+
+/// `{check, node, column, from, to}` for every cell that changed since the previous run.
+fn changes(prev: Option<&Snapshot>, report: &CheckReport) -> Vec<serde_json::Value> {
+    let mut out: Vec<serde_json::Value> = report.sections.iter().flat_map(|s| changes(prev, s)).collect();
+    let header = report.header();
+    for r in &report.results {
+        let row = r.row();
+        let old = prev.and_then(|p| p.get(&key(report.check, r)));
+        for (i, flag) in changed(prev, report.check, r, &row).into_iter().enumerate() {
+            if !flag { continue; }
+            out.push(serde_json::json!({
+                "check": report.check,
+                "node": r.node,
+                "column": header.get(i),
+                "from": old.and_then(|o| o.get(i)),
+                "to": row[i],
+            }));
+        }
+    }
+    out
+}
+
+fn table(report: &CheckReport, prev: Option<&Snapshot>) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(report.header());
+    for r in &report.results {
+        let row = r.row();
+        let flags = changed(prev, report.check, r, &row);
+        table.add_row(row.into_iter().zip(flags).zip(r.cells_colored()).map(|((c, changed), cell)| {
+            if changed && crate::tables::color() { Cell::new(c).fg(Color::Yellow).add_attribute(Attribute::Bold) } else { cell }
+        }));
+    }
+    table
+}
+
+fn print_report(report: &CheckReport, prev: Option<&Snapshot>) {
+    for s in &report.sections {
+        println!("== {} ==", s.check);
+        print_report(s, prev);
+    }
+    if !report.sections.is_empty() { println!("== summary =="); }
+    for line in &report.preamble { println!("{}", line); }
+    if !report.results.is_empty() { println!("{}", table(report, prev)); }
+}
+
+/// Warnings go under the table: stderr would scroll away when the screen is redrawn.
+fn print_warnings(report: &CheckReport) {
+    for s in &report.sections { print_warnings(s); }
+    for w in &report.warnings {
+        println!("WARNING: {}", w.message);
+        for line in &w.lines { println!("  {}", line); }
+    }
+}
+
+/// Rerun `cmd` every `every` seconds until interrupted. Human output redraws the screen
+/// with changed cells highlighted; JSON output is one line per run with its changes, and
+/// csv/tsv output adds each run's rows under one header, with the run and time in front.
+/// A run that errors (e.g. while nodes reboot) is shown and the next one goes ahead.
+pub fn run(cli: &crate::GlobalArgs, cfg: &config::Config, cmd: &CheckCmd, every: u64) -> anyhow::Result<Severity> {
+    let redraw = std::io::stdout().is_terminal();
+    let mut prev: Option<Snapshot> = None;
+    let mut iteration = 0u64;
+    let mut plain_header: Option<Vec<String>> = None;
+    loop {
+        iteration += 1;
+        let started = Instant::now();
+        let started_at = chrono::Utc::now();
+        let result = cmd.run(cli, cfg).map(|mut r| { r.sort_nodes(cfg, cli.sort); r });
+        if let Ok(r) = &result {
+            crate::history::record(r, started_at);
+            crate::alerts::process(cfg, r);
+        }
+        let now = chrono::Utc::now();
+        match cli.output {
+            crate::Output::Human | crate::Output::Nagios => {
+                if redraw { print!("\x1b[H\x1b[2J"); }
+                println!("Every {}s: beeg check {}    {} (run {})", every, cmd.name, crate::timefmt::human(now, cli.utc), iteration);
+                match &result {
+                    Ok(r) => {
+                        print_report(r, prev.as_ref());
+                        println!("Status: {}", crate::tables::paint(r.severity().as_str(), r.severity()));
+                        print_warnings(r);
+                    }
+                    Err(e) => println!("Error: {:#}", e),
+                }
+                if !redraw { println!(); }
+            }
+            crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+                let mut line = serde_json::json!({ "iteration": iteration, "time": crate::timefmt::rfc3339(now) });
+                match &result {
+                    Ok(r) => {
+                        line["report"] = r.to_json();
+                        line["changes"] = changes(prev.as_ref(), r).into();
+                    }
+                    Err(e) => line["error"] = format!("{:#}", e).into(),
+                }
+                crate::formats::print_record(cli.output, &line)?;
+            }
+            crate::Output::Csv | crate::Output::Tsv => {
+                let sep = crate::tables::separator(cli.output).unwrap_or(',');
+                let time = crate::timefmt::rfc3339(now);
+                match &result {
+                    Ok(r) => {
+                        let (header, rows) = r.plain_rows();
+                        let header = [vec!["Run".to_string(), "Time".to_string()], header].concat();
+                        // a header again only when the columns changed
+                        if plain_header.as_ref() != Some(&header) {
+                            println!("{}", crate::tables::line(&header, sep));
+                            plain_header = Some(header);
+                        }
+                        for row in rows { println!("{}", crate::tables::line(&[vec![iteration.to_string(), time.clone()], row].concat(), sep)); }
+                    }
+                    Err(e) => eprintln!("{} run {}: error: {:#}", time, iteration, e),
+                }
+            }
+        }
+        std::io::stdout().flush()?;
+        super::summary::write_all(&cmd.report_files(), &cmd.name, result.as_ref(), started_at, cli.strict)?;
+        if let Ok(r) = &result { prev = Some(snapshot(r)); }
+        std::thread::sleep(Duration::from_secs(every).saturating_sub(started.elapsed()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(state: &str) -> CheckReport {
+        let mut r = CheckReport::new("storage-target", &["Target", "State"]);
+        for (target, st) in [("101", "Online"), ("102", state)] {
+            r.results.push(CheckResult {
+                check: "storage-target",
+                node: "stor01".into(),
+                status: if st == "Online" { Severity::Ok } else { Severity::Failure },
+                detail: String::new(),
+                duration: Duration::ZERO,
+                cells: vec![target.into(), st.into()],
+                data: serde_json::json!({}),
+            });
+        }
+        r
+    }
+
+    #[test]
+    fn changes_between_runs() {
+        let before = snapshot(&report("Offline"));
+        let after = report("Online");
+        assert!(changes(None, &after).is_empty());
+        let c = changes(Some(&before), &after);
+        assert_eq!(c.len(), 2);
+        assert_eq!((c[0]["column"].as_str(), c[0]["from"].as_str(), c[0]["to"].as_str()), (Some("State"), Some("Offline"), Some("Online")));
+        assert_eq!(c[1]["column"], "Status");
+
+        let mut grown = report("Online");
+        grown.results[1].cells[0] = "103".into();
+        let row = grown.results[1].row();
+        assert!(changed(Some(&before), "storage-target", &grown.results[1], &row).iter().all(|c| *c));
+    }
+}
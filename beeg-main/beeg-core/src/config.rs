@@ -0,0 +1,537 @@
+use crate::formats::Format;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Node {
+    pub name: String,
+    pub host: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// SSH user for this node; overrides the top-level `ssh_user`
+    #[serde(default, alias = "user", skip_serializing_if = "Option::is_none")]
+    pub ssh_user: Option<String>,
+    #[serde(default, alias = "port", skip_serializing_if = "Option::is_none")]
+    pub ssh_port: Option<u16>,
+    /// Private key to log in with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    /// Jump host: another node's name, or `[user@]host[:port]`
+    #[serde(default, alias = "jump", skip_serializing_if = "Option::is_none")]
+    pub proxy_jump: Option<String>,
+    /// Run commands through sudo here; overrides the top-level `become`
+    #[serde(rename = "become", default, skip_serializing_if = "Option::is_none")]
+    pub use_sudo: Option<bool>,
+    /// User sudo switches to (root when unset); implies `become`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sudo_user: Option<String>,
+}
+
+/// Labels that name a node's BeeGFS role when it has no `role=` label.
+const ROLES: &[&str] = &["mgmtd", "mgmt", "meta", "storage", "client"];
+
+impl Node {
+    /// The `role=` label, else the first label that is a BeeGFS role; empty when there is none.
+    pub fn role(&self) -> &str {
+        self.labels.iter().find_map(|l| l.strip_prefix("role="))
+            .or_else(|| self.labels.iter().find(|l| ROLES.contains(&l.as_str())).map(String::as_str))
+            .unwrap_or("")
+    }
+}
+
+/// How nodes are ordered in output (`--sort`); which nodes are selected, and the order they
+/// are worked on, always follow the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum NodeOrder {
+    /// As listed in the config
+    #[default]
+    Config,
+    Name,
+    Host,
+    Role,
+}
+
+/// Which BeegFS instance this inventory belongs to; checked before mutating actions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterIdentity {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// sysMgmtdHost every node's beegfs-*.conf must name
+    pub mgmtd_host: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default = "default_transport")] 
+    pub transport: String, // "ssh" | "local"
+    #[serde(default)]
+    pub ssh_user: Option<String>,
+    /// Bastion for every node without its own `proxy_jump`: a node name or `[user@]host[:port]`
+    #[serde(default)]
+    pub ssh_jump_host: Option<String>,
+    /// Run remote commands through `sudo -n`
+    #[serde(rename = "become", default)]
+    pub use_sudo: bool,
+    #[serde(default)]
+    pub sudo_user: Option<String>,
+    /// Program on the nodes that prints the sudo password; uses `sudo -A` instead of `sudo -n`
+    #[serde(default)]
+    pub sudo_askpass: Option<String>,
+    #[serde(default)]
+    pub cluster: Option<ClusterIdentity>,
+    /// Default for `node exec --max-output` (e.g. "4M")
+    #[serde(default)]
+    pub exec_max_output: Option<String>,
+    /// Seconds before beeg gives up on a remote command, connecting included; no limit when unset
+    #[serde(default)]
+    pub exec_timeout: Option<u64>,
+    /// How often to try again when a node cannot be reached or a command times out
+    #[serde(default)]
+    pub exec_retries: u32,
+    /// Nodes probed at once; `DEFAULT_PARALLEL` when unset
+    #[serde(default)]
+    pub exec_parallel: Option<usize>,
+    /// Default for `quota show --warn-percent`
+    #[serde(default)]
+    pub quota_warn_percent: Option<u8>,
+    /// Version requirement for the beeg binary using this config, e.g. `^0.4` or `=0.4.2`
+    #[serde(default)]
+    pub required_beeg_version: Option<String>,
+    /// What happens when the running binary does not satisfy `required_beeg_version`
+    #[serde(default)]
+    pub beeg_version_policy: VersionPolicy,
+    /// Where check runs report nodes that start failing
+    #[serde(default)]
+    pub alerts: Alerts,
+    /// Where listings from the management service come from (`--backend` overrides it)
+    #[serde(default)]
+    pub backend: Backend,
+    /// Reaching the mgmtd's gRPC API, for `backend: beegfs-tool`
+    #[serde(default)]
+    pub grpc: Grpc,
+    /// Set for resumable check runs; every transport made from this config journals through it
+    #[serde(skip)]
+    pub journal: Option<std::sync::Arc<crate::journal::Journal>>,
+    /// Stops this config's commands when cancelled; the process-wide token (Ctrl-C) otherwise
+    #[serde(skip)]
+    pub cancel: Option<crate::transport::cancel::CancelToken>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionPolicy {
+    /// Print a warning and carry on
+    #[default]
+    Warn,
+    /// Stop every command except `config` and `self`
+    Refuse,
+}
+
+/// How beeg reads targets, nodes, mirror groups and pools from the management service.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// beegfs-ctl (or BeeGFS 8's `beegfs`) on the queried node, over the transport
+    #[default]
+    Ssh,
+    /// BeeGFS 8's `beegfs` tool on this machine, which asks the mgmtd over gRPC
+    #[serde(alias = "grpc")]
+    BeegfsTool,
+}
+
+/// Port the BeeGFS 8 mgmtd serves gRPC on unless `grpc.mgmtd` names another.
+pub const GRPC_PORT: u16 = 8010;
+
+/// Reaching the mgmtd's gRPC API from this machine.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Grpc {
+    /// `host[:port]` of the mgmtd; the node with role mgmtd when unset
+    #[serde(default)]
+    pub mgmtd: Option<String>,
+    /// CA certificate the mgmtd's TLS certificate is checked against
+    #[serde(default)]
+    pub tls_cert_file: Option<String>,
+    /// Plain-text gRPC, for a mgmtd running with `tls-disable`
+    #[serde(default)]
+    pub tls_disable: bool,
+    /// The cluster's shared secret (`connAuthFile`)
+    #[serde(default)]
+    pub auth_file: Option<String>,
+    /// Connect without the shared secret, for a mgmtd running with `auth-disable`; without
+    /// this or `auth_file` the tool reads its default `/etc/beegfs/conn.auth`
+    #[serde(default)]
+    pub auth_disable: bool,
+}
+
+impl Grpc {
+    /// `host:port` to query: `mgmtd` when set, else `node` when it is the mgmtd, else the first
+    /// node with role mgmtd, else `node`.
+    pub fn address(&self, cfg: &Config, node: &Node) -> String {
+        let host = match &self.mgmtd {
+            Some(m) => m.clone(),
+            None if node.role() == "mgmtd" => node.host.clone(),
+            None => cfg.nodes.iter().find(|n| n.role() == "mgmtd").unwrap_or(node).host.clone(),
+        };
+        if host.rsplit_once(':').is_some_and(|(_, p)| p.parse::<u16>().is_ok()) { host } else { format!("{}:{}", host, GRPC_PORT) }
+    }
+}
+
+/// Alerting for `beeg check` and `beeg daemon`: who hears about a node that starts failing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alerts {
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    /// Which results count as failing
+    #[serde(default)]
+    pub min_status: AlertLevel,
+    /// Alert again about a node still failing after this many hours; never when unset
+    #[serde(default)]
+    pub repeat_hours: Option<u64>,
+    /// Also report when an alerted node is OK again
+    #[serde(default = "default_true")]
+    pub send_resolved: bool,
+    /// Mail a digest with the failing checks' tables
+    #[serde(default)]
+    pub email: Option<Email>,
+}
+
+/// SMTP delivery of alert digests; the mail is sent with curl.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Email {
+    /// `smtp://host:587` (STARTTLS) or `smtps://host:465`
+    pub smtp_url: String,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Environment variable holding the SMTP password, so it stays out of the config
+    #[serde(default)]
+    pub password_env: Option<String>,
+    /// Refuse to send over a connection without TLS
+    #[serde(default = "default_true")]
+    pub require_tls: bool,
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Alerts { webhooks: Vec::new(), min_status: AlertLevel::default(), repeat_hours: None, send_resolved: true, email: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// beeg's own JSON: `{source, cluster, time, alerts: [...]}`
+    #[default]
+    Generic,
+    /// Slack incoming webhook (`text`)
+    Slack,
+    /// Microsoft Teams incoming webhook (MessageCard)
+    Teams,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertLevel {
+    /// Any result that is not OK
+    Warn,
+    /// FAIL results only
+    #[default]
+    Fail,
+}
+
+fn default_true() -> bool { true }
+
+fn default_transport() -> String { "ssh".to_string() }
+
+/// Nodes probed at once when `exec_parallel` is not set: each is an ssh session of its own.
+pub const DEFAULT_PARALLEL: usize = 32;
+
+/// How many nodes a check probes at once.
+pub fn parallel(cfg: &Config) -> usize {
+    cfg.exec_parallel.unwrap_or(DEFAULT_PARALLEL).max(1)
+}
+
+pub fn default_config_path() -> PathBuf {
+    if let Ok(p) = std::env::var("BEEG_CONFIG") { return PathBuf::from(p); }
+    if let Some(dir) = dirs::config_dir() {
+        let dir = dir.join("beeg");
+        return ["config.yaml", "config.yml", "config.toml"].iter().map(|f| dir.join(f))
+            .find(|p| p.exists())
+            .unwrap_or_else(|| dir.join("config.json"));
+    }
+    PathBuf::from("./beeg.config.json")
+}
+
+pub fn load(explicit: Option<&std::path::PathBuf>) -> Result<Config> {
+    let path = if let Some(p) = explicit { p.clone() } else { default_config_path() };
+    if path.exists() {
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("reading config file: {}", path.display()))?;
+        let format = Format::detect(&path, &data);
+        let value = format.parse(&data)
+            .with_context(|| format!("parsing config file: {}", path.display()))?;
+        let cfg: Config = serde_json::from_value(value)
+            .with_context(|| format!("parsing config file: {}", path.display()))?;
+        Ok(cfg)
+    } else {
+        // env fallback
+        let nodes = std::env::var("BEEG_NODES").ok().map(|s| {
+            s.split(',')
+                .filter(|x| !x.trim().is_empty())
+                .enumerate()
+                .map(|(i, host)| Node { name: format!("node-{}", i+1), host: host.trim().to_string(), ..Default::default() })
+                .collect::<Vec<_>>()
+        }).unwrap_or_default();
+        Ok(Config { nodes, transport: default_transport(), ssh_user: None, ssh_jump_host: None, use_sudo: false, sudo_user: None, sudo_askpass: None, cluster: None, exec_max_output: None, exec_timeout: None, exec_retries: 0, exec_parallel: None, quota_warn_percent: None, required_beeg_version: None, beeg_version_policy: VersionPolicy::Warn, alerts: Alerts::default(), backend: Backend::default(), grpc: Grpc::default(), journal: None, cancel: None })
+    }
+}
+
+/// Read a config file as a plain value, in whatever format it is in; `{}` if it does not exist yet.
+pub fn read_value(path: &Path) -> Result<(serde_json::Value, Format)> {
+    if !path.exists() {
+        // a new file gets the format its extension asks for, JSON otherwise
+        return Ok((serde_json::json!({}), Format::detect(path, "{}")));
+    }
+    let data = fs::read_to_string(path).with_context(|| format!("reading config file: {}", path.display()))?;
+    let format = Format::detect(path, &data);
+    let value = format.parse(&data).with_context(|| format!("parsing config file: {}", path.display()))?;
+    Ok((value, format))
+}
+
+/// Replace a config file with `value` in `format`, via a temp file next to it.
+pub fn write_value(path: &Path, value: &serde_json::Value, format: Format) -> Result<()> {
+    let text = format.render(value)?;
+    // check it loads before it replaces anything
+    serde_json::from_value::<Config>(format.parse(&text)?).context("rendered config does not load")?;
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("creating config dir: {}", dir.display()))?;
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "config".into());
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&tmp, text).with_context(|| format!("writing config file: {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replacing config file: {}", path.display()))?;
+    Ok(())
+}
+
+/// The jump host `node` connects through: its own `proxy_jump`, else `ssh_jump_host`, except
+/// on the jump host itself.
+pub fn first_hop(cfg: &Config, node: &Node) -> Option<String> {
+    if node.proxy_jump.is_some() { return node.proxy_jump.clone(); }
+    let global = cfg.ssh_jump_host.as_ref()?;
+    // `user@host:port` -> `host`
+    let bare = global.rsplit('@').next().unwrap_or(global);
+    let bare = bare.rsplit_once(':').map(|(h, _)| h).unwrap_or(bare);
+    if *global == node.name || bare == node.host || bare == node.name { return None; }
+    Some(global.clone())
+}
+
+/// Follow the jump hosts from node to node, nearest hop first; an entry that is not a node name
+/// ends the chain.
+pub fn jump_chain(cfg: &Config, node: &Node) -> Result<Vec<String>> {
+    let mut chain = Vec::new();
+    let mut seen = std::collections::BTreeSet::from([node.name.clone()]);
+    let mut next = first_hop(cfg, node);
+    while let Some(hop) = next {
+        if !seen.insert(hop.clone()) { anyhow::bail!("jump hosts of {} loop back to {}", node.name, hop); }
+        next = cfg.nodes.iter().find(|n| n.name == hop).and_then(|n| first_hop(cfg, n));
+        chain.push(hop);
+    }
+    Ok(chain)
+}
+
+/// `*` and `?` wildcard match; without wildcards it is plain equality.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut pi, mut ti) = (0, 0);
+    // where the last `*` was, and how much of the text it has taken so far
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) { pi += 1; ti += 1; }
+        else if pi < p.len() && p[pi] == '*' { star = Some((pi, ti)); pi += 1; }
+        else if let Some((sp, st)) = star { pi = sp + 1; ti = st + 1; star = Some((sp, st + 1)); }
+        else { return false; }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// One selector atom: `all`, `name=`/`host=`/`label=<glob>`, another `key=value` as a label,
+/// or a bare glob against name, host and labels.
+fn atom_matches(n: &Node, atom: &str) -> bool {
+    if atom.eq_ignore_ascii_case("all") { return true; }
+    match atom.split_once('=') {
+        Some(("name", p)) => glob_match(p, &n.name),
+        Some(("host", p)) => glob_match(p, &n.host),
+        Some(("label", p)) => n.labels.iter().any(|l| glob_match(p, l)),
+        Some(_) => n.labels.iter().any(|l| glob_match(atom, l)),
+        None => glob_match(atom, &n.name) || glob_match(atom, &n.host) || n.labels.iter().any(|l| glob_match(atom, l)),
+    }
+}
+
+/// Resolve a selector: comma-separated terms are a union, `a&b` an intersection, and `!term`
+/// removes nodes from the result (from all nodes if there are only exclusions).
+/// `gpu,node-0*,!rack=3` is every gpu or node-0* node outside rack 3.
+pub fn select_nodes<'a>(cfg: &'a Config, selector: &str) -> NodeSet<'a> {
+    let terms: Vec<&str> = selector.split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+    let (exclude, include): (Vec<&str>, Vec<&str>) = terms.into_iter().partition(|t| t.starts_with('!'));
+    let term = |n: &Node, t: &str| t.split('&').map(str::trim).all(|a| atom_matches(n, a));
+    NodeSet::from_config(cfg, |n| {
+        (include.is_empty() || include.iter().any(|t| term(n, t))) && !exclude.iter().any(|t| term(n, &t[1..]))
+    })
+}
+
+/// Selected nodes in canonical order: as listed in the config, each once. Every command gets
+/// its nodes from one, so runs list them the same way and their output diffs cleanly.
+#[derive(Debug, Clone, Default)]
+pub struct NodeSet<'a> {
+    nodes: Vec<&'a Node>,
+    /// Position of each node in the config, ascending
+    index: Vec<usize>,
+}
+
+impl<'a> NodeSet<'a> {
+    pub fn from_config(cfg: &'a Config, mut keep: impl FnMut(&Node) -> bool) -> Self {
+        let (index, nodes) = cfg.nodes.iter().enumerate().filter(|(_, n)| keep(n)).unzip();
+        NodeSet { nodes, index }
+    }
+
+    /// Add a node of `cfg` in its canonical place; no-op when it is already in the set.
+    pub fn insert(&mut self, cfg: &'a Config, node: &'a Node) {
+        let Some(i) = cfg.nodes.iter().position(|n| std::ptr::eq(n, node)) else { return };
+        if let Err(at) = self.index.binary_search(&i) {
+            self.index.insert(at, i);
+            self.nodes.insert(at, node);
+        }
+    }
+
+    /// The node's position in the config: stable across selectors and commands.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| n.name == name).map(|p| self.index[p])
+    }
+}
+
+impl<'a> std::ops::Deref for NodeSet<'a> {
+    type Target = [&'a Node];
+    fn deref(&self) -> &Self::Target { &self.nodes }
+}
+
+impl<'a> IntoIterator for NodeSet<'a> {
+    type Item = &'a Node;
+    type IntoIter = std::vec::IntoIter<&'a Node>;
+    fn into_iter(self) -> Self::IntoIter { self.nodes.into_iter() }
+}
+
+impl<'a, 'b> IntoIterator for &'b NodeSet<'a> {
+    type Item = &'b &'a Node;
+    type IntoIter = std::slice::Iter<'b, &'a Node>;
+    fn into_iter(self) -> Self::IntoIter { self.nodes.iter() }
+}
+
+/// Sort key of a node name under `order`, ties broken by config position; `None` for names
+/// that are not config nodes (targets, remote server names).
+pub fn order_key(cfg: &Config, order: NodeOrder, name: &str) -> Option<(String, usize)> {
+    let (i, n) = cfg.nodes.iter().enumerate().find(|(_, n)| n.name == name)?;
+    let key = match order {
+        NodeOrder::Config => String::new(),
+        NodeOrder::Name => n.name.clone(),
+        NodeOrder::Host => n.host.clone(),
+        NodeOrder::Role => n.role().to_string(),
+    };
+    Some((key, i))
+}
+
+/// Reorder output rows by their node for `--sort`. Rows of other names keep their relative
+/// order after the node rows; `NodeOrder::Config` leaves rows as they are.
+pub fn sort_rows<T>(cfg: &Config, order: NodeOrder, rows: &mut [T], node: impl Fn(&T) -> &str) {
+    if order == NodeOrder::Config { return; }
+    rows.sort_by_cached_key(|r| match order_key(cfg, order, node(r)) {
+        Some(k) => (0, k),
+        None => (1, (String::new(), 0)),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selectors() {
+        let node = |name: &str, host: &str, labels: &[&str]| Node { name: name.into(), host: host.into(), labels: labels.iter().map(|l| l.to_string()).collect(), ..Default::default() };
+        let cfg = Config {
+            nodes: vec![
+                node("node-01", "10.0.0.1", &["gpu", "rack=2"]),
+                node("node-02", "10.0.0.2", &["gpu", "rack=3"]),
+                node("stor01", "10.0.1.1", &["storage", "rack=2"]),
+            ],
+            ..load(Some(&PathBuf::from("/nonexistent"))).unwrap()
+        };
+        let names = |sel: &str| select_nodes(&cfg, sel).iter().map(|n| n.name.as_str()).collect::<Vec<_>>().join(" ");
+        assert_eq!(names("all"), "node-01 node-02 stor01");
+        assert_eq!(names("gpu"), "node-01 node-02");
+        assert_eq!(names("10.0.1.1"), "stor01");
+        assert_eq!(names("node-0*"), "node-01 node-02");
+        assert_eq!(names("label=gpu&rack=2"), "node-01");
+        assert_eq!(names("gpu,rack=2"), "node-01 node-02 stor01");
+        assert_eq!(names("!storage"), "node-01 node-02");
+        assert_eq!(names("rack=2,!gpu"), "stor01");
+        assert_eq!(names("host=10.0.0.?"), "node-01 node-02");
+        assert_eq!(names("name=*01"), "node-01 stor01");
+        assert_eq!(names("nomatch"), "");
+        assert!(glob_match("a*b*c", "aXbYbc") && !glob_match("a*b", "ac"));
+    }
+
+    #[test]
+    fn node_sets() {
+        let node = |name: &str, host: &str, labels: &[&str]| Node { name: name.into(), host: host.into(), labels: labels.iter().map(|l| l.to_string()).collect(), ..Default::default() };
+        let cfg = Config {
+            nodes: vec![node("stor02", "10.0.1.2", &["storage"]), node("c01", "10.0.2.1", &["role=client", "gpu"]), node("meta01", "10.0.0.9", &["meta"])],
+            ..load(Some(&PathBuf::from("/nonexistent"))).unwrap()
+        };
+        let mut set = select_nodes(&cfg, "!storage");
+        assert_eq!(set.index_of("meta01"), Some(2));
+        set.insert(&cfg, &cfg.nodes[0]);
+        set.insert(&cfg, &cfg.nodes[0]);
+        assert_eq!(set.iter().map(|n| n.name.as_str()).collect::<Vec<_>>(), vec!["stor02", "c01", "meta01"]);
+        assert_eq!(cfg.nodes[1].role(), "client");
+        assert_eq!(cfg.grpc.address(&cfg, &cfg.nodes[0]), "10.0.1.2:8010");
+        let grpc = Grpc { mgmtd: Some("mgmt01:9010".into()), ..Default::default() };
+        assert_eq!(grpc.address(&cfg, &cfg.nodes[0]), "mgmt01:9010");
+        // configs written before the rename still load
+        assert_eq!(serde_json::from_str::<Backend>(r#""grpc""#).unwrap(), Backend::BeegfsTool);
+        assert_eq!(serde_json::to_string(&Backend::BeegfsTool).unwrap(), r#""beegfs-tool""#);
+
+        let sorted = |order: NodeOrder| {
+            let mut rows = vec!["c01", "target 101", "meta01", "stor02"];
+            sort_rows(&cfg, order, &mut rows, |r| r);
+            rows.join(" ")
+        };
+        assert_eq!(sorted(NodeOrder::Config), "c01 target 101 meta01 stor02");
+        assert_eq!(sorted(NodeOrder::Name), "c01 meta01 stor02 target 101");
+        assert_eq!(sorted(NodeOrder::Host), "meta01 stor02 c01 target 101");
+        assert_eq!(sorted(NodeOrder::Role), "c01 meta01 stor02 target 101");
+    }
+
+    #[test]
+    fn sample_configs_agree() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+        let names = |f: &str| {
+            let cfg = load(Some(&dir.join(f))).unwrap();
+            cfg.nodes.iter().map(|n| (n.name.clone(), n.host.clone(), n.labels.clone())).collect::<Vec<_>>()
+        };
+        let json = names("config.sample.json");
+        assert_eq!(json.len(), 3);
+        assert_eq!(names("config.sample.yaml"), json);
+        assert_eq!(names("config.sample.toml"), json);
+    }
+}
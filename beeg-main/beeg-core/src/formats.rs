@@ -0,0 +1,860 @@
+//! YAML and TOML readers for config-style documents, producing `serde_json::Value` so the
+//! same serde structs load from any of them.
+//!
+//! These are written here because beeg builds from a vendored, offline set of crates that has
+//! no YAML or TOML parser, and inventories and configs only need a small part of either spec.
+//! What is supported:
+//!
+//! - YAML: block mappings and sequences, one-line flow `[..]`/`{..}`, quoted and plain
+//!   scalars, `|`/`>` block scalars, one document (an opening `---` and closing `...` are fine).
+//! - TOML: tables, arrays of tables, dotted keys, strings, decimal numbers, booleans, arrays,
+//!   one-line inline tables, and offset/local date-times, dates and times, kept as strings.
+//!
+//! Anything outside that is an error naming the feature, not a different parse: YAML anchors,
+//! aliases, merge keys, tags, directives, complex keys and multi-document streams; TOML hex,
+//! octal and binary integers, `inf`/`nan`, malformed dates, inline tables over several lines or
+//! with a trailing comma, and tables defined twice.
+//! `Format::render` writes a value back in block YAML or TOML that these readers accept, and
+//! `print` writes what commands show for `--output json`, `jsonl` and `yaml` with it.
+
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::LazyLock;
+
+static TOML_LINE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^(\[\[?[A-Za-z0-9_."' -]+\]\]?|[A-Za-z0-9_."-]+\s*=.*)$"#).unwrap());
+static INT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[-+]?[0-9]+$").unwrap());
+static FLOAT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[-+]?([0-9]+(\.[0-9]*)?|\.[0-9]+)([eE][-+]?[0-9]+)?$").unwrap());
+/// TOML offset or local date-time, local date, or local time.
+static TOML_DATETIME: LazyLock<Regex> = LazyLock::new(|| Regex::new(
+    r"^([0-9]{4}-[0-9]{2}-[0-9]{2}([Tt ][0-9]{2}:[0-9]{2}:[0-9]{2}(\.[0-9]+)?([Zz]|[-+][0-9]{2}:[0-9]{2})?)?|[0-9]{2}:[0-9]{2}:[0-9]{2}(\.[0-9]+)?)$"
+).unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// By file extension, falling back to a look at the content.
+    pub fn detect(path: &Path, text: &str) -> Format {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("json") => Format::Json,
+            Some("yaml") | Some("yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            _ => Format::sniff(text),
+        }
+    }
+
+    fn sniff(text: &str) -> Format {
+        let first = text.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with('#')).unwrap_or("");
+        if first.starts_with('{') { return Format::Json; }
+        if TOML_LINE.is_match(first) { Format::Toml } else { Format::Yaml }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Value> {
+        match self {
+            Format::Json => Ok(serde_json::from_str(text)?),
+            Format::Yaml => parse_yaml(text),
+            Format::Toml => parse_toml(text),
+        }
+    }
+
+    pub fn render(self, value: &Value) -> Result<String> {
+        match self {
+            Format::Json => Ok(serde_json::to_string_pretty(value)? + "\n"),
+            Format::Yaml => Ok(match value {
+                Value::Object(m) if !m.is_empty() => yaml_lines(value).join("\n") + "\n",
+                Value::Array(a) if !a.is_empty() => yaml_lines(value).join("\n") + "\n",
+                v => yaml_out(v) + "\n",
+            }),
+            Format::Toml => {
+                let map = value.as_object().ok_or_else(|| anyhow!("TOML needs a table at the top level"))?;
+                let mut out = String::new();
+                toml_table(map, &[], &mut out)?;
+                Ok(out.trim_start().to_string())
+            }
+        }
+    }
+}
+
+/// Print a command's result as `--output` asks for: pretty JSON, block YAML, or JSON lines
+/// (a line per item of a list, else one line).
+pub fn print(output: crate::Output, value: &impl serde::Serialize) -> Result<()> {
+    let value = serde_json::to_value(value)?;
+    match (output, &value) {
+        (crate::Output::Jsonl, Value::Array(items)) => for item in items { println!("{}", item); },
+        (crate::Output::Jsonl, v) => println!("{}", v),
+        (crate::Output::Yaml, v) => print!("{}", Format::Yaml.render(v)?),
+        (_, v) => print!("{}", Format::Json.render(v)?),
+    }
+    Ok(())
+}
+
+/// Print one record of a stream (a watched run, a line of output): a JSON line, or a YAML
+/// document starting with `---`.
+pub fn print_record(output: crate::Output, value: &Value) -> Result<()> {
+    match output {
+        crate::Output::Yaml => print!("---\n{}", Format::Yaml.render(value)?),
+        _ => println!("{}", serde_json::to_string(value)?),
+    }
+    Ok(())
+}
+
+// ---- YAML ----
+
+struct Line {
+    no: usize,
+    indent: usize,
+    text: String,
+}
+
+/// Cut a trailing `# comment`: a `#` at the start or after whitespace, outside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            // an apostrophe inside a plain scalar (`it's`) does not open a quote
+            (None, '"') | (None, '\'') if prev.is_whitespace() || matches!(prev, '[' | '{' | ',' | ':') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if prev.is_whitespace() => return &line[..i],
+            _ => {}
+        }
+        prev = c;
+    }
+    line
+}
+
+pub fn parse_yaml(text: &str) -> Result<Value> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut ended = false;
+    for (i, raw) in text.lines().enumerate() {
+        let body = strip_comment(raw).trim_end();
+        let trimmed = body.trim_start();
+        if trimmed.is_empty() { continue; }
+        if body.starts_with('%') { bail!("line {}: YAML directives ({}) are not supported", i + 1, trimmed); }
+        if trimmed == "---" || trimmed.starts_with("--- ") || trimmed == "..." {
+            if !lines.is_empty() || trimmed.starts_with("--- ") {
+                if trimmed == "..." { ended = true; continue; }
+                bail!("line {}: multi-document YAML and content after '---' are not supported; use one document per file", i + 1);
+            }
+            continue;
+        }
+        if ended { bail!("line {}: multi-document YAML is not supported; use one document per file", i + 1); }
+        let indent = body.len() - trimmed.len();
+        if body[..indent].contains('\t') { bail!("line {}: tabs are not allowed in YAML indentation", i + 1); }
+        lines.push(Line { no: i + 1, indent, text: trimmed.to_string() });
+    }
+    if lines.is_empty() { return Ok(Value::Null); }
+    let mut y = Yaml { lines, pos: 0, raw: text.lines().map(String::from).collect() };
+    let indent = y.lines[0].indent;
+    let v = y.block(indent)?;
+    if let Some(l) = y.lines.get(y.pos) { bail!("line {}: unexpected indentation", l.no); }
+    Ok(v)
+}
+
+struct Yaml {
+    lines: Vec<Line>,
+    pos: usize,
+    /// Source lines, for block scalars where comments and blank lines are content
+    raw: Vec<String>,
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Split `key: value` at the first `:` followed by a space or the end, outside quotes and brackets.
+fn split_key(text: &str) -> Option<(String, &str)> {
+    let mut quote = None;
+    let mut depth = 0;
+    let bytes = text.as_bytes();
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') if i == 0 => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '[') | (None, '{') => depth += 1,
+            (None, ']') | (None, '}') => depth -= 1,
+            (None, ':') if depth == 0 && (i + 1 == text.len() || bytes[i + 1] == b' ') => {
+                let key = match yaml_scalar(text[..i].trim()) {
+                    Ok(Value::String(s)) => s,
+                    Ok(Value::Null) => String::new(),
+                    Ok(v) => v.to_string(),
+                    Err(_) => return None,
+                };
+                return Some((key, text[i + 1..].trim()));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+impl Yaml {
+    fn block(&mut self, indent: usize) -> Result<Value> {
+        let line = &self.lines[self.pos];
+        if line.text == "?" || line.text.starts_with("? ") { bail!("line {}: complex keys ('? ') are not supported", line.no); }
+        if is_item(&line.text) { return self.seq(indent); }
+        if split_key(&line.text).is_some() { return self.map(indent); }
+        let v = flow(&line.text).map_err(|e| anyhow!("line {}: {}", line.no, e))?;
+        self.pos += 1;
+        Ok(v)
+    }
+
+    /// Whatever follows a `key:` or `-` with nothing after it on the line.
+    fn nested(&mut self, indent: usize, allow_item: bool) -> Result<Value> {
+        match self.lines.get(self.pos) {
+            Some(next) if next.indent > indent => { let i = next.indent; self.block(i) }
+            // a sequence may sit at the same indent as its mapping key
+            Some(next) if allow_item && next.indent == indent && is_item(&next.text) => self.seq(indent),
+            _ => Ok(Value::Null),
+        }
+    }
+
+    fn seq(&mut self, indent: usize) -> Result<Value> {
+        let mut out = Vec::new();
+        while let Some(line) = self.lines.get_mut(self.pos) {
+            if line.indent != indent || !is_item(&line.text) { break; }
+            let rest = line.text[1..].trim_start().to_string();
+            if rest.is_empty() {
+                self.pos += 1;
+                out.push(self.nested(indent, false)?);
+            } else {
+                // treat "- key: v" as a line of its own, indented to where the content starts
+                line.indent += line.text.len() - rest.len();
+                line.text = rest;
+                let i = line.indent;
+                out.push(self.block(i)?);
+            }
+        }
+        Ok(Value::Array(out))
+    }
+
+    fn map(&mut self, indent: usize) -> Result<Value> {
+        let mut out = Map::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent < indent { break; }
+            let no = line.no;
+            if line.indent > indent { bail!("line {}: unexpected indentation", no); }
+            if is_item(&line.text) { break; }
+            if line.text == "?" || line.text.starts_with("? ") { bail!("line {}: complex keys ('? ') are not supported", no); }
+            let (key, rest) = split_key(&line.text).ok_or_else(|| anyhow!("line {}: expected 'key: value'", no))?;
+            if key == "<<" { bail!("line {}: merge keys (<<) are not supported", no); }
+            let rest = rest.to_string();
+            self.pos += 1;
+            let value = if rest.is_empty() {
+                self.nested(indent, true)?
+            } else if rest.starts_with('|') || rest.starts_with('>') {
+                self.block_scalar(indent, no, rest.starts_with('>'))
+            } else {
+                flow(&rest).map_err(|e| anyhow!("line {}: {}", no, e))?
+            };
+            if out.insert(key.clone(), value).is_some() { bail!("line {}: duplicate key '{}'", no, key); }
+        }
+        Ok(Value::Object(out))
+    }
+
+    /// `|` keeps newlines, `>` folds them into spaces; both keep one trailing newline.
+    fn block_scalar(&mut self, indent: usize, header: usize, folded: bool) -> Value {
+        let mut body: Vec<&str> = Vec::new();
+        for raw in &self.raw[header..] {
+            let trimmed = raw.trim_start();
+            if !trimmed.is_empty() && raw.len() - trimmed.len() <= indent { break; }
+            body.push(raw);
+        }
+        while body.last().is_some_and(|l| l.trim().is_empty()) { body.pop(); }
+        let cut = body.iter().filter(|l| !l.trim().is_empty()).map(|l| l.len() - l.trim_start().len()).min().unwrap_or(0);
+        let text: Vec<&str> = body.iter().map(|l| if l.len() >= cut { &l[cut..] } else { "" }).collect();
+        let last = header + body.len();
+        while self.lines.get(self.pos).is_some_and(|l| l.no <= last) { self.pos += 1; }
+        Value::String(text.join(if folded { " " } else { "\n" }) + "\n")
+    }
+}
+
+/// Parse a one-line value: a flow collection or a scalar.
+fn flow(text: &str) -> Result<Value> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut pos = 0;
+    let v = flow_value(&chars, &mut pos, false)?;
+    skip_ws(&chars, &mut pos);
+    if pos != chars.len() { bail!("unexpected '{}' after value", chars[pos..].iter().collect::<String>()); }
+    Ok(v)
+}
+
+fn skip_ws(chars: &[char], pos: &mut usize) {
+    while chars.get(*pos).is_some_and(|c| c.is_whitespace()) { *pos += 1; }
+}
+
+fn flow_value(chars: &[char], pos: &mut usize, nested: bool) -> Result<Value> {
+    skip_ws(chars, pos);
+    match chars.get(*pos) {
+        Some('[') => {
+            *pos += 1;
+            let mut out = Vec::new();
+            loop {
+                skip_ws(chars, pos);
+                if chars.get(*pos) == Some(&']') { *pos += 1; return Ok(Value::Array(out)); }
+                out.push(flow_value(chars, pos, true)?);
+                skip_ws(chars, pos);
+                match chars.get(*pos) {
+                    Some(',') => *pos += 1,
+                    Some(']') => {}
+                    _ => bail!("unterminated [ ... ]"),
+                }
+            }
+        }
+        Some('{') => {
+            *pos += 1;
+            let mut out = Map::new();
+            loop {
+                skip_ws(chars, pos);
+                if chars.get(*pos) == Some(&'}') { *pos += 1; return Ok(Value::Object(out)); }
+                let key = match flow_value(chars, pos, true)? {
+                    Value::String(s) => s,
+                    v => v.to_string(),
+                };
+                skip_ws(chars, pos);
+                let value = if chars.get(*pos) == Some(&':') { *pos += 1; flow_value(chars, pos, true)? } else { Value::Null };
+                out.insert(key, value);
+                skip_ws(chars, pos);
+                match chars.get(*pos) {
+                    Some(',') => *pos += 1,
+                    Some('}') => {}
+                    _ => bail!("unterminated {{ ... }}"),
+                }
+            }
+        }
+        Some('"') => {
+            *pos += 1;
+            let mut s = String::new();
+            while let Some(&c) = chars.get(*pos) {
+                *pos += 1;
+                match c {
+                    '"' => return Ok(Value::String(s)),
+                    '\\' => { s.push(unescape(chars, pos)?); }
+                    _ => s.push(c),
+                }
+            }
+            bail!("unterminated double-quoted string")
+        }
+        Some('\'') => {
+            *pos += 1;
+            let mut s = String::new();
+            while let Some(&c) = chars.get(*pos) {
+                *pos += 1;
+                if c == '\'' {
+                    if chars.get(*pos) == Some(&'\'') { s.push('\''); *pos += 1; continue; }
+                    return Ok(Value::String(s));
+                }
+                s.push(c);
+            }
+            bail!("unterminated single-quoted string")
+        }
+        _ => {
+            let start = *pos;
+            while let Some(&c) = chars.get(*pos) {
+                // inside flow collections a plain scalar ends at an indicator; ':' only before a space
+                if nested && (matches!(c, ',' | ']' | '}') || (c == ':' && chars.get(*pos + 1).is_none_or(|n| n.is_whitespace()))) { break; }
+                *pos += 1;
+            }
+            yaml_scalar(chars[start..*pos].iter().collect::<String>().trim())
+        }
+    }
+}
+
+/// Backslash escape in a double-quoted string; `pos` is just past the backslash.
+fn unescape(chars: &[char], pos: &mut usize) -> Result<char> {
+    let c = *chars.get(*pos).ok_or_else(|| anyhow!("dangling backslash"))?;
+    *pos += 1;
+    Ok(match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '"' | '\\' | '/' | '\'' => c,
+        'u' | 'U' => {
+            let len = if c == 'u' { 4 } else { 8 };
+            let hex: String = chars.get(*pos..*pos + len).ok_or_else(|| anyhow!("short \\{} escape", c))?.iter().collect();
+            *pos += len;
+            u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).ok_or_else(|| anyhow!("bad escape \\{}{}", c, hex))?
+        }
+        _ => bail!("unknown escape \\{}", c),
+    })
+}
+
+/// Resolve a plain scalar the way YAML 1.2's core schema does.
+fn yaml_scalar(s: &str) -> Result<Value> {
+    if s.starts_with('"') || s.starts_with('\'') || s.starts_with('[') || s.starts_with('{') { return flow(s); }
+    match s.chars().next() {
+        Some('&') => bail!("anchors ({}) are not supported", s.split_whitespace().next().unwrap_or(s)),
+        Some('*') => bail!("aliases ({}) are not supported", s.split_whitespace().next().unwrap_or(s)),
+        Some('!') => bail!("tags ({}) are not supported", s.split_whitespace().next().unwrap_or(s)),
+        Some('@') | Some('`') => bail!("plain scalars cannot start with '{}'; quote the value", &s[..1]),
+        _ => {}
+    }
+    Ok(match s {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ => number(s).unwrap_or_else(|| Value::String(s.to_string())),
+    })
+}
+
+fn number(s: &str) -> Option<Value> {
+    if INT.is_match(s) { return s.trim_start_matches('+').parse::<i64>().ok().map(Value::from); }
+    if FLOAT.is_match(s) { return s.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number); }
+    None
+}
+
+fn is_block(v: &Value) -> bool {
+    match v {
+        Value::Object(m) => !m.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        _ => false,
+    }
+}
+
+/// A scalar or empty collection on one line; strings are quoted when they would read back as something else.
+fn yaml_out(v: &Value) -> String {
+    match v {
+        Value::String(s) => {
+            let plain = !s.is_empty()
+                && s.trim() == s
+                && !s.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+                && !s.contains(": ") && !s.contains(" #") && !s.contains('\n')
+                && yaml_scalar(s).ok() == Some(Value::String(s.clone()));
+            if plain { s.clone() } else { Value::String(s.clone()).to_string() }
+        }
+        Value::Array(_) => "[]".into(),
+        Value::Object(_) => "{}".into(),
+        v => v.to_string(),
+    }
+}
+
+/// Block YAML for a non-empty collection, unindented.
+fn yaml_lines(v: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    match v {
+        Value::Object(m) => for (k, x) in m {
+            let key = yaml_out(&Value::String(k.clone()));
+            if is_block(x) {
+                out.push(format!("{}:", key));
+                out.extend(yaml_lines(x).into_iter().map(|l| format!("  {}", l)));
+            } else {
+                out.push(format!("{}: {}", key, yaml_out(x)));
+            }
+        },
+        Value::Array(a) => for x in a {
+            if is_block(x) {
+                let sub = yaml_lines(x);
+                out.push(format!("- {}", sub[0]));
+                out.extend(sub[1..].iter().map(|l| format!("  {}", l)));
+            } else {
+                out.push(format!("- {}", yaml_out(x)));
+            }
+        },
+        v => out.push(yaml_out(v)),
+    }
+    out
+}
+
+// ---- TOML ----
+
+fn toml_key(k: &str) -> String {
+    if !k.is_empty() && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') { k.to_string() } else { Value::String(k.to_string()).to_string() }
+}
+
+fn toml_inline(v: &Value) -> Result<String> {
+    Ok(match v {
+        Value::Null => bail!("TOML has no null"),
+        Value::Array(a) => format!("[{}]", a.iter().map(toml_inline).collect::<Result<Vec<_>>>()?.join(", ")),
+        Value::Object(m) => {
+            let kv: Vec<String> = m.iter().filter(|(_, x)| !x.is_null())
+                .map(|(k, x)| Ok(format!("{} = {}", toml_key(k), toml_inline(x)?))).collect::<Result<_>>()?;
+            if kv.is_empty() { "{}".into() } else { format!("{{ {} }}", kv.join(", ")) }
+        }
+        v => v.to_string(),
+    })
+}
+
+fn is_table_array(v: &Value) -> bool {
+    v.as_array().is_some_and(|a| !a.is_empty() && a.iter().all(Value::is_object))
+}
+
+/// Plain keys first, then sub-tables, then arrays of tables; nulls are left out.
+fn toml_table(map: &Map<String, Value>, path: &[String], out: &mut String) -> Result<()> {
+    for (k, x) in map {
+        if x.is_null() || x.is_object() || is_table_array(x) { continue; }
+        out.push_str(&format!("{} = {}\n", toml_key(k), toml_inline(x)?));
+    }
+    for (k, x) in map {
+        let mut sub = path.to_vec();
+        sub.push(toml_key(k));
+        if let Value::Object(m) = x {
+            out.push_str(&format!("\n[{}]\n", sub.join(".")));
+            toml_table(m, &sub, out)?;
+        } else if is_table_array(x) {
+            for item in x.as_array().into_iter().flatten() {
+                out.push_str(&format!("\n[[{}]]\n", sub.join(".")));
+                toml_table(item.as_object().unwrap_or(&Map::new()), &sub, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_toml(text: &str) -> Result<Value> {
+    let mut t = Toml { chars: text.chars().collect(), pos: 0 };
+    let mut root = Map::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut defined: HashSet<Vec<String>> = HashSet::new();
+    loop {
+        t.skip_blank();
+        let Some(c) = t.peek() else { break };
+        let line = t.line();
+        let res: Result<()> = (|| {
+            if c == '[' {
+                let array = t.peek_at(1) == Some('[');
+                t.pos += if array { 2 } else { 1 };
+                let path = t.key_path()?;
+                t.expect(']')?;
+                if array { t.expect(']')?; }
+                let (last, parent) = path.split_last().ok_or_else(|| anyhow!("empty table name"))?;
+                if !array && !defined.insert(path.clone()) { bail!("table [{}] is defined twice", path.join(".")); }
+                let parent = table_at(&mut root, parent)?;
+                if array {
+                    let slot = parent.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new()));
+                    slot.as_array_mut().ok_or_else(|| anyhow!("'{}' is not an array of tables", last))?.push(Value::Object(Map::new()));
+                } else {
+                    let slot = parent.entry(last.clone()).or_insert_with(|| Value::Object(Map::new()));
+                    if !slot.is_object() { bail!("'{}' is already a value", last); }
+                }
+                current = path;
+            } else {
+                let path = t.key_path()?;
+                t.skip_ws();
+                t.expect('=')?;
+                let value = t.value()?;
+                let (last, parent) = path.split_last().ok_or_else(|| anyhow!("empty key"))?;
+                let mut full = current.clone();
+                full.extend(parent.iter().cloned());
+                let table = table_at(&mut root, &full)?;
+                if table.insert(last.clone(), value).is_some() { bail!("duplicate key '{}'", last); }
+            }
+            t.end_of_line()
+        })();
+        res.map_err(|e| anyhow!("line {}: {}", line, e))?;
+    }
+    Ok(Value::Object(root))
+}
+
+/// The table at `path`, creating missing ones; an array of tables resolves to its last entry.
+fn table_at<'a>(root: &'a mut Map<String, Value>, path: &[String]) -> Result<&'a mut Map<String, Value>> {
+    let mut table = root;
+    for key in path {
+        let slot = table.entry(key.clone()).or_insert_with(|| Value::Object(Map::new()));
+        let slot = match slot {
+            Value::Array(items) => items.last_mut().ok_or_else(|| anyhow!("'{}' is an empty array", key))?,
+            v => v,
+        };
+        table = slot.as_object_mut().ok_or_else(|| anyhow!("'{}' is not a table", key))?;
+    }
+    Ok(table)
+}
+
+struct Toml {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Toml {
+    fn peek(&self) -> Option<char> { self.chars.get(self.pos).copied() }
+    fn peek_at(&self, n: usize) -> Option<char> { self.chars.get(self.pos + n).copied() }
+    fn line(&self) -> usize { self.chars[..self.pos].iter().filter(|&&c| c == '\n').count() + 1 }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(' ') | Some('\t')) { self.pos += 1; }
+    }
+
+    /// Whitespace, newlines and comments.
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += 1,
+                Some('#') => while !matches!(self.peek(), None | Some('\n')) { self.pos += 1 },
+                _ => return,
+            }
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_ws();
+        if self.peek() != Some(c) { bail!("expected '{}'", c); }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_ws();
+        if self.peek() == Some('#') { while !matches!(self.peek(), None | Some('\n')) { self.pos += 1; } }
+        match self.peek() {
+            None | Some('\n') | Some('\r') => Ok(()),
+            Some(c) => bail!("unexpected '{}' after value", c),
+        }
+    }
+
+    fn key_path(&mut self) -> Result<Vec<String>> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_ws();
+            let key = match self.peek() {
+                Some('"') | Some('\'') => self.string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') { self.pos += 1; }
+                    if start == self.pos { bail!("expected a key"); }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            path.push(key);
+            self.skip_ws();
+            if self.peek() != Some('.') { return Ok(path); }
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_ws();
+        match self.peek() {
+            Some('"') | Some('\'') => Ok(Value::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut out = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.peek() == Some(']') { self.pos += 1; return Ok(Value::Array(out)); }
+                    out.push(self.value()?);
+                    self.skip_blank();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {}
+                        _ => bail!("expected ',' or ']' in array"),
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut out = Map::new();
+                loop {
+                    self.skip_ws();
+                    match self.peek() {
+                        Some('}') if out.is_empty() => { self.pos += 1; return Ok(Value::Object(out)); }
+                        Some('}') => bail!("trailing ',' in inline table"),
+                        Some('\n') | Some('\r') | Some('#') => bail!("inline tables must be on one line; use a [table] instead"),
+                        _ => {}
+                    }
+                    let path = self.key_path()?;
+                    self.expect('=')?;
+                    let value = self.value()?;
+                    let (last, parent) = path.split_last().ok_or_else(|| anyhow!("empty key"))?;
+                    if table_at(&mut out, parent)?.insert(last.clone(), value).is_some() { bail!("duplicate key '{}' in inline table", last); }
+                    self.skip_ws();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => { self.pos += 1; return Ok(Value::Object(out)); }
+                        Some('\n') | Some('\r') | Some('#') | None => bail!("inline tables must be on one line; use a [table] instead"),
+                        _ => bail!("expected ',' or '}}' in inline table"),
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| !matches!(c, ',' | ']' | '}' | '#' | '\n' | '\r')) { self.pos += 1; }
+                let raw: String = self.chars[start..self.pos].iter().collect();
+                let raw = raw.trim();
+                match raw {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "" => bail!("missing value"),
+                    _ => {
+                        let digits = raw.trim_start_matches(['+', '-']);
+                        if ["0x", "0o", "0b"].iter().any(|p| digits.starts_with(p)) { bail!("hex, octal and binary integers ({}) are not supported", raw); }
+                        if matches!(digits, "inf" | "nan") { bail!("{} is not supported: JSON has no such number", raw); }
+                        let plain = raw.replace('_', "");
+                        if let Some(n) = number(&plain) { return Ok(n); }
+                        // dates and times are kept as written
+                        if TOML_DATETIME.is_match(raw) { return Ok(Value::String(raw.to_string())); }
+                        bail!("invalid value '{}'", raw)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Basic `"..."`, literal `'...'` and their `"""`/`'''` multi-line forms.
+    fn string(&mut self) -> Result<String> {
+        let q = self.peek().ok_or_else(|| anyhow!("expected a string"))?;
+        let multi = self.peek_at(1) == Some(q) && self.peek_at(2) == Some(q);
+        self.pos += if multi { 3 } else { 1 };
+        // a newline right after the opening quotes is not part of the string
+        if multi && self.peek() == Some('\n') { self.pos += 1; }
+        let mut s = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| anyhow!("unterminated string"))?;
+            if c == q && (!multi || (self.peek_at(1) == Some(q) && self.peek_at(2) == Some(q))) {
+                self.pos += if multi { 3 } else { 1 };
+                return Ok(s);
+            }
+            if c == '\n' && !multi { bail!("unterminated string"); }
+            self.pos += 1;
+            if c == '\\' && q == '"' {
+                if multi && self.peek() == Some('\n') {
+                    // line-ending backslash trims the newline and following whitespace
+                    while self.peek().is_some_and(|c| c.is_whitespace()) { self.pos += 1; }
+                    continue;
+                }
+                s.push(unescape(&self.chars, &mut self.pos)?);
+            } else {
+                s.push(c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn yaml_config() {
+        let v = parse_yaml("# beeg inventory
+---
+transport: ssh
+ssh_user: 'beeg''admin'
+cluster: { name: prod, mgmtd_host: mgmt01 }
+nodes:
+- name: node-a   # first
+  host: 10.0.0.11
+  labels: [gpu, \"rack:2\"]
+- name: node-b
+  host: 10.0.0.12
+  labels:
+    - gpu
+quota_warn_percent: 85
+motd: |
+  line one
+  # not a comment
+empty:
+").unwrap();
+        assert_eq!(v["ssh_user"], "beeg'admin");
+        assert_eq!(v["cluster"], json!({"name": "prod", "mgmtd_host": "mgmt01"}));
+        assert_eq!(v["nodes"][0]["labels"], json!(["gpu", "rack:2"]));
+        assert_eq!(v["nodes"][1], json!({"name": "node-b", "host": "10.0.0.12", "labels": ["gpu"]}));
+        assert_eq!(v["quota_warn_percent"], 85);
+        assert_eq!(v["motd"], "line one\n# not a comment\n");
+        assert_eq!(v["empty"], Value::Null);
+        assert!(parse_yaml("a: 1\n  b: 2\n").is_err());
+        assert!(parse_yaml("a: 1\na: 2\n").is_err());
+        assert_eq!(parse_yaml("---\na: 1\n...\n").unwrap(), json!({"a": 1}));
+    }
+
+    #[test]
+    fn yaml_unsupported() {
+        let err = |text: &str| parse_yaml(text).unwrap_err().to_string();
+        assert_eq!(err("base: &base\n  a: 1\n"), "line 1: anchors (&base) are not supported");
+        assert_eq!(err("a: 1\nb: *base\n"), "line 2: aliases (*base) are not supported");
+        assert_eq!(err("a:\n  <<: 1\n"), "line 2: merge keys (<<) are not supported");
+        assert_eq!(err("a: !!str 1\n"), "line 1: tags (!!str) are not supported");
+        assert!(err("- &n x\n").contains("anchors"));
+        assert!(err("a: 1\n---\nb: 2\n").contains("multi-document"));
+        assert!(err("a: 1\n...\nb: 2\n").contains("multi-document"));
+        assert!(err("%YAML 1.2\n---\na: 1\n").contains("directives"));
+        assert!(err("? a\n: 1\n").contains("complex keys"));
+        // inside quotes and plain scalars these characters are content
+        assert_eq!(parse_yaml("a: \"*x\"\nb: x*y & z\n").unwrap(), json!({"a": "*x", "b": "x*y & z"}));
+    }
+
+    #[test]
+    fn toml_config() {
+        let v = parse_toml(r#"transport = "ssh"  # default
+exec_max_output = '4M'
+quota_warn_percent = 8_5
+
+[cluster]
+name = "prod"
+mgmtd_host = "mgmt01"
+
+[[nodes]]
+name = "node-a"
+host = "10.0.0.11"
+labels = [
+  "gpu", # trailing comma next
+  "rack2",
+]
+
+[[nodes]]
+name = "node-b"
+host = "10.0.0.12"
+extra = { when = 2024-06-01T09:00:00Z, ratio = 0.5 }
+"#).unwrap();
+        assert_eq!(v["quota_warn_percent"], 85);
+        assert_eq!(v["cluster"]["mgmtd_host"], "mgmt01");
+        assert_eq!(v["nodes"][0]["labels"], json!(["gpu", "rack2"]));
+        assert_eq!(v["nodes"][1]["extra"], json!({"when": "2024-06-01T09:00:00Z", "ratio": 0.5}));
+        assert!(parse_toml("a = 1\na = 2\n").is_err());
+        assert!(parse_toml("a = \"x\" b\n").unwrap_err().to_string().starts_with("line 1"));
+        let v = parse_toml("d = 1979-05-27\nt = 07:32:00\nl = 1979-05-27T07:32:00.5\no = 1979-05-27 07:32:00-07:00\n").unwrap();
+        assert_eq!(v, json!({"d": "1979-05-27", "t": "07:32:00", "l": "1979-05-27T07:32:00.5", "o": "1979-05-27 07:32:00-07:00"}));
+    }
+
+    #[test]
+    fn toml_unsupported() {
+        let err = |text: &str| parse_toml(text).unwrap_err().to_string();
+        assert_eq!(err("a = 0xff\n"), "line 1: hex, octal and binary integers (0xff) are not supported");
+        assert!(err("a = inf\n").contains("not supported"));
+        assert_eq!(err("a = 1979-5-27\n"), "line 1: invalid value '1979-5-27'");
+        assert!(err("a = { b = 1,\n c = 2 }\n").contains("on one line"));
+        assert!(err("a = { b = 1, }\n").contains("trailing ','"));
+        assert!(err("a = { b = 1, b = 2 }\n").contains("duplicate key"));
+        assert_eq!(err("[a]\nx = 1\n[a]\ny = 2\n"), "line 3: table [a] is defined twice");
+    }
+
+    #[test]
+    fn render_round_trip() {
+        let v = json!({
+            "transport": "ssh",
+            "ssh_user": null,
+            "cluster": {"name": "prod", "mgmtd_host": "mgmt01"},
+            "nodes": [
+                {"name": "node-a", "host": "10.0.0.11", "labels": ["gpu", "rack: 2"]},
+                {"name": "true", "host": "# odd", "labels": []},
+            ],
+            "quota_warn_percent": 85,
+        });
+        let yaml = Format::Yaml.render(&v).unwrap();
+        assert!(yaml.contains("  - host: 10.0.0.11\n    labels:\n      - gpu\n      - \"rack: 2\"\n    name: node-a\n"));
+        assert_eq!(parse_yaml(&yaml).unwrap(), v);
+        let toml = Format::Toml.render(&v).unwrap();
+        assert!(toml.contains("[[nodes]]\nhost = \"10.0.0.11\"\nlabels = [\"gpu\", \"rack: 2\"]\nname = \"node-a\""));
+        let mut no_null = v.clone();
+        no_null.as_object_mut().unwrap().remove("ssh_user");
+        assert_eq!(parse_toml(&toml).unwrap(), no_null);
+        assert!(Format::Toml.render(&json!([1])).is_err());
+    }
+
+    #[test]
+    fn detect() {
+        let p = Path::new;
+        assert_eq!(Format::detect(p("beeg.yml"), ""), Format::Yaml);
+        assert_eq!(Format::detect(p("config"), "{\"nodes\": []}"), Format::Json);
+        assert_eq!(Format::detect(p("config"), "# x\n[[nodes]]\nname = \"a\"\n"), Format::Toml);
+        assert_eq!(Format::detect(p("config"), "transport = \"local\"\n"), Format::Toml);
+        assert_eq!(Format::detect(p("config"), "nodes:\n  - name: a\n"), Format::Yaml);
+    }
+}
@@ -0,0 +1,262 @@
+//! `beeg history`: every check result, kept per node so a node's status can be traced back.
+//!
+//! The store is an append-only JSON-lines file in the state dir, one record per node and
+//! check, written after each `beeg check` run (including `--watch` iterations).
+
+use crate::checks::{CheckReport, Severity};
+use crate::state;
+use crate::timefmt::TimeRange;
+use anyhow::{Context, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+const HISTORY_FILE: &str = "history.jsonl";
+/// Past this size the history is cut to its newer half.
+const MAX_HISTORY_BYTES: u64 = 32 << 20;
+
+/// One node's result of one check run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// Start of the run; shared by all records of that run
+    pub ts: i64,
+    pub check: String,
+    pub node: String,
+    /// `OK`, `WARN`, `NOPERM` or `FAIL`
+    pub status: String,
+    #[serde(default)]
+    pub detail: String,
+    #[serde(default)]
+    pub duration_ms: u64,
+}
+
+/// A stretch of consecutive runs where a node's check kept the same status.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Span {
+    pub check: String,
+    pub node: String,
+    pub status: String,
+    pub first: i64,
+    pub last: i64,
+    pub runs: usize,
+    /// Detail of the latest run in the span
+    pub detail: String,
+}
+
+/// `BEEG_HISTORY=0` turns recording off, e.g. for throwaway runs in CI.
+fn enabled() -> bool {
+    !std::env::var("BEEG_HISTORY").is_ok_and(|v| v == "0")
+}
+
+fn records_of(report: &CheckReport, ts: i64) -> Vec<Record> {
+    report.leaf_results().into_iter().map(|r| Record {
+        ts,
+        check: r.check.to_string(),
+        node: r.node.clone(),
+        status: r.status.as_str().to_string(),
+        detail: r.detail.clone(),
+        duration_ms: r.duration.as_millis() as u64,
+    }).collect()
+}
+
+fn append(records: &[Record]) -> Result<()> {
+    let dir = state::state_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("creating state dir: {}", dir.display()))?;
+    let path = dir.join(HISTORY_FILE);
+    let mut f = std::fs::OpenOptions::new().create(true).append(true).open(&path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    let mut buf = String::new();
+    for r in records { buf.push_str(&serde_json::to_string(r)?); buf.push('\n'); }
+    f.write_all(buf.as_bytes())?;
+    if f.metadata()?.len() > MAX_HISTORY_BYTES {
+        let text = std::fs::read_to_string(&path)?;
+        let lines: Vec<&str> = text.lines().collect();
+        let tmp = dir.join(format!(".{}.{}.tmp", HISTORY_FILE, std::process::id()));
+        std::fs::write(&tmp, lines[lines.len() / 2..].join("\n") + "\n")?;
+        std::fs::rename(&tmp, &path)?;
+    }
+    Ok(())
+}
+
+/// Record the results of a finished check run; failures to record are only reported.
+pub fn record(report: &CheckReport, started: chrono::DateTime<chrono::Utc>) {
+    if !enabled() { return; }
+    let records = records_of(report, started.timestamp());
+    if records.is_empty() { return; }
+    if let Err(e) = append(&records) { log::warn!("could not record check history: {:#}", e); }
+}
+
+/// Record results that come from outside a check run, such as `beeg fsck`; failures to record
+/// are only reported.
+pub fn record_records(records: &[Record]) {
+    if !enabled() || records.is_empty() { return; }
+    if let Err(e) = append(records) { log::warn!("could not record history: {:#}", e); }
+}
+
+/// The newest record of `check` on `node`.
+pub fn latest(check: &str, node: &str) -> Option<Record> {
+    load().into_iter().filter(|r| r.check == check && r.node == node).max_by_key(|r| r.ts)
+}
+
+pub fn load() -> Vec<Record> {
+    let text = std::fs::read_to_string(state::state_dir().join(HISTORY_FILE)).unwrap_or_default();
+    text.lines().filter_map(|l| serde_json::from_str(l).ok()).collect()
+}
+
+/// Which records `list` and `show` look at.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub check: Option<String>,
+    pub node: Option<String>,
+    pub range: Option<TimeRange>,
+}
+
+impl Filter {
+    fn matches(&self, r: &Record) -> bool {
+        self.check.as_deref().is_none_or(|c| c == r.check)
+            && self.node.as_deref().is_none_or(|n| n == r.node)
+            && self.range.is_none_or(|range| {
+                range.since.is_none_or(|s| r.ts >= s.timestamp()) && range.until.is_none_or(|u| r.ts <= u.timestamp())
+            })
+    }
+}
+
+/// Collapse `records` (oldest first) into status spans per check and node, each in time order.
+pub(crate) fn spans(records: &[&Record]) -> Vec<Span> {
+    let mut by: BTreeMap<(&str, &str), Vec<Span>> = BTreeMap::new();
+    for r in records {
+        let spans = by.entry((&r.check, &r.node)).or_default();
+        match spans.last_mut() {
+            Some(s) if s.status == r.status => {
+                s.last = r.ts;
+                s.runs += 1;
+                s.detail = r.detail.clone();
+            }
+            _ => spans.push(Span {
+                check: r.check.clone(),
+                node: r.node.clone(),
+                status: r.status.clone(),
+                first: r.ts,
+                last: r.ts,
+                runs: 1,
+                detail: r.detail.clone(),
+            }),
+        }
+    }
+    by.into_values().flatten().collect()
+}
+
+fn severity(status: &str) -> Severity {
+    match status {
+        "OK" => Severity::Ok,
+        "WARN" => Severity::Warning,
+        "NOPERM" => Severity::NoPerm,
+        _ => Severity::Failure,
+    }
+}
+
+fn time(ts: i64, utc: bool) -> String {
+    chrono::DateTime::from_timestamp(ts, 0).map(|t| crate::timefmt::human(t, utc)).unwrap_or_default()
+}
+
+fn no_history_note(cli: &crate::GlobalArgs) {
+    crate::tables::note(cli.output, &format!("no recorded results match; history is kept in {}", state::state_dir().join(HISTORY_FILE).display()));
+}
+
+/// The most recent `limit` matching results, oldest first.
+pub fn list(cli: &crate::GlobalArgs, filter: &Filter, failing: bool, limit: usize) -> Result<()> {
+    let records = load();
+    let mut matched: Vec<&Record> = records.iter()
+        .filter(|r| filter.matches(r) && (!failing || r.status != "OK"))
+        .collect();
+    let total = matched.len();
+    matched.drain(..total.saturating_sub(limit));
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            if matched.is_empty() { no_history_note(cli); return Ok(()); }
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Time", "Check", "Node", "Status", "Detail"]);
+            for r in &matched {
+                table.add_row(vec![
+                    comfy_table::Cell::new(time(r.ts, cli.utc)),
+                    comfy_table::Cell::new(&r.check),
+                    comfy_table::Cell::new(&r.node),
+                    crate::tables::status_cell(&r.status, severity(&r.status)),
+                    comfy_table::Cell::new(&r.detail),
+                ]);
+            }
+            crate::tables::print(cli.output, &table);
+            if total > matched.len() {
+                crate::tables::note(cli.output, &format!("latest {} of {} results; --limit shows more", matched.len(), total));
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &matched)?,
+    }
+    Ok(())
+}
+
+/// Status changes over time for each matching node and check, with when the current status began.
+pub fn show(cli: &crate::GlobalArgs, filter: &Filter) -> Result<()> {
+    let records = load();
+    let matched: Vec<&Record> = records.iter().filter(|r| filter.matches(r)).collect();
+    let spans = spans(&matched);
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            if spans.is_empty() { no_history_note(cli); return Ok(()); }
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Check", "Node", "Status", "From", "To", "Runs", "Detail"]);
+            for s in &spans {
+                table.add_row(vec![
+                    comfy_table::Cell::new(&s.check),
+                    comfy_table::Cell::new(&s.node),
+                    crate::tables::status_cell(&s.status, severity(&s.status)),
+                    comfy_table::Cell::new(time(s.first, cli.utc)),
+                    comfy_table::Cell::new(time(s.last, cli.utc)),
+                    comfy_table::Cell::new(s.runs),
+                    comfy_table::Cell::new(&s.detail),
+                ]);
+            }
+            crate::tables::print(cli.output, &table);
+            // the latest span of each node and check is its current status
+            let mut current: BTreeMap<(&str, &str), &Span> = BTreeMap::new();
+            for s in &spans { current.insert((&s.check, &s.node), s); }
+            for s in current.values().filter(|s| s.status != "OK") {
+                crate::tables::note(cli.output, &format!("{} on {}: {} since {} ({} runs)", s.check, s.node, s.status, time(s.first, cli.utc), s.runs));
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &spans)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(ts: i64, node: &str, status: &str) -> Record {
+        Record { ts, check: "nvidia-driver".into(), node: node.into(), status: status.into(), detail: format!("run {}", ts), duration_ms: 10 }
+    }
+
+    #[test]
+    fn status_spans() {
+        let records = [
+            rec(1, "node-3", "OK"), rec(1, "node-4", "OK"),
+            rec(2, "node-3", "OK"), rec(2, "node-4", "OK"),
+            rec(3, "node-3", "FAIL"), rec(3, "node-4", "OK"),
+            rec(4, "node-3", "FAIL"),
+        ];
+        let refs: Vec<&Record> = records.iter().collect();
+        let s = spans(&refs);
+        assert_eq!(s.len(), 3);
+        assert_eq!((s[0].node.as_str(), s[0].status.as_str(), s[0].first, s[0].last, s[0].runs), ("node-3", "OK", 1, 2, 2));
+        assert_eq!((s[1].status.as_str(), s[1].first, s[1].last, s[1].runs, s[1].detail.as_str()), ("FAIL", 3, 4, 2, "run 4"));
+        assert_eq!((s[2].node.as_str(), s[2].runs), ("node-4", 3));
+
+        let range = TimeRange { since: chrono::DateTime::from_timestamp(3, 0), until: None };
+        let f = Filter { check: Some("nvidia-driver".into()), node: Some("node-3".into()), range: Some(range) };
+        assert_eq!(records.iter().filter(|r| f.matches(r)).count(), 2);
+    }
+}
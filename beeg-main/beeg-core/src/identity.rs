@@ -0,0 +1,62 @@
+use crate::config;
+
+/// Every sysMgmtdHost named in the node's BeeGFS service configs.
+const PROBE: &str = "sed -n 's/^[[:space:]]*sysMgmtdHost[[:space:]]*=[[:space:]]*\\([^[:space:]#]*\\).*/\\1/p' /etc/beegfs/beegfs-*.conf 2>/dev/null | sort -u";
+
+/// Refuse to go on unless every node in `nodes` belongs to the cluster recorded in the config.
+///
+/// Run this before anything that changes state on nodes. Without a `cluster` entry in the
+/// config there is nothing to compare against; that only warns.
+pub fn verify(cfg: &config::Config, nodes: &[&config::Node], timeout: u64) -> anyhow::Result<()> {
+    let Some(expected) = &cfg.cluster else {
+        log::warn!("no cluster identity in the config; cannot verify which BeegFS instance these nodes belong to (set cluster.mgmtd_host)");
+        return Ok(());
+    };
+    let mut nodes = nodes.to_vec();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    nodes.dedup_by(|a, b| a.name == b.name);
+    let cmd = crate::checks::wrap_timeout(PROBE, timeout);
+    let seen: Vec<(String, Result<Vec<String>, String>)> = crate::checks::per_node(cfg, &nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &cmd).map(|o| o.stdout.split_whitespace().map(String::from).collect()).map_err(|e| e.to_string())
+    }).into_iter().map(|(n, res, _)| (n.name.clone(), res)).collect();
+
+    let problems = mismatches(&expected.mgmtd_host, &seen);
+    if problems.is_empty() { return Ok(()); }
+    let cluster = expected.name.as_deref().map(|n| format!("'{}' ", n)).unwrap_or_default();
+    anyhow::bail!(
+        "refusing to act: not every node belongs to cluster {}(sysMgmtdHost {}):\n  {}",
+        cluster, expected.mgmtd_host, problems.join("\n  ")
+    )
+}
+
+/// One line per node that does not (provably) point at `expected`.
+pub(crate) fn mismatches(expected: &str, seen: &[(String, Result<Vec<String>, String>)]) -> Vec<String> {
+    seen.iter().filter_map(|(node, res)| match res {
+        Err(e) => Some(format!("{}: could not read its BeegFS configs: {}", node, e)),
+        Ok(hosts) if hosts.is_empty() => Some(format!("{}: no sysMgmtdHost in /etc/beegfs/beegfs-*.conf", node)),
+        Ok(hosts) => {
+            let other: Vec<&str> = hosts.iter().map(String::as_str).filter(|h| !h.eq_ignore_ascii_case(expected)).collect();
+            (!other.is_empty()).then(|| format!("{}: sysMgmtdHost {}", node, other.join(", ")))
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mismatch_lines() {
+        let seen = vec![
+            ("a".to_string(), Ok(vec!["mgmt01".to_string()])),
+            ("b".to_string(), Ok(vec!["MGMT01".to_string(), "stage-mgmt".to_string()])),
+            ("c".to_string(), Ok(vec![])),
+            ("d".to_string(), Err("timeout".to_string())),
+        ];
+        let m = mismatches("mgmt01", &seen);
+        assert_eq!(m.len(), 3);
+        assert_eq!(m[0], "b: sysMgmtdHost stage-mgmt");
+        assert!(m[1].starts_with("c: no sysMgmtdHost"));
+        assert!(m[2].starts_with("d: could not read"));
+    }
+}
@@ -0,0 +1,197 @@
+use crate::state;
+use crate::transport::{CappedOutput, ExecOutput, OutputCap, Transport};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+const RUN_FILE: &str = "run.json";
+const ENTRIES_FILE: &str = "run.jsonl";
+
+/// The check run whose remote output is being journaled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMeta {
+    /// Command line without the program name
+    pub args: Vec<String>,
+    /// Config file in effect, so a resume reads the same inventory
+    pub config: Option<PathBuf>,
+    pub started_at: i64,
+    pub finished: bool,
+}
+
+/// One completed remote command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    host: String,
+    cmd: String,
+    stdout: String,
+    stderr: String,
+    #[serde(default)]
+    status: Option<i32>,
+}
+
+/// Append-only record of a check run's remote command output, written as each command
+/// completes so an interrupted run loses nothing. When resuming, recorded output is handed
+/// back in place of running the command again.
+#[derive(Debug)]
+pub struct Journal {
+    file: Mutex<File>,
+    replay: Mutex<HashMap<(String, String), VecDeque<ExecOutput>>>,
+    replayed: AtomicUsize,
+    recorded: AtomicUsize,
+}
+
+/// A timeout (`timeout` exits 124) or an ssh connection failure (255) did not finish; the
+/// command runs again on resume.
+fn completed(out: &ExecOutput) -> bool {
+    !matches!(out.status, Some(124) | Some(255))
+}
+
+impl Journal {
+    fn open(replay: HashMap<(String, String), VecDeque<ExecOutput>>, truncate: bool) -> Result<Self> {
+        let dir = state::state_dir();
+        std::fs::create_dir_all(&dir).with_context(|| format!("creating state dir: {}", dir.display()))?;
+        let path = dir.join(ENTRIES_FILE);
+        let mut opts = OpenOptions::new();
+        opts.create(true);
+        if truncate { opts.write(true).truncate(true); } else { opts.append(true); }
+        let file = opts.open(&path).with_context(|| format!("opening {}", path.display()))?;
+        Ok(Journal { file: Mutex::new(file), replay: Mutex::new(replay), replayed: AtomicUsize::new(0), recorded: AtomicUsize::new(0) })
+    }
+
+    /// Begin journaling a new run; this replaces the previous one.
+    pub fn start(args: Vec<String>, config: Option<PathBuf>) -> Result<Arc<Journal>> {
+        let journal = Journal::open(HashMap::new(), true)?;
+        state::write_json(RUN_FILE, &RunMeta { args, config, started_at: chrono::Utc::now().timestamp(), finished: false })?;
+        Ok(Arc::new(journal))
+    }
+
+    /// The last run, if it did not finish, with its recorded output queued for replay.
+    pub fn resume_last() -> Result<(RunMeta, Arc<Journal>)> {
+        let meta: RunMeta = state::read_json(RUN_FILE).context("no interrupted check run to resume")?;
+        if meta.finished { anyhow::bail!("the last check run (`beeg {}`) finished; nothing to resume", meta.args.join(" ")); }
+        let text = std::fs::read_to_string(state::state_dir().join(ENTRIES_FILE)).unwrap_or_default();
+        let mut replay: HashMap<(String, String), VecDeque<ExecOutput>> = HashMap::new();
+        // a line cut short by the interruption is skipped
+        for e in text.lines().filter_map(|l| serde_json::from_str::<Entry>(l).ok()) {
+            replay.entry((e.host, e.cmd)).or_default().push_back(ExecOutput { stdout: e.stdout, stderr: e.stderr, status: e.status });
+        }
+        Ok((meta, Arc::new(Journal::open(replay, false)?)))
+    }
+
+    pub fn finish(&self) -> Result<()> {
+        let mut meta: RunMeta = state::read_json(RUN_FILE).context("run journal disappeared")?;
+        meta.finished = true;
+        state::write_json(RUN_FILE, &meta)
+    }
+
+    /// Recorded commands handed back instead of being run.
+    pub fn replayed(&self) -> usize { self.replayed.load(Ordering::Relaxed) }
+
+    /// Commands run (and recorded) during this process.
+    pub fn recorded(&self) -> usize { self.recorded.load(Ordering::Relaxed) }
+
+    fn take(&self, host: &str, cmd: &str) -> Option<ExecOutput> {
+        let mut replay = self.replay.lock().unwrap_or_else(|e| e.into_inner());
+        let out = replay.get_mut(&(host.to_string(), cmd.to_string()))?.pop_front()?;
+        self.replayed.fetch_add(1, Ordering::Relaxed);
+        Some(out)
+    }
+
+    fn record(&self, host: &str, cmd: &str, out: &ExecOutput) -> Result<()> {
+        let e = Entry { host: host.into(), cmd: cmd.into(), stdout: out.stdout.clone(), stderr: out.stderr.clone(), status: out.status };
+        let mut line = serde_json::to_string(&e)?;
+        line.push('\n');
+        // one write per line keeps concurrent node threads from interleaving
+        self.file.lock().unwrap_or_else(|e| e.into_inner()).write_all(line.as_bytes())?;
+        self.recorded.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Wraps a transport so `exec` and `exec_idempotent` go through the journal; streaming calls pass straight through.
+pub struct JournalTransport {
+    pub inner: Box<dyn Transport + Send + Sync>,
+    pub journal: Arc<Journal>,
+}
+
+impl JournalTransport {
+    fn replayed(&self, host: &str, cmd: &str, run: impl Fn() -> Result<ExecOutput>) -> Result<ExecOutput> {
+        if let Some(out) = self.journal.take(host, cmd) { return Ok(out); }
+        let out = run()?;
+        if completed(&out) {
+            // losing a journal line only means the command runs again on resume
+            let _ = self.journal.record(host, cmd, &out);
+        }
+        Ok(out)
+    }
+}
+
+impl Transport for JournalTransport {
+    fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.replayed(host, cmd, || self.inner.exec(host, cmd))
+    }
+
+    fn exec_idempotent(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.replayed(host, cmd, || self.inner.exec_idempotent(host, cmd))
+    }
+
+    fn exec_stream(&self, host: &str, cmd: &str, sink: &mut dyn Write) -> Result<u64> {
+        self.inner.exec_stream(host, cmd, sink)
+    }
+
+    fn exec_capped(&self, host: &str, cmd: &str, cap: OutputCap) -> Result<CappedOutput> {
+        self.inner.exec_capped(host, cmd, cap)
+    }
+
+    fn exec_lines(&self, host: &str, cmd: &str, on_line: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> {
+        self.inner.exec_lines(host, cmd, on_line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Transport for Counting {
+        fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            let status = if host == "down" { 255 } else { 0 };
+            Ok(ExecOutput { stdout: format!("{} {}", host, cmd), stderr: String::new(), status: Some(status) })
+        }
+        fn exec_stream(&self, _: &str, _: &str, _: &mut dyn Write) -> Result<u64> { Ok(0) }
+        fn exec_capped(&self, _: &str, _: &str, _: OutputCap) -> Result<CappedOutput> { unimplemented!() }
+        fn exec_lines(&self, _: &str, _: &str, _: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> { unimplemented!() }
+    }
+
+    #[test]
+    fn interrupted_run_resumes() {
+        let dir = std::env::temp_dir().join(format!("beeg-journal-{}", crate::checks::rand_suffix()));
+        std::env::set_var("BEEG_STATE_DIR", &dir);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let tr = |journal: Arc<Journal>| JournalTransport { inner: Box::new(Counting(runs.clone())), journal };
+
+        let first = tr(Journal::start(vec!["check".into(), "ofed".into()], None).unwrap());
+        first.exec("up", "v").unwrap();
+        first.exec("down", "v").unwrap();
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+
+        // the node that answered is replayed, the unreachable one is asked again
+        let (meta, journal) = Journal::resume_last().unwrap();
+        assert_eq!(meta.args, vec!["check", "ofed"]);
+        let again = tr(journal.clone());
+        assert_eq!(again.exec("up", "v").unwrap().stdout, "up v");
+        again.exec("down", "v").unwrap();
+        assert_eq!((runs.load(Ordering::Relaxed), journal.replayed()), (3, 1));
+
+        journal.finish().unwrap();
+        assert!(Journal::resume_last().is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,112 @@
+//! The parts of `beeg` that reach a BeeGFS cluster: the node inventory, the transports that
+//! run commands on nodes, typed wrappers around the BeeGFS tools, and the checks. The `beeg`
+//! binary is a command line on top of this crate; other tools can run the same checks without
+//! shelling out to it.
+//!
+//! A check is looked up by name and parsed from the arguments `beeg check` would take, then
+//! run against a config. It returns a [`checks::CheckReport`]; nothing is printed unless the
+//! report is rendered.
+//!
+//! ```no_run
+//! use beeg_core::{checks::CheckCmd, config, GlobalArgs};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let cfg = config::load(None)?;
+//! let report = CheckCmd::parse(["ofed", "-s", "storage"])?.run(&GlobalArgs::default(), &cfg)?;
+//! for r in &report.results {
+//!     println!("{} {} {}", r.node, r.status.as_str(), r.detail);
+//! }
+//! println!("{}", report.severity().as_str());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Commands on nodes go through [`transport::from_config`], so they honour the config's
+//! SSH, sudo, timeout and retry settings; setting [`config::Config::cancel`] stops a caller's
+//! commands without affecting anyone else's.
+
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+
+pub mod alerts;
+pub mod beegconf;
+pub mod beegfs;
+pub mod checks;
+pub mod config;
+pub mod formats;
+pub mod history;
+pub mod identity;
+pub mod journal;
+pub mod logging;
+pub mod notes;
+pub mod prompt;
+#[cfg(feature = "tui")]
+pub mod record;
+pub mod selfupdate;
+pub mod sshconfig;
+pub mod state;
+pub mod tables;
+pub mod telemetry;
+pub mod timefmt;
+pub mod transfer;
+pub mod transport;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+/// The `beeg` options that shape how results are shown. Checks read them to decide on
+/// output; when embedding, `GlobalArgs::default()` gives plain human output.
+#[derive(Debug, Clone, Default, Args)]
+pub struct GlobalArgs {
+    /// Increase output verbosity (-v, -vv); also raises --log-level to info, debug
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Diagnostics to show: warn, info (nodes probed, with durations), debug (every command run)
+    #[arg(long, global = true, value_enum, default_value_t = logging::LogLevel::Warn)]
+    pub log_level: logging::LogLevel,
+
+    /// Append diagnostics to this file; stderr keeps only warnings and errors
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Diagnostics as text lines or JSON lines with host, command and duration fields
+    #[arg(long, global = true, value_enum, default_value_t = logging::LogFormat::Text)]
+    pub log_format: logging::LogFormat,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Output::Human)]
+    pub output: Output,
+
+    /// Order of nodes in output; what runs where is unaffected
+    #[arg(long, value_enum, default_value_t = config::NodeOrder::Config)]
+    pub sort: config::NodeOrder,
+
+    /// Plain human output without colors; also when NO_COLOR is set or stdout is not a terminal
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Show timestamps in UTC instead of local time (human output)
+    #[arg(long, global = true)]
+    pub utc: bool,
+
+    /// Treat check warnings as failures (exit code 2 instead of 1)
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Record TUI screens (e.g. `check client-mount`) to an asciinema v2 cast file
+    #[arg(long, global = true, value_name = "FILE")]
+    pub record_session: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Output {
+    #[default]
+    Human,
+    Json,
+    Jsonl,
+    Yaml,
+    Csv,
+    Tsv,
+    /// One status line with perfdata and Nagios exit codes; `beeg check` only
+    Nagios,
+}
@@ -0,0 +1,275 @@
+//! beeg's diagnostics, through the `log` crate: warnings, with `-v` each node a check probed
+//! and how long it took, with `-vv` every command run on a node with its host, exit status and
+//! duration. They go to stderr as `WARNING: ...` lines; `--log-file` appends them to a file
+//! instead, and stderr keeps the warnings and errors. `--log-format json` writes one object per
+//! line with the fields (`host`, `command`, `node`, `check`, `duration_ms`, ...) as keys.
+//!
+//! Every record also carries the fields of the [`scope`]s it was logged in, such as the check
+//! being run and the node being probed, so a command can be traced back to both.
+//!
+//! Nothing is printed until [`init`] installs the logger; an embedder may install its own.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    #[default]
+    Warn,
+    /// Also each node a check probed, with its duration
+    Info,
+    /// Also every command run on a node
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+struct Logger {
+    level: LevelFilter,
+    format: LogFormat,
+    /// `--log-file`; stderr then only gets warnings and errors, as text
+    file: Option<Mutex<File>>,
+}
+
+/// Install the logger for `--log-level` (raised by `-v`: info, `-vv`: debug, `-vvv`: trace),
+/// `--log-file` and `--log-format`. A logger installed earlier stays.
+pub fn init(args: &crate::GlobalArgs) -> Result<()> {
+    let file = match &args.log_file {
+        Some(path) => Some(Mutex::new(
+            OpenOptions::new().create(true).append(true).open(path).with_context(|| format!("opening log file: {}", path.display()))?,
+        )),
+        None => None,
+    };
+    let level = args.log_level.filter().max(verbosity(args.verbose));
+    if log::set_boxed_logger(Box::new(Logger { level, format: args.log_format, file })).is_ok() {
+        log::set_max_level(level);
+    }
+    Ok(())
+}
+
+fn verbosity(v: u8) -> LevelFilter {
+    match v {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // other crates' records are theirs to debug, not ours
+        metadata.level() <= self.level && metadata.target().starts_with("beeg")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) { return; }
+        let Some(file) = &self.file else {
+            eprintln!("{}", format(self.format, record, false));
+            return;
+        };
+        if record.level() <= Level::Warn { eprintln!("{}", format(LogFormat::Text, record, false)); }
+        let mut f = file.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(f, "{}", format(self.format, record, true));
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file { let _ = file.lock().unwrap_or_else(|e| e.into_inner()).flush(); }
+    }
+}
+
+thread_local! {
+    /// Fields of the scopes entered on this thread, outermost first
+    static CONTEXT: RefCell<Vec<(&'static str, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Fields added to every record logged on this thread until it is dropped.
+#[must_use]
+pub struct Scope {
+    /// Length of the context before the scope was entered
+    outer: usize,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CONTEXT.with(|c| c.borrow_mut().truncate(self.outer));
+    }
+}
+
+/// Add `fields` to the records of this thread, e.g. `scope(&[("check", name)])`.
+pub fn scope(fields: &[(&'static str, &str)]) -> Scope {
+    enter(fields.iter().map(|(k, v)| (*k, v.to_string())).collect())
+}
+
+/// The fields of this thread's scopes, to carry over to a thread working for it with `enter`.
+pub fn context() -> Vec<(&'static str, String)> {
+    CONTEXT.with(|c| c.borrow().clone())
+}
+
+/// `scope` with owned fields, e.g. from another thread's `context`.
+pub fn enter(fields: Vec<(&'static str, String)>) -> Scope {
+    CONTEXT.with(|c| {
+        let mut c = c.borrow_mut();
+        let outer = c.len();
+        c.extend(fields);
+        Scope { outer }
+    })
+}
+
+/// `record` as one line; text written to a file starts with the time.
+fn format(format: LogFormat, record: &Record, to_file: bool) -> String {
+    let mut own = Fields::default();
+    let _ = record.key_values().visit(&mut own);
+    // scope fields first, the innermost of a repeated key winning; the record's own win over all
+    let mut fields = Fields::default();
+    for (k, v) in context() {
+        if own.0.iter().any(|(o, _)| o == k) { continue; }
+        match fields.0.iter_mut().find(|(f, _)| f == k) {
+            Some(f) => f.1 = v.into(),
+            None => fields.0.push((k.to_string(), v.into())),
+        }
+    }
+    fields.0.extend(own.0);
+    let now = || chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    match format {
+        LogFormat::Text => {
+            let mut line = format!("{}: {}", label(record.level()), record.args());
+            for (k, v) in &fields.0 {
+                match v {
+                    serde_json::Value::String(s) => line += &format!(" {}={:?}", k, s),
+                    v => line += &format!(" {}={}", k, v),
+                }
+            }
+            if to_file { format!("{} {}", now(), line) } else { line }
+        }
+        LogFormat::Json => {
+            let mut obj = serde_json::Map::new();
+            obj.insert("time".into(), now().into());
+            obj.insert("level".into(), record.level().as_str().to_lowercase().into());
+            obj.insert("target".into(), record.target().into());
+            obj.insert("message".into(), record.args().to_string().into());
+            obj.extend(fields.0);
+            serde_json::Value::Object(obj).to_string()
+        }
+    }
+}
+
+/// The prefix beeg has always used on stderr.
+fn label(level: Level) -> &'static str {
+    match level {
+        Level::Error => "ERROR",
+        Level::Warn => "WARNING",
+        Level::Info => "INFO",
+        Level::Debug => "DEBUG",
+        Level::Trace => "TRACE",
+    }
+}
+
+/// A record's key-values, numbers and booleans kept as such for JSON.
+#[derive(Default)]
+struct Fields(Vec<(String, serde_json::Value)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let v = if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else if let Some(n) = value.to_f64() {
+            n.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.push((key.to_string(), v));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records() {
+        let line = |format, to_file| {
+            let kvs: [(&str, Value); 3] = [("host", "stor01".into()), ("status", 0u64.into()), ("command", "echo hi".into())];
+            let kvs = &kvs[..];
+            let record = Record::builder()
+                .level(Level::Debug)
+                .target("beeg_core::transport")
+                .args(format_args!("exec finished"))
+                .key_values(&kvs)
+                .build();
+            super::format(format, &record, to_file)
+        };
+        assert_eq!(line(LogFormat::Text, false), r#"DEBUG: exec finished host="stor01" status=0 command="echo hi""#);
+        assert!(line(LogFormat::Text, true).ends_with(" DEBUG: exec finished host=\"stor01\" status=0 command=\"echo hi\""));
+        let json: serde_json::Value = serde_json::from_str(&line(LogFormat::Json, true)).unwrap();
+        assert_eq!(json["level"], "debug");
+        assert_eq!(json["message"], "exec finished");
+        assert_eq!(json["host"], "stor01");
+        assert_eq!(json["status"], 0);
+        assert_eq!(json["command"], "echo hi");
+        assert!(json["time"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn scoped_fields() {
+        let line = || {
+            let kvs: [(&str, Value); 1] = [("host", "stor01".into())];
+            let kvs = &kvs[..];
+            let record = Record::builder().level(Level::Debug).target("beeg_core").args(format_args!("exec finished")).key_values(&kvs).build();
+            super::format(LogFormat::Text, &record, false)
+        };
+        {
+            let _check = scope(&[("check", "all")]);
+            let _inner = scope(&[("check", "time"), ("host", "ignored")]);
+            // a worker thread carries the context over
+            let ctx = context();
+            let worker = std::thread::spawn(move || {
+                let _node = enter(ctx);
+                let _more = scope(&[("node", "s01")]);
+                line()
+            }).join().unwrap();
+            assert_eq!(worker, r#"DEBUG: exec finished check="time" node="s01" host="stor01""#);
+            assert_eq!(line(), r#"DEBUG: exec finished check="time" host="stor01""#);
+        }
+        assert_eq!(line(), r#"DEBUG: exec finished host="stor01""#);
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(LogLevel::Warn.filter().max(verbosity(0)), LevelFilter::Warn);
+        assert_eq!(LogLevel::Warn.filter().max(verbosity(2)), LevelFilter::Debug);
+        assert_eq!(LogLevel::Trace.filter().max(verbosity(1)), LevelFilter::Trace);
+        assert_eq!(LogLevel::Error.filter().max(verbosity(0)), LevelFilter::Error);
+    }
+}
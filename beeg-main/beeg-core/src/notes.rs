@@ -0,0 +1,146 @@
+use crate::state;
+use anyhow::Result;
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+
+const NOTES_FILE: &str = "notes.json";
+
+/// What a note is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteKind {
+    /// Inventory node, by name or host
+    Node,
+    /// Storage or metadata target, by target ID
+    Target,
+}
+
+impl NoteKind {
+    fn as_str(&self) -> &'static str {
+        match self { NoteKind::Node => "node", NoteKind::Target => "target" }
+    }
+}
+
+/// An operator note, shown next to warnings about its subject.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub id: u32,
+    pub kind: NoteKind,
+    pub subject: String,
+    pub text: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub created_at: i64,
+}
+
+pub fn load() -> Vec<Note> {
+    state::read_json(NOTES_FILE).unwrap_or_default()
+}
+
+/// Notes about any of `subjects`, oldest first.
+pub(crate) fn matching<'a>(notes: &'a [Note], kind: NoteKind, subjects: &[&str]) -> Vec<&'a Note> {
+    notes.iter().filter(|n| n.kind == kind && subjects.contains(&n.subject.as_str())).collect()
+}
+
+/// "3d", "5h", "12m": how long ago `ts` was.
+pub(crate) fn age(ts: i64, now: i64) -> String {
+    let secs = (now - ts).max(0);
+    match secs {
+        s if s >= 86_400 => format!("{}d", s / 86_400),
+        s if s >= 3_600 => format!("{}h", s / 3_600),
+        s => format!("{}m", s / 60),
+    }
+}
+
+/// Print the notes for `subjects` on stderr, under the warning that named them.
+pub fn show(kind: NoteKind, subjects: &[&str]) {
+    let notes = load();
+    let now = chrono::Utc::now().timestamp();
+    for n in matching(&notes, kind, subjects) {
+        let by = n.author.as_deref().map(|a| format!("{}, ", a)).unwrap_or_default();
+        eprintln!("  NOTE [{} {}] {} ({}{} ago, #{})", n.kind.as_str(), n.subject, n.text, by, age(n.created_at, now), n.id);
+    }
+}
+
+pub fn add(cli: &crate::GlobalArgs, kind: NoteKind, subject: &str, text: &str) -> Result<()> {
+    let mut notes = load();
+    let note = Note {
+        id: notes.iter().map(|n| n.id).max().unwrap_or(0) + 1,
+        kind,
+        subject: subject.to_string(),
+        text: text.to_string(),
+        author: std::env::var("SUDO_USER").or_else(|_| std::env::var("USER")).ok(),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    notes.push(note.clone());
+    state::write_json(NOTES_FILE, &notes)?;
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => println!("added note #{} to {} {}", note.id, kind.as_str(), subject),
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &note)?,
+    }
+    Ok(())
+}
+
+pub fn list(cli: &crate::GlobalArgs, kind: Option<NoteKind>, subject: Option<&str>) -> Result<()> {
+    let notes = load();
+    let shown: Vec<&Note> = notes.iter()
+        .filter(|n| kind.is_none_or(|k| n.kind == k) && subject.is_none_or(|s| n.subject == s))
+        .collect();
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["ID", "Kind", "Subject", "Note", "Author", "Added"]);
+            for n in &shown {
+                let added = chrono::DateTime::from_timestamp(n.created_at, 0).map(|t| crate::timefmt::human(t, cli.utc)).unwrap_or_default();
+                table.add_row(vec![
+                    n.id.to_string(), n.kind.as_str().to_string(), n.subject.clone(), n.text.clone(),
+                    n.author.clone().unwrap_or_default(), added,
+                ]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let arr: Vec<_> = shown.iter().map(|n| serde_json::json!({
+                "id": n.id,
+                "kind": n.kind,
+                "subject": n.subject,
+                "text": n.text,
+                "author": n.author,
+                "created_at": chrono::DateTime::from_timestamp(n.created_at, 0).map(crate::timefmt::rfc3339),
+            })).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn remove(cli: &crate::GlobalArgs, id: u32) -> Result<()> {
+    let mut notes = load();
+    let before = notes.len();
+    notes.retain(|n| n.id != id);
+    if notes.len() == before { anyhow::bail!("no note #{}", id); }
+    state::write_json(NOTES_FILE, &notes)?;
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => println!("removed note #{}", id),
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &serde_json::json!({ "removed": id }))?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_and_age() {
+        let note = |id, kind, subject: &str| Note { id, kind, subject: subject.into(), text: "x".into(), author: None, created_at: 0 };
+        let notes = vec![note(1, NoteKind::Node, "s07"), note(2, NoteKind::Target, "s07"), note(3, NoteKind::Node, "s08")];
+        let m = matching(&notes, NoteKind::Node, &["s07", "s09"]);
+        assert_eq!(m.iter().map(|n| n.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(age(0, 3 * 86_400 + 5), "3d");
+        assert_eq!(age(0, 7_200), "2h");
+        assert_eq!(age(0, 59), "0m");
+    }
+}
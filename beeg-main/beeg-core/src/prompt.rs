@@ -0,0 +1,11 @@
+use std::io::{self, BufRead, Write};
+
+/// Ask a y/N question on stderr; anything other than y/yes counts as no.
+pub fn confirm(question: &str) -> anyhow::Result<bool> {
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    let ans = line.trim().to_ascii_lowercase();
+    Ok(ans == "y" || ans == "yes")
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// Terminal writer that also keeps an asciinema v2 cast of everything drawn, one event per flush.
+pub struct Recorder<W: Write> {
+    inner: W,
+    cast: Option<Cast>,
+}
+
+struct Cast {
+    file: BufWriter<File>,
+    start: Instant,
+    /// Bytes written since the last flush; may end inside a UTF-8 sequence
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Pass-through when `path` is `None`; otherwise creates the cast and writes its header.
+    pub fn new(inner: W, path: Option<&Path>, title: &str) -> Result<Self> {
+        let Some(path) = path else { return Ok(Recorder { inner, cast: None }) };
+        let (width, height) = crossterm::terminal::size().ok().filter(|&(w, h)| w > 0 && h > 0).unwrap_or((80, 24));
+        let file = File::create(path).with_context(|| format!("creating session recording: {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+        let header = serde_json::json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": chrono::Utc::now().timestamp(),
+            "title": title,
+            "env": { "TERM": std::env::var("TERM").ok(), "SHELL": std::env::var("SHELL").ok() },
+        });
+        writeln!(file, "{}", header)?;
+        Ok(Recorder { inner, cast: Some(Cast { file, start: Instant::now(), pending: Vec::new() }) })
+    }
+}
+
+impl Cast {
+    fn emit(&mut self) -> std::io::Result<()> {
+        // hold back an incomplete trailing UTF-8 sequence for the next event
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        if valid == 0 { return Ok(()); }
+        let rest = self.pending.split_off(valid);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = rest;
+        let event = serde_json::json!([self.start.elapsed().as_secs_f64(), "o", text]);
+        writeln!(self.file, "{}", event)?;
+        self.file.flush()
+    }
+}
+
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(c) = &mut self.cast { c.pending.extend_from_slice(&buf[..n]); }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()?;
+        match &mut self.cast {
+            Some(c) => c.emit(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cast_events() {
+        let path = std::env::temp_dir().join(format!("beeg-record-{}.cast", std::process::id()));
+        let mut r = Recorder::new(Vec::new(), Some(&path), "test").unwrap();
+        r.write_all(b"\x1b[2Jhello ").unwrap();
+        r.flush().unwrap();
+        // "—" split across two flushes ends up whole in the second event
+        let dash = "—".as_bytes();
+        r.write_all(&dash[..1]).unwrap();
+        r.flush().unwrap();
+        r.write_all(&dash[1..]).unwrap();
+        r.flush().unwrap();
+        assert_eq!(r.inner, "\x1b[2Jhello —".as_bytes());
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "\x1b[2Jhello ");
+        assert_eq!(lines[2][2], "—");
+    }
+}
@@ -0,0 +1,382 @@
+use crate::checks::{rand_suffix, Severity};
+use crate::config::{self, VersionPolicy};
+use anyhow::{bail, Context, Result};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where releases live unless `--base-url` or `BEEG_RELEASE_URL` say otherwise. Layout:
+/// `channels/<channel>` holds a version number, `v<version>/SHA256SUMS` with its signature
+/// `v<version>/SHA256SUMS.sig`, and `v<version>/beeg-<os>-<arch>` the release itself.
+const DEFAULT_BASE_URL: &str = "https://github.com/tuned-willow/beegfs/releases/download";
+
+/// The release signing key (`ssh-ed25519 AAAA...`) this binary trusts, pinned when it is built.
+/// Without one it cannot verify a release and refuses to install any.
+const RELEASE_KEY: Option<&str> = option_env!("BEEG_RELEASE_KEY");
+
+/// `ssh-keygen -Y sign -n` namespace of release signatures, so no other signature by the same
+/// key passes.
+const SIGNATURE_NAMESPACE: &str = "beeg-release";
+
+pub const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+/// `major.minor.patch[-pre]`; a pre-release sorts just below its release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub triple: [u64; 3],
+    pub pre: Option<String>,
+}
+
+impl Version {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim().trim_start_matches('v');
+        let (nums, pre) = match s.split_once('-') {
+            Some((n, p)) => (n, Some(p.to_string())),
+            None => (s, None),
+        };
+        let parts = parse_parts(nums)?;
+        if parts.len() != 3 { bail!("version '{}' needs major.minor.patch", s); }
+        Ok(Version { triple: [parts[0], parts[1], parts[2]], pre })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.triple.cmp(&other.triple)
+            .then_with(|| self.pre.is_none().cmp(&other.pre.is_none()))
+            .then_with(|| self.pre.cmp(&other.pre))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c] = self.triple;
+        write!(f, "{}.{}.{}", a, b, c)?;
+        if let Some(p) = &self.pre { write!(f, "-{}", p)?; }
+        Ok(())
+    }
+}
+
+fn parse_parts(s: &str) -> Result<Vec<u64>> {
+    let parts: Vec<u64> = s.split('.').map(|p| p.parse().with_context(|| format!("bad version number '{}'", s))).collect::<Result<_>>()?;
+    if parts.is_empty() || parts.len() > 3 { bail!("bad version number '{}'", s); }
+    Ok(parts)
+}
+
+/// A Cargo-style requirement such as `^0.4`, `=0.4.2` or `>=0.3, <0.5`: every comma-separated
+/// comparator must hold. Each one is kept as a half-open `[low, high)` range of releases.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionReq {
+    text: String,
+    ranges: Vec<([u64; 3], Option<[u64; 3]>)>,
+}
+
+impl VersionReq {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut ranges = Vec::new();
+        for c in s.split(',').map(str::trim) {
+            if c == "*" { continue; }
+            let op_len = c.find(|ch: char| ch.is_ascii_digit()).with_context(|| format!("bad version requirement '{}'", c))?;
+            let (op, v) = (c[..op_len].trim(), c[op_len..].trim().trim_start_matches('v'));
+            let parts = parse_parts(v)?;
+            let pad = |p: &[u64]| [p[0], p.get(1).copied().unwrap_or(0), p.get(2).copied().unwrap_or(0)];
+            // next release after the last component given: 1.2 -> 1.3.0
+            let bump = |i: usize| { let mut t = pad(&parts); t[i] += 1; for x in t.iter_mut().skip(i + 1) { *x = 0; } t };
+            let last = parts.len() - 1;
+            let range = match op {
+                "=" => (pad(&parts), Some(bump(last))),
+                ">=" => (pad(&parts), None),
+                ">" => (bump(last), None),
+                "<" => ([0; 3], Some(pad(&parts))),
+                "<=" => ([0; 3], Some(bump(last))),
+                "~" => (pad(&parts), Some(bump(if parts.len() == 1 { 0 } else { 1 }))),
+                "^" | "" => {
+                    let i = if parts[0] > 0 || parts.len() == 1 { 0 } else if parts[1] > 0 || parts.len() == 2 { 1 } else { 2 };
+                    (pad(&parts), Some(bump(i)))
+                }
+                _ => bail!("bad version requirement '{}': unknown operator '{}'", c, op),
+            };
+            ranges.push(range);
+        }
+        Ok(VersionReq { text: s.trim().to_string(), ranges })
+    }
+
+    pub fn matches(&self, v: &Version) -> bool {
+        let at = |t: [u64; 3]| Version { triple: t, pre: None };
+        self.ranges.iter().all(|(lo, hi)| *v >= at(*lo) && hi.is_none_or(|h| *v < at(h)))
+    }
+}
+
+impl std::fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(&self.text) }
+}
+
+fn pin(cfg: &config::Config) -> Result<Option<VersionReq>> {
+    cfg.required_beeg_version.as_deref().map(|r| VersionReq::parse(r).context("parsing required_beeg_version")).transpose()
+}
+
+/// Compare the running binary against the config's `required_beeg_version`: a warning, or an
+/// error under `beeg_version_policy: refuse`.
+pub fn check_pin(cfg: &config::Config) -> Result<()> {
+    let Some(req) = pin(cfg)? else { return Ok(()) };
+    let current = Version::parse(CURRENT)?;
+    if req.matches(&current) { return Ok(()); }
+    let msg = format!("this config requires beeg {} but {} is running; `beeg self update` installs a matching release", req, current);
+    match cfg.beeg_version_policy {
+        VersionPolicy::Warn => { log::warn!("{}", msg); Ok(()) }
+        VersionPolicy::Refuse => bail!("{}", msg),
+    }
+}
+
+fn asset_name() -> String {
+    format!("beeg-{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Fetch `url` into `dest` with curl; unless `insecure`, over https only, redirects included.
+fn download(url: &str, dest: &Path, insecure: bool) -> Result<()> {
+    let mut cmd = Command::new("curl");
+    cmd.args(["-fsSL", "--retry", "2"]);
+    if !insecure { cmd.args(["--proto", "=https", "--proto-redir", "=https"]); }
+    let out = cmd.arg("-o").arg(dest).arg(url).output().context("running curl")?;
+    if !out.status.success() { bail!("downloading {} failed: {}", url, String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(())
+}
+
+fn fetch_text(url: &str, dir: &Path, insecure: bool) -> Result<String> {
+    let tmp = dir.join(format!(".beeg-fetch-{}", rand_suffix()));
+    let res = download(url, &tmp, insecure).and_then(|_| std::fs::read_to_string(&tmp).with_context(|| format!("reading {}", url)));
+    let _ = std::fs::remove_file(&tmp);
+    res
+}
+
+/// Refuse a release server that is not https, unless `insecure`.
+pub(crate) fn check_base(base: &str, insecure: bool) -> Result<()> {
+    if !insecure && !base.starts_with("https://") {
+        bail!("refusing release server {}: only https:// is used without --insecure", base);
+    }
+    Ok(())
+}
+
+/// Check that `sig` is an `ssh-keygen -Y sign` signature of `data` by `key` in the release
+/// namespace, with `ssh-keygen -Y verify`.
+pub(crate) fn verify_signature(data: &Path, sig: &Path, key: &str, dir: &Path) -> Result<()> {
+    let signers = dir.join(format!(".beeg-signers-{}", rand_suffix()));
+    std::fs::write(&signers, format!("beeg-release namespaces=\"{}\" {}\n", SIGNATURE_NAMESPACE, key.trim()))?;
+    let res = (|| -> Result<()> {
+        let out = Command::new("ssh-keygen")
+            .args(["-Y", "verify", "-f"]).arg(&signers)
+            .args(["-I", "beeg-release", "-n", SIGNATURE_NAMESPACE, "-s"]).arg(sig)
+            .stdin(std::fs::File::open(data).with_context(|| format!("reading {}", data.display()))?)
+            .output().context("running ssh-keygen")?;
+        if !out.status.success() {
+            let why = String::from_utf8_lossy(&out.stderr).trim().to_string();
+            bail!("signature check failed{}", if why.is_empty() { String::new() } else { format!(": {}", why) });
+        }
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(&signers);
+    res
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let out = Command::new("sha256sum").arg(path).output()
+        .or_else(|_| Command::new("shasum").args(["-a", "256"]).arg(path).output())
+        .context("running sha256sum")?;
+    if !out.status.success() { bail!("sha256sum {}: {}", path.display(), String::from_utf8_lossy(&out.stderr).trim()); }
+    String::from_utf8_lossy(&out.stdout).split_whitespace().next().map(str::to_lowercase).context("empty sha256sum output")
+}
+
+/// The checksum listed for `asset` in a `SHA256SUMS` file (`<hex>  [*]<name>` lines).
+pub(crate) fn listed_sum(sums: &str, asset: &str) -> Option<String> {
+    sums.lines().find_map(|l| {
+        let (sum, name) = l.trim().split_once(char::is_whitespace)?;
+        (name.trim().trim_start_matches('*') == asset).then(|| sum.to_lowercase())
+    })
+}
+
+pub struct UpdateOptions {
+    pub channel: String,
+    pub version: Option<String>,
+    pub base_url: Option<String>,
+    /// Only report what would be installed
+    pub check: bool,
+    /// Install even when the release does not satisfy the config's pin, or is older than this one
+    pub force: bool,
+    /// Accept a release server that is not https
+    pub insecure: bool,
+}
+
+pub fn update(cli: &crate::GlobalArgs, cfg: &config::Config, opts: &UpdateOptions) -> Result<Severity> {
+    let base = opts.base_url.clone().or_else(|| std::env::var("BEEG_RELEASE_URL").ok()).unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+    let base = base.trim_end_matches('/');
+    check_base(base, opts.insecure)?;
+    let current = Version::parse(CURRENT)?;
+    let exe = std::env::current_exe().and_then(|p| p.canonicalize()).context("locating the running binary")?;
+    let dir = exe.parent().context("binary has no parent directory")?.to_path_buf();
+
+    let target = match &opts.version {
+        Some(v) => Version::parse(v)?,
+        None => {
+            let text = fetch_text(&format!("{}/channels/{}", base, opts.channel), &std::env::temp_dir(), opts.insecure)?;
+            Version::parse(&text).with_context(|| format!("channel {} does not name a version", opts.channel))?
+        }
+    };
+    let req = pin(cfg)?;
+    let allowed = req.as_ref().is_none_or(|r| r.matches(&target));
+    if !allowed && !opts.force && !opts.check {
+        bail!(
+            "beeg {} does not satisfy required_beeg_version {}; pick one with --version or pass --force",
+            target, req.as_ref().map(|r| r.to_string()).unwrap_or_default(),
+        );
+    }
+    let newer = target > current;
+    // a channel moving back is not a reason to downgrade on its own
+    if target < current && opts.version.is_none() && !opts.force && !opts.check {
+        bail!("channel {} offers {}, older than the running {}; pass --version {} or --force to downgrade", opts.channel, target, current, target);
+    }
+
+    let mut installed = None;
+    if !opts.check && target != current {
+        installed = Some(install(base, &target, &exe, &dir, opts.insecure)?);
+    }
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            if opts.check {
+                println!("running {}, {} offers {}{}", current, opts.version.as_ref().map(|_| "requested".to_string()).unwrap_or_else(|| format!("channel {}", opts.channel)), target,
+                    if newer { " (update available)" } else if target == current { " (up to date)" } else { " (older than running)" });
+            } else if target == current {
+                println!("beeg {} is already installed at {}", current, exe.display());
+            } else {
+                println!("updated {}: {} -> {}", exe.display(), current, target);
+            }
+            if let Some(r) = &req { println!("required_beeg_version {}: {}", r, if allowed { "satisfied" } else { "NOT satisfied" }); }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "path": exe.display().to_string(),
+                "channel": opts.channel,
+                "current": current.to_string(),
+                "target": target.to_string(),
+                "update_available": newer,
+                "installed": installed.is_some(),
+                "required": req.as_ref().map(|r| r.to_string()),
+                "satisfies_required": allowed,
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    if !allowed && opts.force && !opts.check { log::warn!("installed {} although the config requires {}", target, req.map(|r| r.to_string()).unwrap_or_default()); }
+    Ok(if opts.check && newer { Severity::Warning } else { Severity::Ok })
+}
+
+/// `SHA256SUMS` of `release`, once its signature checks out against the pinned key.
+fn signed_sums(release: &str, dir: &Path, insecure: bool) -> Result<String> {
+    let key = RELEASE_KEY.context("this build has no release signing key (BEEG_RELEASE_KEY when it was built) to verify a download with; install the release by hand")?;
+    let sums = dir.join(format!(".beeg-sums-{}", rand_suffix()));
+    let sig = dir.join(format!(".beeg-sums-{}.sig", rand_suffix()));
+    let res = (|| -> Result<String> {
+        download(&format!("{}/SHA256SUMS", release), &sums, insecure)?;
+        download(&format!("{}/SHA256SUMS.sig", release), &sig, insecure)?;
+        verify_signature(&sums, &sig, key, dir).context("SHA256SUMS is not signed by the beeg release key")?;
+        std::fs::read_to_string(&sums).context("reading SHA256SUMS")
+    })();
+    let _ = std::fs::remove_file(&sums);
+    let _ = std::fs::remove_file(&sig);
+    res
+}
+
+/// Download, check and swap in the release; the old binary stays in place until the final rename.
+fn install(base: &str, target: &Version, exe: &Path, dir: &Path, insecure: bool) -> Result<PathBuf> {
+    let asset = asset_name();
+    let release = format!("{}/v{}", base, target);
+    let sums = signed_sums(&release, dir, insecure)?;
+    let want = listed_sum(&sums, &asset).with_context(|| format!("release {} has no {} in SHA256SUMS", target, asset))?;
+
+    let tmp = dir.join(format!(".beeg.new-{}", rand_suffix()));
+    let res = (|| -> Result<()> {
+        download(&format!("{}/{}", release, asset), &tmp, insecure)?;
+        let got = sha256_file(&tmp)?;
+        if got != want { bail!("checksum mismatch for {}: expected {}, got {}", asset, want, got); }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))?;
+        }
+        // the new binary has to run here and call itself the version we asked for
+        let out = Command::new(&tmp).arg("--version").output().context("running the downloaded binary")?;
+        let said = String::from_utf8_lossy(&out.stdout);
+        if !out.status.success() || said.split_whitespace().nth(1).and_then(|v| Version::parse(v).ok()).as_ref() != Some(target) {
+            bail!("downloaded binary reports '{}' instead of beeg {}", said.trim(), target);
+        }
+        std::fs::rename(&tmp, exe).with_context(|| format!("replacing {}", exe.display()))
+    })();
+    if res.is_err() { let _ = std::fs::remove_file(&tmp); }
+    res.map(|_| exe.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements() {
+        let v = |s: &str| Version::parse(s).unwrap();
+        let ok = |req: &str, ver: &str| VersionReq::parse(req).unwrap().matches(&v(ver));
+        assert!(v("0.10.0") > v("0.9.9") && v("1.0.0-rc1") < v("1.0.0"));
+        assert!(ok("0.4", "0.4.7") && !ok("0.4", "0.5.0"));
+        assert!(ok("^1.2", "1.9.0") && !ok("^1.2", "2.0.0") && !ok("^0.0.3", "0.0.4"));
+        assert!(ok("=0.4.2", "0.4.2") && !ok("=0.4.2", "0.4.3") && ok("=0.4", "0.4.3"));
+        assert!(ok("~1.2.3", "1.2.9") && !ok("~1.2.3", "1.3.0"));
+        assert!(ok(">=0.3, <0.5", "0.4.1") && !ok(">=0.3, <0.5", "0.5.0") && !ok(">0.3", "0.3.9"));
+        assert!(ok("<=0.4", "0.4.9") && ok("*", "3.0.0"));
+        assert!(!ok(">=0.4", "0.4.0-rc1"));
+        assert!(VersionReq::parse("!1.0").is_err() && VersionReq::parse("1.x").is_err());
+    }
+
+    #[test]
+    fn checksums() {
+        let sums = "abc123  beeg-linux-x86_64\nDEF456 *beeg-linux-aarch64\n";
+        assert_eq!(listed_sum(sums, "beeg-linux-aarch64").as_deref(), Some("def456"));
+        assert_eq!(listed_sum(sums, "beeg-macos-aarch64"), None);
+    }
+
+    #[test]
+    fn release_server() {
+        assert!(check_base("https://mirror.example/beeg", false).is_ok());
+        assert!(check_base("http://mirror.example/beeg", false).is_err());
+        assert!(check_base("file:///srv/beeg", false).is_err());
+        assert!(check_base("file:///srv/beeg", true).is_ok());
+    }
+
+    #[test]
+    fn signatures() {
+        let dir = std::env::temp_dir().join(format!("beeg-sig-test-{}", rand_suffix()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = dir.join("release");
+        let sums = dir.join("SHA256SUMS");
+        std::fs::write(&sums, "abc123  beeg-linux-x86_64\n").unwrap();
+        let ssh_keygen = |args: &[&str]| Command::new("ssh-keygen").args(args).output().unwrap().status.success();
+        assert!(ssh_keygen(&["-q", "-t", "ed25519", "-N", "", "-f", key.to_str().unwrap()]));
+        let sign = |ns: &str| ssh_keygen(&["-q", "-Y", "sign", "-f", key.to_str().unwrap(), "-n", ns, sums.to_str().unwrap()]);
+        let public = std::fs::read_to_string(dir.join("release.pub")).unwrap();
+        let sig = dir.join("SHA256SUMS.sig");
+
+        assert!(sign(SIGNATURE_NAMESPACE));
+        let good = verify_signature(&sums, &sig, &public, &dir);
+        std::fs::write(&sums, "666666  beeg-linux-x86_64\n").unwrap();
+        let tampered = verify_signature(&sums, &sig, &public, &dir);
+        std::fs::remove_file(&sig).unwrap();
+        assert!(sign("file"));
+        let other_namespace = verify_signature(&sums, &sig, &public, &dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(good.is_ok(), "{:?}", good);
+        assert!(tampered.is_err() && other_namespace.is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::checks::Severity;
+use crate::config::{self, Node};
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// One `Host` block. It matches the node name and its address, so a plain `ssh <host>`
+/// picks up the port, key and jump host too.
+fn host_block(cfg: &config::Config, node: &Node) -> Result<String> {
+    let mut patterns = vec![node.name.as_str()];
+    if node.host != node.name { patterns.push(&node.host); }
+    let mut out = String::new();
+    let chain = config::jump_chain(cfg, node)?;
+    // ssh applies the jump host's own ProxyJump, so naming the first hop is enough
+    if chain.len() > 1 { out.push_str(&format!("# via {}\n", chain.iter().rev().cloned().collect::<Vec<_>>().join(" -> "))); }
+    out.push_str(&format!("Host {}\n", patterns.join(" ")));
+    out.push_str(&format!("    HostName {}\n", node.host));
+    if let Some(user) = node.ssh_user.as_ref().or(cfg.ssh_user.as_ref()) { out.push_str(&format!("    User {}\n", user)); }
+    if let Some(port) = node.ssh_port { out.push_str(&format!("    Port {}\n", port)); }
+    if let Some(key) = &node.identity_file {
+        out.push_str(&format!("    IdentityFile {}\n", key));
+        out.push_str("    IdentitiesOnly yes\n");
+    }
+    if let Some(hop) = chain.first() { out.push_str(&format!("    ProxyJump {}\n", hop)); }
+    Ok(out)
+}
+
+pub(crate) fn render(cfg: &config::Config, nodes: &[&Node], source: &Path) -> Result<String> {
+    let mut out = format!(
+        "# Generated by `beeg ssh-config generate` from {}; edits here are overwritten.\n# Use it from ~/.ssh/config with: Include <this file>\n",
+        source.display(),
+    );
+    for n in nodes {
+        out.push('\n');
+        out.push_str(&host_block(cfg, n)?);
+    }
+    Ok(out)
+}
+
+/// Line diff of `old` against `new`, as `-`/`+` lines; empty when they match.
+pub(crate) fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let (a, b): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    // longest common subsequence table, from the end
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j, mut out) = (0, 0, Vec::new());
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] { i += 1; j += 1; }
+        else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) { out.push(format!("-{}", a[i])); i += 1; }
+        else { out.push(format!("+{}", b[j])); j += 1; }
+    }
+    out
+}
+
+pub fn generate(cli: &crate::GlobalArgs, cfg: &config::Config, source: &Path, selector: &str, out: Option<&Path>, check: bool) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    let text = render(cfg, &nodes, source)?;
+    let Some(path) = out else {
+        match cli.output {
+            crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => print!("{}", text),
+            crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &serde_json::json!({"hosts": nodes.len(), "config": text}))?,
+        }
+        return Ok(Severity::Ok);
+    };
+
+    let current = match std::fs::read_to_string(path) {
+        Ok(t) => Some(t),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    let diff = diff_lines(current.as_deref().unwrap_or(""), &text);
+    let drift = current.is_none() || !diff.is_empty();
+    if drift && !check {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        std::fs::write(path, &text).with_context(|| format!("writing {}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let state = match (drift, check, current.is_some()) {
+                (false, _, _) => "up to date",
+                (true, true, false) => "missing",
+                (true, true, true) => "out of date",
+                (true, false, _) => "written",
+            };
+            println!("{}: {} ({} hosts)", path.display(), state, nodes.len());
+            if drift && check { for line in &diff { println!("{}", line); } }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "path": path.display().to_string(),
+                "hosts": nodes.len(),
+                "exists": current.is_some(),
+                "drift": drift,
+                "written": drift && !check,
+                "diff": diff,
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    if drift && check { log::warn!("{} does not match the config; run without --check to update it", path.display()); }
+    Ok(if drift && check { Severity::Warning } else { Severity::Ok })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> config::Config {
+        let node = |name: &str, host: &str, jump: Option<&str>| Node { name: name.into(), host: host.into(), proxy_jump: jump.map(String::from), ..Default::default() };
+        let mut nodes = vec![node("outer", "198.51.100.1", None), node("inner", "10.0.0.1", Some("outer")), node("stor01", "10.1.0.1", Some("inner"))];
+        nodes[2].ssh_port = Some(2222);
+        nodes[2].identity_file = Some("~/.ssh/id_beeg".into());
+        nodes[1].ssh_user = Some("jump".into());
+        serde_json::from_value(serde_json::json!({"ssh_user": "beegadmin", "nodes": serde_json::to_value(&nodes).unwrap()})).unwrap()
+    }
+
+    #[test]
+    fn blocks_and_chains() {
+        let cfg = cfg();
+        assert_eq!(config::jump_chain(&cfg, &cfg.nodes[2]).unwrap(), vec!["inner", "outer"]);
+        let text = render(&cfg, &cfg.nodes.iter().collect::<Vec<_>>(), Path::new("beeg.yaml")).unwrap();
+        assert!(text.contains("Host inner 10.0.0.1\n    HostName 10.0.0.1\n    User jump\n    ProxyJump outer\n"));
+        assert!(text.contains("# via outer -> inner\nHost stor01 10.1.0.1\n    HostName 10.1.0.1\n    User beegadmin\n    Port 2222\n    IdentityFile ~/.ssh/id_beeg\n    IdentitiesOnly yes\n    ProxyJump inner\n"));
+
+        let mut looped = cfg.clone();
+        looped.nodes[0].proxy_jump = Some("stor01".into());
+        assert!(config::jump_chain(&looped, &looped.nodes[2]).is_err());
+    }
+
+    #[test]
+    fn diff() {
+        assert!(diff_lines("a\nb\n", "a\nb\n").is_empty());
+        assert_eq!(diff_lines("a\nb\nc\n", "a\nx\nc\nd\n"), vec!["-b", "+x", "+d"]);
+    }
+}
@@ -0,0 +1,43 @@
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Directory for beeg's own state and caches (`BEEG_STATE_DIR` overrides).
+pub fn state_dir() -> PathBuf {
+    if let Ok(p) = std::env::var("BEEG_STATE_DIR") { return PathBuf::from(p); }
+    if let Some(dir) = dirs::state_dir().or_else(dirs::data_local_dir) {
+        return dir.join("beeg");
+    }
+    PathBuf::from("./.beeg-state")
+}
+
+/// Hold an exclusive lock on `<state_dir>/<name>.lock` until the returned file is dropped, so
+/// a read-modify-write of a state file does not race another beeg process.
+pub fn lock(name: &str) -> Result<fs::File> {
+    let dir = state_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("creating state dir: {}", dir.display()))?;
+    let path = dir.join(format!("{}.lock", name));
+    let f = fs::OpenOptions::new().create(true).truncate(false).write(true).open(&path)
+        .with_context(|| format!("opening lock file: {}", path.display()))?;
+    f.lock().with_context(|| format!("locking {}", path.display()))?;
+    Ok(f)
+}
+
+/// Read `<state_dir>/<name>`; missing or unparsable files read as `None`.
+pub fn read_json<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let data = fs::read_to_string(state_dir().join(name)).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Write `<state_dir>/<name>` atomically (temp file + rename).
+pub fn write_json<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    let dir = state_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("creating state dir: {}", dir.display()))?;
+    let path = dir.join(name);
+    let tmp = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&tmp, serde_json::to_vec_pretty(value)?)
+        .with_context(|| format!("writing state file: {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("replacing state file: {}", path.display()))?;
+    Ok(())
+}
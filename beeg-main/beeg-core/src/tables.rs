@@ -0,0 +1,136 @@
+//! Tables in the plain formats of `--output csv` and `--output tsv`, so command output can go
+//! straight into a spreadsheet or awk. Every command builds its comfy-table as for human
+//! output and hands it to `print`, which picks the format.
+//!
+//! CSV quotes fields as RFC 4180 does. TSV has no quoting, so tabs and line breaks inside a
+//! cell become spaces and `; ` respectively. Lines besides the table (summaries, notes) go to
+//! stderr in both, and a command that shows several tables prints them as blocks separated
+//! by an empty line.
+//!
+//! Human tables color status cells by severity (green OK, yellow WARN, red FAIL, magenta
+//! NOPERM) when stdout is a terminal, unless `--no-color` or `NO_COLOR` says otherwise.
+
+use comfy_table::{Cell, Color, Row, Table};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::checks::Severity;
+use crate::Output;
+
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Turn color off for the rest of the run (`--no-color`, `NO_COLOR`).
+pub fn disable_color() {
+    NO_COLOR.store(true, Ordering::Relaxed);
+}
+
+/// Whether human output may be colored: not turned off, and stdout is a terminal.
+pub fn color() -> bool {
+    !NO_COLOR.load(Ordering::Relaxed) && std::io::stdout().is_terminal()
+}
+
+fn severity_color(sev: Severity) -> Color {
+    match sev {
+        Severity::Ok => Color::Green,
+        Severity::Warning => Color::Yellow,
+        Severity::NoPerm => Color::Magenta,
+        Severity::Failure => Color::Red,
+    }
+}
+
+/// A table cell showing `text` in the color of `sev`.
+pub fn status_cell(text: impl ToString, sev: Severity) -> Cell {
+    let cell = Cell::new(text);
+    if color() { cell.fg(severity_color(sev)) } else { cell }
+}
+
+/// `text` in the color of `sev`, for status lines outside a table.
+pub fn paint(text: &str, sev: Severity) -> String {
+    if !color() { return text.to_string(); }
+    let code = match sev {
+        Severity::Ok => 32,
+        Severity::Warning => 33,
+        Severity::NoPerm => 35,
+        Severity::Failure => 31,
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+/// The field separator of a plain output format, `None` for human and JSON output.
+pub fn separator(output: Output) -> Option<char> {
+    match output {
+        Output::Csv => Some(','),
+        Output::Tsv => Some('\t'),
+        Output::Human | Output::Json | Output::Jsonl | Output::Yaml | Output::Nagios => None,
+    }
+}
+
+fn field(s: &str, sep: char) -> String {
+    if sep == '\t' { return s.replace('\t', " ").replace("\r\n", "; ").replace(['\n', '\r'], "; "); }
+    if s.contains([sep, '"', '\n', '\r']) { format!("\"{}\"", s.replace('"', "\"\"")) } else { s.to_string() }
+}
+
+/// One line of separated fields, without the line break.
+pub fn line<S: AsRef<str>>(fields: &[S], sep: char) -> String {
+    fields.iter().map(|f| field(f.as_ref(), sep)).collect::<Vec<_>>().join(&sep.to_string())
+}
+
+fn cells(row: &Row) -> Vec<String> {
+    row.cell_iter().map(|c| c.content()).collect()
+}
+
+/// `table` as separated lines: its header, then a line per row.
+pub fn delimited(table: &Table, sep: char) -> String {
+    let mut out = String::new();
+    for row in table.header().into_iter().chain(table.row_iter()) {
+        out.push_str(&line(&cells(row), sep));
+        out.push('\n');
+    }
+    out
+}
+
+/// Print `table` as `output` asks for: boxed for humans, separated values for csv/tsv.
+pub fn print(output: Output, table: &Table) {
+    match separator(output) {
+        Some(sep) => print!("{}", delimited(table, sep)),
+        None => println!("{}", table),
+    }
+}
+
+/// Print a further table of the same command; in csv/tsv after an empty line.
+pub fn print_another(output: Output, table: &Table) {
+    if separator(output).is_some() { println!(); }
+    print(output, table);
+}
+
+/// Print a line that goes with a table: to stdout for humans, to stderr when stdout carries
+/// csv/tsv, so the table stays readable by a machine.
+pub fn note(output: Output, text: &str) {
+    match separator(output) {
+        Some(_) => eprintln!("{}", text),
+        None => println!("{}", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_and_tsv() {
+        let mut table = Table::new();
+        table.set_header(vec!["Node", "Detail"]);
+        table.add_row(vec!["n1", "ok"]);
+        table.add_row(vec!["n2", "say \"hi\", then\nleave"]);
+        table.add_row(vec!["n3", "a\tb"]);
+        assert_eq!(delimited(&table, ','), "Node,Detail\nn1,ok\nn2,\"say \"\"hi\"\", then\nleave\"\nn3,a\tb\n");
+        assert_eq!(delimited(&table, '\t'), "Node\tDetail\nn1\tok\nn2\tsay \"hi\", then; leave\nn3\ta b\n");
+    }
+
+    #[test]
+    fn no_color() {
+        disable_color();
+        assert_eq!(paint("FAIL", Severity::Failure), "FAIL");
+        assert_eq!(status_cell("OK", Severity::Ok).content(), "OK");
+    }
+}
@@ -0,0 +1,278 @@
+use crate::checks::CheckReport;
+use crate::state;
+use anyhow::{Context, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const SETTINGS_FILE: &str = "telemetry.json";
+const EVENTS_FILE: &str = "usage.jsonl";
+/// Past this size the event log is cut to its newer half.
+const MAX_EVENTS_BYTES: u64 = 4 << 20;
+
+/// Opt-in state, kept per user in the state dir; nothing is recorded without it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    pub enabled: bool,
+    /// Where each event is also POSTed as JSON
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Random ID so submitted events from one install can be grouped; not derived from the host
+    #[serde(default)]
+    pub install_id: String,
+}
+
+/// One command run. Only the subcommand path is kept: no arguments, hosts or paths.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub ts: i64,
+    /// e.g. `check capacity`
+    pub command: String,
+    /// The command that ran this one, e.g. `check all` for its sub-checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub millis: u64,
+    /// Exit code; 3 marks a run that ended in an error
+    pub exit: u8,
+    pub version: String,
+}
+
+/// Exit code recorded for a command that returned an error.
+pub const EXIT_ERROR: u8 = 3;
+
+/// `BEEG_TELEMETRY=0` turns recording off regardless of the opt-in, e.g. in CI.
+pub fn settings() -> Option<Settings> {
+    if std::env::var("BEEG_TELEMETRY").is_ok_and(|v| v == "0") { return None; }
+    state::read_json::<Settings>(SETTINGS_FILE).filter(|s| s.enabled)
+}
+
+/// `check capacity`, `node exec`, ...: the subcommand names of a parsed command line.
+pub fn command_path(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut m = matches;
+    while let Some((name, sub)) = m.subcommand() {
+        names.push(name.to_string());
+        m = sub;
+    }
+    names.join(" ")
+}
+
+fn append(settings: &Settings, events: &[Event]) -> Result<()> {
+    let dir = state::state_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("creating state dir: {}", dir.display()))?;
+    let path = dir.join(EVENTS_FILE);
+    let mut f = std::fs::OpenOptions::new().create(true).append(true).open(&path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    let mut buf = String::new();
+    for e in events { buf.push_str(&serde_json::to_string(e)?); buf.push('\n'); }
+    f.write_all(buf.as_bytes())?;
+    if f.metadata()?.len() > MAX_EVENTS_BYTES {
+        let text = std::fs::read_to_string(&path)?;
+        let lines: Vec<&str> = text.lines().collect();
+        let tmp = dir.join(format!(".{}.{}.tmp", EVENTS_FILE, std::process::id()));
+        std::fs::write(&tmp, lines[lines.len() / 2..].join("\n") + "\n")?;
+        std::fs::rename(&tmp, &path)?;
+    }
+    if let Some(url) = &settings.endpoint {
+        for e in events { submit(url, &settings.install_id, e); }
+    }
+    Ok(())
+}
+
+/// Fire-and-forget POST; a slow or missing endpoint must never hold up the command. URL and
+/// body reach curl as a config on stdin, not on its command line where `ps` shows them, and a
+/// thread of its own reaps curl so a long-running `beeg daemon` collects no zombies.
+fn submit(url: &str, install_id: &str, e: &Event) {
+    let mut body = serde_json::to_value(e).unwrap_or_default();
+    body["install_id"] = install_id.into();
+    let config = curl_config(url, &body);
+    let Ok(mut child) = Command::new("curl")
+        .args(["-fsS", "-m", "5", "-H", "Content-Type: application/json", "-K", "-"])
+        .stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null())
+        .spawn() else { return };
+    std::thread::spawn(move || {
+        if let Some(mut stdin) = child.stdin.take() { let _ = stdin.write_all(config.as_bytes()); }
+        let _ = child.wait();
+    });
+}
+
+/// A curl config posting `body` to `url`; values are quoted the way curl reads them.
+fn curl_config(url: &str, body: &serde_json::Value) -> String {
+    let quote = |v: &str| serde_json::to_string(v).unwrap_or_default();
+    format!("url = {}\ndata-binary = {}\n", quote(url), quote(&body.to_string()))
+}
+
+fn event(command: &str, parent: Option<&str>, took: Duration, exit: u8) -> Event {
+    Event {
+        ts: chrono::Utc::now().timestamp(),
+        command: command.to_string(),
+        parent: parent.map(String::from),
+        millis: took.as_millis() as u64,
+        exit,
+        version: crate::selfupdate::CURRENT.to_string(),
+    }
+}
+
+/// Record a finished command when telemetry is on; failures to record are ignored.
+pub fn record(command: &str, took: Duration, exit: u8) {
+    if command.is_empty() || command.starts_with("usage") { return; }
+    if let Some(s) = settings() { let _ = append(&s, &[event(command, None, took, exit)]); }
+}
+
+/// Record the sub-checks of `check all` with their own timings, so slow ones stand out.
+pub fn record_sections(report: &CheckReport) {
+    if report.sections.is_empty() { return; }
+    let Some(s) = settings() else { return };
+    let parent = format!("check {}", report.check);
+    let events: Vec<Event> = report.sections.iter()
+        .map(|sub| event(&format!("check {}", sub.check), Some(&parent), sub.duration, sub.severity().exit_code(false)))
+        .collect();
+    let _ = append(&s, &events);
+}
+
+pub fn load_events() -> Vec<Event> {
+    let text = std::fs::read_to_string(state::state_dir().join(EVENTS_FILE)).unwrap_or_default();
+    text.lines().filter_map(|l| serde_json::from_str(l).ok()).collect()
+}
+
+/// Per-command numbers for `usage stats`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stat {
+    pub command: String,
+    pub runs: usize,
+    /// Runs that exited 2 or hit an error
+    pub failures: usize,
+    pub median_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+    pub last_used: i64,
+}
+
+/// Group `events` by command, most used first.
+pub(crate) fn stats(events: &[Event]) -> Vec<Stat> {
+    let mut by: BTreeMap<&str, Vec<&Event>> = BTreeMap::new();
+    for e in events { by.entry(&e.command).or_default().push(e); }
+    let mut out: Vec<Stat> = by.into_iter().map(|(command, es)| {
+        let mut ms: Vec<u64> = es.iter().map(|e| e.millis).collect();
+        ms.sort_unstable();
+        // nearest-rank percentile
+        let pct = |p: usize| ms[((ms.len() * p).div_ceil(100)).clamp(1, ms.len()) - 1];
+        Stat {
+            command: command.to_string(),
+            runs: es.len(),
+            failures: es.iter().filter(|e| e.exit >= 2).count(),
+            median_ms: pct(50),
+            p95_ms: pct(95),
+            max_ms: *ms.last().unwrap_or(&0),
+            last_used: es.iter().map(|e| e.ts).max().unwrap_or(0),
+        }
+    }).collect();
+    out.sort_by(|a, b| b.runs.cmp(&a.runs).then_with(|| a.command.cmp(&b.command)));
+    out
+}
+
+fn fmt_ms(ms: u64) -> String {
+    if ms >= 10_000 { format!("{}s", ms / 1000) } else if ms >= 1000 { format!("{:.1}s", ms as f64 / 1000.0) } else { format!("{}ms", ms) }
+}
+
+pub fn enable(cli: &crate::GlobalArgs, endpoint: Option<&str>) -> Result<()> {
+    let mut s: Settings = state::read_json(SETTINGS_FILE).unwrap_or_default();
+    s.enabled = true;
+    if endpoint.is_some() { s.endpoint = endpoint.map(String::from); }
+    if s.install_id.is_empty() { s.install_id = hex::encode(rand::random::<[u8; 16]>()); }
+    state::write_json(SETTINGS_FILE, &s)?;
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            println!("usage recording on; events go to {}", state::state_dir().join(EVENTS_FILE).display());
+            if let Some(url) = &s.endpoint { println!("each event is also sent to {}", url); }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &s)?,
+    }
+    Ok(())
+}
+
+pub fn disable(cli: &crate::GlobalArgs) -> Result<()> {
+    let mut s: Settings = state::read_json(SETTINGS_FILE).unwrap_or_default();
+    s.enabled = false;
+    s.endpoint = None;
+    state::write_json(SETTINGS_FILE, &s)?;
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => println!("usage recording off; recorded events are kept until `beeg usage clear`"),
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &s)?,
+    }
+    Ok(())
+}
+
+pub fn clear(cli: &crate::GlobalArgs) -> Result<()> {
+    let path = state::state_dir().join(EVENTS_FILE);
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e).with_context(|| format!("removing {}", path.display())),
+        _ => {}
+    }
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => println!("removed recorded usage events"),
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &serde_json::json!({"cleared": path.display().to_string()}))?,
+    }
+    Ok(())
+}
+
+pub fn show_stats(cli: &crate::GlobalArgs, days: Option<u64>) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    let since = days.map(|d| now - d as i64 * 86_400).unwrap_or(i64::MIN);
+    let events: Vec<Event> = load_events().into_iter().filter(|e| e.ts >= since).collect();
+    let rows = stats(&events);
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Command", "Runs", "Failures", "Median", "p95", "Max", "Last used"]);
+            for s in &rows {
+                table.add_row(vec![
+                    s.command.clone(), s.runs.to_string(), s.failures.to_string(),
+                    fmt_ms(s.median_ms), fmt_ms(s.p95_ms), fmt_ms(s.max_ms),
+                    format!("{} ago", crate::notes::age(s.last_used, now)),
+                ]);
+            }
+            crate::tables::print(cli.output, &table);
+            crate::tables::note(cli.output, &format!("{} runs{}", events.len(), days.map(|d| format!(" in the last {} days", d)).unwrap_or_default()));
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &serde_json::json!({"events": events.len(), "commands": rows}))?,
+    }
+
+    // Warnings
+    if settings().is_none() { log::warn!("usage recording is off; `beeg usage enable` turns it on"); }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn submit_config() {
+        let body = serde_json::json!({"command": "check all", "install_id": "abc"});
+        assert_eq!(
+            curl_config("https://t.example/v1?key=s3cret", &body),
+            "url = \"https://t.example/v1?key=s3cret\"\ndata-binary = \"{\\\"command\\\":\\\"check all\\\",\\\"install_id\\\":\\\"abc\\\"}\"\n",
+        );
+    }
+
+    #[test]
+    fn usage_stats() {
+        let ev = |command: &str, millis: u64, exit: u8, ts: i64| Event { ts, command: command.into(), parent: None, millis, exit, version: "0.1.0".into() };
+        let mut events: Vec<Event> = (1..=20).map(|i| ev("check capacity", i * 100, 0, i as i64)).collect();
+        events.push(ev("node exec", 50, 2, 30));
+        events.push(ev("node exec", 70, EXIT_ERROR, 40));
+        let s = stats(&events);
+        assert_eq!(s[0], Stat { command: "check capacity".into(), runs: 20, failures: 0, median_ms: 1000, p95_ms: 1900, max_ms: 2000, last_used: 20 });
+        assert_eq!((s[1].runs, s[1].failures, s[1].median_ms, s[1].last_used), (2, 2, 50, 40));
+
+        let check = <crate::checks::CheckCmd as clap::Subcommand>::augment_subcommands(clap::Command::new("check"));
+        let cmd = <crate::GlobalArgs as clap::Args>::augment_args(clap::Command::new("beeg")).subcommand(check);
+        let m = cmd.try_get_matches_from(["beeg", "--output", "json", "check", "capacity", "--selector", "mgmt01"]).unwrap();
+        assert_eq!(command_path(&m), "check capacity");
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use clap::Args;
+
+/// `--since` / `--until` pair shared by commands that look back in time
+/// (logs, history, crash and event listings).
+#[derive(Debug, Clone, Args)]
+pub struct TimeRangeArgs {
+    /// Start of the window: a duration ago (30m, 2h, 1d12h) or a timestamp ("2024-06-01 09:00", RFC3339)
+    #[arg(long)]
+    pub since: Option<String>,
+    /// End of the window, same syntax as --since (default: now)
+    #[arg(long)]
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl TimeRangeArgs {
+    pub fn resolve(&self) -> Result<TimeRange> {
+        let now = Utc::now();
+        let since = self.since.as_deref().map(|s| parse_time_arg(s, now)).transpose().context("invalid --since")?;
+        let until = self.until.as_deref().map(|s| parse_time_arg(s, now)).transpose().context("invalid --until")?;
+        if let (Some(s), Some(u)) = (since, until) {
+            if s > u { bail!("--since ({}) is after --until ({})", rfc3339(s), rfc3339(u)); }
+        }
+        Ok(TimeRange { since, until })
+    }
+}
+
+/// Parse a human time argument relative to `now`.
+///
+/// Accepts durations ago (`90s`, `15m`, `2h`, `1d12h`, `1w`), `now`, RFC3339
+/// timestamps, and local `YYYY-MM-DD[ HH:MM[:SS]]`.
+pub fn parse_time_arg(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("now") { return Ok(now); }
+    if let Some(d) = parse_duration(s) { return Ok(now - d); }
+    if let Ok(ts) = DateTime::parse_from_rfc3339(s) { return Ok(ts.with_timezone(&Utc)); }
+    for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, fmt) { return local_to_utc(naive); }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return local_to_utc(date.and_hms_opt(0, 0, 0).unwrap());
+    }
+    bail!("unrecognized time '{}' (try 2h, 30m, \"2024-06-01 09:00\" or RFC3339)", s)
+}
+
+/// Parse `1d12h30m`-style durations; `None` if `s` is not a duration.
+pub fn parse_duration(s: &str) -> Option<chrono::Duration> {
+    if s.is_empty() { return None; }
+    let mut total = chrono::Duration::zero();
+    let mut num = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() { num.push(c); continue; }
+        let n: i64 = num.parse().ok()?;
+        num.clear();
+        total += match c {
+            's' => chrono::Duration::seconds(n),
+            'm' => chrono::Duration::minutes(n),
+            'h' => chrono::Duration::hours(n),
+            'd' => chrono::Duration::days(n),
+            'w' => chrono::Duration::weeks(n),
+            _ => return None,
+        };
+    }
+    // a trailing bare number is not a duration ("2024" is not "2024s")
+    if !num.is_empty() { return None; }
+    Some(total)
+}
+
+fn local_to_utc(naive: NaiveDateTime) -> Result<DateTime<Utc>> {
+    match Local.from_local_datetime(&naive).earliest() {
+        Some(ts) => Ok(ts.with_timezone(&Utc)),
+        None => bail!("{} does not exist in the local timezone", naive),
+    }
+}
+
+/// Timestamp for JSON output.
+pub fn rfc3339(ts: DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// Timestamp for human output: local time unless `--utc` was given.
+pub fn human(ts: DateTime<Utc>, utc: bool) -> String {
+    if utc {
+        ts.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    } else {
+        ts.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_time_arg("2h", now()).unwrap(), now() - chrono::Duration::hours(2));
+        assert_eq!(parse_time_arg("1d12h", now()).unwrap(), now() - chrono::Duration::hours(36));
+        assert!(parse_duration("2024").is_none());
+        assert!(parse_duration("5x").is_none());
+    }
+
+    #[test]
+    fn absolute() {
+        let ts = parse_time_arg("2024-05-31T08:00:00+02:00", now()).unwrap();
+        assert_eq!(rfc3339(ts), "2024-05-31T06:00:00Z");
+        assert!(parse_time_arg("2024-06-01 09:00", now()).is_ok());
+        assert!(parse_time_arg("2024-06-01", now()).is_ok());
+        assert!(parse_time_arg("yesterday-ish", now()).is_err());
+    }
+
+    #[test]
+    fn range_order() {
+        let args = TimeRangeArgs { since: Some("1h".into()), until: Some("2h".into()) };
+        assert!(args.resolve().is_err());
+    }
+}
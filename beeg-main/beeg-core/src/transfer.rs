@@ -0,0 +1,179 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{IsTerminal, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::transport::Transport;
+
+/// Parse sizes like `512`, `64K`, `1M`, `2GiB`, `1T` (powers of 1024).
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let t = s.trim();
+    let t = t.strip_suffix("iB").or_else(|| t.strip_suffix('B')).unwrap_or(t);
+    let (num, mult) = match t.chars().last() {
+        Some('K') | Some('k') => (&t[..t.len() - 1], 1u64 << 10),
+        Some('M') | Some('m') => (&t[..t.len() - 1], 1 << 20),
+        Some('G') | Some('g') => (&t[..t.len() - 1], 1 << 30),
+        Some('T') | Some('t') => (&t[..t.len() - 1], 1 << 40),
+        _ => (t, 1),
+    };
+    let n: u64 = num.trim().parse().map_err(|_| format!("invalid size '{}'", s))?;
+    n.checked_mul(mult).ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+/// Inverse of `parse_size` for display: `1.5M`, `312.0G`, `900B`.
+pub fn format_size(n: u64) -> String {
+    const UNITS: [(&str, u64); 4] = [("T", 1 << 40), ("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+    for (suffix, unit) in UNITS {
+        if n >= unit { return format!("{:.1}{}", n as f64 / unit as f64, suffix); }
+    }
+    format!("{}B", n)
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Source bytes per chunk; each chunk is compressed as its own frame and is the unit of resume
+    pub chunk_size: u64,
+    /// Stop after this many source bytes and append a truncation marker
+    pub max_size: Option<u64>,
+    /// Draw a progress line on stderr (only when it is a terminal)
+    pub progress: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchResult {
+    pub local: PathBuf,
+    pub size: u64,
+    pub fetched: u64,
+    pub compressed: u64,
+    pub truncated: bool,
+    pub resumed: bool,
+}
+
+/// Sidecar written after every chunk so an interrupted fetch can continue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Partial {
+    remote: String,
+    /// Size of the file when the fetch began
+    size: u64,
+    limit: u64,
+    chunk_size: u64,
+    codec: String,
+    chunks_done: u64,
+    written: u64,
+}
+
+impl Partial {
+    /// Whether this interrupted fetch can go on into `next`, a fresh start of the same request.
+    /// A log that grew since still resumes: the copy ends where the file ended when the fetch
+    /// began. A file that shrank was rotated or rewritten and starts over.
+    fn continues_as(&self, next: &Partial, max_size: Option<u64>) -> bool {
+        let limit = max_size.map_or(self.size, |m| m.min(self.size));
+        self.remote == next.remote && self.codec == next.codec && self.chunk_size == next.chunk_size
+            && self.limit == limit && next.size >= self.size
+    }
+}
+
+/// Pull `remote` from `host` into `dest` plus a `.zst` (or `.gz`) extension.
+///
+/// The file is read in fixed-size chunks, each compressed on the remote side
+/// into a separate frame; concatenated frames decompress to the original
+/// bytes, so an interrupted transfer resumes at the last complete chunk.
+pub fn fetch_file(tr: &dyn Transport, host: &str, remote: &str, dest: &Path, opts: &FetchOptions) -> Result<FetchResult> {
+    if opts.chunk_size == 0 { bail!("chunk size must be positive"); }
+    let remote_q = shell_escape::escape(remote.into()).to_string();
+    let probe = tr.exec_idempotent(host, &format!(
+        "stat -L -c %s {} && (command -v zstd >/dev/null 2>&1 && echo zstd || echo gzip)", remote_q
+    ))?;
+    let mut lines = probe.stdout.lines();
+    let size: u64 = match lines.next().and_then(|l| l.trim().parse().ok()) {
+        Some(n) => n,
+        None => bail!("cannot stat {} on {}: {}", remote, host, probe.stderr.trim()),
+    };
+    let codec = lines.next().unwrap_or("gzip").trim().to_string();
+    let (ext, compress) = if codec == "zstd" { ("zst", "zstd -q -c -T0") } else { ("gz", "gzip -c") };
+
+    let limit = opts.max_size.map_or(size, |m| m.min(size));
+    let local = PathBuf::from(format!("{}.{}", dest.display(), ext));
+    let part_path = PathBuf::from(format!("{}.part.json", local.display()));
+    if let Some(dir) = local.parent() { fs::create_dir_all(dir)?; }
+
+    let mut state = Partial { remote: remote.to_string(), size, limit, chunk_size: opts.chunk_size, codec, chunks_done: 0, written: 0 };
+    let mut resumed = false;
+    if let Ok(data) = fs::read_to_string(&part_path) {
+        if let Ok(prev) = serde_json::from_str::<Partial>(&data) {
+            if prev.continues_as(&state, opts.max_size) {
+                state = prev;
+                resumed = true;
+            }
+        }
+    }
+    let mut file = OpenOptions::new().create(true).write(true).truncate(!resumed).open(&local)
+        .with_context(|| format!("opening {}", local.display()))?;
+    if resumed {
+        // drop whatever a half-finished chunk left behind
+        file.set_len(state.written)?;
+        file.seek(SeekFrom::End(0))?;
+    }
+
+    let show = opts.progress && std::io::stderr().is_terminal();
+    let (size, limit) = (state.size, state.limit);
+    let chunks = limit.div_ceil(opts.chunk_size);
+    for i in state.chunks_done..chunks {
+        let offset = i * opts.chunk_size;
+        let len = opts.chunk_size.min(limit - offset);
+        let cmd = format!("tail -c +{} {} | head -c {} | {}", offset + 1, remote_q, len, compress);
+        let n = tr.exec_stream(host, &cmd, &mut file)
+            .with_context(|| format!("fetching chunk {}/{} of {} from {}", i + 1, chunks, remote, host))?;
+        file.flush()?;
+        state.chunks_done = i + 1;
+        state.written += n;
+        fs::write(&part_path, serde_json::to_string(&state)?)?;
+        if show {
+            eprint!("\r{}:{} {}/{} MiB ({} chunk(s) left)   ", host, remote, (offset + len) >> 20, limit >> 20, chunks - i - 1);
+        }
+    }
+    if show { eprintln!(); }
+
+    let truncated = limit < size;
+    if truncated {
+        let marker = format!("\n[beeg: truncated at {} of {} bytes]\n", limit, size);
+        let cmd = format!("printf '%s' {} | {}", shell_escape::escape(marker.into()), compress);
+        state.written += tr.exec_stream(host, &cmd, &mut file)?;
+        file.flush()?;
+    }
+    let _ = fs::remove_file(&part_path);
+
+    Ok(FetchResult { local, size, fetched: limit, compressed: state.written, truncated, resumed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("64K"), Ok(64 << 10));
+        assert_eq!(parse_size("1M"), Ok(1 << 20));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert!(parse_size("lots").is_err());
+        assert_eq!(format_size(900), "900B");
+        assert_eq!(format_size(3 << 19), "1.5M");
+    }
+
+    #[test]
+    fn resume_matching() {
+        let prev = Partial { remote: "/var/log/beegfs-storage.log".into(), size: 100, limit: 100, chunk_size: 10, codec: "zstd".into(), chunks_done: 4, written: 33 };
+        let fresh = |size: u64, limit: u64| Partial { size, limit, chunks_done: 0, written: 0, ..prev.clone() };
+        // the log grew since: go on, up to where it ended before
+        assert!(prev.continues_as(&fresh(150, 150), None));
+        assert!(!prev.continues_as(&fresh(90, 90), None));
+        assert!(!prev.continues_as(&Partial { chunk_size: 20, ..fresh(100, 100) }, None));
+        assert!(!prev.continues_as(&Partial { codec: "gzip".into(), ..fresh(100, 100) }, None));
+        let capped = Partial { limit: 50, ..prev.clone() };
+        assert!(capped.continues_as(&fresh(150, 50), Some(50)));
+        assert!(!capped.continues_as(&fresh(150, 60), Some(60)));
+        assert!(!capped.continues_as(&fresh(150, 150), None));
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::config::Config;
+
+/// Asks in-flight commands to stop. Cancelling a token cancels every child made from it,
+/// but not its parent: quitting a TUI stops its own workers, Ctrl-C stops everything.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+    parent: Option<Box<CancelToken>>,
+}
+
+impl CancelToken {
+    pub fn child(&self) -> CancelToken {
+        CancelToken { flag: Arc::default(), parent: Some(Box::new(self.clone())) }
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed) || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+
+    /// The token's own flag, for a signal handler to set.
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.flag.clone()
+    }
+}
+
+/// The process-wide token, cancelled by Ctrl-C; transports use it unless the config has its own.
+pub fn global() -> &'static CancelToken {
+    static GLOBAL: OnceLock<CancelToken> = OnceLock::new();
+    GLOBAL.get_or_init(CancelToken::default)
+}
+
+/// The token commands run through `cfg` answer to.
+pub fn of(cfg: &Config) -> CancelToken {
+    cfg.cancel.clone().unwrap_or_else(|| global().clone())
+}
+
+/// A copy of `cfg` whose commands still run once Ctrl-C has cancelled the rest: for the steps
+/// that put back what an interrupted run changed, such as restarting a stopped service.
+pub fn detached(cfg: &Config) -> Config {
+    Config { cancel: Some(CancelToken::default()), ..cfg.clone() }
+}
+
+/// A command that was stopped, or never started, because its token was cancelled. Tell it
+/// apart from a failed command with `was_cancelled`.
+#[derive(Debug)]
+pub struct Cancelled {
+    pub host: String,
+}
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cancelled on {}", self.host)
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether `e` is, or was caused by, a `Cancelled`.
+pub fn was_cancelled(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| c.is::<Cancelled>())
+}
@@ -0,0 +1,202 @@
+use anyhow::Result;
+use comfy_table::{Cell, Table, presets::UTF8_FULL};
+use std::io::{BufRead, BufReader};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use super::SshTransport;
+use crate::checks::Severity;
+use crate::config;
+
+/// One step of the connection walk-through.
+#[derive(Debug, Clone)]
+struct Step {
+    name: &'static str,
+    /// `None` when the step was skipped
+    severity: Option<Severity>,
+    millis: u128,
+    detail: String,
+}
+
+impl Step {
+    fn new(name: &'static str, started: Instant, severity: Severity, detail: impl Into<String>) -> Self {
+        Step { name, severity: Some(severity), millis: started.elapsed().as_millis(), detail: detail.into() }
+    }
+
+    fn skipped(name: &'static str, why: &str) -> Self {
+        Step { name, severity: None, millis: 0, detail: why.to_string() }
+    }
+}
+
+fn status(s: &Step) -> &'static str {
+    s.severity.map(|v| v.as_str()).unwrap_or("SKIP")
+}
+
+/// Pull the interesting lines out of `ssh -vvv` stderr: offered/accepted auth methods and the outcome.
+pub(crate) fn parse_ssh_log(log: &str) -> (Option<String>, Option<String>) {
+    let mut methods = None;
+    let mut outcome = None;
+    for line in log.lines() {
+        if let Some(m) = line.split("Authentications that can continue:").nth(1) {
+            methods = Some(m.trim().to_string());
+        } else if line.contains("Authenticated to") || line.contains("Authentication succeeded") {
+            outcome = Some(line.trim_start_matches("debug1: ").trim().to_string());
+        } else if ["Permission denied", "Host key verification failed", "Connection refused", "Connection timed out", "Could not resolve hostname"]
+            .iter().any(|p| line.contains(p)) {
+            outcome = Some(line.trim().to_string());
+        }
+    }
+    (methods, outcome)
+}
+
+/// Walk through the connection to `node` one layer at a time.
+pub fn run(cli: &crate::GlobalArgs, cfg: &config::Config, node: &str, port: Option<u16>, timeout: u64) -> Result<Severity> {
+    let node = crate::checks::single_node(cfg, node)?;
+    let port = port.or(node.ssh_port).unwrap_or(22);
+    let limit = Duration::from_secs(timeout);
+    let mut steps = Vec::new();
+    let mut ssh_log = String::new();
+    let local = cfg.transport == "local";
+
+    if local {
+        for s in ["dns", "tcp", "banner", "auth"] { steps.push(Step::skipped(s, "local transport")); }
+    } else {
+        // behind a jump host the direct dns/tcp/banner probes say nothing; ssh -vvv still covers the path
+        let hop = config::first_hop(cfg, node);
+        if let Some(hop) = &hop {
+            let why = format!("reached through jump host {}", hop);
+            for s in ["dns", "tcp", "banner"] { steps.push(Step::skipped(s, &why)); }
+        } else {
+            // DNS
+            let started = Instant::now();
+            let addrs: Vec<SocketAddr> = match (node.host.as_str(), port).to_socket_addrs() {
+                Ok(a) => a.collect(),
+                Err(e) => { steps.push(Step::new("dns", started, Severity::Failure, e.to_string())); Vec::new() }
+            };
+            if !addrs.is_empty() {
+                let list: Vec<String> = addrs.iter().map(|a| a.ip().to_string()).collect();
+                steps.push(Step::new("dns", started, Severity::Ok, list.join(", ")));
+            }
+
+            // TCP + banner
+            let started = Instant::now();
+            let stream = addrs.iter().find_map(|a| TcpStream::connect_timeout(a, limit).ok());
+            match stream {
+                None if addrs.is_empty() => steps.push(Step::skipped("tcp", "no address")),
+                None => steps.push(Step::new("tcp", started, Severity::Failure, format!("cannot connect to port {}", port))),
+                Some(s) => {
+                    steps.push(Step::new("tcp", started, Severity::Ok, format!("{} port {}", s.peer_addr().map(|a| a.ip().to_string()).unwrap_or_default(), port)));
+                    let started = Instant::now();
+                    let _ = s.set_read_timeout(Some(limit));
+                    let mut banner = String::new();
+                    match BufReader::new(s).read_line(&mut banner) {
+                        Ok(_) if banner.starts_with("SSH-") => steps.push(Step::new("banner", started, Severity::Ok, banner.trim())),
+                        Ok(_) => steps.push(Step::new("banner", started, Severity::Failure, format!("not an SSH server: {:?}", banner.trim()))),
+                        Err(e) => steps.push(Step::new("banner", started, Severity::Failure, e.to_string())),
+                    }
+                }
+            }
+        }
+
+        // auth, with the same options the transport uses
+        let started = Instant::now();
+        let ssh = SshTransport::new(cfg);
+        let out = ssh.command_opts(&node.host, "true", &["-vvv", "-p", &port.to_string()]).and_then(|mut c| Ok(c.output()?));
+        match out {
+            Ok(o) => {
+                ssh_log = String::from_utf8_lossy(&o.stderr).into_owned();
+                let (methods, outcome) = parse_ssh_log(&ssh_log);
+                let detail = format!("server offers: {}; {}", methods.as_deref().unwrap_or("?"), outcome.as_deref().unwrap_or("no outcome in ssh log"));
+                let sev = if o.status.success() { Severity::Ok } else { Severity::Failure };
+                steps.push(Step::new("auth", started, sev, detail));
+            }
+            Err(e) => steps.push(Step::new("auth", started, Severity::Failure, format!("cannot run ssh: {}", e))),
+        }
+    }
+
+    let tr = super::from_config(cfg);
+    let reached = steps.iter().all(|s| s.severity.is_none_or(|v| v == Severity::Ok));
+    if reached {
+        // remote shell
+        let started = Instant::now();
+        match tr.exec(&node.host, "echo beeg-ok; id -un") {
+            Ok(o) if o.stdout.starts_with("beeg-ok") => {
+                let user = o.stdout.lines().nth(1).unwrap_or("?").to_string();
+                steps.push(Step::new("shell", started, Severity::Ok, format!("logged in as {}", user)));
+            }
+            Ok(o) => steps.push(Step::new("shell", started, Severity::Failure, format!("unexpected output: {:?} {}", o.stdout.trim(), o.stderr.trim()))),
+            Err(e) => steps.push(Step::new("shell", started, Severity::Failure, e.to_string())),
+        }
+        // sudo
+        let started = Instant::now();
+        match tr.exec(&node.host, "if [ \"$(id -u)\" = 0 ]; then echo root; elif sudo -n true 2>/dev/null; then echo nopasswd; else echo password; fi") {
+            Ok(o) => {
+                let (sev, detail) = match o.stdout.trim() {
+                    "root" => (Severity::Ok, "already root"),
+                    "nopasswd" => (Severity::Ok, "passwordless sudo"),
+                    _ => (Severity::Warning, "sudo needs a password (or is not allowed)"),
+                };
+                steps.push(Step::new("sudo", started, sev, detail));
+            }
+            Err(e) => steps.push(Step::new("sudo", started, Severity::Failure, e.to_string())),
+        }
+    } else {
+        for s in ["shell", "sudo"] { steps.push(Step::skipped(s, "an earlier step failed")); }
+    }
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Step", "Status", "Time (ms)", "Detail"]);
+            for s in &steps {
+                let st = match s.severity {
+                    Some(sev) => crate::tables::status_cell(status(s), sev),
+                    None => Cell::new(status(s)),
+                };
+                table.add_row(vec![Cell::new(s.name), st, Cell::new(s.millis), Cell::new(&s.detail)]);
+            }
+            crate::tables::note(cli.output, &format!("{} ({})", node.name, node.host));
+            crate::tables::print(cli.output, &table);
+            // the raw ssh log is long; show it when asked for or when auth went wrong
+            let auth_failed = steps.iter().any(|s| s.name == "auth" && s.severity.is_some_and(|v| v != Severity::Ok));
+            if !ssh_log.is_empty() && (cli.verbose > 0 || auth_failed) {
+                crate::tables::note(cli.output, &format!("--- ssh -vvv ---\n{}", ssh_log.trim_end()));
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let obj = serde_json::json!({
+                "node": node.name,
+                "host": node.host,
+                "transport": cfg.transport,
+                "steps": steps.iter().map(|s| serde_json::json!({
+                    "step": s.name,
+                    "status": status(s),
+                    "ms": s.millis,
+                    "detail": s.detail,
+                })).collect::<Vec<_>>(),
+                "ssh_log": ssh_log,
+            });
+            crate::formats::print(cli.output, &obj)?;
+        }
+    }
+    Ok(steps.iter().filter_map(|s| s.severity).max().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssh_log_lines() {
+        let log = "debug1: Authentications that can continue: publickey,password\n\
+                   debug1: Offering public key: /root/.ssh/id_ed25519\n\
+                   debug1: Authentication succeeded (publickey).\n\
+                   Authenticated to node01 ([10.0.0.11]:22) using \"publickey\".\n";
+        let (methods, outcome) = parse_ssh_log(log);
+        assert_eq!(methods.as_deref(), Some("publickey,password"));
+        assert!(outcome.unwrap().starts_with("Authenticated to node01"));
+        let (_, outcome) = parse_ssh_log("user@node01: Permission denied (publickey).\n");
+        assert_eq!(outcome.as_deref(), Some("user@node01: Permission denied (publickey)."));
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use std::io::Write;
+use std::time::Instant;
+
+use super::{CappedOutput, ExecOutput, OutputCap, Transport};
+
+/// Records every command at debug level with its host, exit status and duration, so
+/// `--log-level debug` shows what a slow node spent its time on. Sits right above ssh, so each
+/// retry is a record of its own and `command` is what actually ran, sudo included.
+pub struct LoggedTransport {
+    pub inner: Box<dyn Transport + Send + Sync>,
+}
+
+impl LoggedTransport {
+    fn logged<T>(&self, call: &str, host: &str, cmd: &str, run: impl FnOnce() -> Result<T>, status: impl Fn(&T) -> Option<i32>) -> Result<T> {
+        if !log::log_enabled!(log::Level::Debug) { return run(); }
+        let started = Instant::now();
+        let res = run();
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match &res {
+            Ok(out) => match status(out) {
+                Some(status) => log::debug!(host, status, duration_ms, command = cmd; "{} finished", call),
+                None => log::debug!(host, duration_ms, command = cmd; "{} finished", call),
+            },
+            Err(e) => log::debug!(host, duration_ms, command = cmd; "{} failed: {:#}", call, e),
+        }
+        res
+    }
+}
+
+impl Transport for LoggedTransport {
+    fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.logged("exec", host, cmd, || self.inner.exec(host, cmd), |o| o.status)
+    }
+
+    fn exec_idempotent(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.logged("exec", host, cmd, || self.inner.exec_idempotent(host, cmd), |o| o.status)
+    }
+
+    fn exec_stream(&self, host: &str, cmd: &str, sink: &mut dyn Write) -> Result<u64> {
+        // a stream that ends without an error exited 0
+        self.logged("exec_stream", host, cmd, || self.inner.exec_stream(host, cmd, sink), |_| Some(0))
+    }
+
+    fn exec_capped(&self, host: &str, cmd: &str, cap: OutputCap) -> Result<CappedOutput> {
+        self.logged("exec_capped", host, cmd, || self.inner.exec_capped(host, cmd, cap), |_| None)
+    }
+
+    fn exec_lines(&self, host: &str, cmd: &str, on_line: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> {
+        self.logged("exec_lines", host, cmd, || self.inner.exec_lines(host, cmd, on_line), |s| *s)
+    }
+}
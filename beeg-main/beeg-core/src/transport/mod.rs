@@ -0,0 +1,537 @@
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{self, Config};
+use std::collections::HashMap;
+use cancel::{CancelToken, Cancelled};
+
+pub mod cancel;
+pub mod debug;
+mod logged;
+mod retry;
+mod sudo;
+
+#[derive(Debug, Clone)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Exit code of the command (ssh itself exits 255); `None` when killed by a signal
+    pub status: Option<i32>,
+}
+
+/// A command that did not finish within `exec_timeout`; it was killed, so nothing is known
+/// about its outcome. Tell it apart from a failed command with `timed_out`.
+#[derive(Debug)]
+pub struct TimedOut {
+    pub host: String,
+    pub after: Duration,
+}
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "timed out after {}s on {}", self.after.as_secs(), self.host)
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Whether `e` is, or was caused by, a `TimedOut`.
+pub fn timed_out(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| c.is::<TimedOut>())
+}
+
+/// Memory limit for `exec_capped`: keep at most `max` bytes of each stream,
+/// copying the complete streams to the spill writers when given.
+pub struct OutputCap {
+    pub max: usize,
+    pub spill_stdout: Option<Box<dyn Write + Send>>,
+    pub spill_stderr: Option<Box<dyn Write + Send>>,
+}
+
+/// Output of `exec_capped`; `*_bytes` count everything the command wrote.
+#[derive(Debug, Clone)]
+pub struct CappedOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub stdout_bytes: u64,
+    pub stderr_bytes: u64,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+}
+
+pub trait Transport: Send + Sync {
+    fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput>;
+    /// `exec` for a command that changes nothing on the node, so running it twice is harmless.
+    /// Only these are tried again when the node could not be reached (`exec_retries`).
+    fn exec_idempotent(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.exec(host, cmd)
+    }
+    /// Run `cmd` and copy its raw stdout into `sink` as it arrives; returns bytes copied.
+    fn exec_stream(&self, host: &str, cmd: &str, sink: &mut dyn Write) -> Result<u64>;
+    /// Like `exec`, but never holds more than `cap.max` bytes per stream in memory.
+    fn exec_capped(&self, host: &str, cmd: &str, cap: OutputCap) -> Result<CappedOutput>;
+    /// Run `cmd` and hand each line of stdout and stderr (`true`) to `on_line` as it
+    /// arrives; returns the exit code. The command's stdin stays open until it ends, so
+    /// `stoppable` commands end when we do.
+    fn exec_lines(&self, host: &str, cmd: &str, on_line: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>>;
+}
+
+/// Wrap `cmd` so it is terminated, children first, once its stdin closes: when beeg exits or
+/// is interrupted, or the SSH connection drops. Without this a remote `dmesg -w` would
+/// outlive the session until it next writes.
+pub fn stoppable(cmd: &str) -> String {
+    format!(
+        "exec 3<&0\n\
+         ( {} ) </dev/null 3<&- & p=$!\n\
+         ( cat <&3 >/dev/null; pkill -TERM -P $p; kill -TERM $p ) >/dev/null 2>&1 & w=$!\n\
+         wait $p; s=$?; kill $w 2>/dev/null; exit $s",
+        cmd
+    )
+}
+
+/// Per-node SSH options; `jump` is the `-J` list, outermost hop first.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SshNode {
+    user: Option<String>,
+    port: Option<u16>,
+    identity_file: Option<String>,
+    jump: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SshTransport {
+    /// Kill ssh after this long
+    timeout: Option<Duration>,
+    user: Option<String>,
+    /// `-J` for hosts that are not in the config, from `ssh_jump_host`
+    jump: Option<String>,
+    /// Keyed by node host (and name); hosts not in the config use `user` and `jump` alone.
+    /// A node whose jump hosts loop holds the error instead.
+    nodes: HashMap<String, Result<SshNode, String>>,
+    cancel: CancelToken,
+}
+
+#[derive(Debug, Clone, Default)]
+struct LocalTransport {
+    timeout: Option<Duration>,
+    cancel: CancelToken,
+}
+
+impl SshTransport {
+    fn new(cfg: &Config) -> Self {
+        // `user@host[:port]` of a jump hop that is itself a node
+        let hop = |name: &str| match cfg.nodes.iter().find(|n| n.name == name) {
+            None => name.to_string(),
+            Some(n) => {
+                let mut s = n.host.clone();
+                if let Some(u) = n.ssh_user.as_ref().or(cfg.ssh_user.as_ref()) { s = format!("{}@{}", u, s); }
+                if let Some(p) = n.ssh_port { s = format!("{}:{}", s, p); }
+                s
+            }
+        };
+        let join = |chain: Vec<String>| (!chain.is_empty()).then(|| chain.iter().rev().map(|h| hop(h)).collect::<Vec<_>>().join(","));
+        let mut nodes = HashMap::new();
+        for n in &cfg.nodes {
+            let entry = config::jump_chain(cfg, n).map_err(|e| e.to_string()).map(|chain| SshNode {
+                user: n.ssh_user.clone().or(cfg.ssh_user.clone()),
+                port: n.ssh_port,
+                identity_file: n.identity_file.clone(),
+                jump: join(chain),
+            });
+            nodes.entry(n.name.clone()).or_insert_with(|| entry.clone());
+            nodes.insert(n.host.clone(), entry);
+        }
+        // an unlisted host takes the same path as a node with no jump host of its own
+        let unlisted = config::Node { name: String::new(), host: String::new(), ..Default::default() };
+        let jump = config::jump_chain(cfg, &unlisted).ok().and_then(join);
+        SshTransport { timeout: exec_timeout(cfg), user: cfg.ssh_user.clone(), jump, nodes, cancel: cancel::of(cfg) }
+    }
+
+    fn command(&self, host: &str, cmd: &str) -> Result<Command> {
+        self.command_opts(host, cmd, &[])
+    }
+
+    /// `command` with extra ssh options (e.g. `-vvv`) ahead of the target; they win over the
+    /// node's own settings.
+    fn command_opts(&self, host: &str, cmd: &str, extra: &[&str]) -> Result<Command> {
+        let node = match self.nodes.get(host) {
+            Some(Ok(n)) => n.clone(),
+            Some(Err(e)) => anyhow::bail!("{}", e),
+            None => SshNode { user: self.user.clone(), jump: self.jump.clone(), ..Default::default() },
+        };
+        let target = if let Some(u) = &node.user { format!("{}@{}", u, host) } else { host.to_string() };
+        let mut c = Command::new("ssh");
+        c.arg("-o").arg("BatchMode=yes")
+            .arg("-o").arg("StrictHostKeyChecking=accept-new")
+            .arg("-o").arg("ConnectTimeout=5");
+        if let Some(p) = node.port { c.arg("-p").arg(p.to_string()); }
+        if let Some(key) = &node.identity_file {
+            c.arg("-i").arg(expand_home(key)).arg("-o").arg("IdentitiesOnly=yes");
+        }
+        if let Some(j) = &node.jump { c.arg("-J").arg(j); }
+        c.args(extra).arg(target).arg(cmd);
+        Ok(c)
+    }
+}
+
+/// ssh expands `~` in config files but not in `-i`, and no shell is involved here.
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).display().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+impl Transport for SshTransport {
+    fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        output_within(self.command(host, cmd)?, host, self.timeout, &self.cancel)
+    }
+
+    fn exec_stream(&self, host: &str, cmd: &str, sink: &mut dyn Write) -> Result<u64> {
+        stream_command(self.command(host, cmd)?, host, sink, self.timeout, &self.cancel)
+    }
+
+    fn exec_capped(&self, host: &str, cmd: &str, cap: OutputCap) -> Result<CappedOutput> {
+        capped_command(self.command(host, cmd)?, cap, host, self.timeout, &self.cancel)
+    }
+
+    fn exec_lines(&self, host: &str, cmd: &str, on_line: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> {
+        lines_command(self.command(host, cmd)?, host, on_line, &self.cancel)
+    }
+}
+
+impl Transport for LocalTransport {
+    fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        let mut c = Command::new("sh");
+        c.arg("-lc").arg(cmd);
+        output_within(c, host, self.timeout, &self.cancel)
+    }
+
+    fn exec_stream(&self, host: &str, cmd: &str, sink: &mut dyn Write) -> Result<u64> {
+        let mut c = Command::new("sh");
+        c.arg("-lc").arg(cmd);
+        stream_command(c, host, sink, self.timeout, &self.cancel)
+    }
+
+    fn exec_capped(&self, host: &str, cmd: &str, cap: OutputCap) -> Result<CappedOutput> {
+        let mut c = Command::new("sh");
+        c.arg("-lc").arg(cmd);
+        capped_command(c, cap, host, self.timeout, &self.cancel)
+    }
+
+    fn exec_lines(&self, host: &str, cmd: &str, on_line: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> {
+        let mut c = Command::new("sh");
+        c.arg("-lc").arg(cmd);
+        lines_command(c, host, on_line, &self.cancel)
+    }
+}
+
+fn exec_timeout(cfg: &Config) -> Option<Duration> {
+    cfg.exec_timeout.filter(|s| *s > 0).map(Duration::from_secs)
+}
+
+/// Wait for `child` at most `limit`; when time runs out or `cancel` fires it is killed and
+/// `None` returned. Ctrl-C reaches a local `ssh` too, so one that ended as `cancel` fired
+/// counts as stopped rather than finished.
+fn wait_within(child: &mut Child, limit: Option<Duration>, cancel: &CancelToken) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = limit.map(|l| Instant::now() + l);
+    loop {
+        if let Some(status) = child.try_wait()? { return Ok((!cancel.is_cancelled()).then_some(status)); }
+        if deadline.is_some_and(|d| Instant::now() >= d) || cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// `Command::output` with a time limit. Output of a killed command is dropped: its readers
+/// are left to finish on their own, as a grandchild may still hold the pipes.
+fn output_within(mut cmd: Command, host: &str, limit: Option<Duration>, cancel: &CancelToken) -> Result<ExecOutput> {
+    started(host, cancel)?;
+    let mut child = cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let read = |r: Box<dyn Read + Send>| std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let mut r = r;
+        r.read_to_end(&mut buf).map(|_| buf)
+    });
+    let stdout = read(Box::new(child.stdout.take().expect("piped stdout")));
+    let stderr = read(Box::new(child.stderr.take().expect("piped stderr")));
+    let Some(status) = wait_within(&mut child, limit, cancel)? else { return Err(stopped(host, limit, cancel)) };
+    let join = |h: std::thread::JoinHandle<std::io::Result<Vec<u8>>>| h.join().map_err(|_| anyhow::anyhow!("output reader panicked"));
+    let (stdout, stderr) = (join(stdout)??, join(stderr)??);
+    Ok(ExecOutput { stdout: String::from_utf8_lossy(&stdout).into(), stderr: String::from_utf8_lossy(&stderr).into(), status: status.code() })
+}
+
+/// Fail right away when `cancel` has fired: no new command starts after Ctrl-C.
+fn started(host: &str, cancel: &CancelToken) -> Result<()> {
+    if cancel.is_cancelled() { return Err(Cancelled { host: host.to_string() }.into()); }
+    Ok(())
+}
+
+/// Why `wait_within` gave up on a command.
+fn stopped(host: &str, limit: Option<Duration>, cancel: &CancelToken) -> anyhow::Error {
+    match cancel.is_cancelled() {
+        true => Cancelled { host: host.to_string() }.into(),
+        false => TimedOut { host: host.to_string(), after: limit.unwrap_or_default() }.into(),
+    }
+}
+
+/// Stderr kept from a streamed command for its error message; the rest is drained unread.
+const STREAM_STDERR_MAX: usize = 64 * 1024;
+
+/// Copies stdout into `sink` as it comes. `limit` is an idle limit here: the command is killed
+/// once no output arrived for that long, so a transfer of any size can finish but a stalled
+/// one cannot hang.
+fn stream_command(mut cmd: Command, host: &str, sink: &mut dyn Write, limit: Option<Duration>, cancel: &CancelToken) -> Result<u64> {
+    started(host, cancel)?;
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().expect("piped stdout");
+    let stderr = child.stderr.take().expect("piped stderr");
+    // drained alongside, so a command that writes a pipe buffer of stderr cannot stall
+    let err_reader = std::thread::spawn(move || read_capped(stderr, STREAM_STDERR_MAX, None));
+    // the copy blocks until the stream ends, so a watcher kills the command when `cancel` fires
+    // or the output stalls
+    let start = Instant::now();
+    let last_read = std::sync::atomic::AtomicU64::new(0);
+    let (done, idle) = (AtomicBool::new(false), AtomicBool::new(false));
+    let child = Mutex::new(child);
+    let copied = std::thread::scope(|s| {
+        s.spawn(|| while !done.load(Ordering::Relaxed) {
+            let quiet = start.elapsed().saturating_sub(Duration::from_millis(last_read.load(Ordering::Relaxed)));
+            if limit.is_some_and(|l| quiet >= l) { idle.store(true, Ordering::Relaxed); }
+            if cancel.is_cancelled() || idle.load(Ordering::Relaxed) {
+                let _ = child.lock().unwrap().kill();
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        });
+        let mut stdout = Progress { inner: stdout, start, last_read: &last_read };
+        let copied = std::io::copy(&mut stdout, sink);
+        done.store(true, Ordering::Relaxed);
+        copied
+    });
+    let status = child.into_inner().unwrap().wait()?;
+    let (stderr, _) = err_reader.join().map_err(|_| anyhow::anyhow!("stderr reader panicked"))??;
+    if cancel.is_cancelled() { return Err(Cancelled { host: host.to_string() }.into()); }
+    if idle.load(Ordering::Relaxed) { return Err(TimedOut { host: host.to_string(), after: limit.unwrap_or_default() }.into()); }
+    let copied = copied?;
+    if !status.success() {
+        anyhow::bail!("remote command exited with {}: {}", status, String::from_utf8_lossy(&stderr).trim());
+    }
+    Ok(copied)
+}
+
+/// A reader that notes when it last returned data, as milliseconds since `start`.
+struct Progress<'a, R> {
+    inner: R,
+    start: Instant,
+    last_read: &'a std::sync::atomic::AtomicU64,
+}
+
+impl<R: Read> Read for Progress<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.last_read.store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Read `r` to the end, keeping the first `max` bytes and teeing everything into `spill`.
+fn read_capped(mut r: impl Read, max: usize, mut spill: Option<Box<dyn Write + Send>>) -> std::io::Result<(Vec<u8>, u64)> {
+    let mut kept = Vec::new();
+    let mut total = 0u64;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 { break; }
+        total += n as u64;
+        if kept.len() < max { kept.extend_from_slice(&buf[..n.min(max - kept.len())]); }
+        if let Some(w) = spill.as_mut() { w.write_all(&buf[..n])?; }
+    }
+    if let Some(w) = spill.as_mut() { w.flush()?; }
+    Ok((kept, total))
+}
+
+fn capped_command(mut cmd: Command, cap: OutputCap, host: &str, limit: Option<Duration>, cancel: &CancelToken) -> Result<CappedOutput> {
+    started(host, cancel)?;
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().expect("piped stdout");
+    let stderr = child.stderr.take().expect("piped stderr");
+    // drain both streams on their own threads so a chatty command cannot stall on a full pipe
+    let max = cap.max;
+    let (spill_stdout, spill_stderr) = (cap.spill_stdout, cap.spill_stderr);
+    let out_reader = std::thread::spawn(move || read_capped(stdout, max, spill_stdout));
+    let err_reader = std::thread::spawn(move || read_capped(stderr, max, spill_stderr));
+    if wait_within(&mut child, limit, cancel)?.is_none() { return Err(stopped(host, limit, cancel)); }
+    let (out, out_total) = out_reader.join().map_err(|_| anyhow::anyhow!("stdout reader panicked"))??;
+    let (err, err_total) = err_reader.join().map_err(|_| anyhow::anyhow!("stderr reader panicked"))??;
+    Ok(CappedOutput {
+        stdout: String::from_utf8_lossy(&out).into(),
+        stderr: String::from_utf8_lossy(&err).into(),
+        stdout_bytes: out_total,
+        stderr_bytes: err_total,
+        stdout_truncated: out_total > out.len() as u64,
+        stderr_truncated: err_total > err.len() as u64,
+    })
+}
+
+fn lines_command(mut cmd: Command, host: &str, on_line: &mut dyn FnMut(bool, &str), cancel: &CancelToken) -> Result<Option<i32>> {
+    started(host, cancel)?;
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdin = child.stdin.take();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let readers = [(false, Box::new(child.stdout.take().expect("piped stdout")) as Box<dyn Read + Send>),
+                   (true, Box::new(child.stderr.take().expect("piped stderr")))]
+        .map(|(is_err, r)| {
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                let mut r = BufReader::new(r);
+                let mut buf = Vec::new();
+                while matches!(r.read_until(b'\n', &mut buf), Ok(n) if n > 0) {
+                    let line = String::from_utf8_lossy(&buf).trim_end_matches(['\n', '\r']).to_string();
+                    if tx.send((is_err, line)).is_err() { break; }
+                    buf.clear();
+                }
+            })
+        });
+    drop(tx);
+    loop {
+        if cancel.is_cancelled() {
+            // closing stdin ends a `stoppable` command, remote ones too once ssh is gone; the
+            // readers are left to finish on their own
+            drop(stdin);
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Cancelled { host: host.to_string() }.into());
+        }
+        match rx.recv_timeout(Duration::from_millis(20)) {
+            Ok((is_err, line)) => on_line(is_err, &line),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    for r in readers { let _ = r.join(); }
+    let status = child.wait()?;
+    drop(stdin);
+    Ok(status.code())
+}
+
+pub fn from_config(cfg: &Config) -> Box<dyn Transport + Send + Sync> {
+    let inner: Box<dyn Transport + Send + Sync> = match cfg.transport.as_str() {
+        "local" => Box::new(LocalTransport { timeout: exec_timeout(cfg), cancel: cancel::of(cfg) }),
+        _ => Box::new(SshTransport::new(cfg)),
+    };
+    let inner = Box::new(logged::LoggedTransport { inner });
+    let inner = retry::RetryTransport::wrap_if_needed(cfg, inner);
+    let inner = sudo::SudoTransport::wrap_if_needed(cfg, inner);
+    match &cfg.journal {
+        Some(journal) => Box::new(crate::journal::JournalTransport { inner, journal: journal.clone() }),
+        None => inner,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capped_read() {
+        let data = vec![b'x'; 200 * 1024];
+        let (kept, total) = read_capped(&data[..], 1000, None).unwrap();
+        assert_eq!((kept.len(), total), (1000, 200 * 1024));
+        let (kept, total) = read_capped(&b"short"[..], 1000, None).unwrap();
+        assert_eq!((kept.as_slice(), total), (&b"short"[..], 5));
+    }
+
+    #[test]
+    fn streamed_lines() {
+        let mut lines = Vec::new();
+        let status = LocalTransport::default().exec_lines("localhost", &stoppable("echo a; echo b >&2; echo c; exit 3"), &mut |is_err, l| lines.push((is_err, l.to_string()))).unwrap();
+        assert_eq!(status, Some(3));
+        lines.sort();
+        assert_eq!(lines, vec![(false, "a".into()), (false, "c".into()), (true, "b".into())]);
+    }
+
+    #[test]
+    fn cancelled_commands_stop() {
+        let started = Instant::now();
+        for lines in [false, true] {
+            let parent = CancelToken::default();
+            let local = LocalTransport { timeout: None, cancel: parent.child() };
+            std::thread::scope(|s| {
+                s.spawn(|| { std::thread::sleep(Duration::from_millis(200)); parent.cancel(); });
+                let err = match lines {
+                    false => local.exec("localhost", "sleep 5").unwrap_err(),
+                    true => local.exec_lines("localhost", &stoppable("echo a; sleep 5"), &mut |_, _| {}).unwrap_err(),
+                };
+                assert!(cancel::was_cancelled(&err) && !timed_out(&err));
+            });
+            // nothing new starts once cancelled
+            assert!(cancel::was_cancelled(&local.exec("localhost", "true").unwrap_err()));
+        }
+        assert!(started.elapsed() < Duration::from_secs(4));
+        // cancelling a child leaves its parent alone
+        let child = CancelToken::default();
+        child.child().cancel();
+        assert!(!child.is_cancelled());
+
+        // a detached config still runs what puts things back
+        let cancelled = CancelToken::default();
+        cancelled.cancel();
+        let cfg = Config { transport: "local".into(), cancel: Some(cancelled), ..config::load(Some(&std::path::PathBuf::from("/nonexistent"))).unwrap() };
+        assert!(cancel::was_cancelled(&from_config(&cfg).exec("localhost", "true").unwrap_err()));
+        assert_eq!(from_config(&cancel::detached(&cfg)).exec("localhost", "true").unwrap().status, Some(0));
+    }
+
+    #[test]
+    fn streamed_output() {
+        let local = LocalTransport { timeout: Some(Duration::from_millis(500)), cancel: CancelToken::default() };
+        // far more stderr than a pipe holds, then stdout
+        let mut out = Vec::new();
+        assert_eq!(local.exec_stream("localhost", "head -c 1000000 /dev/zero >&2; echo ok", &mut out).unwrap(), 3);
+        assert_eq!(out, b"ok\n");
+        let err = local.exec_stream("localhost", "head -c 1000000 /dev/zero >&2; echo failed >&2; exit 2", &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().starts_with("remote command exited with exit status: 2"));
+        // slow but steady output is not idle; output that stops is
+        assert!(local.exec_stream("localhost", "for i in 1 2 3 4; do echo $i; sleep 0.3; done", &mut Vec::new()).is_ok());
+        let started = Instant::now();
+        let err = local.exec_stream("localhost", "echo a; exec sleep 5", &mut Vec::new()).unwrap_err();
+        assert!(timed_out(&err) && started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn per_node_ssh() {
+        let cfg: Config = serde_json::from_value(serde_json::json!({
+            "ssh_user": "beegadmin",
+            "nodes": [
+                {"name": "bastion", "host": "198.51.100.1", "ssh_port": 2200, "ssh_user": "jump"},
+                {"name": "s01", "host": "10.0.0.1", "identity_file": "/keys/beeg", "proxy_jump": "bastion"},
+                {"name": "s02", "host": "10.0.0.2", "proxy_jump": "s01"},
+            ]
+        })).unwrap();
+        let ssh = SshTransport::new(&cfg);
+        let args = |host: &str| ssh.command(host, "true").unwrap().get_args().map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>().join(" ");
+        assert!(args("198.51.100.1").ends_with("-p 2200 jump@198.51.100.1 true"));
+        assert!(args("10.0.0.1").ends_with("-i /keys/beeg -o IdentitiesOnly=yes -J jump@198.51.100.1:2200 beegadmin@10.0.0.1 true"));
+        assert!(args("s02").ends_with("-J jump@198.51.100.1:2200,beegadmin@10.0.0.1 beegadmin@s02 true"));
+        assert!(args("unknown").ends_with("ConnectTimeout=5 beegadmin@unknown true"));
+
+        // a global bastion covers every node without its own jump host, but not itself
+        let mut cfg = cfg;
+        cfg.ssh_jump_host = Some("bastion".into());
+        cfg.nodes.push(config::Node { name: "s03".into(), host: "10.0.0.3".into(), ..Default::default() });
+        let ssh = SshTransport::new(&cfg);
+        let args = |host: &str| ssh.command(host, "true").unwrap().get_args().map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>().join(" ");
+        assert!(args("10.0.0.3").ends_with("-J jump@198.51.100.1:2200 beegadmin@10.0.0.3 true"));
+        assert!(args("10.9.9.9").ends_with("-J jump@198.51.100.1:2200 beegadmin@10.9.9.9 true"));
+        assert!(!args("198.51.100.1").contains("-J"));
+        assert!(args("s02").ends_with("-J jump@198.51.100.1:2200,beegadmin@10.0.0.1 beegadmin@s02 true"));
+    }
+}
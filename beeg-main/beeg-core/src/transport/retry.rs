@@ -0,0 +1,121 @@
+use anyhow::Result;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use super::cancel::CancelToken;
+use super::{timed_out, CappedOutput, ExecOutput, OutputCap, Transport};
+use crate::config::Config;
+
+/// Wait before the first retry; doubles with every further one.
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Wait before retry `attempt` (1-based) when the first one waits `first`.
+pub(crate) fn backoff(first: Duration, attempt: u32) -> Duration {
+    first.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(MAX_BACKOFF)
+}
+
+/// Tries a read-only command (`exec_idempotent`) again when it timed out or, over ssh, could
+/// not connect (ssh exits 255). A command that ran and failed is not retried: it would only
+/// fail again. Anything else may have changed the node before the connection dropped, and a
+/// streaming call has already passed output on, so those run once.
+pub struct RetryTransport {
+    inner: Box<dyn Transport + Send + Sync>,
+    retries: u32,
+    first_backoff: Duration,
+    /// Whether exit code 255 means ssh could not connect rather than the command's own code
+    ssh: bool,
+    /// Cuts the wait short; a cancelled command is not tried again
+    cancel: CancelToken,
+}
+
+impl RetryTransport {
+    pub fn wrap_if_needed(cfg: &Config, inner: Box<dyn Transport + Send + Sync>) -> Box<dyn Transport + Send + Sync> {
+        if cfg.exec_retries == 0 { return inner; }
+        Box::new(RetryTransport { inner, retries: cfg.exec_retries, first_backoff: FIRST_BACKOFF, ssh: cfg.transport != "local", cancel: super::cancel::of(cfg) })
+    }
+
+    fn again(&self, attempt: u32) -> bool {
+        if attempt >= self.retries { return false; }
+        let until = Instant::now() + backoff(self.first_backoff, attempt + 1);
+        while Instant::now() < until && !self.cancel.is_cancelled() {
+            std::thread::sleep(Duration::from_millis(20).min(until.saturating_duration_since(Instant::now())));
+        }
+        !self.cancel.is_cancelled()
+    }
+}
+
+impl Transport for RetryTransport {
+    fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.inner.exec(host, cmd)
+    }
+
+    fn exec_idempotent(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        let mut attempt = 0;
+        loop {
+            let res = self.inner.exec_idempotent(host, cmd);
+            let unreachable = match &res {
+                Ok(out) => self.ssh && out.status == Some(255),
+                Err(e) => timed_out(e),
+            };
+            if !unreachable || !self.again(attempt) { return res; }
+            attempt += 1;
+        }
+    }
+
+    fn exec_stream(&self, host: &str, cmd: &str, sink: &mut dyn Write) -> Result<u64> {
+        self.inner.exec_stream(host, cmd, sink)
+    }
+
+    fn exec_capped(&self, host: &str, cmd: &str, cap: OutputCap) -> Result<CappedOutput> {
+        self.inner.exec_capped(host, cmd, cap)
+    }
+
+    fn exec_lines(&self, host: &str, cmd: &str, on_line: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> {
+        self.inner.exec_lines(host, cmd, on_line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Times out on `flaky` until the third call; `down` never connects.
+    struct Flaky(Arc<AtomicU32>);
+
+    impl Transport for Flaky {
+        fn exec(&self, host: &str, _: &str) -> Result<ExecOutput> {
+            let n = self.0.fetch_add(1, Ordering::Relaxed);
+            match host {
+                "flaky" if n < 2 => Err(super::super::TimedOut { host: host.into(), after: Duration::from_secs(5) }.into()),
+                "down" => Ok(ExecOutput { stdout: String::new(), stderr: "ssh: connect to host down port 22: No route to host".into(), status: Some(255) }),
+                _ => Ok(ExecOutput { stdout: "ok".into(), stderr: String::new(), status: Some(1) }),
+            }
+        }
+        fn exec_stream(&self, _: &str, _: &str, _: &mut dyn Write) -> Result<u64> { unimplemented!() }
+        fn exec_capped(&self, _: &str, _: &str, _: OutputCap) -> Result<CappedOutput> { unimplemented!() }
+        fn exec_lines(&self, _: &str, _: &str, _: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> { unimplemented!() }
+    }
+
+    #[test]
+    fn retries_only_unreachable() {
+        let b = |attempt| backoff(FIRST_BACKOFF, attempt);
+        assert_eq!((b(1), b(2), b(3), b(40)), (Duration::from_secs(1), Duration::from_secs(2), Duration::from_secs(4), MAX_BACKOFF));
+        let calls = Arc::new(AtomicU32::new(0));
+        let tr = RetryTransport { inner: Box::new(Flaky(calls.clone())), retries: 2, first_backoff: Duration::ZERO, ssh: true, cancel: CancelToken::default() };
+        assert_eq!(tr.exec_idempotent("flaky", "true").unwrap().stdout, "ok");
+        assert_eq!(calls.swap(0, Ordering::Relaxed), 3);
+        // a command that failed on its own is not run again
+        assert_eq!(tr.exec_idempotent("n1", "false").unwrap().status, Some(1));
+        assert_eq!(calls.swap(0, Ordering::Relaxed), 1);
+        // nor is one that may have changed the node before the connection went
+        assert!(tr.exec("flaky", "systemctl restart beegfs-meta").is_err());
+        assert_eq!(tr.exec("down", "systemctl restart beegfs-meta").unwrap().status, Some(255));
+        assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+        let tr = RetryTransport { retries: 1, ..tr };
+        assert_eq!(tr.exec_idempotent("down", "true").unwrap().status, Some(255));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+}
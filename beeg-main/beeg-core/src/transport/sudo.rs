@@ -0,0 +1,164 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::{CappedOutput, ExecOutput, OutputCap, Transport};
+use crate::config::Config;
+
+/// How commands on one host are escalated.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Become {
+    /// User to run as; root when `None`
+    pub user: Option<String>,
+    /// Helper on the node that prints the sudo password (`sudo -A`)
+    pub askpass: Option<String>,
+}
+
+/// `become`/`sudo_user` for `node`, falling back to the top-level settings; setting a
+/// `sudo_user` turns `become` on unless it is explicitly false.
+pub(crate) fn become_for(cfg: &Config, node: Option<&crate::config::Node>) -> Option<Become> {
+    let user = node.and_then(|n| n.sudo_user.clone()).or(cfg.sudo_user.clone());
+    let on = node.and_then(|n| n.use_sudo).unwrap_or(cfg.use_sudo || user.is_some());
+    on.then(|| Become { user, askpass: cfg.sudo_askpass.clone() })
+}
+
+/// `sudo -n [-u user] -- sh -c '<cmd>'`, or `sudo -A` with an askpass helper.
+pub(crate) fn wrap(cmd: &str, b: &Become) -> String {
+    let esc = |s: &str| shell_escape::escape(s.to_string().into()).into_owned();
+    let mut s = match &b.askpass {
+        Some(p) => format!("SUDO_ASKPASS={} sudo -A", esc(p)),
+        None => "sudo -n".to_string(),
+    };
+    if let Some(u) = &b.user { s += &format!(" -u {}", esc(u)); }
+    s + &format!(" -- sh -c {}", esc(cmd))
+}
+
+/// The line of `stderr` saying sudo refused to run the command, if any.
+pub(crate) fn denied(stderr: &str) -> Option<&str> {
+    const REFUSALS: &[&str] = &[
+        "sudo: a password is required",
+        "sudo: a terminal is required",
+        "is not in the sudoers file",
+        "is not allowed to execute",
+        "sudo: no askpass program",
+        "incorrect password attempt",
+        "sudo: not found",
+        "sudo: command not found",
+    ];
+    stderr.lines().find(|l| REFUSALS.iter().any(|r| l.contains(r))).map(str::trim)
+}
+
+/// Runs every command through sudo on the hosts that ask for it.
+pub struct SudoTransport {
+    pub(crate) inner: Box<dyn Transport + Send + Sync>,
+    /// By node host and name
+    pub(crate) hosts: HashMap<String, Option<Become>>,
+    /// For hosts that are not in the config
+    pub(crate) default: Option<Become>,
+}
+
+impl SudoTransport {
+    /// `inner` itself when no node escalates.
+    pub(crate) fn wrap_if_needed(cfg: &Config, inner: Box<dyn Transport + Send + Sync>) -> Box<dyn Transport + Send + Sync> {
+        let mut hosts = HashMap::new();
+        for n in &cfg.nodes {
+            let b = become_for(cfg, Some(n));
+            hosts.entry(n.name.clone()).or_insert_with(|| b.clone());
+            hosts.insert(n.host.clone(), b);
+        }
+        let default = become_for(cfg, None);
+        if default.is_none() && hosts.values().all(Option::is_none) { return inner; }
+        Box::new(SudoTransport { inner, hosts, default })
+    }
+
+    fn spec(&self, host: &str) -> Option<&Become> {
+        match self.hosts.get(host) {
+            Some(b) => b.as_ref(),
+            None => self.default.as_ref(),
+        }
+    }
+}
+
+fn refused(host: &str, b: &Become, line: &str) -> anyhow::Error {
+    if line.contains("not found") { return anyhow::anyhow!("`become` is set but sudo is not installed on {}: {}", host, line); }
+    anyhow::anyhow!(
+        "sudo to {} is not permitted on {}: {}; allow beeg's commands without a password (NOPASSWD) for the SSH user, set sudo_askpass, or turn off `become` for this node",
+        b.user.as_deref().unwrap_or("root"), host, line,
+    )
+}
+
+impl SudoTransport {
+    /// `run` on `cmd`, wrapped in sudo when `host` asks for it.
+    fn escalated(&self, host: &str, cmd: &str, run: impl Fn(&str) -> Result<ExecOutput>) -> Result<ExecOutput> {
+        let Some(b) = self.spec(host) else { return run(cmd) };
+        let out = run(&wrap(cmd, b))?;
+        if let Some(line) = denied(&out.stderr) { return Err(refused(host, b, line)); }
+        Ok(out)
+    }
+}
+
+impl Transport for SudoTransport {
+    fn exec(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.escalated(host, cmd, |c| self.inner.exec(host, c))
+    }
+
+    fn exec_idempotent(&self, host: &str, cmd: &str) -> Result<ExecOutput> {
+        self.escalated(host, cmd, |c| self.inner.exec_idempotent(host, c))
+    }
+
+    fn exec_stream(&self, host: &str, cmd: &str, sink: &mut dyn Write) -> Result<u64> {
+        let Some(b) = self.spec(host) else { return self.inner.exec_stream(host, cmd, sink) };
+        self.inner.exec_stream(host, &wrap(cmd, b), sink).map_err(|e| match denied(&e.to_string()) {
+            Some(line) => refused(host, b, line),
+            None => e,
+        })
+    }
+
+    fn exec_capped(&self, host: &str, cmd: &str, cap: OutputCap) -> Result<CappedOutput> {
+        let Some(b) = self.spec(host) else { return self.inner.exec_capped(host, cmd, cap) };
+        let out = self.inner.exec_capped(host, &wrap(cmd, b), cap)?;
+        if let Some(line) = denied(&out.stderr) { return Err(refused(host, b, line)); }
+        Ok(out)
+    }
+
+    fn exec_lines(&self, host: &str, cmd: &str, on_line: &mut dyn FnMut(bool, &str)) -> Result<Option<i32>> {
+        let Some(b) = self.spec(host) else { return self.inner.exec_lines(host, cmd, on_line) };
+        let mut refusal = None;
+        let status = self.inner.exec_lines(host, &wrap(cmd, b), &mut |is_err, line| match denied(line) {
+            Some(l) if is_err => refusal = Some(l.to_string()),
+            _ => on_line(is_err, line),
+        })?;
+        match refusal {
+            Some(line) => Err(refused(host, b, &line)),
+            None => Ok(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalation() {
+        let cfg: Config = serde_json::from_value(serde_json::json!({
+            "become": true,
+            "nodes": [
+                {"name": "a", "host": "10.0.0.1"},
+                {"name": "b", "host": "10.0.0.2", "become": false},
+                {"name": "c", "host": "10.0.0.3", "sudo_user": "beegfs"},
+            ]
+        })).unwrap();
+        assert_eq!(become_for(&cfg, Some(&cfg.nodes[0])), Some(Become { user: None, askpass: None }));
+        assert_eq!(become_for(&cfg, Some(&cfg.nodes[1])), None);
+        let c = become_for(&cfg, Some(&cfg.nodes[2])).unwrap();
+        assert_eq!(wrap("systemctl is-active 'beegfs-meta'", &c), r#"sudo -n -u beegfs -- sh -c 'systemctl is-active '\''beegfs-meta'\'''"#);
+        let askpass = Become { user: None, askpass: Some("/usr/local/bin/vault-pass".into()) };
+        assert_eq!(wrap("dmesg", &askpass), "SUDO_ASKPASS=/usr/local/bin/vault-pass sudo -A -- sh -c dmesg");
+
+        assert_eq!(denied("sudo: a password is required\n"), Some("sudo: a password is required"));
+        assert!(denied("beeg is not in the sudoers file.  This incident will be reported.").is_some());
+        assert!(denied("Sorry, user beeg is not allowed to execute '/bin/sh -c dmesg' as root on s01.").is_some());
+        assert!(denied("dmesg: read kernel buffer failed: Operation not permitted").is_none());
+    }
+}
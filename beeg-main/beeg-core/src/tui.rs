@@ -0,0 +1,25 @@
+use crate::record::Recorder;
+use crossterm::{execute, terminal};
+use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
+use std::io::{stdout, Stdout};
+
+/// Full-screen terminal shared by the live views, recorded with `--record-session`.
+pub type Term = Terminal<CrosstermBackend<Recorder<Stdout>>>;
+
+/// Switch to raw mode on the alternate screen; `title` names the cast when recording.
+pub fn enter(cli: &crate::GlobalArgs, title: &str) -> anyhow::Result<Term> {
+    let mut out = Recorder::new(stdout(), cli.record_session.as_deref(), title)?;
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen)?;
+    Ok(Terminal::new(CrosstermBackend::new(out))?)
+}
+
+/// Restore the terminal. Leaving the alternate screen goes through the backend, so a
+/// recording ends on the normal screen too.
+pub fn leave(term: &mut Term) -> anyhow::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(term.backend_mut(), terminal::LeaveAlternateScreen)?;
+    term.show_cursor()?;
+    Ok(())
+}
@@ -0,0 +1,309 @@
+//! `beeg baseline`: keep the JSON result of a check or benchmark under a name and compare a
+//! fresh run of the same command with it later, e.g. before and after a firmware update.
+//!
+//! The command runs as a child `beeg ... --output json`, so anything with JSON output can be a
+//! baseline. Results are compared leaf by leaf: array items are matched by their `check`,
+//! `node` and `target` fields rather than by position, and times and durations are left out.
+//! A regression is a worse status, a lower version (a downgrade), a throughput or rate
+//! (`*_s`, `iops`) down by `--threshold` percent or more, or a value that is gone.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use comfy_table::{presets::UTF8_FULL, Cell, Table};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use crate::checks::Severity;
+use crate::state;
+
+const BASELINES_FILE: &str = "baselines.json";
+
+/// Fields that differ on every run and say nothing about the system.
+const VOLATILE: &[&str] = &["started", "finished", "created", "saved", "timestamp", "ts", "elapsed", "baseline", "bundle"];
+
+/// Fields that identify an item in an array of objects, in the order they are shown.
+const IDENTITY: &[&str] = &["check", "node", "target", "name"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Baseline {
+    /// The beeg arguments that produced it, without `--output`
+    pub command: Vec<String>,
+    pub saved: i64,
+    pub exit_code: i32,
+    pub result: Value,
+}
+
+fn load() -> BTreeMap<String, Baseline> {
+    state::read_json(BASELINES_FILE).unwrap_or_default()
+}
+
+/// Run `beeg <global> --output json <command>` and return its exit code and JSON output.
+fn run_json(global: &[String], command: &[String]) -> Result<(i32, Value)> {
+    if matches!(command.first().map(String::as_str), Some("baseline" | "dashboard")) {
+        bail!("'beeg {}' cannot be a baseline", command[0]);
+    }
+    let exe = std::env::current_exe().context("locating the beeg binary")?;
+    let out = Command::new(exe).args(global).args(["--output", "json"]).args(command)
+        .output().context("running beeg")?;
+    let code = out.status.code().unwrap_or(-1);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let value = serde_json::from_str(stdout.trim()).map_err(|_| {
+        let err = String::from_utf8_lossy(&out.stderr);
+        anyhow::anyhow!("'beeg {}' gave no JSON result (exit code {}): {}", command.join(" "), code, err.trim().lines().last().unwrap_or("no output"))
+    })?;
+    Ok((code, value))
+}
+
+/// Leaves of `v` by path, e.g. `results[node=n1].kernel`.
+fn flatten(v: &Value, path: &str, out: &mut BTreeMap<String, Value>) {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match v {
+        Value::Object(map) => {
+            for (k, x) in map {
+                if VOLATILE.contains(&k.as_str()) || k.ends_with("_ms") { continue; }
+                flatten(x, &join(k), out);
+            }
+        }
+        Value::Array(items) if items.iter().any(|x| x.is_object() || x.is_array()) => {
+            for (i, x) in items.iter().enumerate() {
+                let id: Vec<String> = IDENTITY.iter()
+                    .filter_map(|k| x.get(k).and_then(|v| v.as_str().map(String::from).or_else(|| v.as_u64().map(|n| n.to_string()))).map(|v| format!("{}={}", k, v)))
+                    .collect();
+                let key = if id.is_empty() { i.to_string() } else { id.join(",") };
+                flatten(x, &format!("{}[{}]", path, key), out);
+            }
+        }
+        _ => { out.insert(path.to_string(), v.clone()); }
+    }
+}
+
+fn show(v: Option<&Value>) -> String {
+    match v {
+        None => "-".into(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) => "null".into(),
+        // rates come with a long tail of digits that only get in the way
+        Some(Value::Number(n)) if n.is_f64() => format!("{:.1}", n.as_f64().unwrap_or_default()),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn severity_rank(s: &str) -> Option<u8> {
+    match s {
+        "OK" => Some(0),
+        "WARN" => Some(1),
+        "NOPERM" => Some(2),
+        "FAIL" | "ERROR" => Some(3),
+        _ => None,
+    }
+}
+
+/// Whether a larger value at `path` is better: throughputs, rates and IOPS.
+fn is_rate(path: &str) -> bool {
+    path.split(['.', '[']).any(|seg| seg.ends_with("_s") || seg.contains("iops"))
+}
+
+/// A version number, e.g. `535.129.03` or `MLNX_OFED_LINUX-23.10-0.5.5.0`; one word only,
+/// so a detail text that mentions a number is not taken for one.
+fn version(s: &str) -> Option<Vec<u64>> {
+    let re = Regex::new(r"\d+\.\d+").unwrap();
+    if s.contains(char::is_whitespace) || !re.is_match(s) { return None; }
+    crate::checks::compat::numeric(s)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct Difference {
+    pub path: String,
+    pub baseline: String,
+    pub now: String,
+    /// `worse`, `better`, `downgrade`, `upgrade`, `-25%`, `changed`, `missing`, `new`
+    pub change: String,
+    pub regression: bool,
+}
+
+/// Classify one changed leaf.
+fn classify(path: &str, base: &Value, now: &Value, threshold: u8) -> Option<(String, bool)> {
+    let key = path.rsplit('.').next().unwrap_or(path);
+    match (base, now) {
+        (Value::String(b), Value::String(n)) if key == "status" || key == "verdict" => {
+            match (severity_rank(b), severity_rank(n)) {
+                (Some(rb), Some(rn)) => Some(if rn > rb { ("worse".into(), true) } else { ("better".into(), false) }),
+                _ => Some(("changed".into(), false)),
+            }
+        }
+        (Value::String(b), Value::String(n)) => match (version(b), version(n)) {
+            (Some(vb), Some(vn)) if vn < vb => Some(("downgrade".into(), true)),
+            (Some(vb), Some(vn)) if vn > vb => Some(("upgrade".into(), false)),
+            _ => Some(("changed".into(), false)),
+        },
+        (Value::Number(b), Value::Number(n)) => {
+            let (b, n) = (b.as_f64()?, n.as_f64()?);
+            if b == 0.0 { return Some(("changed".into(), false)); }
+            let pct = (n - b) * 100.0 / b.abs();
+            if is_rate(path) && pct.abs() < threshold as f64 { return None; }
+            Some((format!("{:+.0}%", pct), is_rate(path) && pct <= -(threshold as f64)))
+        }
+        _ => Some(("changed".into(), false)),
+    }
+}
+
+/// Every leaf that differs between `base` and `now`. Rates that moved less than `threshold`
+/// percent count as unchanged.
+pub(crate) fn compare(base: &Value, now: &Value, threshold: u8) -> Vec<Difference> {
+    let (mut b, mut n) = (BTreeMap::new(), BTreeMap::new());
+    flatten(base, "", &mut b);
+    flatten(now, "", &mut n);
+    let mut paths: Vec<&String> = b.keys().chain(n.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    paths.into_iter().filter_map(|p| {
+        let (bv, nv) = (b.get(p), n.get(p));
+        let (change, regression) = match (bv, nv) {
+            (Some(x), Some(y)) if x == y => return None,
+            (Some(x), Some(y)) => classify(p, x, y, threshold)?,
+            (Some(_), None) => ("missing".into(), true),
+            (None, _) => ("new".into(), false),
+        };
+        Some(Difference { path: p.clone(), baseline: show(bv), now: show(nv), change, regression })
+    }).collect()
+}
+
+pub fn save(cli: &crate::Cli, global: &[String], name: &str, command: &[String], force: bool) -> Result<()> {
+    let mut all = load();
+    if all.contains_key(name) && !force { bail!("baseline '{}' exists; pass --force to replace it", name); }
+    let (exit_code, result) = run_json(global, command)?;
+    let b = Baseline { command: command.to_vec(), saved: Utc::now().timestamp(), exit_code, result };
+    all.insert(name.to_string(), b);
+    state::write_json(BASELINES_FILE, &all)?;
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => println!("saved baseline '{}' of 'beeg {}' (exit code {})", name, command.join(" "), exit_code),
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            crate::formats::print(cli.output, &serde_json::json!({"saved": name, "command": command, "exit_code": exit_code}))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn compare_cmd(cli: &crate::Cli, global: &[String], name: &str, threshold: u8) -> Result<Severity> {
+    let all = load();
+    let Some(b) = all.get(name) else { bail!("no baseline '{}'; see 'beeg baseline list'", name) };
+    let (exit_code, result) = run_json(global, &b.command)?;
+    let mut diffs = compare(&b.result, &result, threshold);
+    if exit_code != b.exit_code {
+        diffs.insert(0, Difference {
+            path: "exit code".into(),
+            baseline: b.exit_code.to_string(),
+            now: exit_code.to_string(),
+            change: if exit_code > b.exit_code { "worse".into() } else { "better".into() },
+            regression: exit_code > b.exit_code,
+        });
+    }
+    let regressions = diffs.iter().filter(|d| d.regression).count();
+    let saved = chrono::DateTime::from_timestamp(b.saved, 0).unwrap_or_default();
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["What", "Baseline", "Now", "Change"]);
+            for d in &diffs {
+                let sev = if d.regression { Severity::Warning } else { Severity::Ok };
+                table.add_row(vec![Cell::new(&d.path), Cell::new(&d.baseline), Cell::new(&d.now), crate::tables::status_cell(&d.change, sev)]);
+            }
+            if !diffs.is_empty() { crate::tables::print(cli.output, &table); }
+            crate::tables::note(cli.output, &format!("'beeg {}' vs baseline '{}' from {}: {} difference(s), {} regression(s)",
+                b.command.join(" "), name, crate::timefmt::human(saved, cli.utc), diffs.len(), regressions));
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            crate::formats::print(cli.output, &serde_json::json!({
+                "baseline": name,
+                "command": b.command,
+                "saved": crate::timefmt::rfc3339(saved),
+                "differences": diffs,
+                "regressions": regressions,
+            }))?;
+        }
+    }
+    if regressions > 0 { log::warn!("{} regression(s) against baseline '{}'", regressions, name); }
+    Ok(if regressions > 0 { Severity::Warning } else { Severity::Ok })
+}
+
+pub fn list(cli: &crate::Cli) -> Result<()> {
+    let all = load();
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Name", "Command", "Exit code", "Saved"]);
+            for (name, b) in &all {
+                let saved = chrono::DateTime::from_timestamp(b.saved, 0).map(|t| crate::timefmt::human(t, cli.utc)).unwrap_or_default();
+                table.add_row(vec![name.clone(), format!("beeg {}", b.command.join(" ")), b.exit_code.to_string(), saved]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let arr: Vec<_> = all.iter().map(|(name, b)| serde_json::json!({
+                "name": name,
+                "command": b.command,
+                "exit_code": b.exit_code,
+                "saved": chrono::DateTime::from_timestamp(b.saved, 0).map(crate::timefmt::rfc3339),
+            })).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn remove(cli: &crate::Cli, name: &str) -> Result<()> {
+    let mut all = load();
+    if all.remove(name).is_none() { bail!("no baseline '{}'", name); }
+    state::write_json(BASELINES_FILE, &all)?;
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => println!("removed baseline '{}'", name),
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &serde_json::json!({ "removed": name }))?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differences() {
+        let base = serde_json::json!({
+            "check": "nvidia-driver", "status": "OK", "duration_ms": 120,
+            "results": [
+                {"node": "n1", "version": "535.129.03", "status": "OK"},
+                {"node": "n2", "version": "535.129.03", "status": "OK"},
+                {"node": "n3", "version": "535.129.03", "status": "OK"},
+            ],
+            "aggregate": {"write": {"bytes_s": 1000, "iops": 50}, "read": {"bytes_s": 1000, "iops": 50}},
+            "warnings": [],
+        });
+        let now = serde_json::json!({
+            "check": "nvidia-driver", "status": "WARN", "duration_ms": 340,
+            "results": [
+                {"node": "n2", "version": "535.129.03", "status": "OK"},
+                {"node": "n1", "version": "530.30.02", "status": "WARN"},
+            ],
+            "aggregate": {"write": {"bytes_s": 750, "iops": 49}, "read": {"bytes_s": 1100, "iops": 50}},
+            "warnings": ["driver differs"],
+        });
+        let d = compare(&base, &now, 20);
+        let got: Vec<(&str, &str, bool)> = d.iter().map(|d| (d.path.as_str(), d.change.as_str(), d.regression)).collect();
+        assert_eq!(got, vec![
+            ("aggregate.write.bytes_s", "-25%", true),
+            ("results[node=n1].status", "worse", true),
+            ("results[node=n1].version", "downgrade", true),
+            ("results[node=n3].node", "missing", true),
+            ("results[node=n3].status", "missing", true),
+            ("results[node=n3].version", "missing", true),
+            ("status", "worse", true),
+            ("warnings", "changed", false),
+        ]);
+    }
+}
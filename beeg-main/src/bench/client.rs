@@ -0,0 +1,328 @@
+//! `beeg bench client`: streaming write and read throughput through the BeeGFS client, from
+//! every selected client at once. Each client writes its own file under the mount with
+//! direct I/O (fio, or dd where fio is missing), then all read their files back, so the
+//! aggregate is what the file system delivers to that many clients. Runs can be saved as a
+//! baseline and later runs are compared against it.
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use clap::ValueEnum;
+use comfy_table::{presets::UTF8_FULL, Cell, Table};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use shell_escape::escape;
+use std::collections::BTreeMap;
+
+use super::format_rate;
+use crate::checks::{exec_error, per_node, rand_suffix, wrap_timeout, Severity};
+use crate::{config, state, transport};
+
+/// Saved baselines, keyed by `baseline_key`.
+const BASELINE_FILE: &str = "bench-client.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// fio with libaio and `--iodepth` requests in flight
+    Fio,
+    /// dd, one request at a time
+    Dd,
+}
+
+impl Mode {
+    fn as_str(self) -> &'static str {
+        match self { Mode::Fio => "fio", Mode::Dd => "dd" }
+    }
+}
+
+pub(crate) struct ClientOptions {
+    pub mount: String,
+    pub selector: String,
+    pub mode: Mode,
+    pub blocksize: u64,
+    /// Bytes each client writes and reads
+    pub size: u64,
+    /// Requests in flight per client (fio only)
+    pub iodepth: u32,
+    /// A node or the aggregate this much below the baseline is a regression
+    pub regress_percent: u8,
+    pub save_baseline: bool,
+    /// Timeout seconds for each client's write or read
+    pub timeout: u64,
+}
+
+/// Throughput and IOPS of one client for one direction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Rate {
+    pub bytes_s: u64,
+    pub iops: u64,
+}
+
+/// One client's (or the aggregate's) write and read results.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Sample {
+    pub write: Option<Rate>,
+    pub read: Option<Rate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Baseline {
+    saved: i64,
+    aggregate: Sample,
+    nodes: BTreeMap<String, Sample>,
+}
+
+/// Runs are only comparable with the same mount, tool and request pattern.
+fn baseline_key(opts: &ClientOptions) -> String {
+    let depth = if opts.mode == Mode::Fio { format!(" iodepth={}", opts.iodepth) } else { String::new() };
+    format!("{} {} bs={} size={}{}", opts.mount, opts.mode.as_str(), opts.blocksize, opts.size, depth)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Write,
+    Read,
+}
+
+impl Phase {
+    fn as_str(self) -> &'static str {
+        match self { Phase::Write => "write", Phase::Read => "read" }
+    }
+}
+
+fn command(opts: &ClientOptions, file: &str, phase: Phase) -> String {
+    let f = escape(file.into());
+    match (opts.mode, phase) {
+        (Mode::Fio, _) => format!(
+            "fio --name=beeg --filename={} --rw={} --bs={} --size={} --direct=1 --ioengine=libaio --iodepth={} --output-format=json",
+            f, phase.as_str(), opts.blocksize, opts.size, opts.iodepth,
+        ),
+        (Mode::Dd, Phase::Write) => format!(
+            "LC_ALL=C dd if=/dev/zero of={} bs={} count={} oflag=direct conv=fsync 2>&1",
+            f, opts.blocksize, opts.size.div_ceil(opts.blocksize),
+        ),
+        (Mode::Dd, Phase::Read) => format!("LC_ALL=C dd if={} of=/dev/null bs={} iflag=direct 2>&1", f, opts.blocksize),
+    }
+}
+
+/// The summary line of dd: `10737418240 bytes (11 GB, 10 GiB) copied, 9.81 s, 1.1 GB/s`.
+fn parse_dd(text: &str, blocksize: u64) -> Option<Rate> {
+    let re = Regex::new(r"(\d+) bytes .*copied, ([\d.]+) s").unwrap();
+    let c = text.lines().rev().find_map(|l| re.captures(l))?;
+    let bytes: u64 = c[1].parse().ok()?;
+    let secs: f64 = c[2].parse().ok()?;
+    if secs <= 0.0 { return None; }
+    Some(Rate { bytes_s: (bytes as f64 / secs) as u64, iops: (bytes as f64 / blocksize as f64 / secs) as u64 })
+}
+
+/// `jobs[0].<write|read>` of `fio --output-format=json`; older fio only has `bw` in KiB/s.
+fn parse_fio(text: &str, phase: Phase) -> Option<Rate> {
+    // fio may print notes before the JSON document
+    let v: serde_json::Value = serde_json::from_str(&text[text.find('{')?..]).ok()?;
+    let job = &v["jobs"][0][phase.as_str()];
+    let bytes_s = job["bw_bytes"].as_u64().or_else(|| job["bw"].as_u64().map(|k| k * 1024))?;
+    let iops = job["iops"].as_f64()? as u64;
+    if bytes_s == 0 { return None; }
+    Some(Rate { bytes_s, iops })
+}
+
+fn measure(tr: &dyn transport::Transport, node: &config::Node, opts: &ClientOptions, file: &str, phase: Phase) -> Result<Rate, String> {
+    let out = tr.exec(&node.host, &wrap_timeout(&command(opts, file, phase), opts.timeout)).map_err(|e| exec_error(&e))?;
+    let parsed = match opts.mode {
+        Mode::Fio => parse_fio(&out.stdout, phase),
+        Mode::Dd => parse_dd(&out.stdout, opts.blocksize),
+    };
+    match parsed {
+        Some(r) if out.status == Some(0) => Ok(r),
+        _ if out.status == Some(127) => Err(format!("{} not found; install it or use --mode {}", opts.mode.as_str(), if opts.mode == Mode::Fio { "dd" } else { "fio" })),
+        _ if out.status == Some(124) => Err(format!("{} did not finish in {}s", phase.as_str(), opts.timeout)),
+        _ => {
+            let text = if out.stderr.trim().is_empty() { out.stdout.trim() } else { out.stderr.trim() };
+            Err(format!("{} failed: {}", phase.as_str(), text.lines().last().unwrap_or("no output")))
+        }
+    }
+}
+
+/// Change from `base` to `now` in percent, e.g. `-23.5`.
+fn change(base: Option<Rate>, now: Option<Rate>) -> Option<f64> {
+    match (base, now) {
+        (Some(b), Some(n)) if b.bytes_s > 0 => Some((n.bytes_s as f64 - b.bytes_s as f64) * 100.0 / b.bytes_s as f64),
+        _ => None,
+    }
+}
+
+/// Regressions of `now` against `base`, as `write -23% vs baseline`.
+fn regressions(base: &Sample, now: &Sample, percent: u8) -> Vec<String> {
+    [("write", base.write, now.write), ("read", base.read, now.read)].iter()
+        .filter_map(|&(what, b, n)| change(b, n).filter(|c| *c <= -(percent as f64)).map(|c| format!("{} {:.0}% vs baseline", what, c)))
+        .collect()
+}
+
+struct Row {
+    node: String,
+    sample: Sample,
+    errors: Vec<String>,
+    issues: Vec<String>,
+    status: Severity,
+}
+
+pub fn run(cli: &crate::Cli, cfg: &config::Config, opts: &ClientOptions) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, &opts.selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", opts.selector); }
+    let dir = format!("{}/.beeg-bench", opts.mount.trim_end_matches('/'));
+    let run_id = rand_suffix();
+    let file = |n: &config::Node| format!("{}/{}-{}", dir, n.name, run_id);
+
+    // every client writes at the same time, then every client reads
+    let writes: Vec<_> = per_node(cfg, &nodes, |tr, n| {
+        let d = escape(dir.as_str().into());
+        let prep = format!("mkdir -p {} && test -w {}", d, d);
+        match tr.exec(&n.host, &wrap_timeout(&prep, 30)) {
+            Ok(o) if o.status == Some(0) => measure(tr, n, opts, &file(n), Phase::Write),
+            Ok(_) => Err(format!("cannot write to {}", dir)),
+            Err(e) => Err(exec_error(&e)),
+        }
+    }).into_iter().map(|(_, w, _)| w).collect();
+    let reads = per_node(cfg, &nodes, |tr, n| {
+        let i = nodes.iter().position(|m| m.name == n.name).unwrap_or_default();
+        if writes[i].is_err() { return Err("write failed".to_string()); }
+        measure(tr, n, opts, &file(n), Phase::Read)
+    }).into_iter().map(|(_, r, _)| r);
+    per_node(cfg, &nodes, |tr, n| {
+        let _ = tr.exec(&n.host, &wrap_timeout(&format!("rm -f {}; rmdir {} 2>/dev/null", escape(file(n).into()), escape(dir.as_str().into())), 60));
+        Ok(())
+    });
+
+    let mut rows: Vec<Row> = nodes.iter().zip(writes.into_iter().zip(reads)).map(|(n, (w, r))| {
+        // a failed write leaves nothing to read; its error is the one that matters
+        let errors: Vec<String> = match (&w, &r) {
+            (Err(e), _) | (Ok(_), Err(e)) => vec![e.clone()],
+            _ => Vec::new(),
+        };
+        Row {
+            node: n.name.clone(),
+            sample: Sample { write: w.ok(), read: r.ok() },
+            status: if errors.is_empty() { Severity::Ok } else { Severity::Failure },
+            errors,
+            issues: Vec::new(),
+        }
+    }).collect();
+    config::sort_rows(cfg, cli.sort, &mut rows, |r| &r.node);
+
+    let sum = |rates: Vec<Option<Rate>>| {
+        let ok: Vec<Rate> = rates.into_iter().flatten().collect();
+        (!ok.is_empty()).then(|| Rate { bytes_s: ok.iter().map(|r| r.bytes_s).sum(), iops: ok.iter().map(|r| r.iops).sum() })
+    };
+    let aggregate = Sample {
+        write: sum(rows.iter().map(|r| r.sample.write).collect()),
+        read: sum(rows.iter().map(|r| r.sample.read).collect()),
+    };
+
+    let key = baseline_key(opts);
+    let mut baselines: BTreeMap<String, Baseline> = state::read_json(BASELINE_FILE).unwrap_or_default();
+    let baseline = baselines.get(&key).cloned();
+    let mut aggregate_issues = Vec::new();
+    if let Some(b) = &baseline {
+        for r in &mut rows {
+            if let Some(base) = b.nodes.get(&r.node) {
+                r.issues = regressions(base, &r.sample, opts.regress_percent);
+                if !r.issues.is_empty() { r.status = r.status.max(Severity::Warning); }
+            }
+        }
+        aggregate_issues = regressions(&b.aggregate, &aggregate, opts.regress_percent);
+    }
+
+    let fmt = |r: Option<Rate>| r.map(|r| (format_rate(r.bytes_s), r.iops.to_string())).unwrap_or_else(|| ("-".into(), "-".into()));
+    let vs = |node: Option<&Sample>, now: &Sample| match node {
+        Some(b) => [("W", b.write, now.write), ("R", b.read, now.read)].iter()
+            .filter_map(|&(what, b, n)| change(b, n).map(|c| format!("{} {:+.0}%", what, c)))
+            .collect::<Vec<_>>().join(" "),
+        None => "-".into(),
+    };
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "Write", "Write IOPS", "Read", "Read IOPS", "vs baseline", "Status", "Detail"]);
+            for r in &rows {
+                let ((w, wi), (rd, ri)) = (fmt(r.sample.write), fmt(r.sample.read));
+                let detail: Vec<&str> = r.errors.iter().chain(&r.issues).map(String::as_str).collect();
+                table.add_row(vec![
+                    Cell::new(&r.node), Cell::new(w), Cell::new(wi), Cell::new(rd), Cell::new(ri),
+                    Cell::new(vs(baseline.as_ref().and_then(|b| b.nodes.get(&r.node)), &r.sample)),
+                    crate::tables::status_cell(r.status.as_str(), r.status), Cell::new(detail.join("; ")),
+                ]);
+            }
+            let ((w, wi), (rd, ri)) = (fmt(aggregate.write), fmt(aggregate.read));
+            let status = if aggregate_issues.is_empty() { Severity::Ok } else { Severity::Warning };
+            table.add_row(vec![
+                Cell::new("total"), Cell::new(w), Cell::new(wi), Cell::new(rd), Cell::new(ri),
+                Cell::new(vs(baseline.as_ref().map(|b| &b.aggregate), &aggregate)),
+                crate::tables::status_cell(status.as_str(), status), Cell::new(aggregate_issues.join("; ")),
+            ]);
+            crate::tables::print(cli.output, &table);
+            let about = format!("{} per client, {} blocks, {}", crate::transfer::format_size(opts.size), crate::transfer::format_size(opts.blocksize), opts.mode.as_str());
+            match &baseline {
+                Some(b) => crate::tables::note(cli.output, &format!("{}; baseline from {}", about, crate::timefmt::human(chrono::DateTime::from_timestamp(b.saved, 0).unwrap_or_default(), cli.utc))),
+                None => crate::tables::note(cli.output, &format!("{}; no baseline yet (--save-baseline keeps this run as one)", about)),
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "mount": opts.mount,
+                "mode": opts.mode.as_str(),
+                "blocksize": opts.blocksize,
+                "size": opts.size,
+                "nodes": rows.iter().map(|r| serde_json::json!({
+                    "node": r.node,
+                    "write": r.sample.write,
+                    "read": r.sample.read,
+                    "status": r.status.as_str(),
+                    "errors": r.errors,
+                    "regressions": r.issues,
+                })).collect::<Vec<_>>(),
+                "aggregate": aggregate,
+                "aggregate_regressions": aggregate_issues,
+                "baseline": baseline.as_ref().map(|b| serde_json::json!({"saved": crate::timefmt::rfc3339(chrono::DateTime::from_timestamp(b.saved, 0).unwrap_or_default()), "aggregate": b.aggregate, "nodes": b.nodes})),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    let failed = rows.iter().any(|r| r.status == Severity::Failure);
+    if !aggregate_issues.is_empty() { log::warn!("aggregate throughput regressed: {}", aggregate_issues.join(", ")); }
+    if opts.save_baseline {
+        if failed {
+            log::warn!("not saved as baseline: some clients failed");
+        } else {
+            let nodes = rows.iter().map(|r| (r.node.clone(), r.sample.clone())).collect();
+            baselines.insert(key, Baseline { saved: Utc::now().timestamp(), aggregate, nodes });
+            state::write_json(BASELINE_FILE, &baselines)?;
+        }
+    }
+    let worst = rows.iter().map(|r| r.status).max().unwrap_or_default();
+    Ok(if aggregate_issues.is_empty() { worst } else { worst.max(Severity::Warning) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tools() {
+        let dd = "4096+0 records in\n4096+0 records out\n4294967296 bytes (4.3 GB, 4.0 GiB) copied, 4 s, 1.1 GB/s\n";
+        assert_eq!(parse_dd(dd, 1 << 20), Some(Rate { bytes_s: 1 << 30, iops: 1024 }));
+        assert_eq!(parse_dd("dd: failed to open", 1 << 20), None);
+        let fio = "note: both iodepth >= 1 and synchronous I/O engine\n{\"jobs\": [{\"read\": {\"bw_bytes\": 2147483648, \"iops\": 2048.3}, \"write\": {\"bw_bytes\": 0, \"iops\": 0}}]}";
+        assert_eq!(parse_fio(fio, Phase::Read), Some(Rate { bytes_s: 2 << 30, iops: 2048 }));
+        assert_eq!(parse_fio(fio, Phase::Write), None);
+        assert_eq!(parse_fio("{\"jobs\": [{\"write\": {\"bw\": 1024, \"iops\": 1.0}}]}", Phase::Write), Some(Rate { bytes_s: 1 << 20, iops: 1 }));
+    }
+
+    #[test]
+    fn baseline_regressions() {
+        let rate = |b| Some(Rate { bytes_s: b, iops: 1 });
+        let base = Sample { write: rate(1000), read: rate(1000) };
+        assert_eq!(regressions(&base, &Sample { write: rate(750), read: rate(950) }, 20), vec!["write -25% vs baseline"]);
+        assert!(regressions(&base, &Sample { write: rate(1300), read: None }, 20).is_empty());
+    }
+}
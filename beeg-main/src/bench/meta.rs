@@ -0,0 +1,278 @@
+//! `beeg bench meta`: metadata operation rates through the BeeGFS client. Every selected
+//! client creates, stats and removes its own set of empty files in a directory of its own,
+//! with mdtest where it is installed and a small shell workload where it is not. The rates
+//! are checked against `--min` and against a saved baseline.
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use clap::ValueEnum;
+use comfy_table::{presets::UTF8_FULL, Cell, Table};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use shell_escape::escape;
+use std::collections::BTreeMap;
+
+use crate::checks::{exec_error, per_node, rand_suffix, wrap_timeout, Severity};
+use crate::{config, state};
+
+/// Saved baselines, keyed by `baseline_key`.
+const BASELINE_FILE: &str = "bench-meta.json";
+
+/// The operations measured, in the order they run.
+pub(crate) const OPS: [&str; 3] = ["create", "stat", "unlink"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// mdtest where it is installed, else the built-in workload
+    Auto,
+    Mdtest,
+    /// A shell loop per operation; slower per file than mdtest, needs nothing on the client
+    Builtin,
+}
+
+pub(crate) struct MetaOptions {
+    pub mount: String,
+    pub selector: String,
+    pub mode: Mode,
+    /// Files per client
+    pub files: u64,
+    /// Lowest acceptable total rate per operation, ops/s
+    pub min: BTreeMap<String, f64>,
+    pub regress_percent: u8,
+    pub save_baseline: bool,
+    /// Timeout seconds for each client's run
+    pub timeout: u64,
+}
+
+/// `OP=RATE` for `--min`, e.g. `create=5000`.
+pub(crate) fn parse_min(s: &str) -> Result<(String, f64), String> {
+    let (op, rate) = s.split_once('=').ok_or_else(|| format!("expected OP=RATE, e.g. create=5000 (got '{}')", s))?;
+    if !OPS.contains(&op) { return Err(format!("unknown operation '{}'; one of {}", op, OPS.join(", "))); }
+    let rate: f64 = rate.parse().map_err(|_| format!("invalid rate '{}'", rate))?;
+    Ok((op.to_string(), rate))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Baseline {
+    saved: i64,
+    total: BTreeMap<String, f64>,
+    nodes: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+/// Rates are only comparable for the same mount, files per client and tool.
+fn baseline_key(opts: &MetaOptions, tool: &str) -> String {
+    format!("{} files={} {}", opts.mount, opts.files, tool)
+}
+
+/// One client's run: the tool used, then create, stat and unlink time stamps, or mdtest's summary.
+fn script(opts: &MetaOptions, dir: &str) -> String {
+    let d = escape(dir.into());
+    let builtin = format!(
+        "now() {{ date +%s.%N; }}\n\
+         mkdir -p \"$d\" || exit 1\n\
+         t0=$(now); i=0\n\
+         while [ $i -lt {n} ]; do : > \"$d/f.$i\" || exit 1; i=$((i+1)); done\n\
+         t1=$(now); ls -l \"$d\" > /dev/null || exit 1\n\
+         t2=$(now); find \"$d\" -type f -delete || exit 1\n\
+         t3=$(now)\n\
+         echo \"create $t0 $t1\"; echo \"stat $t1 $t2\"; echo \"unlink $t2 $t3\"\n",
+        n = opts.files,
+    );
+    let mdtest = format!("mdtest -F -C -T -r -n {} -d \"$d\"\n", opts.files);
+    let body = match opts.mode {
+        Mode::Builtin => format!("echo tool: builtin\n{}", builtin),
+        Mode::Mdtest => format!("command -v mdtest >/dev/null || {{ echo 'mdtest not found; use --mode builtin' >&2; exit 127; }}\necho tool: mdtest\n{}", mdtest),
+        Mode::Auto => format!("if command -v mdtest >/dev/null; then\necho tool: mdtest\n{}else\necho tool: builtin\n{}fi\n", mdtest, builtin),
+    };
+    format!("d={}\ntrap 'rm -rf \"$d\"' EXIT\n{}", d, body)
+}
+
+/// Rates from the built-in workload's time stamps.
+fn parse_builtin(text: &str, files: u64) -> BTreeMap<String, f64> {
+    text.lines().filter_map(|l| {
+        let f: Vec<&str> = l.split_whitespace().collect();
+        let (op, start, end) = match f.as_slice() { [op, s, e] if OPS.contains(op) => (*op, s.parse::<f64>().ok()?, e.parse::<f64>().ok()?), _ => return None };
+        let secs = end - start;
+        (secs > 0.0).then(|| (op.to_string(), files as f64 / secs))
+    }).collect()
+}
+
+/// Mean rates of mdtest's `SUMMARY rate` table (newer mdtest also prints a `SUMMARY time`).
+fn parse_mdtest(text: &str) -> BTreeMap<String, f64> {
+    let re = Regex::new(r"^\s*File (creation|stat|removal)\s*:\s*([\d.]+)\s+([\d.]+)\s+([\d.]+)").unwrap();
+    let mut rates = BTreeMap::new();
+    let mut in_rate = false;
+    for line in text.lines() {
+        if line.contains("SUMMARY") { in_rate = line.contains("rate"); continue; }
+        if !in_rate { continue; }
+        if let Some(c) = re.captures(line) {
+            let op = match &c[1] { "creation" => "create", "stat" => "stat", _ => "unlink" };
+            if let Ok(mean) = c[4].parse() { rates.insert(op.to_string(), mean); }
+        }
+    }
+    rates
+}
+
+struct Row {
+    node: String,
+    tool: String,
+    rates: BTreeMap<String, f64>,
+    issues: Vec<String>,
+    status: Severity,
+}
+
+/// Operations of `now` at least `percent` below `base`, as `create -30% vs baseline`.
+fn regressions(base: &BTreeMap<String, f64>, now: &BTreeMap<String, f64>, percent: u8) -> Vec<String> {
+    OPS.iter().filter_map(|op| {
+        let (b, n) = (base.get(*op)?, now.get(*op)?);
+        let c = (n - b) * 100.0 / b;
+        (*b > 0.0 && c <= -(percent as f64)).then(|| format!("{} {:.0}% vs baseline", op, c))
+    }).collect()
+}
+
+pub fn run(cli: &crate::Cli, cfg: &config::Config, opts: &MetaOptions) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, &opts.selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", opts.selector); }
+    let base = format!("{}/.beeg-bench", opts.mount.trim_end_matches('/'));
+    let run_id = rand_suffix();
+
+    let results = per_node(cfg, &nodes, |tr, n| -> Result<(String, BTreeMap<String, f64>), String> {
+        let dir = format!("{}/meta-{}-{}", base, n.name, run_id);
+        let cmd = format!("mkdir -p {} || exit 1\n{}", escape(base.as_str().into()), script(opts, &dir));
+        let out = tr.exec(&n.host, &wrap_timeout(&cmd, opts.timeout)).map_err(|e| exec_error(&e))?;
+        let _ = tr.exec(&n.host, &format!("rmdir {} 2>/dev/null", escape(base.as_str().into())));
+        let tool = out.stdout.lines().find_map(|l| l.strip_prefix("tool: ")).unwrap_or("-").to_string();
+        let rates = if tool == "mdtest" { parse_mdtest(&out.stdout) } else { parse_builtin(&out.stdout, opts.files) };
+        match out.status {
+            Some(0) if rates.len() == OPS.len() => Ok((tool, rates)),
+            Some(124) => Err(format!("did not finish in {}s", opts.timeout)),
+            _ => {
+                let text = if out.stderr.trim().is_empty() { out.stdout.trim() } else { out.stderr.trim() };
+                let last = text.lines().last().unwrap_or("no output");
+                Err(if tool == "-" { last.to_string() } else { format!("{} failed: {}", tool, last) })
+            }
+        }
+    });
+
+    let mut rows: Vec<Row> = results.into_iter().map(|(n, r, _)| match r {
+        Ok((tool, rates)) => Row { node: n.name.clone(), tool, rates, issues: Vec::new(), status: Severity::Ok },
+        Err(e) => Row { node: n.name.clone(), tool: "-".into(), rates: BTreeMap::new(), issues: vec![e], status: Severity::Failure },
+    }).collect();
+    config::sort_rows(cfg, cli.sort, &mut rows, |r| &r.node);
+
+    let ok: Vec<&Row> = rows.iter().filter(|r| r.status == Severity::Ok).collect();
+    let total: BTreeMap<String, f64> = if ok.is_empty() { BTreeMap::new() } else {
+        OPS.iter().map(|op| (op.to_string(), ok.iter().filter_map(|r| r.rates.get(*op)).sum())).collect()
+    };
+    // mdtest and the shell workload measure different things; a mixed run has no baseline
+    let tools: Vec<&str> = { let mut t: Vec<&str> = ok.iter().map(|r| r.tool.as_str()).collect(); t.sort_unstable(); t.dedup(); t };
+    let tool = if tools.len() == 1 { Some(tools[0].to_string()) } else { None };
+
+    let mut total_issues: Vec<String> = opts.min.iter()
+        .filter_map(|(op, min)| total.get(op).filter(|r| *r < min).map(|r| format!("{} {:.0}/s below --min {:.0}/s", op, r, min)))
+        .collect();
+    let mut baselines: BTreeMap<String, Baseline> = state::read_json(BASELINE_FILE).unwrap_or_default();
+    let key = tool.as_deref().map(|t| baseline_key(opts, t));
+    let baseline = key.as_ref().and_then(|k| baselines.get(k)).cloned();
+    if let Some(b) = &baseline {
+        for r in rows.iter_mut().filter(|r| r.status == Severity::Ok) {
+            if let Some(base) = b.nodes.get(&r.node) { r.issues = regressions(base, &r.rates, opts.regress_percent); }
+            if !r.issues.is_empty() { r.status = Severity::Warning; }
+        }
+        total_issues.extend(regressions(&b.total, &total, opts.regress_percent));
+    }
+
+    let rate = |rates: &BTreeMap<String, f64>, op: &str| rates.get(op).map(|r| format!("{:.0}/s", r)).unwrap_or_else(|| "-".into());
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "Tool", "Create", "Stat", "Unlink", "Status", "Detail"]);
+            for r in &rows {
+                let mut cells = vec![Cell::new(&r.node), Cell::new(&r.tool)];
+                cells.extend(OPS.iter().map(|op| Cell::new(rate(&r.rates, op))));
+                cells.push(crate::tables::status_cell(r.status.as_str(), r.status));
+                cells.push(Cell::new(r.issues.join("; ")));
+                table.add_row(cells);
+            }
+            let status = if total_issues.is_empty() { Severity::Ok } else { Severity::Warning };
+            let mut cells = vec![Cell::new("total"), Cell::new(tool.as_deref().unwrap_or("-"))];
+            cells.extend(OPS.iter().map(|op| Cell::new(rate(&total, op))));
+            cells.push(crate::tables::status_cell(status.as_str(), status));
+            cells.push(Cell::new(total_issues.join("; ")));
+            table.add_row(cells);
+            crate::tables::print(cli.output, &table);
+            let about = format!("{} files per client", opts.files);
+            match &baseline {
+                Some(b) => crate::tables::note(cli.output, &format!("{}; baseline from {}", about, crate::timefmt::human(chrono::DateTime::from_timestamp(b.saved, 0).unwrap_or_default(), cli.utc))),
+                None => crate::tables::note(cli.output, &format!("{}; no baseline yet (--save-baseline keeps this run as one)", about)),
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "mount": opts.mount,
+                "files": opts.files,
+                "nodes": rows.iter().map(|r| serde_json::json!({
+                    "node": r.node,
+                    "tool": r.tool,
+                    "ops_per_s": r.rates,
+                    "status": r.status.as_str(),
+                    "issues": r.issues,
+                })).collect::<Vec<_>>(),
+                "total_ops_per_s": total,
+                "total_issues": total_issues,
+                "baseline": baseline.as_ref().map(|b| serde_json::json!({"saved": crate::timefmt::rfc3339(chrono::DateTime::from_timestamp(b.saved, 0).unwrap_or_default()), "total": b.total, "nodes": b.nodes})),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    let failed = rows.iter().any(|r| r.status == Severity::Failure);
+    if !total_issues.is_empty() { log::warn!("metadata rates: {}", total_issues.join(", ")); }
+    if opts.save_baseline {
+        match key {
+            Some(key) if !failed => {
+                let nodes = rows.iter().map(|r| (r.node.clone(), r.rates.clone())).collect();
+                baselines.insert(key, Baseline { saved: Utc::now().timestamp(), total, nodes });
+                state::write_json(BASELINE_FILE, &baselines)?;
+            }
+            _ => log::warn!("not saved as baseline: {}", if failed { "some clients failed" } else { "clients used different tools" }),
+        }
+    }
+    let worst = rows.iter().map(|r| r.status).max().unwrap_or_default();
+    Ok(if total_issues.is_empty() { worst } else { worst.max(Severity::Warning) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rates() {
+        let rates = parse_builtin("tool: builtin\ncreate 100.0 102.0\nstat 102.0 102.5\nunlink 102.5 104.5\n", 1000);
+        assert_eq!(rates.get("create"), Some(&500.0));
+        assert_eq!(rates.get("stat"), Some(&2000.0));
+        assert_eq!(rates.get("unlink"), Some(&500.0));
+
+        let mdtest = "SUMMARY rate: (of 1 iterations)
+   Operation                     Max            Min           Mean        Std Dev
+   ---------                     ---            ---           ----        -------
+   File creation             :      12000.000      10000.000      11000.000       1000.000
+   File stat                 :      90000.000      90000.000      90000.000          0.000
+   File read                 :          0.000          0.000          0.000          0.000
+   File removal              :      15000.000      15000.000      15000.000          0.000
+SUMMARY time: (of 1 iterations)
+   File creation             :          9.091          9.091          9.091          0.000
+";
+        let rates = parse_mdtest(mdtest);
+        assert_eq!((rates.get("create"), rates.get("stat"), rates.get("unlink")), (Some(&11000.0), Some(&90000.0), Some(&15000.0)));
+    }
+
+    #[test]
+    fn min_and_regressions() {
+        assert_eq!(parse_min("create=5000"), Ok(("create".to_string(), 5000.0)));
+        assert!(parse_min("mkdir=10").is_err() && parse_min("stat").is_err());
+        let m = |c: f64, s: f64| BTreeMap::from([("create".to_string(), c), ("stat".to_string(), s)]);
+        assert_eq!(regressions(&m(1000.0, 1000.0), &m(700.0, 900.0), 20), vec!["create -30% vs baseline"]);
+    }
+}
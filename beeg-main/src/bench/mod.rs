@@ -0,0 +1,42 @@
+//! `beeg bench`: run BeeGFS benchmarks across the cluster and report them per target or node.
+
+pub mod client;
+pub mod meta;
+pub mod storage;
+
+/// Throughput in bytes per second for display: `1.2G/s`, `830.0M/s`.
+pub(crate) fn format_rate(bytes_s: u64) -> String {
+    format!("{}/s", crate::transfer::format_size(bytes_s))
+}
+
+fn median(values: &[u64]) -> Option<u64> {
+    let mut v = values.to_vec();
+    v.sort_unstable();
+    match v.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(v[n / 2]),
+        n => Some((v[n / 2 - 1] + v[n / 2]) / 2),
+    }
+}
+
+/// Indexes of `values` below `percent`% of their median: the slow outliers of a run where every
+/// member should perform alike. Needs at least three values to tell an outlier from a pair.
+pub(crate) fn slow_outliers(values: &[u64], percent: u8) -> Vec<usize> {
+    if values.len() < 3 { return Vec::new(); }
+    let Some(median) = median(values) else { return Vec::new() };
+    let floor = median as f64 * percent as f64 / 100.0;
+    values.iter().enumerate().filter(|(_, &v)| (v as f64) < floor).map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outliers() {
+        assert_eq!(slow_outliers(&[1000, 980, 400, 1020], 75), vec![2]);
+        assert_eq!(slow_outliers(&[1000, 400], 75), Vec::<usize>::new());
+        assert_eq!(slow_outliers(&[1000, 1000, 1000], 75), Vec::<usize>::new());
+        assert_eq!(format_rate(1 << 30), "1.0G/s");
+    }
+}
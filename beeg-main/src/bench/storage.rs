@@ -0,0 +1,321 @@
+//! `beeg bench storage`: the storage targets' own streaming throughput, measured by the
+//! storage servers with `beegfs-ctl --storagebench` (no client or network involved). beeg
+//! starts the benchmark, polls its status until every target is done and reports each
+//! target's throughput, flagging targets well below the others.
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use comfy_table::{presets::UTF8_FULL, Cell, Table};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+use super::{format_rate, slow_outliers};
+use crate::checks::{single_node, wrap_timeout, Severity};
+use crate::{config, transport};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    Write,
+    /// Reads the files a previous write left behind (see --keep-files)
+    Read,
+    /// Write, then read the same files back
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Write,
+    Read,
+}
+
+impl Phase {
+    fn as_str(self) -> &'static str {
+        match self { Phase::Write => "write", Phase::Read => "read" }
+    }
+}
+
+pub(crate) struct StorageOptions {
+    /// Node to run beegfs-ctl on; the first mgmtd in the config when `None`
+    pub node: Option<String>,
+    /// Target IDs, comma-separated, or `all`
+    pub targets: String,
+    pub blocksize: u64,
+    /// Bytes per thread and target
+    pub size: u64,
+    pub threads: u32,
+    pub mode: Mode,
+    /// Seconds between status polls
+    pub interval: u64,
+    /// Seconds a phase may take before it is stopped
+    pub max_time: u64,
+    /// A target below this share of the median is slow
+    pub slow_percent: u8,
+    /// Leave the benchmark files on the targets
+    pub keep_files: bool,
+    pub timeout: u64,
+}
+
+/// What `beegfs-ctl --storagebench --status --verbose` says: how many targets are in each
+/// state, and the throughput of each target once the run is over.
+#[derive(Debug, Default, PartialEq)]
+struct Status {
+    states: BTreeMap<String, u32>,
+    /// (target ID, KiB/s, node)
+    targets: Vec<(String, u64, String)>,
+}
+
+impl Status {
+    fn count(&self, state: &str) -> u32 {
+        self.states.get(state).copied().unwrap_or(0)
+    }
+
+    /// Whether any target is still busy with the benchmark.
+    fn busy(&self) -> bool {
+        ["Initialized", "Running", "Stopping", "Finishing"].iter().any(|s| self.count(s) > 0)
+    }
+}
+
+fn parse_status(text: &str) -> Status {
+    let state = Regex::new(r"^\s*(Uninitialized|Initialized|Running|Stopping|Stopped|Finishing|Finished|Error)\s*:\s*(\d+)\s*$").unwrap();
+    let target = Regex::new(r"^\s*(\d+)\s+(\d+)\s+KiB/s\s+nodeID:\s*(\S+)").unwrap();
+    let mut status = Status::default();
+    let mut listing = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("List of all targets") { listing = true; continue; }
+        if let Some(c) = state.captures(line) {
+            *status.states.entry(c[1].to_string()).or_default() += c[2].parse::<u32>().unwrap_or(0);
+        } else if let Some(c) = target.captures(line).filter(|_| listing) {
+            status.targets.push((c[1].to_string(), c[2].parse().unwrap_or(0), c[3].to_string()));
+        }
+    }
+    status
+}
+
+/// `--alltargets` or `--targetids=...` for the target list given on the command line.
+fn target_args(targets: &str) -> Result<String> {
+    if targets == "all" { return Ok("--alltargets".into()); }
+    let ids: Vec<&str> = targets.split(',').map(str::trim).collect();
+    if ids.iter().any(|id| id.is_empty() || !id.chars().all(|c| c.is_ascii_digit())) {
+        bail!("--targets takes numeric target IDs separated by commas, or 'all' (got '{}')", targets);
+    }
+    Ok(format!("--targetids={}", ids.join(",")))
+}
+
+/// The node to drive the benchmark from: `--node`, else the first mgmtd in the config.
+pub(crate) fn ctl_node<'a>(cfg: &'a config::Config, selector: Option<&str>) -> Result<&'a config::Node> {
+    match selector {
+        Some(s) => single_node(cfg, s),
+        None => cfg.nodes.iter().find(|n| n.role() == "mgmtd")
+            .ok_or_else(|| anyhow::anyhow!("no node with role mgmtd in the config; pass --node to choose where beegfs-ctl runs")),
+    }
+}
+
+struct Run<'a> {
+    tr: Box<dyn transport::Transport>,
+    node: &'a config::Node,
+    targets: String,
+    opts: &'a StorageOptions,
+}
+
+impl Run<'_> {
+    fn ctl(&self, args: &str) -> Result<transport::ExecOutput> {
+        let cmd = format!("beegfs-ctl --storagebench {} {}", self.targets, args);
+        let out = self.tr.exec(&self.node.host, &wrap_timeout(&cmd, self.opts.timeout))?;
+        if out.status != Some(0) {
+            crate::checks::check_permission(&out, &self.node.name, "beegfs-ctl --storagebench")?;
+            let msg = if out.stderr.trim().is_empty() { out.stdout.trim() } else { out.stderr.trim() };
+            bail!("{} on {} failed: {}", cmd, self.node.name, msg);
+        }
+        Ok(out)
+    }
+
+    /// Start one phase and wait for it; the targets' results, or an error after stopping it.
+    fn phase(&self, phase: Phase) -> Result<Status> {
+        let o = self.opts;
+        self.ctl(&format!("--{} --blocksize={} --size={} --threads={}", phase.as_str(), o.blocksize, o.size, o.threads))?;
+        let started = Instant::now();
+        let progress = std::io::stderr().is_terminal();
+        loop {
+            std::thread::sleep(Duration::from_secs(o.interval));
+            let status = match self.ctl("--status --verbose") {
+                Ok(out) => parse_status(&out.stdout),
+                Err(e) => { let _ = self.ctl("--stop"); return Err(e); }
+            };
+            if progress {
+                eprint!("\r{}: {} running, {} finished, {}s   ", phase.as_str(), status.count("Running"), status.count("Finished"), started.elapsed().as_secs());
+            }
+            if !status.busy() {
+                if progress { eprintln!(); }
+                if status.count("Error") > 0 {
+                    bail!("{} benchmark ended with errors on {} target(s); see the storage server logs", phase.as_str(), status.count("Error"));
+                }
+                return Ok(status);
+            }
+            if started.elapsed() >= Duration::from_secs(o.max_time) {
+                if progress { eprintln!(); }
+                let _ = self.ctl("--stop");
+                bail!("{} benchmark still running after {}s; stopped it", phase.as_str(), o.max_time);
+            }
+        }
+    }
+}
+
+/// One target's result: KiB/s per phase.
+struct Row {
+    target: String,
+    node: String,
+    rates: BTreeMap<&'static str, u64>,
+    issues: Vec<String>,
+    status: Severity,
+}
+
+pub fn run(cli: &crate::Cli, cfg: &config::Config, opts: &StorageOptions) -> Result<Severity> {
+    let node = ctl_node(cfg, opts.node.as_deref())?;
+    let run = Run { tr: transport::from_config(cfg), node, targets: target_args(&opts.targets)?, opts };
+    let phases: &[Phase] = match opts.mode {
+        Mode::Write => &[Phase::Write],
+        Mode::Read => &[Phase::Read],
+        Mode::Both => &[Phase::Write, Phase::Read],
+    };
+
+    let mut rows: Vec<Row> = Vec::new();
+    let mut aggregate = BTreeMap::new();
+    let mut failure = None;
+    for &phase in phases {
+        let status = match run.phase(phase) {
+            Ok(s) => s,
+            Err(e) => { failure = Some(e); break; }
+        };
+        let rates: Vec<u64> = status.targets.iter().map(|t| t.1).collect();
+        let slow = slow_outliers(&rates, opts.slow_percent);
+        aggregate.insert(phase.as_str(), rates.iter().sum::<u64>());
+        for (i, (target, kib, node)) in status.targets.into_iter().enumerate() {
+            let idx = match rows.iter().position(|r| r.target == target) {
+                Some(idx) => idx,
+                None => {
+                    rows.push(Row { target, node, rates: BTreeMap::new(), issues: Vec::new(), status: Severity::Ok });
+                    rows.len() - 1
+                }
+            };
+            let row = &mut rows[idx];
+            row.rates.insert(phase.as_str(), kib);
+            if slow.contains(&i) {
+                row.status = row.status.max(Severity::Warning);
+                row.issues.push(format!("{} below {}% of the median", phase.as_str(), opts.slow_percent));
+            }
+        }
+    }
+    // a target that took part in one phase but has no result for another did not finish it
+    if failure.is_none() {
+        for row in &mut rows {
+            for &phase in phases {
+                if !row.rates.contains_key(phase.as_str()) {
+                    row.status = Severity::Failure;
+                    row.issues.push(format!("no {} result", phase.as_str()));
+                }
+            }
+        }
+    }
+    if !opts.keep_files && opts.mode != Mode::Read {
+        if let Err(e) = run.ctl("--cleanup") { log::warn!("could not remove the benchmark files: {:#}", e); }
+    }
+    if rows.is_empty() {
+        return Err(failure.unwrap_or_else(|| anyhow::anyhow!("beegfs-ctl on {} reported no storage targets", node.name)));
+    }
+    rows.sort_by_key(|r| r.target.parse::<u64>().unwrap_or(u64::MAX));
+
+    let rate = |r: &Row, p: &str| r.rates.get(p).map(|&k| format_rate(k * 1024)).unwrap_or_else(|| "-".into());
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            let mut header = vec!["Target", "Node"];
+            header.extend(phases.iter().map(|p| match p { Phase::Write => "Write", Phase::Read => "Read" }));
+            header.extend(["Status", "Detail"]);
+            table.set_header(header);
+            for r in &rows {
+                let mut cells = vec![Cell::new(&r.target), Cell::new(&r.node)];
+                cells.extend(phases.iter().map(|p| Cell::new(rate(r, p.as_str()))));
+                cells.push(crate::tables::status_cell(r.status.as_str(), r.status));
+                cells.push(Cell::new(r.issues.join("; ")));
+                table.add_row(cells);
+            }
+            crate::tables::print(cli.output, &table);
+            let total: Vec<String> = phases.iter().filter_map(|p| aggregate.get(p.as_str()).map(|k| format!("{} {}", p.as_str(), format_rate(*k * 1024)))).collect();
+            if !total.is_empty() {
+                crate::tables::note(cli.output, &format!("Aggregate: {} ({} targets, {} x {} per target, {} block size)",
+                    total.join(", "), rows.len(), opts.threads, crate::transfer::format_size(opts.size), crate::transfer::format_size(opts.blocksize)));
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "node": node.name,
+                "blocksize": opts.blocksize,
+                "size": opts.size,
+                "threads": opts.threads,
+                "aggregate_kib_s": aggregate,
+                "targets": rows.iter().map(|r| serde_json::json!({
+                    "target": r.target,
+                    "node": r.node,
+                    "write_kib_s": r.rates.get("write"),
+                    "read_kib_s": r.rates.get("read"),
+                    "status": r.status.as_str(),
+                    "issues": r.issues,
+                })).collect::<Vec<_>>(),
+                "error": failure.as_ref().map(|e| format!("{:#}", e)),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    let slow: Vec<&str> = rows.iter().filter(|r| r.status == Severity::Warning).map(|r| r.target.as_str()).collect();
+    if !slow.is_empty() { log::warn!("slow target(s): {}", slow.join(", ")); }
+    if let Some(e) = failure {
+        log::warn!("{:#}", e);
+        return Ok(Severity::Failure);
+    }
+    Ok(rows.iter().map(|r| r.status).max().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DONE: &str = "Server benchmark status:
+Finished:    3
+
+Write benchmark results:
+Min throughput:            402112  KiB/s   nodeID: stor02 [ID: 2], targetID: 201
+Max throughput:            1257894  KiB/s   nodeID: stor01 [ID: 1], targetID: 102
+Avg throughput:            968480  KiB/s
+Aggregate throughput:      2905440  KiB/s
+
+List of all targets:
+101                        1245434  KiB/s   nodeID: stor01 [ID: 1]
+102                        1257894  KiB/s   nodeID: stor01 [ID: 1]
+201                         402112  KiB/s   nodeID: stor02 [ID: 2]
+";
+
+    #[test]
+    fn status() {
+        let s = parse_status(DONE);
+        assert!(!s.busy());
+        assert_eq!(s.count("Finished"), 3);
+        assert_eq!(s.targets, vec![
+            ("101".to_string(), 1245434, "stor01".to_string()),
+            ("102".to_string(), 1257894, "stor01".to_string()),
+            ("201".to_string(), 402112, "stor02".to_string()),
+        ]);
+        let s = parse_status("Server benchmark status:\nRunning:     2\nFinished:    1\n");
+        assert!(s.busy() && s.targets.is_empty());
+    }
+
+    #[test]
+    fn targets() {
+        assert_eq!(target_args("all").unwrap(), "--alltargets");
+        assert_eq!(target_args("101, 102").unwrap(), "--targetids=101,102");
+        assert!(target_args("101;rm").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::CompShell;
+use anyhow::{bail, Context, Result};
+use clap::CommandFactory;
+use std::path::{Path, PathBuf};
+
+/// The shell named by `$SHELL`, e.g. `/usr/bin/zsh`.
+pub(crate) fn detect(shell_env: Option<&str>) -> Option<CompShell> {
+    let name = Path::new(shell_env?).file_name()?.to_str()?;
+    match name {
+        "bash" => Some(CompShell::Bash),
+        "zsh" => Some(CompShell::Zsh),
+        "fish" => Some(CompShell::Fish),
+        "pwsh" | "powershell" => Some(CompShell::PowerShell),
+        "elvish" => Some(CompShell::Elvish),
+        _ => None,
+    }
+}
+
+/// Name the shell looks the script up by.
+pub(crate) fn file_name(sh: CompShell) -> &'static str {
+    match sh {
+        CompShell::Bash => "beeg",
+        CompShell::Zsh => "_beeg",
+        CompShell::Fish => "beeg.fish",
+        CompShell::PowerShell => "beeg.ps1",
+        CompShell::Elvish => "beeg.elv",
+    }
+}
+
+/// Where each shell loads completions from: the packaged-software directory for `system`,
+/// else under the user's data and config dirs. PowerShell and Elvish have no system one.
+pub(crate) fn default_dir(sh: CompShell, system: bool, data: &Path, config: &Path, home: &Path) -> Option<PathBuf> {
+    Some(match (sh, system) {
+        (CompShell::Bash, true) => PathBuf::from("/usr/share/bash-completion/completions"),
+        (CompShell::Zsh, true) => PathBuf::from("/usr/local/share/zsh/site-functions"),
+        (CompShell::Fish, true) => PathBuf::from("/usr/share/fish/vendor_completions.d"),
+        (CompShell::PowerShell | CompShell::Elvish, true) => return None,
+        (CompShell::Bash, false) => data.join("bash-completion/completions"),
+        (CompShell::Zsh, false) => home.join(".zfunc"),
+        (CompShell::Fish, false) => config.join("fish/completions"),
+        (CompShell::PowerShell, false) => config.join("powershell"),
+        (CompShell::Elvish, false) => config.join("elvish/lib"),
+    })
+}
+
+/// What the user still has to do for the shell to pick up `path`, if anything.
+fn activation(sh: CompShell, path: &Path, dir: &Path) -> Option<String> {
+    match sh {
+        // bash-completion and fish load from these directories on their own
+        CompShell::Bash | CompShell::Fish => None,
+        CompShell::Zsh if dir.starts_with("/usr") => Some("restart zsh (or run `compinit`)".into()),
+        CompShell::Zsh => Some(format!("add `fpath=({} $fpath)` before `compinit` in ~/.zshrc", dir.display())),
+        CompShell::PowerShell => Some(format!("add `. {}` to $PROFILE", path.display())),
+        CompShell::Elvish if dir.ends_with("elvish/lib") => Some("add `use beeg` to ~/.config/elvish/rc.elv".into()),
+        CompShell::Elvish => Some(format!("`use` {} from ~/.config/elvish/rc.elv", path.display())),
+    }
+}
+
+fn script(sh: CompShell) -> Vec<u8> {
+    let mut buf = Vec::new();
+    clap_complete::generate(sh.shell(), &mut crate::Cli::command(), "beeg", &mut buf);
+    buf
+}
+
+pub(crate) struct InstallOptions {
+    pub shell: Option<CompShell>,
+    pub system: bool,
+    pub dir: Option<PathBuf>,
+    pub print: bool,
+}
+
+pub fn install(cli: &crate::Cli, opts: &InstallOptions) -> Result<()> {
+    let env = std::env::var("SHELL").ok();
+    let Some(sh) = opts.shell.or_else(|| detect(env.as_deref())) else {
+        bail!("cannot tell the shell from $SHELL ({}); pass --shell", env.as_deref().unwrap_or("unset"));
+    };
+    let name = format!("{:?}", sh).to_lowercase();
+    if opts.print {
+        use std::io::Write;
+        std::io::stdout().write_all(&script(sh))?;
+        return Ok(());
+    }
+
+    let home = dirs::home_dir().context("no home directory")?;
+    let dir = match &opts.dir {
+        Some(d) => d.clone(),
+        None => {
+            let data = dirs::data_dir().unwrap_or_else(|| home.join(".local/share"));
+            let config = dirs::config_dir().unwrap_or_else(|| home.join(".config"));
+            default_dir(sh, opts.system, &data, &config, &home)
+                .with_context(|| format!("{} has no system-wide completion directory; install per user or pass --dir", name))?
+        }
+    };
+    let path = dir.join(file_name(sh));
+    std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&path, script(sh)))
+        .with_context(|| format!("installing {}; `beeg completions install --print` writes the script to stdout instead", path.display()))?;
+    let next = activation(sh, &path, &dir);
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            println!("installed {} completions to {}", name, path.display());
+            if let Some(n) = &next { println!("to enable them: {}", n); }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "shell": name,
+                "scope": if opts.dir.is_some() { "custom" } else if opts.system { "system" } else { "user" },
+                "path": path.display().to_string(),
+                "activation": next,
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shells_and_dirs() {
+        assert!(matches!(detect(Some("/usr/bin/zsh")), Some(CompShell::Zsh)));
+        assert!(matches!(detect(Some("/usr/local/bin/pwsh")), Some(CompShell::PowerShell)));
+        assert!(detect(Some("/bin/tcsh")).is_none());
+        assert!(detect(None).is_none());
+
+        let (data, config, home) = (Path::new("/home/u/.local/share"), Path::new("/home/u/.config"), Path::new("/home/u"));
+        assert_eq!(default_dir(CompShell::Bash, false, data, config, home).unwrap(), Path::new("/home/u/.local/share/bash-completion/completions"));
+        assert_eq!(default_dir(CompShell::Fish, true, data, config, home).unwrap(), Path::new("/usr/share/fish/vendor_completions.d"));
+        assert!(default_dir(CompShell::Elvish, true, data, config, home).is_none());
+
+        let zsh = default_dir(CompShell::Zsh, false, data, config, home).unwrap();
+        assert_eq!(activation(CompShell::Zsh, &zsh.join("_beeg"), &zsh).unwrap(), "add `fpath=(/home/u/.zfunc $fpath)` before `compinit` in ~/.zshrc");
+        assert!(!script(CompShell::Bash).is_empty());
+    }
+}
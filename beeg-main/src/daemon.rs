@@ -0,0 +1,263 @@
+//! `beeg daemon`: run a set of checks on an interval, log each round, keep the check history
+//! and report status changes per node, optionally to webhooks.
+
+use crate::checks::{self, client, Check, CheckCmd, CheckReport, Severity};
+use crate::{config, state};
+use anyhow::{bail, Context, Result};
+use clap::{Command as ClapCommand, FromArgMatches, Subcommand};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Last status per check and node, so a restarted daemon does not report everything again.
+const STATE_FILE: &str = "daemon.json";
+
+type Statuses = BTreeMap<String, BTreeMap<String, String>>;
+
+/// What `beeg daemon` runs and where it reports.
+pub(crate) struct DaemonOptions {
+    /// Check names, as for `beeg check <name>`
+    pub checks: Vec<String>,
+    /// Seconds between the starts of two rounds
+    pub interval: u64,
+    /// Run one round and exit with its exit code
+    pub once: bool,
+    /// Node selector for checks that take one
+    pub selector: String,
+    /// Management node for the checks that query one (meta, capacity, ...)
+    pub mgmt_node: Option<String>,
+    /// Node to run storage-target from
+    pub storage_node: Option<String>,
+    pub targets: String,
+    /// Client mountpoint for client-mount
+    pub mount: Option<String>,
+    pub timeout: u64,
+    /// URLs each status change is POSTed to as JSON
+    pub webhooks: Vec<String>,
+}
+
+/// Arguments for `beeg check <name>` from the daemon's options: only those the check takes.
+/// Checks that query a single node get `--mgmt-node`, or `--storage-node` for storage-target.
+fn check_args(name: &str, opts: &DaemonOptions) -> Result<Vec<String>> {
+    let root = CheckCmd::augment_subcommands(ClapCommand::new("check"));
+    let Some(sub) = root.find_subcommand(name) else {
+        let names: Vec<&str> = checks::REGISTRY.iter().map(|r| r.name).collect();
+        bail!("unknown check '{}'; available: {}", name, names.join(", "));
+    };
+    if sub.has_subcommands() { bail!("check '{}' has subcommands and cannot run from the daemon", name); }
+    let has = |id: &str| sub.get_arguments().find(|a| a.get_id() == id);
+    let mut args = vec![name.to_string()];
+    if let Some(sel) = has("selector") {
+        if sel.is_required_set() {
+            let (node, flag) = if name == checks::StorageTarget::NAME { (&opts.storage_node, "--storage-node") } else { (&opts.mgmt_node, "--mgmt-node") };
+            let Some(node) = node else { bail!("check '{}' runs from one node; give it with {}", name, flag) };
+            args.extend(["--selector".to_string(), node.clone()]);
+        } else {
+            args.extend(["--selector".to_string(), opts.selector.clone()]);
+        }
+    }
+    if has("mount").is_some() {
+        let Some(mount) = &opts.mount else { bail!("check '{}' needs --mount", name) };
+        args.extend(["--mount".to_string(), mount.clone()]);
+    }
+    if has("targets").is_some() { args.extend(["--targets".to_string(), opts.targets.clone()]); }
+    if has("timeout").is_some() { args.extend(["--timeout".to_string(), opts.timeout.to_string()]); }
+    Ok(args)
+}
+
+fn parse_check(name: &str, opts: &DaemonOptions) -> Result<CheckCmd> {
+    let root = CheckCmd::augment_subcommands(ClapCommand::new("check").no_binary_name(true));
+    let matches = root.try_get_matches_from(check_args(name, opts)?).with_context(|| format!("check '{}'", name))?;
+    let cmd = CheckCmd::from_arg_matches(&matches)?;
+    if !cmd.registration().is_some_and(|r| r.watchable) {
+        bail!("check '{}' changes cluster state and cannot run repeatedly", name);
+    }
+    Ok(cmd)
+}
+
+fn run_check(cli: &crate::Cli, cfg: &config::Config, cmd: &CheckCmd, opts: &DaemonOptions) -> Result<CheckReport> {
+    // the client-mount check draws a TUI; the daemon wants its report only
+    if cmd.name == client::ClientMount::NAME {
+        let mount = opts.mount.as_deref().unwrap_or_default();
+        return Ok(client::mount_report(&client::collect_mount(cfg, &opts.selector, mount, opts.timeout)));
+    }
+    cmd.run(cli, cfg)
+}
+
+/// A node whose status for a check changed between two rounds.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub(crate) struct Transition {
+    pub check: String,
+    pub node: String,
+    /// `None` the first time a node is seen
+    pub from: Option<String>,
+    pub to: String,
+    pub detail: String,
+}
+
+/// Status changes in `report` against `prev`, which is updated to the new statuses. A node seen
+/// for the first time only counts when it is not OK, so a fresh start reports what is broken.
+pub(crate) fn transitions(prev: &mut Statuses, report: &CheckReport) -> Vec<Transition> {
+    let mut out = Vec::new();
+    for r in report.leaf_results() {
+        let to = r.status.as_str().to_string();
+        let from = prev.entry(r.check.to_string()).or_default().insert(r.node.clone(), to.clone());
+        let changed = match &from { Some(f) => *f != to, None => r.status != Severity::Ok };
+        if changed {
+            out.push(Transition { check: r.check.to_string(), node: r.node.clone(), from, to, detail: r.detail.clone() });
+        }
+    }
+    out
+}
+
+/// POST `t` to every webhook; a failed delivery is only logged.
+fn notify(webhooks: &[String], t: &Transition, time: &str) {
+    let mut body = serde_json::to_value(t).unwrap_or_default();
+    body["time"] = time.into();
+    body["source"] = "beeg".into();
+    for url in webhooks {
+        if let Err(e) = crate::alerts::post(url, &body) {
+            log::warn!("webhook {}: {:#}", crate::alerts::url_host(url), e);
+        }
+    }
+}
+
+fn log_round(cli: &crate::Cli, name: &str, result: &Result<CheckReport>, changes: &[Transition]) -> Result<()> {
+    let now = chrono::Utc::now();
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let time = crate::timefmt::human(now, cli.utc);
+            match result {
+                Ok(r) => {
+                    let ok = r.leaf_results().iter().filter(|x| x.status == Severity::Ok).count();
+                    let line = format!("{} {}: {} ({}/{} OK, {:.1}s)", time, name, r.severity().as_str(), ok, r.leaf_results().len(), r.duration.as_secs_f64());
+                    println!("{}", crate::tables::paint(&line, r.severity()));
+                }
+                Err(e) => println!("{} {}: ERROR {:#}", time, name, e),
+            }
+            for t in changes {
+                println!("{} {} on {}: {} -> {}{}", time, t.check, t.node, t.from.as_deref().unwrap_or("new"), t.to,
+                    if t.detail.is_empty() { String::new() } else { format!(" ({})", t.detail) });
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let time = crate::timefmt::rfc3339(now);
+            let mut line = serde_json::json!({ "time": time, "event": "check", "check": name });
+            match result {
+                Ok(r) => {
+                    line["status"] = r.severity().as_str().into();
+                    line["duration_ms"] = (r.duration.as_millis() as u64).into();
+                    line["results"] = r.leaf_results().iter().map(|x| x.to_json()).collect::<Vec<_>>().into();
+                }
+                Err(e) => line["error"] = format!("{:#}", e).into(),
+            }
+            crate::formats::print_record(cli.output, &line)?;
+            for t in changes {
+                let mut v = serde_json::to_value(t)?;
+                v["time"] = time.clone().into();
+                v["event"] = "transition".into();
+                crate::formats::print_record(cli.output, &v)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One run of every check: logged, added to the history and compared with the last statuses.
+fn round(cli: &crate::Cli, cfg: &config::Config, cmds: &[CheckCmd], opts: &DaemonOptions, prev: &mut Statuses) -> Result<Severity> {
+    let mut worst = Severity::Ok;
+    let mut reports = Vec::new();
+    for cmd in cmds {
+        let started = chrono::Utc::now();
+        let clock = Instant::now();
+        let result = run_check(cli, cfg, cmd, opts).map(|mut r| {
+            r.duration = clock.elapsed();
+            r.sort_nodes(cfg, cli.sort);
+            r
+        });
+        let changes = match &result {
+            Ok(r) => {
+                crate::history::record(r, started);
+                worst = worst.max(r.severity());
+                transitions(prev, r)
+            }
+            Err(e) => {
+                worst = worst.max(checks::error_severity(e));
+                Vec::new()
+            }
+        };
+        log_round(cli, &cmd.name, &result, &changes)?;
+        let time = crate::timefmt::rfc3339(chrono::Utc::now());
+        for t in &changes { notify(&opts.webhooks, t, &time); }
+        if let Ok(r) = result { reports.push(r); }
+    }
+    // one alert digest for the whole round
+    crate::alerts::process_all(cfg, &reports.iter().collect::<Vec<_>>());
+    if let Err(e) = state::write_json(STATE_FILE, prev) { log::warn!("saving daemon state: {:#}", e); }
+    Ok(worst)
+}
+
+/// Run the checks every `interval` seconds until killed; with `--once` a single round.
+pub fn run(cli: &crate::Cli, cfg: &config::Config, opts: &DaemonOptions) -> Result<Severity> {
+    if opts.checks.is_empty() { bail!("no checks given; e.g. --checks ofed,client-mount"); }
+    let cmds = opts.checks.iter().map(|name| parse_check(name, opts)).collect::<Result<Vec<_>>>()?;
+    let mut prev: Statuses = state::read_json(STATE_FILE).unwrap_or_default();
+    if opts.once { return round(cli, cfg, &cmds, opts, &mut prev); }
+    eprintln!("running {} every {}s", opts.checks.join(", "), opts.interval);
+    loop {
+        let started = Instant::now();
+        round(cli, cfg, &cmds, opts, &mut prev)?;
+        thread::sleep(Duration::from_secs(opts.interval).saturating_sub(started.elapsed()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::CheckResult;
+    use crate::parse_interval;
+
+    fn opts() -> DaemonOptions {
+        DaemonOptions {
+            checks: Vec::new(), interval: 60, once: true, selector: "all".into(), mgmt_node: None,
+            storage_node: Some("stor01".into()), targets: "all".into(), mount: None, timeout: 10, webhooks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn arguments_per_check() {
+        assert_eq!(parse_interval("5m"), Ok(300));
+        assert_eq!(parse_interval("90"), Ok(90));
+        assert!(parse_interval("0s").is_err() && parse_interval("soon").is_err());
+
+        let o = opts();
+        assert_eq!(check_args("ofed", &o).unwrap(), vec!["ofed", "--selector", "all"]);
+        assert_eq!(check_args("storage-target", &o).unwrap(), vec!["storage-target", "--selector", "stor01", "--targets", "all", "--timeout", "10"]);
+        assert!(check_args("capacity", &o).is_err());
+        assert!(check_args("client-mount", &o).is_err());
+        assert!(check_args("nope", &o).is_err());
+        assert!(parse_check("ofed", &o).is_ok());
+        assert!(parse_check("meta-mirror", &DaemonOptions { mount: Some("/mnt/beegfs".into()), ..opts() }).is_err());
+    }
+
+    #[test]
+    fn status_changes() {
+        let result = |node: &str, status: Severity| CheckResult {
+            check: "ofed", node: node.into(), status, detail: String::new(),
+            duration: Duration::ZERO, cells: Vec::new(), data: serde_json::json!({}),
+        };
+        let mut prev = Statuses::new();
+        let mut report = CheckReport::new("ofed", &[]);
+        report.results = vec![result("n1", Severity::Ok), result("n2", Severity::Failure)];
+        let t = transitions(&mut prev, &report);
+        assert_eq!(t.len(), 1);
+        assert_eq!((t[0].node.as_str(), t[0].from.as_deref(), t[0].to.as_str()), ("n2", None, "FAIL"));
+
+        report.results = vec![result("n1", Severity::Failure), result("n2", Severity::Failure)];
+        let t = transitions(&mut prev, &report);
+        assert_eq!((t.len(), t[0].node.as_str(), t[0].from.as_deref()), (1, "n1", Some("OK")));
+
+        report.results = vec![result("n1", Severity::Ok), result("n2", Severity::Failure)];
+        assert_eq!(transitions(&mut prev, &report)[0].to, "OK");
+    }
+}
@@ -0,0 +1,412 @@
+use crate::checks::all::AllArgs;
+use crate::checks::{CheckReport, Severity};
+use crate::config;
+use crate::status::{self, Cluster, SERVICES};
+use chrono::{DateTime, Utc};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{prelude::*, widgets::*};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub selector: String,
+    pub mgmt_node: Option<String>,
+    /// Seconds between refreshes
+    pub interval: u64,
+    pub timeout: u64,
+}
+
+const TABS: [&str; 4] = ["Nodes", "Services", "Targets", "Capacity"];
+
+/// Sent to the refresher thread.
+enum Request {
+    /// Refresh now instead of waiting for the interval
+    Refresh,
+    /// Run the node checks on this node with every refresh, or stop
+    Drill(Option<String>),
+    Quit,
+}
+
+/// Sent back by the refresher thread.
+enum Update {
+    Cluster(Result<Cluster, String>, DateTime<Utc>),
+    Checks(String, CheckReport, DateTime<Utc>),
+}
+
+struct App {
+    tab: usize,
+    /// Highlighted row of the current tab
+    row: usize,
+    /// Node drilled into
+    drill: Option<String>,
+    cluster: Option<Cluster>,
+    error: Option<String>,
+    refreshed: Option<DateTime<Utc>>,
+    /// Check results of the drilled node and when they were taken
+    checks: Option<(String, CheckReport, DateTime<Utc>)>,
+}
+
+/// `check all` on one node: the version checks, plus storage-target on storage nodes.
+fn node_checks(cfg: &config::Config, node: &str, timeout: u64) -> CheckReport {
+    let storage = cfg.nodes.iter().find(|n| n.name == node).is_some_and(|n| n.role() == "storage");
+    crate::checks::all::run(cfg, &AllArgs {
+        selector: node.to_string(),
+        mount: None,
+        storage_node: storage.then(|| node.to_string()),
+        targets: "all".into(),
+        require: Vec::new(),
+        timeout,
+    })
+}
+
+/// Gather every `interval` (or on request) until told to quit or the UI goes away.
+fn refresher(cfg: config::Config, opts: Options, rx: Receiver<Request>, tx: Sender<Update>) {
+    let mut drill: Option<String> = None;
+    let run_checks = |node: &str| Update::Checks(node.to_string(), node_checks(&cfg, node, opts.timeout), Utc::now());
+    loop {
+        let nodes = config::select_nodes(&cfg, &opts.selector);
+        let cluster = status::gather(&cfg, &nodes, opts.mgmt_node.as_deref(), opts.timeout).map_err(|e| format!("{:#}", e));
+        if tx.send(Update::Cluster(cluster, Utc::now())).is_err() { return; }
+        if let Some(node) = &drill {
+            if tx.send(run_checks(node)).is_err() { return; }
+        }
+        let next = Instant::now() + Duration::from_secs(opts.interval);
+        loop {
+            match rx.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                Ok(Request::Refresh) | Err(RecvTimeoutError::Timeout) => break,
+                Ok(Request::Drill(node)) => {
+                    drill = node;
+                    if let Some(node) = &drill {
+                        if tx.send(run_checks(node)).is_err() { return; }
+                    }
+                }
+                Ok(Request::Quit) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+fn severity_style(s: Severity) -> Style {
+    match s {
+        Severity::Ok => Style::default().fg(Color::Green),
+        Severity::Warning => Style::default().fg(Color::Yellow),
+        Severity::NoPerm => Style::default().fg(Color::Magenta),
+        Severity::Failure => Style::default().fg(Color::Red),
+    }
+}
+
+fn service_style(state: &str) -> Style {
+    match state {
+        "active" => Style::default().fg(Color::Green),
+        "failed" => Style::default().fg(Color::Red),
+        "-" => Style::default(),
+        _ => Style::default().fg(Color::Yellow),
+    }
+}
+
+fn pct(free: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { free as f64 * 100.0 / total as f64 }
+}
+
+/// `████░░░░░░` for a used share, ten cells wide.
+fn bar(used_pct: f64) -> String {
+    let full = ((used_pct / 10.0).round() as usize).min(10);
+    format!("{}{}", "█".repeat(full), "░".repeat(10 - full))
+}
+
+/// Cells of a table row and the config node it drills into.
+type TabRow = (Vec<String>, Option<String>);
+
+/// Header and rows of `tab`.
+pub(crate) fn tab_rows(cfg: &config::Config, cluster: &Cluster, tab: usize) -> (Vec<&'static str>, Vec<TabRow>) {
+    let spaces = cluster.targets.as_ref().map(|t| t.spaces.as_slice()).unwrap_or_default();
+    let owner = |target_node: &str| cfg.nodes.iter().find(|n| status::on_node(target_node, n)).map(|n| n.name.clone());
+    match tab {
+        0 => (vec!["Node", "Host", "Role", "Reachable", "RTT (ms)", "Targets", "Free"], cluster.nodes.iter().map(|n| {
+            let role = cfg.nodes.iter().find(|c| c.name == n.name).map(|c| c.role().to_string()).unwrap_or_default();
+            let mine: Vec<_> = spaces.iter().filter(|sp| owner(&sp.node).as_deref() == Some(n.name.as_str())).collect();
+            let (f, t) = mine.iter().fold((0u64, 0u64), |(f, t), sp| (f + sp.free_bytes, t + sp.total_bytes));
+            let (reach, rtt) = match &n.probe {
+                Ok((_, rtt)) => ("yes".to_string(), format!("{:.0}", rtt.as_secs_f64() * 1000.0)),
+                Err(_) => ("NO".to_string(), "-".to_string()),
+            };
+            let free = if mine.is_empty() { "-".into() } else { format!("{} ({:.0}%)", crate::transfer::format_size(f), pct(f, t)) };
+            let targets = if mine.is_empty() { "-".into() } else { mine.len().to_string() };
+            (vec![n.name.clone(), n.host.clone(), role, reach, rtt, targets, free], Some(n.name.clone()))
+        }).collect()),
+        1 => {
+            let mut header = vec!["Node"];
+            header.extend(SERVICES);
+            (header, cluster.nodes.iter().map(|n| {
+                let mut row = vec![n.name.clone()];
+                match &n.probe {
+                    Ok((svc, _)) => row.extend(SERVICES.iter().map(|s| svc.get(*s).cloned().unwrap_or_else(|| "-".into()))),
+                    Err(_) => row.extend(SERVICES.iter().map(|_| "unreachable".to_string())),
+                }
+                (row, Some(n.name.clone()))
+            }).collect())
+        }
+        2 => (vec!["Target", "Node", "Reachability", "Consistency", "Pool"], spaces.iter().map(|sp| {
+            let st = cluster.targets.as_ref().ok().and_then(|t| t.states.get(&sp.id));
+            (vec![
+                sp.id.clone(),
+                status::node_id_host(&sp.node).to_string(),
+                st.map(|s| s.reachability.clone()).unwrap_or_else(|| "unknown".into()),
+                st.map(|s| s.consistency.clone()).unwrap_or_else(|| "unknown".into()),
+                sp.pool.clone().unwrap_or_else(|| "-".into()),
+            ], owner(&sp.node))
+        }).collect()),
+        _ => (vec!["Target", "Node", "Pool", "Used", "Free", "Total", "Inodes free"], spaces.iter().map(|sp| {
+            (vec![
+                sp.id.clone(),
+                status::node_id_host(&sp.node).to_string(),
+                sp.pool.clone().unwrap_or_else(|| "-".into()),
+                format!("{} {:.0}%", bar(100.0 - sp.free_pct()), 100.0 - sp.free_pct()),
+                crate::transfer::format_size(sp.free_bytes),
+                crate::transfer::format_size(sp.total_bytes),
+                format!("{:.0}%", pct(sp.free_inodes, sp.total_inodes)),
+            ], owner(&sp.node))
+        }).collect()),
+    }
+}
+
+/// Worst problems first, from the same rules as `beeg status`.
+fn issues(cluster: &Cluster) -> Vec<(Severity, String)> {
+    let mut out: Vec<(Severity, String)> = cluster.nodes.iter().flat_map(status::node_issues).collect();
+    if let Ok(t) = &cluster.targets {
+        let ids: Vec<&str> = t.spaces.iter().map(|sp| sp.id.as_str()).collect();
+        out.extend(status::target_issues(t, &ids));
+    }
+    out.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    out
+}
+
+fn cell_style(tab: usize, col: usize, text: &str) -> Style {
+    match (tab, col) {
+        (0, 3) if text == "NO" => Style::default().fg(Color::Red),
+        (1, c) if c > 0 => service_style(text),
+        (2, 2) if text != "Online" => Style::default().fg(Color::Red),
+        (2, 3) if text != "Good" => Style::default().fg(Color::Yellow),
+        (2, 4) | (3, 2) if text == "emergency" => Style::default().fg(Color::Red),
+        (2, 4) | (3, 2) if text == "low" => Style::default().fg(Color::Yellow),
+        _ => Style::default(),
+    }
+}
+
+fn draw_drill(f: &mut Frame, area: Rect, app: &App, cfg: &config::Config, node: &str, utc: bool) {
+    let parts = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(3)]).split(area);
+    let conf = cfg.nodes.iter().find(|n| n.name == node);
+    let mut lines = vec![Line::from(format!("Host: {}    Role: {}    Labels: {}",
+        conf.map(|n| n.host.as_str()).unwrap_or("-"),
+        conf.map(|n| n.role()).unwrap_or("-"),
+        conf.map(|n| n.labels.join(", ")).filter(|l| !l.is_empty()).unwrap_or_else(|| "-".into())))];
+    match app.cluster.as_ref().and_then(|c| c.nodes.iter().find(|n| n.name == node)).map(|n| &n.probe) {
+        Some(Ok((svc, rtt))) => {
+            let mut spans = vec![Span::raw(format!("Reachable, {:.0} ms    Services: ", rtt.as_secs_f64() * 1000.0))];
+            for (name, st) in svc {
+                spans.push(Span::styled(format!("{} {}  ", name, st), service_style(st)));
+            }
+            lines.push(Line::from(spans));
+        }
+        Some(Err(e)) => lines.push(Line::styled(format!("Unreachable: {}", e), Style::default().fg(Color::Red))),
+        None => lines.push(Line::from("Not among the selected nodes")),
+    }
+    if let Some(Ok(t)) = app.cluster.as_ref().map(|c| &c.targets) {
+        let mine: Vec<String> = t.spaces.iter().filter(|sp| conf.is_some_and(|n| status::on_node(&sp.node, n)))
+            .map(|sp| format!("{} {} {:.0}% free", sp.id, t.states.get(&sp.id).map(|s| s.reachability.as_str()).unwrap_or("unknown"), sp.free_pct()))
+            .collect();
+        if !mine.is_empty() { lines.push(Line::from(format!("Targets: {}", mine.join(", ")))); }
+    }
+    f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!("Node {}", node))), parts[0]);
+
+    let (title, rows): (String, Vec<Row>) = match &app.checks {
+        Some((n, report, at)) if n == node => {
+            let rows = report.sections.iter().flat_map(|s| s.results.iter().map(move |r| (s.check, r)))
+                .chain(report.results.iter().filter(|r| r.cells.first().map(String::as_str) != Some(r.check)).map(|r| (r.check, r)))
+                .map(|(check, r)| {
+                    let detail = if r.detail.is_empty() { r.cells.join("  ") } else { r.detail.clone() };
+                    Row::new(vec![
+                        ratatui::widgets::Cell::from(check),
+                        ratatui::widgets::Cell::from(r.status.as_str()).style(severity_style(r.status)),
+                        ratatui::widgets::Cell::from(detail),
+                    ])
+                }).collect();
+            (format!("Checks as of {}", crate::timefmt::human(*at, utc)), rows)
+        }
+        _ => ("Checks (running...)".to_string(), Vec::new()),
+    };
+    let table = Table::new(rows, [Constraint::Length(16), Constraint::Length(6), Constraint::Min(20)])
+        .header(Row::new(vec!["Check", "Status", "Detail"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(table, parts[1]);
+}
+
+fn draw(f: &mut Frame, app: &App, cfg: &config::Config, opts: &Options, utc: bool, state: &mut TableState) {
+    let chunks = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+        .split(f.size());
+
+    let refreshed = app.refreshed.map(|t| crate::timefmt::human(t, utc)).unwrap_or_else(|| "loading...".into());
+    let title = format!("beeg dashboard — {} — refreshed {} (every {}s)", opts.selector, refreshed, opts.interval);
+    let tabs = Tabs::new(TABS.to_vec())
+        .select(app.tab)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(tabs, chunks[0]);
+
+    match (&app.drill, &app.cluster) {
+        (Some(node), _) => draw_drill(f, chunks[1], app, cfg, node, utc),
+        (None, Some(cluster)) => {
+            let (header, rows) = tab_rows(cfg, cluster, app.tab);
+            let widths: Vec<Constraint> = header.iter().map(|_| Constraint::Ratio(1, header.len() as u32)).collect();
+            let body = rows.into_iter().map(|(cells, _)| {
+                Row::new(cells.into_iter().enumerate().map(|(i, c)| {
+                    let style = cell_style(app.tab, i, &c);
+                    ratatui::widgets::Cell::from(c).style(style)
+                }).collect::<Vec<_>>())
+            });
+            let mut block_title = TABS[app.tab].to_string();
+            if app.tab >= 2 {
+                match &cluster.targets {
+                    Ok(t) => block_title += &format!(" via {}", t.mgmt),
+                    Err(e) => block_title += &format!(": {}", e),
+                }
+            }
+            let table = Table::new(body, widths)
+                .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .block(Block::default().borders(Borders::ALL).title(block_title));
+            state.select(Some(app.row));
+            f.render_stateful_widget(table, chunks[1], state);
+        }
+        (None, None) => {
+            let text = app.error.clone().unwrap_or_else(|| "Gathering cluster state...".into());
+            f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL)), chunks[1]);
+        }
+    }
+
+    let status_line = match (&app.error, &app.cluster) {
+        (Some(e), _) => Line::styled(format!("Refresh failed: {}", e), Style::default().fg(Color::Red)),
+        (None, Some(c)) => {
+            let found = issues(c);
+            match found.first() {
+                None => Line::styled("No issues", severity_style(Severity::Ok)),
+                Some((sev, msg)) => Line::styled(format!("{} issue(s): {}", found.len(), msg), severity_style(*sev)),
+            }
+        }
+        (None, None) => Line::from(""),
+    };
+    f.render_widget(Paragraph::new(status_line), chunks[2]);
+    let keys = if app.drill.is_some() { "esc back · r refresh · q quit" } else { "tab/1-4 switch · ↑↓ select · enter drill into node · r refresh · q quit" };
+    f.render_widget(Paragraph::new(keys).style(Style::default().add_modifier(Modifier::DIM)), chunks[3]);
+}
+
+/// Full-screen cluster view: tabs for nodes, services, targets and capacity, refreshed every
+/// `interval` seconds, with a per-node view that reruns the node checks.
+pub fn run(cli: &crate::Cli, cfg: &config::Config, opts: Options) -> anyhow::Result<()> {
+    if config::select_nodes(cfg, &opts.selector).is_empty() { anyhow::bail!("selector '{}' matches no nodes", opts.selector); }
+    let (req_tx, req_rx) = mpsc::channel::<Request>();
+    let (upd_tx, upd_rx) = mpsc::channel::<Update>();
+    let (mut worker_cfg, worker_opts) = (cfg.clone(), opts.clone());
+    // cancelled on quit, so a refresh in flight returns at once and the refresher can be joined
+    let cancel = crate::transport::cancel::of(cfg).child();
+    worker_cfg.cancel = Some(cancel.clone());
+    let worker = std::thread::spawn(move || refresher(worker_cfg, worker_opts, req_rx, upd_tx));
+
+    let mut terminal = crate::tui::enter(cli, "beeg dashboard")?;
+    let mut app = App { tab: 0, row: 0, drill: None, cluster: None, error: None, refreshed: None, checks: None };
+    let mut state = TableState::default();
+    let result = (|| -> anyhow::Result<()> {
+        loop {
+            while let Ok(upd) = upd_rx.try_recv() {
+                match upd {
+                    Update::Cluster(Ok(mut c), at) => {
+                        config::sort_rows(cfg, cli.sort, &mut c.nodes, |n| &n.name);
+                        app.cluster = Some(c);
+                        app.error = None;
+                        app.refreshed = Some(at);
+                    }
+                    Update::Cluster(Err(e), _) => app.error = Some(e),
+                    Update::Checks(node, report, at) => app.checks = Some((node, report, at)),
+                }
+            }
+            let rows = app.cluster.as_ref().map(|c| tab_rows(cfg, c, app.tab).1).unwrap_or_default();
+            app.row = app.row.min(rows.len().saturating_sub(1));
+            terminal.draw(|f| draw(f, &app, cfg, &opts, cli.utc, &mut state))?;
+
+            if !event::poll(Duration::from_millis(200))? { continue; }
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press { continue; }
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Esc if app.drill.is_none() => return Ok(()),
+                KeyCode::Esc | KeyCode::Backspace => {
+                    app.drill = None;
+                    let _ = req_tx.send(Request::Drill(None));
+                }
+                KeyCode::Char('r') => { let _ = req_tx.send(Request::Refresh); }
+                _ if app.drill.is_some() => {}
+                KeyCode::Tab | KeyCode::Right => { app.tab = (app.tab + 1) % TABS.len(); app.row = 0; }
+                KeyCode::BackTab | KeyCode::Left => { app.tab = (app.tab + TABS.len() - 1) % TABS.len(); app.row = 0; }
+                KeyCode::Char(c @ '1'..='4') => { app.tab = c as usize - '1' as usize; app.row = 0; }
+                KeyCode::Down | KeyCode::Char('j') => app.row = (app.row + 1).min(rows.len().saturating_sub(1)),
+                KeyCode::Up | KeyCode::Char('k') => app.row = app.row.saturating_sub(1),
+                KeyCode::Enter => {
+                    if let Some(node) = rows.get(app.row).and_then(|(_, n)| n.clone()) {
+                        app.drill = Some(node.clone());
+                        let _ = req_tx.send(Request::Drill(Some(node)));
+                    }
+                }
+                _ => {}
+            }
+        }
+    })();
+    let _ = req_tx.send(Request::Quit);
+    cancel.cancel();
+    let left = crate::tui::leave(&mut terminal);
+    let _ = worker.join();
+    left?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beegfs::{TargetSpace, TargetState};
+    use crate::status::{NodeStatus, Targets};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn rows_per_tab() {
+        let node = |name: &str, labels: &[&str]| config::Node { name: name.into(), host: format!("{}.cluster", name), labels: labels.iter().map(|l| l.to_string()).collect(), ..Default::default() };
+        let cfg = config::Config { nodes: vec![node("stor01", &["role=storage"]), node("cli01", &[])], ..config::load(Some(&std::path::PathBuf::from("/nonexistent"))).unwrap() };
+        let services = BTreeMap::from([("storage".to_string(), "active".to_string())]);
+        let cluster = Cluster {
+            nodes: vec![
+                NodeStatus { name: "stor01".into(), host: "stor01.cluster".into(), probe: Ok((services, Duration::from_millis(3))) },
+                NodeStatus { name: "cli01".into(), host: "cli01.cluster".into(), probe: Err("timed out".into()) },
+            ],
+            mgmt: Some("stor01".into()),
+            targets: Ok(Targets {
+                mgmt: "stor01".into(),
+                spaces: vec![TargetSpace { id: "101".into(), node: "beegfs-storage stor01 [ID: 1]".into(), pool: Some("low".into()), total_bytes: 100, free_bytes: 25, total_inodes: 10, free_inodes: 5 }],
+                states: BTreeMap::from([("101".to_string(), TargetState { reachability: "Online".into(), consistency: "Good".into(), node: "stor01".into() })]),
+            }),
+            targets_denied: false,
+        };
+
+        let (_, nodes) = tab_rows(&cfg, &cluster, 0);
+        assert_eq!(nodes[0].0[5..], ["1".to_string(), "25B (25%)".to_string()]);
+        assert_eq!(nodes[1].0[3], "NO");
+        let (header, services) = tab_rows(&cfg, &cluster, 1);
+        assert_eq!(header, vec!["Node", "mgmtd", "meta", "storage", "client"]);
+        assert_eq!(services[0].0, vec!["stor01", "-", "-", "active", "-"]);
+        let (_, capacity) = tab_rows(&cfg, &cluster, 3);
+        assert_eq!(capacity[0].0[3], "████████░░ 75%");
+        // a target row drills into the node that serves it
+        assert_eq!(capacity[0].1.as_deref(), Some("stor01"));
+        assert_eq!(issues(&cluster)[0].0, Severity::Failure);
+    }
+}
@@ -0,0 +1,311 @@
+//! `beeg node drain` and `beeg node undrain`: take storage nodes out of service for
+//! maintenance and bring them back. What drain changed is recorded in the state dir, so
+//! undrain puts back exactly that, even after a drain that stopped halfway.
+
+use crate::beegfs::{Ctl, NodeType};
+use crate::checks::{error_severity, wrap_timeout, Severity, NOPERM_HINT};
+use crate::service::{self, Action, Service};
+use crate::{config, identity, prompt, state, transport};
+use anyhow::{bail, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MAINTENANCE_FILE: &str = "maintenance.json";
+
+/// A target drain set offline, with the consistency it had before.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DrainedTarget {
+    pub id: String,
+    pub consistency: String,
+}
+
+/// What drain did to one node, by config node name in `maintenance.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Drained {
+    pub host: String,
+    /// Unix time the drain started
+    pub since: i64,
+    /// Node the target states were set through
+    pub mgmt_node: String,
+    pub targets: Vec<DrainedTarget>,
+    /// Whether beegfs-storage was stopped, or was about to be
+    pub stopped: bool,
+}
+
+type Maintenance = BTreeMap<String, Drained>;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub yes: bool,
+    pub mgmt_node: Option<String>,
+    /// Seconds the client sessions have to stay quiet before the service is stopped
+    pub settle: u64,
+    /// Seconds to wait for them at most
+    pub settle_timeout: u64,
+    /// Seconds for each command on a node
+    pub timeout: u64,
+    /// Seconds the service gets to stop or start
+    pub verify_timeout: u64,
+}
+
+/// Seconds between session counts.
+const SESSION_POLL: Duration = Duration::from_secs(2);
+
+/// Client connections on the storage port with data queued in either direction. The port is
+/// `connStoragePortTCP` from beegfs-storage.conf, 8003 when it is not set.
+const SESSIONS: &str = r#"P=$(awk -F= '$1 ~ /^[[:space:]]*connStoragePortTCP[[:space:]]*$/ { gsub(/[[:space:]]/, "", $2); print $2 }' /etc/beegfs/beegfs-storage.conf 2>/dev/null); ss -Htn state established "( sport = :${P:-8003} )" | awk '$1 > 0 || $2 > 0' | wc -l"#;
+
+/// The count `SESSIONS` printed.
+pub(crate) fn parse_sessions(text: &str) -> Option<u32> {
+    text.trim().lines().last()?.trim().parse().ok()
+}
+
+/// Poll the busy sessions until there have been none for `settle` seconds; the last count when
+/// they do not settle within `timeout` seconds.
+fn wait_sessions(tr: &dyn transport::Transport, host: &str, opts: &Options) -> Result<u32, String> {
+    let deadline = Instant::now() + Duration::from_secs(opts.settle_timeout);
+    let mut quiet_since: Option<Instant> = None;
+    loop {
+        let busy = match tr.exec_idempotent(host, &wrap_timeout(SESSIONS, opts.timeout)) {
+            Ok(out) => parse_sessions(&out.stdout).ok_or_else(|| format!("counting sessions failed: {}", out.stderr.trim().lines().last().unwrap_or("no count")))?,
+            Err(e) => return Err(format!("counting sessions failed: {:#}", e)),
+        };
+        if busy > 0 {
+            quiet_since = None;
+        } else if quiet_since.get_or_insert_with(Instant::now).elapsed() >= Duration::from_secs(opts.settle) {
+            return Ok(0);
+        }
+        if Instant::now() >= deadline {
+            return Err(format!("{} client session(s) still busy after {}s", busy, opts.settle_timeout));
+        }
+        thread::sleep(SESSION_POLL);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NodeResult {
+    node: String,
+    host: String,
+    /// done, failed or skipped
+    result: &'static str,
+    targets: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sessions: Option<u32>,
+    /// The unit's state once drain or undrain is through with it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip)]
+    severity: Severity,
+}
+
+impl NodeResult {
+    fn new(n: &config::Node, targets: &[DrainedTarget]) -> Self {
+        NodeResult { node: n.name.clone(), host: n.host.clone(), result: "skipped", targets: targets.iter().map(|t| t.id.clone()).collect(), sessions: None, service: None, error: None, severity: Severity::Warning }
+    }
+
+    fn fail(&mut self, e: &anyhow::Error) {
+        self.result = "failed";
+        self.error = Some(format!("{:#}", e));
+        self.severity = error_severity(e);
+    }
+}
+
+fn storage(action: Action, opts: &Options) -> service::Options {
+    service::Options { action, service: Service::Storage, serial: 1, yes: true, timeout: opts.timeout, verify_timeout: opts.verify_timeout }
+}
+
+/// Stop or start beegfs-storage through `beeg service`, which verifies the unit got there.
+fn storage_service(cfg: &config::Config, n: &config::Node, action: Action, opts: &Options) -> Result<String> {
+    let o = service::act(cfg, n, 0, &storage(action, opts));
+    match o.error {
+        Some(e) => bail!("systemctl {} beegfs-storage: {}", action.as_str(), e),
+        None => Ok(o.state.map(|s| s.describe()).unwrap_or_else(|| "-".into())),
+    }
+}
+
+fn save(record: &Maintenance) -> Result<()> {
+    state::write_json(MAINTENANCE_FILE, record).map_err(|e| e.context("recording the maintenance state, which undrain needs"))
+}
+
+fn ask(nodes: &[&config::Node], yes: bool, what: &str) -> Result<bool> {
+    if yes { return Ok(true); }
+    if !std::io::stdin().is_terminal() { bail!("refusing to {} without confirmation; pass --yes to run non-interactively", what); }
+    let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+    if !prompt::confirm(&format!("{} on {} node(s), one at a time ({})?", what, nodes.len(), names.join(", ")))? {
+        eprintln!("Aborted; no nodes were touched.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Set every storage target of the nodes offline, wait for client I/O to them to stop, then
+/// stop beegfs-storage; one node at a time, stopping at the first that fails.
+pub fn drain(cli: &crate::Cli, cfg: &config::Config, selector: &str, opts: &Options) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    let mut record: Maintenance = state::read_json(MAINTENANCE_FILE).unwrap_or_default();
+    if let Some(n) = nodes.iter().find(|n| record.contains_key(&n.name)) {
+        bail!("{} is already drained; run `beeg node undrain {}` first", n.name, n.name);
+    }
+    let mgmt = match &opts.mgmt_node {
+        Some(sel) => crate::checks::single_node(cfg, sel)?,
+        None => match cfg.nodes.iter().find(|n| n.role() == "mgmtd") {
+            Some(n) => n,
+            None => bail!("drain needs --mgmt-node or a node with the mgmtd role"),
+        },
+    };
+    let ctl = Ctl::new(cfg, mgmt, opts.timeout);
+    let states = ctl.target_states(NodeType::Storage)?;
+    let mut plan = Vec::new();
+    for n in &nodes {
+        let targets: Vec<DrainedTarget> = states.iter()
+            .filter(|(_, t)| crate::status::on_node(&t.node, n))
+            .map(|(id, t)| DrainedTarget { id: id.clone(), consistency: t.consistency.clone() })
+            .collect();
+        if targets.is_empty() { bail!("the mgmtd lists no storage targets on {}", n.name); }
+        plan.push((*n, targets));
+    }
+    // with a buddy already down, the drain would take the last copy of its chunks offline
+    let others: BTreeMap<String, _> = states.into_iter().filter(|(_, t)| !nodes.iter().any(|n| crate::status::on_node(&t.node, n))).collect();
+    let bad = crate::rollout::unhealthy(&others);
+    if !bad.is_empty() { bail!("refusing to drain while other targets are not Online/Good: {}", bad.join(", ")); }
+
+    identity::verify(cfg, &nodes, opts.timeout)?;
+    if !ask(&nodes, opts.yes, "drain the storage targets and stop beegfs-storage")? { return Ok(Severity::Warning); }
+
+    let tr = transport::from_config(cfg);
+    let mut rows = Vec::new();
+    for (n, targets) in &plan {
+        let mut r = NodeResult::new(n, targets);
+        if rows.iter().any(|r: &NodeResult| r.result == "failed") { rows.push(r); continue; }
+        let res = (|| -> Result<()> {
+            let mut entry = Drained { host: n.host.clone(), since: chrono::Utc::now().timestamp(), mgmt_node: mgmt.name.clone(), targets: Vec::new(), stopped: false };
+            // each change is recorded before it is made, so a drain cut short by Ctrl-C, a
+            // cancelled command or a lost session leaves nothing undrain does not know about;
+            // putting back a change that never happened is harmless
+            for t in targets {
+                entry.targets.push(t.clone());
+                record.insert(n.name.clone(), entry.clone());
+                save(&record)?;
+                ctl.set_target_state(NodeType::Storage, &t.id, "offline")?;
+            }
+            r.sessions = Some(wait_sessions(tr.as_ref(), &n.host, opts).map_err(anyhow::Error::msg)?);
+            entry.stopped = true;
+            record.insert(n.name.clone(), entry);
+            save(&record)?;
+            r.service = Some(storage_service(cfg, n, Action::Stop, opts)?);
+            Ok(())
+        })();
+        match res {
+            Ok(()) => { r.result = "done"; r.severity = Severity::Ok; }
+            Err(e) => r.fail(&e),
+        }
+        rows.push(r);
+    }
+    report(cli, cfg, "drain", rows)
+}
+
+/// Start beegfs-storage again and set the targets back to Good, then forget the record.
+pub fn undrain(cli: &crate::Cli, cfg: &config::Config, selector: &str, opts: &Options) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    let mut record: Maintenance = state::read_json(MAINTENANCE_FILE).unwrap_or_default();
+    if let Some(n) = nodes.iter().find(|n| !record.contains_key(&n.name)) {
+        bail!("{} is not drained (no maintenance record in {})", n.name, state::state_dir().join(MAINTENANCE_FILE).display());
+    }
+    identity::verify(cfg, &nodes, opts.timeout)?;
+    if !ask(&nodes, opts.yes, "start beegfs-storage and put the storage targets back")? { return Ok(Severity::Warning); }
+
+    let mut rows = Vec::new();
+    for n in &nodes {
+        let mut entry = record[&n.name].clone();
+        let mut r = NodeResult::new(n, &entry.targets);
+        if rows.iter().any(|r: &NodeResult| r.result == "failed") { rows.push(r); continue; }
+        let res = (|| -> Result<()> {
+            if entry.stopped {
+                r.service = Some(storage_service(cfg, n, Action::Start, opts)?);
+                entry.stopped = false;
+                record.insert(n.name.clone(), entry.clone());
+                save(&record)?;
+            }
+            let mgmt = match &opts.mgmt_node {
+                Some(sel) => crate::checks::single_node(cfg, sel)?,
+                None => match cfg.nodes.iter().find(|m| m.name == entry.mgmt_node) {
+                    Some(m) => m,
+                    None => bail!("{}, which {} was drained through, is no longer in the config; pass --mgmt-node", entry.mgmt_node, n.name),
+                },
+            };
+            let ctl = Ctl::new(cfg, mgmt, opts.timeout);
+            while let Some(t) = entry.targets.first().cloned() {
+                // a target that needed a resync before is left to the mgmtd to resync
+                if t.consistency.eq_ignore_ascii_case("good") { ctl.set_target_state(NodeType::Storage, &t.id, "good")?; }
+                entry.targets.remove(0);
+                record.insert(n.name.clone(), entry.clone());
+                save(&record)?;
+            }
+            record.remove(&n.name);
+            save(&record)
+        })();
+        match res {
+            Ok(()) => { r.result = "done"; r.severity = Severity::Ok; }
+            Err(e) => r.fail(&e),
+        }
+        rows.push(r);
+    }
+    report(cli, cfg, "undrain", rows)
+}
+
+fn report(cli: &crate::Cli, cfg: &config::Config, action: &str, mut rows: Vec<NodeResult>) -> Result<Severity> {
+    let sev = rows.iter().map(|r| r.severity).max().unwrap_or_default();
+    let failed: Vec<String> = rows.iter().filter(|r| r.result == "failed").map(|r| r.node.clone()).collect();
+    let skipped: Vec<String> = rows.iter().filter(|r| r.result == "skipped").map(|r| r.node.clone()).collect();
+    config::sort_rows(cfg, cli.sort, &mut rows, |r| &r.node);
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "Host", "Targets", "Busy sessions", "beegfs-storage", "Result", "Detail"]);
+            for r in &rows {
+                let dash = || "-".to_string();
+                table.add_row(vec![
+                    r.node.clone(), r.host.clone(), r.targets.join(","),
+                    r.sessions.map(|s| s.to_string()).unwrap_or_else(dash), r.service.clone().unwrap_or_else(dash),
+                    r.result.to_string(), r.error.clone().unwrap_or_default(),
+                ]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            crate::formats::print(cli.output, &serde_json::json!({"action": action, "nodes": rows}))?;
+        }
+    }
+    if !failed.is_empty() { log::warn!("{} failed on {}; what was done is recorded, `beeg node undrain` reverses it", action, failed.join(", ")); }
+    if !skipped.is_empty() { log::warn!("stopped after the failed node; not touched: {}", skipped.join(", ")); }
+    if sev == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+    Ok(sev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_and_record() {
+        assert_eq!(parse_sessions("0\n"), Some(0));
+        assert_eq!(parse_sessions("motd line\n  3\n"), Some(3));
+        assert_eq!(parse_sessions("sh: ss: command not found\n"), None);
+
+        let record: Maintenance = BTreeMap::from([("stor01".to_string(), Drained {
+            host: "10.0.0.11".into(), since: 1_792_000_000, mgmt_node: "mgmt01".into(),
+            targets: vec![DrainedTarget { id: "101".into(), consistency: "Good".into() }], stopped: true,
+        })]);
+        let text = serde_json::to_string(&record).unwrap();
+        assert_eq!(serde_json::from_str::<Maintenance>(&text).unwrap(), record);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::checks::single_node;
+use crate::{beegfs, config, transfer};
+use anyhow::Result;
+use comfy_table::{Table, presets::UTF8_FULL};
+
+pub fn info(cli: &crate::Cli, cfg: &config::Config, selector: &str, path: &str, timeout: u64) -> Result<()> {
+    let node = single_node(cfg, selector)?;
+    let e = beegfs::Ctl::new(cfg, node, timeout).entry_info(path)?;
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let opt = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".into());
+            let num = |v: Option<u32>| v.map(|n| n.to_string()).unwrap_or_else(|| "-".into());
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Field", "Value"]);
+            table.add_row(vec!["Path".to_string(), path.to_string()]);
+            table.add_row(vec!["Entry type".to_string(), opt(&e.entry_type)]);
+            table.add_row(vec!["Entry ID".to_string(), opt(&e.entry_id)]);
+            table.add_row(vec!["Metadata".to_string(), opt(&e.metadata_node)]);
+            if let Some(g) = &e.metadata_buddy_group { table.add_row(vec!["Metadata buddy group".to_string(), g.clone()]); }
+            table.add_row(vec!["Stripe pattern".to_string(), opt(&e.pattern)]);
+            table.add_row(vec!["Chunk size".to_string(), e.chunk_size.map(transfer::format_size).unwrap_or_else(|| "-".into())]);
+            table.add_row(vec!["Targets desired".to_string(), num(e.desired_targets)]);
+            table.add_row(vec!["Targets actual".to_string(), num(e.actual_targets)]);
+            table.add_row(vec!["Storage pool".to_string(), opt(&e.storage_pool)]);
+            crate::tables::print(cli.output, &table);
+            if !e.targets.is_empty() {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.set_header(vec!["Target", "Node"]);
+                for t in &e.targets { table.add_row(vec![t.id.clone(), t.node.clone()]); }
+                crate::tables::print_another(cli.output, &table);
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let mut v = serde_json::to_value(&e)?;
+            v["path"] = serde_json::json!(path);
+            v["node"] = serde_json::json!(node.name);
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    if let (Some(d), Some(a)) = (e.desired_targets, e.actual_targets) {
+        if a < d { log::warn!("{} is striped over {} of {} desired targets", path, a, d); }
+    }
+    Ok(())
+}
@@ -0,0 +1,340 @@
+use crate::checks::{self, capacity, client, Check, CheckReport, CheckResult, Severity};
+use crate::config;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// What one round of `beeg export prometheus` checks.
+pub(crate) struct ExportOptions {
+    /// Nodes whose beegfs-* services (and client mounts) are probed
+    pub selector: String,
+    /// Management node for target capacity; skipped when `None`
+    pub mgmt_node: Option<String>,
+    /// Node to read storage target states from; skipped when `None`
+    pub storage_node: Option<String>,
+    /// Target IDs for the target states: comma-separated or 'all'
+    pub targets: String,
+    /// Client mountpoint to probe; skipped when `None`
+    pub mount: Option<String>,
+    pub timeout: u64,
+}
+
+/// Help text and `(labels, value)` samples of one metric.
+type Family = (&'static str, Vec<(String, f64)>);
+
+/// Gauges by metric name, rendered in the Prometheus text exposition format.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    families: BTreeMap<&'static str, Family>,
+}
+
+impl Metrics {
+    pub fn gauge(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+        let labels: Vec<String> = labels.iter().map(|(k, v)| format!("{}=\"{}\"", k, escape(v))).collect();
+        self.families.entry(name).or_insert((help, Vec::new())).1.push((labels.join(","), value));
+    }
+
+    pub fn render(&self) -> String {
+        let mut s = String::new();
+        for (name, (help, samples)) in &self.families {
+            let _ = writeln!(s, "# HELP {} {}", name, help);
+            let _ = writeln!(s, "# TYPE {} gauge", name);
+            for (labels, v) in samples {
+                if labels.is_empty() {
+                    let _ = writeln!(s, "{} {}", name, v);
+                } else {
+                    let _ = writeln!(s, "{}{{{}}} {}", name, labels, v);
+                }
+            }
+        }
+        s
+    }
+}
+
+fn escape(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// 0 OK, 1 warning, 2 failure, 4 permission denied, as in the exit codes.
+fn level(s: Severity) -> f64 {
+    match s { Severity::Ok => 0.0, Severity::Warning => 1.0, Severity::Failure => 2.0, Severity::NoPerm => 4.0 }
+}
+
+/// `(unit, active state)` from `systemctl list-units --plain --no-legend`; units systemd
+/// only knows by name (not installed) are left out.
+pub(crate) fn parse_units(text: &str) -> Vec<(String, String)> {
+    text.lines().filter_map(|l| {
+        let mut cols = l.split_whitespace().skip_while(|c| *c == "●");
+        let unit = cols.next()?;
+        let (load, active) = (cols.next()?, cols.next()?);
+        if load == "not-found" { return None; }
+        Some((unit.trim_end_matches(".service").to_string(), active.to_string()))
+    }).collect()
+}
+
+/// One row per node: unreachable nodes and failed units fail, other inactive units warn.
+fn services_report(cfg: &config::Config, selector: &str, timeout: u64) -> CheckReport {
+    let nodes = config::select_nodes(cfg, selector);
+    let cmd = checks::wrap_timeout("systemctl list-units --all --plain --no-legend --type=service 'beegfs-*'", timeout);
+    let probes = checks::per_node(cfg, &nodes, |tr, n| tr.exec_idempotent(&n.host, &cmd).map_err(|e| format!("{:#}", e)));
+
+    let mut report = CheckReport::new("services", &["Services"]);
+    for (n, out, duration) in probes {
+        let (status, detail, units) = match out {
+            Ok(o) if o.status == Some(0) => {
+                let units = parse_units(&o.stdout);
+                let failed: Vec<&str> = units.iter().filter(|(_, a)| a == "failed").map(|(u, _)| u.as_str()).collect();
+                let inactive: Vec<&str> = units.iter().filter(|(_, a)| a != "active" && a != "failed").map(|(u, _)| u.as_str()).collect();
+                let (status, detail) = if !failed.is_empty() {
+                    (Severity::Failure, format!("failed: {}", failed.join(", ")))
+                } else if !inactive.is_empty() {
+                    (Severity::Warning, format!("inactive: {}", inactive.join(", ")))
+                } else {
+                    (Severity::Ok, String::new())
+                };
+                (status, detail, Some(units))
+            }
+            Ok(o) => (Severity::Failure, format!("systemctl exited {:?}: {}", o.status, o.stderr.trim()), None),
+            Err(e) => (Severity::Failure, e, None),
+        };
+        let reachable = units.is_some();
+        let units = units.unwrap_or_default();
+        let active: BTreeMap<&str, bool> = units.iter().map(|(u, a)| (u.as_str(), a == "active")).collect();
+        report.results.push(CheckResult {
+            check: "services",
+            node: n.name.clone(),
+            status,
+            detail,
+            duration,
+            cells: vec![units.iter().map(|(u, _)| u.as_str()).collect::<Vec<_>>().join(", ")],
+            data: serde_json::json!({ "reachable": reachable, "services": active }),
+        });
+    }
+    report
+}
+
+/// `beeg_check_*` for any report, then the check's own metrics.
+fn record(m: &mut Metrics, errors: &mut Vec<String>, check: &'static str, run: impl FnOnce() -> Result<CheckReport>, detail: fn(&mut Metrics, &CheckReport)) {
+    let started = Instant::now();
+    let res = run();
+    m.gauge("beeg_check_duration_seconds", "Time the check took in the last round", &[("check", check)], started.elapsed().as_secs_f64());
+    match res {
+        Ok(r) => {
+            m.gauge("beeg_check_up", "Whether the check could run (1) or errored before reporting (0)", &[("check", check)], 1.0);
+            m.gauge("beeg_check_status", "Worst result of the check: 0 OK, 1 warning, 2 failure, 4 permission denied", &[("check", check)], level(r.severity()));
+            let mut nodes: BTreeMap<&str, Severity> = BTreeMap::new();
+            for x in &r.results {
+                let worst = nodes.entry(&x.node).or_default();
+                *worst = (*worst).max(x.status);
+            }
+            for (node, s) in nodes {
+                m.gauge("beeg_check_node_status", "Worst result of the check on a node: 0 OK, 1 warning, 2 failure, 4 permission denied", &[("check", check), ("node", node)], level(s));
+            }
+            detail(m, &r);
+        }
+        Err(e) => {
+            m.gauge("beeg_check_up", "Whether the check could run (1) or errored before reporting (0)", &[("check", check)], 0.0);
+            errors.push(format!("{}: {:#}", check, e));
+        }
+    }
+}
+
+fn bool_gauge(v: &serde_json::Value) -> f64 {
+    if v.as_bool().unwrap_or(false) { 1.0 } else { 0.0 }
+}
+
+fn service_metrics(m: &mut Metrics, r: &CheckReport) {
+    for x in &r.results {
+        m.gauge("beeg_node_up", "Whether the node answered the service probe", &[("node", &x.node)], bool_gauge(&x.data["reachable"]));
+        for (unit, active) in x.data["services"].as_object().into_iter().flatten() {
+            m.gauge("beegfs_service_active", "Whether a beegfs-* systemd service is active", &[("node", &x.node), ("service", unit)], bool_gauge(active));
+        }
+    }
+}
+
+fn target_metrics(m: &mut Metrics, r: &CheckReport) {
+    if let Some(x) = r.results.first() {
+        m.gauge("beegfs_storage_service_active", "Whether beegfs-storage is active on the node the target states are read from", &[("node", &x.node)], bool_gauge(&x.data["service_active"]));
+    }
+    for x in &r.results {
+        let target = x.data["target"].as_str().unwrap_or_default();
+        m.gauge("beegfs_target_present", "Whether the storage target is registered", &[("target", target)], bool_gauge(&x.data["present"]));
+        if x.data["present"].as_bool() == Some(true) {
+            let state = x.data["state"].as_str().unwrap_or_default();
+            m.gauge("beegfs_target_state", "Reported state of the storage target; always 1, the state is a label", &[("target", target), ("state", state)], 1.0);
+        }
+    }
+}
+
+fn capacity_metrics(m: &mut Metrics, r: &CheckReport) {
+    for x in &r.results {
+        let d = &x.data;
+        let target = d["target"].as_str().unwrap_or_default();
+        let labels = [("node", x.node.as_str()), ("target", target)];
+        let num = |k: &str| d[k].as_f64().unwrap_or(f64::NAN);
+        m.gauge("beegfs_target_space_total_bytes", "Size of the storage target", &labels, num("total_bytes"));
+        m.gauge("beegfs_target_space_free_bytes", "Free space on the storage target", &labels, num("free_bytes"));
+        m.gauge("beegfs_target_inodes_total", "Inodes on the storage target", &labels, num("total_inodes"));
+        m.gauge("beegfs_target_inodes_free", "Free inodes on the storage target", &labels, num("free_inodes"));
+        if let Some(pool) = d["pool"].as_str() {
+            m.gauge("beegfs_target_capacity_pool", "Capacity pool of the storage target; always 1, the pool is a label", &[("node", &x.node), ("target", target), ("pool", pool)], 1.0);
+        }
+        for (resource, key) in [("space", "space_days_left"), ("inodes", "inodes_days_left")] {
+            if let Some(days) = d[key].as_f64() {
+                m.gauge("beegfs_target_days_left", "Estimated days until the target runs out, from recorded history", &[("node", &x.node), ("target", target), ("resource", resource)], days);
+            }
+        }
+    }
+}
+
+fn mount_metrics(m: &mut Metrics, r: &CheckReport) {
+    for x in &r.results {
+        for probe in ["defined", "client", "df", "ls", "rw"] {
+            let Some(v) = x.data[probe].as_str() else { continue };
+            m.gauge("beegfs_client_mount_probe_ok", "Whether a client mount probe passed", &[("node", &x.node), ("probe", probe)], if v == "OK" { 1.0 } else { 0.0 });
+        }
+    }
+}
+
+/// Run one round of checks. Checks that error out are reported by `beeg_check_up` and
+/// returned as messages.
+pub(crate) fn collect(cfg: &config::Config, opts: &ExportOptions) -> (Metrics, Vec<String>) {
+    let started = Instant::now();
+    let (mut m, mut errors) = (Metrics::default(), Vec::new());
+    record(&mut m, &mut errors, "services", || Ok(services_report(cfg, &opts.selector, opts.timeout)), service_metrics);
+    if let Some(sel) = &opts.storage_node {
+        let run = || checks::single_node(cfg, sel).and_then(|n| checks::storage_target_report(cfg, n, &opts.targets, opts.timeout));
+        record(&mut m, &mut errors, checks::StorageTarget::NAME, run, target_metrics);
+    }
+    if let Some(sel) = &opts.mgmt_node {
+        let args = capacity::CapacityArgs {
+            selector: sel.clone(),
+            min_free_percent: 10,
+            min_free_inodes_percent: 10,
+            warn_days: 30,
+            history_days: 7,
+            timeout: opts.timeout,
+        };
+        record(&mut m, &mut errors, capacity::Capacity::NAME, || capacity::run(cfg, &args), capacity_metrics);
+    }
+    if let Some(mount) = &opts.mount {
+        let run = || Ok(client::mount_report(&client::collect_mount(cfg, &opts.selector, mount, opts.timeout)));
+        record(&mut m, &mut errors, client::ClientMount::NAME, run, mount_metrics);
+    }
+    m.gauge("beeg_export_duration_seconds", "Time the last round of checks took", &[], started.elapsed().as_secs_f64());
+    m.gauge("beeg_export_last_run_timestamp_seconds", "When the last round of checks finished", &[], chrono::Utc::now().timestamp() as f64);
+    (m, errors)
+}
+
+/// Status line, content type and body for a request line such as `GET /metrics HTTP/1.1`;
+/// `metrics` is `None` until the first round of checks is done. Once the collector has stopped
+/// (`collecting` false) a scrape fails rather than serve results that no longer change.
+fn respond(request: &str, metrics: Option<&str>, collecting: bool) -> (&'static str, &'static str, String) {
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let path = target.split('?').next().unwrap_or_default();
+    if method != "GET" { return ("405 Method Not Allowed", "text/plain", "only GET is supported\n".into()); }
+    match (path, metrics) {
+        ("/metrics", _) if !collecting => ("503 Service Unavailable", "text/plain", "the checks stopped running; see the exporter's log\n".into()),
+        ("/metrics", Some(m)) => ("200 OK", CONTENT_TYPE, m.to_string()),
+        ("/metrics", None) => ("503 Service Unavailable", "text/plain", "the first round of checks is still running\n".into()),
+        ("/", _) => ("200 OK", "text/html", "<html><head><title>beeg exporter</title></head><body><a href=\"/metrics\">Metrics</a></body></html>\n".into()),
+        _ => ("404 Not Found", "text/plain", "not found\n".into()),
+    }
+}
+
+fn serve(mut stream: TcpStream, latest: &RwLock<Option<String>>, collecting: bool) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // the headers do not matter, but are read so the client is not cut off mid-request
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() { break; }
+    }
+    let metrics = latest.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let (status, content_type, body) = respond(&request, metrics.as_deref(), collecting);
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, content_type, body.len())?;
+    stream.write_all(body.as_bytes())
+}
+
+/// Run the checks every `interval` seconds and serve the latest results on `listen`, or
+/// print them once (`--once`, e.g. for node_exporter's textfile collector).
+pub fn prometheus(cfg: &config::Config, listen: &str, interval: u64, once: bool, opts: ExportOptions) -> Result<()> {
+    if once {
+        let (m, errors) = collect(cfg, &opts);
+        print!("{}", m.render());
+        for e in errors { log::warn!("{}", e); }
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind(listen).with_context(|| format!("listening on {}", listen))?;
+    eprintln!("serving metrics on http://{}/metrics, checking every {}s", listener.local_addr()?, interval);
+    let latest: Arc<RwLock<Option<String>>> = Arc::default();
+    let (cfg, writer) = (cfg.clone(), latest.clone());
+    let collector = thread::spawn(move || loop {
+        let started = Instant::now();
+        // a round that panics keeps the last results, whose beeg_export_last_run_timestamp_seconds
+        // then falls behind, and the next round runs as usual
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| collect(&cfg, &opts))) {
+            Ok((m, errors)) => {
+                for e in errors { log::warn!("{}", e); }
+                *writer.write().unwrap_or_else(|e| e.into_inner()) = Some(m.render());
+            }
+            Err(_) => log::error!("a round of checks panicked; serving the previous results"),
+        }
+        thread::sleep(Duration::from_secs(interval).saturating_sub(started.elapsed()));
+    });
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let (latest, collecting) = (latest.clone(), !collector.is_finished());
+        thread::spawn(move || { let _ = serve(stream, &latest, collecting); });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposition() {
+        let mut m = Metrics::default();
+        m.gauge("beegfs_service_active", "Whether a beegfs-* systemd service is active", &[("node", "s\"1"), ("service", "beegfs-storage")], 1.0);
+        m.gauge("beeg_export_duration_seconds", "Time the last round of checks took", &[], 2.5);
+        assert_eq!(m.render(), "# HELP beeg_export_duration_seconds Time the last round of checks took
+# TYPE beeg_export_duration_seconds gauge
+beeg_export_duration_seconds 2.5
+# HELP beegfs_service_active Whether a beegfs-* systemd service is active
+# TYPE beegfs_service_active gauge
+beegfs_service_active{node=\"s\\\"1\",service=\"beegfs-storage\"} 1
+");
+
+        let units = parse_units("beegfs-meta.service    loaded    active   running BeeGFS Metadata Server
+● beegfs-storage.service loaded    failed   failed  BeeGFS Storage Server
+beegfs-helperd.service not-found inactive dead    beegfs-helperd.service
+");
+        assert_eq!(units, vec![("beegfs-meta".to_string(), "active".to_string()), ("beegfs-storage".to_string(), "failed".to_string())]);
+
+        assert_eq!(respond("GET /metrics HTTP/1.1", Some("x 1\n"), true).0, "200 OK");
+        assert_eq!(respond("GET /metrics?x=1 HTTP/1.1", None, true).0, "503 Service Unavailable");
+        assert_eq!(respond("POST /metrics HTTP/1.1", Some(""), true).0, "405 Method Not Allowed");
+        assert_eq!(respond("GET /nope HTTP/1.1", Some(""), true).0, "404 Not Found");
+    }
+
+    #[test]
+    fn stopped_collector() {
+        let (status, _, body) = respond("GET /metrics HTTP/1.1", Some("x 1\n"), false);
+        assert_eq!((status, body.as_str()), ("503 Service Unavailable", "the checks stopped running; see the exporter's log\n"));
+        assert_eq!(respond("GET / HTTP/1.1", Some("x 1\n"), false).0, "200 OK");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{config, state};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const FACTS_FILE: &str = "facts.json";
+
+/// Packages a client node needs; arch-specific ones must exist for the node's `uname -m`.
+pub const CLIENT_PACKAGES: &[&str] = &["beegfs-client", "beegfs-utils"];
+
+/// What we know about a node's platform, as of `gathered_at`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Facts {
+    pub arch: String,
+    pub os: String,
+    pub kernel: String,
+    /// Client package -> best version installable from the node's repos (for its arch or noarch)
+    pub packages: BTreeMap<String, Option<String>>,
+    pub gathered_at: i64,
+}
+
+fn probe_script() -> String {
+    format!(r#"echo "ARCH $(uname -m)"
+. /etc/os-release 2>/dev/null; echo "OS ${{ID:-unknown}} ${{VERSION_ID:-}}"
+echo "KERNEL $(uname -r)"
+for p in {}; do
+  v=
+  if command -v dnf >/dev/null 2>&1; then v=$(dnf -q repoquery --arch "$(uname -m),noarch" --qf '%{{version}}\n' "$p" 2>/dev/null | sort -V | tail -n1)
+  elif command -v apt-cache >/dev/null 2>&1; then v=$(apt-cache policy "$p" 2>/dev/null | sed -n 's/^ *Candidate: *//p'); [ "$v" = "(none)" ] && v=
+  elif command -v zypper >/dev/null 2>&1; then v=$(zypper -q --no-refresh info "$p" 2>/dev/null | sed -n 's/^Version *: *//p')
+  fi
+  echo "PKG $p ${{v:-none}}"
+done"#, CLIENT_PACKAGES.join(" "))
+}
+
+pub(crate) fn parse_facts(text: &str) -> Facts {
+    let mut f = Facts::default();
+    for line in text.lines() {
+        let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match key {
+            "ARCH" => f.arch = rest.to_string(),
+            "OS" => f.os = rest.to_string(),
+            "KERNEL" => f.kernel = rest.to_string(),
+            "PKG" => {
+                let (name, ver) = rest.split_once(' ').unwrap_or((rest, "none"));
+                let ver = ver.trim();
+                f.packages.insert(name.to_string(), (ver != "none" && !ver.is_empty()).then(|| ver.to_string()));
+            }
+            _ => {}
+        }
+    }
+    f
+}
+
+/// Architectures where no node can install `pkg`, with the nodes concerned.
+pub(crate) fn arches_missing(facts: &BTreeMap<String, Facts>, pkg: &str) -> BTreeMap<String, Vec<String>> {
+    let mut by_arch: BTreeMap<&str, (bool, Vec<String>)> = BTreeMap::new();
+    for (node, f) in facts {
+        let e = by_arch.entry(f.arch.as_str()).or_default();
+        e.0 |= f.packages.get(pkg).is_some_and(Option::is_some);
+        e.1.push(node.clone());
+    }
+    by_arch.into_iter().filter(|(_, (found, _))| !found).map(|(a, (_, nodes))| (a.to_string(), nodes)).collect()
+}
+
+/// Gather facts on the selected nodes, merge them into the cache and report them.
+pub fn run(cli: &crate::Cli, cfg: &config::Config, selector: &str, timeout: u64) -> anyhow::Result<()> {
+    let nodes = config::select_nodes(cfg, selector);
+    let cmd = crate::checks::wrap_timeout(&probe_script(), timeout);
+    let now = chrono::Utc::now().timestamp();
+    let mut results: Vec<(String, Result<Facts, String>)> = crate::checks::per_node(cfg, &nodes, |tr, n| {
+        tr.exec_idempotent(&n.host, &cmd).map(|o| parse_facts(&o.stdout)).map_err(|e| e.to_string())
+    }).into_iter().map(|(n, res, _)| (n.name.clone(), res)).collect();
+    config::sort_rows(cfg, cli.sort, &mut results, |(n, _)| n);
+
+    let mut gathered = BTreeMap::new();
+    for (node, res) in &results {
+        if let Ok(f) = res {
+            gathered.insert(node.clone(), Facts { gathered_at: now, ..f.clone() });
+        }
+    }
+    let mut cache: BTreeMap<String, Facts> = state::read_json(FACTS_FILE).unwrap_or_default();
+    cache.extend(gathered.clone());
+    state::write_json(FACTS_FILE, &cache)?;
+
+    let pkg = |f: &Facts, p: &str| f.packages.get(p).cloned().flatten().unwrap_or_else(|| "-".into());
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            let mut header = vec!["Node", "Arch", "OS", "Kernel"];
+            header.extend(CLIENT_PACKAGES);
+            table.set_header(header);
+            for (node, res) in &results {
+                let mut row = vec![node.clone()];
+                match res {
+                    Ok(f) => {
+                        row.extend([f.arch.clone(), f.os.clone(), f.kernel.clone()]);
+                        row.extend(CLIENT_PACKAGES.iter().map(|p| pkg(f, p)));
+                    }
+                    Err(e) => row.push(format!("ERR: {}", e)),
+                }
+                table.add_row(row);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let arr: Vec<_> = results.iter().map(|(node, res)| match res {
+                Ok(f) => serde_json::json!({
+                    "node": node,
+                    "ok": true,
+                    "arch": f.arch,
+                    "os": f.os,
+                    "kernel": f.kernel,
+                    "packages": f.packages,
+                }),
+                Err(e) => serde_json::json!({ "node": node, "ok": false, "error": e }),
+            }).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+
+    // Warnings
+    for p in CLIENT_PACKAGES {
+        for (arch, nodes) in arches_missing(&gathered, p) {
+            log::warn!("no {} package available for {} (nodes: {})", p, arch, nodes.join(", "));
+        }
+    }
+    let failed: Vec<&str> = results.iter().filter(|(_, r)| r.is_err()).map(|(n, _)| n.as_str()).collect();
+    if !failed.is_empty() { log::warn!("could not gather facts from: {}", failed.join(", ")); }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_group() {
+        let x86 = parse_facts("ARCH x86_64\nOS rocky 9.3\nKERNEL 5.14.0\nPKG beegfs-client 7.4.2\nPKG beegfs-utils 7.4.2\n");
+        let arm = parse_facts("ARCH aarch64\nOS rocky 9.3\nKERNEL 5.14.0\nPKG beegfs-client 7.4.2\nPKG beegfs-utils none\n");
+        assert_eq!(x86.arch, "x86_64");
+        assert_eq!(arm.packages["beegfs-utils"], None);
+        let facts = BTreeMap::from([("a".to_string(), x86), ("b".to_string(), arm.clone()), ("c".to_string(), arm)]);
+        assert!(arches_missing(&facts, "beegfs-client").is_empty());
+        let missing = arches_missing(&facts, "beegfs-utils");
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing["aarch64"], vec!["b".to_string(), "c".to_string()]);
+    }
+}
@@ -0,0 +1,251 @@
+//! `beeg fsck run`: run beegfs-fsck on one node, show its output as it comes, and sum up the
+//! errors it found per category. The outcome goes into the history store (check `fsck`), so
+//! `beeg history show --check fsck` compares runs over time.
+
+use crate::checks::{permission_denied, Severity, NOPERM_HINT};
+use crate::{config, history, identity, prompt, transport};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL};
+use regex::Regex;
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::time::Instant;
+
+const CHECK: &str = "fsck";
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Mode {
+    /// Look for inconsistencies (`--checkfs`)
+    CheckFs,
+    /// Set the quota IDs on existing chunks (`--enablequota`)
+    EnableQuota,
+}
+
+impl Mode {
+    fn flag(self) -> &'static str {
+        match self { Mode::CheckFs => "--checkfs", Mode::EnableQuota => "--enablequota" }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self { Mode::CheckFs => "check-fs", Mode::EnableQuota => "enable-quota" }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub mode: Mode,
+    /// Repair what is found, answering every question with the default (`--automatic`)
+    pub fix: bool,
+    pub yes: bool,
+    /// Let fsck assume nothing modifies the file system while it runs (`--runOffline`)
+    pub run_offline: bool,
+    /// Reuse the database of the previous run instead of gathering again (`--noFetch`)
+    pub no_fetch: bool,
+    pub db_path: Option<String>,
+    /// Passed on to beegfs-fsck as they are
+    pub extra: Vec<String>,
+}
+
+/// The beegfs-fsck command line for `opts`; read-only unless fixing.
+pub(crate) fn command(opts: &Options) -> String {
+    let mut args = vec!["beegfs-fsck".to_string(), opts.mode.flag().to_string()];
+    if opts.mode == Mode::CheckFs {
+        args.push(if opts.fix { "--automatic".into() } else { "--readOnly".into() });
+    }
+    if opts.run_offline { args.push("--runOffline".into()); }
+    if opts.no_fetch { args.push("--noFetch".into()); }
+    if let Some(p) = &opts.db_path { args.push(format!("--databasePath={}", shell_escape::escape(p.into()))); }
+    args.extend(opts.extra.iter().map(|a| shell_escape::escape(a.into()).to_string()));
+    args.join(" ")
+}
+
+/// One category fsck checks, and what it found.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct Category {
+    pub name: String,
+    pub errors: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub(crate) struct Summary {
+    /// The last `Step N: ...` fsck announced
+    pub step: Option<String>,
+    pub categories: Vec<Category>,
+    /// The overall count fsck printed, when it did
+    pub total: Option<u64>,
+    /// Lines that report fsck itself failing
+    pub failures: Vec<String>,
+}
+
+impl Summary {
+    pub fn errors(&self) -> u64 {
+        self.total.unwrap_or_else(|| self.categories.iter().map(|c| c.errors).sum())
+    }
+
+    /// `3 errors: 2 Dangling directory entry, 1 Chunks without an inode`
+    pub fn describe(&self) -> String {
+        let found: Vec<String> = self.categories.iter().filter(|c| c.errors > 0).map(|c| format!("{} {}", c.errors, c.name)).collect();
+        match (self.errors(), found.is_empty()) {
+            (0, _) => "no errors".into(),
+            (n, true) => format!("{} errors", n),
+            (n, false) => format!("{} errors: {}", n, found.join(", ")),
+        }
+    }
+}
+
+/// Reads fsck's output a line at a time, as it streams in.
+pub(crate) struct Parser {
+    step: Regex,
+    category: Regex,
+    found: Regex,
+    summary: Summary,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Parser {
+            step: Regex::new(r"^\s*(Step \d+:.*?)\s*$").unwrap(),
+            category: Regex::new(r"^\s*\*\s*(.+?)\s*\.\.\.\s*(.*?)\s*$").unwrap(),
+            found: Regex::new(r"(?i)found\s+(\d+)\s+errors?").unwrap(),
+            summary: Summary::default(),
+        }
+    }
+
+    pub fn line(&mut self, line: &str) {
+        let s = &mut self.summary;
+        if let Some(c) = self.step.captures(line) {
+            s.step = Some(c[1].trim_end_matches(':').to_string());
+        } else if let Some(c) = self.category.captures(line) {
+            let errors = self.found.captures(&c[2]).and_then(|f| f[1].parse().ok()).unwrap_or(0);
+            s.categories.push(Category { name: c[1].to_string(), errors });
+        } else if let Some(n) = self.found.captures(line).and_then(|f| f[1].parse::<u64>().ok()) {
+            // `>>> Found 4 errors.` belongs to the category above it; a count after the last
+            // step's categories with nothing to attach to is fsck's total
+            match s.categories.last_mut() {
+                Some(c) if line.trim_start().starts_with(">>>") => c.errors += n,
+                _ => s.total = Some(n),
+            }
+        } else if line.contains("ERROR") || line.contains("Exception") || line.contains("Aborting") {
+            s.failures.push(line.trim().to_string());
+        }
+    }
+
+    pub fn finish(self) -> Summary {
+        self.summary
+    }
+}
+
+fn severity(summary: &Summary, status: Option<i32>) -> Severity {
+    if summary.failures.iter().any(|f| permission_denied(f)) { return Severity::NoPerm; }
+    match (status, summary.errors()) {
+        (Some(0), 0) if summary.failures.is_empty() => Severity::Ok,
+        _ => Severity::Failure,
+    }
+}
+
+pub fn run(cli: &crate::Cli, cfg: &config::Config, selector: &str, opts: &Options) -> Result<Severity> {
+    let node = crate::checks::single_node(cfg, selector)?;
+    if opts.fix && opts.mode != Mode::CheckFs { bail!("--fix only applies to --mode check-fs"); }
+    let cmd = command(opts);
+    if opts.fix {
+        identity::verify(cfg, &[node], 30)?;
+        if !opts.yes {
+            if !std::io::stdin().is_terminal() { bail!("refusing to let beegfs-fsck repair the file system without confirmation; pass --yes to run non-interactively"); }
+            if !prompt::confirm(&format!("Run `{}` on {}, repairing what it finds?", cmd, node.name))? {
+                eprintln!("Aborted; fsck was not started.");
+                return Ok(Severity::Warning);
+            }
+        }
+    }
+
+    let human = matches!(cli.output, crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios);
+    let started = Instant::now();
+    let started_at = chrono::Utc::now();
+    let mut parser = Parser::new();
+    let tr = transport::from_config(cfg);
+    // JSON goes to stdout once fsck is done, so its progress goes to stderr meanwhile
+    let status = tr.exec_lines(&node.host, &transport::stoppable(&cmd), &mut |is_err, line| {
+        parser.line(line);
+        if human && !is_err { println!("{}", line); } else { eprintln!("{}", line); }
+    })?;
+    let summary = parser.finish();
+    let sev = severity(&summary, status);
+    let mut detail = format!("{} ({}{})", summary.describe(), opts.mode.as_str(), if opts.fix { ", fix" } else { "" });
+    if status != Some(0) { detail.push_str(&format!("; beegfs-fsck exited {:?}", status)); }
+
+    let previous = history::latest(CHECK, &node.name);
+    history::record_records(&[history::Record {
+        ts: started_at.timestamp(), check: CHECK.into(), node: node.name.clone(), status: sev.as_str().into(),
+        detail: detail.clone(), duration_ms: started.elapsed().as_millis() as u64,
+    }]);
+
+    let when = |ts: i64| chrono::DateTime::from_timestamp(ts, 0).map(|t| crate::timefmt::human(t, cli.utc)).unwrap_or_default();
+    if human {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Category", "Errors"]);
+        for c in &summary.categories {
+            let sev = if c.errors > 0 { Severity::Failure } else { Severity::Ok };
+            table.add_row(vec![comfy_table::Cell::new(&c.name), crate::tables::status_cell(c.errors, sev)]);
+        }
+        if !summary.categories.is_empty() { crate::tables::print(cli.output, &table); }
+        crate::tables::note(cli.output, &format!("{}: {}", node.name, detail));
+        if let Some(p) = &previous { crate::tables::note(cli.output, &format!("previous fsck on {}: {}", when(p.ts), p.detail)); }
+    } else {
+        let v = serde_json::json!({
+            "node": node.name, "command": cmd, "mode": opts.mode.as_str(), "fix": opts.fix, "exit_code": status,
+            "status": sev.as_str(), "errors": summary.errors(), "summary": summary,
+            "duration_ms": started.elapsed().as_millis() as u64, "previous": previous,
+        });
+        crate::formats::print(cli.output, &v)?;
+    }
+    for f in &summary.failures { log::warn!("{}", f); }
+    if sev == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+    Ok(sev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "Step 1: Check settings: Done
+Step 2: Gather data from nodes:
+        Fetched data > Directory entries: 120345 | Inodes: 120402 | Chunks: 480112
+Step 3: Check for errors...
+        * Duplicated inode IDs ... Finished
+        * Dangling directory entry ...
+        >>> Found 2 errors. Detailed information can also be found in /var/log/beegfs-fsck.log.
+        * Chunks without an inode (orphaned chunks) ... Found 1 error.
+        * Wrong owner node saved in inode ... Finished
+Found 3 errors. Detailed information can also be found in /var/log/beegfs-fsck.log.
+";
+
+    #[test]
+    fn summary() {
+        let mut p = Parser::new();
+        OUTPUT.lines().for_each(|l| p.line(l));
+        let s = p.finish();
+        assert_eq!(s.step.as_deref(), Some("Step 3: Check for errors..."));
+        assert_eq!(s.categories.len(), 4);
+        assert_eq!(s.categories[1], Category { name: "Dangling directory entry".into(), errors: 2 });
+        assert_eq!(s.categories[2].errors, 1);
+        assert_eq!(s.errors(), 3);
+        assert_eq!(s.describe(), "3 errors: 2 Dangling directory entry, 1 Chunks without an inode (orphaned chunks)");
+        assert_eq!(severity(&s, Some(0)), Severity::Failure);
+
+        let mut clean = Parser::new();
+        ["Step 3: Check for errors...", "        * Duplicated inode IDs ... Finished"].iter().for_each(|l| clean.line(l));
+        let clean = clean.finish();
+        assert_eq!((clean.describe().as_str(), severity(&clean, Some(0))), ("no errors", Severity::Ok));
+        assert_eq!(severity(&clean, Some(1)), Severity::Failure);
+    }
+
+    #[test]
+    fn command_line() {
+        let opts = Options { mode: Mode::CheckFs, fix: false, yes: false, run_offline: true, no_fetch: false, db_path: Some("/var/tmp/fsck db".into()), extra: vec!["--overwriteDbFile".into()] };
+        assert_eq!(command(&opts), "beegfs-fsck --checkfs --readOnly --runOffline --databasePath='/var/tmp/fsck db' --overwriteDbFile");
+        assert_eq!(command(&Options { fix: true, run_offline: false, db_path: None, extra: vec![], ..opts.clone() }), "beegfs-fsck --checkfs --automatic");
+        assert_eq!(command(&Options { mode: Mode::EnableQuota, ..opts }).split(' ').take(3).collect::<Vec<_>>(), ["beegfs-fsck", "--enablequota", "--runOffline"]);
+    }
+}
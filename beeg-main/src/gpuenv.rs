@@ -0,0 +1,291 @@
+use crate::beegconf::drift;
+use crate::checks::{single_node, wrap_timeout, Severity, CUDA, NVIDIA_DRIVER, NVIDIA_FS};
+use crate::config;
+use anyhow::{bail, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One GPU's PCIe link as the kernel reports it. The current speed drops while a GPU idles,
+/// so it is left out when comparing nodes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GpuLink {
+    pub bus_id: String,
+    pub width: String,
+    pub max_width: String,
+    pub speed: String,
+    pub max_speed: String,
+}
+
+/// What a GDS/CUDA job relies on below the application, on one node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GpuEnv {
+    pub driver: String,
+    pub cuda: String,
+    pub nvidia_fs: String,
+    /// `loaded`, `available` (installed, not loaded) or `missing`
+    pub nvidia_peermem: String,
+    /// `off`, `passthrough` or `dma` (translated), from the IOMMU groups' default domains
+    pub iommu: String,
+    /// IOMMU parameters on the kernel command line
+    pub iommu_cmdline: Vec<String>,
+    pub gpus: Vec<GpuLink>,
+    /// PCI devices with ACS request or completion redirect on; `None` when lspci is missing
+    /// or cannot read the capabilities (it needs root)
+    pub acs_redirect: Option<Vec<String>>,
+}
+
+fn probe_script() -> String {
+    format!(r#"echo "DRIVER $({})"
+echo "CUDA $({})"
+echo "NVFS $({})"
+if lsmod 2>/dev/null | grep -qE '^(nvidia_peermem|nv_peer_mem) '; then echo "PEERMEM loaded"
+elif modinfo nvidia_peermem >/dev/null 2>&1 || modinfo nv_peer_mem >/dev/null 2>&1; then echo "PEERMEM available"
+else echo "PEERMEM missing"; fi
+echo "CMDLINE $(cat /proc/cmdline 2>/dev/null)"
+echo "IOMMU $(cat /sys/kernel/iommu_groups/*/type 2>/dev/null | sort -u | tr '\n' ' ')"
+for d in /sys/bus/pci/devices/*; do
+  [ "$(cat "$d/vendor" 2>/dev/null)" = 0x10de ] || continue
+  case "$(cat "$d/class" 2>/dev/null)" in 0x0300*|0x0302*) ;; *) continue ;; esac
+  echo "GPU ${{d##*/}}|$(cat "$d/current_link_width" 2>/dev/null)|$(cat "$d/max_link_width" 2>/dev/null)|$(cat "$d/current_link_speed" 2>/dev/null)|$(cat "$d/max_link_speed" 2>/dev/null)"
+done
+if command -v lspci >/dev/null 2>&1; then
+  out=$(lspci -D -vvv 2>/dev/null)
+  case "$out" in
+    *"access denied"*) echo "ACS unknown" ;;
+    *) echo "$out" | awk '/^[0-9a-f]/ {{dev=$1}} /ACSCtl:/ {{print "ACSCTL", dev, $0}}'; echo "ACS checked" ;;
+  esac
+else echo "ACS unknown"; fi"#, NVIDIA_DRIVER.script(), CUDA.script(), NVIDIA_FS.script())
+}
+
+/// Drop the ` PCIe` suffix newer kernels add to link speeds.
+fn speed(s: &str) -> String {
+    s.trim().trim_end_matches("PCIe").trim().to_string()
+}
+
+pub(crate) fn parse(text: &str) -> GpuEnv {
+    let mut env = GpuEnv::default();
+    let mut acs = Vec::new();
+    let mut acs_checked = false;
+    for line in text.lines() {
+        let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        // the version queries print nothing when a pipeline's first command fails
+        let version = || if rest.is_empty() { "unknown".to_string() } else { rest.to_string() };
+        match key {
+            "DRIVER" => env.driver = version(),
+            "CUDA" => env.cuda = version(),
+            "NVFS" => env.nvidia_fs = version(),
+            "PEERMEM" => env.nvidia_peermem = rest.to_string(),
+            "CMDLINE" => {
+                env.iommu_cmdline = rest.split_whitespace()
+                    .filter(|a| ["intel_iommu=", "amd_iommu=", "iommu=", "iommu.passthrough=", "iommu.strict="].iter().any(|p| a.starts_with(p)))
+                    .map(String::from)
+                    .collect();
+            }
+            "IOMMU" => {
+                let types: Vec<&str> = rest.split_whitespace().collect();
+                env.iommu = if types.is_empty() { "off" } else if types.iter().all(|t| *t == "identity") { "passthrough" } else { "dma" }.into();
+            }
+            "GPU" => {
+                let f: Vec<&str> = rest.split('|').collect();
+                let at = |i: usize| f.get(i).map(|s| s.trim().to_string()).unwrap_or_default();
+                env.gpus.push(GpuLink { bus_id: at(0), width: at(1), max_width: at(2), speed: speed(&at(3)), max_speed: speed(&at(4)) });
+            }
+            "ACSCTL" => {
+                let (dev, ctl) = rest.split_once(' ').unwrap_or((rest, ""));
+                if ctl.contains("ReqRedir+") || ctl.contains("CmpltRedir+") { acs.push(dev.to_string()); }
+            }
+            "ACS" => acs_checked = rest == "checked",
+            _ => {}
+        }
+    }
+    env.acs_redirect = acs_checked.then_some(acs);
+    env
+}
+
+/// Key/value view of `env` for comparing nodes; GPUs are numbered in bus order.
+pub(crate) fn flatten(env: &GpuEnv) -> BTreeMap<String, String> {
+    let list = |v: &[String]| if v.is_empty() { "none".to_string() } else { v.join(" ") };
+    let mut out = BTreeMap::from([
+        ("driver".to_string(), env.driver.clone()),
+        ("cuda".to_string(), env.cuda.clone()),
+        ("nvidia_fs".to_string(), env.nvidia_fs.clone()),
+        ("nvidia_peermem".to_string(), env.nvidia_peermem.clone()),
+        ("iommu".to_string(), env.iommu.clone()),
+        ("iommu_cmdline".to_string(), list(&env.iommu_cmdline)),
+        ("acs_redirect".to_string(), env.acs_redirect.as_deref().map(list).unwrap_or_else(|| "unknown".into())),
+        ("gpus".to_string(), env.gpus.len().to_string()),
+    ]);
+    for (i, g) in env.gpus.iter().enumerate() {
+        out.insert(format!("gpu{}.bus_id", i), g.bus_id.clone());
+        out.insert(format!("gpu{}.width", i), format!("x{} of x{}", g.width, g.max_width));
+        out.insert(format!("gpu{}.max_speed", i), g.max_speed.clone());
+    }
+    out
+}
+
+/// Things in `env` known to slow down or break GPUDirect Storage.
+pub(crate) fn issues(env: &GpuEnv) -> Vec<String> {
+    let mut out = Vec::new();
+    for g in &env.gpus {
+        if g.width != g.max_width && !g.width.is_empty() {
+            out.push(format!("GPU {} link runs at x{} of x{}", g.bus_id, g.width, g.max_width));
+        }
+    }
+    if env.gpus.is_empty() { return out; }
+    if let Some(devs) = env.acs_redirect.as_ref().filter(|d| !d.is_empty()) {
+        out.push(format!("ACS redirect is on for {} device(s) ({}); peer-to-peer DMA detours through the root complex", devs.len(), devs.join(", ")));
+    }
+    if env.iommu == "dma" {
+        out.push("IOMMU translates DMA; GDS expects it off or in passthrough (iommu=pt)".into());
+    }
+    out
+}
+
+fn gather(cfg: &config::Config, nodes: &[&config::Node], timeout: u64) -> Vec<(String, Result<GpuEnv, String>)> {
+    let cmd = wrap_timeout(&probe_script(), timeout);
+    crate::checks::per_node(cfg, nodes, |tr, n| tr.exec_idempotent(&n.host, &cmd).map(|o| parse(&o.stdout)).map_err(|e| e.to_string()))
+        .into_iter().map(|(n, res, _)| (n.name.clone(), res))
+        .collect()
+}
+
+/// Capture the GPU environment of the selected nodes; with `reference`, report only what
+/// differs from that (known-good) node.
+pub fn run(cli: &crate::Cli, cfg: &config::Config, selector: &str, reference: Option<&str>, timeout: u64) -> Result<Severity> {
+    let mut nodes = config::select_nodes(cfg, selector);
+    let reference = reference.map(|r| single_node(cfg, r)).transpose()?;
+    if let Some(r) = reference { nodes.insert(cfg, r); }
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    let mut results = gather(cfg, &nodes, timeout);
+    config::sort_rows(cfg, cli.sort, &mut results, |(n, _)| n);
+    let unreachable: Vec<(&str, &str)> = results.iter().filter_map(|(n, r)| r.as_ref().err().map(|e| (n.as_str(), e.as_str()))).collect();
+
+    if let Some(r) = reference {
+        if unreachable.iter().any(|(n, _)| *n == r.name) { bail!("could not capture the GPU environment of reference node {}", r.name); }
+        let flat: Vec<(&str, BTreeMap<String, String>)> = results.iter()
+            .filter_map(|(n, res)| res.as_ref().ok().map(|e| (n.as_str(), flatten(e))))
+            .collect();
+        let drifted = drift(&flat, Some(&r.name), &[]);
+        match cli.output {
+            crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+                if drifted.is_empty() && crate::tables::separator(cli.output).is_none() {
+                    println!("GPU environment matches {} on {} node(s)", r.name, flat.len() - 1);
+                } else {
+                    let mut table = Table::new();
+                    table.load_preset(UTF8_FULL);
+                    table.set_header(vec!["Key", r.name.as_str(), "Differs on"]);
+                    let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "(absent)".into());
+                    for d in &drifted {
+                        let mut by: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+                        for (n, v) in &d.differs { by.entry(show(v)).or_default().push(n); }
+                        let lines: Vec<String> = by.iter().map(|(v, ns)| format!("{}: {}", v, ns.join(", "))).collect();
+                        table.add_row(vec![d.key.clone(), show(&d.expected), lines.join("\n")]);
+                    }
+                    crate::tables::print(cli.output, &table);
+                }
+            }
+            crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+                let v = serde_json::json!({
+                    "reference": r.name,
+                    "nodes": flat.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+                    "drift": drifted,
+                    "unreachable": unreachable.iter().map(|(n, e)| serde_json::json!({"node": n, "error": e})).collect::<Vec<_>>(),
+                });
+                crate::formats::print(cli.output, &v)?;
+            }
+        }
+
+        // Warnings
+        for (n, e) in &unreachable { log::warn!("could not capture the GPU environment of {}: {}", n, e); }
+        if !drifted.is_empty() { log::warn!("{} key(s) differ from {}", drifted.len(), r.name); }
+        return Ok(if !unreachable.is_empty() { Severity::Failure } else if !drifted.is_empty() { Severity::Warning } else { Severity::Ok });
+    }
+
+    let found: Vec<(&str, Vec<String>)> = results.iter()
+        .filter_map(|(n, r)| r.as_ref().ok().map(|e| (n.as_str(), issues(e))))
+        .filter(|(_, i)| !i.is_empty())
+        .collect();
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "Driver", "CUDA", "nvidia-fs", "peermem", "IOMMU", "ACS redirect", "GPU links"]);
+            for (node, res) in &results {
+                let mut row = vec![node.clone()];
+                match res {
+                    Ok(e) => {
+                        let acs = match &e.acs_redirect {
+                            None => "unknown".to_string(),
+                            Some(d) if d.is_empty() => "none".to_string(),
+                            Some(d) => d.join("\n"),
+                        };
+                        let links: Vec<String> = e.gpus.iter()
+                            .map(|g| format!("{} x{}/x{} {}/{}", g.bus_id, g.width, g.max_width, g.speed, g.max_speed))
+                            .collect();
+                        let iommu = if e.iommu_cmdline.is_empty() { e.iommu.clone() } else { format!("{} ({})", e.iommu, e.iommu_cmdline.join(" ")) };
+                        row.extend([e.driver.clone(), e.cuda.clone(), e.nvidia_fs.clone(), e.nvidia_peermem.clone(), iommu, acs,
+                            if links.is_empty() { "-".into() } else { links.join("\n") }]);
+                    }
+                    Err(e) => row.push(format!("ERR: {}", e)),
+                }
+                table.add_row(row);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let arr: Vec<_> = results.iter().map(|(node, res)| match res {
+                Ok(e) => {
+                    let mut v = serde_json::json!({ "node": node, "ok": true });
+                    v.as_object_mut().unwrap().extend(serde_json::to_value(e)?.as_object().cloned().unwrap_or_default());
+                    v["issues"] = issues(e).into();
+                    Ok(v)
+                }
+                Err(e) => Ok(serde_json::json!({ "node": node, "ok": false, "error": e })),
+            }).collect::<Result<_, serde_json::Error>>()?;
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+
+    // Warnings
+    for (n, list) in &found {
+        for i in list { log::warn!("{}: {}", n, i); }
+    }
+    for (n, e) in &unreachable { log::warn!("could not capture the GPU environment of {}: {}", n, e); }
+    Ok(if !unreachable.is_empty() { Severity::Failure } else if !found.is_empty() { Severity::Warning } else { Severity::Ok })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE: &str = "DRIVER 550.54.15\nCUDA 12.4\nNVFS 2.20.5\nPEERMEM loaded\n\
+        CMDLINE BOOT_IMAGE=/vmlinuz ro intel_iommu=on iommu=pt quiet\nIOMMU identity \n\
+        GPU 0000:17:00.0|16|16|16.0 GT/s PCIe|16.0 GT/s PCIe\nGPU 0000:65:00.0|8|16|2.5 GT/s PCIe|16.0 GT/s PCIe\n\
+        ACSCTL 0000:16:01.0 \t\tACSCtl:\tSrcValid+ TransBlk- ReqRedir+ CmpltRedir+ UpstreamFwd+\n\
+        ACSCTL 0000:64:01.0 \t\tACSCtl:\tSrcValid- TransBlk- ReqRedir- CmpltRedir- UpstreamFwd-\nACS checked\n";
+
+    #[test]
+    fn parse_and_compare() {
+        let env = parse(PROBE);
+        assert_eq!((env.driver.as_str(), env.nvidia_peermem.as_str(), env.iommu.as_str()), ("550.54.15", "loaded", "passthrough"));
+        assert_eq!(env.iommu_cmdline, vec!["intel_iommu=on", "iommu=pt"]);
+        assert_eq!(env.gpus[1], GpuLink { bus_id: "0000:65:00.0".into(), width: "8".into(), max_width: "16".into(), speed: "2.5 GT/s".into(), max_speed: "16.0 GT/s".into() });
+        assert_eq!(env.acs_redirect, Some(vec!["0000:16:01.0".to_string()]));
+        let found = issues(&env);
+        assert_eq!(found.len(), 2);
+        assert!(found[0].contains("x8 of x16"));
+
+        assert_eq!(parse("IOMMU DMA-FQ identity\nACS unknown\n").iommu, "dma");
+        assert_eq!(parse("IOMMU \n").acs_redirect, None);
+
+        let good = flatten(&env);
+        assert_eq!(good["gpu1.width"], "x8 of x16");
+        let mut other = env.clone();
+        other.gpus.pop();
+        other.driver = "535.161.08".into();
+        let configs = vec![("gpu01", good), ("gpu02", flatten(&other))];
+        let keys: Vec<String> = drift(&configs, Some("gpu01"), &[]).into_iter().map(|d| d.key).collect();
+        assert_eq!(keys, vec!["driver", "gpu1.bus_id", "gpu1.max_speed", "gpu1.width", "gpus"]);
+    }
+}
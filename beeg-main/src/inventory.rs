@@ -0,0 +1,349 @@
+use crate::config::{self, Node};
+use crate::formats;
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum InventoryFormat {
+    /// Ansible INI or YAML inventory
+    Ansible,
+}
+
+/// Groups every inventory has implicitly; not useful as labels.
+const IMPLICIT_GROUPS: &[&str] = &["all", "ungrouped"];
+
+/// Hosts and groups as Ansible sees them, before groups are flattened into labels.
+#[derive(Debug, Default)]
+pub(crate) struct Inventory {
+    /// Inventory name -> `ansible_host`, if set
+    hosts: BTreeMap<String, Option<String>>,
+    members: BTreeMap<String, BTreeSet<String>>,
+    children: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Inventory {
+    fn add_host(&mut self, group: &str, name: &str, address: Option<String>) {
+        let slot = self.hosts.entry(name.to_string()).or_default();
+        if address.is_some() { *slot = address; }
+        self.members.entry(group.to_string()).or_default().insert(name.to_string());
+    }
+
+    /// Groups a host is in, directly or through a parent's `children`.
+    fn groups_of(&self, host: &str) -> BTreeSet<String> {
+        let mut out: BTreeSet<String> = self.members.iter().filter(|(_, m)| m.contains(host)).map(|(g, _)| g.clone()).collect();
+        // parents of groups already found, until nothing new turns up
+        loop {
+            let parents: Vec<String> = self.children.iter()
+                .filter(|(p, kids)| !out.contains(*p) && kids.iter().any(|k| out.contains(k)))
+                .map(|(p, _)| p.clone()).collect();
+            if parents.is_empty() { return out; }
+            out.extend(parents);
+        }
+    }
+
+    pub fn nodes(&self) -> Vec<Node> {
+        self.hosts.iter().map(|(name, address)| Node {
+            name: name.clone(),
+            host: address.clone().unwrap_or_else(|| name.clone()),
+            labels: self.groups_of(name).into_iter().filter(|g| !IMPLICIT_GROUPS.contains(&g.as_str())).collect(),
+            ..Default::default()
+        }).collect()
+    }
+}
+
+/// Expand Ansible host ranges: `node[01:03]` -> node01, node02, node03; `[a:c]` and `[1:9:2]` too.
+pub(crate) fn expand_hosts(pattern: &str) -> Result<Vec<String>> {
+    let (Some(open), Some(close)) = (pattern.find('['), pattern.find(']')) else { return Ok(vec![pattern.to_string()]) };
+    if close < open { bail!("bad host range in '{}'", pattern); }
+    let (head, range, tail) = (&pattern[..open], &pattern[open + 1..close], &pattern[close + 1..]);
+    let parts: Vec<&str> = range.split(':').collect();
+    let (start, end, step) = match parts.as_slice() {
+        [a, b] => (*a, *b, 1),
+        [a, b, s] => (*a, *b, s.parse::<usize>().with_context(|| format!("bad step in '{}'", pattern))?),
+        _ => bail!("bad host range in '{}'", pattern),
+    };
+    let items: Vec<String> = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(a), Ok(b)) if a <= b => {
+            // a leading zero pads every number to the width of the start
+            let width = if start.starts_with('0') { start.len() } else { 0 };
+            (a..=b).step_by(step.max(1)).map(|n| format!("{:0width$}", n, width = width)).collect()
+        }
+        _ => match (start.chars().collect::<Vec<_>>().as_slice(), end.chars().collect::<Vec<_>>().as_slice()) {
+            (&[a], &[b]) if a.is_ascii_alphabetic() && a <= b => (a..=b).step_by(step.max(1)).map(String::from).collect(),
+            _ => bail!("bad host range in '{}'", pattern),
+        },
+    };
+    let mut out = Vec::new();
+    for rest in expand_hosts(tail)? {
+        out.extend(items.iter().map(|i| format!("{}{}{}", head, i, rest)));
+    }
+    Ok(out)
+}
+
+/// `key=value` pairs after the host pattern; values may be quoted.
+fn host_vars(rest: &str) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    let mut chars = rest.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) { chars.next(); }
+        let key: String = std::iter::from_fn(|| chars.next_if(|c| *c != '=' && !c.is_whitespace())).collect();
+        if key.is_empty() { return out; }
+        if chars.next_if_eq(&'=').is_none() { continue; }
+        let value: String = match chars.peek().copied() {
+            Some(q) if q == '"' || q == '\'' => { chars.next(); let v = std::iter::from_fn(|| chars.next_if(|c| *c != q)).collect(); chars.next(); v }
+            _ => std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect(),
+        };
+        out.insert(key, value);
+    }
+}
+
+fn address(vars: &BTreeMap<String, String>) -> Option<String> {
+    vars.get("ansible_host").or_else(|| vars.get("ansible_ssh_host")).cloned()
+}
+
+pub(crate) fn parse_ini(text: &str) -> Result<Inventory> {
+    let mut inv = Inventory::default();
+    let mut group = "ungrouped".to_string();
+    let mut kind = "hosts";
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') { continue; }
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let (name, k) = header.split_once(':').unwrap_or((header, "hosts"));
+            group = name.trim().to_string();
+            kind = match k { "hosts" | "children" | "vars" => k, _ => bail!("line {}: unknown section [{}]", i + 1, header) };
+            inv.members.entry(group.clone()).or_default();
+            continue;
+        }
+        match kind {
+            "children" => { inv.children.entry(group.clone()).or_default().insert(line.to_string()); }
+            "vars" => {}
+            _ => {
+                let (pattern, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+                let vars = host_vars(rest);
+                for host in expand_hosts(pattern).with_context(|| format!("line {}", i + 1))? {
+                    inv.add_host(&group, &host, address(&vars));
+                }
+            }
+        }
+    }
+    Ok(inv)
+}
+
+fn yaml_group(inv: &mut Inventory, name: &str, group: &serde_json::Value) -> Result<()> {
+    inv.members.entry(name.to_string()).or_default();
+    if group.is_null() { return Ok(()); }
+    let group = group.as_object().with_context(|| format!("group '{}' is not a mapping", name))?;
+    if let Some(hosts) = group.get("hosts").and_then(|h| h.as_object()) {
+        for (pattern, vars) in hosts {
+            let vars: BTreeMap<String, String> = vars.as_object().into_iter().flatten()
+                .map(|(k, v)| (k.clone(), v.as_str().map(String::from).unwrap_or_else(|| v.to_string()))).collect();
+            for host in expand_hosts(pattern)? { inv.add_host(name, &host, address(&vars)); }
+        }
+    }
+    if let Some(children) = group.get("children").and_then(|c| c.as_object()) {
+        for (child, body) in children {
+            inv.children.entry(name.to_string()).or_default().insert(child.clone());
+            yaml_group(inv, child, body)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_yaml(text: &str) -> Result<Inventory> {
+    let doc = formats::parse_yaml(text)?;
+    let mut inv = Inventory::default();
+    match &doc {
+        serde_json::Value::Object(groups) => for (name, body) in groups { yaml_group(&mut inv, name, body)?; },
+        serde_json::Value::Null => {}
+        _ => bail!("a YAML inventory is a mapping of groups"),
+    }
+    Ok(inv)
+}
+
+/// INI unless the file says YAML by extension or by a `group:` first line.
+fn is_yaml(path: &Path, text: &str) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("yml") | Some("yaml") => true,
+        Some("ini") | Some("cfg") => false,
+        _ => text.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with(';'))
+            .is_some_and(|l| l.ends_with(':') && !l.starts_with('[')),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    Added,
+    Updated,
+    Unchanged,
+    Removed,
+    Kept,
+}
+
+impl Change {
+    fn as_str(self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Updated => "updated",
+            Change::Unchanged => "unchanged",
+            Change::Removed => "removed",
+            Change::Kept => "kept (not in inventory)",
+        }
+    }
+}
+
+/// Merge inventory nodes into `existing` by name, taking host and labels from the inventory and
+/// keeping SSH settings; nodes missing from the inventory are dropped with `replace`, kept otherwise.
+fn merge(existing: &[Node], imported: Vec<Node>, replace: bool) -> (Vec<Node>, Vec<(Node, Change)>) {
+    let mut changes = Vec::new();
+    let mut merged = Vec::new();
+    let mut imported: BTreeMap<String, Node> = imported.into_iter().map(|n| (n.name.clone(), n)).collect();
+    for old in existing {
+        match imported.remove(&old.name) {
+            Some(new) => {
+                let change = if new.host == old.host && new.labels == old.labels { Change::Unchanged } else { Change::Updated };
+                let node = Node { host: new.host, labels: new.labels, ..old.clone() };
+                changes.push((node.clone(), change));
+                merged.push(node);
+            }
+            None if replace => changes.push((old.clone(), Change::Removed)),
+            None => { changes.push((old.clone(), Change::Kept)); merged.push(old.clone()); }
+        }
+    }
+    for new in imported.into_values() {
+        changes.push((new.clone(), Change::Added));
+        merged.push(new);
+    }
+    (merged, changes)
+}
+
+pub fn import(cli: &crate::Cli, config_path: &Path, inventory: &Path, format: InventoryFormat, replace: bool, dry_run: bool) -> Result<()> {
+    let text = std::fs::read_to_string(inventory).with_context(|| format!("reading inventory: {}", inventory.display()))?;
+    let inv = match format {
+        InventoryFormat::Ansible if is_yaml(inventory, &text) => parse_yaml(&text),
+        InventoryFormat::Ansible => parse_ini(&text),
+    }.with_context(|| format!("parsing inventory: {}", inventory.display()))?;
+    let imported = inv.nodes();
+    if imported.is_empty() { bail!("no hosts in {}", inventory.display()); }
+
+    let (mut value, config_format) = config::read_value(config_path)?;
+    let existing: Vec<Node> = match value.get("nodes") {
+        Some(n) => serde_json::from_value(n.clone()).with_context(|| format!("reading nodes from {}", config_path.display()))?,
+        None => Vec::new(),
+    };
+    let (merged, changes) = merge(&existing, imported, replace);
+    let changed = changes.iter().any(|(_, c)| matches!(c, Change::Added | Change::Updated | Change::Removed));
+    if changed && !dry_run {
+        value.as_object_mut().context("config file is not a mapping")?.insert("nodes".into(), serde_json::to_value(&merged)?);
+        config::write_value(config_path, &value, config_format)?;
+    }
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "Host", "Labels", "Change"]);
+            for (n, c) in &changes { table.add_row(vec![n.name.clone(), n.host.clone(), n.labels.join(", "), c.as_str().to_string()]); }
+            crate::tables::print(cli.output, &table);
+            let count = |c: Change| changes.iter().filter(|(_, x)| *x == c).count();
+            crate::tables::note(cli.output, &format!(
+                "{} added, {} updated, {} removed, {} unchanged; {}",
+                count(Change::Added), count(Change::Updated), count(Change::Removed), count(Change::Unchanged),
+                if dry_run { "dry run, nothing written".to_string() }
+                else if changed { format!("wrote {}", config_path.display()) }
+                else { format!("{} is up to date", config_path.display()) },
+            ));
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "config": config_path.display().to_string(),
+                "inventory": inventory.display().to_string(),
+                "written": changed && !dry_run,
+                "nodes": changes.iter().map(|(n, c)| serde_json::json!({"name": n.name, "host": n.host, "labels": n.labels, "change": c.as_str()})).collect::<Vec<_>>(),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    let kept: Vec<&str> = changes.iter().filter(|(_, c)| *c == Change::Kept).map(|(n, _)| n.name.as_str()).collect();
+    if !kept.is_empty() { log::warn!("{} node(s) in the config are not in the inventory (use --replace to drop them): {}", kept.len(), kept.join(", ")); }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ini_inventory() {
+        let inv = parse_ini("# BeeGFS hosts
+bastion ansible_host=192.0.2.1
+
+[storage]
+stor[01:03].example ansible_host=\"10.0.1.1\"
+
+[meta]
+meta01 ansible_host=10.0.2.1 ansible_user=root
+
+[beegfs:children]
+storage
+meta
+
+[beegfs:vars]
+ansible_become=true
+").unwrap();
+        let nodes = inv.nodes();
+        assert_eq!(nodes.len(), 5);
+        assert_eq!(nodes[0].name, "bastion");
+        assert!(nodes[0].labels.is_empty());
+        let meta = nodes.iter().find(|n| n.name == "meta01").unwrap();
+        assert_eq!(meta.host, "10.0.2.1");
+        assert_eq!(meta.labels, vec!["beegfs", "meta"]);
+        assert!(nodes.iter().any(|n| n.name == "stor03.example"));
+        assert!(parse_ini("[x:bogus]\n").is_err());
+    }
+
+    #[test]
+    fn yaml_inventory() {
+        let inv = parse_yaml("all:
+  hosts:
+    mgmt01:
+  children:
+    clients:
+      hosts:
+        gpu[a:b]:
+          ansible_host: 10.0.3.1
+      children:
+        dgx:
+          hosts:
+            dgx01: { ansible_host: 10.0.4.1 }
+").unwrap();
+        let nodes = inv.nodes();
+        let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["dgx01", "gpua", "gpub", "mgmt01"]);
+        assert_eq!(nodes[0].labels, vec!["clients", "dgx"]);
+        assert_eq!(nodes[0].host, "10.0.4.1");
+        assert_eq!(nodes[3].host, "mgmt01");
+    }
+
+    #[test]
+    fn ranges_and_merge() {
+        assert_eq!(expand_hosts("n[8:10]").unwrap(), vec!["n8", "n9", "n10"]);
+        assert_eq!(expand_hosts("r[1:2]n[01:02]").unwrap(), vec!["r1n01", "r2n01", "r1n02", "r2n02"]);
+        assert_eq!(expand_hosts("x[0:4:2]").unwrap(), vec!["x0", "x2", "x4"]);
+        assert!(expand_hosts("bad[3:1]").is_err());
+
+        let node = |name: &str, host: &str| Node { name: name.into(), host: host.into(), ..Default::default() };
+        let (merged, changes) = merge(&[node("a", "1"), node("b", "2")], vec![node("b", "3"), node("c", "4")], false);
+        assert_eq!(merged.iter().map(|n| n.name.as_str()).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(changes.iter().map(|(_, c)| *c).collect::<Vec<_>>(), vec![Change::Kept, Change::Updated, Change::Added]);
+        let tuned = Node { ssh_port: Some(2222), ..node("b", "2") };
+        assert_eq!(merge(&[tuned], vec![node("b", "3")], false).0[0].ssh_port, Some(2222));
+        let (merged, _) = merge(&[node("a", "1")], vec![node("c", "4")], true);
+        assert_eq!(merged.len(), 1);
+    }
+}
@@ -0,0 +1,223 @@
+//! `beeg logs collect`: BeeGFS logs, journal excerpts and kernel messages from every selected
+//! node, packed on the node and copied back with `transfer::fetch_file` as one tarball per
+//! node for support tickets.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use comfy_table::{presets::UTF8_FULL, Cell, Table};
+use shell_escape::escape;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::checks::Severity;
+use crate::timefmt::TimeRange;
+use crate::{config, transfer, transport};
+
+/// Kernel messages kept next to the BeeGFS logs: the fabric, GPUs, disks and stuck tasks.
+const DMESG_PATTERN: &str = "beegfs|rdma|infiniband|mlx[45]|ib_|nvidia|nvme|oom|hung_task|blocked for more than|I/O error|EXT4-fs|XFS";
+
+pub(crate) struct CollectOptions {
+    pub selector: String,
+    pub range: TimeRange,
+    /// Local directory the tarballs land in
+    pub out: PathBuf,
+    /// Newest bytes kept of each log file
+    pub max_file_size: u64,
+}
+
+/// Source bytes per chunk when copying a packed tarball back.
+const CHUNK_SIZE: u64 = 64 << 20;
+
+/// One node's tarball, or why there is none.
+pub(crate) struct Bundle {
+    pub node: String,
+    pub path: PathBuf,
+    pub result: Result<u64, String>,
+    /// Log files cut to `--max-file-size`: path in the tarball and the bytes dropped from the front
+    pub truncated: Vec<(String, u64)>,
+}
+
+/// `journalctl` arguments for the window; the current boot when no start is given.
+fn journal_window(range: &TimeRange) -> String {
+    match (range.since, range.until) {
+        (None, _) => "-b".to_string(),
+        (Some(s), None) => format!("--since @{}", s.timestamp()),
+        (Some(s), Some(u)) => format!("--since @{} --until @{}", s.timestamp(), u.timestamp()),
+    }
+}
+
+/// Shell commands that gather logs into `$d`: `logs/` with the log files under
+/// /var/log/beegfs-* and any `logStdFile` set in /etc/beegfs, rotated ones included (with
+/// `--since`, files last written before it are left out), `journal.txt` and `dmesg.txt`.
+/// What could not be read is added to `$d/errors.txt`. A file over `max_file_size` keeps its
+/// newest bytes under a `[truncated: first N bytes dropped]` line, and is listed in
+/// `$d/truncated.txt` and as a `TRUNCATED <path> <N>` line on stdout.
+pub(crate) fn log_commands(range: &TimeRange, max_file_size: u64) -> String {
+    let newer = range.since.map(|s| format!(" && [ -n \"$(find \"$f\" -newermt @{} 2>/dev/null)\" ]", s.timestamp())).unwrap_or_default();
+    let window = journal_window(range);
+    format!(
+        "mkdir -p \"$d/logs\"\n\
+         for f in $( {{ ls -d /var/log/beegfs-*; for l in $(sed -n 's/^[[:space:]]*logStdFile[[:space:]]*=[[:space:]]*//p' /etc/beegfs/*.conf); do ls -d \"$l\"*; done; }} 2>/dev/null | sort -u ); do\n\
+         \x20 [ -f \"$f\" ]{newer} || continue\n\
+         \x20 if [ ! -r \"$f\" ]; then echo \"$f: permission denied\" >> \"$d/errors.txt\"; continue; fi\n\
+         \x20 o=\"logs/$(basename \"$f\")\"; drop=$(( $(stat -L -c %s \"$f\") - {max} ))\n\
+         \x20 if [ \"$drop\" -gt 0 ]; then\n\
+         \x20   {{ echo \"[truncated: first $drop bytes dropped]\"; tail -c {max} \"$f\"; }} > \"$d/$o\"\n\
+         \x20   echo \"$o $drop\" >> \"$d/truncated.txt\"; echo \"TRUNCATED $o $drop\"\n\
+         \x20 else cat \"$f\" > \"$d/$o\"; fi\n\
+         done\n\
+         if command -v journalctl >/dev/null 2>&1; then\n\
+         \x20 journalctl --no-pager -o short-iso {window} -u 'beegfs-*' > \"$d/journal.txt\" 2>> \"$d/errors.txt\"\n\
+         \x20 journalctl --no-pager -o short-iso {window} -k 2>> \"$d/errors.txt\" | grep -Ei {pattern} > \"$d/dmesg.txt\"\n\
+         else\n\
+         \x20 dmesg -T 2>> \"$d/errors.txt\" | grep -Ei {pattern} > \"$d/dmesg.txt\"\n\
+         fi\n",
+        newer = newer,
+        max = max_file_size,
+        window = window,
+        pattern = escape(DMESG_PATTERN.into()),
+    )
+}
+
+/// Wrap `body` so it runs in a fresh temporary directory `$d` on the node, starting with an
+/// `info.txt` (host, kernel, time of collection and window). The directory is packed into
+/// `$d.tar`, readable only by the SSH user, whose path is printed as `BUNDLE <path>`, and removed.
+pub(crate) fn packed(range: &TimeRange, body: &str) -> String {
+    let show = |t: Option<DateTime<Utc>>| t.map(crate::timefmt::rfc3339).unwrap_or_else(|| "-".into());
+    format!(
+        "d=$(mktemp -d /tmp/beeg-logs.XXXXXX) || exit 1\n\
+         trap 'rm -rf \"$d\"' EXIT\n\
+         {{ echo \"host: $(hostname)\"; echo \"collected: $(date -u +%Y-%m-%dT%H:%M:%SZ)\"; echo \"since: {since}\"; echo \"until: {until}\"; uname -a; }} > \"$d/info.txt\"\n\
+         {body}\
+         (umask 077 && tar -cf \"$d.tar\" -C \"$d\" .) && echo \"BUNDLE $d.tar\"",
+        since = show(range.since),
+        until = show(range.until),
+        body = body,
+    )
+}
+
+/// The tarball `packed` left on the node and the files it cut, from the script's stdout.
+fn parse_packed(stdout: &str) -> (Option<String>, Vec<(String, u64)>) {
+    let mut bundle = None;
+    let mut truncated = Vec::new();
+    for line in stdout.lines() {
+        if let Some(path) = line.strip_prefix("BUNDLE ") {
+            // only ever remove what `packed` made
+            if path.starts_with("/tmp/beeg-logs.") && path.ends_with(".tar") { bundle = Some(path.to_string()); }
+        } else if let Some(rest) = line.strip_prefix("TRUNCATED ") {
+            if let Some((file, n)) = rest.rsplit_once(' ').and_then(|(f, n)| Some((f, n.parse().ok()?))) {
+                truncated.push((file.to_string(), n));
+            }
+        }
+    }
+    (bundle, truncated)
+}
+
+/// Run `script(node)` (built with `packed`) on every node in parallel and copy the tarball it
+/// leaves back with `transfer::fetch_file`, into `path(node)` plus `.zst` or `.gz`. The tarball
+/// on the node is removed afterwards. A failure on one node does not stop the others and
+/// leaves no partial file behind.
+pub(crate) fn fetch_all(
+    cfg: &config::Config,
+    nodes: &[&config::Node],
+    script: impl Fn(&config::Node) -> String + Sync,
+    path: impl Fn(&config::Node) -> PathBuf + Sync,
+) -> Vec<Bundle> {
+    let opts = transfer::FetchOptions { chunk_size: CHUNK_SIZE, max_size: None, progress: false };
+    crate::checks::per_node(cfg, nodes, |tr, n| {
+        let out = tr.exec(&n.host, &script(n)).map_err(|e| format!("{:#}", e))?;
+        let (remote, truncated) = parse_packed(&out.stdout);
+        let Some(remote) = remote else {
+            return Err(out.stderr.lines().map(str::trim).rfind(|l| !l.is_empty()).unwrap_or("packing the logs failed").to_string());
+        };
+        let dest = path(n);
+        let fetched = transfer::fetch_file(tr, &n.host, &remote, &dest, &opts);
+        // removed even when Ctrl-C cut the copy short
+        let cleanup = transport::from_config(&transport::cancel::detached(cfg));
+        let _ = cleanup.exec(&n.host, &format!("rm -f {}", escape(remote.into())));
+        match fetched {
+            Ok(r) => Ok(Bundle { node: n.name.clone(), path: r.local, result: Ok(r.compressed), truncated }),
+            Err(e) => {
+                // a half-written tarball would only confuse whoever opens the ticket
+                for ext in ["zst", "gz"] {
+                    let _ = fs::remove_file(format!("{}.{}", dest.display(), ext));
+                    let _ = fs::remove_file(format!("{}.{}.part.json", dest.display(), ext));
+                }
+                Err(format!("{:#}", e))
+            }
+        }
+    }).into_iter().map(|(n, b, _)| b.unwrap_or_else(|e| Bundle { node: n.name.clone(), path: PathBuf::new(), result: Err(e), truncated: Vec::new() })).collect()
+}
+
+/// Collect a bundle from every node in `opts.selector` in parallel, into
+/// `<out>/<node>-<time>.tar.zst` (`.tar.gz` from nodes without zstd).
+pub fn collect(cli: &crate::Cli, cfg: &config::Config, opts: &CollectOptions) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, &opts.selector);
+    if nodes.is_empty() { anyhow::bail!("selector '{}' matched no nodes", opts.selector); }
+    fs::create_dir_all(&opts.out).with_context(|| format!("creating {}", opts.out.display()))?;
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let script = packed(&opts.range, &log_commands(&opts.range, opts.max_file_size));
+    let mut bundles = fetch_all(cfg, &nodes, |_| script.clone(), |n| opts.out.join(format!("{}-{}.tar", n.name, stamp)));
+    config::sort_rows(cfg, cli.sort, &mut bundles, |b| &b.node);
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "Bundle", "Size", "Truncated", "Status"]);
+            for b in &bundles {
+                let cut = if b.truncated.is_empty() { "-".to_string() } else { b.truncated.iter().map(|(f, _)| f.as_str()).collect::<Vec<_>>().join(", ") };
+                let (path, size, status) = match &b.result {
+                    Ok(n) => (b.path.display().to_string(), crate::transfer::format_size(*n), crate::tables::status_cell("OK", Severity::Ok)),
+                    Err(e) => ("-".into(), "-".into(), crate::tables::status_cell(format!("ERR: {}", e), Severity::Failure)),
+                };
+                table.add_row(vec![Cell::new(&b.node), Cell::new(path), Cell::new(size), Cell::new(cut), status]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let arr: Vec<_> = bundles.iter().map(|b| match &b.result {
+                Ok(n) => serde_json::json!({"node": b.node, "ok": true, "bundle": b.path.display().to_string(), "bytes": n, "truncated": truncated_json(&b.truncated)}),
+                Err(e) => serde_json::json!({"node": b.node, "ok": false, "error": e}),
+            }).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+
+    let failed: Vec<&str> = bundles.iter().filter(|b| b.result.is_err()).map(|b| b.node.as_str()).collect();
+    if !failed.is_empty() { log::warn!("no bundle from {} node(s): {}", failed.len(), failed.join(", ")); }
+    Ok(if failed.is_empty() { Severity::Ok } else { Severity::Failure })
+}
+
+/// `[{path, dropped_bytes}]` for a manifest or `--output json`.
+pub(crate) fn truncated_json(files: &[(String, u64)]) -> serde_json::Value {
+    files.iter().map(|(f, n)| serde_json::json!({"path": f, "dropped_bytes": n})).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_window() {
+        let since = DateTime::from_timestamp(1_700_000_000, 0);
+        let range = TimeRange { since, until: None };
+        let script = packed(&range, &log_commands(&range, 1 << 20));
+        assert!(script.contains("-newermt @1700000000"));
+        assert!(script.contains("journalctl --no-pager -o short-iso --since @1700000000 -u 'beegfs-*'"));
+        assert!(script.contains("tail -c 1048576") && script.contains("[truncated: first $drop bytes dropped]"));
+        assert!(script.contains("echo \"BUNDLE $d.tar\""));
+        assert!(script.contains("since: 2023-11-14T22:13:20Z"));
+        // no start: every log file, and the journal of the current boot
+        let script = log_commands(&TimeRange { since: None, until: None }, 1 << 20);
+        assert!(!script.contains("newermt") && script.contains("short-iso -b -k"));
+    }
+
+    #[test]
+    fn packed_output() {
+        let (bundle, truncated) = parse_packed("TRUNCATED logs/beegfs-storage.log 1024\nTRUNCATED logs/my log 7\nBUNDLE /tmp/beeg-logs.Ab12Cd.tar\n");
+        assert_eq!(bundle.as_deref(), Some("/tmp/beeg-logs.Ab12Cd.tar"));
+        assert_eq!(truncated, vec![("logs/beegfs-storage.log".to_string(), 1024), ("logs/my log".to_string(), 7)]);
+        assert_eq!(parse_packed("BUNDLE /etc/passwd\n").0, None);
+    }
+}
@@ -1,35 +1,84 @@
-use clap::{Args, Parser, Subcommand, ValueEnum, CommandFactory};
+use clap::{Args, Parser, Subcommand, ValueEnum, CommandFactory, FromArgMatches};
 use clap_complete::{generate_to, Shell};
 use std::path::PathBuf;
 use std::fs;
+use std::process::ExitCode;
 
-mod config;
-mod transport;
-mod checks;
+use beeg_core::{beegconf, beegfs, checks, config, formats, history, identity, journal, logging, notes, prompt, selfupdate, sshconfig, state, tables, telemetry, timefmt, transfer, transport, GlobalArgs, Output};
+#[cfg(feature = "daemon")]
+use beeg_core::alerts;
+#[cfg(feature = "tui")]
+use beeg_core::tui;
+
+mod baseline;
+mod bench;
+#[cfg(feature = "daemon")]
+mod daemon;
+mod drain;
+#[cfg(feature = "tui")]
+mod dashboard;
+mod entry;
+#[cfg(feature = "exporter")]
+mod export;
+mod facts;
+mod fsck;
+mod gpuenv;
+mod inventory;
+mod logs;
+mod migrate;
+mod mount;
+mod plan;
+mod completions;
+mod quota;
+mod resync;
+mod rollout;
+mod service;
+mod status;
+mod support;
+mod verify;
 
 #[derive(Debug, Parser)]
 #[command(name = "beeg", version, about = "BeegFS CLI assistant", long_about = None)]
 struct Cli {
-    /// Increase output verbosity (-v, -vv)
-    #[arg(short, long, action = clap::ArgAction::Count)]
-    verbose: u8,
-
-    /// Output format
-    #[arg(long, value_enum, default_value_t = Output::Human)]
-    output: Output,
+    #[command(flatten)]
+    global: GlobalArgs,
 
     /// Config file to use (for node inventory, auth, etc.)
     #[arg(short, long)]
     config: Option<PathBuf>,
 
+    /// Reach nodes through this SSH bastion (node name or [user@]host[:port]); overrides `ssh_jump_host`
+    #[arg(long, global = true, value_name = "HOST")]
+    jump_host: Option<String>,
+
+    /// Give up on a remote command after this many seconds, connecting included; overrides `exec_timeout`
+    #[arg(long, global = true, value_name = "SECS")]
+    exec_timeout: Option<u64>,
+
+    /// Try again this often, with growing pauses, when a node is unreachable or times out; overrides `exec_retries`
+    #[arg(long, global = true, value_name = "N")]
+    retries: Option<u32>,
+
+    /// Probe at most this many nodes at once; overrides `exec_parallel` (default 32)
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    parallel: Option<u64>,
+
+    /// Read targets, nodes, mirror groups and pools over ssh (beegfs-ctl) or with the `beegfs` tool on this machine; overrides `backend`
+    #[arg(long, global = true, value_enum, value_name = "BACKEND")]
+    backend: Option<config::Backend>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum Output {
-    Human,
-    Json,
+/// The display options live in `beeg_core`, where the checks read them.
+impl std::ops::Deref for Cli {
+    type Target = GlobalArgs;
+    fn deref(&self) -> &GlobalArgs { &self.global }
+}
+
+impl std::ops::DerefMut for Cli {
+    fn deref_mut(&mut self) -> &mut GlobalArgs { &mut self.global }
 }
 
 #[derive(Debug, Subcommand)]
@@ -37,6 +86,9 @@ enum Commands {
     /// Show a summarized status view (cluster or node)
     Status(StatusArgs),
 
+    /// Full-screen cluster dashboard: nodes, services, targets and capacity, refreshed live
+    Dashboard(DashboardArgs),
+
     /// Node-oriented actions
     #[command(subcommand)]
     Node(NodeCmd),
@@ -45,271 +97,2016 @@ enum Commands {
     #[command(subcommand)]
     Config(ConfigCmd),
 
+    /// Start, stop or restart a BeeGFS service on nodes a batch at a time, or show its state
+    Service(ServiceArgs),
+
+    /// Rolling operations across nodes, gated on cluster health
+    #[command(subcommand)]
+    Rollout(RolloutCmd),
+
+    /// Put a BeeGFS client mount in place on nodes, or take it away
+    #[command(subcommand)]
+    Mount(MountCmd),
+
+    /// Buddy mirror resyncs
+    #[command(subcommand)]
+    Resync(ResyncCmd),
+
+    /// File system consistency checks with beegfs-fsck
+    #[command(subcommand)]
+    Fsck(FsckCmd),
+
     /// Generate shell completion files
     Completions(CompletionsArgs),
 
     /// Cluster checks
     #[command(subcommand)]
     Check(checks::CheckCmd),
+
+    /// Transport diagnostics
+    #[command(subcommand)]
+    Transport(TransportCmd),
+
+    /// Operator notes on nodes and targets, shown next to warnings about them
+    #[command(subcommand)]
+    Note(NoteCmd),
+
+    /// BeegFS quota usage
+    #[command(subcommand)]
+    Quota(QuotaCmd),
+
+    /// File and directory placement
+    #[command(subcommand)]
+    Entry(EntryCmd),
+
+    /// Data integrity verification
+    #[command(subcommand)]
+    Verify(VerifyCmd),
+
+    /// Data migration checks
+    #[command(subcommand)]
+    Migrate(MigrateCmd),
+
+    /// Capacity planning
+    #[command(subcommand)]
+    Plan(PlanCmd),
+
+    /// OpenSSH client config for the inventory
+    #[command(subcommand)]
+    SshConfig(SshConfigCmd),
+
+    /// Manage the beeg binary itself
+    #[command(name = "self", subcommand)]
+    SelfCmd(SelfCmd),
+
+    /// Opt-in usage metrics: which commands run and how long they take
+    #[command(subcommand)]
+    Usage(UsageCmd),
+
+    /// Continue an interrupted check run
+    #[command(subcommand)]
+    Resume(ResumeCmd),
+
+    /// Expose check results to monitoring systems
+    #[command(subcommand)]
+    Export(ExportCmd),
+
+    /// Gather node logs for support tickets
+    #[command(subcommand)]
+    Logs(LogsCmd),
+
+    /// Pack configs, versions, listings, network info and logs of all nodes into one archive
+    SupportBundle(SupportBundleArgs),
+
+    /// Benchmark the storage targets, clients or metadata
+    #[command(subcommand)]
+    Bench(BenchCmd),
+
+    /// Keep check or benchmark results under a name and compare later runs with them
+    #[command(subcommand)]
+    Baseline(BaselineCmd),
+
+    /// Past check results per node: when a check started failing and for how long
+    #[command(subcommand)]
+    History(HistoryCmd),
+
+    /// Run checks on an interval, log the results, keep their history and report status changes
+    Daemon(DaemonArgs),
 }
 
 #[derive(Debug, Args)]
-struct StatusArgs {
-    /// Optional node selector (name, ip, label)
-    #[arg(short, long)]
-    selector: Option<String>,
+struct DaemonArgs {
+    /// Checks to run each round, e.g. ofed,client-mount,storage-target
+    #[arg(long, value_delimiter = ',', required = true)]
+    checks: Vec<String>,
+    /// Time between the starts of two rounds: 30s, 5m, 1h or plain seconds
+    #[arg(long, value_parser = parse_interval, default_value = "5m")]
+    interval: u64,
+    /// Run one round and exit with its exit code (e.g. from cron)
+    #[arg(long)]
+    once: bool,
+    /// Nodes for the checks that take a selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Management node for the checks that query one (meta, capacity, pool-limits, buddy-groups)
+    #[arg(long)]
+    mgmt_node: Option<String>,
+    /// Node to run storage-target from
+    #[arg(long)]
+    storage_node: Option<String>,
+    /// Target IDs for storage-target: comma-separated or 'all'
+    #[arg(long, default_value = "all")]
+    targets: String,
+    /// Client mountpoint for client-mount
+    #[arg(long)]
+    mount: Option<String>,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+    /// POST each status change of a node (e.g. OK -> FAIL) as JSON to this URL; repeatable
+    #[arg(long)]
+    webhook: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum HistoryCmd {
+    /// Recorded check results, newest last
+    List(HistoryListArgs),
+    /// Status changes over time per node, e.g. `history show --check nvidia-driver --node node-3`
+    Show(HistoryShowArgs),
 }
 
 #[derive(Debug, Args)]
-struct CompletionsArgs {
-    /// Shell to generate completions for (default: all)
-    #[arg(long, value_enum)]
-    shell: Option<CompShell>,
-    /// Output directory to write completion files
+struct HistoryFilterArgs {
+    /// Only this check, e.g. nvidia-driver
     #[arg(long)]
-    dir: Option<PathBuf>,
+    check: Option<String>,
+    /// Only this node
+    #[arg(long)]
+    node: Option<String>,
+    #[command(flatten)]
+    range: timefmt::TimeRangeArgs,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum CompShell { Bash, Zsh, Fish, PowerShell, Elvish }
+impl HistoryFilterArgs {
+    fn filter(&self) -> anyhow::Result<history::Filter> {
+        Ok(history::Filter { check: self.check.clone(), node: self.node.clone(), range: Some(self.range.resolve()?) })
+    }
+}
+
+#[derive(Debug, Args)]
+struct HistoryListArgs {
+    #[command(flatten)]
+    filter: HistoryFilterArgs,
+    /// Only results that were not OK
+    #[arg(long)]
+    failing: bool,
+    /// Show at most this many results, the latest
+    #[arg(long, default_value_t = 50)]
+    limit: usize,
+}
+
+#[derive(Debug, Args)]
+struct HistoryShowArgs {
+    #[command(flatten)]
+    filter: HistoryFilterArgs,
+}
 
 #[derive(Debug, Subcommand)]
-enum NodeCmd {
-    /// List known nodes
+enum BaselineCmd {
+    /// Run a beeg command and keep its JSON result, e.g. `baseline save pre-fw -- check versions`
+    Save(BaselineSaveArgs),
+    /// Run the saved command again and show what changed, flagging regressions
+    Compare(BaselineCompareArgs),
+    /// List saved baselines
     List,
-    /// Execute a read-only command on nodes
-    Exec(ExecArgs),
+    /// Remove a saved baseline
+    Rm(BaselineRmArgs),
 }
 
 #[derive(Debug, Args)]
-struct ExecArgs {
-    /// Node selector: name/ip/label, or 'all'
-    #[arg(short, long, default_value = "all")]
-    selector: String,
-    /// Command to run (read-only diagnostics)
+struct BaselineSaveArgs {
+    /// Name to save it under
+    name: String,
+    /// Replace an existing baseline of that name
+    #[arg(long)]
+    force: bool,
+    /// The beeg command to run, without `beeg` and `--output`
     #[arg(last = true, required = true)]
-    cmd: Vec<String>,
+    command: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+struct BaselineCompareArgs {
+    name: String,
+    /// Throughputs and rates down by this percentage or more are regressions; smaller moves are ignored
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u8).range(1..=100))]
+    threshold: u8,
+}
+
+#[derive(Debug, Args)]
+struct BaselineRmArgs {
+    name: String,
 }
 
 #[derive(Debug, Subcommand)]
-enum ConfigCmd {
-    /// Get a config value by key
-    Get(ConfigGetArgs),
-    /// Set a config value by key
-    Set(ConfigSetArgs),
+enum BenchCmd {
+    /// Throughput of every storage target via beegfs-ctl --storagebench, flagging slow ones
+    Storage(BenchStorageArgs),
+    /// Write and read throughput and IOPS through the mount, from many clients at once
+    Client(BenchClientArgs),
+    /// File create, stat and unlink rates through the mount, with mdtest or a built-in workload
+    Meta(BenchMetaArgs),
 }
 
 #[derive(Debug, Args)]
-struct ConfigGetArgs {
-    /// Config key to read
+struct BenchMetaArgs {
+    /// BeeGFS mountpoint on the clients (e.g., /mnt/beegfs)
     #[arg(long)]
-    key: String,
-    /// Optional node selector
-    #[arg(short, long)]
-    selector: Option<String>,
+    mount: String,
+    /// Clients to run on: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    #[arg(long, value_enum, default_value_t = bench::meta::Mode::Auto)]
+    mode: bench::meta::Mode,
+    /// Files each client creates, stats and removes
+    #[arg(long, default_value_t = 100_000, value_parser = clap::value_parser!(u64).range(1..))]
+    files: u64,
+    /// Lowest acceptable total rate of an operation, e.g. `create=5000` (repeatable)
+    #[arg(long, value_parser = bench::meta::parse_min)]
+    min: Vec<(String, f64)>,
+    /// Flag clients and the total more than this percentage below the baseline
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u8).range(1..=100))]
+    regress_percent: u8,
+    /// Keep this run as the baseline for later runs with the same mount, files and tool
+    #[arg(long)]
+    save_baseline: bool,
+    /// Timeout seconds for each client's run
+    #[arg(long, default_value_t = 1800)]
+    timeout: u64,
 }
 
 #[derive(Debug, Args)]
-struct ConfigSetArgs {
-    /// Config key to write
+struct BenchClientArgs {
+    /// BeeGFS mountpoint on the clients (e.g., /mnt/beegfs)
     #[arg(long)]
-    key: String,
-    /// New value
+    mount: String,
+    /// Clients to run on: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    #[arg(long, value_enum, default_value_t = bench::client::Mode::Fio)]
+    mode: bench::client::Mode,
+    /// Block size of each request
+    #[arg(long, value_parser = transfer::parse_size, default_value = "1M")]
+    blocksize: u64,
+    /// Data each client writes, then reads back
+    #[arg(long, value_parser = transfer::parse_size, default_value = "4G")]
+    size: u64,
+    /// Requests in flight per client (fio only)
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..))]
+    iodepth: u32,
+    /// Flag clients and the total more than this percentage below the baseline
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u8).range(1..=100))]
+    regress_percent: u8,
+    /// Keep this run as the baseline for later runs with the same mount, mode and sizes
     #[arg(long)]
-    value: String,
-    /// Optional node selector
-    #[arg(short, long)]
-    selector: Option<String>,
-    /// Require an explicit confirmation for write operations
-    #[arg(long, default_value_t = true)]
-    confirm: bool,
+    save_baseline: bool,
+    /// Timeout seconds for each client's write or read
+    #[arg(long, default_value_t = 900)]
+    timeout: u64,
 }
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+#[derive(Debug, Args)]
+struct BenchStorageArgs {
+    /// Node to run beegfs-ctl on (name/host/label) [default: the first mgmtd in the config]
+    #[arg(long)]
+    node: Option<String>,
+    /// Target IDs: comma-separated or 'all'
+    #[arg(long, default_value = "all")]
+    targets: String,
+    /// Block size of each request
+    #[arg(long, value_parser = transfer::parse_size, default_value = "1M")]
+    blocksize: u64,
+    /// Data written or read per thread and target
+    #[arg(long, value_parser = transfer::parse_size, default_value = "10G")]
+    size: u64,
+    /// Threads per target
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+    #[arg(long, value_enum, default_value_t = bench::storage::Mode::Both)]
+    mode: bench::storage::Mode,
+    /// Seconds between status polls
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+    /// Seconds a write or read run may take before it is stopped
+    #[arg(long, default_value_t = 3600)]
+    max_time: u64,
+    /// Flag targets below this percentage of the median throughput
+    #[arg(long, default_value_t = 75, value_parser = clap::value_parser!(u8).range(1..=100))]
+    slow_percent: u8,
+    /// Leave the benchmark files on the targets, e.g. for a later --mode read
+    #[arg(long)]
+    keep_files: bool,
+    /// Timeout seconds per beegfs-ctl call
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
 
-    // Load configuration once; many commands need it
-    let cfg = config::load(cli.config.as_ref())?;
+#[derive(Debug, Args)]
+struct SupportBundleArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    #[command(flatten)]
+    range: timefmt::TimeRangeArgs,
+    /// Archive to write [default: beeg-support-<time>.tar.gz]
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Node to run the beegfs-ctl listings on [default: the first mgmtd among the nodes]
+    #[arg(long)]
+    mgmt_node: Option<String>,
+    /// Leave out logs, journal and kernel messages
+    #[arg(long)]
+    no_logs: bool,
+    /// Keep at most this much of each log file, the newest part
+    #[arg(long, value_parser = transfer::parse_size, default_value = "64M")]
+    max_file_size: u64,
+}
 
-    match &cli.command {
-        Commands::Status(args) => cmd_status(&cli, args)?,
-        Commands::Node(cmd) => match cmd {
-            NodeCmd::List => cmd_node_list(&cli, &cfg)?,
-            NodeCmd::Exec(args) => cmd_node_exec(&cli, &cfg, args)?,
-        },
-        Commands::Config(cmd) => match cmd {
-            ConfigCmd::Get(args) => cmd_config_get(&cli, args)?,
-            ConfigCmd::Set(args) => cmd_config_set(&cli, args)?,
-        },
-        Commands::Completions(args) => cmd_completions(args)?,
-        Commands::Check(cmd) => checks::run_check_cmd(&cli, &cfg, &cmd)?,
-    }
+#[derive(Debug, Subcommand)]
+enum LogsCmd {
+    /// Pack BeeGFS logs, journal excerpts and kernel messages into a tarball per node
+    Collect(LogsCollectArgs),
+}
 
-    Ok(())
+#[derive(Debug, Args)]
+struct LogsCollectArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    #[command(flatten)]
+    range: timefmt::TimeRangeArgs,
+    /// Local directory; bundles land in <out>/<node>-<time>.tar.zst (.tar.gz without zstd on the node)
+    #[arg(long, default_value = "beeg-logs")]
+    out: PathBuf,
+    /// Keep at most this much of each log file, the newest part
+    #[arg(long, value_parser = transfer::parse_size, default_value = "256M")]
+    max_file_size: u64,
 }
 
-fn cmd_status(cli: &Cli, args: &StatusArgs) -> anyhow::Result<()> {
-    match cli.output {
-        Output::Human => {
-            println!(
-                "Status: selector={:?} (prototype; implement remote queries)",
-                args.selector
-            );
-        }
-        Output::Json => {
-            let obj = serde_json::json!({
-                "status": "ok",
-                "selector": args.selector,
-                "prototype": true,
-            });
-            println!("{}", serde_json::to_string_pretty(&obj)?);
-        }
-    }
-    Ok(())
+#[derive(Debug, Subcommand)]
+enum ExportCmd {
+    /// Run checks on an interval and serve them as Prometheus metrics over HTTP
+    Prometheus(ExportPrometheusArgs),
 }
 
-fn cmd_node_list(cli: &Cli, cfg: &config::Config) -> anyhow::Result<()> {
-    let nodes: Vec<_> = cfg.nodes.iter().map(|n| &n.name).collect();
-    match cli.output {
-        Output::Human => {
-            println!("Known nodes (prototype):");
-            for n in nodes {
-                println!("- {}", n);
-            }
-        }
-        Output::Json => {
-            println!("{}", serde_json::to_string_pretty(&nodes)?);
-        }
-    }
-    Ok(())
+#[derive(Debug, Args)]
+struct ExportPrometheusArgs {
+    /// Address to serve /metrics on
+    #[arg(long, default_value = "0.0.0.0:9188")]
+    listen: String,
+    /// Seconds between rounds of checks
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+    /// Print the metrics of one round to stdout and exit (e.g. for node_exporter's textfile collector)
+    #[arg(long)]
+    once: bool,
+    /// Nodes whose beegfs-* services (and client mounts) are checked: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Management node to read target capacity from (skipped when omitted)
+    #[arg(long)]
+    mgmt_node: Option<String>,
+    /// Node to read storage target states from (skipped when omitted)
+    #[arg(long)]
+    storage_node: Option<String>,
+    /// Target IDs for the target states: comma-separated or 'all'
+    #[arg(long, default_value = "all")]
+    targets: String,
+    /// Client mountpoint to probe on the selected nodes (skipped when omitted)
+    #[arg(long)]
+    mount: Option<String>,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
 }
 
-fn cmd_node_exec(cli: &Cli, cfg: &config::Config, args: &ExecArgs) -> anyhow::Result<()> {
-    let selector = &args.selector;
-    let cmdline = args.cmd.join(" ");
-    let targets = config::select_nodes(cfg, selector);
-    match cli.output {
-        Output::Human => {
-            println!(
-                "Exec (prototype): selector='{}' cmd='{}' on {} node(s)",
-                selector, cmdline, targets.len()
-            );
-            let tr = transport::from_config(cfg);
-            for n in targets {
-                match tr.exec(&n.host, &cmdline) {
-                    Ok(out) => {
-                        println!("=== {} ===\n{}", n.name, out.stdout);
-                        if !out.stderr.trim().is_empty() {
-                            eprintln!("--- {} (stderr) ---\n{}", n.name, out.stderr);
-                        }
-                    }
-                    Err(e) => eprintln!("!!! {} error: {}", n.name, e),
-                }
-            }
-        }
-        Output::Json => {
-            let tr = transport::from_config(cfg);
-            let mut results = Vec::new();
-            for n in targets {
-                let res = match tr.exec(&n.host, &cmdline) {
-                    Ok(out) => serde_json::json!({
-                        "node": n.name,
-                        "ok": true,
-                        "stdout": out.stdout,
-                        "stderr": out.stderr,
-                    }),
-                    Err(e) => serde_json::json!({
-                        "node": n.name,
-                        "ok": false,
-                        "error": e.to_string(),
-                    }),
-                };
-                results.push(res);
-            }
-            println!("{}", serde_json::to_string_pretty(&results)?);
-        }
-    }
-    Ok(())
+#[derive(Debug, Subcommand)]
+enum ResumeCmd {
+    /// Re-run the last check, reusing the output of nodes that already answered
+    Last,
 }
 
-fn cmd_config_get(cli: &Cli, args: &ConfigGetArgs) -> anyhow::Result<()> {
-    let value = serde_json::json!({"key": args.key, "value": "<value>", "proto": true});
-    match cli.output {
-        Output::Human => println!("{} = <value> (prototype)", args.key),
-        Output::Json => println!("{}", serde_json::to_string_pretty(&value)?),
-    }
-    Ok(())
+#[derive(Debug, Subcommand)]
+enum UsageCmd {
+    /// Start recording command names, durations and exit codes (no arguments or hosts)
+    Enable(UsageEnableArgs),
+    /// Stop recording and submitting
+    Disable,
+    /// Runs, failures and timings per command and check
+    Stats(UsageStatsArgs),
+    /// Delete the recorded events
+    Clear,
 }
 
-fn cmd_config_set(cli: &Cli, args: &ConfigSetArgs) -> anyhow::Result<()> {
-    if args.confirm {
-        // In a future version, prompt y/N. For now, print a warning.
-        eprintln!("WARNING: write operations are not implemented; dry-run only.");
-    }
+#[derive(Debug, Args)]
+struct UsageEnableArgs {
+    /// Also POST each event as JSON to this URL
+    #[arg(long)]
+    endpoint: Option<String>,
+}
 
-    match cli.output {
-        Output::Human => println!(
-            "Set (prototype): key='{}' value='{}' selector={:?}",
-            args.key, args.value, args.selector
-        ),
-        Output::Json => {
-            let obj = serde_json::json!({
-                "action": "set",
-                "key": args.key,
-                "value": args.value,
-                "selector": args.selector,
-                "prototype": true,
-            });
-            println!("{}", serde_json::to_string_pretty(&obj)?);
-        }
-    }
-    Ok(())
+#[derive(Debug, Args)]
+struct UsageStatsArgs {
+    /// Only count the last N days
+    #[arg(long)]
+    days: Option<u64>,
 }
 
-fn cmd_completions(args: &CompletionsArgs) -> anyhow::Result<()> {
-    let mut wrote = Vec::new();
-    let outdir = if let Some(d) = &args.dir { d.clone() } else { std::env::current_dir()? };
-    fs::create_dir_all(&outdir)?;
+#[derive(Debug, Subcommand)]
+enum SelfCmd {
+    /// Download a release, verify its signed checksum and replace the running binary
+    Update(SelfUpdateArgs),
+}
 
-    let shells: Vec<Shell> = match args.shell {
-        Some(CompShell::Bash) => vec![Shell::Bash],
-        Some(CompShell::Zsh) => vec![Shell::Zsh],
-        Some(CompShell::Fish) => vec![Shell::Fish],
-        Some(CompShell::PowerShell) => vec![Shell::PowerShell],
-        Some(CompShell::Elvish) => vec![Shell::Elvish],
-        None => vec![Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell, Shell::Elvish],
-    };
+#[derive(Debug, Args)]
+struct SelfUpdateArgs {
+    /// Release channel to follow
+    #[arg(long, default_value = "stable")]
+    channel: String,
+    /// Install this version instead of the channel's (also for downgrades)
+    #[arg(long = "version", value_name = "VERSION")]
+    target_version: Option<String>,
+    /// Release server; defaults to $BEEG_RELEASE_URL, then the project's release downloads
+    #[arg(long)]
+    base_url: Option<String>,
+    /// Only show what would be installed; exit 1 if an update is available
+    #[arg(long)]
+    check: bool,
+    /// Install even if the release does not satisfy the config's required_beeg_version, or is older than this one
+    #[arg(long)]
+    force: bool,
+    /// Accept a release server that is not https (an http or file:// mirror); the signature is still checked
+    #[arg(long)]
+    insecure: bool,
+}
 
-    let mut cmd = Cli::command();
-    for sh in shells {
-        let path = generate_to(sh, &mut cmd, "beeg", &outdir)?;
-        wrote.push(path);
-    }
-    for p in wrote { println!("wrote completion: {}", p.display()); }
-    Ok(())
+#[derive(Debug, Subcommand)]
+enum SshConfigCmd {
+    /// Render a Host block per node with user, port, key and jump hosts from the beeg config
+    Generate(SshConfigGenerateArgs),
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(Debug, Args)]
+struct SshConfigGenerateArgs {
+    /// File to write, e.g. ~/.ssh/config.d/beeg; prints to stdout when not given
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Only compare --out with what would be written; exit 1 if it differs
+    #[arg(long, requires = "out")]
+    check: bool,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+}
 
-    #[test]
-    fn parse_status_json() {
-        let cli = Cli::parse_from(["beeg", "--output", "json", "status"]);
-        match cli.command { Commands::Status(_) => {}, _ => panic!("expected status"), }
-        assert!(matches!(cli.output, Output::Json));
-    }
+#[derive(Debug, Subcommand)]
+enum PlanCmd {
+    /// Simulate where new data would land given current free space and capacity pool limits
+    Placement(PlanPlacementArgs),
+}
 
-    #[test]
-    fn parse_check_nvidia() {
-        let cli = Cli::parse_from(["beeg", "check", "nvidia-driver", "-s", "all"]);
-        match cli.command { Commands::Check(_) => {}, _ => panic!("expected check"), }
-    }
+#[derive(Debug, Args)]
+struct PlanPlacementArgs {
+    /// Amount of new data, e.g. 200T
+    #[arg(long, value_parser = transfer::parse_size)]
+    size: u64,
+    /// Storage pool (name or ID); default: all storage targets
+    #[arg(long)]
+    pool: Option<String>,
+    /// Targets per file
+    #[arg(long, default_value_t = 4)]
+    stripe: usize,
+    /// Management node to query (name/host/label); must resolve to one node
+    #[arg(long, visible_alias = "node")]
+    selector: String,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum MigrateCmd {
+    /// Compare a copied tree with its source: file counts, sizes, mtimes and sampled checksums
+    Verify(MigrateVerifyArgs),
+}
+
+#[derive(Debug, Args)]
+struct MigrateVerifyArgs {
+    /// Source tree, as mounted on the clients
+    #[arg(long)]
+    src: String,
+    /// Destination tree, as mounted on the clients
+    #[arg(long)]
+    dst: String,
+    /// Client selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Use at most this many clients
+    #[arg(long, default_value_t = 8)]
+    clients: usize,
+    /// Files to checksum on both sides, picked at random among those of equal size (0 to skip)
+    #[arg(long, default_value_t = 1000)]
+    sample: usize,
+    /// Seconds an mtime may differ by before it is reported
+    #[arg(long, default_value_t = 1.0)]
+    mtime_tolerance: f64,
+    /// Write the full report as JSON to this local file
+    #[arg(long)]
+    report: Option<PathBuf>,
+    /// Sign the report with this SSH private key (`ssh-keygen -Y sign`), writing <report>.sig
+    #[arg(long, requires = "report")]
+    sign_key: Option<PathBuf>,
+    /// Timeout seconds per remote command
+    #[arg(long, default_value_t = 3600)]
+    timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum VerifyCmd {
+    /// Check files against a sha256sum-style manifest, spread over several clients
+    Checksums(VerifyChecksumsArgs),
+}
+
+#[derive(Debug, Args)]
+struct VerifyChecksumsArgs {
+    /// Directory on the clients that manifest paths are relative to
+    #[arg(long)]
+    path: String,
+    /// Local manifest in `sha256sum` format (md5/sha1/sha512 also accepted)
+    #[arg(long)]
+    manifest: PathBuf,
+    /// Client selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Use at most this many clients
+    #[arg(long, default_value_t = 8)]
+    clients: usize,
+    /// Files per remote command
+    #[arg(long, default_value_t = 256)]
+    batch: usize,
+    /// Timeout seconds per batch
+    #[arg(long, default_value_t = 3600)]
+    timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum EntryCmd {
+    /// Stripe pattern, chunk size and storage targets of a file or directory
+    Info(EntryInfoArgs),
+}
+
+#[derive(Debug, Args)]
+struct EntryInfoArgs {
+    /// Path on the BeegFS mount of the client
+    path: String,
+    /// Client to run beegfs-ctl on (name/host/label); must resolve to one node
+    #[arg(short, long)]
+    selector: String,
+    /// Timeout seconds
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum QuotaCmd {
+    /// Show used/limit for space and chunk files per user or group
+    Show(QuotaShowArgs),
+}
+
+#[derive(Debug, Args)]
+#[command(group(clap::ArgGroup::new("ids").required(true).args(["uid", "gid", "all"])))]
+struct QuotaShowArgs {
+    /// Node to run beegfs-ctl on (name/host/label); must resolve to one node
+    #[arg(long, visible_alias = "node")]
+    selector: String,
+    /// User IDs, comma-separated
+    #[arg(long)]
+    uid: Option<String>,
+    /// Group IDs, comma-separated
+    #[arg(long)]
+    gid: Option<String>,
+    /// Every user with a quota entry (every group with --groups)
+    #[arg(long)]
+    all: bool,
+    /// With --all: list groups instead of users
+    #[arg(long, requires = "all")]
+    groups: bool,
+    /// Sort order
+    #[arg(long, value_enum, default_value_t = quota::SortBy::Used)]
+    sort: quota::SortBy,
+    /// Warn at this space or chunk-file utilization (default 90, or `quota_warn_percent` from the config)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    warn_percent: Option<u8>,
+    /// Timeout seconds
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum NoteCmd {
+    /// Attach a note to a node or target
+    Add(NoteAddArgs),
+    /// List notes
+    List(NoteListArgs),
+    /// Remove a note by ID
+    Rm(NoteRmArgs),
+}
+
+#[derive(Debug, Args)]
+struct NoteAddArgs {
+    /// What the note is about
+    kind: notes::NoteKind,
+    /// Node name or target ID
+    subject: String,
+    /// Note text
+    text: String,
+}
+
+#[derive(Debug, Args)]
+struct NoteListArgs {
+    /// Only notes of this kind
+    #[arg(long)]
+    kind: Option<notes::NoteKind>,
+    /// Only notes about this node or target
+    #[arg(long)]
+    subject: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct NoteRmArgs {
+    /// Note ID as shown by `beeg note list`
+    id: u32,
+}
+
+#[derive(Debug, Subcommand)]
+enum TransportCmd {
+    /// Walk through the connection to one node: DNS, TCP, banner, auth, shell, sudo
+    Debug(TransportDebugArgs),
+}
+
+#[derive(Debug, Args)]
+struct TransportDebugArgs {
+    /// Node to connect to (name/host/label); must resolve to one node
+    node: String,
+    /// SSH port [default: the node's ssh_port, else 22]
+    #[arg(long)]
+    port: Option<u16>,
+    /// Timeout seconds per step
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+struct StatusArgs {
+    /// Optional node selector (name, ip, label)
+    #[arg(short, long)]
+    selector: Option<String>,
+    /// Print one cached token (e.g. `beegfs:OK 42/42`) for shell prompts
+    #[arg(long)]
+    short: bool,
+    /// With --short: probe now and update the cache instead of reading it
+    #[arg(long, requires = "short")]
+    refresh: bool,
+    /// With --short: seconds before the cached token is refreshed in the background
+    #[arg(long, default_value_t = 300, requires = "short")]
+    max_age: u64,
+    /// Management node to read targets and capacity from (default: the node running beegfs-mgmtd)
+    #[arg(long, conflicts_with = "short")]
+    mgmt_node: Option<String>,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+struct DashboardArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Management node to read targets and capacity from (default: the node running beegfs-mgmtd)
+    #[arg(long)]
+    mgmt_node: Option<String>,
+    /// Seconds between refreshes
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+    /// Timeout seconds per operation
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct CompletionsArgs {
+    #[command(subcommand)]
+    cmd: Option<CompletionsCmd>,
+    /// Shell to generate completions for (default: all)
+    #[arg(long, value_enum)]
+    shell: Option<CompShell>,
+    /// Output directory to write completion files
+    #[arg(long)]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum CompletionsCmd {
+    /// Install completions where the shell loads them from
+    Install(CompletionsInstallArgs),
+}
+
+#[derive(Debug, Args)]
+struct CompletionsInstallArgs {
+    /// Shell to install for (default: detected from $SHELL)
+    #[arg(long, value_enum)]
+    shell: Option<CompShell>,
+    /// Install for all users instead of the current one (usually needs root)
+    #[arg(long)]
+    system: bool,
+    /// Install into this directory instead
+    #[arg(long, conflicts_with = "system")]
+    dir: Option<PathBuf>,
+    /// Print the script to stdout instead of installing it
+    #[arg(long, conflicts_with_all = ["system", "dir"])]
+    print: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CompShell { Bash, Zsh, Fish, PowerShell, Elvish }
+
+impl CompShell {
+    fn shell(self) -> Shell {
+        match self {
+            CompShell::Bash => Shell::Bash,
+            CompShell::Zsh => Shell::Zsh,
+            CompShell::Fish => Shell::Fish,
+            CompShell::PowerShell => Shell::PowerShell,
+            CompShell::Elvish => Shell::Elvish,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum NodeCmd {
+    /// List known nodes
+    List,
+    /// Execute a read-only command on nodes
+    Exec(ExecArgs),
+    /// Copy a (large) file from nodes: compressed, chunked and resumable
+    Fetch(FetchArgs),
+    /// Gather and cache node facts: arch, OS, kernel, installable BeegFS client packages
+    Facts(FactsArgs),
+    /// Take storage nodes out for maintenance: targets offline, client I/O settled, beegfs-storage stopped
+    Drain(DrainArgs),
+    /// Reverse a drain: start beegfs-storage and put the targets back
+    Undrain(DrainArgs),
+}
+
+#[derive(Debug, Args)]
+struct DrainArgs {
+    /// Node selector: name/ip/label
+    selector: String,
+    /// Node to set target states through [default: the one the drain used, else the first node with the mgmtd role]
+    #[arg(long)]
+    mgmt_node: Option<String>,
+    /// Seconds the client sessions have to stay quiet before beegfs-storage is stopped
+    #[arg(long, default_value_t = 30)]
+    settle: u64,
+    /// Seconds to wait for the client sessions before giving up on the node
+    #[arg(long, default_value_t = 600)]
+    settle_timeout: u64,
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// Timeout seconds per command on a node
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+    /// Seconds beegfs-storage gets to stop or start
+    #[arg(long, default_value_t = 120)]
+    verify_timeout: u64,
+}
+
+impl DrainArgs {
+    fn options(&self) -> drain::Options {
+        drain::Options { yes: self.yes, mgmt_node: self.mgmt_node.clone(), settle: self.settle, settle_timeout: self.settle_timeout, timeout: self.timeout, verify_timeout: self.verify_timeout }
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct FactsArgs {
+    #[command(subcommand)]
+    cmd: Option<FactsCmd>,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum FactsCmd {
+    /// Capture what GDS/CUDA jobs depend on: driver, CUDA, nvidia-fs, PCIe links, ACS, IOMMU, nvidia-peermem
+    GpuEnv(GpuEnvArgs),
+}
+
+#[derive(Debug, Args)]
+struct GpuEnvArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Show only what differs from this known-good node
+    #[arg(long, value_name = "NODE")]
+    diff: Option<String>,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+struct FetchArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Remote file to copy
+    #[arg(long)]
+    path: String,
+    /// Local directory; files land in <out>/<node>/<basename>.zst
+    #[arg(long, default_value = ".")]
+    out: PathBuf,
+    /// Cap per file (e.g. 512M); the copy ends with a truncation marker
+    #[arg(long, value_parser = transfer::parse_size)]
+    max_size: Option<u64>,
+    /// Chunk size; an interrupted copy resumes at the last complete chunk
+    #[arg(long, value_parser = transfer::parse_size, default_value = "64M")]
+    chunk_size: u64,
+}
+
+#[derive(Debug, Args)]
+struct ExecArgs {
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Keep at most this much stdout/stderr per node (default 1M, or `exec_max_output` from the config; 0 = no limit)
+    #[arg(long, value_parser = transfer::parse_size)]
+    max_output: Option<u64>,
+    /// Also write each node's full output to <dir>/<node>.stdout and <node>.stderr
+    #[arg(long)]
+    spill_dir: Option<PathBuf>,
+    /// Print output lines as they arrive, prefixed with the node, e.g. for `dmesg -w`; Ctrl-C stops the remote commands
+    #[arg(long, conflicts_with_all = ["max_output", "spill_dir"])]
+    stream: bool,
+    /// Command to run (read-only diagnostics)
+    #[arg(last = true, required = true)]
+    cmd: Vec<String>,
+}
+
+const DEFAULT_EXEC_MAX_OUTPUT: u64 = 1 << 20;
+
+#[derive(Debug, Subcommand)]
+enum ConfigCmd {
+    /// Read a key from a BeeGFS service config on the selected nodes
+    Get(ConfigGetArgs),
+    /// Change a key in a BeeGFS service config on the selected nodes, keeping a backup
+    Set(ConfigSetArgs),
+    /// Compare a BeeGFS service config across nodes and report keys that differ
+    Diff(ConfigDiffArgs),
+    /// Import nodes from an Ansible inventory (INI or YAML) into the beeg config; groups become labels
+    ImportInventory(ImportInventoryArgs),
+}
+
+#[derive(Debug, Args)]
+struct ImportInventoryArgs {
+    /// Inventory file, e.g. hosts.ini or inventory.yml
+    path: PathBuf,
+    /// Inventory format
+    #[arg(long, value_enum, default_value_t = inventory::InventoryFormat::Ansible)]
+    format: inventory::InventoryFormat,
+    /// Drop config nodes that are not in the inventory
+    #[arg(long)]
+    replace: bool,
+    /// Show the changes without writing the config file
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct ConfigDiffArgs {
+    /// Service config to compare: client, storage, meta, mgmtd, helperd or a path
+    #[arg(long, default_value = "client")]
+    file: String,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Compare against this node instead of the value most nodes have
+    #[arg(long)]
+    reference: Option<String>,
+    /// Keys that are expected to differ, comma-separated (e.g. storeStorageDirectory)
+    #[arg(long, value_delimiter = ',')]
+    ignore: Vec<String>,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+struct ConfigGetArgs {
+    /// Config key to read
+    #[arg(long)]
+    key: String,
+    /// Optional node selector
+    #[arg(short, long)]
+    selector: Option<String>,
+    /// Service config to read: client, storage, meta, mgmtd, helperd or a path
+    #[arg(long, default_value = "client")]
+    file: String,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+struct ConfigSetArgs {
+    /// Config key to write
+    #[arg(long)]
+    key: String,
+    /// New value
+    #[arg(long)]
+    value: String,
+    /// Optional node selector
+    #[arg(short, long)]
+    selector: Option<String>,
+    /// Service config to edit: client, storage, meta, mgmtd, helperd or a path
+    #[arg(long, default_value = "client")]
+    file: String,
+    /// Require an explicit confirmation for write operations
+    #[arg(long, default_value_t = true)]
+    confirm: bool,
+    /// Answer the confirmation with yes, for non-interactive use
+    #[arg(short, long)]
+    yes: bool,
+    /// Show the edit each node would get without changing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+struct ServiceArgs {
+    /// What to do with the service
+    #[arg(value_enum)]
+    action: service::Action,
+    /// Which BeeGFS service
+    #[arg(value_enum)]
+    service: service::Service,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Nodes acted on at a time; the next batch starts once this one is verified
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    serial: u32,
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// Timeout seconds per systemctl call
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+    /// Seconds a node gets to reach the expected state before the rollout stops
+    #[arg(long, default_value_t = 60)]
+    verify_timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum RolloutCmd {
+    /// Restart a BeeGFS service a batch of nodes at a time, optionally waiting for its targets to be Online/Good in between
+    Restart(RolloutRestartArgs),
+}
+
+#[derive(Debug, Args)]
+struct RolloutRestartArgs {
+    /// Service to restart: storage, meta, mgmtd, client or helperd (`beegfs-storage` works too)
+    #[arg(value_parser = service::parse_service)]
+    service: service::Service,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Nodes restarted at a time
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    serial: u32,
+    /// After each batch wait until the mgmtd has noticed the restart and reports every target of the service Online and Good
+    #[arg(long)]
+    wait_healthy: bool,
+    /// Seconds the targets get to recover after a batch before the rollout is aborted
+    #[arg(long, default_value_t = 600)]
+    health_timeout: u64,
+    /// Seconds the targets have to stay healthy before the next batch starts
+    #[arg(long, default_value_t = 30)]
+    settle: u64,
+    /// Node to read target states on [default: the first node with the mgmtd role]
+    #[arg(long)]
+    mgmt_node: Option<String>,
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// Timeout seconds per systemctl call or listing
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+    /// Seconds a node gets to report the service active again
+    #[arg(long, default_value_t = 60)]
+    verify_timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum FsckCmd {
+    /// Run beegfs-fsck on one node, stream its progress, sum up the errors and record the outcome in the history
+    Run(FsckRunArgs),
+}
+
+#[derive(Debug, Args)]
+struct FsckRunArgs {
+    /// What fsck does
+    #[arg(long, value_enum, default_value_t = fsck::Mode::CheckFs)]
+    mode: fsck::Mode,
+    /// Node to run beegfs-fsck on; it needs the BeeGFS client config
+    #[arg(short, long)]
+    selector: String,
+    /// Repair what is found instead of only reporting it (asks first)
+    #[arg(long)]
+    fix: bool,
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// Tell fsck nothing modifies the file system while it runs (--runOffline)
+    #[arg(long)]
+    run_offline: bool,
+    /// Reuse the database of the previous run instead of gathering the data again (--noFetch)
+    #[arg(long)]
+    no_fetch: bool,
+    /// Where fsck keeps its database (--databasePath)
+    #[arg(long)]
+    db_path: Option<String>,
+    /// Further beegfs-fsck argument, passed on as is (repeatable)
+    #[arg(long = "fsck-arg", value_name = "ARG", allow_hyphen_values = true)]
+    fsck_args: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum ResyncCmd {
+    /// Resync progress, rate and ETA per buddy group; exits non-zero when a resync has stalled
+    Status(ResyncStatusArgs),
+}
+
+#[derive(Debug, Args)]
+struct ResyncStatusArgs {
+    /// Buddy group IDs, comma separated, or 'all'
+    #[arg(long, default_value = "all")]
+    group: String,
+    /// Buddy groups of storage targets or of meta nodes
+    #[arg(long, default_value = "storage", value_parser = ["storage", "meta"])]
+    nodetype: String,
+    /// Poll every SECONDS, refreshing the table, until no resync is running or one stalls
+    #[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    watch: Option<u64>,
+    /// Seconds a running resync may go without syncing anything before it counts as stalled
+    #[arg(long, default_value_t = 300)]
+    stall: u64,
+    /// Node to query [default: the first node with the mgmtd role]
+    #[arg(long)]
+    mgmt_node: Option<String>,
+    /// Timeout seconds per listing
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(Debug, Subcommand)]
+enum MountCmd {
+    /// Add the mount to beegfs-mounts.conf, mount it where it is missing and verify it with a write test
+    Ensure(MountEnsureArgs),
+    /// Unmount and drop the mount from beegfs-mounts.conf, fstab and systemd
+    Remove(MountRemoveArgs),
+}
+
+#[derive(Debug, Args)]
+struct MountEnsureArgs {
+    /// Mountpoint
+    #[arg(long, default_value = "/mnt/beegfs")]
+    mount: String,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Client config the mount uses
+    #[arg(long, default_value = "/etc/beegfs/beegfs-client.conf")]
+    client_conf: String,
+    /// How the mount survives a reboot
+    #[arg(long, value_enum, default_value_t = mount::Persist::MountsConf)]
+    persist: mount::Persist,
+    /// Show what each node is missing without changing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+}
+
+#[derive(Debug, Args)]
+struct MountRemoveArgs {
+    /// Mountpoint
+    #[arg(long, default_value = "/mnt/beegfs")]
+    mount: String,
+    /// Node selector: name/ip/label, or 'all'
+    #[arg(short, long, default_value = "all")]
+    selector: String,
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// Show what each node would lose without changing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Timeout seconds per node
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+}
+
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let started = std::time::Instant::now();
+    let result = run(&cli, None);
+    let command = telemetry::command_path(&matches);
+    match result {
+        Ok(code) => {
+            telemetry::record(&command, started.elapsed(), code);
+            ExitCode::from(code)
+        }
+        Err(e) if transport::cancel::was_cancelled(&e) => {
+            telemetry::record(&command, started.elapsed(), EXIT_INTERRUPTED);
+            eprintln!("interrupted");
+            ExitCode::from(EXIT_INTERRUPTED)
+        }
+        Err(e) => {
+            telemetry::record(&command, started.elapsed(), telemetry::EXIT_ERROR);
+            eprintln!("Error: {:?}", e);
+            if let (Output::Nagios, Commands::Check(cmd)) = (cli.output, &cli.command) {
+                // a plugin that could not check anything is UNKNOWN, and says why on stdout
+                println!("BEEG {} UNKNOWN - {}", cmd.name.to_uppercase(), format!("{:#}", e).replace('|', "/").replace('\n', " "));
+                return ExitCode::from(3);
+            }
+            // an error that stopped a check counts as a failure, unless permission was denied
+            ExitCode::from(checks::error_severity(&e).exit_code(cli.strict))
+        }
+    }
+}
+
+/// Exit code after Ctrl-C, as a shell reports a command killed by SIGINT.
+const EXIT_INTERRUPTED: u8 = 130;
+
+/// Let Ctrl-C cancel the commands in flight so the run winds down and keeps its journal; a
+/// second Ctrl-C exits at once.
+fn catch_interrupt() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| {
+        use signal_hook::{consts::SIGINT, flag};
+        let cancelled = transport::cancel::global().flag();
+        let _ = flag::register_conditional_shutdown(SIGINT, EXIT_INTERRUPTED as i32, cancelled.clone());
+        let _ = flag::register(SIGINT, cancelled);
+    });
+}
+
+/// Global options a child `beeg` run needs to reach the same nodes the same way.
+fn child_args(cli: &Cli) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(c) = &cli.config { args.extend(["--config".to_string(), c.display().to_string()]); }
+    if let Some(j) = &cli.jump_host { args.extend(["--jump-host".to_string(), j.clone()]); }
+    if let Some(t) = cli.exec_timeout { args.extend(["--exec-timeout".to_string(), t.to_string()]); }
+    if let Some(r) = cli.retries { args.extend(["--retries".to_string(), r.to_string()]); }
+    if let Some(p) = cli.parallel { args.extend(["--parallel".to_string(), p.to_string()]); }
+    if let Some(b) = cli.backend { args.extend(["--backend".to_string(), b.to_possible_value().expect("no skipped backends").get_name().to_string()]); }
+    args
+}
+
+/// `journal` is set when resuming; a check run otherwise starts a journal of its own.
+fn run(cli: &Cli, journal: Option<std::sync::Arc<journal::Journal>>) -> anyhow::Result<u8> {
+    logging::init(cli)?;
+    // Load configuration once; many commands need it
+    let mut cfg = config::load(cli.config.as_ref())?;
+    if let Some(j) = &cli.jump_host { cfg.ssh_jump_host = Some(j.clone()); }
+    if let Some(t) = cli.exec_timeout { cfg.exec_timeout = Some(t); }
+    if let Some(r) = cli.retries { cfg.exec_retries = r; }
+    if let Some(p) = cli.parallel { cfg.exec_parallel = Some(p as usize); }
+    if let Some(b) = cli.backend { cfg.backend = b; }
+    if cli.no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) { tables::disable_color(); }
+    if matches!(cli.output, Output::Nagios) && !matches!(cli.command, Commands::Check(_)) {
+        anyhow::bail!("--output nagios is for `beeg check` only");
+    }
+    // `config` and `self` stay usable so a wrong pin can be fixed
+    if !matches!(cli.command, Commands::Config(_) | Commands::SelfCmd(_) | Commands::Completions(_) | Commands::Usage(_)) {
+        selfupdate::check_pin(&cfg)?;
+    }
+
+    match &cli.command {
+        Commands::Status(args) => return cmd_status(cli, &cfg, args),
+        Commands::Dashboard(args) => cmd_dashboard(cli, &cfg, args)?,
+        Commands::Node(cmd) => match cmd {
+            NodeCmd::List => cmd_node_list(cli, &cfg)?,
+            NodeCmd::Exec(args) => cmd_node_exec(cli, &cfg, args)?,
+            NodeCmd::Fetch(args) => cmd_node_fetch(cli, &cfg, args)?,
+            NodeCmd::Drain(args) => return Ok(drain::drain(cli, &cfg, &args.selector, &args.options())?.exit_code(cli.strict)),
+            NodeCmd::Undrain(args) => return Ok(drain::undrain(cli, &cfg, &args.selector, &args.options())?.exit_code(cli.strict)),
+            NodeCmd::Facts(args) => match &args.cmd {
+                Some(FactsCmd::GpuEnv(a)) => return Ok(gpuenv::run(cli, &cfg, &a.selector, a.diff.as_deref(), a.timeout)?.exit_code(cli.strict)),
+                None => facts::run(cli, &cfg, &args.selector, args.timeout)?,
+            },
+        },
+        Commands::Config(cmd) => match cmd {
+            ConfigCmd::Get(args) => {
+                let selector = args.selector.as_deref().unwrap_or("all");
+                return Ok(beegconf::get(cli, &cfg, selector, &args.file, &args.key, args.timeout)?.exit_code(cli.strict));
+            }
+            ConfigCmd::Set(args) => {
+                let opts = beegconf::SetOptions { file: &args.file, dry_run: args.dry_run, confirm: args.confirm, yes: args.yes, timeout: args.timeout };
+                let selector = args.selector.as_deref().unwrap_or("all");
+                return Ok(beegconf::set(cli, &cfg, selector, &args.key, &args.value, &opts)?.exit_code(cli.strict));
+            }
+            ConfigCmd::Diff(args) => {
+                return Ok(beegconf::diff(cli, &cfg, &args.selector, &args.file, args.reference.as_deref(), &args.ignore, args.timeout)?.exit_code(cli.strict));
+            }
+            ConfigCmd::ImportInventory(args) => {
+                let path = cli.config.clone().unwrap_or_else(config::default_config_path);
+                inventory::import(cli, &path, &args.path, args.format, args.replace, args.dry_run)?;
+            }
+        },
+        Commands::Service(args) => {
+            let opts = service::Options { action: args.action, service: args.service, serial: args.serial as usize, yes: args.yes, timeout: args.timeout, verify_timeout: args.verify_timeout };
+            return Ok(service::run(cli, &cfg, &args.selector, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Rollout(RolloutCmd::Restart(args)) => {
+            let opts = rollout::Options {
+                service: service::Options { action: service::Action::Restart, service: args.service, serial: args.serial as usize, yes: args.yes, timeout: args.timeout, verify_timeout: args.verify_timeout },
+                wait_healthy: args.wait_healthy,
+                health_timeout: args.health_timeout,
+                settle: args.settle,
+                mgmt_node: args.mgmt_node.clone(),
+            };
+            return Ok(rollout::restart(cli, &cfg, &args.selector, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Fsck(FsckCmd::Run(args)) => {
+            let opts = fsck::Options { mode: args.mode, fix: args.fix, yes: args.yes, run_offline: args.run_offline, no_fetch: args.no_fetch, db_path: args.db_path.clone(), extra: args.fsck_args.clone() };
+            return Ok(fsck::run(cli, &cfg, &args.selector, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Resync(ResyncCmd::Status(args)) => {
+            let groups = (args.group != "all").then(|| args.group.split(',').map(|g| g.trim().to_string()).filter(|g| !g.is_empty()).collect());
+            let opts = resync::Options { node_type: beegfs::NodeType::parse(&args.nodetype)?, groups, mgmt_node: args.mgmt_node.clone(), stall: args.stall, watch: args.watch, timeout: args.timeout };
+            return Ok(resync::status(cli, &cfg, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Mount(MountCmd::Ensure(args)) => {
+            let opts = mount::EnsureOptions { mount: args.mount.clone(), client_conf: args.client_conf.clone(), persist: args.persist, dry_run: args.dry_run, timeout: args.timeout };
+            return Ok(mount::ensure(cli, &cfg, &args.selector, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Mount(MountCmd::Remove(args)) => {
+            let opts = mount::RemoveOptions { mount: args.mount.clone(), yes: args.yes, dry_run: args.dry_run, timeout: args.timeout };
+            return Ok(mount::remove(cli, &cfg, &args.selector, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Completions(args) => match &args.cmd {
+            Some(CompletionsCmd::Install(a)) => {
+                let opts = completions::InstallOptions { shell: a.shell, system: a.system, dir: a.dir.clone(), print: a.print };
+                completions::install(cli, &opts)?;
+            }
+            None => cmd_completions(args)?,
+        },
+        Commands::Check(cmd) => {
+            // a watched check never finishes, so there is nothing to resume
+            if cmd.registration().is_some_and(|r| r.resumable) && cmd.watch().is_none() {
+                cfg.journal = Some(match journal {
+                    Some(j) => j,
+                    None => {
+                        let path = cli.config.clone().unwrap_or_else(config::default_config_path);
+                        journal::Journal::start(std::env::args().skip(1).collect(), Some(path))?
+                    }
+                });
+            }
+            // a watched check runs until Ctrl-C, so there it keeps ending the process
+            if cmd.watch().is_none() { catch_interrupt(); }
+            let severity = checks::run_check_cmd(cli, &cfg, cmd);
+            let interrupted = transport::cancel::global().is_cancelled();
+            if let Some(j) = &cfg.journal {
+                match &severity {
+                    Ok(_) if !interrupted => j.finish()?,
+                    _ => eprintln!("NOTE: output from nodes that answered is kept; `beeg resume last` re-runs only the rest"),
+                }
+            }
+            if interrupted {
+                eprintln!("interrupted");
+                return Ok(EXIT_INTERRUPTED);
+            }
+            let severity = severity?;
+            if let Output::Nagios = cli.output { return Ok(severity.nagios(cli.strict).1); }
+            return Ok(severity.exit_code(cli.strict));
+        }
+        Commands::Transport(TransportCmd::Debug(args)) => {
+            return Ok(transport::debug::run(cli, &cfg, &args.node, args.port, args.timeout)?.exit_code(cli.strict));
+        }
+        Commands::Note(cmd) => match cmd {
+            NoteCmd::Add(args) => notes::add(cli, args.kind, &args.subject, &args.text)?,
+            NoteCmd::List(args) => notes::list(cli, args.kind, args.subject.as_deref())?,
+            NoteCmd::Rm(args) => notes::remove(cli, args.id)?,
+        },
+        Commands::Quota(QuotaCmd::Show(args)) => {
+            let scope = match (&args.uid, &args.gid) {
+                (Some(u), _) => quota::Scope::Uids(u.clone()),
+                (_, Some(g)) => quota::Scope::Gids(g.clone()),
+                _ if args.groups => quota::Scope::AllGroups,
+                _ => quota::Scope::AllUsers,
+            };
+            quota::show(cli, &cfg, &args.selector, &scope, args.sort, args.warn_percent, args.timeout)?;
+        }
+        Commands::Entry(EntryCmd::Info(args)) => entry::info(cli, &cfg, &args.selector, &args.path, args.timeout)?,
+        Commands::Verify(VerifyCmd::Checksums(args)) => {
+            let opts = verify::VerifyOptions { clients: args.clients, batch_size: args.batch, timeout: args.timeout };
+            return Ok(verify::checksums(cli, &cfg, &args.path, &args.manifest, &args.selector, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Migrate(MigrateCmd::Verify(args)) => {
+            let opts = migrate::MigrateOptions {
+                clients: args.clients,
+                sample: args.sample,
+                mtime_tolerance: args.mtime_tolerance,
+                timeout: args.timeout,
+                report: args.report.clone(),
+                sign_key: args.sign_key.clone(),
+            };
+            return Ok(migrate::verify(cli, &cfg, &args.src, &args.dst, &args.selector, &opts)?.exit_code(cli.strict));
+        }
+        Commands::SshConfig(SshConfigCmd::Generate(args)) => {
+            let source = cli.config.clone().unwrap_or_else(config::default_config_path);
+            return Ok(sshconfig::generate(cli, &cfg, &source, &args.selector, args.out.as_deref(), args.check)?.exit_code(cli.strict));
+        }
+        Commands::Plan(PlanCmd::Placement(args)) => {
+            return Ok(plan::placement(cli, &cfg, &args.selector, args.size, args.pool.as_deref(), args.stripe, args.timeout)?.exit_code(cli.strict));
+        }
+        Commands::SelfCmd(SelfCmd::Update(args)) => {
+            let opts = selfupdate::UpdateOptions {
+                channel: args.channel.clone(),
+                version: args.target_version.clone(),
+                base_url: args.base_url.clone(),
+                check: args.check,
+                force: args.force,
+                insecure: args.insecure,
+            };
+            return Ok(selfupdate::update(cli, &cfg, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Resume(ResumeCmd::Last) => {
+            let (meta, j) = journal::Journal::resume_last()?;
+            let mut again = Cli::try_parse_from(std::iter::once("beeg".to_string()).chain(meta.args.iter().cloned()))
+                .map_err(|e| anyhow::anyhow!("cannot parse the interrupted command `beeg {}`: {}", meta.args.join(" "), e))?;
+            if again.config.is_none() { again.config = meta.config.clone(); }
+            eprintln!("resuming `beeg {}` from {}", meta.args.join(" "), timefmt::human(chrono::DateTime::from_timestamp(meta.started_at, 0).unwrap_or_default(), cli.utc));
+            let code = run(&again, Some(j.clone()));
+            eprintln!("reused {} recorded result(s), ran {} command(s) again", j.replayed(), j.recorded());
+            return code;
+        }
+        Commands::Export(ExportCmd::Prometheus(args)) => cmd_export_prometheus(&cfg, args)?,
+        Commands::Logs(LogsCmd::Collect(args)) => {
+            let opts = logs::CollectOptions {
+                selector: args.selector.clone(),
+                range: args.range.resolve()?,
+                out: args.out.clone(),
+                max_file_size: args.max_file_size,
+            };
+            return Ok(logs::collect(cli, &cfg, &opts)?.exit_code(cli.strict));
+        }
+        Commands::SupportBundle(args) => {
+            let opts = support::BundleOptions {
+                selector: args.selector.clone(),
+                range: args.range.resolve()?,
+                out: args.out.clone(),
+                mgmt_node: args.mgmt_node.clone(),
+                logs: !args.no_logs,
+                max_file_size: args.max_file_size,
+            };
+            return Ok(support::bundle(cli, &cfg, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Baseline(cmd) => match cmd {
+            BaselineCmd::Save(args) => baseline::save(cli, &child_args(cli), &args.name, &args.command, args.force)?,
+            BaselineCmd::Compare(args) => return Ok(baseline::compare_cmd(cli, &child_args(cli), &args.name, args.threshold)?.exit_code(cli.strict)),
+            BaselineCmd::List => baseline::list(cli)?,
+            BaselineCmd::Rm(args) => baseline::remove(cli, &args.name)?,
+        },
+        Commands::Daemon(args) => return cmd_daemon(cli, &cfg, args),
+        Commands::History(cmd) => match cmd {
+            HistoryCmd::List(args) => history::list(cli, &args.filter.filter()?, args.failing, args.limit)?,
+            HistoryCmd::Show(args) => history::show(cli, &args.filter.filter()?)?,
+        },
+        Commands::Bench(BenchCmd::Storage(args)) => {
+            let opts = bench::storage::StorageOptions {
+                node: args.node.clone(),
+                targets: args.targets.clone(),
+                blocksize: args.blocksize,
+                size: args.size,
+                threads: args.threads,
+                mode: args.mode,
+                interval: args.interval,
+                max_time: args.max_time,
+                slow_percent: args.slow_percent,
+                keep_files: args.keep_files,
+                timeout: args.timeout,
+            };
+            return Ok(bench::storage::run(cli, &cfg, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Bench(BenchCmd::Client(args)) => {
+            if args.blocksize == 0 { anyhow::bail!("--blocksize must be greater than 0"); }
+            let opts = bench::client::ClientOptions {
+                mount: args.mount.clone(),
+                selector: args.selector.clone(),
+                mode: args.mode,
+                blocksize: args.blocksize,
+                size: args.size,
+                iodepth: args.iodepth,
+                regress_percent: args.regress_percent,
+                save_baseline: args.save_baseline,
+                timeout: args.timeout,
+            };
+            return Ok(bench::client::run(cli, &cfg, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Bench(BenchCmd::Meta(args)) => {
+            let opts = bench::meta::MetaOptions {
+                mount: args.mount.clone(),
+                selector: args.selector.clone(),
+                mode: args.mode,
+                files: args.files,
+                min: args.min.iter().cloned().collect(),
+                regress_percent: args.regress_percent,
+                save_baseline: args.save_baseline,
+                timeout: args.timeout,
+            };
+            return Ok(bench::meta::run(cli, &cfg, &opts)?.exit_code(cli.strict));
+        }
+        Commands::Usage(cmd) => match cmd {
+            UsageCmd::Enable(args) => telemetry::enable(cli, args.endpoint.as_deref())?,
+            UsageCmd::Disable => telemetry::disable(cli)?,
+            UsageCmd::Stats(args) => telemetry::show_stats(cli, args.days)?,
+            UsageCmd::Clear => telemetry::clear(cli)?,
+        },
+    }
+
+    Ok(0)
+}
+
+#[cfg(feature = "tui")]
+fn cmd_dashboard(cli: &Cli, cfg: &config::Config, args: &DashboardArgs) -> anyhow::Result<()> {
+    let opts = dashboard::Options { selector: args.selector.clone(), mgmt_node: args.mgmt_node.clone(), interval: args.interval, timeout: args.timeout };
+    dashboard::run(cli, cfg, opts)
+}
+
+#[cfg(not(feature = "tui"))]
+fn cmd_dashboard(_cli: &Cli, _cfg: &config::Config, _args: &DashboardArgs) -> anyhow::Result<()> {
+    anyhow::bail!("`beeg dashboard` needs a beeg built with the `tui` feature")
+}
+
+#[cfg(feature = "exporter")]
+fn cmd_export_prometheus(cfg: &config::Config, args: &ExportPrometheusArgs) -> anyhow::Result<()> {
+    let opts = export::ExportOptions {
+        selector: args.selector.clone(),
+        mgmt_node: args.mgmt_node.clone(),
+        storage_node: args.storage_node.clone(),
+        targets: args.targets.clone(),
+        mount: args.mount.clone(),
+        timeout: args.timeout,
+    };
+    export::prometheus(cfg, &args.listen, args.interval, args.once, opts)
+}
+
+#[cfg(not(feature = "exporter"))]
+fn cmd_export_prometheus(_cfg: &config::Config, _args: &ExportPrometheusArgs) -> anyhow::Result<()> {
+    anyhow::bail!("`beeg export prometheus` needs a beeg built with the `exporter` feature")
+}
+
+#[cfg(feature = "daemon")]
+fn cmd_daemon(cli: &Cli, cfg: &config::Config, args: &DaemonArgs) -> anyhow::Result<u8> {
+    let opts = daemon::DaemonOptions {
+        checks: args.checks.clone(),
+        interval: args.interval,
+        once: args.once,
+        selector: args.selector.clone(),
+        mgmt_node: args.mgmt_node.clone(),
+        storage_node: args.storage_node.clone(),
+        targets: args.targets.clone(),
+        mount: args.mount.clone(),
+        timeout: args.timeout,
+        webhooks: args.webhook.clone(),
+    };
+    Ok(daemon::run(cli, cfg, &opts)?.exit_code(cli.strict))
+}
+
+#[cfg(not(feature = "daemon"))]
+fn cmd_daemon(_cli: &Cli, _cfg: &config::Config, _args: &DaemonArgs) -> anyhow::Result<u8> {
+    anyhow::bail!("`beeg daemon` needs a beeg built with the `daemon` feature")
+}
+
+/// `5m`, `1h30m`, or plain seconds.
+pub(crate) fn parse_interval(s: &str) -> std::result::Result<u64, String> {
+    let secs = match s.parse::<u64>() {
+        Ok(n) => n,
+        Err(_) => timefmt::parse_duration(s).map(|d| d.num_seconds().max(0) as u64)
+            .ok_or_else(|| format!("'{}' is not a duration like 30s, 5m or 1h", s))?,
+    };
+    if secs == 0 { return Err("the interval must be at least one second".into()); }
+    Ok(secs)
+}
+
+fn cmd_status(cli: &Cli, cfg: &config::Config, args: &StatusArgs) -> anyhow::Result<u8> {
+    if args.short {
+        let selector = args.selector.as_deref().unwrap_or("all");
+        status::short(cli, cfg, selector, args.refresh, args.max_age)?;
+        return Ok(0);
+    }
+    Ok(status::cluster(cli, cfg, args.selector.as_deref(), args.mgmt_node.as_deref(), args.timeout)?.exit_code(cli.strict))
+}
+
+fn cmd_node_list(cli: &Cli, cfg: &config::Config) -> anyhow::Result<()> {
+    let set = config::select_nodes(cfg, "all");
+    let mut nodes: Vec<&config::Node> = set.to_vec();
+    config::sort_rows(cfg, cli.sort, &mut nodes, |n| &n.name);
+    // the index is the node's place in the config, whatever `--sort` says
+    let index = |n: &config::Node| set.index_of(&n.name).unwrap_or_default();
+    match cli.output {
+        Output::Human | Output::Csv | Output::Tsv | Output::Nagios => {
+            let mut table = comfy_table::Table::new();
+            table.load_preset(comfy_table::presets::UTF8_FULL);
+            table.set_header(vec!["#", "Node", "Host", "Role", "Labels"]);
+            for n in &nodes {
+                table.add_row(vec![index(n).to_string(), n.name.clone(), n.host.clone(), n.role().to_string(), n.labels.join(", ")]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        Output::Json | Output::Jsonl | Output::Yaml => {
+            let arr: Vec<_> = nodes.iter().map(|n| serde_json::json!({
+                "index": index(n),
+                "name": n.name,
+                "host": n.host,
+                "role": n.role(),
+                "labels": n.labels,
+            })).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+    Ok(())
+}
+
+fn cmd_node_exec(cli: &Cli, cfg: &config::Config, args: &ExecArgs) -> anyhow::Result<()> {
+    let selector = &args.selector;
+    let cmdline = args.cmd.join(" ");
+    let targets = config::select_nodes(cfg, selector);
+    if args.stream { return node_exec_stream(cli, cfg, &targets, &cmdline); }
+    let max_output = match (args.max_output, &cfg.exec_max_output) {
+        (Some(m), _) => m,
+        (None, Some(s)) => transfer::parse_size(s).map_err(|e| anyhow::anyhow!("config exec_max_output: {}", e))?,
+        (None, None) => DEFAULT_EXEC_MAX_OUTPUT,
+    };
+    let max = if max_output == 0 { usize::MAX } else { usize::try_from(max_output).unwrap_or(usize::MAX) };
+    if let Some(d) = &args.spill_dir { fs::create_dir_all(d)?; }
+
+    let tr = transport::from_config(cfg);
+    let mut results = Vec::new();
+    for n in targets {
+        let res = (|| {
+            let spill = |ext: &str| -> anyhow::Result<Option<Box<dyn std::io::Write + Send>>> {
+                let Some(d) = &args.spill_dir else { return Ok(None) };
+                let f = fs::File::create(d.join(format!("{}.{}", n.name, ext)))?;
+                Ok(Some(Box::new(std::io::BufWriter::new(f))))
+            };
+            let cap = transport::OutputCap { max, spill_stdout: spill("stdout")?, spill_stderr: spill("stderr")? };
+            tr.exec_capped(&n.host, &cmdline, cap)
+        })();
+        results.push((n, res));
+    }
+    config::sort_rows(cfg, cli.sort, &mut results, |(n, _)| &n.name);
+    let spilled = |name: &str, ext: &str| args.spill_dir.as_ref().map(|d| d.join(format!("{}.{}", name, ext)));
+    let marker = |bytes: u64, kept: usize, path: Option<PathBuf>| {
+        let full = path.map(|p| format!("; full output in {}", p.display())).unwrap_or_else(|| "; use --spill-dir for all of it".into());
+        format!("[... truncated: showing {} of {}{}]", transfer::format_size(kept as u64), transfer::format_size(bytes), full)
+    };
+
+    match cli.output {
+        Output::Human | Output::Csv | Output::Tsv | Output::Nagios => {
+            println!(
+                "Exec (prototype): selector='{}' cmd='{}' on {} node(s)",
+                selector, cmdline, results.len()
+            );
+            for (n, res) in &results {
+                match res {
+                    Ok(out) => {
+                        println!("=== {} ===\n{}", n.name, out.stdout);
+                        if out.stdout_truncated { println!("{}", marker(out.stdout_bytes, max.min(out.stdout_bytes as usize), spilled(&n.name, "stdout"))); }
+                        if !out.stderr.trim().is_empty() {
+                            eprintln!("--- {} (stderr) ---\n{}", n.name, out.stderr);
+                            if out.stderr_truncated { eprintln!("{}", marker(out.stderr_bytes, max.min(out.stderr_bytes as usize), spilled(&n.name, "stderr"))); }
+                        }
+                    }
+                    Err(e) => eprintln!("!!! {} error: {}", n.name, e),
+                }
+            }
+        }
+        Output::Json | Output::Jsonl | Output::Yaml => {
+            let arr: Vec<_> = results.iter().map(|(n, res)| match res {
+                Ok(out) => serde_json::json!({
+                    "node": n.name,
+                    "ok": true,
+                    "stdout": out.stdout,
+                    "stderr": out.stderr,
+                    "stdout_bytes": out.stdout_bytes,
+                    "stderr_bytes": out.stderr_bytes,
+                    "stdout_truncated": out.stdout_truncated,
+                    "stderr_truncated": out.stderr_truncated,
+                    "stdout_file": spilled(&n.name, "stdout"),
+                    "stderr_file": spilled(&n.name, "stderr"),
+                }),
+                Err(e) => serde_json::json!({
+                    "node": n.name,
+                    "ok": false,
+                    "error": e.to_string(),
+                }),
+            }).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+    let truncated: Vec<&str> = results.iter()
+        .filter(|(_, r)| r.as_ref().is_ok_and(|o| o.stdout_truncated || o.stderr_truncated))
+        .map(|(n, _)| n.name.as_str()).collect();
+    if !truncated.is_empty() {
+        log::warn!("output truncated at {} per stream on: {}", transfer::format_size(max_output), truncated.join(", "));
+    }
+    Ok(())
+}
+
+/// `node exec --stream`: all nodes at once, each line as `<node>: <line>` (stderr to stderr),
+/// or one JSON object per line.
+fn node_exec_stream(cli: &Cli, cfg: &config::Config, targets: &[&config::Node], cmdline: &str) -> anyhow::Result<()> {
+    let width = targets.iter().map(|n| n.name.len()).max().unwrap_or(0);
+    let cmd = transport::stoppable(cmdline);
+    let results: Vec<(&str, anyhow::Result<Option<i32>>)> = std::thread::scope(|s| {
+        let handles: Vec<_> = targets.iter().map(|n| {
+            let cmd = &cmd;
+            s.spawn(move || {
+                let tr = transport::from_config(cfg);
+                tr.exec_lines(&n.host, cmd, &mut |is_err, line| match cli.output {
+                    Output::Human | Output::Csv | Output::Tsv | Output::Nagios if is_err => eprintln!("{:width$} ! {}", n.name, line),
+                    Output::Human | Output::Csv | Output::Tsv | Output::Nagios => println!("{:width$} | {}", n.name, line),
+                    Output::Json | Output::Jsonl | Output::Yaml => {
+                        let _ = formats::print_record(cli.output, &serde_json::json!({
+                            "node": n.name,
+                            "stream": if is_err { "stderr" } else { "stdout" },
+                            "line": line,
+                        }));
+                    }
+                })
+            })
+        }).collect();
+        targets.iter().zip(handles)
+            .map(|(n, h)| (n.name.as_str(), h.join().unwrap_or_else(|_| Err(anyhow::anyhow!("exec thread panicked")))))
+            .collect()
+    });
+    for (node, res) in &results {
+        match res {
+            Ok(Some(0)) => {}
+            Ok(Some(code)) => eprintln!("!!! {} exited with {}", node, code),
+            Ok(None) => eprintln!("!!! {} was killed by a signal", node),
+            Err(e) => eprintln!("!!! {} error: {}", node, e),
+        }
+    }
+    Ok(())
+}
+
+fn cmd_node_fetch(cli: &Cli, cfg: &config::Config, args: &FetchArgs) -> anyhow::Result<()> {
+    let targets = config::select_nodes(cfg, &args.selector);
+    let tr = transport::from_config(cfg);
+    let base = std::path::Path::new(&args.path).file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_else(|| "file".into());
+    let opts = transfer::FetchOptions {
+        chunk_size: args.chunk_size,
+        max_size: args.max_size,
+        progress: matches!(cli.output, Output::Human),
+    };
+
+    let mut results = Vec::new();
+    for n in targets {
+        let dest = args.out.join(&n.name).join(&base);
+        results.push((n.name.clone(), transfer::fetch_file(tr.as_ref(), &n.host, &args.path, &dest, &opts)));
+    }
+
+    match cli.output {
+        Output::Human | Output::Csv | Output::Tsv | Output::Nagios => {
+            let mut table = comfy_table::Table::new();
+            table.load_preset(comfy_table::presets::UTF8_FULL);
+            table.set_header(vec!["Node", "File", "Size", "Fetched", "Compressed", "Status"]);
+            for (name, res) in &results {
+                match res {
+                    Ok(r) => {
+                        let status = match (r.truncated, r.resumed) {
+                            (true, true) => "TRUNCATED (resumed)",
+                            (true, false) => "TRUNCATED",
+                            (false, true) => "OK (resumed)",
+                            (false, false) => "OK",
+                        };
+                        let sev = if r.truncated { checks::Severity::Warning } else { checks::Severity::Ok };
+                        table.add_row(vec![
+                            comfy_table::Cell::new(name), comfy_table::Cell::new(r.local.display()),
+                            comfy_table::Cell::new(r.size), comfy_table::Cell::new(r.fetched), comfy_table::Cell::new(r.compressed), tables::status_cell(status, sev),
+                        ]);
+                    }
+                    Err(e) => {
+                        let mut row: Vec<comfy_table::Cell> = [name.as_str(), "", "", "", ""].into_iter().map(comfy_table::Cell::new).collect();
+                        row.push(tables::status_cell(format!("ERR: {:#}", e), checks::Severity::Failure));
+                        table.add_row(row);
+                    }
+                }
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        Output::Json | Output::Jsonl | Output::Yaml => {
+            let arr: Vec<_> = results.iter().map(|(name, res)| match res {
+                Ok(r) => serde_json::json!({
+                    "node": name,
+                    "ok": true,
+                    "local": r.local,
+                    "size": r.size,
+                    "fetched": r.fetched,
+                    "compressed": r.compressed,
+                    "truncated": r.truncated,
+                    "resumed": r.resumed,
+                }),
+                Err(e) => serde_json::json!({
+                    "node": name,
+                    "ok": false,
+                    "error": format!("{:#}", e),
+                }),
+            }).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    if failed > 0 {
+        log::warn!("fetch failed on {} node(s); rerun the same command to resume", failed);
+    }
+    Ok(())
+}
+
+fn cmd_completions(args: &CompletionsArgs) -> anyhow::Result<()> {
+    let mut wrote = Vec::new();
+    let outdir = if let Some(d) = &args.dir { d.clone() } else { std::env::current_dir()? };
+    fs::create_dir_all(&outdir)?;
+
+    let shells: Vec<Shell> = match args.shell {
+        Some(sh) => vec![sh.shell()],
+        None => vec![Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell, Shell::Elvish],
+    };
+
+    let mut cmd = Cli::command();
+    for sh in shells {
+        let path = generate_to(sh, &mut cmd, "beeg", &outdir)?;
+        wrote.push(path);
+    }
+    for p in wrote { println!("wrote completion: {}", p.display()); }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_status_json() {
+        let cli = Cli::parse_from(["beeg", "--output", "json", "status"]);
+        match cli.command { Commands::Status(_) => {}, _ => panic!("expected status"), }
+        assert!(matches!(cli.output, Output::Json));
+    }
+
+    #[test]
+    fn parse_plain_outputs() {
+        let cli = Cli::parse_from(["beeg", "--output", "csv", "node", "list"]);
+        assert!(matches!(cli.output, Output::Csv));
+        let cli = Cli::parse_from(["beeg", "--output", "tsv", "check", "time"]);
+        assert!(matches!(cli.output, Output::Tsv));
+        let cli = Cli::parse_from(["beeg", "--output", "yaml", "status"]);
+        assert!(matches!(cli.output, Output::Yaml));
+        let cli = Cli::parse_from(["beeg", "--output", "jsonl", "check", "all"]);
+        assert!(matches!(cli.output, Output::Jsonl));
+        let cli = Cli::parse_from(["beeg", "--output", "nagios", "check", "ofed"]);
+        assert!(matches!(cli.output, Output::Nagios));
+        let cli = Cli::parse_from(["beeg", "check", "time", "--no-color"]);
+        assert!(cli.no_color && matches!(cli.output, Output::Human));
+    }
+
+    #[test]
+    fn parse_log_flags() {
+        let cli = Cli::parse_from(["beeg", "check", "time", "--log-level", "debug", "--log-file", "run.jsonl", "--log-format", "json"]);
+        assert_eq!((cli.log_level, cli.log_format), (logging::LogLevel::Debug, logging::LogFormat::Json));
+        assert_eq!(cli.log_file.as_deref(), Some(std::path::Path::new("run.jsonl")));
+    }
+
+    #[test]
+    fn parse_status_short() {
+        let cli = Cli::parse_from(["beeg", "status", "--short", "--max-age", "60"]);
+        match cli.command { Commands::Status(a) => assert!(a.short && a.max_age == 60), _ => panic!("expected status"), }
+        assert!(Cli::try_parse_from(["beeg", "status", "--refresh"]).is_err());
+    }
+
+    #[test]
+    fn parse_status_cluster() {
+        let cli = Cli::parse_from(["beeg", "status", "-s", "storage", "--mgmt-node", "mgmt01"]);
+        match cli.command { Commands::Status(a) => assert_eq!((a.mgmt_node.as_deref(), a.timeout), (Some("mgmt01"), 10)), _ => panic!("expected status"), }
+        assert!(Cli::try_parse_from(["beeg", "status", "--short", "--mgmt-node", "m"]).is_err());
+    }
+
+    #[test]
+    fn parse_check_nvidia() {
+        let cli = Cli::parse_from(["beeg", "check", "nvidia-driver", "-s", "all"]);
+        match cli.command { Commands::Check(_) => {}, _ => panic!("expected check"), }
+    }
+
+    #[test]
+    fn parse_check_cuda() {
+        let cli = Cli::parse_from(["beeg", "check", "cuda", "-s", "gpu"]);
+        match cli.command { Commands::Check(_) => {}, _ => panic!("expected check cuda"), }
+    }
+
+    #[test]
+    fn parse_check_require() {
+        let cli = Cli::parse_from(["beeg", "check", "cuda", "--require", "cuda>=12.2", "driver>=535", "ofed>=23.10", "-s", "gpu"]);
+        match cli.command { Commands::Check(c) if c.name == "cuda" => {}, _ => panic!("expected check cuda"), }
+        let cli = Cli::parse_from(["beeg", "check", "all", "--require", "driver>=535"]);
+        match cli.command { Commands::Check(c) if c.name == "all" => {}, _ => panic!("expected check all"), }
+        assert!(Cli::try_parse_from(["beeg", "check", "ofed", "--require", "ofed~23"]).is_err());
+    }
 
     #[test]
-    fn parse_check_cuda() {
-        let cli = Cli::parse_from(["beeg", "check", "cuda", "-s", "gpu"]);
-        match cli.command { Commands::Check(_) => {}, _ => panic!("expected check cuda"), }
+    fn parse_export_prometheus() {
+        let cli = Cli::parse_from(["beeg", "export", "prometheus", "--listen", "127.0.0.1:9188", "--mgmt-node", "mgmt01", "--mount", "/mnt/beegfs"]);
+        match cli.command {
+            Commands::Export(ExportCmd::Prometheus(a)) => {
+                assert_eq!((a.listen.as_str(), a.interval, a.selector.as_str()), ("127.0.0.1:9188", 60, "all"));
+                assert_eq!(a.mgmt_node.as_deref(), Some("mgmt01"));
+                assert!(a.storage_node.is_none() && !a.once);
+            }
+            _ => panic!("expected export prometheus"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "export", "prometheus", "--interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_logs_collect() {
+        let cli = Cli::parse_from(["beeg", "logs", "collect", "-s", "storage", "--since", "1h", "--out", "bundle", "--max-file-size", "64M"]);
+        match cli.command {
+            Commands::Logs(LogsCmd::Collect(a)) => {
+                assert_eq!((a.selector.as_str(), a.range.since.as_deref(), a.out), ("storage", Some("1h"), PathBuf::from("bundle")));
+                assert_eq!(a.max_file_size, 64 << 20);
+            }
+            _ => panic!("expected logs collect"),
+        }
+    }
+
+    #[test]
+    fn parse_support_bundle() {
+        let cli = Cli::parse_from(["beeg", "support-bundle", "--since", "2d", "--mgmt-node", "mgmt01", "--no-logs"]);
+        match cli.command {
+            Commands::SupportBundle(a) => {
+                assert_eq!((a.selector.as_str(), a.range.since.as_deref(), a.mgmt_node.as_deref()), ("all", Some("2d"), Some("mgmt01")));
+                assert!(a.no_logs && a.out.is_none());
+                assert_eq!(a.max_file_size, 64 << 20);
+            }
+            _ => panic!("expected support-bundle"),
+        }
+    }
+
+    #[test]
+    fn parse_bench_storage() {
+        let cli = Cli::parse_from(["beeg", "bench", "storage", "--targets", "101,102", "--blocksize", "512K", "--size", "1G", "--mode", "write"]);
+        match cli.command {
+            Commands::Bench(BenchCmd::Storage(a)) => {
+                assert_eq!((a.targets.as_str(), a.blocksize, a.size), ("101,102", 512 << 10, 1 << 30));
+                assert_eq!((a.mode, a.threads, a.slow_percent), (bench::storage::Mode::Write, 1, 75));
+                assert!(a.node.is_none() && !a.keep_files);
+            }
+            _ => panic!("expected bench storage"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "bench", "storage", "--threads", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_bench_client() {
+        let cli = Cli::parse_from(["beeg", "bench", "client", "--mount", "/mnt/beegfs", "-s", "clients", "--mode", "dd", "--save-baseline"]);
+        match cli.command {
+            Commands::Bench(BenchCmd::Client(a)) => {
+                assert_eq!((a.mount.as_str(), a.selector.as_str(), a.mode), ("/mnt/beegfs", "clients", bench::client::Mode::Dd));
+                assert_eq!((a.size, a.blocksize, a.regress_percent), (4 << 30, 1 << 20, 20));
+                assert!(a.save_baseline);
+            }
+            _ => panic!("expected bench client"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "bench", "client", "-s", "clients"]).is_err());
+    }
+
+    #[test]
+    fn parse_baseline() {
+        let cli = Cli::parse_from(["beeg", "baseline", "save", "pre-fw", "--force", "--", "check", "versions", "-s", "all"]);
+        match cli.command {
+            Commands::Baseline(BaselineCmd::Save(a)) => {
+                assert_eq!((a.name.as_str(), a.force), ("pre-fw", true));
+                assert_eq!(a.command, vec!["check", "versions", "-s", "all"]);
+            }
+            _ => panic!("expected baseline save"),
+        }
+        let cli = Cli::parse_from(["beeg", "baseline", "compare", "pre-fw", "--threshold", "10"]);
+        match cli.command { Commands::Baseline(BaselineCmd::Compare(a)) => assert_eq!(a.threshold, 10), _ => panic!("expected baseline compare") }
+        assert!(Cli::try_parse_from(["beeg", "baseline", "save", "pre-fw"]).is_err());
+    }
+
+    #[test]
+    fn parse_daemon() {
+        let cli = Cli::parse_from(["beeg", "daemon", "--interval", "5m", "--checks", "ofed,client-mount,storage-target", "--mount", "/mnt/beegfs", "--webhook", "https://hooks.example/beeg"]);
+        match cli.command {
+            Commands::Daemon(a) => {
+                assert_eq!((a.interval, a.checks.len(), a.webhook.len()), (300, 3, 1));
+                assert_eq!(a.mount.as_deref(), Some("/mnt/beegfs"));
+            }
+            _ => panic!("expected daemon"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "daemon"]).is_err());
+        assert!(Cli::try_parse_from(["beeg", "daemon", "--checks", "ofed", "--interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_history() {
+        let cli = Cli::parse_from(["beeg", "history", "show", "--check", "nvidia-driver", "--node", "node-3", "--since", "7d"]);
+        match cli.command {
+            Commands::History(HistoryCmd::Show(a)) => {
+                assert_eq!((a.filter.check.as_deref(), a.filter.node.as_deref()), (Some("nvidia-driver"), Some("node-3")));
+                assert_eq!(a.filter.range.since.as_deref(), Some("7d"));
+            }
+            _ => panic!("expected history show"),
+        }
+        let cli = Cli::parse_from(["beeg", "history", "list", "--failing", "--limit", "10"]);
+        match cli.command { Commands::History(HistoryCmd::List(a)) => assert_eq!((a.failing, a.limit), (true, 10)), _ => panic!("expected history list") }
+    }
+
+    #[test]
+    fn parse_bench_meta() {
+        let cli = Cli::parse_from(["beeg", "bench", "meta", "--mount", "/mnt/beegfs", "--files", "1000", "--min", "create=5000", "--min", "stat=20000"]);
+        match cli.command {
+            Commands::Bench(BenchCmd::Meta(a)) => {
+                assert_eq!((a.files, a.mode), (1000, bench::meta::Mode::Auto));
+                assert_eq!(a.min, vec![("create".to_string(), 5000.0), ("stat".to_string(), 20000.0)]);
+            }
+            _ => panic!("expected bench meta"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "bench", "meta", "--mount", "/m", "--min", "mkdir=1"]).is_err());
+    }
+
+    #[test]
+    fn parse_check_watch() {
+        let cli = Cli::parse_from(["beeg", "check", "storage-target", "--node", "stor01", "--watch", "5"]);
+        match cli.command { Commands::Check(c) => assert_eq!(c.watch(), Some(5)), _ => panic!("expected check storage-target") }
+        let cli = Cli::parse_from(["beeg", "check", "versions"]);
+        match cli.command { Commands::Check(c) => assert_eq!(c.watch(), None), _ => panic!("expected check versions") }
+        assert!(Cli::try_parse_from(["beeg", "check", "time", "--watch", "0"]).is_err());
+        assert!(Cli::try_parse_from(["beeg", "check", "meta-mirror", "--mount", "/m", "--writer", "a", "--reader", "b", "--watch", "5"]).is_err());
+    }
+
+    #[test]
+    fn parse_report_file() {
+        let cli = Cli::parse_from(["beeg", "--output", "human", "check", "all", "--mount", "/mnt/beegfs", "--report-file", "out/beeg.json", "--report-file", "junit=out/beeg.xml"]);
+        match cli.command {
+            Commands::Check(c) => {
+                let paths: Vec<PathBuf> = c.report_files().into_iter().map(|f| f.path).collect();
+                assert_eq!(paths, vec![PathBuf::from("out/beeg.json"), PathBuf::from("out/beeg.xml")]);
+            }
+            _ => panic!("expected check all"),
+        }
+        let cli = Cli::parse_from(["beeg", "check", "time"]);
+        match cli.command { Commands::Check(c) => assert!(c.report_files().is_empty()), _ => panic!("expected check time") }
+    }
+
+    #[test]
+    fn parse_check_helperd() {
+        let cli = Cli::parse_from(["beeg", "check", "helperd", "-s", "client", "--max-log-age-hours", "24"]);
+        match cli.command { Commands::Check(c) if c.name == "helperd" => {}, _ => panic!("expected check helperd"), }
+    }
+
+    #[test]
+    fn parse_sort() {
+        let cli = Cli::parse_from(["beeg", "--sort", "host", "check", "versions"]);
+        assert_eq!(cli.sort, config::NodeOrder::Host);
+        assert_eq!(Cli::parse_from(["beeg", "node", "list"]).sort, config::NodeOrder::Config);
+        assert!(Cli::try_parse_from(["beeg", "--sort", "rack", "node", "list"]).is_err());
     }
 
     #[test]
@@ -318,27 +2115,501 @@ mod tests {
         match cli.command { Commands::Check(_) => {}, _ => panic!("expected check nvidia-fs"), }
     }
 
+    #[test]
+    fn parse_check_gds() {
+        let cli = Cli::parse_from(["beeg", "check", "gds", "--mount", "/mnt/beegfs", "--selector", "gpu", "--gdsio"]);
+        match cli.command { Commands::Check(c) if c.name == "gds" => {}, _ => panic!("expected check gds"), }
+        assert!(Cli::try_parse_from(["beeg", "check", "gds", "-s", "gpu"]).is_err());
+    }
+
+    #[test]
+    fn parse_check_gpu_inventory() {
+        let cli = Cli::parse_from(["beeg", "check", "gpu-inventory", "-s", "gpu", "--group-by", "dgx,hgx"]);
+        match cli.command { Commands::Check(c) if c.name == "gpu-inventory" => {}, _ => panic!("expected check gpu-inventory"), }
+    }
+
     #[test]
     fn parse_check_ofed() {
         let cli = Cli::parse_from(["beeg", "check", "ofed", "-s", "all"]);
         match cli.command { Commands::Check(_) => {}, _ => panic!("expected check ofed"), }
     }
 
+    #[test]
+    fn parse_check_netdev() {
+        let cli = Cli::parse_from(["beeg", "check", "netdev", "--expect-mtu", "9000", "--expect-rate", "100G"]);
+        match cli.command { Commands::Check(c) if c.name == "netdev" => {}, _ => panic!("expected check netdev"), }
+        assert!(Cli::try_parse_from(["beeg", "check", "netdev", "--expect-rate", "fast"]).is_err());
+    }
+
+    #[test]
+    fn parse_check_sysctl() {
+        let cli = Cli::parse_from(["beeg", "check", "sysctl", "-s", "storage", "--profile", "tuning.yaml"]);
+        match cli.command { Commands::Check(c) if c.name == "sysctl" => {}, _ => panic!("expected check sysctl"), }
+    }
+
+    #[test]
+    fn parse_check_disks() {
+        let cli = Cli::parse_from(["beeg", "check", "disks", "--selector", "storage", "--meta", "--wear-warn", "70"]);
+        match cli.command { Commands::Check(c) if c.name == "disks" => {}, _ => panic!("expected check disks"), }
+    }
+
+    #[test]
+    fn parse_check_target_fs() {
+        let cli = Cli::parse_from(["beeg", "check", "target-fs", "--selector", "storage", "--meta-fs", "xfs"]);
+        match cli.command { Commands::Check(c) if c.name == "target-fs" => {}, _ => panic!("expected check target-fs"), }
+    }
+
+    #[test]
+    fn parse_check_memory() {
+        let cli = Cli::parse_from(["beeg", "check", "memory", "--selector", "storage", "--interval", "5", "--conn-percent", "10"]);
+        match cli.command { Commands::Check(c) if c.name == "memory" => {}, _ => panic!("expected check memory"), }
+    }
+
+    #[test]
+    fn parse_check_firewall() {
+        let cli = Cli::parse_from(["beeg", "check", "firewall", "--selector", "client", "--no-connect"]);
+        match cli.command { Commands::Check(c) if c.name == "firewall" => {}, _ => panic!("expected check firewall"), }
+    }
+
+    #[test]
+    fn parse_check_dns() {
+        let cli = Cli::parse_from(["beeg", "check", "dns", "--selector", "all", "--no-reverse"]);
+        match cli.command { Commands::Check(c) if c.name == "dns" => {}, _ => panic!("expected check dns"), }
+    }
+
+    #[test]
+    fn parse_check_conn_config() {
+        let cli = Cli::parse_from(["beeg", "check", "conn-config", "--selector", "client"]);
+        match cli.command { Commands::Check(c) if c.name == "conn-config" => {}, _ => panic!("expected check conn-config"), }
+    }
+
+    #[test]
+    fn parse_check_ib_fabric() {
+        let cli = Cli::parse_from(["beeg", "check", "ib-fabric", "-s", "storage", "-d", "mlx5_0,mlx5_1", "--expected-rate", "200"]);
+        match cli.command { Commands::Check(c) if c.name == "ib-fabric" => {}, _ => panic!("expected check ib-fabric"), }
+    }
+
     #[test]
     fn parse_check_client_mount() {
         let cli = Cli::parse_from(["beeg", "check", "client-mount", "--mount", "/mnt/beegfs", "-s", "all"]);
         match cli.command { Commands::Check(_) => {}, _ => panic!("expected check client mount"), }
     }
 
+    #[test]
+    fn parse_check_client_mount_no_tui() {
+        assert!(Cli::try_parse_from(["beeg", "check", "client-mount", "--mount", "/mnt/beegfs", "--no-tui"]).is_ok());
+    }
+
+    #[test]
+    fn parse_check_client_mount_export() {
+        assert!(Cli::try_parse_from(["beeg", "check", "client-mount", "--mount", "/mnt/beegfs", "--export", "results.csv"]).is_ok());
+        assert!(Cli::try_parse_from(["beeg", "check", "client-mount", "--mount", "/mnt/beegfs", "--export"]).is_err());
+    }
+
+    #[test]
+    fn parse_service() {
+        let cli = Cli::parse_from(["beeg", "service", "restart", "storage", "-s", "role=storage", "--serial", "2", "--yes"]);
+        match cli.command {
+            Commands::Service(a) => {
+                assert_eq!((a.action, a.service, a.serial, a.yes, a.verify_timeout), (service::Action::Restart, service::Service::Storage, 2, true, 60));
+                assert_eq!(a.selector, "role=storage");
+            }
+            _ => panic!("expected service"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "service", "status", "helperd"]).is_ok());
+        assert!(Cli::try_parse_from(["beeg", "service", "reload", "meta"]).is_err());
+        assert!(Cli::try_parse_from(["beeg", "service", "restart", "meta", "--serial", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_rollout() {
+        let cli = Cli::parse_from(["beeg", "rollout", "restart", "beegfs-storage", "--selector", "storage", "--serial", "1", "--wait-healthy"]);
+        match cli.command {
+            Commands::Rollout(RolloutCmd::Restart(a)) => {
+                assert_eq!((a.service, a.serial, a.wait_healthy, a.health_timeout, a.settle), (service::Service::Storage, 1, true, 600, 30));
+                assert_eq!(a.selector, "storage");
+            }
+            _ => panic!("expected rollout restart"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "rollout", "restart", "beegfs-mon"]).is_err());
+    }
+
+    #[test]
+    fn parse_drain() {
+        match Cli::parse_from(["beeg", "node", "drain", "stor01", "--settle", "10", "-y"]).command {
+            Commands::Node(NodeCmd::Drain(a)) => assert_eq!((a.selector.as_str(), a.settle, a.settle_timeout, a.yes), ("stor01", 10, 600, true)),
+            _ => panic!("expected node drain"),
+        }
+        assert!(matches!(Cli::parse_from(["beeg", "node", "undrain", "stor01"]).command, Commands::Node(NodeCmd::Undrain(_))));
+        assert!(Cli::try_parse_from(["beeg", "node", "drain"]).is_err());
+    }
+
+    #[test]
+    fn parse_fsck() {
+        match Cli::parse_from(["beeg", "fsck", "run", "--mode", "check-fs", "--selector", "mgmt01", "--fsck-arg", "--overwriteDbFile"]).command {
+            Commands::Fsck(FsckCmd::Run(a)) => {
+                assert_eq!((a.mode, a.selector.as_str(), a.fix), (fsck::Mode::CheckFs, "mgmt01", false));
+                assert_eq!(a.fsck_args, vec!["--overwriteDbFile"]);
+            }
+            _ => panic!("expected fsck run"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "fsck", "run"]).is_err());
+        assert!(Cli::try_parse_from(["beeg", "fsck", "run", "-s", "mgmt01", "--mode", "repair"]).is_err());
+    }
+
+    #[test]
+    fn parse_resync() {
+        match Cli::parse_from(["beeg", "resync", "status", "--group", "all", "--watch"]).command {
+            Commands::Resync(ResyncCmd::Status(a)) => assert_eq!((a.group.as_str(), a.nodetype.as_str(), a.watch, a.stall), ("all", "storage", Some(10), 300)),
+            _ => panic!("expected resync status"),
+        }
+        match Cli::parse_from(["beeg", "resync", "status", "--group", "1,2", "--nodetype", "meta", "--watch", "5"]).command {
+            Commands::Resync(ResyncCmd::Status(a)) => assert_eq!((a.group.as_str(), a.nodetype.as_str(), a.watch), ("1,2", "meta", Some(5))),
+            _ => panic!("expected resync status"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "resync", "status", "--nodetype", "client"]).is_err());
+    }
+
+    #[test]
+    fn parse_mount() {
+        let cli = Cli::parse_from(["beeg", "mount", "ensure", "--mount", "/mnt/beegfs", "--selector", "clients", "--persist", "systemd"]);
+        match cli.command {
+            Commands::Mount(MountCmd::Ensure(a)) => {
+                assert_eq!((a.mount.as_str(), a.selector.as_str(), a.persist, a.dry_run), ("/mnt/beegfs", "clients", mount::Persist::Systemd, false));
+                assert_eq!(a.client_conf, "/etc/beegfs/beegfs-client.conf");
+            }
+            _ => panic!("expected mount ensure"),
+        }
+        match Cli::parse_from(["beeg", "mount", "remove", "-s", "cli01", "-y"]).command {
+            Commands::Mount(MountCmd::Remove(a)) => assert_eq!((a.mount.as_str(), a.yes), ("/mnt/beegfs", true)),
+            _ => panic!("expected mount remove"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "mount", "ensure", "--persist", "autofs"]).is_err());
+    }
+
+    #[test]
+    fn parse_entry_info() {
+        let cli = Cli::parse_from(["beeg", "entry", "info", "/mnt/beegfs/data/x.h5", "--selector", "client-a"]);
+        match cli.command {
+            Commands::Entry(EntryCmd::Info(a)) => { assert_eq!(a.path, "/mnt/beegfs/data/x.h5"); assert_eq!(a.selector, "client-a"); }
+            _ => panic!("expected entry info"),
+        }
+    }
+
+    #[test]
+    fn parse_verify_checksums() {
+        let cli = Cli::parse_from(["beeg", "verify", "checksums", "--path", "/mnt/beegfs/dataset", "--manifest", "sha256sums.txt", "--clients", "8"]);
+        match cli.command {
+            Commands::Verify(VerifyCmd::Checksums(a)) => { assert_eq!(a.clients, 8); assert_eq!(a.selector, "all"); }
+            _ => panic!("expected verify checksums"),
+        }
+    }
+
+    #[test]
+    fn parse_migrate_verify() {
+        let cli = Cli::parse_from(["beeg", "migrate", "verify", "--src", "/mnt/beegfs/old", "--dst", "/mnt/beegfs/new", "--report", "r.json", "--sign-key", "id_ed25519"]);
+        match cli.command {
+            Commands::Migrate(MigrateCmd::Verify(a)) => {
+                assert_eq!((a.src.as_str(), a.dst.as_str()), ("/mnt/beegfs/old", "/mnt/beegfs/new"));
+                assert_eq!((a.sample, a.clients), (1000, 8));
+                assert!(a.sign_key.is_some());
+            }
+            _ => panic!("expected migrate verify"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "migrate", "verify", "--src", "a", "--dst", "b", "--sign-key", "k"]).is_err());
+    }
+
+    #[test]
+    fn parse_plan_placement() {
+        let cli = Cli::parse_from(["beeg", "plan", "placement", "--size", "200T", "--pool", "capacity", "--stripe", "4", "--node", "mgmt01"]);
+        match cli.command {
+            Commands::Plan(PlanCmd::Placement(a)) => {
+                assert_eq!(a.size, 200 << 40);
+                assert_eq!((a.pool.as_deref(), a.stripe, a.selector.as_str()), (Some("capacity"), 4, "mgmt01"));
+            }
+            _ => panic!("expected plan placement"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "plan", "placement", "--size", "lots", "--node", "m"]).is_err());
+    }
+
+    #[test]
+    fn parse_import_inventory() {
+        let cli = Cli::parse_from(["beeg", "config", "import-inventory", "--format", "ansible", "path/to/hosts.ini", "--dry-run"]);
+        match cli.command {
+            Commands::Config(ConfigCmd::ImportInventory(a)) => {
+                assert_eq!(a.path, PathBuf::from("path/to/hosts.ini"));
+                assert!(a.dry_run && !a.replace);
+            }
+            _ => panic!("expected config import-inventory"),
+        }
+    }
+
+    #[test]
+    fn parse_config_get() {
+        let cli = Cli::parse_from(["beeg", "config", "get", "--key", "sysMgmtdHost", "--file", "meta"]);
+        match cli.command {
+            Commands::Config(ConfigCmd::Get(a)) => assert_eq!((a.key.as_str(), a.file.as_str(), a.selector), ("sysMgmtdHost", "meta", None)),
+            _ => panic!("expected config get"),
+        }
+    }
+
+    #[test]
+    fn parse_config_diff() {
+        let cli = Cli::parse_from(["beeg", "config", "diff", "--file", "beegfs-client.conf", "--selector", "all", "--reference", "c01", "--ignore", "logLevel,connInterfacesFile"]);
+        match cli.command {
+            Commands::Config(ConfigCmd::Diff(a)) => {
+                assert_eq!((a.file.as_str(), a.reference.as_deref()), ("beegfs-client.conf", Some("c01")));
+                assert_eq!(a.ignore, vec!["logLevel", "connInterfacesFile"]);
+            }
+            _ => panic!("expected config diff"),
+        }
+    }
+
+    #[test]
+    fn parse_config_set() {
+        let cli = Cli::parse_from(["beeg", "config", "set", "--key", "connMgmtdPortTCP", "--value", "8108", "-s", "role=storage", "--file", "storage", "--dry-run"]);
+        match cli.command {
+            Commands::Config(ConfigCmd::Set(a)) => {
+                assert_eq!((a.file.as_str(), a.timeout), ("storage", 30));
+                assert!(a.dry_run && a.confirm && !a.yes);
+            }
+            _ => panic!("expected config set"),
+        }
+        let cli = Cli::parse_from(["beeg", "config", "set", "--key", "k", "--value", "v", "--yes"]);
+        match cli.command { Commands::Config(ConfigCmd::Set(a)) => assert!(a.yes && !a.dry_run), _ => panic!("expected config set") }
+    }
+
+    #[test]
+    fn parse_ssh_config_generate() {
+        let cli = Cli::parse_from(["beeg", "ssh-config", "generate", "--out", "/tmp/beeg.ssh", "--check"]);
+        match cli.command {
+            Commands::SshConfig(SshConfigCmd::Generate(a)) => { assert!(a.check); assert_eq!(a.selector, "all"); }
+            _ => panic!("expected ssh-config generate"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "ssh-config", "generate", "--check"]).is_err());
+    }
+
+    #[test]
+    fn parse_completions() {
+        let cli = Cli::parse_from(["beeg", "completions", "--shell", "zsh", "--dir", "/opt/beeg/completions"]);
+        match cli.command { Commands::Completions(a) => assert!(a.cmd.is_none() && a.dir.is_some()), _ => panic!("expected completions") }
+        let cli = Cli::parse_from(["beeg", "completions", "install", "--shell", "fish", "--system"]);
+        match cli.command {
+            Commands::Completions(CompletionsArgs { cmd: Some(CompletionsCmd::Install(a)), .. }) => assert!(a.system && !a.print),
+            _ => panic!("expected completions install"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "completions", "install", "--print", "--system"]).is_err());
+    }
+
+    #[test]
+    fn parse_resume_last() {
+        let cli = Cli::parse_from(["beeg", "resume", "last"]);
+        assert!(matches!(cli.command, Commands::Resume(ResumeCmd::Last)));
+    }
+
+    #[test]
+    fn parse_jump_host() {
+        let cli = Cli::parse_from(["beeg", "node", "list", "--jump-host", "admin@bastion:2222"]);
+        assert_eq!(cli.jump_host.as_deref(), Some("admin@bastion:2222"));
+    }
+
+    #[test]
+    fn parse_backend() {
+        let cli = Cli::parse_from(["beeg", "check", "capacity", "--selector", "mgmt01", "--backend", "beegfs-tool"]);
+        assert_eq!(cli.backend, Some(config::Backend::BeegfsTool));
+        assert!(child_args(&cli).ends_with(&["--backend".to_string(), "beegfs-tool".to_string()]));
+        assert_eq!(Cli::parse_from(["beeg", "status"]).backend, None);
+    }
+
+    #[test]
+    fn parse_usage() {
+        let cli = Cli::parse_from(["beeg", "usage", "stats", "--days", "30"]);
+        match cli.command { Commands::Usage(UsageCmd::Stats(a)) => assert_eq!(a.days, Some(30)), _ => panic!("expected usage stats") }
+        let cli = Cli::parse_from(["beeg", "usage", "enable", "--endpoint", "https://metrics.example/beeg"]);
+        match cli.command { Commands::Usage(UsageCmd::Enable(a)) => assert!(a.endpoint.is_some()), _ => panic!("expected usage enable") }
+    }
+
+    #[test]
+    fn parse_self_update() {
+        let cli = Cli::parse_from(["beeg", "self", "update", "--version", "0.4.2", "--check"]);
+        match cli.command {
+            Commands::SelfCmd(SelfCmd::Update(a)) => {
+                assert_eq!(a.channel, "stable");
+                assert_eq!(a.target_version.as_deref(), Some("0.4.2"));
+                assert!(a.check && !a.force && !a.insecure);
+            }
+            _ => panic!("expected self update"),
+        }
+    }
+
+    #[test]
+    fn parse_record_session() {
+        let cli = Cli::parse_from(["beeg", "check", "client-mount", "--mount", "/mnt/beegfs", "--record-session", "incident.cast"]);
+        assert_eq!(cli.record_session, Some(PathBuf::from("incident.cast")));
+    }
+
     #[test]
     fn parse_check_storage_target() {
         let cli = Cli::parse_from(["beeg", "check", "storage-target", "--selector", "node-a", "--targets", "all"]);
         match cli.command { Commands::Check(_) => {}, _ => panic!("expected check storage-target"), }
     }
 
+    #[test]
+    fn parse_check_meta() {
+        let cli = Cli::parse_from(["beeg", "check", "meta", "--selector", "mgmt01"]);
+        match cli.command { Commands::Check(c) if c.name == "meta" => {}, _ => panic!("expected check meta"), }
+    }
+
+    #[test]
+    fn parse_check_mgmtd() {
+        let cli = Cli::parse_from(["beeg", "check", "mgmtd", "--selector", "all", "--expect", "mgmt01"]);
+        match cli.command { Commands::Check(c) if c.name == "mgmtd" => {}, _ => panic!("expected check mgmtd"), }
+    }
+
+    #[test]
+    fn parse_check_buddy_groups() {
+        let cli = Cli::parse_from(["beeg", "check", "buddy-groups", "--selector", "mgmt01", "--nodetype", "meta"]);
+        match cli.command { Commands::Check(c) if c.name == "buddy-groups" => {}, _ => panic!("expected check buddy-groups"), }
+        assert!(Cli::try_parse_from(["beeg", "check", "buddy-groups", "--selector", "mgmt01", "--nodetype", "client"]).is_err());
+    }
+
+    #[test]
+    fn parse_check_time() {
+        let cli = Cli::parse_from(["beeg", "check", "time", "--selector", "all", "--max-skew-ms", "200", "--reference", "mgmt01"]);
+        match cli.command { Commands::Check(c) if c.name == "time" => {}, _ => panic!("expected check time"), }
+    }
+
+    #[test]
+    fn parse_check_rdma_bandwidth() {
+        let cli = Cli::parse_from(["beeg", "check", "rdma-bandwidth", "--client", "c01", "--server", "s01", "--min-gbps", "90", "--tests", "send"]);
+        match cli.command { Commands::Check(c) if c.name == "rdma-bandwidth" => {}, _ => panic!("expected check rdma-bandwidth"), }
+        assert!(Cli::try_parse_from(["beeg", "check", "rdma-bandwidth", "--client", "c01", "--server", "s01", "--tests", "atomic"]).is_err());
+    }
+
+    #[test]
+    fn parse_check_versions() {
+        let cli = Cli::parse_from(["beeg", "check", "versions", "-s", "all"]);
+        match cli.command { Commands::Check(c) if c.name == "versions" => {}, _ => panic!("expected check versions"), }
+    }
+
+    #[test]
+    fn parse_check_all() {
+        let cli = Cli::parse_from(["beeg", "check", "all", "--selector", "all", "--mount", "/mnt/beegfs", "--storage-node", "node-a"]);
+        match cli.command { Commands::Check(c) if c.name == "all" => {}, _ => panic!("expected check all"), }
+    }
+
+    #[test]
+    fn parse_check_meta_mirror() {
+        let cli = Cli::parse_from(["beeg", "check", "meta-mirror", "--mount", "/mnt/beegfs", "--writer", "c1", "--reader", "c2", "--yes"]);
+        match cli.command { Commands::Check(_) => {}, _ => panic!("expected check meta-mirror"), }
+    }
+
+    #[test]
+    fn parse_node_fetch() {
+        let cli = Cli::parse_from(["beeg", "node", "fetch", "-s", "all", "--path", "/var/log/beegfs-client.log", "--max-size", "1G"]);
+        match cli.command { Commands::Node(NodeCmd::Fetch(a)) => assert_eq!(a.max_size, Some(1 << 30)), _ => panic!("expected node fetch"), }
+    }
+
+    #[test]
+    fn parse_node_exec_caps() {
+        let cli = Cli::parse_from(["beeg", "node", "exec", "--max-output", "4M", "--spill-dir", "/tmp/out", "--", "journalctl", "-b"]);
+        match cli.command {
+            Commands::Node(NodeCmd::Exec(a)) => {
+                assert_eq!(a.max_output, Some(4 << 20));
+                assert_eq!(a.cmd, vec!["journalctl", "-b"]);
+            }
+            _ => panic!("expected node exec"),
+        }
+    }
+
+    #[test]
+    fn parse_transport_debug() {
+        let cli = Cli::parse_from(["beeg", "transport", "debug", "node-a", "--port", "2222"]);
+        match cli.command {
+            Commands::Transport(TransportCmd::Debug(a)) => { assert_eq!(a.node, "node-a"); assert_eq!(a.port, Some(2222)); }
+            _ => panic!("expected transport debug"),
+        }
+    }
+
+    #[test]
+    fn parse_note_add() {
+        let cli = Cli::parse_from(["beeg", "note", "add", "node", "s07", "pending RMA on nvme1"]);
+        match cli.command {
+            Commands::Note(NoteCmd::Add(a)) => {
+                assert_eq!(a.kind, notes::NoteKind::Node);
+                assert_eq!(a.subject, "s07");
+                assert_eq!(a.text, "pending RMA on nvme1");
+            }
+            _ => panic!("expected note add"),
+        }
+    }
+
+    #[test]
+    fn parse_quota_show() {
+        let cli = Cli::parse_from(["beeg", "quota", "show", "--node", "mgmt01", "--all", "--groups", "--sort", "files"]);
+        match cli.command {
+            Commands::Quota(QuotaCmd::Show(a)) => { assert!(a.all && a.groups); assert_eq!(a.sort, quota::SortBy::Files); }
+            _ => panic!("expected quota show"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "quota", "show", "--node", "mgmt01"]).is_err());
+        assert!(Cli::try_parse_from(["beeg", "quota", "show", "--node", "m", "--uid", "1", "--gid", "2"]).is_err());
+    }
+
+    #[test]
+    fn parse_dashboard() {
+        let cli = Cli::parse_from(["beeg", "dashboard", "-s", "storage", "--interval", "5"]);
+        match cli.command { Commands::Dashboard(a) => assert_eq!((a.selector.as_str(), a.interval), ("storage", 5)), _ => panic!("expected dashboard") }
+        assert!(Cli::try_parse_from(["beeg", "dashboard", "--interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_node_facts() {
+        let cli = Cli::parse_from(["beeg", "node", "facts", "-s", "gpu"]);
+        match cli.command { Commands::Node(NodeCmd::Facts(a)) => assert_eq!(a.selector, "gpu"), _ => panic!("expected node facts"), }
+        let cli = Cli::parse_from(["beeg", "node", "facts", "gpu-env", "--selector", "gpu", "--diff", "gpu01"]);
+        match cli.command {
+            Commands::Node(NodeCmd::Facts(FactsArgs { cmd: Some(FactsCmd::GpuEnv(a)), .. })) => assert_eq!((a.selector.as_str(), a.diff.as_deref()), ("gpu", Some("gpu01"))),
+            _ => panic!("expected node facts gpu-env"),
+        }
+    }
+
+    #[test]
+    fn check_registry() {
+        let names: Vec<&str> = checks::REGISTRY.iter().map(|r| r.name).collect();
+        let mut dedup = names.clone();
+        dedup.sort();
+        dedup.dedup();
+        assert_eq!(dedup.len(), names.len(), "duplicate check names");
+        assert!(!names.contains(&"list"));
+        let cli = Cli::parse_from(["beeg", "check", "list"]);
+        match cli.command { Commands::Check(c) => assert_eq!(c.name, "list"), _ => panic!("expected check list"), }
+        assert!(Cli::try_parse_from(["beeg", "check", "no-such-check"]).is_err());
+    }
+
+    #[test]
+    fn severity_exit_codes() {
+        use checks::Severity;
+        assert_eq!(Severity::Ok.exit_code(false), 0);
+        assert_eq!(Severity::Warning.exit_code(false), 1);
+        assert_eq!(Severity::Warning.exit_code(true), 2);
+        assert_eq!(Severity::Failure.exit_code(false), 2);
+        let cli = Cli::parse_from(["beeg", "check", "cuda", "--strict"]);
+        assert!(cli.strict);
+    }
+
     #[test]
     fn parse_node_exec() {
         let cli = Cli::parse_from(["beeg", "node", "exec", "--", "echo", "hi"]);
         match cli.command { Commands::Node(NodeCmd::Exec(_)) => {}, _ => panic!("expected node exec"), }
     }
+
+    #[test]
+    fn parse_node_exec_stream() {
+        let cli = Cli::parse_from(["beeg", "node", "exec", "--stream", "-s", "storage", "--", "dmesg", "-w"]);
+        match cli.command {
+            Commands::Node(NodeCmd::Exec(a)) => assert!(a.stream && a.cmd == ["dmesg", "-w"]),
+            _ => panic!("expected node exec"),
+        }
+        assert!(Cli::try_parse_from(["beeg", "node", "exec", "--stream", "--spill-dir", "/tmp/x", "--", "true"]).is_err());
+    }
 }
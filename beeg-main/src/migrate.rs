@@ -0,0 +1,387 @@
+use crate::checks::{wrap_timeout, Severity};
+use crate::verify::{read_sums, spread, sum_cmd};
+use crate::{config, timefmt, transfer, transport};
+use anyhow::{Context, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Size and mtime of one regular file, from `find -printf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FileMeta {
+    pub size: u64,
+    pub mtime: f64,
+}
+
+/// Regular files of a tree by path relative to its root.
+pub(crate) type Tree = BTreeMap<String, FileMeta>;
+
+/// One `find` run: the files it saw and, for the top level, the directories to list next.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Listing {
+    pub files: Vec<(String, FileMeta)>,
+    pub dirs: Vec<String>,
+}
+
+/// Namespace for `ssh-keygen -Y sign`/`verify`, so the signature can't pass for another kind of file.
+pub const SIGN_NAMESPACE: &str = "beeg-migrate-verify";
+
+/// Files per `sha256sum` command when hashing the sample.
+const HASH_BATCH: usize = 64;
+
+/// Problems listed in human output; `--output json` and `--report` carry all of them.
+const SHOWN_PROBLEMS: usize = 50;
+
+/// `find` under `root`: the top level when `dir` is `None`, otherwise every file below `dir`.
+/// Paths start with `./` so names beginning with `-` are never taken for options.
+fn list_cmd(root: &str, dir: Option<&str>) -> String {
+    let root = shell_escape::escape(root.into());
+    match dir {
+        None => format!("cd {} && find . -mindepth 1 -maxdepth 1 -printf '%y\\0%P\\0%s\\0%T@\\0'", root),
+        Some(d) => format!("cd {} && find {} -type f -printf 'f\\0%P\\0%s\\0%T@\\0'", root, shell_escape::escape(format!("./{}", d).into())),
+    }
+}
+
+/// Parse NUL-separated `type, path, size, mtime` records. Only regular files and directories
+/// are kept; symlinks, sockets and the like are not compared.
+pub(crate) fn parse_listing(text: &str, prefix: Option<&str>) -> Result<Listing> {
+    let fields: Vec<&str> = text.split('\0').collect();
+    let mut out = Listing::default();
+    // a complete listing ends in NUL, leaving one empty field
+    for rec in fields.chunks(4).filter(|r| r.len() == 4) {
+        let path = match prefix {
+            // %P is relative to the starting point, which is the directory itself
+            Some(p) => format!("{}/{}", p, rec[1]),
+            None => rec[1].to_string(),
+        };
+        match rec[0] {
+            "f" => {
+                let size = rec[2].parse().with_context(|| format!("bad size '{}' for {}", rec[2], path))?;
+                let mtime = rec[3].parse().with_context(|| format!("bad mtime '{}' for {}", rec[3], path))?;
+                out.files.push((path, FileMeta { size, mtime }));
+            }
+            "d" => out.dirs.push(path),
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Problem {
+    /// In the source only
+    Missing,
+    /// In the destination only
+    Extra,
+    Size(u64, u64),
+    Mtime(f64, f64),
+    Checksum(String, String),
+    /// The sampled file could not be hashed on one side
+    Unreadable(String),
+}
+
+impl Problem {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Problem::Missing => "missing",
+            Problem::Extra => "extra",
+            Problem::Size(..) => "size",
+            Problem::Mtime(..) => "mtime",
+            Problem::Checksum(..) => "checksum",
+            Problem::Unreadable(_) => "unreadable",
+        }
+    }
+
+    /// Extra files and touched mtimes are worth a look; lost or changed data is a failure.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Problem::Extra | Problem::Mtime(..) => Severity::Warning,
+            _ => Severity::Failure,
+        }
+    }
+
+    pub fn detail(&self) -> String {
+        match self {
+            Problem::Missing => "not in destination".into(),
+            Problem::Extra => "not in source".into(),
+            Problem::Size(s, d) => format!("{} vs {} bytes", s, d),
+            Problem::Mtime(s, d) => format!("mtime differs by {:.1}s", d - s),
+            Problem::Checksum(s, d) => format!("{} vs {}", s, d),
+            Problem::Unreadable(e) => e.clone(),
+        }
+    }
+}
+
+/// Compare the listings; a size difference hides an mtime difference on the same file.
+pub(crate) fn compare(src: &Tree, dst: &Tree, mtime_tolerance: f64) -> Vec<(String, Problem)> {
+    let mut out = Vec::new();
+    for (path, s) in src {
+        match dst.get(path) {
+            None => out.push((path.clone(), Problem::Missing)),
+            Some(d) if d.size != s.size => out.push((path.clone(), Problem::Size(s.size, d.size))),
+            Some(d) if (d.mtime - s.mtime).abs() > mtime_tolerance => out.push((path.clone(), Problem::Mtime(s.mtime, d.mtime))),
+            Some(_) => {}
+        }
+    }
+    out.extend(dst.keys().filter(|p| !src.contains_key(*p)).map(|p| (p.clone(), Problem::Extra)));
+    out
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrateOptions {
+    /// Use at most this many of the selected nodes
+    pub clients: usize,
+    /// Files to checksum on both sides
+    pub sample: usize,
+    /// Seconds mtimes may differ by
+    pub mtime_tolerance: f64,
+    /// Seconds per remote command
+    pub timeout: u64,
+    pub report: Option<PathBuf>,
+    /// SSH private key to sign the report with
+    pub sign_key: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Src,
+    Dst,
+}
+
+/// Sign `report` with `ssh-keygen -Y sign`, which writes `<report>.sig` next to it.
+fn sign(report: &Path, key: &Path) -> Result<PathBuf> {
+    let sig = PathBuf::from(format!("{}.sig", report.display()));
+    // ssh-keygen refuses to overwrite an old signature
+    let _ = std::fs::remove_file(&sig);
+    let out = Command::new("ssh-keygen")
+        .args(["-q", "-Y", "sign", "-n", SIGN_NAMESPACE, "-f"])
+        .arg(key)
+        .arg(report)
+        .output()
+        .context("running ssh-keygen")?;
+    if !out.status.success() {
+        anyhow::bail!("ssh-keygen -Y sign failed: {}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(sig)
+}
+
+pub fn verify(cli: &crate::Cli, cfg: &config::Config, src: &str, dst: &str, selector: &str, opts: &MigrateOptions) -> Result<Severity> {
+    let nodes: Vec<&config::Node> = config::select_nodes(cfg, selector).into_iter().take(opts.clients.max(1)).collect();
+    if nodes.is_empty() { anyhow::bail!("selector '{}' matched no nodes", selector); }
+    let started = Instant::now();
+    let roots = [(Side::Src, src), (Side::Dst, dst)];
+
+    // the top levels come from one client; every directory under them is one work item
+    let tr = transport::from_config(cfg);
+    let mut trees: [Tree; 2] = Default::default();
+    let mut dirs: Vec<(Side, String, String)> = Vec::new();
+    for (i, (side, root)) in roots.iter().enumerate() {
+        let out = tr.exec_idempotent(&nodes[0].host, &wrap_timeout(&list_cmd(root, None), opts.timeout))?;
+        let top = parse_listing(&out.stdout, None)?;
+        if top.files.is_empty() && top.dirs.is_empty() && !out.stderr.trim().is_empty() {
+            anyhow::bail!("listing {} on {} failed: {}", root, nodes[0].name, out.stderr.trim());
+        }
+        trees[i].extend(top.files);
+        dirs.extend(top.dirs.into_iter().map(|d| (*side, root.to_string(), d)));
+    }
+    let dir_count = dirs.len();
+    let timeout = opts.timeout;
+    let listed = spread(cfg, &nodes, dirs, move |tr, host, (side, root, dir): (Side, String, String)| {
+        let res = tr.exec_idempotent(host, &wrap_timeout(&list_cmd(&root, Some(&dir)), timeout)).map_err(|e| e.to_string()).and_then(|o| {
+            let l = parse_listing(&o.stdout, Some(&dir)).map_err(|e| e.to_string())?;
+            // find keeps going past unreadable directories and says so on stderr
+            let errs: Vec<String> = o.stderr.lines().filter(|l| !l.trim().is_empty()).map(String::from).collect();
+            Ok((l.files, errs))
+        });
+        (side, root, dir, res)
+    });
+    let mut per_client: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    let mut errors: Vec<String> = Vec::new();
+    for (name, done) in listed {
+        let Some(done) = done else {
+            log::warn!("migrate thread for {} panicked", name);
+            continue;
+        };
+        per_client.entry(name).or_default().0 += done.len();
+        for (side, root, dir, res) in done {
+            match res {
+                Ok((files, errs)) => {
+                    trees[side as usize].extend(files);
+                    errors.extend(errs);
+                }
+                Err(e) => errors.push(format!("{}/{}: {}", root.trim_end_matches('/'), dir, e)),
+            }
+        }
+    }
+    let mut unchecked = dir_count - per_client.values().map(|c| c.0).sum::<usize>();
+    let [src_tree, dst_tree] = &trees;
+    let mut problems = compare(src_tree, dst_tree, opts.mtime_tolerance);
+
+    // checksum a sample of the files that look identical by size
+    let mut candidates: Vec<&String> = src_tree.iter().filter(|(p, s)| dst_tree.get(*p).is_some_and(|d| d.size == s.size)).map(|(p, _)| p).collect();
+    candidates.shuffle(&mut rand::thread_rng());
+    candidates.truncate(opts.sample);
+    candidates.sort();
+    let sampled = candidates.len();
+    let mut batches: Vec<(Side, String, Vec<String>)> = Vec::new();
+    for (side, root) in roots {
+        for chunk in candidates.chunks(HASH_BATCH) {
+            batches.push((side, root.to_string(), chunk.iter().map(|p| p.to_string()).collect()));
+        }
+    }
+    let batch_count = batches.len();
+    let hashed = spread(cfg, &nodes, batches, move |tr, host, (side, root, files): (Side, String, Vec<String>)| {
+        let sums = match tr.exec_idempotent(host, &wrap_timeout(&sum_cmd(&root, "sha256sum", &files), timeout)) {
+            Ok(o) => read_sums(&files, &o.stdout, &o.stderr),
+            Err(e) => vec![Err(e.to_string()); files.len()],
+        };
+        (side, files.into_iter().zip(sums).collect::<Vec<_>>())
+    });
+    let mut sums: [BTreeMap<String, Result<String, String>>; 2] = Default::default();
+    let mut batches_done = 0;
+    for (name, done) in hashed {
+        let Some(done) = done else {
+            log::warn!("migrate thread for {} panicked", name);
+            continue;
+        };
+        batches_done += done.len();
+        for (side, files) in done {
+            per_client.entry(name.clone()).or_default().1 += files.len();
+            sums[side as usize].extend(files);
+        }
+    }
+    unchecked += batch_count - batches_done;
+    for path in &candidates {
+        match (sums[0].get(*path), sums[1].get(*path)) {
+            (Some(Ok(s)), Some(Ok(d))) if s != d => problems.push((path.to_string(), Problem::Checksum(s.clone(), d.clone()))),
+            (Some(Err(e)), _) => problems.push((path.to_string(), Problem::Unreadable(format!("source: {}", e)))),
+            (_, Some(Err(e))) => problems.push((path.to_string(), Problem::Unreadable(format!("destination: {}", e)))),
+            _ => {}
+        }
+    }
+    problems.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut severity = problems.iter().map(|(_, p)| p.severity()).max().unwrap_or_default();
+    if !errors.is_empty() || unchecked > 0 { severity = Severity::Failure; }
+    let count = |kind: &str| problems.iter().filter(|(_, p)| p.kind() == kind).count();
+    let bytes = |t: &Tree| t.values().map(|m| m.size).sum::<u64>();
+    let side_json = |t: &Tree, p: &str| t.get(p).map(|m| serde_json::json!({"size": m.size, "mtime": m.mtime}));
+    let report = serde_json::json!({
+        "src": src,
+        "dst": dst,
+        "status": severity.as_str(),
+        "generated_at": timefmt::rfc3339(chrono::Utc::now()),
+        "operator": std::env::var("SUDO_USER").or_else(|_| std::env::var("USER")).ok(),
+        "cluster": cfg.cluster.as_ref().and_then(|c| c.name.clone()),
+        "src_files": src_tree.len(),
+        "src_bytes": bytes(src_tree),
+        "dst_files": dst_tree.len(),
+        "dst_bytes": bytes(dst_tree),
+        "mtime_tolerance_s": opts.mtime_tolerance,
+        "sampled": sampled,
+        "counts": {
+            "missing": count("missing"),
+            "extra": count("extra"),
+            "size": count("size"),
+            "mtime": count("mtime"),
+            "checksum": count("checksum"),
+            "unreadable": count("unreadable"),
+        },
+        "problems": problems.iter().map(|(path, p)| serde_json::json!({
+            "path": path,
+            "problem": p.kind(),
+            "detail": p.detail(),
+            "source": side_json(src_tree, path),
+            "destination": side_json(dst_tree, path),
+        })).collect::<Vec<_>>(),
+        "listing_errors": errors,
+        "clients": per_client.iter().map(|(n, (d, f))| serde_json::json!({"node": n, "dirs": d, "files_hashed": f})).collect::<Vec<_>>(),
+        "seconds": started.elapsed().as_secs_f64(),
+    });
+
+    let mut signed = None;
+    if let Some(path) = &opts.report {
+        std::fs::write(path, serde_json::to_string_pretty(&report)? + "\n").with_context(|| format!("writing report: {}", path.display()))?;
+        if let Some(key) = &opts.sign_key { signed = Some(sign(path, key)?); }
+    }
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Tree", "Path", "Files", "Size"]);
+            table.add_row(vec!["Source".to_string(), src.to_string(), src_tree.len().to_string(), transfer::format_size(bytes(src_tree))]);
+            table.add_row(vec!["Destination".to_string(), dst.to_string(), dst_tree.len().to_string(), transfer::format_size(bytes(dst_tree))]);
+            crate::tables::print(cli.output, &table);
+            if !problems.is_empty() {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.set_header(vec!["File", "Problem", "Detail"]);
+                for (path, p) in problems.iter().take(SHOWN_PROBLEMS) {
+                    table.add_row(vec![path.clone(), p.kind().to_uppercase(), p.detail()]);
+                }
+                crate::tables::print_another(cli.output, &table);
+                if problems.len() > SHOWN_PROBLEMS {
+                    crate::tables::note(cli.output, &format!("... and {} more (all of them are in --output json and --report)", problems.len() - SHOWN_PROBLEMS));
+                }
+            }
+            crate::tables::note(cli.output, &format!(
+                "{} dirs listed and {} files hashed on {} clients in {:.0}s: {} missing, {} extra, {} size, {} mtime, {} checksum mismatches",
+                dir_count, sampled, per_client.len(), started.elapsed().as_secs_f64(),
+                count("missing"), count("extra"), count("size"), count("mtime"), count("checksum"),
+            ));
+            if let Some(path) = &opts.report {
+                match &signed {
+                    Some(sig) => crate::tables::note(cli.output, &format!("Report: {} (signature {})", path.display(), sig.display())),
+                    None => crate::tables::note(cli.output, &format!("Report: {}", path.display())),
+                }
+            }
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => crate::formats::print(cli.output, &report)?,
+    }
+
+    // Warnings
+    if !errors.is_empty() {
+        log::warn!("{} listing errors; the comparison is incomplete{}", errors.len(), errors.iter().take(10).map(|e| format!("\n  {}", e)).collect::<String>());
+    }
+    if unchecked > 0 { log::warn!("{} work items were not done", unchecked); }
+    if count("unreadable") > 0 { log::warn!("{} sampled files could not be hashed", count("unreadable")); }
+    if opts.report.is_some() && opts.sign_key.is_none() { log::warn!("report is unsigned; pass --sign-key for change records"); }
+    Ok(severity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(size: u64, mtime: f64) -> FileMeta {
+        FileMeta { size, mtime }
+    }
+
+    #[test]
+    fn listing() {
+        let top = parse_listing("f\0a.txt\x0012\x001700000000.5000000000\0d\0sub\x004096\x001700000000.0\0l\0link\x003\x001700000000.0\0", None).unwrap();
+        assert_eq!(top.files, vec![("a.txt".to_string(), meta(12, 1700000000.5))]);
+        assert_eq!(top.dirs, vec!["sub".to_string()]);
+        let sub = parse_listing("f\0x/y.bin\x001024\x001700000001.0\0", Some("sub")).unwrap();
+        assert_eq!(sub.files[0].0, "sub/x/y.bin");
+        assert!(parse_listing("f\0a\0big\x000\0", None).is_err());
+        assert_eq!(parse_listing("", None).unwrap(), Listing::default());
+    }
+
+    #[test]
+    fn problems() {
+        let src: Tree = [("a", meta(1, 10.0)), ("b", meta(2, 10.0)), ("c", meta(3, 10.0)), ("d", meta(4, 10.0))]
+            .into_iter().map(|(p, m)| (p.to_string(), m)).collect();
+        let dst: Tree = [("a", meta(1, 10.4)), ("b", meta(5, 99.0)), ("c", meta(3, 12.0)), ("e", meta(1, 10.0))]
+            .into_iter().map(|(p, m)| (p.to_string(), m)).collect();
+        let p = compare(&src, &dst, 1.0);
+        let kinds: Vec<(&str, &str)> = p.iter().map(|(path, p)| (path.as_str(), p.kind())).collect();
+        assert_eq!(kinds, vec![("b", "size"), ("c", "mtime"), ("d", "missing"), ("e", "extra")]);
+        assert_eq!(p[1].1.severity(), Severity::Warning);
+        assert_eq!(p[2].1.severity(), Severity::Failure);
+    }
+}
@@ -0,0 +1,282 @@
+//! `beeg mount ensure` and `beeg mount remove`: put a BeeGFS client mount in place on the
+//! selected nodes, or take it away, in one shell script per node that reports a line per step.
+
+use crate::checks::{permission_denied, wrap_timeout, Severity, NOPERM_HINT};
+use crate::{config, identity, prompt, transport};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+/// How the mount comes back after a reboot.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Persist {
+    /// A line in beegfs-mounts.conf, mounted by the beegfs-client service
+    MountsConf,
+    /// A beegfs line in /etc/fstab
+    Fstab,
+    /// A systemd .mount unit, enabled
+    Systemd,
+}
+
+impl Persist {
+    fn as_str(self) -> &'static str {
+        match self { Persist::MountsConf => "mounts-conf", Persist::Fstab => "fstab", Persist::Systemd => "systemd" }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnsureOptions {
+    pub mount: String,
+    /// Client config the mount uses
+    pub client_conf: String,
+    pub persist: Persist,
+    pub dry_run: bool,
+    pub timeout: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveOptions {
+    pub mount: String,
+    pub yes: bool,
+    pub dry_run: bool,
+    pub timeout: u64,
+}
+
+/// Helpers both scripts use: `one` folds an error onto one line, `has FILE COND` tells
+/// whether a line of FILE matches the awk condition (`m` is the mountpoint).
+const COMMON: &str = r#"F=/etc/beegfs/beegfs-mounts.conf
+one() { tr '\n' ' ' | sed 's/ *$//'; }
+has() { [ -r "$1" ] && awk -v m="$M" "$2 { f = 1 } END { exit !f }" "$1"; }
+unit() { echo "/etc/systemd/system/$(systemd-escape --path --suffix=mount "$M" 2>/dev/null)"; }
+"#;
+
+/// CONF (beegfs-mounts.conf), MOUNT and PERSIST steps of `ensure`.
+const ENSURE: &str = r#"if has "$F" '$1 == m'; then echo "CONF present"
+elif [ -e "$F" ] && [ ! -r "$F" ]; then echo "CONF error $F: Permission denied"
+elif [ "$PERSIST" != mounts-conf ]; then echo "CONF missing"
+elif [ "$DRY" = 1 ]; then echo "CONF would-add $F"
+elif err=$(printf '%s %s\n' "$M" "$C" 2>&1 >> "$F"); then echo "CONF added $F"
+else echo "CONF error $(printf '%s' "$err" | one)"; fi
+
+if has /proc/mounts '$2 == m && $3 == "beegfs"'; then echo "MOUNT present"
+elif [ "$DRY" = 1 ]; then echo "MOUNT would-mount"
+elif err=$({ mkdir -p "$M" && mount -t beegfs beegfs_nodev "$M" -o "cfgFile=$C"; } 2>&1); then echo "MOUNT mounted"
+else echo "MOUNT error $(printf '%s' "$err" | one)"; fi
+
+case "$PERSIST" in
+fstab)
+  if has /etc/fstab '$2 == m && $3 == "beegfs"'; then echo "PERSIST present /etc/fstab"
+  elif [ "$DRY" = 1 ]; then echo "PERSIST would-add /etc/fstab"
+  elif err=$(printf 'beegfs_nodev %s beegfs rw,relatime,cfgFile=%s,_netdev,nofail 0 0\n' "$M" "$C" 2>&1 >> /etc/fstab); then echo "PERSIST added /etc/fstab"
+  else echo "PERSIST error $(printf '%s' "$err" | one)"; fi;;
+systemd)
+  U=$(unit)
+  if [ -f "$U" ]; then echo "PERSIST present $U"
+  elif [ "$U" = /etc/systemd/system/ ]; then echo "PERSIST error systemd-escape not found"
+  elif [ "$DRY" = 1 ]; then echo "PERSIST would-add $U"
+  elif err=$({ printf '[Unit]\nDescription=BeeGFS %s\nAfter=beegfs-helperd.service network-online.target\nWants=network-online.target\n\n[Mount]\nWhat=beegfs_nodev\nWhere=%s\nType=beegfs\nOptions=rw,relatime,cfgFile=%s,_netdev\n\n[Install]\nWantedBy=remote-fs.target\n' "$M" "$M" "$C" > "$U" && systemctl daemon-reload && systemctl enable "${U##*/}"; } 2>&1); then echo "PERSIST added $U"
+  else echo "PERSIST error $(printf '%s' "$err" | one)"; fi;;
+esac
+"#;
+
+/// MOUNT, CONF, FSTAB and UNIT steps of `remove`; nothing is dropped while the mount is busy.
+const REMOVE: &str = r#"if ! has /proc/mounts '$2 == m && $3 == "beegfs"'; then echo "MOUNT absent"
+elif [ "$DRY" = 1 ]; then echo "MOUNT would-unmount"
+elif err=$(umount "$M" 2>&1); then echo "MOUNT unmounted"
+else echo "MOUNT error $(printf '%s' "$err" | one)"; exit 0; fi
+
+drop() {
+  if ! has "$1" "$2"; then echo "$3 absent"
+  elif [ "$DRY" = 1 ]; then echo "$3 would-remove $1"
+  elif err=$(cp -p "$1" "$1.beeg-$TS" 2>&1 && awk -v m="$M" "!($2)" "$1.beeg-$TS" 2>&1 > "$1"); then echo "$3 removed $1 (backup $1.beeg-$TS)"
+  else echo "$3 error $(printf '%s' "$err" | one)"; fi
+}
+drop "$F" '$1 == m' CONF
+drop /etc/fstab '$2 == m && $3 == "beegfs"' FSTAB
+
+U=$(unit)
+if [ ! -f "$U" ]; then echo "UNIT absent"
+elif [ "$DRY" = 1 ]; then echo "UNIT would-remove $U"
+elif err=$({ systemctl disable "${U##*/}" && rm -f "$U" && systemctl daemon-reload; } 2>&1); then echo "UNIT removed $U"
+else echo "UNIT error $(printf '%s' "$err" | one)"; fi
+"#;
+
+fn vars(mount: &str, client_conf: &str, persist: &str, dry_run: bool) -> String {
+    let esc = |s: &str| shell_escape::escape(s.into()).to_string();
+    format!(
+        "M={}; C={}; PERSIST={}; DRY={}; TS={}\n",
+        esc(mount), esc(client_conf), persist, u8::from(dry_run), chrono::Local::now().format("%Y%m%d-%H%M%S"),
+    )
+}
+
+/// One step a node's script reported: `present`, `added`, `would-add`, `error`, ...
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct Step {
+    pub status: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+}
+
+/// `NAME status detail` lines, by step name.
+pub(crate) fn parse_steps(text: &str) -> BTreeMap<String, Step> {
+    text.lines().filter_map(|l| {
+        let mut parts = l.trim().splitn(3, ' ');
+        let name = parts.next().filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_uppercase()))?;
+        let status = parts.next()?.to_string();
+        Some((name.to_string(), Step { status, detail: parts.next().unwrap_or("").to_string() }))
+    }).collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NodeResult {
+    node: String,
+    host: String,
+    steps: BTreeMap<String, Step>,
+    /// The client-mount write probe after `ensure`: OK, NOPERM or ERR
+    #[serde(skip_serializing_if = "Option::is_none")]
+    write_test: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl NodeResult {
+    /// Everything that went wrong on the node, one message each.
+    fn problems(&self) -> Vec<String> {
+        let mut out: Vec<String> = self.error.iter().cloned().collect();
+        out.extend(self.steps.iter().filter(|(_, s)| s.status == "error").map(|(n, s)| format!("{}: {}", n.to_lowercase(), s.detail)));
+        match self.write_test.as_deref() {
+            Some("NOPERM") => out.push("write test: Permission denied".into()),
+            Some(v) if v != "OK" => out.push("write test failed".into()),
+            _ => {}
+        }
+        out
+    }
+}
+
+fn severity(rows: &[NodeResult]) -> Severity {
+    rows.iter().flat_map(NodeResult::problems)
+        .map(|p| if permission_denied(&p) { Severity::NoPerm } else { Severity::Failure })
+        .max().unwrap_or_default()
+}
+
+/// Run `script` on every node at once; `after` gets each node's steps to add to them.
+fn run_all(cfg: &config::Config, nodes: &[&config::Node], script: &str, timeout: u64, after: impl Fn(&dyn transport::Transport, &config::Node, &mut NodeResult) + Sync) -> Vec<NodeResult> {
+    let cmd = wrap_timeout(script, timeout);
+    let result = |n: &config::Node| NodeResult { node: n.name.clone(), host: n.host.clone(), steps: BTreeMap::new(), write_test: None, error: None };
+    crate::checks::per_node(cfg, nodes, |tr, n| {
+        let mut r = result(n);
+        match tr.exec(&n.host, &cmd) {
+            Ok(out) if out.status == Some(0) => {
+                r.steps = parse_steps(&out.stdout);
+                after(tr, n, &mut r);
+            }
+            Ok(out) => r.error = Some(out.stderr.trim().lines().last().map(String::from).unwrap_or_else(|| format!("script exited {:?}", out.status))),
+            Err(e) => r.error = Some(format!("{:#}", e)),
+        }
+        Ok(r)
+    }).into_iter().map(|(n, r, _)| r.unwrap_or_else(|e| NodeResult { error: Some(e), ..result(n) })).collect()
+}
+
+fn show(cli: &crate::Cli, cfg: &config::Config, mut rows: Vec<NodeResult>, columns: &[(&str, &str)], head: serde_json::Value) -> Result<Severity> {
+    config::sort_rows(cfg, cli.sort, &mut rows, |r| &r.node);
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            let mut header = vec!["Node"];
+            header.extend(columns.iter().map(|(_, title)| *title));
+            header.push("Detail");
+            table.set_header(header);
+            for r in &rows {
+                let mut row = vec![r.node.clone()];
+                row.extend(columns.iter().map(|(step, _)| match *step {
+                    "WRITE" => r.write_test.clone().unwrap_or_else(|| "-".into()),
+                    _ => r.steps.get(*step).map(|s| s.status.clone()).unwrap_or_else(|| "-".into()),
+                }));
+                let mut detail = r.problems();
+                detail.extend(r.steps.values().filter(|s| s.status != "error" && !s.detail.is_empty()).map(|s| s.detail.clone()));
+                row.push(detail.join("; "));
+                table.add_row(row);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let mut v = head;
+            v["nodes"] = serde_json::to_value(&rows)?;
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+    let failed: Vec<&str> = rows.iter().filter(|r| !r.problems().is_empty()).map(|r| r.node.as_str()).collect();
+    if !failed.is_empty() { log::warn!("not done on {}", failed.join(", ")); }
+    let sev = severity(&rows);
+    if sev == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+    Ok(sev)
+}
+
+/// Configure, mount and persist `opts.mount` where it is missing, then write a test file.
+pub fn ensure(cli: &crate::Cli, cfg: &config::Config, selector: &str, opts: &EnsureOptions) -> Result<Severity> {
+    if !opts.mount.starts_with('/') { bail!("--mount must be an absolute path"); }
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    if !opts.dry_run { identity::verify(cfg, &nodes, opts.timeout)?; }
+    let script = vars(&opts.mount, &opts.client_conf, opts.persist.as_str(), opts.dry_run) + COMMON + ENSURE;
+    let rows = run_all(cfg, &nodes, &script, opts.timeout, |tr, n, r| {
+        let mounted = r.steps.get("MOUNT").is_some_and(|s| s.status == "present" || s.status == "mounted");
+        if opts.dry_run || !mounted { return; }
+        crate::checks::client::probe_node(tr, &n.host, &opts.mount, opts.timeout, [4], |_, val, _| r.write_test = Some(val));
+    });
+    let head = serde_json::json!({"mount": opts.mount, "client_conf": opts.client_conf, "persist": opts.persist.as_str(), "dry_run": opts.dry_run});
+    show(cli, cfg, rows, &[("CONF", "beegfs-mounts.conf"), ("MOUNT", "Mounted"), ("PERSIST", "Persisted"), ("WRITE", "Write test")], head)
+}
+
+/// Unmount `opts.mount` and drop it from beegfs-mounts.conf, fstab and systemd, after asking.
+pub fn remove(cli: &crate::Cli, cfg: &config::Config, selector: &str, opts: &RemoveOptions) -> Result<Severity> {
+    if !opts.mount.starts_with('/') { bail!("--mount must be an absolute path"); }
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    if !opts.dry_run {
+        identity::verify(cfg, &nodes, opts.timeout)?;
+        if !opts.yes {
+            if !std::io::stdin().is_terminal() { bail!("refusing to remove {} without confirmation; pass --yes to run non-interactively", opts.mount); }
+            let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+            let question = format!("Unmount {} and remove it from beegfs-mounts.conf, fstab and systemd on {} node(s) ({})?", opts.mount, nodes.len(), names.join(", "));
+            if !prompt::confirm(&question)? {
+                eprintln!("Aborted; no mounts were touched.");
+                return Ok(Severity::Warning);
+            }
+        }
+    }
+    let script = vars(&opts.mount, "", "none", opts.dry_run) + COMMON + REMOVE;
+    let rows = run_all(cfg, &nodes, &script, opts.timeout, |_, _, _| {});
+    let head = serde_json::json!({"mount": opts.mount, "dry_run": opts.dry_run});
+    show(cli, cfg, rows, &[("MOUNT", "Mounted"), ("CONF", "beegfs-mounts.conf"), ("FSTAB", "fstab"), ("UNIT", "systemd unit")], head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        let s = parse_steps("CONF present\nMOUNT error mount: /mnt/beegfs: unknown filesystem type 'beegfs'.\nPERSIST would-add /etc/systemd/system/mnt-beegfs.mount\nnoise from a login script\n");
+        assert_eq!(s.len(), 3);
+        assert_eq!(s["CONF"], Step { status: "present".into(), detail: String::new() });
+        assert_eq!(s["MOUNT"].detail, "mount: /mnt/beegfs: unknown filesystem type 'beegfs'.");
+        assert_eq!(s["PERSIST"].status, "would-add");
+
+        let node = |steps: &str, write: Option<&str>| NodeResult { node: "cli01".into(), host: "cli01".into(), steps: parse_steps(steps), write_test: write.map(String::from), error: None };
+        assert_eq!(severity(&[node("CONF present\nMOUNT mounted\n", Some("OK"))]), Severity::Ok);
+        assert_eq!(severity(&[node("CONF error /etc/beegfs/beegfs-mounts.conf: Permission denied\nMOUNT present\n", Some("OK"))]), Severity::NoPerm);
+        assert_eq!(severity(&[node("MOUNT present\n", Some("ERR"))]), Severity::Failure);
+        assert_eq!(node("MOUNT error umount: target is busy.\n", None).problems(), vec!["mount: umount: target is busy."]);
+    }
+
+    #[test]
+    fn script_vars() {
+        let v = vars("/mnt/bee gfs", "/etc/beegfs/beegfs-client.conf", "fstab", true);
+        assert!(v.starts_with("M='/mnt/bee gfs'; C=/etc/beegfs/beegfs-client.conf; PERSIST=fstab; DRY=1; TS="), "{}", v);
+    }
+}
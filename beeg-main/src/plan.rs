@@ -0,0 +1,202 @@
+use crate::beegfs::{self, TargetSpace};
+use crate::checks::capacity::fetch_targets;
+use crate::checks::pool_limits::{self, expected_pool, Limits};
+use crate::checks::{single_node, wrap_timeout, Severity};
+use crate::{config, notes, transfer, transport};
+use anyhow::Result;
+use comfy_table::{Table, presets::UTF8_FULL};
+
+/// The write is placed in this many steps, re-sorting the targets into pools after each.
+const STEPS: u64 = 1000;
+
+/// Where one target ends up once the write is placed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Landing {
+    pub added: u64,
+    pub pool_before: &'static str,
+    pub pool_after: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Simulation {
+    /// Per target, in input order
+    pub landings: Vec<Landing>,
+    /// Bytes no target could take
+    pub unplaced: u64,
+    /// Steps that found fewer eligible targets than the stripe width
+    pub narrow_steps: u64,
+}
+
+/// The worse of the pool the mgmtd reports and the one the limits give for the current free
+/// space, so targets the mgmtd already moved (e.g. by dynamic limits) stay where it put them.
+fn pool_of(t: &TargetSpace, limits: &Limits) -> &'static str {
+    let by_limits = expected_pool(t, limits).0;
+    match t.pool.as_deref() {
+        Some("emergency") => "emergency",
+        Some("low") if by_limits == "normal" => "low",
+        _ => by_limits,
+    }
+}
+
+/// Place `size` bytes the way the target chooser does: files go to the normal pool, the low
+/// pool makes up the stripe width when normal targets run short, and emergency targets get
+/// nothing. Each step is spread evenly over the eligible targets.
+pub(crate) fn simulate(targets: &[TargetSpace], limits: &Limits, size: u64, stripe: usize) -> Simulation {
+    let mut now: Vec<TargetSpace> = targets.to_vec();
+    let before: Vec<&'static str> = targets.iter().map(|t| pool_of(t, limits)).collect();
+    let mut added = vec![0u64; targets.len()];
+    let step = size.div_ceil(STEPS).max(1);
+    let mut left = size;
+    let mut narrow_steps = 0;
+    while left > 0 {
+        let this = step.min(left);
+        let pools: Vec<&str> = now.iter().map(|t| pool_of(t, limits)).collect();
+        let mut eligible: Vec<usize> = (0..now.len()).filter(|&i| pools[i] == "normal").collect();
+        if eligible.len() < stripe {
+            let mut low: Vec<usize> = (0..now.len()).filter(|&i| pools[i] == "low").collect();
+            low.sort_by_key(|&i| std::cmp::Reverse(now[i].free_bytes));
+            eligible.extend(low.into_iter().take(stripe - eligible.len()));
+        }
+        if eligible.is_empty() { break; }
+        if eligible.len() < stripe { narrow_steps += 1; }
+        let share = this / eligible.len() as u64;
+        let mut rest = this % eligible.len() as u64;
+        let mut placed = 0;
+        for i in eligible {
+            let want = share + if rest > 0 { rest -= 1; 1 } else { 0 };
+            let got = want.min(now[i].free_bytes);
+            now[i].free_bytes -= got;
+            added[i] += got;
+            placed += got;
+        }
+        if placed == 0 { break; }
+        left -= placed;
+    }
+    let landings = now.iter().zip(before).zip(added)
+        .map(|((t, pool_before), added)| Landing { added, pool_before, pool_after: pool_of(t, limits) })
+        .collect();
+    Simulation { landings, unplaced: left, narrow_steps }
+}
+
+pub fn placement(cli: &crate::Cli, cfg: &config::Config, selector: &str, size: u64, pool: Option<&str>, stripe: usize, timeout: u64) -> Result<Severity> {
+    let mgmt = single_node(cfg, selector)?;
+    let tr = transport::from_config(cfg);
+    let out = tr.exec_idempotent(&mgmt.host, &wrap_timeout(pool_limits::PROBE, timeout))?;
+    let (limits, _) = pool_limits::parse_probe(&out.stdout)?;
+    let mut targets = fetch_targets(cfg, mgmt, timeout)?;
+    let pool_name = match pool {
+        Some(want) => {
+            let pools = beegfs::Ctl::new(cfg, mgmt, timeout).storage_pools()?;
+            let p = pools.iter().find(|p| p.id == want || p.name.eq_ignore_ascii_case(want)).ok_or_else(|| {
+                let known: Vec<String> = pools.iter().map(|p| format!("{} ({})", p.name, p.id)).collect();
+                anyhow::anyhow!("no storage pool '{}' on {}; known: {}", want, mgmt.name, if known.is_empty() { "none".into() } else { known.join(", ") })
+            })?;
+            targets.retain(|t| p.targets.contains(&t.id));
+            if targets.is_empty() { anyhow::bail!("storage pool {} has no targets with space info", p.name); }
+            p.name.clone()
+        }
+        None => "all targets".into(),
+    };
+    let sim = simulate(&targets, &limits, size, stripe.max(1));
+    let fmt = transfer::format_size;
+
+    let to_emergency: Vec<&str> = targets.iter().zip(&sim.landings)
+        .filter(|(_, l)| l.pool_after == "emergency" && l.pool_before != "emergency").map(|(t, _)| t.id.as_str()).collect();
+    let to_low: Vec<&str> = targets.iter().zip(&sim.landings)
+        .filter(|(_, l)| l.pool_after == "low" && l.pool_before == "normal").map(|(t, _)| t.id.as_str()).collect();
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            crate::tables::note(cli.output, &format!(
+                "Placing {} on {} ({} targets), stripe {}; limits: space low < {}, emergency < {}",
+                fmt(size), pool_name, targets.len(), stripe, fmt(limits.space_low), fmt(limits.space_emergency),
+            ));
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Target", "Node", "Free", "Added", "Free after", "Pool now", "Pool after"]);
+            for (t, l) in targets.iter().zip(&sim.landings) {
+                table.add_row(vec![
+                    t.id.clone(), t.node.clone(), fmt(t.free_bytes), fmt(l.added), fmt(t.free_bytes - l.added),
+                    t.pool.clone().unwrap_or_else(|| l.pool_before.to_string()), l.pool_after.to_string(),
+                ]);
+            }
+            crate::tables::print(cli.output, &table);
+            crate::tables::note(cli.output, &format!(
+                "{} placed, {} unplaced; {} target(s) would enter the low pool, {} the emergency pool",
+                fmt(size - sim.unplaced), fmt(sim.unplaced), to_low.len(), to_emergency.len(),
+            ));
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "mgmt": mgmt.name,
+                "pool": pool,
+                "size": size,
+                "stripe": stripe,
+                "placed": size - sim.unplaced,
+                "unplaced": sim.unplaced,
+                "dynamic_pools": limits.dynamic,
+                "targets": targets.iter().zip(&sim.landings).map(|(t, l)| serde_json::json!({
+                    "target": t.id,
+                    "node": t.node,
+                    "free_bytes": t.free_bytes,
+                    "added_bytes": l.added,
+                    "free_after_bytes": t.free_bytes - l.added,
+                    "pool": t.pool,
+                    "pool_after": l.pool_after,
+                })).collect::<Vec<_>>(),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    if sim.unplaced > 0 {
+        log::warn!("only {} of {} fits before every target is in the emergency pool", fmt(size - sim.unplaced), fmt(size));
+    }
+    if !to_emergency.is_empty() {
+        log::warn!("the write would push {} target(s) into the emergency pool: {}", to_emergency.len(), to_emergency.join(", "));
+        notes::show(notes::NoteKind::Target, &to_emergency);
+    }
+    if !to_low.is_empty() { log::warn!("{} target(s) would drop to the low pool: {}", to_low.len(), to_low.join(", ")); }
+    if sim.narrow_steps > 0 {
+        log::warn!("fewer than {} targets outside the emergency pool for part of the write; those files get a narrower stripe", stripe);
+    }
+    if limits.dynamic { log::warn!("dynamic pools are on; the mgmtd may raise the limits, so targets can leave the normal pool earlier than shown"); }
+    Ok(if sim.unplaced > 0 || !to_emergency.is_empty() { Severity::Failure }
+       else if !to_low.is_empty() || sim.narrow_steps > 0 { Severity::Warning }
+       else { Severity::Ok })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T: u64 = 1 << 40;
+
+    fn target(id: &str, free_bytes: u64) -> TargetSpace {
+        TargetSpace { id: id.into(), node: "s01".into(), pool: None, total_bytes: 10 * T, free_bytes, total_inodes: 1 << 30, free_inodes: 1 << 30 }
+    }
+
+    #[test]
+    fn placement_sim() {
+        let (limits, _) = pool_limits::parse_probe("CONF tuneStorageSpaceLowLimit 1T\nCONF tuneStorageSpaceEmergencyLimit 100G\n").unwrap();
+        let targets = [target("1", 5 * T), target("2", 5 * T), target("3", 2 * T)];
+        // 6T spread evenly: target 3 drops to low after 1T, the rest goes to 1 and 2
+        let sim = simulate(&targets, &limits, 6 * T, 2);
+        assert_eq!(sim.unplaced, 0);
+        assert_eq!(sim.landings[2].pool_after, "low");
+        assert_eq!(sim.landings.iter().map(|l| l.added).sum::<u64>(), 6 * T);
+        assert!(sim.landings[0].added > 2 * T);
+
+        // more than fits: everything ends up below the emergency limit and the rest is left over
+        let sim = simulate(&targets, &limits, 20 * T, 2);
+        assert!(sim.unplaced > 0);
+        assert!(sim.landings.iter().all(|l| l.pool_after == "emergency"));
+        assert_eq!(simulate(&targets[..1], &limits, T, 2).narrow_steps, STEPS);
+
+        // the mgmtd's own emergency verdict wins over the static limits
+        let mut held = target("4", 5 * T);
+        held.pool = Some("emergency".into());
+        assert_eq!(simulate(&[held, target("5", 5 * T)], &limits, T, 1).landings[0].added, 0);
+    }
+}
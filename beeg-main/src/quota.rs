@@ -0,0 +1,183 @@
+use crate::checks::{single_node, wrap_timeout};
+use crate::{config, transport};
+use anyhow::Result;
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL};
+
+/// Used when neither `--warn-percent` nor `quota_warn_percent` in the config is set.
+pub const DEFAULT_WARN_PERCENT: u8 = 90;
+
+/// Which IDs to ask beegfs-ctl about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    Uids(String),
+    Gids(String),
+    AllUsers,
+    AllGroups,
+}
+
+impl Scope {
+    fn ctl_args(&self) -> String {
+        match self {
+            Scope::Uids(ids) => format!("--uid --list {}", shell_escape::escape(ids.into())),
+            Scope::Gids(ids) => format!("--gid --list {}", shell_escape::escape(ids.into())),
+            Scope::AllUsers => "--uid --all".into(),
+            Scope::AllGroups => "--gid --all".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    /// Highest space or chunk-file utilization first
+    Used,
+    /// Most bytes used first
+    Size,
+    /// Most chunk files first
+    Files,
+    Name,
+    Id,
+}
+
+/// Usage and limits of one user or group; `None` limits are unlimited.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct QuotaEntry {
+    pub name: String,
+    pub id: u32,
+    pub size_used: u64,
+    pub size_limit: Option<u64>,
+    pub files_used: u64,
+    pub files_limit: Option<u64>,
+}
+
+impl QuotaEntry {
+    pub fn size_pct(&self) -> Option<f64> { pct(self.size_used, self.size_limit) }
+    pub fn files_pct(&self) -> Option<f64> { pct(self.files_used, self.files_limit) }
+
+    /// The higher of the two utilizations.
+    pub fn utilization(&self) -> Option<f64> {
+        match (self.size_pct(), self.files_pct()) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+fn pct(used: u64, limit: Option<u64>) -> Option<f64> {
+    match limit {
+        Some(0) => None,
+        Some(l) => Some(used as f64 * 100.0 / l as f64),
+        None => None,
+    }
+}
+
+/// Parse `beegfs-ctl --getquota --csv`: `name,id,size,hard,files,hard`, sizes in bytes, `unlimited` for no limit.
+pub(crate) fn parse_quota_csv(text: &str) -> Vec<QuotaEntry> {
+    let limit = |s: &str| if s.trim() == "unlimited" { Some(None) } else { s.trim().parse().ok().map(Some) };
+    text.lines().filter_map(|line| {
+        let f: Vec<&str> = line.split(',').collect();
+        if f.len() != 6 { return None; }
+        Some(QuotaEntry {
+            name: f[0].trim().to_string(),
+            id: f[1].trim().parse().ok()?,
+            size_used: f[2].trim().parse().ok()?,
+            size_limit: limit(f[3])?,
+            files_used: f[4].trim().parse().ok()?,
+            files_limit: limit(f[5])?,
+        })
+    }).collect()
+}
+
+pub(crate) fn sort(entries: &mut [QuotaEntry], by: SortBy) {
+    let desc = |a: f64, b: f64| b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal);
+    match by {
+        SortBy::Used => entries.sort_by(|a, b| desc(a.utilization().unwrap_or(-1.0), b.utilization().unwrap_or(-1.0))),
+        SortBy::Size => entries.sort_by_key(|e| std::cmp::Reverse(e.size_used)),
+        SortBy::Files => entries.sort_by_key(|e| std::cmp::Reverse(e.files_used)),
+        SortBy::Name => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortBy::Id => entries.sort_by_key(|e| e.id),
+    }
+}
+
+pub fn show(cli: &crate::Cli, cfg: &config::Config, selector: &str, scope: &Scope, sort_by: SortBy, warn_percent: Option<u8>, timeout: u64) -> Result<()> {
+    let node = single_node(cfg, selector)?;
+    let warn = warn_percent.or(cfg.quota_warn_percent).unwrap_or(DEFAULT_WARN_PERCENT);
+    let tr = transport::from_config(cfg);
+    let out = tr.exec_idempotent(&node.host, &wrap_timeout(&format!("beegfs-ctl --getquota --csv {}", scope.ctl_args()), timeout))?;
+    let mut entries = parse_quota_csv(&out.stdout);
+    if entries.is_empty() {
+        let err = out.stderr.trim();
+        anyhow::bail!("no quota data from beegfs-ctl on {}{}", node.name, if err.is_empty() { String::new() } else { format!(": {}", err) });
+    }
+    sort(&mut entries, sort_by);
+
+    let kind = if matches!(scope, Scope::Gids(_) | Scope::AllGroups) { "group" } else { "user" };
+    let limit = |l: Option<u64>, f: &dyn Fn(u64) -> String| l.map(f).unwrap_or_else(|| "unlimited".into());
+    let p = |v: Option<f64>| v.map(|v| format!("{:.0}%", v)).unwrap_or_else(|| "-".into());
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            let who = if kind == "group" { "Group" } else { "User" };
+            table.set_header(vec![who, "ID", "Size used", "Size limit", "Size %", "Chunk files", "Files limit", "Files %"]);
+            for e in &entries {
+                table.add_row(vec![
+                    e.name.clone(), e.id.to_string(),
+                    crate::transfer::format_size(e.size_used), limit(e.size_limit, &crate::transfer::format_size), p(e.size_pct()),
+                    e.files_used.to_string(), limit(e.files_limit, &|n: u64| n.to_string()), p(e.files_pct()),
+                ]);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let arr: Vec<_> = entries.iter().map(|e| serde_json::json!({
+                "kind": kind,
+                "name": e.name,
+                "id": e.id,
+                "size_used": e.size_used,
+                "size_limit": e.size_limit,
+                "size_pct": e.size_pct(),
+                "files_used": e.files_used,
+                "files_limit": e.files_limit,
+                "files_pct": e.files_pct(),
+            })).collect();
+            crate::formats::print(cli.output, &arr)?;
+        }
+    }
+
+    // Warnings
+    let over: Vec<String> = entries.iter()
+        .filter(|e| e.utilization().is_some_and(|u| u >= warn as f64))
+        .map(|e| format!("{} ({:.0}%)", e.name, e.utilization().unwrap_or_default()))
+        .collect();
+    if !over.is_empty() { log::warn!("{} quota at or above {}%: {}", kind, warn, over.join(", ")); }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "name,id,size,hard,files,hard
+root,0,302625452032,unlimited,124963,unlimited
+alice,1001,950,1000,10,100
+bob,1002,100,1000,95,100
+broken line
+";
+
+    #[test]
+    fn parse_and_sort() {
+        let mut q = parse_quota_csv(CSV);
+        assert_eq!(q.len(), 3);
+        assert_eq!(q[0].size_limit, None);
+        assert_eq!(q[0].utilization(), None);
+        assert_eq!(q[1].size_pct(), Some(95.0));
+        assert_eq!(q[2].utilization(), Some(95.0));
+        sort(&mut q, SortBy::Used);
+        assert_eq!(q.last().unwrap().name, "root");
+        sort(&mut q, SortBy::Size);
+        assert_eq!(q[0].name, "root");
+        sort(&mut q, SortBy::Id);
+        assert_eq!(q.iter().map(|e| e.id).collect::<Vec<_>>(), vec![0, 1001, 1002]);
+    }
+}
@@ -0,0 +1,243 @@
+//! `beeg resync status`: progress of the buddy mirror resyncs, one row per group, with a
+//! rate and ETA from the previous sample. Samples are kept in the state dir, so a resync
+//! that stops making headway is caught across separate runs (cron) as well as by `--watch`.
+
+use crate::beegfs::{Ctl, NodeType, ResyncStats};
+use crate::checks::{error_severity, Severity, NOPERM_HINT};
+use crate::{config, state};
+use anyhow::{bail, Result};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const SAMPLES_FILE: &str = "resync.json";
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub node_type: NodeType,
+    /// Buddy group IDs; `None` for all
+    pub groups: Option<Vec<String>>,
+    pub mgmt_node: Option<String>,
+    /// Seconds a running resync may go without syncing anything before it counts as stalled
+    pub stall: u64,
+    /// Poll every this many seconds until no resync is running
+    pub watch: Option<u64>,
+    pub timeout: u64,
+}
+
+/// The last look at a group's resync job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Sample {
+    /// Tells a new job of the group from the one sampled before
+    pub start_time: Option<String>,
+    pub synced: u64,
+    /// Unix time of the sample
+    pub at: i64,
+    /// Unix time `synced` last went up
+    pub changed_at: i64,
+}
+
+/// Compare `stats` with the group's previous sample: the new sample, and the entries synced
+/// per second since then when it was of the same job.
+pub(crate) fn observe(prev: Option<&Sample>, stats: &ResyncStats, now: i64) -> (Sample, Option<f64>) {
+    let synced = stats.synced();
+    match prev.filter(|p| p.start_time == stats.start_time && p.synced <= synced && p.at < now) {
+        Some(p) => {
+            let rate = (synced - p.synced) as f64 / (now - p.at) as f64;
+            let changed_at = if synced > p.synced { now } else { p.changed_at };
+            (Sample { start_time: stats.start_time.clone(), synced, at: now, changed_at }, Some(rate))
+        }
+        None => (Sample { start_time: stats.start_time.clone(), synced, at: now, changed_at: now }, None),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GroupRow {
+    group: String,
+    primary: String,
+    secondary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<ResyncStats>,
+    synced: u64,
+    total: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percent: Option<f64>,
+    /// Entries per second since the previous sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eta_secs: Option<u64>,
+    /// Seconds without headway, once that reaches `--stall`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stalled_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip)]
+    severity: Severity,
+}
+
+impl GroupRow {
+    fn state(&self) -> &str {
+        self.stats.as_ref().map(|s| s.state.as_str()).unwrap_or("-")
+    }
+}
+
+fn grade(stats: &ResyncStats, stalled: bool) -> Severity {
+    match stats.state.to_ascii_lowercase().as_str() {
+        _ if stalled => Severity::Failure,
+        "failure" | "errors" => Severity::Failure,
+        "interrupted" => Severity::Warning,
+        _ if stats.errors() > 0 => Severity::Warning,
+        _ => Severity::Ok,
+    }
+}
+
+/// `1h05m`, `4m10s`, `40s`.
+fn eta(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h{:02}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// One look at every selected group, updating the samples.
+fn poll(ctl: &Ctl, opts: &Options, samples: &mut BTreeMap<String, Sample>) -> Result<Vec<GroupRow>> {
+    let nt = opts.node_type;
+    let mut groups = ctl.mirror_groups(nt)?;
+    if let Some(wanted) = &opts.groups {
+        if let Some(g) = wanted.iter().find(|g| !groups.iter().any(|m| &m.id == *g)) { bail!("there is no {} buddy group {}", nt.as_str(), g); }
+        groups.retain(|m| wanted.contains(&m.id));
+    }
+    let now = chrono::Utc::now().timestamp();
+    Ok(groups.into_iter().map(|g| {
+        let mut row = GroupRow { group: g.id.clone(), primary: g.primary, secondary: g.secondary, stats: None, synced: 0, total: 0, percent: None, rate: None, eta_secs: None, stalled_secs: None, error: None, severity: Severity::Ok };
+        match ctl.resync_stats(nt, &g.id) {
+            Ok(stats) => {
+                let key = format!("{}/{}", nt.as_str(), g.id);
+                let (sample, rate) = observe(samples.get(&key), &stats, now);
+                let (synced, total) = stats.progress();
+                (row.synced, row.total) = (synced, total);
+                row.percent = (total > 0).then(|| (synced.min(total) as f64 * 100.0 / total as f64 * 10.0).round() / 10.0);
+                if stats.running() {
+                    row.rate = rate;
+                    row.eta_secs = rate.filter(|r| *r > 0.0 && total > synced).map(|r| ((total - synced) as f64 / r).ceil() as u64);
+                    let idle = (now - sample.changed_at).max(0) as u64;
+                    if idle >= opts.stall { row.stalled_secs = Some(idle); }
+                }
+                row.severity = grade(&stats, row.stalled_secs.is_some());
+                row.stats = Some(stats);
+                samples.insert(key, sample);
+            }
+            Err(e) => {
+                row.severity = error_severity(&e);
+                row.error = Some(format!("{:#}", e));
+            }
+        }
+        row
+    }).collect())
+}
+
+fn table(rows: &[GroupRow]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Group", "Primary", "Secondary", "State", "Synced", "Progress", "Rate", "ETA", "Errors", "Detail"]);
+    let dash = || "-".to_string();
+    for r in rows {
+        let detail = match (&r.error, r.stalled_secs, r.stats.as_ref()) {
+            (Some(e), _, _) => e.clone(),
+            (None, Some(s), _) => format!("stalled: nothing synced for {}", eta(s)),
+            (None, None, Some(st)) if !st.running() => st.end_time.clone().map(|t| format!("ended {}", t)).unwrap_or_default(),
+            _ => String::new(),
+        };
+        table.add_row(vec![
+            comfy_table::Cell::new(&r.group), comfy_table::Cell::new(&r.primary), comfy_table::Cell::new(&r.secondary),
+            crate::tables::status_cell(r.state(), r.severity),
+            comfy_table::Cell::new(if r.total > 0 { format!("{}/{}", r.synced, r.total) } else { dash() }),
+            comfy_table::Cell::new(r.percent.map(|p| format!("{:.1}%", p)).unwrap_or_else(dash)),
+            comfy_table::Cell::new(r.rate.map(|v| format!("{:.1}/s", v)).unwrap_or_else(dash)),
+            comfy_table::Cell::new(r.eta_secs.map(eta).unwrap_or_else(dash)),
+            comfy_table::Cell::new(r.stats.as_ref().map(|s| s.errors().to_string()).unwrap_or_else(dash)),
+            comfy_table::Cell::new(detail),
+        ]);
+    }
+    table
+}
+
+pub fn status(cli: &crate::Cli, cfg: &config::Config, opts: &Options) -> Result<Severity> {
+    let mgmt = match &opts.mgmt_node {
+        Some(sel) => crate::checks::single_node(cfg, sel)?,
+        None => match cfg.nodes.iter().find(|n| n.role() == "mgmtd") {
+            Some(n) => n,
+            None => bail!("resync status needs --mgmt-node or a node with the mgmtd role"),
+        },
+    };
+    let ctl = Ctl::new(cfg, mgmt, opts.timeout);
+    let redraw = opts.watch.is_some() && std::io::stdout().is_terminal();
+    let mut samples: BTreeMap<String, Sample> = state::read_json(SAMPLES_FILE).unwrap_or_default();
+    let mut iteration = 0u64;
+    loop {
+        iteration += 1;
+        let started = Instant::now();
+        let rows = poll(&ctl, opts, &mut samples)?;
+        if let Err(e) = state::write_json(SAMPLES_FILE, &samples) { log::warn!("saving resync samples: {:#}", e); }
+        let sev = rows.iter().map(|r| r.severity).max().unwrap_or_default();
+        let now = chrono::Utc::now();
+        match cli.output {
+            crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+                if redraw { print!("\x1b[H\x1b[2J"); }
+                if let Some(every) = opts.watch {
+                    crate::tables::note(cli.output, &format!("Every {}s: beeg resync status    {} (run {})", every, crate::timefmt::human(now, cli.utc), iteration));
+                }
+                if rows.is_empty() { crate::tables::note(cli.output, &format!("No {} buddy groups.", opts.node_type.as_str())); }
+                else { crate::tables::print(cli.output, &table(&rows)); }
+            }
+            crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+                let v = serde_json::json!({"time": crate::timefmt::rfc3339(now), "node_type": opts.node_type.as_str(), "groups": rows});
+                if opts.watch.is_some() { crate::formats::print_record(cli.output, &v)?; } else { crate::formats::print(cli.output, &v)?; }
+            }
+        }
+        std::io::stdout().flush()?;
+
+        let stalled: Vec<&str> = rows.iter().filter(|r| r.stalled_secs.is_some()).map(|r| r.group.as_str()).collect();
+        let running = rows.iter().any(|r| r.stats.as_ref().is_some_and(ResyncStats::running));
+        // a watch ends once nothing is left to watch, or as soon as something stalls
+        if let (Some(every), true, true) = (opts.watch, running, stalled.is_empty()) {
+            std::thread::sleep(Duration::from_secs(every).saturating_sub(started.elapsed()));
+            continue;
+        }
+        if !stalled.is_empty() { log::warn!("resync stalled in buddy group(s) {}: nothing synced for {}s or more", stalled.join(", "), opts.stall); }
+        if sev == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+        return Ok(sev);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(state: &str, start: &str, synced: u64) -> ResyncStats {
+        ResyncStats { state: state.into(), start_time: Some(start.into()), end_time: None, counters: BTreeMap::from([("synced files".to_string(), synced)]) }
+    }
+
+    #[test]
+    fn samples_and_grades() {
+        let (first, rate) = observe(None, &stats("Running", "06:10", 100), 1000);
+        assert_eq!((first.changed_at, rate), (1000, None));
+        let (second, rate) = observe(Some(&first), &stats("Running", "06:10", 400), 1010);
+        assert_eq!((second.changed_at, rate), (1010, Some(30.0)));
+        let (third, rate) = observe(Some(&second), &stats("Running", "06:10", 400), 1310);
+        assert_eq!((third.changed_at, rate), (1010, Some(0.0)));
+        // a new job of the group starts over
+        let (restarted, rate) = observe(Some(&third), &stats("Running", "07:00", 5), 1320);
+        assert_eq!((restarted.changed_at, rate), (1320, None));
+
+        assert_eq!(grade(&stats("Running", "06:10", 1), false), Severity::Ok);
+        assert_eq!(grade(&stats("Running", "06:10", 1), true), Severity::Failure);
+        assert_eq!(grade(&stats("Interrupted", "06:10", 1), false), Severity::Warning);
+        assert_eq!(grade(&stats("Errors", "06:10", 1), false), Severity::Failure);
+        assert_eq!((eta(40), eta(250), eta(3900)), ("40s".to_string(), "4m10s".to_string(), "1h05m".to_string()));
+    }
+}
@@ -0,0 +1,204 @@
+//! `beeg rollout restart`: restart a BeeGFS service a batch of nodes at a time and, with
+//! `--wait-healthy`, hold the next batch until the mgmtd has noticed the restart and reports
+//! every target of the service Online and Good again.
+
+use crate::beegfs::{Ctl, NodeType, TargetState};
+use crate::checks::Severity;
+use crate::{config, transport};
+use crate::service::{self, Service};
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The restart itself; `action` is always restart
+    pub service: service::Options,
+    pub wait_healthy: bool,
+    /// Seconds the targets get to recover after a batch
+    pub health_timeout: u64,
+    /// Seconds the targets have to stay healthy before the next batch
+    pub settle: u64,
+    pub mgmt_node: Option<String>,
+}
+
+/// Seconds between target listings while waiting.
+const HEALTH_POLL: Duration = Duration::from_secs(5);
+
+/// The mgmtd's `sysTargetOfflineTimeoutSecs` when its config does not set it.
+const DEFAULT_OFFLINE_TIMEOUT: u64 = 180;
+
+/// `sysTargetOfflineTimeoutSecs` from the mgmtd config on `mgmt`, or the BeeGFS default.
+fn offline_timeout(cfg: &config::Config, mgmt: &config::Node, timeout: u64) -> u64 {
+    let cmd = "sed -n 's/^[[:space:]]*sysTargetOfflineTimeoutSecs[[:space:]]*=[[:space:]]*//p' /etc/beegfs/beegfs-mgmtd.conf";
+    transport::from_config(cfg).exec_idempotent(&mgmt.host, &crate::checks::wrap_timeout(cmd, timeout)).ok()
+        .and_then(|o| o.stdout.lines().last().and_then(|l| l.trim().parse().ok()))
+        .unwrap_or(DEFAULT_OFFLINE_TIMEOUT)
+}
+
+/// Whether target listings taken after a restart can be trusted yet. Right after `systemctl
+/// restart` the mgmtd still shows the targets Online from before it; only once one of them
+/// was seen not Online, or the offline timeout has passed without that (the new instance
+/// re-registered before the mgmtd missed the old one), is an Online listing of the new one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Recovery {
+    Stale,
+    Current,
+}
+
+impl Recovery {
+    /// The next state given a listing taken `since_restart` after the batch was restarted.
+    /// Listings without any target of the batch (a mgmtd restart) are current at once.
+    pub(crate) fn next(self, states: &BTreeMap<String, TargetState>, batch: &[&config::Node], since_restart: Duration, offline_timeout: Duration) -> Recovery {
+        let own: Vec<&TargetState> = states.values().filter(|t| batch.iter().any(|n| crate::status::on_node(&t.node, n))).collect();
+        let noticed = own.is_empty() || own.iter().any(|t| !t.reachability.eq_ignore_ascii_case("online"));
+        if self == Recovery::Current || noticed || since_restart >= offline_timeout { Recovery::Current } else { Recovery::Stale }
+    }
+}
+
+/// The targets whose state the gate watches for `service`.
+fn watched(service: Service) -> Option<NodeType> {
+    match service {
+        Service::Storage | Service::Mgmtd => Some(NodeType::Storage),
+        Service::Meta => Some(NodeType::Meta),
+        Service::Client | Service::Helperd => None,
+    }
+}
+
+/// `101 on stor01: Offline/Needs-resync` for every target that is not Online and Good.
+pub(crate) fn unhealthy(states: &BTreeMap<String, TargetState>) -> Vec<String> {
+    states.iter()
+        .filter(|(_, t)| !t.reachability.eq_ignore_ascii_case("online") || !t.consistency.eq_ignore_ascii_case("good"))
+        .map(|(id, t)| format!("{} on {}: {}/{}", id, crate::status::node_id_host(&t.node), t.reachability, t.consistency))
+        .collect()
+}
+
+fn listing(ctl: &Ctl, nt: NodeType) -> Result<BTreeMap<String, TargetState>, String> {
+    let states = ctl.target_states(nt).map_err(|e| format!("{:#}", e))?;
+    if states.is_empty() { return Err(format!("the mgmtd lists no {} targets", nt.as_str())); }
+    Ok(states)
+}
+
+fn healthy(states: &BTreeMap<String, TargetState>) -> Result<(), String> {
+    let bad = unhealthy(states);
+    if bad.is_empty() { Ok(()) } else { Err(format!("not Online/Good: {}", bad.join(", "))) }
+}
+
+fn check(ctl: &Ctl, nt: NodeType) -> Result<(), String> {
+    healthy(&listing(ctl, nt)?)
+}
+
+/// After `batch` was restarted, poll the target states until the mgmtd has noticed the
+/// restart (see `Recovery`) and they have then been healthy for `settle` seconds in a row, or
+/// give up after `timeout` seconds with what was wrong last.
+fn wait_healthy(ctl: &Ctl, nt: NodeType, batch: &[&config::Node], offline_timeout: u64, timeout: u64, settle: u64) -> Result<(), String> {
+    let restarted = Instant::now();
+    let deadline = restarted + Duration::from_secs(timeout);
+    let mut recovery = Recovery::Stale;
+    let mut healthy_since: Option<Instant> = None;
+    loop {
+        let states = listing(ctl, nt);
+        if let Ok(states) = &states {
+            recovery = recovery.next(states, batch, restarted.elapsed(), Duration::from_secs(offline_timeout));
+        }
+        let last = match states.and_then(|s| healthy(&s)) {
+            Ok(()) if recovery == Recovery::Stale => Some(format!("the mgmtd still lists the targets of the restarted node(s) as before the restart (waiting up to {}s, its offline timeout)", offline_timeout)),
+            Ok(()) if healthy_since.get_or_insert_with(Instant::now).elapsed() >= Duration::from_secs(settle) => return Ok(()),
+            Ok(()) => None,
+            Err(e) => { healthy_since = None; Some(e) }
+        };
+        if Instant::now() >= deadline {
+            return Err(format!("{} targets not healthy after {}s{}", nt.as_str(), timeout, last.map(|e| format!("; {}", e)).unwrap_or_default()));
+        }
+        thread::sleep(HEALTH_POLL);
+    }
+}
+
+pub fn restart(cli: &crate::Cli, cfg: &config::Config, selector: &str, opts: &Options) -> Result<Severity> {
+    let svc = &opts.service;
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+
+    let gate = match (opts.wait_healthy, watched(svc.service)) {
+        (false, _) => None,
+        (true, None) => bail!("--wait-healthy watches target states, which {} has none of; the restart is verified per node either way", svc.service.unit()),
+        (true, Some(nt)) => {
+            let mgmt = match &opts.mgmt_node {
+                Some(sel) => crate::checks::single_node(cfg, sel)?,
+                None => match cfg.nodes.iter().find(|n| n.role() == "mgmtd") {
+                    Some(n) => n,
+                    None => bail!("--wait-healthy needs --mgmt-node or a node with the mgmtd role"),
+                },
+            };
+            let ctl = Ctl::new(cfg, mgmt, svc.timeout);
+            // restarting into a degraded cluster would take more targets away from it
+            if let Err(e) = check(&ctl, nt) { bail!("refusing to start the rollout, the cluster is not healthy now: {}", e); }
+            let offline = offline_timeout(cfg, mgmt, svc.timeout);
+            if offline >= opts.health_timeout {
+                bail!("--health-timeout {}s does not outlast the mgmtd's sysTargetOfflineTimeoutSecs ({}s); raise it", opts.health_timeout, offline);
+            }
+            Some((ctl, nt, mgmt.name.clone(), offline))
+        }
+    };
+
+    if !service::confirm(cfg, &nodes, svc)? { return Ok(Severity::Warning); }
+    let batches: Vec<&[&config::Node]> = nodes.chunks(svc.serial.max(1)).collect();
+    let outcomes = service::rolling(&nodes, svc.serial, |n, batch| service::act(cfg, n, batch, svc), |batch| match &gate {
+        Some((ctl, nt, _, offline)) => wait_healthy(ctl, *nt, batches[batch - 1], *offline, opts.health_timeout, opts.settle),
+        None => Ok(()),
+    });
+
+    let mut extra = serde_json::Map::new();
+    extra.insert("wait_healthy".into(), opts.wait_healthy.into());
+    if let Some((_, nt, mgmt, offline)) = &gate {
+        extra.insert("mgmt_node".into(), mgmt.clone().into());
+        extra.insert("targets".into(), nt.as_str().into());
+        extra.insert("offline_timeout".into(), (*offline).into());
+    }
+    service::report(cli, cfg, svc, outcomes, extra)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unhealthy_targets() {
+        let t = |r: &str, c: &str, node: &str| TargetState { reachability: r.into(), consistency: c.into(), node: node.into() };
+        let states = BTreeMap::from([
+            ("101".to_string(), t("Online", "Good", "beegfs-storage stor01 [ID: 1]")),
+            ("201".to_string(), t("Online", "Needs-resync", "beegfs-storage stor02 [ID: 2]")),
+            ("202".to_string(), t("Probably-offline", "Good", "2")),
+        ]);
+        assert_eq!(unhealthy(&states), vec!["201 on stor02: Online/Needs-resync", "202 on 2: Probably-offline/Good"]);
+        assert!(unhealthy(&BTreeMap::from([("1".to_string(), t("online", "good", "1"))])).is_empty());
+        assert_eq!(watched(Service::Meta), Some(NodeType::Meta));
+        assert_eq!(watched(Service::Client), None);
+    }
+
+    #[test]
+    fn stale_listing_after_restart() {
+        let t = |r: &str, node: &str| TargetState { reachability: r.into(), consistency: "Good".into(), node: node.into() };
+        let stor01 = config::Node { name: "stor01".into(), host: "10.0.0.1".into(), ..Default::default() };
+        let batch = [&stor01];
+        let offline = Duration::from_secs(180);
+        let secs = Duration::from_secs;
+        // still shows Online from before the restart: not to be trusted yet
+        let before = BTreeMap::from([("101".to_string(), t("Online", "beegfs-storage stor01 [ID: 1]")), ("201".to_string(), t("Online", "beegfs-storage stor02 [ID: 2]"))]);
+        assert!(healthy(&before).is_ok());
+        assert_eq!(Recovery::Stale.next(&before, &batch, secs(10), offline), Recovery::Stale);
+        // the mgmtd noticed; Online from here on is the new instance
+        let noticed = BTreeMap::from([("101".to_string(), t("Probably-offline", "beegfs-storage stor01 [ID: 1]")), ("201".to_string(), t("Online", "beegfs-storage stor02 [ID: 2]"))]);
+        assert_eq!(Recovery::Stale.next(&noticed, &batch, secs(20), offline), Recovery::Current);
+        assert_eq!(Recovery::Current.next(&before, &batch, secs(30), offline), Recovery::Current);
+        // another node going offline says nothing about this one
+        let other = BTreeMap::from([("101".to_string(), t("Online", "beegfs-storage stor01 [ID: 1]")), ("201".to_string(), t("Offline", "beegfs-storage stor02 [ID: 2]"))]);
+        assert_eq!(Recovery::Stale.next(&other, &batch, secs(20), offline), Recovery::Stale);
+        // never seen offline, but the offline timeout has passed since
+        assert_eq!(Recovery::Stale.next(&before, &batch, secs(180), offline), Recovery::Current);
+        // the batch has no targets of its own (mgmtd)
+        let mgmt = config::Node { name: "mgmt01".into(), host: "10.0.0.9".into(), ..Default::default() };
+        assert_eq!(Recovery::Stale.next(&before, &[&mgmt], secs(5), offline), Recovery::Current);
+    }
+}
@@ -0,0 +1,388 @@
+//! `beeg service`: start, stop or restart a BeeGFS service on the selected nodes a batch at a
+//! time, checking after each batch that the service came up (or went down) before going on.
+
+use crate::checks::{permission_denied, wrap_timeout, Severity, NOPERM_HINT};
+use crate::{config, identity, prompt, transport};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Action {
+    Start,
+    Stop,
+    Restart,
+    /// Show the unit's state; changes nothing
+    Status,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self { Action::Start => "start", Action::Stop => "stop", Action::Restart => "restart", Action::Status => "status" }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Service {
+    Client,
+    Meta,
+    Storage,
+    Mgmtd,
+    Helperd,
+}
+
+/// `storage`, `beegfs-storage` or `beegfs-storage.service`.
+pub fn parse_service(s: &str) -> Result<Service, String> {
+    let name = s.strip_suffix(".service").unwrap_or(s);
+    let name = name.strip_prefix("beegfs-").unwrap_or(name);
+    Service::from_str(name, true).map_err(|_| format!("'{}' is not a BeeGFS service (client, meta, storage, mgmtd or helperd)", s))
+}
+
+impl Service {
+    pub fn unit(self) -> &'static str {
+        match self {
+            Service::Client => "beegfs-client",
+            Service::Meta => "beegfs-meta",
+            Service::Storage => "beegfs-storage",
+            Service::Mgmtd => "beegfs-mgmtd",
+            Service::Helperd => "beegfs-helperd",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub action: Action,
+    pub service: Service,
+    /// Nodes acted on at once
+    pub serial: usize,
+    pub yes: bool,
+    /// Seconds for each systemctl call
+    pub timeout: u64,
+    /// Seconds a node gets to reach the expected state after the action
+    pub verify_timeout: u64,
+}
+
+/// Seconds between verification probes, and how long a started unit has to stay up.
+const POLL: Duration = Duration::from_secs(1);
+const SETTLE: Duration = Duration::from_secs(3);
+
+/// The unit's state, plus the beegfs mounts for the client.
+fn probe_cmd(service: Service) -> String {
+    let mut cmd = format!("systemctl show -p LoadState -p ActiveState -p SubState -p ActiveEnterTimestamp {}", service.unit());
+    if service == Service::Client {
+        cmd.push_str("; awk '$3 == \"beegfs\" { print \"Mount=\" $2 }' /proc/mounts");
+    }
+    cmd
+}
+
+/// What a node's probe found.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub(crate) struct UnitState {
+    pub load: String,
+    pub active: String,
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Mounted beegfs file systems (client only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<String>,
+}
+
+impl UnitState {
+    /// `active (running)`, or `not installed` when systemd has no such unit.
+    pub fn describe(&self) -> String {
+        if self.load == "not-found" { return "not installed".into(); }
+        format!("{} ({})", self.active, self.sub)
+    }
+}
+
+/// `Key=value` lines of `systemctl show` and the `Mount=` lines after them.
+pub(crate) fn parse_state(text: &str) -> UnitState {
+    let mut fields: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut st = UnitState::default();
+    for (k, v) in text.lines().filter_map(|l| l.trim().split_once('=')) {
+        if k == "Mount" { st.mounts.push(v.to_string()); } else { fields.insert(k, v); }
+    }
+    let get = |k: &str| fields.get(k).map(|v| v.to_string()).unwrap_or_default();
+    st.load = get("LoadState");
+    st.active = get("ActiveState");
+    st.sub = get("SubState");
+    st.since = fields.get("ActiveEnterTimestamp").filter(|v| !v.is_empty()).map(|v| v.to_string());
+    st
+}
+
+/// Whether `st` is what `action` should leave behind: `Ok`, `Err` with the reason, or `None`
+/// while the unit is still on its way.
+pub(crate) fn verdict(action: Action, service: Service, st: &UnitState) -> Option<Result<(), String>> {
+    if st.load == "not-found" { return Some(Err(format!("{} is not installed", service.unit()))); }
+    match (action, st.active.as_str()) {
+        (_, "activating" | "deactivating" | "reloading") => None,
+        (Action::Stop, "inactive" | "failed") => Some(Ok(())),
+        (Action::Stop, other) => Some(Err(format!("still {}", other))),
+        (_, "active") if service == Service::Client && st.mounts.is_empty() => Some(Err("active, but no beegfs file system is mounted".into())),
+        (_, "active") => Some(Ok(())),
+        (_, other) => Some(Err(format!("{} ({})", other, st.sub))),
+    }
+}
+
+/// What happened on one node.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Outcome {
+    pub node: String,
+    pub host: String,
+    /// Batch the node was in, from 1; 0 for `status`
+    pub batch: usize,
+    /// done, failed or skipped
+    pub result: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<UnitState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl Outcome {
+    fn skipped(n: &config::Node, batch: usize) -> Outcome {
+        Outcome { node: n.name.clone(), host: n.host.clone(), batch, result: "skipped", state: None, error: None, duration_ms: 0 }
+    }
+
+    pub fn failed(&self) -> bool { self.result == "failed" }
+}
+
+fn probe(tr: &dyn transport::Transport, host: &str, service: Service, timeout: u64) -> Result<UnitState, String> {
+    match tr.exec_idempotent(host, &wrap_timeout(&probe_cmd(service), timeout)) {
+        Ok(out) if out.status == Some(0) => Ok(parse_state(&out.stdout)),
+        Ok(out) => Err(out.stderr.trim().lines().last().unwrap_or("systemctl show failed").to_string()),
+        Err(e) => Err(format!("{:#}", e)),
+    }
+}
+
+/// Run `action` on one node and wait for the unit to settle in the state it should have.
+pub(crate) fn act(cfg: &config::Config, n: &config::Node, batch: usize, opts: &Options) -> Outcome {
+    act_on(transport::from_config(cfg).as_ref(), n, batch, opts)
+}
+
+fn act_on(tr: &dyn transport::Transport, n: &config::Node, batch: usize, opts: &Options) -> Outcome {
+    let started = Instant::now();
+    let result = (|| -> Result<UnitState, String> {
+        if opts.action == Action::Status { return probe(tr, &n.host, opts.service, opts.timeout); }
+        let cmd = format!("systemctl {} {}", opts.action.as_str(), opts.service.unit());
+        match tr.exec(&n.host, &wrap_timeout(&cmd, opts.timeout)) {
+            Ok(out) if out.status == Some(0) => {}
+            Ok(out) => return Err(out.stderr.trim().lines().last().map(String::from).unwrap_or_else(|| format!("{} exited {:?}", cmd, out.status))),
+            Err(e) => return Err(format!("{:#}", e)),
+        }
+        let deadline = Instant::now() + Duration::from_secs(opts.verify_timeout);
+        let mut settled_at: Option<Instant> = None;
+        loop {
+            let st = probe(tr, &n.host, opts.service, opts.timeout)?;
+            match verdict(opts.action, opts.service, &st) {
+                // a started unit has to stay up a moment, so a crash right after start is caught
+                Some(Ok(())) if opts.action == Action::Stop || settled_at.is_some_and(|t| t.elapsed() >= SETTLE) => return Ok(st),
+                Some(Ok(())) => { settled_at.get_or_insert_with(Instant::now); }
+                Some(Err(e)) if Instant::now() >= deadline => return Err(format!("not verified after {}s: {}", opts.verify_timeout, e)),
+                None if Instant::now() >= deadline => return Err(format!("not verified after {}s: still {}", opts.verify_timeout, st.describe())),
+                _ => settled_at = None,
+            }
+            thread::sleep(POLL);
+        }
+    })();
+    let (state, error) = match result {
+        Ok(st) => (Some(st), None),
+        Err(e) => (None, Some(e)),
+    };
+    let result = if error.is_some() { "failed" } else { "done" };
+    Outcome { node: n.name.clone(), host: n.host.clone(), batch, result, state, error, duration_ms: started.elapsed().as_millis() as u64 }
+}
+
+/// Run `step` on `serial` nodes at a time, then `gate` once the whole batch is done; a gate
+/// error fails the batch. After a batch with a failure the rest are skipped.
+pub(crate) fn rolling(
+    nodes: &[&config::Node],
+    serial: usize,
+    step: impl Fn(&config::Node, usize) -> Outcome + Sync,
+    mut gate: impl FnMut(usize) -> Result<(), String>,
+) -> Vec<Outcome> {
+    let mut out = Vec::new();
+    for (i, batch) in nodes.chunks(serial.max(1)).enumerate() {
+        if out.iter().any(Outcome::failed) {
+            out.extend(batch.iter().map(|n| Outcome::skipped(n, i + 1)));
+            continue;
+        }
+        let mut done = thread::scope(|s| {
+            let handles: Vec<_> = batch.iter().map(|n| s.spawn(|| step(n, i + 1))).collect();
+            handles.into_iter().zip(batch).map(|(h, n)| h.join().unwrap_or_else(|_| Outcome { result: "failed", error: Some("thread panicked".into()), ..Outcome::skipped(n, i + 1) })).collect::<Vec<_>>()
+        });
+        if !done.iter().any(Outcome::failed) {
+            if let Err(e) = gate(i + 1) {
+                for o in &mut done { o.result = "failed"; o.error = Some(e.clone()); }
+            }
+        }
+        out.extend(done);
+    }
+    out
+}
+
+/// OK when every node is done, NOPERM when refused, a failure otherwise; skipped nodes warn.
+fn severity(outcomes: &[Outcome]) -> Severity {
+    outcomes.iter().map(|o| match (o.result, &o.error) {
+        (_, Some(e)) if permission_denied(e) => Severity::NoPerm,
+        ("failed", _) => Severity::Failure,
+        ("skipped", _) => Severity::Warning,
+        _ => Severity::Ok,
+    }).max().unwrap_or_default()
+}
+
+/// Check the nodes belong to the cluster and ask before touching them; `false` when declined.
+pub(crate) fn confirm(cfg: &config::Config, nodes: &[&config::Node], opts: &Options) -> Result<bool> {
+    identity::verify(cfg, nodes, opts.timeout)?;
+    if opts.yes { return Ok(true); }
+    let unit = opts.service.unit();
+    if !std::io::stdin().is_terminal() { bail!("refusing to {} {} without confirmation; pass --yes to run non-interactively", opts.action.as_str(), unit); }
+    let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+    let question = format!("{} {} on {} node(s), {} at a time ({})?", capitalize(opts.action.as_str()), unit, nodes.len(), opts.serial, names.join(", "));
+    if !prompt::confirm(&question)? {
+        eprintln!("Aborted; no services were touched.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+pub fn run(cli: &crate::Cli, cfg: &config::Config, selector: &str, opts: &Options) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", selector); }
+    let outcomes = if opts.action == Action::Status {
+        crate::checks::per_node(cfg, &nodes, |tr, n| Ok(act_on(tr, n, 0, opts)))
+            .into_iter().map(|(n, o, _)| o.unwrap_or_else(|_| Outcome::skipped(n, 0))).collect()
+    } else {
+        if !confirm(cfg, &nodes, opts)? { return Ok(Severity::Warning); }
+        rolling(&nodes, opts.serial, |n, batch| act(cfg, n, batch, opts), |_| Ok(()))
+    };
+    report(cli, cfg, opts, outcomes, serde_json::Map::new())
+}
+
+/// Print the outcomes as a table or, with `extra` fields added, as JSON; warn about failed
+/// and skipped nodes. The severity decides the exit code.
+pub(crate) fn report(cli: &crate::Cli, cfg: &config::Config, opts: &Options, outcomes: Vec<Outcome>, extra: serde_json::Map<String, serde_json::Value>) -> Result<Severity> {
+    let unit = opts.service.unit();
+    let mut rows = outcomes.clone();
+    config::sort_rows(cfg, cli.sort, &mut rows, |o| &o.node);
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            let status = opts.action == Action::Status;
+            let mut header = vec!["Node", "Host"];
+            if !status { header.extend(["Batch", "Result"]); }
+            header.extend(["State", "Since", "Detail"]);
+            table.set_header(header);
+            for o in &rows {
+                let mut row = vec![o.node.clone(), o.host.clone()];
+                if !status { row.extend([o.batch.to_string(), o.result.to_string()]); }
+                let st = o.state.as_ref();
+                row.push(st.map(UnitState::describe).unwrap_or_else(|| "-".into()));
+                row.push(st.and_then(|s| s.since.clone()).unwrap_or_else(|| "-".into()));
+                let detail = match (&o.error, st) {
+                    (Some(e), _) => e.clone(),
+                    (None, Some(s)) if !s.mounts.is_empty() => format!("mounted: {}", s.mounts.join(", ")),
+                    _ => String::new(),
+                };
+                row.push(detail);
+                table.add_row(row);
+            }
+            crate::tables::print(cli.output, &table);
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let mut v = serde_json::json!({"action": opts.action.as_str(), "unit": unit, "serial": opts.serial, "nodes": rows});
+            if let Some(obj) = v.as_object_mut() { obj.extend(extra); }
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    let failed: Vec<&str> = outcomes.iter().filter(|o| o.failed()).map(|o| o.node.as_str()).collect();
+    let skipped: Vec<&str> = outcomes.iter().filter(|o| o.result == "skipped").map(|o| o.node.as_str()).collect();
+    if !failed.is_empty() && opts.action != Action::Status { log::warn!("{} {} failed on {}", opts.action.as_str(), unit, failed.join(", ")); }
+    if !skipped.is_empty() { log::warn!("stopped after the failed batch; not touched: {}", skipped.join(", ")); }
+    let sev = severity(&outcomes);
+    if sev == Severity::NoPerm { log::warn!("permission denied; {}", NOPERM_HINT); }
+    Ok(sev)
+}
+
+fn capitalize(s: &str) -> String {
+    let mut c = s.chars();
+    c.next().map(|f| f.to_uppercase().chain(c).collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORAGE_UP: &str = "LoadState=loaded\nActiveState=active\nSubState=running\nActiveEnterTimestamp=Thu 2026-10-15 05:40:02 UTC\n";
+    const CLIENT_UP: &str = "LoadState=loaded\nActiveState=active\nSubState=exited\nActiveEnterTimestamp=Thu 2026-10-15 05:40:02 UTC\nMount=/mnt/beegfs\n";
+
+    #[test]
+    fn states_and_verdicts() {
+        let st = parse_state(STORAGE_UP);
+        assert_eq!((st.describe().as_str(), st.since.as_deref()), ("active (running)", Some("Thu 2026-10-15 05:40:02 UTC")));
+        assert_eq!(verdict(Action::Restart, Service::Storage, &st), Some(Ok(())));
+        assert_eq!(verdict(Action::Stop, Service::Storage, &st), Some(Err("still active".into())));
+
+        let client = parse_state(CLIENT_UP);
+        assert_eq!(client.mounts, vec!["/mnt/beegfs"]);
+        assert_eq!(verdict(Action::Start, Service::Client, &client), Some(Ok(())));
+        let unmounted = parse_state(&CLIENT_UP.replace("Mount=/mnt/beegfs\n", ""));
+        assert!(matches!(verdict(Action::Start, Service::Client, &unmounted), Some(Err(e)) if e.contains("no beegfs")));
+
+        let starting = parse_state("LoadState=loaded\nActiveState=activating\nSubState=start\nActiveEnterTimestamp=\n");
+        assert_eq!((verdict(Action::Start, Service::Meta, &starting), starting.since), (None, None));
+        let crashed = parse_state("LoadState=loaded\nActiveState=failed\nSubState=failed\n");
+        assert_eq!(verdict(Action::Restart, Service::Meta, &crashed), Some(Err("failed (failed)".into())));
+        assert_eq!(verdict(Action::Stop, Service::Meta, &crashed), Some(Ok(())));
+        let missing = parse_state("LoadState=not-found\nActiveState=inactive\nSubState=dead\n");
+        assert_eq!(missing.describe(), "not installed");
+        assert!(matches!(verdict(Action::Stop, Service::Mgmtd, &missing), Some(Err(_))));
+    }
+
+    #[test]
+    fn rolling_stops_after_a_failed_batch() {
+        let nodes: Vec<config::Node> = (1..=5).map(|i| config::Node { name: format!("stor{:02}", i), host: format!("10.0.0.{}", i), ..Default::default() }).collect();
+        let refs: Vec<&config::Node> = nodes.iter().collect();
+        let step = |n: &config::Node, batch| Outcome {
+            result: if n.name == "stor03" { "failed" } else { "done" },
+            error: (n.name == "stor03").then(|| "Job for beegfs-storage.service failed".into()),
+            ..Outcome::skipped(n, batch)
+        };
+        let out = rolling(&refs, 2, step, |_| Ok(()));
+        let got: Vec<(&str, usize, &str)> = out.iter().map(|o| (o.node.as_str(), o.batch, o.result)).collect();
+        assert_eq!(got, vec![("stor01", 1, "done"), ("stor02", 1, "done"), ("stor03", 2, "failed"), ("stor04", 2, "done"), ("stor05", 3, "skipped")]);
+        assert_eq!(severity(&out), Severity::Failure);
+        assert_eq!(severity(&out[..2]), Severity::Ok);
+        let denied = Outcome { result: "failed", error: Some("Failed to restart beegfs-storage.service: Access denied".into()), ..Outcome::skipped(&nodes[0], 1) };
+        assert_eq!(severity(&[denied]), Severity::NoPerm);
+
+        // a gate that does not pass fails its batch and stops the rest
+        let mut gates = Vec::new();
+        let out = rolling(&refs[..4], 1, |n, batch| Outcome { result: "done", ..Outcome::skipped(n, batch) }, |batch| {
+            gates.push(batch);
+            if batch == 2 { Err("target 202 Offline/Good".into()) } else { Ok(()) }
+        });
+        assert_eq!(gates, vec![1, 2]);
+        let got: Vec<(&str, &str)> = out.iter().map(|o| (o.node.as_str(), o.result)).collect();
+        assert_eq!(got, vec![("stor01", "done"), ("stor02", "failed"), ("stor03", "skipped"), ("stor04", "skipped")]);
+        assert_eq!(out[1].error.as_deref(), Some("target 202 Offline/Good"));
+    }
+
+    #[test]
+    fn service_names() {
+        assert_eq!(parse_service("storage"), Ok(Service::Storage));
+        assert_eq!(parse_service("beegfs-storage"), Ok(Service::Storage));
+        assert_eq!(parse_service("beegfs-meta.service"), Ok(Service::Meta));
+        assert!(parse_service("beegfs-mon").is_err());
+    }
+}
@@ -0,0 +1,400 @@
+use crate::checks::Severity;
+use crate::{config, state};
+use comfy_table::{presets::UTF8_FULL, Cell, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::{Command, Stdio};
+
+const BADGE_FILE: &str = "status-badge.json";
+
+/// Cached result of the cheap health probe behind `status --short`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Badge {
+    checked_at: i64,
+    total: usize,
+    unhealthy: Vec<String>,
+    #[serde(default)]
+    refresh_started: Option<i64>,
+}
+
+impl Badge {
+    fn token(&self) -> String {
+        if self.total == 0 {
+            "beegfs:NONE".to_string()
+        } else if self.unhealthy.is_empty() {
+            format!("beegfs:OK {}/{}", self.total, self.total)
+        } else {
+            format!("beegfs:DEGRADED {}!", self.unhealthy.len())
+        }
+    }
+}
+
+/// Print a one-token health badge for shell prompts and status bars.
+///
+/// Reads the cache only; when the entry is older than `max_age` seconds a
+/// detached `status --short --refresh` is started and the stale token is
+/// printed with a trailing `?`. `refresh` probes synchronously instead.
+pub fn short(cli: &crate::Cli, cfg: &config::Config, selector: &str, refresh: bool, max_age: u64) -> anyhow::Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    let mut cache: BTreeMap<String, Badge> = state::read_json(BADGE_FILE).unwrap_or_default();
+
+    let (badge, stale) = if refresh {
+        let badge = probe(cfg, selector, now);
+        cache.insert(selector.to_string(), badge.clone());
+        state::write_json(BADGE_FILE, &cache)?;
+        (Some(badge), false)
+    } else {
+        let cached = cache.get(selector).cloned();
+        let stale = cached.as_ref().is_none_or(|b| now - b.checked_at > max_age as i64);
+        // one refresher at a time; give up on it after max_age
+        let refreshing = cached.as_ref().and_then(|b| b.refresh_started).is_some_and(|t| now - t <= max_age as i64);
+        if stale && !refreshing {
+            let entry = cache.entry(selector.to_string()).or_default();
+            entry.refresh_started = Some(now);
+            // best effort: a read-only state dir must not break the prompt
+            if state::write_json(BADGE_FILE, &cache).is_ok() {
+                spawn_refresh(cli, selector);
+            }
+        }
+        (cached.filter(|b| b.checked_at > 0), stale)
+    };
+
+    let token = match &badge {
+        Some(b) => format!("{}{}", b.token(), if stale { "?" } else { "" }),
+        None => "beegfs:?".to_string(),
+    };
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => println!("{}", token),
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let obj = serde_json::json!({
+                "token": token,
+                "stale": stale,
+                "checked_at": badge.as_ref().and_then(|b| chrono::DateTime::from_timestamp(b.checked_at, 0)).map(crate::timefmt::rfc3339),
+                "total": badge.as_ref().map(|b| b.total),
+                "unhealthy": badge.as_ref().map(|b| &b.unhealthy),
+            });
+            crate::formats::print(cli.output, &obj)?;
+        }
+    }
+    Ok(())
+}
+
+/// A node is healthy when it answers over the transport and has no failed beegfs-* units.
+fn probe(cfg: &config::Config, selector: &str, now: i64) -> Badge {
+    let nodes = config::select_nodes(cfg, selector);
+    let cmd = crate::checks::wrap_timeout("systemctl --failed --no-legend --plain 'beegfs-*' 2>/dev/null | wc -l", 5);
+    let unhealthy = crate::checks::per_node(cfg, &nodes, |tr, n| Ok(matches!(tr.exec_idempotent(&n.host, &cmd), Ok(o) if o.stdout.trim() == "0")))
+        .into_iter().filter(|(_, healthy, _)| healthy != &Ok(true)).map(|(n, _, _)| n.name.clone())
+        .collect();
+    Badge { checked_at: now, total: nodes.len(), unhealthy, refresh_started: None }
+}
+
+pub(crate) const SERVICES: [&str; 4] = ["mgmtd", "meta", "storage", "client"];
+
+/// State of each BeegFS service, `-` for services that are not installed.
+const SERVICE_PROBE: &str = r#"for s in mgmtd meta storage client; do
+  echo "SVC $s $(systemctl show -p LoadState --value beegfs-$s 2>/dev/null) $(systemctl is-active beegfs-$s 2>/dev/null)"
+done"#;
+
+/// Service name -> `active`, `inactive`, `failed`, ... for the installed ones.
+pub(crate) fn parse_services(text: &str) -> BTreeMap<String, String> {
+    text.lines().filter_map(|l| {
+        let mut cols = l.strip_prefix("SVC ")?.split_whitespace();
+        let (name, load, active) = (cols.next()?, cols.next()?, cols.next().unwrap_or("unknown"));
+        (load == "loaded").then(|| (name.to_string(), active.to_string()))
+    }).collect()
+}
+
+/// One node in the cluster summary.
+#[derive(Debug, Clone)]
+pub(crate) struct NodeStatus {
+    pub name: String,
+    pub host: String,
+    /// Round trip of the probe; `Err` when the node could not be reached
+    pub probe: Result<(BTreeMap<String, String>, std::time::Duration), String>,
+}
+
+/// Storage targets as the mgmtd sees them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Targets {
+    pub mgmt: String,
+    pub spaces: Vec<crate::beegfs::TargetSpace>,
+    pub states: BTreeMap<String, crate::beegfs::TargetState>,
+}
+
+/// Probed nodes plus the mgmtd's view of the targets: what `beeg status` and `beeg dashboard` show.
+#[derive(Debug, Clone)]
+pub(crate) struct Cluster {
+    pub nodes: Vec<NodeStatus>,
+    /// Node the targets were read from, when one was found
+    pub mgmt: Option<String>,
+    pub targets: Result<Targets, String>,
+    /// `targets` failed because the mgmt node refused beegfs-ctl to the SSH user
+    pub targets_denied: bool,
+}
+
+/// `stor01` out of `beegfs-storage stor01 [ID: 1]`.
+pub(crate) fn node_id_host(node: &str) -> &str {
+    let head = node.split(" [ID:").next().unwrap_or(node).trim();
+    head.rsplit(' ').next().unwrap_or(head)
+}
+
+/// Whether the BeegFS node string of a target names config node `n` (by name, host or short host).
+pub(crate) fn on_node(target_node: &str, n: &config::Node) -> bool {
+    let id = node_id_host(target_node);
+    id == n.name || id == n.host || n.host.split('.').next() == Some(id)
+}
+
+fn probe_nodes(cfg: &config::Config, nodes: &[&config::Node], timeout: u64) -> Vec<NodeStatus> {
+    let cmd = crate::checks::wrap_timeout(SERVICE_PROBE, timeout);
+    crate::checks::per_node(cfg, nodes, |tr, n| match tr.exec_idempotent(&n.host, &cmd) {
+        Ok(o) if o.status == Some(255) => Err(o.stderr.trim().to_string()),
+        Ok(o) => Ok(parse_services(&o.stdout)),
+        Err(e) => Err(format!("{:#}", e)),
+    }).into_iter().map(|(n, probe, took)| NodeStatus {
+        name: n.name.clone(),
+        host: n.host.clone(),
+        probe: probe.map(|services| (services, took)),
+    }).collect()
+}
+
+fn fetch_targets(cfg: &config::Config, mgmt: &config::Node, timeout: u64) -> anyhow::Result<Targets> {
+    let spaces = crate::checks::capacity::fetch_targets(cfg, mgmt, timeout)?;
+    let states = crate::beegfs::Ctl::new(cfg, mgmt, timeout).target_states(crate::beegfs::NodeType::Storage)?;
+    Ok(Targets { mgmt: mgmt.name.clone(), spaces, states })
+}
+
+/// `--mgmt-node`, else the first probed node running beegfs-mgmtd, else a node with the mgmtd role.
+fn pick_mgmt<'a>(cfg: &'a config::Config, explicit: Option<&str>, probed: &[NodeStatus]) -> anyhow::Result<Option<&'a config::Node>> {
+    if let Some(sel) = explicit { return crate::checks::single_node(cfg, sel).map(Some); }
+    let running = probed.iter().find(|n| matches!(&n.probe, Ok((svc, _)) if svc.get("mgmtd").is_some_and(|s| s == "active")));
+    Ok(match running {
+        Some(r) => cfg.nodes.iter().find(|n| n.name == r.name),
+        None => cfg.nodes.iter().find(|n| n.role() == "mgmtd"),
+    })
+}
+
+/// What is wrong with one node; the worst decides the exit code.
+pub(crate) fn node_issues(n: &NodeStatus) -> Vec<(Severity, String)> {
+    match &n.probe {
+        Err(e) => vec![(Severity::Failure, format!("{} unreachable: {}", n.name, e))],
+        Ok((svc, _)) => svc.iter().filter(|(_, st)| *st != "active").map(|(name, st)| {
+            let sev = if st == "failed" { Severity::Failure } else { Severity::Warning };
+            (sev, format!("beegfs-{} {} on {}", name, st, n.name))
+        }).collect(),
+    }
+}
+
+pub(crate) fn target_issues(t: &Targets, ids: &[&str]) -> Vec<(Severity, String)> {
+    let mut issues = Vec::new();
+    for id in ids {
+        match t.states.get(*id) {
+            Some(st) if st.reachability != "Online" => issues.push((Severity::Failure, format!("target {} {}", id, st.reachability))),
+            Some(st) if st.consistency != "Good" => issues.push((Severity::Warning, format!("target {} {}", id, st.consistency))),
+            _ => {}
+        }
+    }
+    for sp in t.spaces.iter().filter(|sp| ids.contains(&sp.id.as_str())) {
+        match sp.pool.as_deref() {
+            Some("emergency") => issues.push((Severity::Failure, format!("target {} in the emergency pool", sp.id))),
+            Some("low") => issues.push((Severity::Warning, format!("target {} in the low pool", sp.id))),
+            _ => {}
+        }
+    }
+    issues
+}
+
+/// Probe `nodes` and read the targets from `mgmt_node` or the mgmtd found among them.
+pub(crate) fn gather(cfg: &config::Config, nodes: &[&config::Node], mgmt_node: Option<&str>, timeout: u64) -> anyhow::Result<Cluster> {
+    let probed = probe_nodes(cfg, nodes, timeout);
+    let mgmt = pick_mgmt(cfg, mgmt_node, &probed)?;
+    let mut targets_denied = false;
+    let targets = match mgmt {
+        Some(m) => fetch_targets(cfg, m, timeout).map_err(|e| {
+            targets_denied = crate::checks::error_severity(&e) == Severity::NoPerm;
+            format!("{:#}", e)
+        }),
+        None => Err("no management node found; pass --mgmt-node".to_string()),
+    };
+    Ok(Cluster { nodes: probed, mgmt: mgmt.map(|m| m.name.clone()), targets, targets_denied })
+}
+
+/// `beeg status`: services, reachability, targets and free capacity in one table.
+pub fn cluster(cli: &crate::Cli, cfg: &config::Config, selector: Option<&str>, mgmt_node: Option<&str>, timeout: u64) -> anyhow::Result<Severity> {
+    let selector = selector.unwrap_or("all");
+    let nodes = config::select_nodes(cfg, selector);
+    if nodes.is_empty() { anyhow::bail!("selector '{}' matches no nodes", selector); }
+    let Cluster { nodes: mut probed, mgmt, targets, targets_denied } = gather(cfg, &nodes, mgmt_node, timeout)?;
+    config::sort_rows(cfg, cli.sort, &mut probed, |n| &n.name);
+
+    let mut issues: Vec<(Severity, String)> = probed.iter().flat_map(node_issues).collect();
+    // targets on the selected nodes, or all of them
+    let in_scope: Vec<&str> = match &targets {
+        Ok(t) => t.spaces.iter()
+            .filter(|sp| selector == "all" || nodes.iter().any(|n| on_node(&sp.node, n)))
+            .map(|sp| sp.id.as_str()).collect(),
+        Err(_) => Vec::new(),
+    };
+    match &targets {
+        Ok(t) => issues.extend(target_issues(t, &in_scope)),
+        // without any mgmtd there is nothing to summarize, which is not a fault
+        Err(e) if mgmt.is_some() => {
+            let severity = if targets_denied { Severity::NoPerm } else { Severity::Warning };
+            issues.push((severity, format!("target summary unavailable: {}", e)));
+        }
+        Err(_) => {}
+    }
+    let severity = issues.iter().map(|(s, _)| *s).max().unwrap_or_default();
+
+    let spaces: Vec<&crate::beegfs::TargetSpace> = match &targets {
+        Ok(t) => t.spaces.iter().filter(|sp| in_scope.contains(&sp.id.as_str())).collect(),
+        Err(_) => Vec::new(),
+    };
+    let (free, total) = spaces.iter().fold((0u64, 0u64), |(f, t), sp| (f + sp.free_bytes, t + sp.total_bytes));
+    let mut states: BTreeMap<String, usize> = BTreeMap::new();
+    let mut pools: BTreeMap<String, usize> = BTreeMap::new();
+    if let Ok(t) = &targets {
+        for id in &in_scope {
+            let st = t.states.get(*id).map(|s| format!("{}/{}", s.reachability, s.consistency)).unwrap_or_else(|| "unknown".into());
+            *states.entry(st).or_default() += 1;
+        }
+    }
+    for sp in &spaces { *pools.entry(sp.pool.clone().unwrap_or_else(|| "unknown".into())).or_default() += 1; }
+    let counts: Vec<(&str, usize, usize)> = SERVICES.iter().map(|s| {
+        let installed = probed.iter().filter_map(|n| n.probe.as_ref().ok()).filter_map(|(svc, _)| svc.get(*s)).collect::<Vec<_>>();
+        (*s, installed.iter().filter(|st| **st == "active").count(), installed.len())
+    }).collect();
+    let reachable = probed.iter().filter(|n| n.probe.is_ok()).count();
+    let pct = |f: u64, t: u64| if t == 0 { 0.0 } else { f as f64 * 100.0 / t as f64 };
+    let node_targets = |n: &NodeStatus| -> Vec<&crate::beegfs::TargetSpace> {
+        let Some(cn) = cfg.nodes.iter().find(|c| c.name == n.name) else { return Vec::new() };
+        spaces.iter().copied().filter(|sp| on_node(&sp.node, cn)).collect()
+    };
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let svc: Vec<String> = counts.iter().filter(|(_, _, n)| *n > 0).map(|(s, a, n)| format!("{} {}/{}", s, a, n)).collect();
+            let note = |s: String| crate::tables::note(cli.output, &s);
+            note(format!("Nodes: {}/{} reachable    Services active: {}", reachable, probed.len(), if svc.is_empty() { "none found".into() } else { svc.join(", ") }));
+            match &targets {
+                Ok(t) => {
+                    let st: Vec<String> = states.iter().map(|(k, v)| format!("{} {}", v, k)).collect();
+                    let pl: Vec<String> = pools.iter().map(|(k, v)| format!("{} {}", k, v)).collect();
+                    note(format!("Targets: {} ({}), pools: {}    via {}", in_scope.len(), st.join(", "), pl.join(", "), t.mgmt));
+                    note(format!("Capacity: {} free of {} ({:.0}%)", crate::transfer::format_size(free), crate::transfer::format_size(total), pct(free, total)));
+                }
+                Err(_) => note("Targets: -".into()),
+            }
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            let mut header = vec!["Node", "Host", "Reachable", "RTT (ms)"];
+            header.extend(SERVICES);
+            header.extend(["Targets", "Free"]);
+            table.set_header(header);
+            for n in &probed {
+                let mut row = vec![n.name.clone(), n.host.clone()];
+                match &n.probe {
+                    Ok((svc, rtt)) => {
+                        row.extend(["yes".to_string(), format!("{:.0}", rtt.as_secs_f64() * 1000.0)]);
+                        row.extend(SERVICES.iter().map(|s| svc.get(*s).cloned().unwrap_or_else(|| "-".into())));
+                    }
+                    Err(_) => row.extend(["NO".to_string(), "-".into(), "-".into(), "-".into(), "-".into(), "-".into()]),
+                }
+                let ts = node_targets(n);
+                let (f, t) = ts.iter().fold((0u64, 0u64), |(f, t), sp| (f + sp.free_bytes, t + sp.total_bytes));
+                row.push(if ts.is_empty() { "-".into() } else { ts.len().to_string() });
+                row.push(if ts.is_empty() { "-".into() } else { format!("{} ({:.0}%)", crate::transfer::format_size(f), pct(f, t)) });
+                let reachable = if n.probe.is_ok() { Severity::Ok } else { Severity::Failure };
+                table.add_row(row.into_iter().enumerate().map(|(i, c)| if i == 2 { crate::tables::status_cell(c, reachable) } else { Cell::new(c) }));
+            }
+            crate::tables::print(cli.output, &table);
+            note(format!("Status: {}", crate::tables::paint(severity.as_str(), severity)));
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let node_json: Vec<_> = probed.iter().map(|n| {
+                let ts = node_targets(n);
+                let (services, rtt, error) = match &n.probe {
+                    Ok((svc, rtt)) => (Some(svc), Some(rtt.as_secs_f64() * 1000.0), None),
+                    Err(e) => (None, None, Some(e)),
+                };
+                serde_json::json!({
+                    "name": n.name,
+                    "host": n.host,
+                    "reachable": n.probe.is_ok(),
+                    "rtt_ms": rtt,
+                    "error": error,
+                    "services": services,
+                    "targets": ts.iter().map(|sp| &sp.id).collect::<Vec<_>>(),
+                    "free_bytes": ts.iter().map(|sp| sp.free_bytes).sum::<u64>(),
+                    "total_bytes": ts.iter().map(|sp| sp.total_bytes).sum::<u64>(),
+                })
+            }).collect();
+            let services: serde_json::Map<String, serde_json::Value> = counts.iter()
+                .map(|(s, a, n)| (s.to_string(), serde_json::json!({ "active": a, "installed": n }))).collect();
+            let obj = serde_json::json!({
+                "status": severity.as_str(),
+                "selector": selector,
+                "nodes": node_json,
+                "services": services,
+                "targets": match &targets {
+                    Ok(t) => serde_json::json!({
+                        "mgmt_node": t.mgmt,
+                        "count": in_scope.len(),
+                        "states": states,
+                        "pools": pools,
+                        "free_bytes": free,
+                        "total_bytes": total,
+                    }),
+                    Err(e) => serde_json::json!({ "error": e }),
+                },
+                "issues": issues.iter().map(|(s, m)| serde_json::json!({ "severity": s.as_str(), "message": m })).collect::<Vec<_>>(),
+            });
+            crate::formats::print(cli.output, &obj)?;
+        }
+    }
+
+    // Warnings
+    for (_, m) in &issues { log::warn!("{}", m); }
+    if mgmt.is_none() { eprintln!("NOTE: no management node among the selected nodes; pass --mgmt-node for targets and capacity"); }
+    Ok(severity)
+}
+
+fn spawn_refresh(cli: &crate::Cli, selector: &str) {
+    let Ok(exe) = std::env::current_exe() else { return };
+    let mut cmd = Command::new(exe);
+    if let Some(c) = &cli.config { cmd.arg("--config").arg(c); }
+    cmd.args(["status", "--short", "--refresh", "--selector", selector])
+        .stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    let _ = cmd.spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cluster_parts() {
+        let svc = parse_services("SVC mgmtd not-found inactive\nSVC meta loaded active\nSVC storage loaded failed\nSVC client loaded inactive\n");
+        assert_eq!(svc.len(), 3);
+        assert_eq!(svc["storage"], "failed");
+        let n = NodeStatus { name: "s1".into(), host: "10.0.0.1".into(), probe: Ok((svc, std::time::Duration::ZERO)) };
+        let issues = node_issues(&n);
+        assert_eq!(issues, vec![(Severity::Warning, "beegfs-client inactive on s1".to_string()), (Severity::Failure, "beegfs-storage failed on s1".to_string())]);
+
+        assert_eq!(node_id_host("beegfs-storage stor01 [ID: 1]"), "stor01");
+        assert_eq!(node_id_host("stor02"), "stor02");
+        let node: config::Node = serde_json::from_value(serde_json::json!({"name": "s1", "host": "stor01.example.org"})).unwrap();
+        assert!(on_node("beegfs-storage stor01 [ID: 1]", &node));
+        assert!(!on_node("beegfs-storage stor011 [ID: 3]", &node));
+    }
+
+    #[test]
+    fn tokens() {
+        let mut b = Badge { checked_at: 1, total: 42, unhealthy: vec![], refresh_started: None };
+        assert_eq!(b.token(), "beegfs:OK 42/42");
+        b.unhealthy = vec!["a".into(), "b".into(), "c".into()];
+        assert_eq!(b.token(), "beegfs:DEGRADED 3!");
+        b.total = 0;
+        assert_eq!(b.token(), "beegfs:NONE");
+    }
+}
@@ -0,0 +1,232 @@
+//! `beeg support-bundle`: what BeeGFS support asks for, from every selected node, in one
+//! archive with a manifest. Each node packs its part on its own and it is copied back with
+//! `transfer::fetch_file` (see `logs`); the parts are unpacked side by side here and packed
+//! again with `tar`.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use comfy_table::{presets::UTF8_FULL, Cell, Table};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::checks::Severity;
+use crate::logs;
+use crate::timefmt::TimeRange;
+use crate::config;
+
+pub(crate) struct BundleOptions {
+    pub selector: String,
+    pub range: TimeRange,
+    /// Archive to write; `beeg-support-<time>.tar.gz` when `None`
+    pub out: Option<PathBuf>,
+    /// Node to run the `beegfs-ctl` listings on; the first mgmtd among the nodes when `None`
+    pub mgmt_node: Option<String>,
+    pub logs: bool,
+    pub max_file_size: u64,
+}
+
+/// Commands run on every node, into files under `$d`; `run FILE CMD...` skips tools the node
+/// does not have. The connection auth file (`connAuthFile`) is never copied.
+const NODE_COMMANDS: &str = r#"run() { f="$d/$1"; shift; command -v "$1" >/dev/null 2>&1 || return 0; mkdir -p "$(dirname "$f")"; { echo "\$ $*"; "$@"; } >> "$f" 2>&1; }
+mkdir -p "$d/config"
+auth=$(sed -n 's/^[[:space:]]*connAuthFile[[:space:]]*=[[:space:]]*//p' /etc/beegfs/*.conf 2>/dev/null | sort -u)
+for f in /etc/beegfs/*; do
+  [ -f "$f" ] || continue
+  skip=; for a in $auth; do [ "$f" = "$a" ] && skip=1; done
+  if [ -n "$skip" ]; then echo "$f: connAuthFile, not copied" >> "$d/errors.txt"
+  elif [ -r "$f" ]; then cp -p "$f" "$d/config/"
+  else echo "$f: permission denied" >> "$d/errors.txt"; fi
+done
+run system/os.txt cat /etc/os-release
+run system/uptime.txt uptime
+run system/memory.txt free -m
+run system/mounts.txt findmnt -t beegfs,xfs,ext4,zfs
+run system/df.txt df -hT
+run system/sysctl.txt sysctl net.core.rmem_max net.core.wmem_max vm.min_free_kbytes
+run versions/packages.txt rpm -qa 'beegfs*' 'libbeegfs*'
+run versions/packages.txt dpkg-query -W 'beegfs*' 'libbeegfs*'
+run versions/modules.txt modinfo beegfs
+run versions/modules.txt lsmod
+run versions/ofed.txt ofed_info -s
+run versions/nvidia.txt nvidia-smi
+run services/status.txt systemctl status 'beegfs-*' --no-pager -l
+run network/ip-addr.txt ip addr
+run network/ip-route.txt ip route
+run network/ip-link.txt ip -s link
+run rdma/ibstat.txt ibstat
+run rdma/ibv_devinfo.txt ibv_devinfo
+run rdma/rdma-link.txt rdma link show
+"#;
+
+/// `beegfs-ctl` listings, run on the management node only.
+const CTL_COMMANDS: &str = r#"run ctl/nodes.txt beegfs-ctl --listnodes --nodetype=meta --nicdetails --reachable
+run ctl/nodes.txt beegfs-ctl --listnodes --nodetype=storage --nicdetails --reachable
+run ctl/nodes.txt beegfs-ctl --listnodes --nodetype=client --nicdetails
+run ctl/targets.txt beegfs-ctl --listtargets --nodetype=meta --longnodes --state
+run ctl/targets.txt beegfs-ctl --listtargets --nodetype=storage --longnodes --state --spaceinfo
+run ctl/mirrorgroups.txt beegfs-ctl --listmirrorgroups --nodetype=meta
+run ctl/mirrorgroups.txt beegfs-ctl --listmirrorgroups --nodetype=storage
+run ctl/pools.txt beegfs-ctl --liststoragepools
+"#;
+
+/// Files under `dir` as `(relative path, bytes)`, sorted.
+fn files_under(dir: &Path) -> Vec<(String, u64)> {
+    fn walk(base: &Path, dir: &Path, out: &mut Vec<(String, u64)>) {
+        let Ok(entries) = fs::read_dir(dir) else { return };
+        for e in entries.flatten() {
+            let path = e.path();
+            match e.metadata() {
+                Ok(m) if m.is_dir() => walk(base, &path, out),
+                Ok(m) => out.push((path.strip_prefix(base).unwrap_or(&path).display().to_string(), m.len())),
+                Err(_) => {}
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(dir, dir, &mut out);
+    out.sort();
+    out
+}
+
+fn tar(args: &[&std::ffi::OsStr]) -> Result<()> {
+    let out = Command::new("tar").args(args).output().context("running tar")?;
+    if !out.status.success() { bail!("tar failed: {}", String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(())
+}
+
+/// One node's part of the bundle, unpacked under `dir/<node>`.
+struct Part {
+    node: String,
+    result: Result<Vec<(String, u64)>, String>,
+    /// Log files cut to `--max-file-size`, as `logs::Bundle::truncated`
+    truncated: Vec<(String, u64)>,
+}
+
+pub fn bundle(cli: &crate::Cli, cfg: &config::Config, opts: &BundleOptions) -> Result<Severity> {
+    let nodes = config::select_nodes(cfg, &opts.selector);
+    if nodes.is_empty() { bail!("selector '{}' matched no nodes", opts.selector); }
+    let mgmt = match &opts.mgmt_node {
+        Some(sel) => Some(crate::checks::single_node(cfg, sel)?.name.clone()),
+        None => nodes.iter().find(|n| n.role() == "mgmtd").map(|n| n.name.clone()),
+    };
+    let started = Utc::now();
+    let name = format!("beeg-support-{}", started.format("%Y%m%dT%H%M%SZ"));
+    let out = opts.out.clone().unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", name)));
+    let staging = std::env::temp_dir().join(format!("{}.{}", name, std::process::id()));
+    let dir = staging.join(&name);
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+
+    let result = (|| -> Result<Vec<Part>> {
+        let logs = if opts.logs { logs::log_commands(&opts.range, opts.max_file_size) } else { String::new() };
+        let script = |n: &config::Node| {
+            let ctl = if mgmt.as_deref() == Some(n.name.as_str()) { CTL_COMMANDS } else { "" };
+            logs::packed(&opts.range, &format!("{}{}{}", NODE_COMMANDS, ctl, logs))
+        };
+        let fetched = logs::fetch_all(cfg, &nodes, script, |n| staging.join(format!("{}.tar", n.name)));
+        let mut parts = Vec::new();
+        for b in fetched {
+            let result = b.result.and_then(|_| {
+                let into = dir.join(&b.node);
+                fs::create_dir_all(&into).map_err(|e| e.to_string())?;
+                // tar picks zstd or gzip from the file itself
+                let unpacked = tar(&["-xf".as_ref(), b.path.as_os_str(), "-C".as_ref(), into.as_os_str()]).map_err(|e| format!("{:#}", e));
+                let _ = fs::remove_file(&b.path);
+                unpacked.map(|_| files_under(&into))
+            });
+            parts.push(Part { node: b.node, result, truncated: b.truncated });
+        }
+        config::sort_rows(cfg, cli.sort, &mut parts, |p| &p.node);
+
+        let manifest = serde_json::json!({
+            "beeg_version": crate::selfupdate::CURRENT,
+            "created": crate::timefmt::rfc3339(started),
+            "selector": opts.selector,
+            "since": opts.range.since.map(crate::timefmt::rfc3339),
+            "until": opts.range.until.map(crate::timefmt::rfc3339),
+            "mgmt_node": mgmt,
+            "logs": opts.logs,
+            "nodes": parts.iter().map(|p| match &p.result {
+                Ok(files) => serde_json::json!({
+                    "node": p.node,
+                    "ok": true,
+                    "files": files.iter().map(|(f, n)| serde_json::json!({"path": format!("{}/{}", p.node, f), "bytes": n})).collect::<Vec<_>>(),
+                    "truncated": logs::truncated_json(&p.truncated.iter().map(|(f, n)| (format!("{}/{}", p.node, f), *n)).collect::<Vec<_>>()),
+                }),
+                Err(e) => serde_json::json!({"node": p.node, "ok": false, "error": e}),
+            }).collect::<Vec<_>>(),
+        });
+        fs::write(dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)? + "\n")?;
+        let mut index = format!("beeg support bundle, created {}\n", crate::timefmt::rfc3339(started));
+        index.push_str(&format!("beegfs-ctl listings: {}\n\n", mgmt.as_deref().map(|m| format!("{}/ctl", m)).unwrap_or_else(|| "none (no mgmtd node; use --mgmt-node)".into())));
+        for p in &parts {
+            match &p.result {
+                Ok(files) => {
+                    index.push_str(&format!("{}/\n", p.node));
+                    for (f, n) in files {
+                        let cut = p.truncated.iter().find(|(t, _)| t == f).map(|(_, d)| format!(" (truncated: first {} bytes dropped)", d)).unwrap_or_default();
+                        index.push_str(&format!("  {:<40} {}{}\n", f, crate::transfer::format_size(*n), cut));
+                    }
+                }
+                Err(e) => index.push_str(&format!("{}: not collected: {}\n", p.node, e)),
+            }
+        }
+        fs::write(dir.join("index.txt"), index)?;
+        tar(&["-czf".as_ref(), out.as_os_str(), "-C".as_ref(), staging.as_os_str(), name.as_ref()])?;
+        Ok(parts)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    let parts = result?;
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Node", "Files", "Size", "Status"]);
+            for p in &parts {
+                let row = match &p.result {
+                    Ok(files) => vec![
+                        Cell::new(&p.node), Cell::new(files.len()), Cell::new(crate::transfer::format_size(files.iter().map(|(_, n)| n).sum())),
+                        crate::tables::status_cell("OK", Severity::Ok),
+                    ],
+                    Err(e) => vec![Cell::new(&p.node), Cell::new("-"), Cell::new("-"), crate::tables::status_cell(format!("ERR: {}", e), Severity::Failure)],
+                };
+                table.add_row(row);
+            }
+            crate::tables::print(cli.output, &table);
+            crate::tables::note(cli.output, &format!("Bundle: {}", out.display()));
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let v = serde_json::json!({
+                "bundle": out.display().to_string(),
+                "mgmt_node": mgmt,
+                "nodes": parts.iter().map(|p| match &p.result {
+                    Ok(files) => serde_json::json!({"node": p.node, "ok": true, "files": files.len(), "bytes": files.iter().map(|(_, n)| n).sum::<u64>()}),
+                    Err(e) => serde_json::json!({"node": p.node, "ok": false, "error": e}),
+                }).collect::<Vec<_>>(),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    if mgmt.is_none() { log::warn!("no beegfs-ctl listings: no mgmtd node among the selected ones; pass --mgmt-node"); }
+    let failed: Vec<&str> = parts.iter().filter(|p| p.result.is_err()).map(|p| p.node.as_str()).collect();
+    if !failed.is_empty() { log::warn!("nothing collected from {} node(s): {}", failed.len(), failed.join(", ")); }
+    Ok(if failed.is_empty() { Severity::Ok } else { Severity::Failure })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_listed_relative() {
+        let dir = std::env::temp_dir().join(format!("beeg-support-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::write(dir.join("config/beegfs-client.conf"), "sysMgmtdHost = mgmt01\n").unwrap();
+        fs::write(dir.join("info.txt"), "host: n1\n").unwrap();
+        let files = files_under(&dir);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(files, vec![("config/beegfs-client.conf".to_string(), 22), ("info.txt".to_string(), 9)]);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::checks::{wrap_timeout, Severity};
+use crate::{config, transport};
+use anyhow::{Context, Result};
+use comfy_table::{Cell, Table, presets::UTF8_FULL};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// One `<hash>  <file>` line of a checksum manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ManifestEntry {
+    pub hash: String,
+    pub file: String,
+}
+
+/// Undo sha256sum's escaping of names with a backslash or newline (lines prefixed with `\`).
+fn unescape(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => { out.push('\n'); chars.next(); }
+            ('\\', Some('\\')) => { out.push('\\'); chars.next(); }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parse `sha256sum`-style output (also md5/sha1/sha512); blank and `#` lines are skipped.
+pub(crate) fn parse_sums(text: &str) -> Vec<ManifestEntry> {
+    let re = Regex::new(r"^(\\)?([0-9a-fA-F]{32,128}) [ *](.+)$").unwrap();
+    text.lines().filter_map(|line| {
+        let c = re.captures(line)?;
+        let file = if c.get(1).is_some() { unescape(&c[3]) } else { c[3].to_string() };
+        Some(ManifestEntry { hash: c[2].to_ascii_lowercase(), file })
+    }).collect()
+}
+
+/// The coreutils tool for a hash length.
+pub(crate) fn tool_for(entries: &[ManifestEntry]) -> Result<&'static str> {
+    let tool = |len| match len { 32 => Some("md5sum"), 40 => Some("sha1sum"), 64 => Some("sha256sum"), 128 => Some("sha512sum"), _ => None };
+    let mut lens: Vec<usize> = entries.iter().map(|e| e.hash.len()).collect();
+    lens.sort_unstable();
+    lens.dedup();
+    match lens.as_slice() {
+        [len] => tool(*len).ok_or_else(|| anyhow::anyhow!("unrecognised hash length {} in manifest", len)),
+        [] => anyhow::bail!("manifest has no checksum lines"),
+        _ => anyhow::bail!("manifest mixes hash types (lengths {:?})", lens),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Outcome {
+    Ok,
+    Mismatch(String),
+    Missing,
+    Error(String),
+}
+
+/// Hashes a `*sum` run printed for `files`, or why it printed none for a file.
+pub(crate) fn read_sums(files: &[String], stdout: &str, stderr: &str) -> Vec<Result<String, String>> {
+    let got: BTreeMap<String, String> = parse_sums(stdout).into_iter().map(|e| (e.file, e.hash)).collect();
+    files.iter().map(|f| match got.get(f) {
+        Some(h) => Ok(h.clone()),
+        None => {
+            let needle = format!("{}: ", f);
+            Err(match stderr.lines().find(|l| l.contains(&needle)) {
+                Some(l) => l.rsplit(": ").next().unwrap_or(l).to_string(),
+                None => stderr.lines().last().unwrap_or("no output").to_string(),
+            })
+        }
+    }).collect()
+}
+
+/// Match one batch's output against what the manifest expects.
+pub(crate) fn judge(batch: &[ManifestEntry], stdout: &str, stderr: &str) -> Vec<Outcome> {
+    let files: Vec<String> = batch.iter().map(|e| e.file.clone()).collect();
+    batch.iter().zip(read_sums(&files, stdout, stderr)).map(|(e, got)| match got {
+        Ok(h) if h == e.hash => Outcome::Ok,
+        Ok(h) => Outcome::Mismatch(h),
+        Err(e) if e == "No such file or directory" => Outcome::Missing,
+        Err(e) => Outcome::Error(e),
+    }).collect()
+}
+
+/// `cd <dir> && <tool> -- <files>`, for running a `*sum` tool on a batch.
+pub(crate) fn sum_cmd(dir: &str, tool: &str, files: &[String]) -> String {
+    let files: Vec<String> = files.iter().map(|f| shell_escape::escape(f.as_str().into()).to_string()).collect();
+    format!("cd {} && {} -- {}", shell_escape::escape(dir.into()), tool, files.join(" "))
+}
+
+/// Results of `spread` per node, in node order; `None` if that node's worker panicked.
+pub(crate) type Spread<R> = Vec<(String, Option<Vec<R>>)>;
+
+/// Hand `items` out to one worker per node. Workers pull the next item from a shared queue
+/// when done, so a slow node just ends up doing fewer.
+pub(crate) fn spread<T, R, F>(cfg: &config::Config, nodes: &[&config::Node], items: Vec<T>, work: F) -> Spread<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(&dyn transport::Transport, &str, T) -> R + Send + Sync + 'static,
+{
+    let queue = Arc::new(Mutex::new(items.into_iter().rev().collect::<Vec<T>>()));
+    let work = Arc::new(work);
+    let handles: Vec<_> = nodes.iter().map(|n| {
+        let host = n.host.clone();
+        let queue = Arc::clone(&queue);
+        let work = Arc::clone(&work);
+        let tr = transport::from_config(cfg);
+        thread::spawn(move || {
+            let mut done = Vec::new();
+            while let Some(item) = queue.lock().ok().and_then(|mut q| q.pop()) {
+                done.push(work(tr.as_ref(), &host, item));
+            }
+            done
+        })
+    }).collect();
+    nodes.iter().zip(handles).map(|(n, h)| (n.name.clone(), h.join().ok())).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    /// Use at most this many of the selected nodes
+    pub clients: usize,
+    /// Files per remote command; also the unit of work handed to a client
+    pub batch_size: usize,
+    /// Seconds per batch
+    pub timeout: u64,
+}
+
+struct Checked {
+    entry: ManifestEntry,
+    client: String,
+    outcome: Outcome,
+}
+
+pub fn checksums(cli: &crate::Cli, cfg: &config::Config, path: &str, manifest: &Path, selector: &str, opts: &VerifyOptions) -> Result<Severity> {
+    let text = std::fs::read_to_string(manifest).with_context(|| format!("reading manifest: {}", manifest.display()))?;
+    let entries = parse_sums(&text);
+    let tool = tool_for(&entries)?;
+    let nodes: Vec<&config::Node> = config::select_nodes(cfg, selector).into_iter().take(opts.clients.max(1)).collect();
+    if nodes.is_empty() { anyhow::bail!("selector '{}' matched no nodes", selector); }
+
+    let batches: Vec<Vec<ManifestEntry>> = entries.chunks(opts.batch_size.max(1)).map(|c| c.to_vec()).collect();
+    let started = Instant::now();
+    let (path_owned, timeout) = (path.to_string(), opts.timeout);
+    let spread = spread(cfg, &nodes, batches, move |tr, host, batch: Vec<ManifestEntry>| {
+        let files: Vec<String> = batch.iter().map(|e| e.file.clone()).collect();
+        let outcomes = match tr.exec_idempotent(host, &wrap_timeout(&sum_cmd(&path_owned, tool, &files), timeout)) {
+            Ok(o) => judge(&batch, &o.stdout, &o.stderr),
+            Err(e) => vec![Outcome::Error(e.to_string()); batch.len()],
+        };
+        batch.into_iter().zip(outcomes).collect::<Vec<_>>()
+    });
+    let mut per_client: Vec<(String, usize)> = Vec::new();
+    let mut results: Vec<Checked> = Vec::new();
+    for (name, done) in spread {
+        match done {
+            Some(done) => {
+                let done: Vec<Checked> = done.into_iter().flatten().map(|(entry, outcome)| Checked { entry, client: name.clone(), outcome }).collect();
+                per_client.push((name, done.len()));
+                results.extend(done);
+            }
+            None => { log::warn!("verify thread for {} panicked", name); per_client.push((name, 0)); }
+        }
+    }
+    // a panicked worker leaves its batch unchecked
+    let unchecked = entries.len() - results.len();
+    results.sort_by(|a, b| a.entry.file.cmp(&b.entry.file));
+    let problems: Vec<&Checked> = results.iter().filter(|r| r.outcome != Outcome::Ok).collect();
+    let count = |f: fn(&Outcome) -> bool| results.iter().filter(|r| f(&r.outcome)).count();
+    let ok = count(|o| matches!(o, Outcome::Ok));
+    let mismatched = count(|o| matches!(o, Outcome::Mismatch(_)));
+    let missing = count(|o| matches!(o, Outcome::Missing));
+    let errors = count(|o| matches!(o, Outcome::Error(_)));
+
+    match cli.output {
+        crate::Output::Human | crate::Output::Csv | crate::Output::Tsv | crate::Output::Nagios => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Client", "Files checked"]);
+            for (name, n) in &per_client { table.add_row(vec![name.clone(), n.to_string()]); }
+            crate::tables::print(cli.output, &table);
+            if !problems.is_empty() {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.set_header(vec!["File", "Result", "Client", "Detail"]);
+                for r in &problems {
+                    let (result, detail) = match &r.outcome {
+                        Outcome::Mismatch(h) => ("MISMATCH", format!("expected {}, got {}", r.entry.hash, h)),
+                        Outcome::Missing => ("MISSING", String::new()),
+                        Outcome::Error(e) => ("ERROR", e.clone()),
+                        Outcome::Ok => continue,
+                    };
+                    table.add_row(vec![Cell::new(&r.entry.file), crate::tables::status_cell(result, Severity::Failure), Cell::new(&r.client), Cell::new(detail)]);
+                }
+                crate::tables::print_another(cli.output, &table);
+            }
+            crate::tables::note(cli.output, &format!(
+                "{} files with {} on {} clients in {:.0}s: {} ok, {} mismatched, {} missing, {} errors",
+                entries.len(), tool, per_client.len(), started.elapsed().as_secs_f64(), ok, mismatched, missing, errors,
+            ));
+        }
+        crate::Output::Json | crate::Output::Jsonl | crate::Output::Yaml => {
+            let list = |f: fn(&Outcome) -> bool| problems.iter().filter(|r| f(&r.outcome)).map(|r| serde_json::json!({
+                "file": r.entry.file,
+                "expected": r.entry.hash,
+                "actual": match &r.outcome { Outcome::Mismatch(h) => Some(h), _ => None },
+                "error": match &r.outcome { Outcome::Error(e) => Some(e), _ => None },
+                "client": r.client,
+            })).collect::<Vec<_>>();
+            let v = serde_json::json!({
+                "path": path,
+                "manifest": manifest.display().to_string(),
+                "tool": tool,
+                "files": entries.len(),
+                "ok": ok,
+                "mismatched": list(|o| matches!(o, Outcome::Mismatch(_))),
+                "missing": list(|o| matches!(o, Outcome::Missing)),
+                "errors": list(|o| matches!(o, Outcome::Error(_))),
+                "clients": per_client.iter().map(|(n, c)| serde_json::json!({"node": n, "files": c})).collect::<Vec<_>>(),
+                "seconds": started.elapsed().as_secs_f64(),
+            });
+            crate::formats::print(cli.output, &v)?;
+        }
+    }
+
+    // Warnings
+    if mismatched + missing > 0 { log::warn!("{} mismatched and {} missing files under {}", mismatched, missing, path); }
+    if errors > 0 { log::warn!("{} files could not be checked", errors); }
+    if unchecked > 0 { log::warn!("{} files were not checked", unchecked); }
+    Ok(if mismatched + missing + errors + unchecked > 0 { Severity::Failure } else { Severity::Ok })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUMS: &str = "# dataset v3
+e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  a/empty.bin
+2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824 *b/hello.txt
+\\ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  c/odd\\nname
+";
+
+    #[test]
+    fn manifest() {
+        let m = parse_sums(SUMS);
+        assert_eq!(m.len(), 3);
+        assert_eq!(m[1].file, "b/hello.txt");
+        assert_eq!(m[2].file, "c/odd\nname");
+        assert_eq!(tool_for(&m).unwrap(), "sha256sum");
+        assert!(tool_for(&[]).is_err());
+        assert!(tool_for(&parse_sums("d41d8cd98f00b204e9800998ecf8427e  x\ne3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  y\n")).is_err());
+    }
+
+    #[test]
+    fn outcomes() {
+        let m = parse_sums(SUMS);
+        let stdout = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  a/empty.bin\n0000000000000000000000000000000000000000000000000000000000000000  c/odd\\nname\n";
+        let stdout = stdout.replace("0000000000000000000000000000000000000000000000000000000000000000  c", "\\0000000000000000000000000000000000000000000000000000000000000000  c");
+        let stderr = "sha256sum: b/hello.txt: No such file or directory\n";
+        let o = judge(&m, &stdout, stderr);
+        assert_eq!(o[0], Outcome::Ok);
+        assert_eq!(o[1], Outcome::Missing);
+        assert_eq!(o[2], Outcome::Mismatch("0".repeat(64)));
+        let o = judge(&m[1..2], "", "sha256sum: b/hello.txt: Permission denied\n");
+        assert_eq!(o[0], Outcome::Error("Permission denied".into()));
+    }
+}